@@ -7,6 +7,12 @@ use {
     anchor_lang::prelude::*,
     borsh::{BorshDeserialize, BorshSerialize},
     bytemuck::{Pod, Zeroable},
+    std::fmt,
+};
+
+use crate::{
+    constants::CALLBACK_INFO_LEN,
+    utils::{compute_event_queue_size, compute_slab_size, derive_sub_account_address},
 };
 
 #[derive(
@@ -23,6 +29,94 @@ pub struct CallBackInfo {
     pub sub_account_idx: u8,
 }
 
+impl CallBackInfo {
+    /// Creates a new [`CallBackInfo`] for the given master account, fee tier and sub account index.
+    pub fn new(user_account: &Pubkey, fee_tier: u8, sub_account_idx: u8) -> Self {
+        Self {
+            user_account: *user_account,
+            fee_tier,
+            sub_account_idx,
+        }
+    }
+
+    /// Parses a [`CallBackInfo`] from the first [`CALLBACK_INFO_LEN`] bytes of the given data.
+    ///
+    /// Returns `None` if the data is too short.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < CALLBACK_INFO_LEN {
+            return None;
+        }
+        Some(*bytemuck::from_bytes::<CallBackInfo>(
+            &data[..CALLBACK_INFO_LEN],
+        ))
+    }
+
+    /// Serializes the [`CallBackInfo`] as laid out in the slabs and event queue.
+    pub fn to_bytes(&self) -> [u8; CALLBACK_INFO_LEN] {
+        let mut bytes = [0u8; CALLBACK_INFO_LEN];
+        bytes.copy_from_slice(bytemuck::bytes_of(self));
+        bytes
+    }
+
+    /// Whether the [`CallBackInfo`] is unset, e.g. the slot of a free leaf or an out event without a taker.
+    pub fn is_empty(&self) -> bool {
+        self.user_account == Pubkey::default()
+    }
+
+    /// The owner of the order, as the master account and the index of the sub account in it.
+    pub fn owner(&self) -> (Pubkey, u8) {
+        (self.user_account, self.sub_account_idx)
+    }
+
+    /// Whether the order belongs to the given master account and, if given, sub account index.
+    pub fn is_owned_by(&self, master_account: &Pubkey, sub_account_idx: Option<u8>) -> bool {
+        self.user_account == *master_account
+            && sub_account_idx.map_or(true, |idx| idx == self.sub_account_idx)
+    }
+
+    /// Derives the address of the sub account that owns the order.
+    ///
+    /// This assumes the sub account was created with an account number equal to it's index
+    /// in the master account, which is the case for sub accounts created through the client.
+    pub fn derive_sub_account_address(&self) -> Pubkey {
+        derive_sub_account_address(&self.user_account, self.sub_account_idx).0
+    }
+}
+
+impl fmt::Display for CallBackInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}#{} (fee tier {})",
+            self.user_account, self.sub_account_idx, self.fee_tier
+        )
+    }
+}
+
+/// Gets the maker and taker [`CallBackInfo`]s of the event at the given index.
+///
+/// The callback infos must be interleaved as in the event queue, i.e. the event at index `i` has the maker's
+/// callback info at index `2 * i` and the taker's callback info at index `2 * i + 1`.
+pub fn get_event_callback_infos(
+    callback_infos: &[CallBackInfo],
+    event_idx: usize,
+) -> Option<(&CallBackInfo, &CallBackInfo)> {
+    let maker = callback_infos.get(2 * event_idx)?;
+    let taker = callback_infos.get(2 * event_idx + 1)?;
+    Some((maker, taker))
+}
+
+/// Pairs each event with it's maker and taker [`CallBackInfo`]s, see [`get_event_callback_infos`].
+pub fn zip_event_callback_infos<'a>(
+    events: &'a [FillEvent],
+    callback_infos: &'a [CallBackInfo],
+) -> impl Iterator<Item = (&'a FillEvent, &'a CallBackInfo, &'a CallBackInfo)> {
+    events
+        .iter()
+        .zip(callback_infos.chunks_exact(2))
+        .map(|(event, callbacks)| (event, &callbacks[0], &callbacks[1]))
+}
+
 #[allow(clippy::needless_lifetimes)]
 pub fn load_book_side<'a>(
     account_data: &'a mut [u8],
@@ -68,3 +162,103 @@ pub fn parse_aob_event_queue(
     let callback_infos = bytemuck::cast_slice(callback_infos);
     (header, events, callback_infos)
 }
+
+/// Parses the events pushed to the AOB event queue after the given sequence number,
+/// ordered from oldest to newest, without copying the remaining events in the queue.
+///
+/// Returns the header, the new events and their maker and taker callback infos, interleaved
+/// in the same way as in the event queue, i.e. the event at index `i` has the maker's callback info
+/// at index `2 * i` and the taker's callback info at index `2 * i + 1`.
+/// Events that were pushed and already consumed since the given sequence number can not be recovered.
+pub fn parse_aob_event_queue_since(
+    account_data: &[u8],
+    seq_num: u64,
+) -> (&EventQueueHeader, Vec<FillEvent>, Vec<CallBackInfo>) {
+    let (header, events, callback_infos) = parse_aob_event_queue(account_data);
+    let capacity = events.len();
+    let new_events_len = (header.seq_num.saturating_sub(seq_num)).min(header.count) as usize;
+
+    let mut new_events = Vec::with_capacity(new_events_len);
+    let mut new_callback_infos = Vec::with_capacity(new_events_len * 2);
+    if capacity == 0 {
+        return (header, new_events, new_callback_infos);
+    }
+    let first = header.head as usize + header.count as usize - new_events_len;
+    for i in first..first + new_events_len {
+        let idx = i % capacity;
+        new_events.push(events[idx]);
+        new_callback_infos.push(callback_infos[2 * idx]);
+        new_callback_infos.push(callback_infos[2 * idx + 1]);
+    }
+
+    (header, new_events, new_callback_infos)
+}
+
+/// Builds the account data of an AOB book side with the given order capacity, see [`compute_slab_size`],
+/// holding the given leaves along with their [`CallBackInfo`]s, which is the inverse of [`load_book_side`].
+///
+/// The leaves are inserted in the given order, which only matters for the handles they are assigned.
+/// Returns `None` if the leaves do not fit in the book side or two leaves have the same key.
+pub fn build_book_side(
+    account_tag: AccountTag,
+    capacity: usize,
+    leaves: &[(LeafNode, CallBackInfo)],
+) -> Option<Vec<u8>> {
+    if capacity == 0 || leaves.len() > capacity {
+        return None;
+    }
+    let mut account_data = vec![0u8; compute_slab_size(capacity)];
+    account_data[..8].copy_from_slice(&(account_tag as u64).to_le_bytes());
+
+    let mut slab = load_book_side(&mut account_data, account_tag);
+    for (leaf, callback_info) in leaves.iter() {
+        let (handle, replaced) = slab.insert_leaf(leaf).ok()?;
+        if replaced.is_some() {
+            return None;
+        }
+        slab.callback_infos[handle as usize] = *callback_info;
+    }
+
+    Some(account_data)
+}
+
+/// Builds the account data of an AOB event queue with the given event capacity, see [`compute_event_queue_size`],
+/// holding the given events along with their maker and taker [`CallBackInfo`]s, which is the inverse of
+/// [`parse_aob_event_queue`].
+///
+/// The events are ordered from oldest to newest and the newest event has the sequence number `seq_num - 1`,
+/// the events are placed in the ring buffer where the program would have pushed them, so they may wrap around.
+/// Returns `None` if the events do not fit in the event queue or more events are given than were ever pushed.
+pub fn build_aob_event_queue(
+    capacity: usize,
+    seq_num: u64,
+    events: &[(FillEvent, CallBackInfo, CallBackInfo)],
+) -> Option<Vec<u8>> {
+    if capacity == 0 || events.len() > capacity || (events.len() as u64) > seq_num {
+        return None;
+    }
+    let mut account_data = vec![0u8; compute_event_queue_size(capacity)];
+    account_data[..8].copy_from_slice(&(AccountTag::EventQueue as u64).to_le_bytes());
+
+    let head = ((seq_num - events.len() as u64) % capacity as u64) as usize;
+    let mut header = EventQueueHeader::zeroed();
+    header.head = head as u64;
+    header.count = events.len() as u64;
+    header.seq_num = seq_num;
+    let (header_data, remaining) = account_data[8..].split_at_mut(EventQueueHeader::LEN);
+    header_data.copy_from_slice(bytemuck::bytes_of(&header));
+
+    let (events_data, callback_infos_data) = remaining.split_at_mut(capacity * FillEvent::LEN);
+    for (i, (event, maker, taker)) in events.iter().enumerate() {
+        let idx = (head + i) % capacity;
+        events_data[idx * FillEvent::LEN..(idx + 1) * FillEvent::LEN]
+            .copy_from_slice(bytemuck::bytes_of(event));
+        let maker_offset = 2 * idx * CALLBACK_INFO_LEN;
+        callback_infos_data[maker_offset..maker_offset + CALLBACK_INFO_LEN]
+            .copy_from_slice(&maker.to_bytes());
+        callback_infos_data[maker_offset + CALLBACK_INFO_LEN..maker_offset + 2 * CALLBACK_INFO_LEN]
+            .copy_from_slice(&taker.to_bytes());
+    }
+
+    Some(account_data)
+}
@@ -47,6 +47,9 @@ pub const TOKENS_MAX_CNT: usize = MARKETS_MAX_CNT + 1;
 /// The quote token index.
 pub const QUOTE_TOKEN_IDX: usize = TOKENS_MAX_CNT - 1;
 
+/// The maximum number of caches in the cache account.
+pub const CACHES_MAX_CNT: usize = 512;
+
 /// The maximum number of nodes that a pool can have.
 pub const NODES_MAX_CNT: usize = 24;
 
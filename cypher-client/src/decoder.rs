@@ -0,0 +1,1945 @@
+use {
+    crate::instruction,
+    anchor_lang::{prelude::*, Discriminator},
+    std::fmt,
+};
+
+/// The accounts of a decoded instruction, named as in the IDL.
+///
+/// Accounts of nested account structs are prefixed with the name of the struct, e.g. `dex_market`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InstructionAccounts {
+    /// The accounts expected by the instruction, in order.
+    pub named: Vec<(&'static str, Pubkey)>,
+    /// The remaining accounts, e.g. the price accounts of `cache_oracle_prices`.
+    pub remaining: Vec<Pubkey>,
+}
+
+impl InstructionAccounts {
+    /// Names the given accounts, returning `None` if there are less accounts than names.
+    fn new(names: &[&'static str], accounts: &[Pubkey]) -> Option<Self> {
+        if accounts.len() < names.len() {
+            return None;
+        }
+        let (named, remaining) = accounts.split_at(names.len());
+        Some(Self {
+            named: names.iter().copied().zip(named.iter().copied()).collect(),
+            remaining: remaining.to_vec(),
+        })
+    }
+
+    /// Gets the account with the given name.
+    pub fn get(&self, name: &str) -> Option<&Pubkey> {
+        self.named
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, pk)| pk)
+    }
+}
+
+const AUTHORITY_WITHDRAW_ACCOUNTS: &[&str] = &[
+    "token_pool",
+    "token_pool_node",
+    "token_vault",
+    "destination_token_account",
+    "vault_signer",
+    "authority",
+    "token_program",
+];
+const CACHE_ORACLE_PRICES_ACCOUNTS: &[&str] = &["cache_account", "oracle_products"];
+const CLAIM_IDO_PROCEEDS_ACCOUNTS: &[&str] = &[
+    "market",
+    "quote_pool_node",
+    "quote_vault",
+    "destination_token_account",
+    "ido_authority",
+    "vault_signer",
+    "token_program",
+];
+const CLAIM_LIQUIDITY_MINING_REWARDS_ACCOUNTS: &[&str] = &[
+    "market",
+    "quote_pool_node",
+    "quote_vault",
+    "destination_token_account",
+    "ido_authority",
+    "vault_signer",
+    "token_program",
+];
+const CLOSE_ACCOUNT_ACCOUNTS: &[&str] = &["account", "authority", "rent_destination"];
+const CLOSE_CACHE_ACCOUNT_ACCOUNTS: &[&str] = &["cache_account", "authority", "rent_destination"];
+const CLOSE_CLEARING_ACCOUNTS: &[&str] = &["clearing", "rent_destination", "authority"];
+const CLOSE_FUTURES_MARKET_ACCOUNTS: &[&str] = &[
+    "market",
+    "orderbook",
+    "bids",
+    "asks",
+    "event_queue",
+    "oracle_products",
+    "price_history",
+    "rent_destination",
+    "authority",
+];
+const CLOSE_ORACLE_PRODUCTS_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "oracle_products",
+    "authority",
+    "rent_destination",
+];
+const CLOSE_ORDERS_ACCOUNT_ACCOUNTS: &[&str] =
+    &["master_account", "market", "open_orders", "authority"];
+const CLOSE_PERP_MARKET_ACCOUNTS: &[&str] = &[
+    "market",
+    "orderbook",
+    "bids",
+    "asks",
+    "event_queue",
+    "oracle_products",
+    "rent_destination",
+    "authority",
+];
+const CLOSE_POOL_ACCOUNTS: &[&str] = &[
+    "pool",
+    "oracle_products",
+    "rent_destination",
+    "authority",
+    "token_program",
+];
+const CLOSE_POOL_NODE_ACCOUNTS: &[&str] = &[
+    "pool",
+    "pool_node",
+    "token_mint",
+    "token_vault",
+    "vault_signer",
+    "rent_destination",
+    "authority",
+    "token_program",
+];
+const CLOSE_WHITELIST_ACCOUNTS: &[&str] =
+    &["clearing", "whitelist", "rent_destination", "authority"];
+const CLOSE_SUB_ACCOUNT_ACCOUNTS: &[&str] =
+    &["account", "sub_account", "authority", "rent_destination"];
+const CREATE_ACCOUNT_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "master_account",
+    "authority",
+    "payer",
+    "system_program",
+];
+const CREATE_PUBLIC_CLEARING_ACCOUNTS: &[&str] =
+    &["clearing", "authority", "payer", "system_program"];
+const CREATE_PRIVATE_CLEARING_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "private_clearing",
+    "authority",
+    "payer",
+    "system_program",
+];
+const CREATE_ORACLE_STUB_ACCOUNTS: &[&str] = &["oracle_stub", "payer", "system_program"];
+const CREATE_ORDERS_ACCOUNT_ACCOUNTS: &[&str] = &[
+    "master_account",
+    "market",
+    "open_orders",
+    "authority",
+    "payer",
+    "system_program",
+    "rent",
+];
+const CREATE_FUTURES_MARKET_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "market",
+    "price_history",
+    "oracle_products",
+    "quote_pool",
+    "orderbook",
+    "bids",
+    "asks",
+    "event_queue",
+    "authority",
+    "payer",
+    "system_program",
+    "rent",
+];
+const CREATE_PERP_MARKET_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "market",
+    "oracle_products",
+    "quote_pool",
+    "orderbook",
+    "bids",
+    "asks",
+    "event_queue",
+    "authority",
+    "payer",
+    "system_program",
+    "rent",
+];
+const CREATE_POOL_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "pool",
+    "pool_node",
+    "token_vault",
+    "token_mint",
+    "vault_signer",
+    "oracle_products",
+    "dex_market",
+    "authority",
+    "payer",
+    "system_program",
+    "token_program",
+    "rent",
+];
+const CREATE_POOL_NODE_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "pool",
+    "pool_node",
+    "token_vault",
+    "token_mint",
+    "vault_signer",
+    "authority",
+    "payer",
+    "system_program",
+    "token_program",
+    "rent",
+];
+const CREATE_SUB_ACCOUNT_ACCOUNTS: &[&str] = &[
+    "master_account",
+    "sub_account",
+    "authority",
+    "payer",
+    "system_program",
+];
+const CREATE_WHITELIST_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "whitelist",
+    "account_owner",
+    "payer",
+    "authority",
+    "system_program",
+];
+const CREATE_WHITELISTED_ACCOUNT_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "whitelist",
+    "master_account",
+    "authority",
+    "payer",
+    "system_program",
+];
+const DEPOSIT_DELIVERABLE_ACCOUNTS: &[&str] = &[
+    "market",
+    "pool",
+    "pool_node",
+    "token_mint",
+    "token_vault",
+    "source_token_account",
+    "authority",
+    "token_program",
+];
+const DEPOSIT_FUNDS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "pool",
+    "pool_node",
+    "source_token_account",
+    "token_vault",
+    "token_mint",
+    "authority",
+    "token_program",
+];
+const EDIT_SUB_ACCOUNT_MARGINING_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "authority",
+];
+const CREATE_ORACLE_PRODUCTS_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "oracle_products",
+    "payer",
+    "authority",
+    "system_program",
+];
+const INIT_CACHE_ACCOUNT_ACCOUNTS: &[&str] = &["clearing", "cache_account", "authority"];
+const LIQUIDATE_FUTURES_POSITION_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "liqor_clearing",
+    "liqor_account",
+    "liqor_sub_account",
+    "liqee_clearing",
+    "liqee_account",
+    "liqee_sub_account",
+    "authority",
+];
+const LIQUIDATE_PERP_POSITION_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "liqor_clearing",
+    "liqor_account",
+    "liqor_sub_account",
+    "liqee_clearing",
+    "liqee_account",
+    "liqee_sub_account",
+    "authority",
+];
+const LIQUIDATE_SPOT_POSITION_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "liqor_clearing",
+    "liqor_account",
+    "liqor_sub_account",
+    "liqee_clearing",
+    "liqee_account",
+    "liqee_sub_account",
+    "asset_mint",
+    "asset_pool_node",
+    "liability_mint",
+    "liability_pool",
+    "liability_pool_node",
+    "authority",
+];
+const ROLL_MARKET_EXPIRY_ACCOUNTS: &[&str] = &["clearing", "cache_account", "market", "authority"];
+const REVOKE_WHITELIST_ACCOUNTS: &[&str] = &["clearing", "whitelist", "authority"];
+const SET_ACCOUNT_DELEGATE_ACCOUNTS: &[&str] = &["master_account", "authority", "delegate"];
+const SET_ACCOUNT_FEE_TIER_ACCOUNTS: &[&str] = &["clearing", "master_account", "authority"];
+const SET_CACHE_AUTHORITY_ACCOUNTS: &[&str] = &["cache_account", "authority"];
+const SET_CLEARING_AUTHORITY_ACCOUNTS: &[&str] = &["clearing", "authority"];
+const SET_CLEARING_FEE_TIERS_ACCOUNTS: &[&str] = &["clearing", "authority"];
+const SET_CLEARING_FEE_MINT_ACCOUNTS: &[&str] = &["clearing", "authority"];
+const SET_FUTURES_MARKET_AUTHORITY_ACCOUNTS: &[&str] = &["market", "authority"];
+const SET_FUTURES_MARKET_LIQUIDITY_MINING_INFO_ACCOUNTS: &[&str] = &["market", "authority"];
+const SET_FUTURES_MARKET_PARAMS_ACCOUNTS: &[&str] = &["cache", "market", "authority"];
+const SET_FUTURES_MARKET_STATUS_ACCOUNTS: &[&str] = &["market", "authority"];
+const SET_PERPETUAL_MARKET_AUTHORITY_ACCOUNTS: &[&str] = &["market", "authority"];
+const SET_PERPETUAL_MARKET_LIQUIDITY_MINING_INFO_ACCOUNTS: &[&str] = &["market", "authority"];
+const SET_PERPETUAL_MARKET_PARAMS_ACCOUNTS: &[&str] = &["cache", "market", "authority"];
+const SET_PERPETUAL_MARKET_STATUS_ACCOUNTS: &[&str] = &["market", "authority"];
+const SET_POOL_NODE_AUTHORITY_ACCOUNTS: &[&str] = &["pool_node", "authority"];
+const SET_POOL_NODE_STATUS_ACCOUNTS: &[&str] = &["pool_node", "authority"];
+const SET_POOL_AUTHORITY_ACCOUNTS: &[&str] = &["pool", "authority"];
+const SET_POOL_STATUS_ACCOUNTS: &[&str] = &["pool", "authority"];
+const SET_POOL_PARAMS_ACCOUNTS: &[&str] = &["cache", "pool", "authority"];
+const SET_ORACLE_PRODUCTS_ACCOUNTS: &[&str] = &["clearing", "authority", "oracle_products"];
+const SET_ORACLE_PRODUCTS_V2_ACCOUNTS: &[&str] = &["clearing", "authority", "oracle_products"];
+const SET_ORACLE_STUB_PRICE_ACCOUNTS: &[&str] = &["oracle_stub"];
+const SET_SUB_ACCOUNT_DELEGATE_ACCOUNTS: &[&str] = &["sub_account", "authority", "delegate"];
+const SET_POOL_DEX_MARKET_ACCOUNTS: &[&str] = &["clearing", "pool", "dex_market", "authority"];
+const SETTLE_POSITION_WITH_DELIVERY_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "underlying_pool_node",
+    "quote_pool_node",
+];
+const SETTLE_POSITION_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "quote_pool_node",
+];
+const SWEEP_MARKET_FEES_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "market",
+    "quote_pool_node",
+    "quote_vault",
+    "destination_token_account",
+    "vault_signer",
+    "authority",
+    "token_program",
+];
+const SWEEP_POOL_FEES_ACCOUNTS: &[&str] = &[
+    "token_pool",
+    "quote_pool_node",
+    "quote_vault",
+    "destination_token_account",
+    "vault_signer",
+    "authority",
+    "token_program",
+];
+const TRANSFER_BETWEEN_SUB_ACCOUNTS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "from_sub_account",
+    "to_sub_account",
+    "asset_mint",
+    "asset_pool_node",
+    "authority",
+];
+const UPDATE_ACCOUNT_MARGIN_ACCOUNTS: &[&str] = &["cache_account", "master_account", "signer"];
+const UPDATE_FUNDING_RATE_ACCOUNTS: &[&str] =
+    &["cache_account", "market", "orderbook", "bids", "asks"];
+const UPDATE_MARKET_EXPIRATION_ACCOUNTS: &[&str] = &["clearing", "market", "authority"];
+const UPDATE_TOKEN_INDEX_ACCOUNTS: &[&str] = &["cache_account", "pool"];
+const UPGRADE_ORACLE_PRODUCTS_ACCOUNTS: &[&str] = &[
+    "cache",
+    "price_history",
+    "oracle_products",
+    "payer",
+    "authority",
+    "system_program",
+];
+const WITHDRAW_FUNDS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "pool",
+    "pool_node",
+    "token_vault",
+    "destination_token_account",
+    "token_mint",
+    "vault_signer",
+    "authority",
+    "token_program",
+];
+const CANCEL_SPOT_ORDER_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "asset_pool_node",
+    "quote_pool_node",
+    "asset_mint",
+    "asset_vault",
+    "quote_vault",
+    "authority",
+    "dex_market",
+    "dex_open_orders",
+    "dex_event_queue",
+    "dex_bids",
+    "dex_asks",
+    "dex_coin_vault",
+    "dex_pc_vault",
+    "dex_vault_signer",
+    "dex_token_program",
+    "dex_dex_program",
+];
+const CLOSE_SPOT_OPEN_ORDERS_ACCOUNTS: &[&str] = &[
+    "master_account",
+    "sub_account",
+    "asset_pool",
+    "token_mint",
+    "dex_market",
+    "open_orders",
+    "authority",
+    "dex_program",
+];
+const INIT_SPOT_OPEN_ORDERS_ACCOUNTS: &[&str] = &[
+    "master_account",
+    "sub_account",
+    "pool",
+    "token_mint",
+    "dex_market",
+    "open_orders",
+    "authority",
+    "payer",
+    "system_program",
+    "dex_program",
+    "rent",
+];
+const NEW_SPOT_ORDER_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "asset_pool_node",
+    "quote_pool_node",
+    "asset_mint",
+    "asset_vault",
+    "quote_vault",
+    "vault_signer",
+    "authority",
+    "dex_market",
+    "dex_open_orders",
+    "dex_event_queue",
+    "dex_request_queue",
+    "dex_bids",
+    "dex_asks",
+    "dex_coin_vault",
+    "dex_pc_vault",
+    "dex_vault_signer",
+    "dex_rent",
+    "dex_token_program",
+    "dex_dex_program",
+];
+const SETTLE_SPOT_FUNDS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "asset_pool_node",
+    "quote_pool_node",
+    "asset_mint",
+    "asset_vault",
+    "quote_vault",
+    "authority",
+    "dex_market",
+    "dex_open_orders",
+    "dex_coin_vault",
+    "dex_pc_vault",
+    "dex_vault_signer",
+    "dex_token_program",
+    "dex_dex_program",
+];
+const CANCEL_FUTURES_ORDER_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const CANCEL_FUTURES_ORDERS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const CONSUME_FUTURES_EVENTS_ACCOUNTS: &[&str] =
+    &["clearing", "market", "orderbook", "event_queue"];
+const MULTIPLE_NEW_FUTURES_ORDERS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "price_history",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const NEW_FUTURES_ORDER_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "price_history",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const PRUNE_FUTURES_ORDERS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const SETTLE_FUTURES_FUNDS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "open_orders",
+    "market",
+    "quote_pool_node",
+    "authority",
+];
+const CANCEL_PERP_ORDER_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const CANCEL_PERP_ORDERS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const CONSUME_PERP_EVENTS_ACCOUNTS: &[&str] = &["clearing", "market", "orderbook", "event_queue"];
+const MULTIPLE_NEW_PERP_ORDERS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const NEW_PERP_ORDER_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const PRUNE_PERP_ORDERS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "market",
+    "open_orders",
+    "orderbook",
+    "event_queue",
+    "bids",
+    "asks",
+    "quote_pool_node",
+    "authority",
+];
+const SETTLE_PERP_FUNDS_ACCOUNTS: &[&str] = &[
+    "clearing",
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "open_orders",
+    "market",
+    "quote_pool_node",
+];
+const SETTLE_FUNDING_ACCOUNTS: &[&str] = &[
+    "cache_account",
+    "master_account",
+    "sub_account",
+    "open_orders",
+    "market",
+    "quote_pool_node",
+];
+
+/// A decoded cypher instruction, with its arguments and named accounts.
+///
+/// This is the mirror image of the builders in [`instructions`](crate::instructions).
+#[allow(clippy::large_enum_variant)]
+pub enum CypherInstruction {
+    AuthorityWithdraw {
+        args: instruction::AuthorityWithdraw,
+        accounts: InstructionAccounts,
+    },
+    CacheOraclePrices {
+        args: instruction::CacheOraclePrices,
+        accounts: InstructionAccounts,
+    },
+    ClaimIdoProceeds {
+        args: instruction::ClaimIdoProceeds,
+        accounts: InstructionAccounts,
+    },
+    ClaimLiquidityMiningRewards {
+        args: instruction::ClaimLiquidityMiningRewards,
+        accounts: InstructionAccounts,
+    },
+    CloseAccount {
+        args: instruction::CloseAccount,
+        accounts: InstructionAccounts,
+    },
+    CloseCacheAccount {
+        args: instruction::CloseCacheAccount,
+        accounts: InstructionAccounts,
+    },
+    CloseClearing {
+        args: instruction::CloseClearing,
+        accounts: InstructionAccounts,
+    },
+    CloseFuturesMarket {
+        args: instruction::CloseFuturesMarket,
+        accounts: InstructionAccounts,
+    },
+    CloseOracleProducts {
+        args: instruction::CloseOracleProducts,
+        accounts: InstructionAccounts,
+    },
+    CloseOrdersAccount {
+        args: instruction::CloseOrdersAccount,
+        accounts: InstructionAccounts,
+    },
+    ClosePerpMarket {
+        args: instruction::ClosePerpMarket,
+        accounts: InstructionAccounts,
+    },
+    ClosePool {
+        args: instruction::ClosePool,
+        accounts: InstructionAccounts,
+    },
+    ClosePoolNode {
+        args: instruction::ClosePoolNode,
+        accounts: InstructionAccounts,
+    },
+    CloseWhitelist {
+        args: instruction::CloseWhitelist,
+        accounts: InstructionAccounts,
+    },
+    CloseSubAccount {
+        args: instruction::CloseSubAccount,
+        accounts: InstructionAccounts,
+    },
+    CreateAccount {
+        args: instruction::CreateAccount,
+        accounts: InstructionAccounts,
+    },
+    CreatePublicClearing {
+        args: instruction::CreatePublicClearing,
+        accounts: InstructionAccounts,
+    },
+    CreatePrivateClearing {
+        args: instruction::CreatePrivateClearing,
+        accounts: InstructionAccounts,
+    },
+    CreateOracleStub {
+        args: instruction::CreateOracleStub,
+        accounts: InstructionAccounts,
+    },
+    CreateOrdersAccount {
+        args: instruction::CreateOrdersAccount,
+        accounts: InstructionAccounts,
+    },
+    CreateFuturesMarket {
+        args: instruction::CreateFuturesMarket,
+        accounts: InstructionAccounts,
+    },
+    CreatePerpMarket {
+        args: instruction::CreatePerpMarket,
+        accounts: InstructionAccounts,
+    },
+    CreatePool {
+        args: instruction::CreatePool,
+        accounts: InstructionAccounts,
+    },
+    CreatePoolNode {
+        args: instruction::CreatePoolNode,
+        accounts: InstructionAccounts,
+    },
+    CreateSubAccount {
+        args: instruction::CreateSubAccount,
+        accounts: InstructionAccounts,
+    },
+    CreateWhitelist {
+        args: instruction::CreateWhitelist,
+        accounts: InstructionAccounts,
+    },
+    CreateWhitelistedAccount {
+        args: instruction::CreateWhitelistedAccount,
+        accounts: InstructionAccounts,
+    },
+    DepositDeliverable {
+        args: instruction::DepositDeliverable,
+        accounts: InstructionAccounts,
+    },
+    DepositFunds {
+        args: instruction::DepositFunds,
+        accounts: InstructionAccounts,
+    },
+    EditSubAccountMargining {
+        args: instruction::EditSubAccountMargining,
+        accounts: InstructionAccounts,
+    },
+    CreateOracleProducts {
+        args: instruction::CreateOracleProducts,
+        accounts: InstructionAccounts,
+    },
+    InitCacheAccount {
+        args: instruction::InitCacheAccount,
+        accounts: InstructionAccounts,
+    },
+    LiquidateFuturesPosition {
+        args: instruction::LiquidateFuturesPosition,
+        accounts: InstructionAccounts,
+    },
+    LiquidatePerpPosition {
+        args: instruction::LiquidatePerpPosition,
+        accounts: InstructionAccounts,
+    },
+    LiquidateSpotPosition {
+        args: instruction::LiquidateSpotPosition,
+        accounts: InstructionAccounts,
+    },
+    RollMarketExpiry {
+        args: instruction::RollMarketExpiry,
+        accounts: InstructionAccounts,
+    },
+    RevokeWhitelist {
+        args: instruction::RevokeWhitelist,
+        accounts: InstructionAccounts,
+    },
+    SetAccountDelegate {
+        args: instruction::SetAccountDelegate,
+        accounts: InstructionAccounts,
+    },
+    SetAccountFeeTier {
+        args: instruction::SetAccountFeeTier,
+        accounts: InstructionAccounts,
+    },
+    SetCacheAuthority {
+        args: instruction::SetCacheAuthority,
+        accounts: InstructionAccounts,
+    },
+    SetClearingAuthority {
+        args: instruction::SetClearingAuthority,
+        accounts: InstructionAccounts,
+    },
+    SetClearingFeeTiers {
+        args: instruction::SetClearingFeeTiers,
+        accounts: InstructionAccounts,
+    },
+    SetClearingFeeMint {
+        args: instruction::SetClearingFeeMint,
+        accounts: InstructionAccounts,
+    },
+    SetFuturesMarketAuthority {
+        args: instruction::SetFuturesMarketAuthority,
+        accounts: InstructionAccounts,
+    },
+    SetFuturesMarketLiquidityMiningInfo {
+        args: instruction::SetFuturesMarketLiquidityMiningInfo,
+        accounts: InstructionAccounts,
+    },
+    SetFuturesMarketParams {
+        args: instruction::SetFuturesMarketParams,
+        accounts: InstructionAccounts,
+    },
+    SetFuturesMarketStatus {
+        args: instruction::SetFuturesMarketStatus,
+        accounts: InstructionAccounts,
+    },
+    SetPerpetualMarketAuthority {
+        args: instruction::SetPerpetualMarketAuthority,
+        accounts: InstructionAccounts,
+    },
+    SetPerpetualMarketLiquidityMiningInfo {
+        args: instruction::SetPerpetualMarketLiquidityMiningInfo,
+        accounts: InstructionAccounts,
+    },
+    SetPerpetualMarketParams {
+        args: instruction::SetPerpetualMarketParams,
+        accounts: InstructionAccounts,
+    },
+    SetPerpetualMarketStatus {
+        args: instruction::SetPerpetualMarketStatus,
+        accounts: InstructionAccounts,
+    },
+    SetPoolNodeAuthority {
+        args: instruction::SetPoolNodeAuthority,
+        accounts: InstructionAccounts,
+    },
+    SetPoolNodeStatus {
+        args: instruction::SetPoolNodeStatus,
+        accounts: InstructionAccounts,
+    },
+    SetPoolAuthority {
+        args: instruction::SetPoolAuthority,
+        accounts: InstructionAccounts,
+    },
+    SetPoolStatus {
+        args: instruction::SetPoolStatus,
+        accounts: InstructionAccounts,
+    },
+    SetPoolParams {
+        args: instruction::SetPoolParams,
+        accounts: InstructionAccounts,
+    },
+    SetOracleProducts {
+        args: instruction::SetOracleProducts,
+        accounts: InstructionAccounts,
+    },
+    SetOracleProductsV2 {
+        args: instruction::SetOracleProductsV2,
+        accounts: InstructionAccounts,
+    },
+    SetOracleStubPrice {
+        args: instruction::SetOracleStubPrice,
+        accounts: InstructionAccounts,
+    },
+    SetSubAccountDelegate {
+        args: instruction::SetSubAccountDelegate,
+        accounts: InstructionAccounts,
+    },
+    SetPoolDexMarket {
+        args: instruction::SetPoolDexMarket,
+        accounts: InstructionAccounts,
+    },
+    SettlePositionWithDelivery {
+        args: instruction::SettlePositionWithDelivery,
+        accounts: InstructionAccounts,
+    },
+    SettlePosition {
+        args: instruction::SettlePosition,
+        accounts: InstructionAccounts,
+    },
+    SweepMarketFees {
+        args: instruction::SweepMarketFees,
+        accounts: InstructionAccounts,
+    },
+    SweepPoolFees {
+        args: instruction::SweepPoolFees,
+        accounts: InstructionAccounts,
+    },
+    TransferBetweenSubAccounts {
+        args: instruction::TransferBetweenSubAccounts,
+        accounts: InstructionAccounts,
+    },
+    UpdateAccountMargin {
+        args: instruction::UpdateAccountMargin,
+        accounts: InstructionAccounts,
+    },
+    UpdateFundingRate {
+        args: instruction::UpdateFundingRate,
+        accounts: InstructionAccounts,
+    },
+    UpdateMarketExpiration {
+        args: instruction::UpdateMarketExpiration,
+        accounts: InstructionAccounts,
+    },
+    UpdateTokenIndex {
+        args: instruction::UpdateTokenIndex,
+        accounts: InstructionAccounts,
+    },
+    UpgradeOracleProducts {
+        args: instruction::UpgradeOracleProducts,
+        accounts: InstructionAccounts,
+    },
+    WithdrawFunds {
+        args: instruction::WithdrawFunds,
+        accounts: InstructionAccounts,
+    },
+    CancelSpotOrder {
+        args: instruction::CancelSpotOrder,
+        accounts: InstructionAccounts,
+    },
+    CloseSpotOpenOrders {
+        args: instruction::CloseSpotOpenOrders,
+        accounts: InstructionAccounts,
+    },
+    InitSpotOpenOrders {
+        args: instruction::InitSpotOpenOrders,
+        accounts: InstructionAccounts,
+    },
+    NewSpotOrder {
+        args: instruction::NewSpotOrder,
+        accounts: InstructionAccounts,
+    },
+    SettleSpotFunds {
+        args: instruction::SettleSpotFunds,
+        accounts: InstructionAccounts,
+    },
+    CancelFuturesOrder {
+        args: instruction::CancelFuturesOrder,
+        accounts: InstructionAccounts,
+    },
+    CancelFuturesOrders {
+        args: instruction::CancelFuturesOrders,
+        accounts: InstructionAccounts,
+    },
+    ConsumeFuturesEvents {
+        args: instruction::ConsumeFuturesEvents,
+        accounts: InstructionAccounts,
+    },
+    MultipleNewFuturesOrders {
+        args: instruction::MultipleNewFuturesOrders,
+        accounts: InstructionAccounts,
+    },
+    NewFuturesOrder {
+        args: instruction::NewFuturesOrder,
+        accounts: InstructionAccounts,
+    },
+    PruneFuturesOrders {
+        args: instruction::PruneFuturesOrders,
+        accounts: InstructionAccounts,
+    },
+    SettleFuturesFunds {
+        args: instruction::SettleFuturesFunds,
+        accounts: InstructionAccounts,
+    },
+    CancelPerpOrder {
+        args: instruction::CancelPerpOrder,
+        accounts: InstructionAccounts,
+    },
+    CancelPerpOrders {
+        args: instruction::CancelPerpOrders,
+        accounts: InstructionAccounts,
+    },
+    ConsumePerpEvents {
+        args: instruction::ConsumePerpEvents,
+        accounts: InstructionAccounts,
+    },
+    MultipleNewPerpOrders {
+        args: instruction::MultipleNewPerpOrders,
+        accounts: InstructionAccounts,
+    },
+    NewPerpOrder {
+        args: instruction::NewPerpOrder,
+        accounts: InstructionAccounts,
+    },
+    PrunePerpOrders {
+        args: instruction::PrunePerpOrders,
+        accounts: InstructionAccounts,
+    },
+    SettlePerpFunds {
+        args: instruction::SettlePerpFunds,
+        accounts: InstructionAccounts,
+    },
+    SettleFunding {
+        args: instruction::SettleFunding,
+        accounts: InstructionAccounts,
+    },
+    /// The instruction data does not match any known instruction or there are not enough accounts.
+    Unknown {
+        data: Vec<u8>,
+        accounts: Vec<Pubkey>,
+    },
+}
+
+impl CypherInstruction {
+    /// The name of the instruction, as in the IDL.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AuthorityWithdraw { .. } => "AuthorityWithdraw",
+            Self::CacheOraclePrices { .. } => "CacheOraclePrices",
+            Self::ClaimIdoProceeds { .. } => "ClaimIdoProceeds",
+            Self::ClaimLiquidityMiningRewards { .. } => "ClaimLiquidityMiningRewards",
+            Self::CloseAccount { .. } => "CloseAccount",
+            Self::CloseCacheAccount { .. } => "CloseCacheAccount",
+            Self::CloseClearing { .. } => "CloseClearing",
+            Self::CloseFuturesMarket { .. } => "CloseFuturesMarket",
+            Self::CloseOracleProducts { .. } => "CloseOracleProducts",
+            Self::CloseOrdersAccount { .. } => "CloseOrdersAccount",
+            Self::ClosePerpMarket { .. } => "ClosePerpMarket",
+            Self::ClosePool { .. } => "ClosePool",
+            Self::ClosePoolNode { .. } => "ClosePoolNode",
+            Self::CloseWhitelist { .. } => "CloseWhitelist",
+            Self::CloseSubAccount { .. } => "CloseSubAccount",
+            Self::CreateAccount { .. } => "CreateAccount",
+            Self::CreatePublicClearing { .. } => "CreatePublicClearing",
+            Self::CreatePrivateClearing { .. } => "CreatePrivateClearing",
+            Self::CreateOracleStub { .. } => "CreateOracleStub",
+            Self::CreateOrdersAccount { .. } => "CreateOrdersAccount",
+            Self::CreateFuturesMarket { .. } => "CreateFuturesMarket",
+            Self::CreatePerpMarket { .. } => "CreatePerpMarket",
+            Self::CreatePool { .. } => "CreatePool",
+            Self::CreatePoolNode { .. } => "CreatePoolNode",
+            Self::CreateSubAccount { .. } => "CreateSubAccount",
+            Self::CreateWhitelist { .. } => "CreateWhitelist",
+            Self::CreateWhitelistedAccount { .. } => "CreateWhitelistedAccount",
+            Self::DepositDeliverable { .. } => "DepositDeliverable",
+            Self::DepositFunds { .. } => "DepositFunds",
+            Self::EditSubAccountMargining { .. } => "EditSubAccountMargining",
+            Self::CreateOracleProducts { .. } => "CreateOracleProducts",
+            Self::InitCacheAccount { .. } => "InitCacheAccount",
+            Self::LiquidateFuturesPosition { .. } => "LiquidateFuturesPosition",
+            Self::LiquidatePerpPosition { .. } => "LiquidatePerpPosition",
+            Self::LiquidateSpotPosition { .. } => "LiquidateSpotPosition",
+            Self::RollMarketExpiry { .. } => "RollMarketExpiry",
+            Self::RevokeWhitelist { .. } => "RevokeWhitelist",
+            Self::SetAccountDelegate { .. } => "SetAccountDelegate",
+            Self::SetAccountFeeTier { .. } => "SetAccountFeeTier",
+            Self::SetCacheAuthority { .. } => "SetCacheAuthority",
+            Self::SetClearingAuthority { .. } => "SetClearingAuthority",
+            Self::SetClearingFeeTiers { .. } => "SetClearingFeeTiers",
+            Self::SetClearingFeeMint { .. } => "SetClearingFeeMint",
+            Self::SetFuturesMarketAuthority { .. } => "SetFuturesMarketAuthority",
+            Self::SetFuturesMarketLiquidityMiningInfo { .. } => {
+                "SetFuturesMarketLiquidityMiningInfo"
+            }
+            Self::SetFuturesMarketParams { .. } => "SetFuturesMarketParams",
+            Self::SetFuturesMarketStatus { .. } => "SetFuturesMarketStatus",
+            Self::SetPerpetualMarketAuthority { .. } => "SetPerpetualMarketAuthority",
+            Self::SetPerpetualMarketLiquidityMiningInfo { .. } => {
+                "SetPerpetualMarketLiquidityMiningInfo"
+            }
+            Self::SetPerpetualMarketParams { .. } => "SetPerpetualMarketParams",
+            Self::SetPerpetualMarketStatus { .. } => "SetPerpetualMarketStatus",
+            Self::SetPoolNodeAuthority { .. } => "SetPoolNodeAuthority",
+            Self::SetPoolNodeStatus { .. } => "SetPoolNodeStatus",
+            Self::SetPoolAuthority { .. } => "SetPoolAuthority",
+            Self::SetPoolStatus { .. } => "SetPoolStatus",
+            Self::SetPoolParams { .. } => "SetPoolParams",
+            Self::SetOracleProducts { .. } => "SetOracleProducts",
+            Self::SetOracleProductsV2 { .. } => "SetOracleProductsV2",
+            Self::SetOracleStubPrice { .. } => "SetOracleStubPrice",
+            Self::SetSubAccountDelegate { .. } => "SetSubAccountDelegate",
+            Self::SetPoolDexMarket { .. } => "SetPoolDexMarket",
+            Self::SettlePositionWithDelivery { .. } => "SettlePositionWithDelivery",
+            Self::SettlePosition { .. } => "SettlePosition",
+            Self::SweepMarketFees { .. } => "SweepMarketFees",
+            Self::SweepPoolFees { .. } => "SweepPoolFees",
+            Self::TransferBetweenSubAccounts { .. } => "TransferBetweenSubAccounts",
+            Self::UpdateAccountMargin { .. } => "UpdateAccountMargin",
+            Self::UpdateFundingRate { .. } => "UpdateFundingRate",
+            Self::UpdateMarketExpiration { .. } => "UpdateMarketExpiration",
+            Self::UpdateTokenIndex { .. } => "UpdateTokenIndex",
+            Self::UpgradeOracleProducts { .. } => "UpgradeOracleProducts",
+            Self::WithdrawFunds { .. } => "WithdrawFunds",
+            Self::CancelSpotOrder { .. } => "CancelSpotOrder",
+            Self::CloseSpotOpenOrders { .. } => "CloseSpotOpenOrders",
+            Self::InitSpotOpenOrders { .. } => "InitSpotOpenOrders",
+            Self::NewSpotOrder { .. } => "NewSpotOrder",
+            Self::SettleSpotFunds { .. } => "SettleSpotFunds",
+            Self::CancelFuturesOrder { .. } => "CancelFuturesOrder",
+            Self::CancelFuturesOrders { .. } => "CancelFuturesOrders",
+            Self::ConsumeFuturesEvents { .. } => "ConsumeFuturesEvents",
+            Self::MultipleNewFuturesOrders { .. } => "MultipleNewFuturesOrders",
+            Self::NewFuturesOrder { .. } => "NewFuturesOrder",
+            Self::PruneFuturesOrders { .. } => "PruneFuturesOrders",
+            Self::SettleFuturesFunds { .. } => "SettleFuturesFunds",
+            Self::CancelPerpOrder { .. } => "CancelPerpOrder",
+            Self::CancelPerpOrders { .. } => "CancelPerpOrders",
+            Self::ConsumePerpEvents { .. } => "ConsumePerpEvents",
+            Self::MultipleNewPerpOrders { .. } => "MultipleNewPerpOrders",
+            Self::NewPerpOrder { .. } => "NewPerpOrder",
+            Self::PrunePerpOrders { .. } => "PrunePerpOrders",
+            Self::SettlePerpFunds { .. } => "SettlePerpFunds",
+            Self::SettleFunding { .. } => "SettleFunding",
+            Self::Unknown { .. } => "Unknown",
+        }
+    }
+
+    /// The named accounts of the instruction, `None` if it is unknown.
+    pub fn accounts(&self) -> Option<&InstructionAccounts> {
+        match self {
+            Self::AuthorityWithdraw { accounts, .. }
+            | Self::CacheOraclePrices { accounts, .. }
+            | Self::ClaimIdoProceeds { accounts, .. }
+            | Self::ClaimLiquidityMiningRewards { accounts, .. }
+            | Self::CloseAccount { accounts, .. }
+            | Self::CloseCacheAccount { accounts, .. }
+            | Self::CloseClearing { accounts, .. }
+            | Self::CloseFuturesMarket { accounts, .. }
+            | Self::CloseOracleProducts { accounts, .. }
+            | Self::CloseOrdersAccount { accounts, .. }
+            | Self::ClosePerpMarket { accounts, .. }
+            | Self::ClosePool { accounts, .. }
+            | Self::ClosePoolNode { accounts, .. }
+            | Self::CloseWhitelist { accounts, .. }
+            | Self::CloseSubAccount { accounts, .. }
+            | Self::CreateAccount { accounts, .. }
+            | Self::CreatePublicClearing { accounts, .. }
+            | Self::CreatePrivateClearing { accounts, .. }
+            | Self::CreateOracleStub { accounts, .. }
+            | Self::CreateOrdersAccount { accounts, .. }
+            | Self::CreateFuturesMarket { accounts, .. }
+            | Self::CreatePerpMarket { accounts, .. }
+            | Self::CreatePool { accounts, .. }
+            | Self::CreatePoolNode { accounts, .. }
+            | Self::CreateSubAccount { accounts, .. }
+            | Self::CreateWhitelist { accounts, .. }
+            | Self::CreateWhitelistedAccount { accounts, .. }
+            | Self::DepositDeliverable { accounts, .. }
+            | Self::DepositFunds { accounts, .. }
+            | Self::EditSubAccountMargining { accounts, .. }
+            | Self::CreateOracleProducts { accounts, .. }
+            | Self::InitCacheAccount { accounts, .. }
+            | Self::LiquidateFuturesPosition { accounts, .. }
+            | Self::LiquidatePerpPosition { accounts, .. }
+            | Self::LiquidateSpotPosition { accounts, .. }
+            | Self::RollMarketExpiry { accounts, .. }
+            | Self::RevokeWhitelist { accounts, .. }
+            | Self::SetAccountDelegate { accounts, .. }
+            | Self::SetAccountFeeTier { accounts, .. }
+            | Self::SetCacheAuthority { accounts, .. }
+            | Self::SetClearingAuthority { accounts, .. }
+            | Self::SetClearingFeeTiers { accounts, .. }
+            | Self::SetClearingFeeMint { accounts, .. }
+            | Self::SetFuturesMarketAuthority { accounts, .. }
+            | Self::SetFuturesMarketLiquidityMiningInfo { accounts, .. }
+            | Self::SetFuturesMarketParams { accounts, .. }
+            | Self::SetFuturesMarketStatus { accounts, .. }
+            | Self::SetPerpetualMarketAuthority { accounts, .. }
+            | Self::SetPerpetualMarketLiquidityMiningInfo { accounts, .. }
+            | Self::SetPerpetualMarketParams { accounts, .. }
+            | Self::SetPerpetualMarketStatus { accounts, .. }
+            | Self::SetPoolNodeAuthority { accounts, .. }
+            | Self::SetPoolNodeStatus { accounts, .. }
+            | Self::SetPoolAuthority { accounts, .. }
+            | Self::SetPoolStatus { accounts, .. }
+            | Self::SetPoolParams { accounts, .. }
+            | Self::SetOracleProducts { accounts, .. }
+            | Self::SetOracleProductsV2 { accounts, .. }
+            | Self::SetOracleStubPrice { accounts, .. }
+            | Self::SetSubAccountDelegate { accounts, .. }
+            | Self::SetPoolDexMarket { accounts, .. }
+            | Self::SettlePositionWithDelivery { accounts, .. }
+            | Self::SettlePosition { accounts, .. }
+            | Self::SweepMarketFees { accounts, .. }
+            | Self::SweepPoolFees { accounts, .. }
+            | Self::TransferBetweenSubAccounts { accounts, .. }
+            | Self::UpdateAccountMargin { accounts, .. }
+            | Self::UpdateFundingRate { accounts, .. }
+            | Self::UpdateMarketExpiration { accounts, .. }
+            | Self::UpdateTokenIndex { accounts, .. }
+            | Self::UpgradeOracleProducts { accounts, .. }
+            | Self::WithdrawFunds { accounts, .. }
+            | Self::CancelSpotOrder { accounts, .. }
+            | Self::CloseSpotOpenOrders { accounts, .. }
+            | Self::InitSpotOpenOrders { accounts, .. }
+            | Self::NewSpotOrder { accounts, .. }
+            | Self::SettleSpotFunds { accounts, .. }
+            | Self::CancelFuturesOrder { accounts, .. }
+            | Self::CancelFuturesOrders { accounts, .. }
+            | Self::ConsumeFuturesEvents { accounts, .. }
+            | Self::MultipleNewFuturesOrders { accounts, .. }
+            | Self::NewFuturesOrder { accounts, .. }
+            | Self::PruneFuturesOrders { accounts, .. }
+            | Self::SettleFuturesFunds { accounts, .. }
+            | Self::CancelPerpOrder { accounts, .. }
+            | Self::CancelPerpOrders { accounts, .. }
+            | Self::ConsumePerpEvents { accounts, .. }
+            | Self::MultipleNewPerpOrders { accounts, .. }
+            | Self::NewPerpOrder { accounts, .. }
+            | Self::PrunePerpOrders { accounts, .. }
+            | Self::SettlePerpFunds { accounts, .. }
+            | Self::SettleFunding { accounts, .. } => Some(accounts),
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    /// Whether the instruction is unknown.
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Self::Unknown { .. })
+    }
+}
+
+impl fmt::Debug for CypherInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown { data, accounts } => f
+                .debug_struct("Unknown")
+                .field("data", data)
+                .field("accounts", accounts)
+                .finish(),
+            _ => f
+                .debug_struct(self.name())
+                .field("accounts", self.accounts().unwrap())
+                .finish(),
+        }
+    }
+}
+
+/// Decodes the instruction if the data starts with the instruction's discriminator.
+macro_rules! try_decode_instruction {
+    ($discriminator:expr, $args_data:expr, $accounts:expr, $variant:ident, $names:expr) => {
+        if $discriminator == instruction::$variant::DISCRIMINATOR {
+            let args = match instruction::$variant::deserialize(&mut $args_data) {
+                Ok(a) => a,
+                Err(_) => return None,
+            };
+            let accounts = InstructionAccounts::new($names, $accounts)?;
+            return Some(CypherInstruction::$variant { args, accounts });
+        }
+    };
+}
+
+/// Decodes the given instruction data and accounts into a [`CypherInstruction`].
+///
+/// Returns [`CypherInstruction::Unknown`] if the data does not match any instruction,
+/// the arguments can not be deserialized or there are less accounts than the instruction expects.
+pub fn decode_instruction(data: &[u8], accounts: &[Pubkey]) -> CypherInstruction {
+    match try_decode_instruction(data, accounts) {
+        Some(ix) => ix,
+        None => CypherInstruction::Unknown {
+            data: data.to_vec(),
+            accounts: accounts.to_vec(),
+        },
+    }
+}
+
+fn try_decode_instruction(data: &[u8], accounts: &[Pubkey]) -> Option<CypherInstruction> {
+    if data.len() < 8 {
+        return None;
+    }
+    let (discriminator, mut args_data) = data.split_at(8);
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        AuthorityWithdraw,
+        AUTHORITY_WITHDRAW_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CacheOraclePrices,
+        CACHE_ORACLE_PRICES_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ClaimIdoProceeds,
+        CLAIM_IDO_PROCEEDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ClaimLiquidityMiningRewards,
+        CLAIM_LIQUIDITY_MINING_REWARDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseAccount,
+        CLOSE_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseCacheAccount,
+        CLOSE_CACHE_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseClearing,
+        CLOSE_CLEARING_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseFuturesMarket,
+        CLOSE_FUTURES_MARKET_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseOracleProducts,
+        CLOSE_ORACLE_PRODUCTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseOrdersAccount,
+        CLOSE_ORDERS_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ClosePerpMarket,
+        CLOSE_PERP_MARKET_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ClosePool,
+        CLOSE_POOL_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ClosePoolNode,
+        CLOSE_POOL_NODE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseWhitelist,
+        CLOSE_WHITELIST_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseSubAccount,
+        CLOSE_SUB_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateAccount,
+        CREATE_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreatePublicClearing,
+        CREATE_PUBLIC_CLEARING_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreatePrivateClearing,
+        CREATE_PRIVATE_CLEARING_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateOracleStub,
+        CREATE_ORACLE_STUB_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateOrdersAccount,
+        CREATE_ORDERS_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateFuturesMarket,
+        CREATE_FUTURES_MARKET_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreatePerpMarket,
+        CREATE_PERP_MARKET_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreatePool,
+        CREATE_POOL_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreatePoolNode,
+        CREATE_POOL_NODE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateSubAccount,
+        CREATE_SUB_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateWhitelist,
+        CREATE_WHITELIST_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateWhitelistedAccount,
+        CREATE_WHITELISTED_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        DepositDeliverable,
+        DEPOSIT_DELIVERABLE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        DepositFunds,
+        DEPOSIT_FUNDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        EditSubAccountMargining,
+        EDIT_SUB_ACCOUNT_MARGINING_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CreateOracleProducts,
+        CREATE_ORACLE_PRODUCTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        InitCacheAccount,
+        INIT_CACHE_ACCOUNT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        LiquidateFuturesPosition,
+        LIQUIDATE_FUTURES_POSITION_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        LiquidatePerpPosition,
+        LIQUIDATE_PERP_POSITION_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        LiquidateSpotPosition,
+        LIQUIDATE_SPOT_POSITION_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        RollMarketExpiry,
+        ROLL_MARKET_EXPIRY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        RevokeWhitelist,
+        REVOKE_WHITELIST_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetAccountDelegate,
+        SET_ACCOUNT_DELEGATE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetAccountFeeTier,
+        SET_ACCOUNT_FEE_TIER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetCacheAuthority,
+        SET_CACHE_AUTHORITY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetClearingAuthority,
+        SET_CLEARING_AUTHORITY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetClearingFeeTiers,
+        SET_CLEARING_FEE_TIERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetClearingFeeMint,
+        SET_CLEARING_FEE_MINT_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetFuturesMarketAuthority,
+        SET_FUTURES_MARKET_AUTHORITY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetFuturesMarketLiquidityMiningInfo,
+        SET_FUTURES_MARKET_LIQUIDITY_MINING_INFO_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetFuturesMarketParams,
+        SET_FUTURES_MARKET_PARAMS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetFuturesMarketStatus,
+        SET_FUTURES_MARKET_STATUS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPerpetualMarketAuthority,
+        SET_PERPETUAL_MARKET_AUTHORITY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPerpetualMarketLiquidityMiningInfo,
+        SET_PERPETUAL_MARKET_LIQUIDITY_MINING_INFO_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPerpetualMarketParams,
+        SET_PERPETUAL_MARKET_PARAMS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPerpetualMarketStatus,
+        SET_PERPETUAL_MARKET_STATUS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPoolNodeAuthority,
+        SET_POOL_NODE_AUTHORITY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPoolNodeStatus,
+        SET_POOL_NODE_STATUS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPoolAuthority,
+        SET_POOL_AUTHORITY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPoolStatus,
+        SET_POOL_STATUS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPoolParams,
+        SET_POOL_PARAMS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetOracleProducts,
+        SET_ORACLE_PRODUCTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetOracleProductsV2,
+        SET_ORACLE_PRODUCTS_V2_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetOracleStubPrice,
+        SET_ORACLE_STUB_PRICE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetSubAccountDelegate,
+        SET_SUB_ACCOUNT_DELEGATE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SetPoolDexMarket,
+        SET_POOL_DEX_MARKET_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SettlePositionWithDelivery,
+        SETTLE_POSITION_WITH_DELIVERY_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SettlePosition,
+        SETTLE_POSITION_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SweepMarketFees,
+        SWEEP_MARKET_FEES_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SweepPoolFees,
+        SWEEP_POOL_FEES_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        TransferBetweenSubAccounts,
+        TRANSFER_BETWEEN_SUB_ACCOUNTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        UpdateAccountMargin,
+        UPDATE_ACCOUNT_MARGIN_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        UpdateFundingRate,
+        UPDATE_FUNDING_RATE_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        UpdateMarketExpiration,
+        UPDATE_MARKET_EXPIRATION_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        UpdateTokenIndex,
+        UPDATE_TOKEN_INDEX_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        UpgradeOracleProducts,
+        UPGRADE_ORACLE_PRODUCTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        WithdrawFunds,
+        WITHDRAW_FUNDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CancelSpotOrder,
+        CANCEL_SPOT_ORDER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CloseSpotOpenOrders,
+        CLOSE_SPOT_OPEN_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        InitSpotOpenOrders,
+        INIT_SPOT_OPEN_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        NewSpotOrder,
+        NEW_SPOT_ORDER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SettleSpotFunds,
+        SETTLE_SPOT_FUNDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CancelFuturesOrder,
+        CANCEL_FUTURES_ORDER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CancelFuturesOrders,
+        CANCEL_FUTURES_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ConsumeFuturesEvents,
+        CONSUME_FUTURES_EVENTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        MultipleNewFuturesOrders,
+        MULTIPLE_NEW_FUTURES_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        NewFuturesOrder,
+        NEW_FUTURES_ORDER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        PruneFuturesOrders,
+        PRUNE_FUTURES_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SettleFuturesFunds,
+        SETTLE_FUTURES_FUNDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CancelPerpOrder,
+        CANCEL_PERP_ORDER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        CancelPerpOrders,
+        CANCEL_PERP_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        ConsumePerpEvents,
+        CONSUME_PERP_EVENTS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        MultipleNewPerpOrders,
+        MULTIPLE_NEW_PERP_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        NewPerpOrder,
+        NEW_PERP_ORDER_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        PrunePerpOrders,
+        PRUNE_PERP_ORDERS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SettlePerpFunds,
+        SETTLE_PERP_FUNDS_ACCOUNTS
+    );
+    try_decode_instruction!(
+        discriminator,
+        args_data,
+        accounts,
+        SettleFunding,
+        SETTLE_FUNDING_ACCOUNTS
+    );
+    None
+}
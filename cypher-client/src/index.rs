@@ -0,0 +1,163 @@
+use std::{convert::TryFrom, fmt};
+
+use crate::constants::{CACHES_MAX_CNT, QUOTE_TOKEN_IDX, TOKENS_MAX_CNT};
+
+/// An error returned when an index is out of the bounds of the array it indexes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexError {
+    /// The cache index is out of bounds.
+    CacheIndexOutOfBounds(usize),
+    /// The position slot index is out of bounds.
+    PositionIdxOutOfBounds(usize),
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::CacheIndexOutOfBounds(idx) => write!(
+                f,
+                "Cache index {} is out of bounds, maximum is {}.",
+                idx,
+                CACHES_MAX_CNT - 1
+            ),
+            IndexError::PositionIdxOutOfBounds(idx) => write!(
+                f,
+                "Position index {} is out of bounds, maximum is {}.",
+                idx,
+                TOKENS_MAX_CNT - 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// The index of a [`Cache`](crate::Cache) in the [`CacheAccount`](crate::CacheAccount).
+///
+/// It is guaranteed to be lower than [`CACHES_MAX_CNT`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheIndex(usize);
+
+impl CacheIndex {
+    /// Creates a new [`CacheIndex`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the index is not lower than [`CACHES_MAX_CNT`].
+    pub fn new(idx: usize) -> Result<Self, IndexError> {
+        if idx < CACHES_MAX_CNT {
+            Ok(Self(idx))
+        } else {
+            Err(IndexError::CacheIndexOutOfBounds(idx))
+        }
+    }
+
+    /// The index as a `usize`.
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+impl TryFrom<usize> for CacheIndex {
+    type Error = IndexError;
+
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        Self::new(idx)
+    }
+}
+
+impl TryFrom<u16> for CacheIndex {
+    type Error = IndexError;
+
+    fn try_from(idx: u16) -> Result<Self, Self::Error> {
+        Self::new(idx as usize)
+    }
+}
+
+impl TryFrom<u64> for CacheIndex {
+    type Error = IndexError;
+
+    fn try_from(idx: u64) -> Result<Self, Self::Error> {
+        Self::new(idx as usize)
+    }
+}
+
+impl From<CacheIndex> for usize {
+    fn from(idx: CacheIndex) -> Self {
+        idx.0
+    }
+}
+
+impl From<CacheIndex> for u64 {
+    fn from(idx: CacheIndex) -> Self {
+        idx.0 as u64
+    }
+}
+
+impl fmt::Display for CacheIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The index of a [`PositionSlot`](crate::PositionSlot) in the [`CypherSubAccount`](crate::CypherSubAccount).
+///
+/// It is guaranteed to be lower than [`TOKENS_MAX_CNT`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PositionIdx(usize);
+
+impl PositionIdx {
+    /// The index of the quote token position.
+    pub const QUOTE: PositionIdx = PositionIdx(QUOTE_TOKEN_IDX);
+
+    /// Creates a new [`PositionIdx`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the index is not lower than [`TOKENS_MAX_CNT`].
+    pub fn new(idx: usize) -> Result<Self, IndexError> {
+        if idx < TOKENS_MAX_CNT {
+            Ok(Self(idx))
+        } else {
+            Err(IndexError::PositionIdxOutOfBounds(idx))
+        }
+    }
+
+    /// The index as a `usize`.
+    pub fn get(&self) -> usize {
+        self.0
+    }
+
+    /// Whether this is the index of the quote token position.
+    pub fn is_quote(&self) -> bool {
+        self.0 == QUOTE_TOKEN_IDX
+    }
+}
+
+impl TryFrom<usize> for PositionIdx {
+    type Error = IndexError;
+
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        Self::new(idx)
+    }
+}
+
+impl TryFrom<u8> for PositionIdx {
+    type Error = IndexError;
+
+    fn try_from(idx: u8) -> Result<Self, Self::Error> {
+        Self::new(idx as usize)
+    }
+}
+
+impl From<PositionIdx> for usize {
+    fn from(idx: PositionIdx) -> Self {
+        idx.0
+    }
+}
+
+impl fmt::Display for PositionIdx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -31,7 +31,9 @@ use crate::{
         UpdateMarketExpiration, UpdateTokenIndex, UpgradeOracleProducts, WithdrawFunds,
     },
     constants::SUB_ACCOUNT_ALIAS_LEN,
-    dex, quote_mint, CancelOrderArgs, CreateClearingArgs, CreateFuturesMarketArgs,
+    dex,
+    index::CacheIndex,
+    quote_mint, CancelOrderArgs, CreateClearingArgs, CreateFuturesMarketArgs,
     CreateOracleProductsArgs, CreatePerpetualMarketArgs, CreatePoolArgs, FeeTierArgs,
     LiquidityMiningArgs, NewDerivativeOrderArgs, NewSpotOrderArgs, OperatingStatus, ProductsType,
 };
@@ -609,7 +611,7 @@ pub fn cache_oracle_prices(
     cache_account: &Pubkey,
     oracle_products: &Pubkey,
     price_accounts: &[Pubkey],
-    cache_index: u64,
+    cache_index: CacheIndex,
     pool: &Option<Pubkey>,
     futures_market: &Option<Pubkey>,
 ) -> Result<Instruction, ProgramError> {
@@ -633,7 +635,7 @@ pub fn cache_oracle_prices(
         accounts.push(AccountMeta::new_readonly(futures_market.unwrap(), false));
     }
     let ix_data = crate::instruction::CacheOraclePrices {
-        _cache_index: cache_index,
+        _cache_index: u64::from(cache_index),
     };
     Ok(Instruction {
         program_id: crate::id(),
@@ -659,7 +661,7 @@ pub fn cache_oracle_prices_v1(
     pyth_price_accounts: &[Pubkey],
     chainlink_program_id: &Pubkey,
     chainlink_store_accounts: &[Pubkey],
-    cache_index: u64,
+    cache_index: CacheIndex,
     futures_market: &Option<Pubkey>,
 ) -> Instruction {
     let mut accounts = CacheOraclePrices {
@@ -691,7 +693,7 @@ pub fn cache_oracle_prices_v1(
         accounts.push(AccountMeta::new_readonly(futures_market.unwrap(), false));
     }
     let ix_data = crate::instruction::CacheOraclePrices {
-        _cache_index: cache_index,
+        _cache_index: u64::from(cache_index),
     };
     Instruction {
         program_id: crate::id(),
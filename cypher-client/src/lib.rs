@@ -1,9 +1,16 @@
 #![allow(clippy::too_many_arguments)]
 pub mod aob;
 pub mod constants;
+pub mod decoder;
+pub mod index;
 pub mod instructions;
+pub mod logs;
+pub mod math;
 pub mod serum;
+pub mod simulator;
 pub mod utils;
+pub mod valuation;
+pub mod version;
 
 use agnostic_orderbook::state::Side as AobSide;
 use anchor_lang::prelude::*;
@@ -11,10 +18,10 @@ use anchor_spl::dex::serum_dex::matching::Side as DexSide;
 use bonfida_utils::fp_math::fp32_mul_floor;
 use constants::{INV_ONE_HUNDRED_FIXED, QUOTE_TOKEN_IDX};
 use fixed::types::I80F48;
+use index::{CacheIndex, IndexError, PositionIdx};
+use math::MathError;
 use std::{mem::take, ops::Mul};
-use utils::adjust_decimals;
-
-use crate::constants::TOKENS_MAX_CNT;
+use utils::try_adjust_decimals;
 
 anchor_gen::generate_cpi_interface!(
     idl_path = "idl.json",
@@ -424,6 +431,20 @@ impl CacheAccount {
         &self.caches[price_cache_idx]
     }
 
+    /// gets the price cache at the given index
+    pub fn get_cache(&self, cache_idx: CacheIndex) -> &Cache {
+        &self.caches[cache_idx.get()]
+    }
+
+    /// gets the price cache at the given index, returning an error if the index is out of bounds
+    pub fn try_get_price_cache(
+        &self,
+        price_cache_idx: usize,
+    ) -> std::result::Result<&Cache, IndexError> {
+        let cache_idx = CacheIndex::new(price_cache_idx)?;
+        Ok(self.get_cache(cache_idx))
+    }
+
     /// gets the cache for a given oracle products
     pub fn get_cache_for_oracle_products(&self, oracle_products: &Pubkey) -> Option<&Cache> {
         match self
@@ -458,6 +479,32 @@ impl Cache {
         I80F48::from_bits(self.market_price)
     }
 
+    /// the decimals of a derivative position on a market of the given type
+    pub fn decimals_for(&self, market_type: MarketType) -> u8 {
+        if market_type == MarketType::PerpetualFuture {
+            self.perp_decimals
+        } else {
+            self.futures_decimals
+        }
+    }
+
+    /// the price used to value a derivative position on a market of the given type
+    ///
+    /// perpetual positions are valued at the oracle price, other positions at the twap price,
+    /// falling back to the oracle price if the twap price is not set
+    pub fn price_for(&self, market_type: MarketType) -> I80F48 {
+        if market_type == MarketType::PerpetualFuture {
+            self.oracle_price()
+        } else {
+            let market_price = self.market_price();
+            if market_price == I80F48::ZERO {
+                self.oracle_price()
+            } else {
+                market_price
+            }
+        }
+    }
+
     // spot
     /// gets the init asset weight of the spot token
     pub fn spot_init_asset_weight(&self) -> I80F48 {
@@ -544,6 +591,78 @@ impl Cache {
             .checked_mul(INV_ONE_HUNDRED_FIXED)
             .unwrap()
     }
+
+    /// gets the asset weight of the spot token according to the margin collateral ratio type
+    pub fn spot_asset_weight(&self, mcr_type: MarginCollateralRatioType) -> I80F48 {
+        match mcr_type {
+            MarginCollateralRatioType::Initialization => self.spot_init_asset_weight(),
+            MarginCollateralRatioType::Maintenance => self.spot_maint_asset_weight(),
+        }
+    }
+
+    /// gets the liab weight of the spot token according to the margin collateral ratio type
+    pub fn spot_liab_weight(&self, mcr_type: MarginCollateralRatioType) -> I80F48 {
+        match mcr_type {
+            MarginCollateralRatioType::Initialization => self.spot_init_liab_weight(),
+            MarginCollateralRatioType::Maintenance => self.spot_maint_liab_weight(),
+        }
+    }
+
+    /// gets the asset weight of a derivative position on a market of the given type
+    /// according to the margin collateral ratio type
+    ///
+    /// returns `None` if the market type is not supported
+    pub fn derivative_asset_weight(
+        &self,
+        market_type: MarketType,
+        mcr_type: MarginCollateralRatioType,
+    ) -> Option<I80F48> {
+        match (mcr_type, market_type) {
+            (MarginCollateralRatioType::Initialization, MarketType::PerpetualFuture) => {
+                Some(self.perp_init_asset_weight())
+            }
+            (MarginCollateralRatioType::Maintenance, MarketType::PerpetualFuture) => {
+                Some(self.perp_maint_asset_weight())
+            }
+            (
+                MarginCollateralRatioType::Initialization,
+                MarketType::PairFuture | MarketType::PreIDO | MarketType::IndexFuture,
+            ) => Some(self.futures_init_asset_weight()),
+            (
+                MarginCollateralRatioType::Maintenance,
+                MarketType::PairFuture | MarketType::PreIDO | MarketType::IndexFuture,
+            ) => Some(self.futures_maint_asset_weight()),
+            _ => None,
+        }
+    }
+
+    /// gets the liab weight of a derivative position on a market of the given type
+    /// according to the margin collateral ratio type
+    ///
+    /// returns `None` if the market type is not supported
+    pub fn derivative_liab_weight(
+        &self,
+        market_type: MarketType,
+        mcr_type: MarginCollateralRatioType,
+    ) -> Option<I80F48> {
+        match (mcr_type, market_type) {
+            (MarginCollateralRatioType::Initialization, MarketType::PerpetualFuture) => {
+                Some(self.perp_init_liab_weight())
+            }
+            (MarginCollateralRatioType::Maintenance, MarketType::PerpetualFuture) => {
+                Some(self.perp_maint_liab_weight())
+            }
+            (
+                MarginCollateralRatioType::Initialization,
+                MarketType::PairFuture | MarketType::PreIDO | MarketType::IndexFuture,
+            ) => Some(self.futures_init_liab_weight()),
+            (
+                MarginCollateralRatioType::Maintenance,
+                MarketType::PairFuture | MarketType::PreIDO | MarketType::IndexFuture,
+            ) => Some(self.futures_maint_liab_weight()),
+            _ => None,
+        }
+    }
 }
 
 impl CypherAccount {
@@ -634,17 +753,21 @@ impl CypherSubAccount {
     /// gets the position index for the given identifier.
     ///
     /// this can be a token mint for a spot position or a market's public key for derivatives
-    pub fn get_position_idx(&self, identifier: &Pubkey, is_spot: bool) -> Option<usize> {
+    pub fn get_position_idx(&self, identifier: &Pubkey, is_spot: bool) -> Option<PositionIdx> {
         if *identifier == quote_mint::ID && is_spot {
-            return Some(QUOTE_TOKEN_IDX);
+            return Some(PositionIdx::QUOTE);
         }
-        self.iter_position_slots().position(|p| {
-            if is_spot {
-                p.spot.token_mint == *identifier
-            } else {
-                p.derivative.market == *identifier
-            }
-        })
+        // the index is the one of the slot in the positions array, so empty slots must not be skipped
+        self.positions
+            .iter()
+            .position(|p| {
+                if is_spot {
+                    p.spot.token_mint == *identifier
+                } else {
+                    p.derivative.market == *identifier
+                }
+            })
+            .and_then(|idx| PositionIdx::new(idx).ok())
     }
 
     /// gets the derivative positions
@@ -657,15 +780,13 @@ impl CypherSubAccount {
     }
 
     /// gets the spot position at the given index
-    pub fn get_spot_position(&self, position_idx: usize) -> &SpotPosition {
-        assert!(position_idx < TOKENS_MAX_CNT);
-        &self.positions[position_idx].spot
+    pub fn get_spot_position(&self, position_idx: PositionIdx) -> &SpotPosition {
+        &self.positions[position_idx.get()].spot
     }
 
     /// gets the spot position at the given index
-    pub fn get_spot_position_mut(&mut self, position_idx: usize) -> &mut SpotPosition {
-        assert!(position_idx < TOKENS_MAX_CNT);
-        &mut self.positions[position_idx].spot
+    pub fn get_spot_position_mut(&mut self, position_idx: PositionIdx) -> &mut SpotPosition {
+        &mut self.positions[position_idx.get()].spot
     }
 
     /// gets the derivative positions
@@ -678,58 +799,366 @@ impl CypherSubAccount {
     }
 
     /// gets the derivative position at the given index
-    pub fn get_derivative_position(&self, position_idx: usize) -> &DerivativePosition {
-        assert!(position_idx < TOKENS_MAX_CNT);
-        &self.positions[position_idx].derivative
+    pub fn get_derivative_position(&self, position_idx: PositionIdx) -> &DerivativePosition {
+        &self.positions[position_idx.get()].derivative
     }
 
     /// gets the derivative position at the given index
-    pub fn get_derivative_position_mut(&mut self, position_idx: usize) -> &mut DerivativePosition {
-        assert!(position_idx < TOKENS_MAX_CNT);
-        &mut self.positions[position_idx].derivative
+    pub fn get_derivative_position_mut(
+        &mut self,
+        position_idx: PositionIdx,
+    ) -> &mut DerivativePosition {
+        &mut self.positions[position_idx.get()].derivative
+    }
+
+    /// gets the spot position at the given index, returning an error if the index is out of bounds
+    pub fn try_get_spot_position(
+        &self,
+        position_idx: usize,
+    ) -> std::result::Result<&SpotPosition, IndexError> {
+        let position_idx = PositionIdx::new(position_idx)?;
+        Ok(self.get_spot_position(position_idx))
+    }
+
+    /// gets the derivative position at the given index, returning an error if the index is out of bounds
+    pub fn try_get_derivative_position(
+        &self,
+        position_idx: usize,
+    ) -> std::result::Result<&DerivativePosition, IndexError> {
+        let position_idx = PositionIdx::new(position_idx)?;
+        Ok(self.get_derivative_position(position_idx))
     }
 
     /// gets the c-ratio for this sub account
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
     pub fn get_margin_c_ratio(
         &self,
         cache_account: &CacheAccount,
         mcr_type: MarginCollateralRatioType,
-    ) -> I80F48 {
-        let (liabs_value, _) = self.get_liabilities_value(cache_account, mcr_type);
+    ) -> std::result::Result<I80F48, MathError> {
+        let (liabs_value, _) = self.get_liabilities_value(cache_account, mcr_type)?;
         if liabs_value == I80F48::ZERO {
-            I80F48::MAX
+            Ok(I80F48::MAX)
         } else {
-            let (assets_value, _) = self.get_assets_value(cache_account, mcr_type);
-            assets_value.saturating_div(liabs_value)
+            let (assets_value, _) = self.get_assets_value(cache_account, mcr_type)?;
+            Ok(assets_value.saturating_div(liabs_value))
         }
     }
 
-    /// gets the assets value, liabilities value and c-ratio respectively
+    /// gets the c-ratio, assets value and liabilities value respectively
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
     pub fn get_margin_c_ratio_components(
         &self,
         cache_account: &CacheAccount,
         mcr_type: MarginCollateralRatioType,
-    ) -> (I80F48, I80F48, I80F48) {
-        let (liabilities_value, _) = self.get_liabilities_value(cache_account, mcr_type);
-        let (assets_value, _) = self.get_assets_value(cache_account, mcr_type);
+    ) -> std::result::Result<(I80F48, I80F48, I80F48), MathError> {
+        let (liabilities_value, _) = self.get_liabilities_value(cache_account, mcr_type)?;
+        let (assets_value, _) = self.get_assets_value(cache_account, mcr_type)?;
 
         if liabilities_value == I80F48::ZERO {
-            (I80F48::MAX, assets_value, I80F48::ZERO)
+            Ok((I80F48::MAX, assets_value, I80F48::ZERO))
         } else {
-            (
+            Ok((
                 assets_value.saturating_div(liabilities_value),
                 assets_value,
                 liabilities_value,
-            )
+            ))
         }
     }
 
-    /// gets the assets value of this sub account
+    /// gets the maximum amount of the given token, in native units, that can be withdrawn from this sub account
+    /// while keeping its initialization c-ratio at or above the clearing's initialization margin requirement
+    ///
+    /// only the deposited amount is considered withdrawable, tokens locked in open orders are excluded
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
+    pub fn get_max_withdrawable(
+        &self,
+        clearing: &Clearing,
+        cache_account: &CacheAccount,
+        token_mint: &Pubkey,
+    ) -> std::result::Result<u64, MathError> {
+        let position = match self
+            .positions
+            .iter()
+            .find(|p| p.spot.token_mint == *token_mint)
+        {
+            Some(p) => p.spot,
+            None => return Ok(0),
+        };
+        let cache = cache_account.try_get_price_cache(position.cache_index as usize)?;
+        let deposits = position.try_total_position(cache)?;
+        if !deposits.is_positive() {
+            return Ok(0);
+        }
+
+        let mcr_type = MarginCollateralRatioType::Initialization;
+        let (liabilities_value, _) = self.get_liabilities_value(cache_account, mcr_type)?;
+        // the value of one native unit of the token, as it contributes to the assets value
+        let unit_value = try_adjust_decimals(I80F48::ONE, cache.decimals)?
+            .checked_mul(cache.oracle_price())
+            .and_then(|n| n.checked_mul(cache.spot_init_asset_weight()))
+            .ok_or(MathError::Overflow("withdrawable unit value"))?;
+        if liabilities_value == I80F48::ZERO || !unit_value.is_positive() {
+            return Ok(deposits.to_num::<u64>());
+        }
+
+        let (assets_value, _) = self.get_assets_value(cache_account, mcr_type)?;
+        let required_assets_value = liabilities_value
+            .checked_mul(clearing.init_margin_ratio())
+            .ok_or(MathError::Overflow("required assets value"))?;
+        let excess_assets_value = assets_value.saturating_sub(required_assets_value);
+        if !excess_assets_value.is_positive() {
+            return Ok(0);
+        }
+        let withdrawable = excess_assets_value
+            .checked_div(unit_value)
+            .ok_or(MathError::Overflow("withdrawable amount"))?;
+
+        Ok(withdrawable.min(deposits).to_num::<u64>())
+    }
+
+    /// gets the largest base quantity, in base lots, of an order on the given perpetual or futures market
+    /// that this sub account can place without its initialization c-ratio falling below the clearing's
+    /// initialization margin requirement, up to `max_base_qty`
+    ///
+    /// the order is modeled the way the program accounts for it when it is placed, i.e. a bid locks its quote
+    /// quantity, borrowing it if the quote deposit is insufficient, and an ask locks its base quantity
+    /// by subtracting it from the position, the limit price is in quote lots per base lot
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
+    pub fn get_max_order_size(
+        &self,
+        clearing: &Clearing,
+        cache_account: &CacheAccount,
+        market: &Pubkey,
+        market_state: &AgnosticMarket,
+        side: Side,
+        limit_price: u64,
+        max_base_qty: u64,
+    ) -> std::result::Result<u64, MathError> {
+        let init_margin_ratio = clearing.init_margin_ratio();
+        let mut low = 0;
+        let mut high = max_base_qty;
+        while low < high {
+            let base_qty = low + (high - low + 1) / 2;
+            let sub_account = match self.with_order(
+                cache_account,
+                market,
+                market_state,
+                side,
+                limit_price,
+                base_qty,
+            )? {
+                Some(sa) => sa,
+                None => return Ok(low),
+            };
+            let c_ratio = sub_account
+                .get_margin_c_ratio(cache_account, MarginCollateralRatioType::Initialization)?;
+            if c_ratio >= init_margin_ratio {
+                low = base_qty;
+            } else {
+                high = base_qty - 1;
+            }
+        }
+        Ok(low)
+    }
+
+    /// gets a copy of this sub account with the given order placed, as the program would account for it
+    ///
+    /// returns `None` if there is no position slot available for the market or the order's quantities overflow
+    fn with_order(
+        &self,
+        cache_account: &CacheAccount,
+        market: &Pubkey,
+        market_state: &AgnosticMarket,
+        side: Side,
+        limit_price: u64,
+        base_qty: u64,
+    ) -> std::result::Result<Option<CypherSubAccount>, MathError> {
+        let base_native_qty = match base_qty.checked_mul(market_state.base_multiplier) {
+            Some(q) => q,
+            None => return Ok(None),
+        };
+        let quote_native_qty = match base_qty
+            .checked_mul(limit_price)
+            .and_then(|n| n.checked_mul(market_state.quote_multiplier))
+        {
+            Some(q) => q,
+            None => return Ok(None),
+        };
+        let mut sub_account = *self;
+        let placed = sub_account.apply_order(
+            cache_account,
+            market,
+            market_state.config.market_type,
+            market_state.config.cache_index,
+            side,
+            base_native_qty,
+            quote_native_qty,
+        )?;
+        if placed {
+            Ok(Some(sub_account))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// applies a new resting order with the given native quantities to this sub account, as the program would account for it
+    ///
+    /// a bid locks its quote quantity, borrowing it if the quote deposit is insufficient,
+    /// and an ask locks its base quantity by subtracting it from the position
+    ///
+    /// returns `false`, leaving the sub account unchanged, if there is no position slot available
+    /// for the market, a bid is applied without an initialized quote position or the locked quantities overflow
+    pub(crate) fn apply_order(
+        &mut self,
+        cache_account: &CacheAccount,
+        market: &Pubkey,
+        market_type: MarketType,
+        cache_index: u16,
+        side: Side,
+        base_native_qty: u64,
+        quote_native_qty: u64,
+    ) -> std::result::Result<bool, MathError> {
+        let mut sub_account = *self;
+        let derivative =
+            match sub_account.get_or_init_derivative_position(market, market_type, cache_index) {
+                Some(d) => d,
+                None => return Ok(false),
+            };
+
+        match side {
+            Side::Bid => {
+                derivative.open_orders_cache.pc_total = match derivative
+                    .open_orders_cache
+                    .pc_total
+                    .checked_add(quote_native_qty)
+                {
+                    Some(n) => n,
+                    None => return Ok(false),
+                };
+                let quote_position = match sub_account.get_quote_position_mut() {
+                    Some(q) => q,
+                    None => return Ok(false),
+                };
+                let quote_cache =
+                    cache_account.try_get_price_cache(quote_position.cache_index as usize)?;
+                quote_position
+                    .add_to_total_position(quote_cache, -I80F48::from(quote_native_qty))?;
+            }
+            Side::Ask => {
+                derivative.open_orders_cache.coin_total = match derivative
+                    .open_orders_cache
+                    .coin_total
+                    .checked_add(base_native_qty)
+                {
+                    Some(n) => n,
+                    None => return Ok(false),
+                };
+                derivative.base_position = derivative
+                    .base_position()
+                    .checked_sub(I80F48::from(base_native_qty))
+                    .ok_or(MathError::Overflow("derivative position"))?
+                    .to_bits();
+            }
+        }
+
+        *self = sub_account;
+        Ok(true)
+    }
+
+    /// gets the spot position of the given token, initializing a free position slot for it if there is none
+    ///
+    /// the quote token always uses the slot at [`QUOTE_TOKEN_IDX`], which is never handed out to other tokens
+    ///
+    /// returns `None` if there is no position slot available
+    pub(crate) fn get_or_init_spot_position(
+        &mut self,
+        token_mint: &Pubkey,
+        cache_index: u16,
+    ) -> Option<&mut SpotPosition> {
+        let slot_idx = if *token_mint == quote_mint::ID {
+            QUOTE_TOKEN_IDX
+        } else {
+            self.positions
+                .iter()
+                .position(|p| p.spot.token_mint == *token_mint)
+                .or_else(|| {
+                    self.positions.iter().enumerate().position(|(idx, p)| {
+                        idx != QUOTE_TOKEN_IDX && p.spot.token_mint == Pubkey::default()
+                    })
+                })?
+        };
+        let spot = &mut self.positions[slot_idx].spot;
+        if spot.token_mint == Pubkey::default() {
+            spot.token_mint = *token_mint;
+            spot.cache_index = cache_index;
+        }
+        Some(spot)
+    }
+
+    /// gets the spot position of the quote token, which is always at [`QUOTE_TOKEN_IDX`]
+    ///
+    /// the position is not initialized here since it's cache index is not known,
+    /// so `None` is returned if the quote token has never been deposited
+    pub(crate) fn get_quote_position_mut(&mut self) -> Option<&mut SpotPosition> {
+        let spot = &mut self.positions[QUOTE_TOKEN_IDX].spot;
+        if spot.token_mint == quote_mint::ID {
+            Some(spot)
+        } else {
+            None
+        }
+    }
+
+    /// gets the derivative position on the given market, initializing a free position slot for it if there is none
+    ///
+    /// returns `None` if there is no position slot available
+    pub(crate) fn get_or_init_derivative_position(
+        &mut self,
+        market: &Pubkey,
+        market_type: MarketType,
+        cache_index: u16,
+    ) -> Option<&mut DerivativePosition> {
+        let slot_idx = self
+            .positions
+            .iter()
+            .position(|p| p.derivative.market == *market)
+            .or_else(|| {
+                self.positions
+                    .iter()
+                    .position(|p| p.derivative.market == Pubkey::default())
+            })?;
+        let derivative = &mut self.positions[slot_idx].derivative;
+        if derivative.market == Pubkey::default() {
+            derivative.market = *market;
+            derivative.market_type = market_type;
+            derivative.cache_index = cache_index;
+        }
+        Some(derivative)
+    }
+
+    /// gets the weighted and unweighted assets value of this sub account respectively
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed,
+    /// e.g. because of a corrupted cache entry.
     pub fn get_assets_value(
         &self,
         cache_account: &CacheAccount,
         mcr_type: MarginCollateralRatioType,
-    ) -> (I80F48, I80F48) {
+    ) -> std::result::Result<(I80F48, I80F48), MathError> {
         let mut assets_value = I80F48::ZERO;
         let mut assets_value_unweighted = I80F48::ZERO;
         let mut cum_pc_total: u64 = 0;
@@ -738,94 +1167,64 @@ impl CypherSubAccount {
             // spot
             if position.spot.token_mint != Pubkey::default() {
                 // get the relevant price cache
-                let cache = cache_account.get_price_cache(position.spot.cache_index as usize);
+                let cache =
+                    cache_account.try_get_price_cache(position.spot.cache_index as usize)?;
                 // convert oracle price to fixed type
                 let spot_oracle_price = cache.oracle_price();
                 // get asset weight according to margin collateral ratio type
-                let spot_asset_weight = match mcr_type {
-                    MarginCollateralRatioType::Initialization => cache.spot_init_asset_weight(),
-                    MarginCollateralRatioType::Maintenance => cache.spot_maint_asset_weight(),
-                };
-                let spot_position = position.spot.total_position(cache);
+                let spot_asset_weight = cache.spot_asset_weight(mcr_type);
+                let spot_position = position.spot.try_total_position(cache)?;
                 if spot_position.is_positive() {
                     let spot_position_size = spot_position
                         .checked_add(I80F48::from(position.spot.open_orders_cache.coin_total))
-                        .unwrap();
-                    let spot_value_unweighted = adjust_decimals(spot_position_size, cache.decimals)
-                        .checked_mul(spot_oracle_price)
-                        .unwrap();
-                    assets_value_unweighted += spot_value_unweighted;
-                    assets_value += spot_value_unweighted
+                        .ok_or(MathError::Overflow("spot position size"))?;
+                    let spot_value_unweighted =
+                        try_adjust_decimals(spot_position_size, cache.decimals)?
+                            .checked_mul(spot_oracle_price)
+                            .ok_or(MathError::Overflow("spot asset value"))?;
+                    assets_value_unweighted = assets_value_unweighted
+                        .checked_add(spot_value_unweighted)
+                        .ok_or(MathError::Overflow("assets value"))?;
+                    assets_value = spot_value_unweighted
                         .checked_mul(spot_asset_weight)
-                        .unwrap();
+                        .and_then(|n| assets_value.checked_add(n))
+                        .ok_or(MathError::Overflow("weighted spot asset value"))?;
                 }
-                cum_pc_total += position.spot.open_orders_cache.pc_total;
+                cum_pc_total = cum_pc_total
+                    .checked_add(position.spot.open_orders_cache.pc_total)
+                    .ok_or(MathError::Overflow("quote total"))?;
             }
 
             // derivatives
             if position.derivative.market != Pubkey::default() {
                 // get the relevant price cache
-                let cache = cache_account.get_price_cache(position.derivative.cache_index as usize);
-                let decimals = if position.derivative.market_type == MarketType::PerpetualFuture {
-                    cache.perp_decimals
-                } else {
-                    cache.futures_decimals
-                };
+                let cache =
+                    cache_account.try_get_price_cache(position.derivative.cache_index as usize)?;
+                let decimals = cache.decimals_for(position.derivative.market_type);
                 // convert the orresponding price to fixed type
-                let derivative_price =
-                    if position.derivative.market_type == MarketType::PerpetualFuture {
-                        cache.oracle_price()
-                    } else {
-                        let market_price = cache.market_price();
-                        if market_price == I80F48::ZERO {
-                            cache.oracle_price()
-                        } else {
-                            market_price
-                        }
-                    };
+                let derivative_price = cache.price_for(position.derivative.market_type);
                 // get asset weight according to margin collateral ratio type
-                let derivative_asset_weight = match (mcr_type, position.derivative.market_type) {
-                    (MarginCollateralRatioType::Initialization, MarketType::PairFuture) => {
-                        cache.futures_init_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Initialization, MarketType::PerpetualFuture) => {
-                        cache.perp_init_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Initialization, MarketType::PreIDO) => {
-                        cache.futures_init_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Initialization, MarketType::IndexFuture) => {
-                        cache.futures_init_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::PairFuture) => {
-                        cache.futures_maint_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::PerpetualFuture) => {
-                        cache.perp_maint_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::PreIDO) => {
-                        cache.futures_maint_asset_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::IndexFuture) => {
-                        cache.futures_maint_asset_weight()
-                    }
-                    _ => unreachable!(),
-                };
+                let derivative_asset_weight = cache
+                    .derivative_asset_weight(position.derivative.market_type, mcr_type)
+                    .ok_or(MathError::UnsupportedMarketType(position.derivative.market))?;
                 let derivative_position = position.derivative.base_position();
                 if derivative_position.is_positive() {
                     let derivative_position_size = derivative_position
                         .checked_add(I80F48::from(
                             position.derivative.open_orders_cache.coin_total,
                         ))
-                        .unwrap();
+                        .ok_or(MathError::Overflow("derivative position size"))?;
                     let derivative_value_unweighted =
-                        adjust_decimals(derivative_position_size, decimals)
+                        try_adjust_decimals(derivative_position_size, decimals)?
                             .checked_mul(derivative_price)
-                            .unwrap();
-                    assets_value_unweighted += derivative_value_unweighted;
-                    assets_value += derivative_value_unweighted
+                            .ok_or(MathError::Overflow("derivative asset value"))?;
+                    assets_value_unweighted = assets_value_unweighted
+                        .checked_add(derivative_value_unweighted)
+                        .ok_or(MathError::Overflow("assets value"))?;
+                    assets_value = derivative_value_unweighted
                         .checked_mul(derivative_asset_weight)
-                        .unwrap();
+                        .and_then(|n| assets_value.checked_add(n))
+                        .ok_or(MathError::Overflow("weighted derivative asset value"))?;
                 }
                 // we are going to take derivative coins locked and will price them at the oracle price
                 // regardless of whatever price the limit ask orders are actually placed at
@@ -834,40 +1233,53 @@ impl CypherSubAccount {
                 let derivative_coin_locked = position.derivative.open_orders_cache.coin_locked();
                 if derivative_coin_locked != 0 {
                     let coin_locked_value_unweighted =
-                        adjust_decimals(I80F48::from(derivative_coin_locked), decimals)
+                        try_adjust_decimals(I80F48::from(derivative_coin_locked), decimals)?
                             .checked_mul(derivative_price)
-                            .unwrap();
-                    assets_value_unweighted += coin_locked_value_unweighted;
-                    assets_value += coin_locked_value_unweighted
+                            .ok_or(MathError::Overflow("derivative coin locked value"))?;
+                    assets_value_unweighted = assets_value_unweighted
+                        .checked_add(coin_locked_value_unweighted)
+                        .ok_or(MathError::Overflow("assets value"))?;
+                    assets_value = coin_locked_value_unweighted
                         .checked_mul(derivative_asset_weight)
-                        .unwrap();
+                        .and_then(|n| assets_value.checked_add(n))
+                        .ok_or(MathError::Overflow("weighted derivative coin locked value"))?;
                 }
-                cum_pc_total += position.derivative.open_orders_cache.pc_total;
+                cum_pc_total = cum_pc_total
+                    .checked_add(position.derivative.open_orders_cache.pc_total)
+                    .ok_or(MathError::Overflow("quote total"))?;
             }
         }
 
         let quote_position = self.positions[QUOTE_TOKEN_IDX].spot;
-        let quote_cache = cache_account.get_price_cache(quote_position.cache_index as usize);
-        let cum_pc_total_value = adjust_decimals(I80F48::from(cum_pc_total), quote_cache.decimals)
-            .checked_mul(I80F48::from_bits(quote_cache.oracle_price))
-            .unwrap();
-
-        let quote_asset_weight = match mcr_type {
-            MarginCollateralRatioType::Initialization => quote_cache.spot_init_asset_weight(),
-            MarginCollateralRatioType::Maintenance => quote_cache.spot_maint_asset_weight(),
-        };
-        assets_value_unweighted += cum_pc_total_value;
-        assets_value += cum_pc_total_value.checked_mul(quote_asset_weight).unwrap();
+        let quote_cache = cache_account.try_get_price_cache(quote_position.cache_index as usize)?;
+        let cum_pc_total_value =
+            try_adjust_decimals(I80F48::from(cum_pc_total), quote_cache.decimals)?
+                .checked_mul(I80F48::from_bits(quote_cache.oracle_price))
+                .ok_or(MathError::Overflow("quote total value"))?;
 
-        (assets_value, assets_value_unweighted)
+        let quote_asset_weight = quote_cache.spot_asset_weight(mcr_type);
+        assets_value_unweighted = assets_value_unweighted
+            .checked_add(cum_pc_total_value)
+            .ok_or(MathError::Overflow("assets value"))?;
+        assets_value = cum_pc_total_value
+            .checked_mul(quote_asset_weight)
+            .and_then(|n| assets_value.checked_add(n))
+            .ok_or(MathError::Overflow("weighted quote total value"))?;
+
+        Ok((assets_value, assets_value_unweighted))
     }
 
-    /// gets the liabilities value of this sub account
+    /// gets the weighted and unweighted liabilities value of this sub account respectively
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed,
+    /// e.g. because of a corrupted cache entry.
     pub fn get_liabilities_value(
         &self,
         cache_account: &CacheAccount,
         mcr_type: MarginCollateralRatioType,
-    ) -> (I80F48, I80F48) {
+    ) -> std::result::Result<(I80F48, I80F48), MathError> {
         let mut liabilities_value = I80F48::ZERO;
         let mut liabilities_value_unweighted = I80F48::ZERO;
 
@@ -875,113 +1287,89 @@ impl CypherSubAccount {
             // spot
             if position.spot.token_mint != Pubkey::default() {
                 // get the relevant price cache
-                let cache = cache_account.get_price_cache(position.spot.cache_index as usize);
+                let cache =
+                    cache_account.try_get_price_cache(position.spot.cache_index as usize)?;
                 // convert oracle price to fixed type
                 let spot_oracle_price = cache.oracle_price();
                 // get liability weight according to margin collateral ratio type
-                let spot_liability_weight = match mcr_type {
-                    MarginCollateralRatioType::Initialization => cache.spot_init_liab_weight(),
-                    MarginCollateralRatioType::Maintenance => cache.spot_maint_liab_weight(),
-                };
+                let spot_liability_weight = cache.spot_liab_weight(mcr_type);
                 // get total spot position value according to index
-                let spot_position = position.spot.total_position(cache);
+                let spot_position = position.spot.try_total_position(cache)?;
                 if spot_position.is_negative() {
-                    let spot_value_unweighted = adjust_decimals(spot_position, cache.decimals)
+                    let spot_value_unweighted = try_adjust_decimals(spot_position, cache.decimals)?
                         .abs()
                         .checked_mul(spot_oracle_price)
-                        .unwrap();
-                    liabilities_value_unweighted += spot_value_unweighted;
-                    liabilities_value += spot_value_unweighted
+                        .ok_or(MathError::Overflow("spot liability value"))?;
+                    liabilities_value_unweighted = liabilities_value_unweighted
+                        .checked_add(spot_value_unweighted)
+                        .ok_or(MathError::Overflow("liabilities value"))?;
+                    liabilities_value = spot_value_unweighted
                         .checked_mul(spot_liability_weight)
-                        .unwrap();
+                        .and_then(|n| liabilities_value.checked_add(n))
+                        .ok_or(MathError::Overflow("weighted spot liability value"))?;
                 }
             }
             // derivatives
             if position.derivative.market != Pubkey::default() {
                 // get the relevant price cache
-                let cache = cache_account.get_price_cache(position.derivative.cache_index as usize);
-                let decimals = if position.derivative.market_type == MarketType::PerpetualFuture {
-                    cache.perp_decimals
-                } else {
-                    cache.futures_decimals
-                };
+                let cache =
+                    cache_account.try_get_price_cache(position.derivative.cache_index as usize)?;
+                let decimals = cache.decimals_for(position.derivative.market_type);
                 // convert the orresponding price to fixed type
-                let derivative_price =
-                    if position.derivative.market_type == MarketType::PerpetualFuture {
-                        cache.oracle_price()
-                    } else {
-                        let market_price = cache.market_price();
-                        if market_price == I80F48::ZERO {
-                            cache.oracle_price()
-                        } else {
-                            market_price
-                        }
-                    };
+                let derivative_price = cache.price_for(position.derivative.market_type);
 
                 // get liability weight according to margin collateral ratio type
-                let derivative_liability_weight = match (mcr_type, position.derivative.market_type)
-                {
-                    (MarginCollateralRatioType::Initialization, MarketType::PairFuture) => {
-                        cache.futures_init_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Initialization, MarketType::PerpetualFuture) => {
-                        cache.perp_init_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Initialization, MarketType::PreIDO) => {
-                        cache.futures_init_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Initialization, MarketType::IndexFuture) => {
-                        cache.futures_init_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::PairFuture) => {
-                        cache.futures_maint_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::PerpetualFuture) => {
-                        cache.perp_maint_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::PreIDO) => {
-                        cache.futures_maint_liab_weight()
-                    }
-                    (MarginCollateralRatioType::Maintenance, MarketType::IndexFuture) => {
-                        cache.futures_maint_liab_weight()
-                    }
-                    _ => unreachable!(),
-                };
+                let derivative_liability_weight = cache
+                    .derivative_liab_weight(position.derivative.market_type, mcr_type)
+                    .ok_or(MathError::UnsupportedMarketType(position.derivative.market))?;
                 let derivative_position = position.derivative.base_position();
                 if derivative_position.is_negative() {
                     let derivative_value_unweighted =
-                        adjust_decimals(derivative_position, decimals)
+                        try_adjust_decimals(derivative_position, decimals)?
                             .abs()
                             .checked_mul(derivative_price)
                             .and_then(|n| n.checked_mul(derivative_liability_weight))
-                            .unwrap();
-                    liabilities_value_unweighted += derivative_value_unweighted;
-                    liabilities_value += derivative_value_unweighted
+                            .ok_or(MathError::Overflow("derivative liability value"))?;
+                    liabilities_value_unweighted = liabilities_value_unweighted
+                        .checked_add(derivative_value_unweighted)
+                        .ok_or(MathError::Overflow("liabilities value"))?;
+                    liabilities_value = derivative_value_unweighted
                         .checked_mul(derivative_liability_weight)
-                        .unwrap();
+                        .and_then(|n| liabilities_value.checked_add(n))
+                        .ok_or(MathError::Overflow("weighted derivative liability value"))?;
                 }
             }
         }
 
-        (liabilities_value, liabilities_value_unweighted)
+        Ok((liabilities_value, liabilities_value_unweighted))
     }
 
-    pub fn is_bankrupt(&self, clearing: &Clearing, cache_account: &CacheAccount) -> Result<bool> {
+    /// whether this sub account is bankrupt, i.e. its largest deposit can not cover
+    /// the liquidation fees of the smallest unit of its cheapest borrow
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
+    pub fn is_bankrupt(
+        &self,
+        clearing: &Clearing,
+        cache_account: &CacheAccount,
+    ) -> std::result::Result<bool, MathError> {
         let quote_position = self.positions[QUOTE_TOKEN_IDX].spot;
-        let quote_cache = cache_account.get_price_cache(quote_position.cache_index as usize);
-        let quote_position_size = quote_position.total_position(quote_cache);
+        let quote_cache = cache_account.try_get_price_cache(quote_position.cache_index as usize)?;
+        let quote_position_size = quote_position.try_total_position(quote_cache)?;
         // if the quote token has a deposit we'll use it as the starter for the largest deposit value
         let mut largest_deposit_value = if quote_position_size.is_positive() {
-            adjust_decimals(
+            try_adjust_decimals(
                 quote_position_size
                     .checked_mul(quote_cache.oracle_price())
-                    .unwrap(),
+                    .ok_or(MathError::Overflow("quote deposit value"))?,
                 quote_cache.decimals,
-            )
+            )?
         } else {
             I80F48::ZERO
         };
-        // if the quote token has a borrow we'll use it's value as the starter for the lowest borrow price
+        // if the quote token has a borrow we'll use its value as the starter for the lowest borrow price
         let mut lowest_borrow_price = if quote_position_size.is_negative() {
             quote_cache.oracle_price()
         } else {
@@ -991,15 +1379,18 @@ impl CypherSubAccount {
         for position in self.iter_position_slots() {
             // spot
             if position.spot.token_mint != Pubkey::default() {
-                let cache = cache_account.get_price_cache(position.spot.cache_index as usize);
+                let cache =
+                    cache_account.try_get_price_cache(position.spot.cache_index as usize)?;
                 let spot_oracle_price = cache.oracle_price();
-                let spot_position = position.spot.total_position(cache);
+                let spot_position = position.spot.try_total_position(cache)?;
                 // calculate spot deposit value, if the spot position actually represents a deposit
                 let spot_deposit_value = if spot_position.is_positive() {
-                    adjust_decimals(
-                        spot_position.checked_mul(spot_oracle_price).unwrap(),
+                    try_adjust_decimals(
+                        spot_position
+                            .checked_mul(spot_oracle_price)
+                            .ok_or(MathError::Overflow("spot deposit value"))?,
                         cache.decimals,
-                    )
+                    )?
                 } else {
                     I80F48::ZERO
                 };
@@ -1013,13 +1404,11 @@ impl CypherSubAccount {
 
             // derivatives
             if position.derivative.market != Pubkey::default() {
-                let cache = cache_account.get_price_cache(position.derivative.cache_index as usize);
-                let decimals = if position.derivative.market_type == MarketType::PerpetualFuture {
-                    cache.perp_decimals
-                } else {
-                    cache.futures_decimals
-                };
+                let cache =
+                    cache_account.try_get_price_cache(position.derivative.cache_index as usize)?;
+                let decimals = cache.decimals_for(position.derivative.market_type);
                 // convert the orresponding price to fixed type
+                // bankruptcy uses the raw market price without falling back to the oracle price
                 let derivative_price =
                     if position.derivative.market_type == MarketType::PerpetualFuture {
                         cache.oracle_price()
@@ -1029,10 +1418,12 @@ impl CypherSubAccount {
                 let derivative_position = position.derivative.base_position();
                 // calculate derivative deposit value, if the derivative position actually represents a deposit
                 let derivative_deposit_value = if derivative_position.is_positive() {
-                    adjust_decimals(
-                        derivative_position.checked_mul(derivative_price).unwrap(),
+                    try_adjust_decimals(
+                        derivative_position
+                            .checked_mul(derivative_price)
+                            .ok_or(MathError::Overflow("derivative deposit value"))?,
                         decimals,
-                    )
+                    )?
                 } else {
                     I80F48::ZERO
                 };
@@ -1050,15 +1441,154 @@ impl CypherSubAccount {
             return Ok(false);
         }
 
-        let liq_fee = clearing.liq_liqor_fee() + clearing.liq_insurance_fee();
-        let collateral_for_min_borrow_unit = liq_fee.checked_mul(lowest_borrow_price).unwrap();
+        let liq_fee = clearing
+            .liq_liqor_fee()
+            .checked_add(clearing.liq_insurance_fee())
+            .ok_or(MathError::Overflow("liquidation fee"))?;
+        let collateral_for_min_borrow_unit = liq_fee
+            .checked_mul(lowest_borrow_price)
+            .ok_or(MathError::Overflow("collateral for minimum borrow unit"))?;
+
+        Ok(collateral_for_min_borrow_unit > largest_deposit_value)
+    }
+
+    /// estimates the price of the given price cache at which this sub account's maintenance c-ratio crosses
+    /// the clearing's maintenance margin requirement, holding every other price constant
+    ///
+    /// the varied price is the one positions on the cache are valued at, i.e. the oracle price for spot
+    /// and perpetual positions and the price returned by [`Cache::price_for`] for other derivative positions,
+    /// in which case `market_type` should be given
+    ///
+    /// returns `None` if the c-ratio does not cross the requirement at any positive price
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed
+    /// or the varied price is too close to the edge of the fixed point range.
+    pub fn get_liquidation_price(
+        &self,
+        clearing: &Clearing,
+        cache_account: &CacheAccount,
+        cache_index: usize,
+        market_type: Option<MarketType>,
+    ) -> std::result::Result<Option<I80F48>, MathError> {
+        let cache = cache_account.try_get_price_cache(cache_index)?;
+        let current_price = match market_type {
+            Some(market_type) => cache.price_for(market_type),
+            None => cache.oracle_price(),
+        };
+        let use_market_price = match market_type {
+            Some(market_type) => {
+                market_type != MarketType::PerpetualFuture && cache.market_price != 0
+            }
+            None => false,
+        };
+        let mcr_type = MarginCollateralRatioType::Maintenance;
+
+        // both values are linear in the varied price while position sizes are held constant,
+        // so evaluating them at two prices is enough to solve for the crossing price
+        let price_a = current_price;
+        let price_b = if current_price.is_positive() {
+            current_price
+                .checked_mul(I80F48::from(2))
+                .ok_or(MathError::Overflow("liquidation price"))?
+        } else {
+            I80F48::ONE
+        };
+        let mut values = [(I80F48::ZERO, I80F48::ZERO); 2];
+        for (idx, price) in [price_a, price_b].into_iter().enumerate() {
+            let mut repriced = Box::new(*cache_account);
+            let cache = &mut repriced.caches[cache_index];
+            if use_market_price {
+                cache.market_price = price.to_bits();
+            } else {
+                cache.oracle_price = price.to_bits();
+            }
+            let (assets_value, _) = self.get_assets_value(&repriced, mcr_type)?;
+            let (liabilities_value, _) = self.get_liabilities_value(&repriced, mcr_type)?;
+            values[idx] = (assets_value, liabilities_value);
+        }
 
-        if collateral_for_min_borrow_unit > largest_deposit_value {
-            Ok(true)
+        let price_delta = price_b
+            .checked_sub(price_a)
+            .ok_or(MathError::Overflow("liquidation price delta"))?;
+        let assets_slope = values[1]
+            .0
+            .checked_sub(values[0].0)
+            .and_then(|n| n.checked_div(price_delta))
+            .ok_or(MathError::Overflow("assets value slope"))?;
+        let liabilities_slope = values[1]
+            .1
+            .checked_sub(values[0].1)
+            .and_then(|n| n.checked_div(price_delta))
+            .ok_or(MathError::Overflow("liabilities value slope"))?;
+        let assets_intercept = assets_slope
+            .checked_mul(price_a)
+            .and_then(|n| values[0].0.checked_sub(n))
+            .ok_or(MathError::Overflow("assets value intercept"))?;
+        let liabilities_intercept = liabilities_slope
+            .checked_mul(price_a)
+            .and_then(|n| values[0].1.checked_sub(n))
+            .ok_or(MathError::Overflow("liabilities value intercept"))?;
+
+        // solve `assets_intercept + assets_slope * p = mmr * (liabilities_intercept + liabilities_slope * p)`
+        let maint_margin_ratio = clearing.maint_margin_ratio();
+        let denominator = liabilities_slope
+            .checked_mul(maint_margin_ratio)
+            .and_then(|n| assets_slope.checked_sub(n))
+            .ok_or(MathError::Overflow("liquidation price"))?;
+        if denominator == I80F48::ZERO {
+            return Ok(None);
+        }
+        let numerator = liabilities_intercept
+            .checked_mul(maint_margin_ratio)
+            .and_then(|n| n.checked_sub(assets_intercept))
+            .ok_or(MathError::Overflow("liquidation price"))?;
+        let liquidation_price = numerator
+            .checked_div(denominator)
+            .ok_or(MathError::Overflow("liquidation price"))?;
+
+        if liquidation_price.is_positive() {
+            Ok(Some(liquidation_price))
         } else {
-            Ok(false)
+            Ok(None)
         }
     }
+
+    /// estimates the oracle price of the given spot position's token at which this sub account
+    /// becomes liquidatable, see [`CypherSubAccount::get_liquidation_price`]
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
+    pub fn get_spot_liquidation_price(
+        &self,
+        clearing: &Clearing,
+        cache_account: &CacheAccount,
+        position: &SpotPosition,
+    ) -> std::result::Result<Option<I80F48>, MathError> {
+        self.get_liquidation_price(clearing, cache_account, position.cache_index as usize, None)
+    }
+
+    /// estimates the price of the given derivative position's market at which this sub account
+    /// becomes liquidatable, see [`CypherSubAccount::get_liquidation_price`]
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
+    pub fn get_derivative_liquidation_price(
+        &self,
+        clearing: &Clearing,
+        cache_account: &CacheAccount,
+        position: &DerivativePosition,
+    ) -> std::result::Result<Option<I80F48>, MathError> {
+        self.get_liquidation_price(
+            clearing,
+            cache_account,
+            position.cache_index as usize,
+            Some(position.market_type),
+        )
+    }
 }
 
 impl OrdersAccount {
@@ -1070,6 +1600,29 @@ impl OrdersAccount {
     }
 }
 
+impl CancelOrderArgs {
+    /// creates the args to cancel the given open order by it's order id
+    ///
+    /// on AOB markets the order id encodes the side of the order, so the side must be the open order's side
+    /// for the order to be found on the book
+    pub fn from_open_order(order: &OpenOrder) -> Self {
+        Self {
+            order_id: order.order_id,
+            side: order.side,
+            is_client_id: false,
+        }
+    }
+
+    /// creates the args to cancel the order with the given client order id on the given side
+    pub fn from_client_order_id(client_order_id: u64, side: Side) -> Self {
+        Self {
+            order_id: client_order_id as u128,
+            side,
+            is_client_id: true,
+        }
+    }
+}
+
 impl SubAccountCache {
     /// the value of the assets of this sub account
     pub fn assets_value(&self) -> I80F48 {
@@ -1086,6 +1639,11 @@ impl SubAccountCache {
 }
 
 impl SpotPosition {
+    /// the index of the price cache of this position
+    pub fn cache_index(&self) -> std::result::Result<CacheIndex, IndexError> {
+        CacheIndex::new(self.cache_index as usize)
+    }
+
     /// the position, denominated in the base token
     pub fn position(&self) -> I80F48 {
         I80F48::from_bits(self.position)
@@ -1099,9 +1657,67 @@ impl SpotPosition {
             position * cache.borrow_index()
         }
     }
+
+    /// the total position according to the deposit index if it is positive or the borrow index otherwise,
+    /// see [`SpotPosition::total_position`]
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the total position overflows.
+    pub fn try_total_position(&self, cache: &Cache) -> std::result::Result<I80F48, MathError> {
+        let position = self.position();
+        let index = if position.is_positive() {
+            cache.deposit_index()
+        } else {
+            cache.borrow_index()
+        };
+        position
+            .checked_mul(index)
+            .ok_or(MathError::Overflow("spot total position"))
+    }
+
+    /// the interest accrued by this position, i.e. the difference between the total position and the principal
+    ///
+    /// the principal is the raw position, which is denominated in units of the deposit or borrow index,
+    /// this is positive for deposits, which earn interest, and negative for borrows, which owe interest
+    pub fn accrued_interest(&self, cache: &Cache) -> I80F48 {
+        self.total_position(cache) - self.position()
+    }
+
+    /// adds the given native amount to the total position, which is converted back to the
+    /// position according to the deposit index if it is positive or the borrow index otherwise
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the new position overflows.
+    pub fn add_to_total_position(
+        &mut self,
+        cache: &Cache,
+        amount: I80F48,
+    ) -> std::result::Result<(), MathError> {
+        let total_position = self
+            .try_total_position(cache)?
+            .checked_add(amount)
+            .ok_or(MathError::Overflow("spot position"))?;
+        let index = if total_position.is_positive() {
+            cache.deposit_index()
+        } else {
+            cache.borrow_index()
+        };
+        self.position = total_position
+            .checked_div(index)
+            .ok_or(MathError::Overflow("spot position"))?
+            .to_bits();
+        Ok(())
+    }
 }
 
 impl DerivativePosition {
+    /// the index of the price cache of this position
+    pub fn cache_index(&self) -> std::result::Result<CacheIndex, IndexError> {
+        CacheIndex::new(self.cache_index as usize)
+    }
+
     /// the deposits of this position
     pub fn base_position(&self) -> I80F48 {
         I80F48::from_bits(self.base_position)
@@ -1121,6 +1737,60 @@ impl DerivativePosition {
         base_position += I80F48::from(self.open_orders_cache.coin_total);
         base_position
     }
+
+    /// the funding accrued by this position on the given perpetual market since it was last settled,
+    /// in native quote units, positive if it is owed by the position and negative if it is receivable
+    ///
+    /// this is the difference between the market's funding and the funding settled by the position
+    /// on the side of the position, multiplied by the position
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the funding delta overflows, e.g. because of a corrupted market.
+    pub fn get_pending_funding(
+        &self,
+        market: &PerpetualMarket,
+    ) -> std::result::Result<I80F48, MathError> {
+        let position = self.total_position();
+        let funding_delta = if position.is_positive() {
+            market
+                .long_funding()
+                .checked_sub(I80F48::from_bits(self.long_funding_settled))
+        } else {
+            market
+                .short_funding()
+                .checked_sub(I80F48::from_bits(self.short_funding_settled))
+        };
+        match funding_delta {
+            Some(d) => Ok(d.saturating_mul(position)),
+            None => Err(MathError::Overflow("funding delta")),
+        }
+    }
+
+    /// the value of the funding accrued by this position on the given perpetual market since it was last settled,
+    /// see [`DerivativePosition::get_pending_funding`], valued at the oracle price of the given quote token cache
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value can not be computed.
+    pub fn get_pending_funding_value(
+        &self,
+        market: &PerpetualMarket,
+        quote_cache: &Cache,
+    ) -> std::result::Result<I80F48, MathError> {
+        try_adjust_decimals(self.get_pending_funding(market)?, quote_cache.decimals)?
+            .checked_mul(quote_cache.oracle_price())
+            .ok_or(MathError::Overflow("pending funding value"))
+    }
+
+    /// the funding this position is projected to accrue over the next hour at the given hourly funding rate,
+    /// see [`PerpetualMarket::get_projected_funding_rate`], and price in native quote units per native base unit,
+    /// in native quote units, positive if it is owed by the position and negative if it is receivable
+    pub fn get_projected_hourly_funding(&self, funding_rate: I80F48, price: I80F48) -> I80F48 {
+        funding_rate
+            .saturating_mul(price)
+            .saturating_mul(self.total_position())
+    }
 }
 
 impl OpenOrdersCache {
@@ -1229,6 +1899,56 @@ impl Pool {
 }
 
 impl PoolNode {
+    /// the deposits of this pool node, denominated in units of the pool's deposit index
+    pub fn deposits(&self) -> I80F48 {
+        I80F48::from_bits(self.deposits)
+    }
+
+    /// the borrows of this pool node, denominated in units of the pool's borrow index
+    pub fn borrows(&self) -> I80F48 {
+        I80F48::from_bits(self.borrows)
+    }
+
+    /// the deposits of this pool node, in native units
+    pub fn total_deposits(&self, pool: &Pool) -> I80F48 {
+        self.deposits().saturating_mul(pool.deposit_index())
+    }
+
+    /// the borrows of this pool node, in native units
+    pub fn total_borrows(&self, pool: &Pool) -> I80F48 {
+        self.borrows().saturating_mul(pool.borrow_index())
+    }
+
+    /// the pool node's utilization rate
+    pub fn utilization_rate(&self, pool: &Pool) -> I80F48 {
+        let borrows = self.total_borrows(pool);
+        if borrows == I80F48::ZERO {
+            I80F48::ZERO
+        } else {
+            borrows.saturating_div(self.total_deposits(pool))
+        }
+    }
+
+    /// the share of the pool's deposits held by this pool node
+    pub fn deposits_share(&self, pool: &Pool) -> I80F48 {
+        let pool_deposits = pool.deposits();
+        if pool_deposits == I80F48::ZERO {
+            I80F48::ZERO
+        } else {
+            self.deposits().saturating_div(pool_deposits)
+        }
+    }
+
+    /// the share of the pool's borrows held by this pool node
+    pub fn borrows_share(&self, pool: &Pool) -> I80F48 {
+        let pool_borrows = pool.borrows();
+        if pool_borrows == I80F48::ZERO {
+            I80F48::ZERO
+        } else {
+            self.borrows().saturating_div(pool_borrows)
+        }
+    }
+
     /// accumulated borrows
     pub fn accum_borrows(&self) -> I80F48 {
         I80F48::from_bits(self.accum_borrows)
@@ -1236,7 +1956,7 @@ impl PoolNode {
 
     /// accumulated repays
     pub fn accum_repays(&self) -> I80F48 {
-        I80F48::from_bits(self.accum_borrows)
+        I80F48::from_bits(self.accum_repays)
     }
 }
 
@@ -1261,6 +1981,44 @@ impl PerpetualMarket {
     pub fn short_funding(&self) -> I80F48 {
         I80F48::from_bits(self.short_funding)
     }
+
+    /// the projected hourly funding rate, as a fraction of the index price, if the given impact prices
+    /// were to hold until the next funding update
+    ///
+    /// the premium of the mid point between the impact bid and impact ask prices over the index price
+    /// is clamped to the market's minimum and maximum funding, which are in percent, all prices must be
+    /// in the same units, e.g. quote lots per base lot
+    ///
+    /// returns `None` if the index price is not positive
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the premium overflows, e.g. because of extreme impact prices.
+    pub fn get_projected_funding_rate(
+        &self,
+        impact_bid_price: I80F48,
+        impact_ask_price: I80F48,
+        index_price: I80F48,
+    ) -> std::result::Result<Option<I80F48>, MathError> {
+        if !index_price.is_positive() {
+            return Ok(None);
+        }
+        let impact_mid_price = impact_bid_price
+            .checked_add(impact_ask_price)
+            .and_then(|n| n.checked_div(I80F48::from(2)))
+            .ok_or(MathError::Overflow("impact mid price"))?;
+        let premium = impact_mid_price
+            .checked_sub(index_price)
+            .and_then(|n| n.checked_div(index_price))
+            .ok_or(MathError::Overflow("funding premium"))?;
+        let min_funding = I80F48::from(self.min_funding)
+            .checked_mul(INV_ONE_HUNDRED_FIXED)
+            .ok_or(MathError::Overflow("min funding"))?;
+        let max_funding = I80F48::from(self.max_funding)
+            .checked_mul(INV_ONE_HUNDRED_FIXED)
+            .ok_or(MathError::Overflow("max funding"))?;
+        Ok(Some(premium.max(min_funding).min(max_funding)))
+    }
 }
 
 pub trait Market: Send + Sync {
@@ -1336,3 +2094,37 @@ impl Market for FuturesMarket {
         self.inner.config.decimals
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    fn get_liquidation_price_at(
+        oracle_price: I80F48,
+    ) -> std::result::Result<Option<I80F48>, MathError> {
+        let clearing = Clearing::zeroed();
+        let mut cache_account = Box::new(CacheAccount::zeroed());
+        cache_account.caches[1].oracle_price = oracle_price.to_bits();
+        CypherSubAccount::zeroed().get_liquidation_price(&clearing, &cache_account, 1, None)
+    }
+
+    #[test]
+    fn liquidation_price_of_an_empty_sub_account_is_none() {
+        assert_eq!(get_liquidation_price_at(I80F48::from_num(20)), Ok(None));
+    }
+
+    #[test]
+    fn liquidation_price_at_the_edge_of_the_range_is_an_error() {
+        // doubling the largest price overflows
+        assert_eq!(
+            get_liquidation_price_at(I80F48::MAX),
+            Err(MathError::Overflow("liquidation price"))
+        );
+        // a non-positive price is varied up to one, which is out of range from the smallest price
+        assert_eq!(
+            get_liquidation_price_at(I80F48::MIN),
+            Err(MathError::Overflow("liquidation price delta"))
+        );
+    }
+}
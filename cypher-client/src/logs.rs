@@ -0,0 +1,254 @@
+use {
+    crate::{
+        AccountActionLog, ClearingActionLog, DepositOrWithdrawLog, FundingPaymentLog,
+        LiquidatePositionLog, MarketActionLog, OrderFillLog, OrdersAccountActionLog, PoolActionLog,
+        SettlePositionLog, SubAccountActionLog, TransferBetweenSubAccountsLog,
+        WhitelistCreationLog,
+    },
+    anchor_lang::{prelude::*, Discriminator},
+    std::fmt,
+};
+
+/// The prefix of the log messages which hold base64 encoded event data.
+pub const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// The prefix of the log messages which hold the name of the executing Anchor instruction.
+pub const INSTRUCTION_LOG_PREFIX: &str = "Program log: Instruction: ";
+
+/// The prefix of the log messages emitted when a program is invoked or returns.
+const PROGRAM_LOG_PREFIX: &str = "Program ";
+
+/// A typed event emitted by the cypher program.
+pub enum CypherEvent {
+    OrderFill(OrderFillLog),
+    LiquidatePosition(LiquidatePositionLog),
+    DepositOrWithdraw(DepositOrWithdrawLog),
+    FundingPayment(FundingPaymentLog),
+    SettlePosition(SettlePositionLog),
+    TransferBetweenSubAccounts(TransferBetweenSubAccountsLog),
+    AccountAction(AccountActionLog),
+    SubAccountAction(SubAccountActionLog),
+    OrdersAccountAction(OrdersAccountActionLog),
+    ClearingAction(ClearingActionLog),
+    MarketAction(MarketActionLog),
+    PoolAction(PoolActionLog),
+    WhitelistCreation(WhitelistCreationLog),
+}
+
+/// Deserializes the event if the data starts with the event's discriminator.
+macro_rules! try_decode_event {
+    ($data:expr, $log:ty, $variant:ident) => {
+        if $data[..8] == <$log>::DISCRIMINATOR {
+            return match <$log>::deserialize(&mut &$data[8..]) {
+                Ok(e) => Some(CypherEvent::$variant(e)),
+                Err(_) => None,
+            };
+        }
+    };
+}
+
+impl CypherEvent {
+    /// Decodes the event from the given event data, including the discriminator.
+    ///
+    /// Returns `None` if the data does not hold a known event or can not be deserialized.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        try_decode_event!(data, OrderFillLog, OrderFill);
+        try_decode_event!(data, LiquidatePositionLog, LiquidatePosition);
+        try_decode_event!(data, DepositOrWithdrawLog, DepositOrWithdraw);
+        try_decode_event!(data, FundingPaymentLog, FundingPayment);
+        try_decode_event!(data, SettlePositionLog, SettlePosition);
+        try_decode_event!(
+            data,
+            TransferBetweenSubAccountsLog,
+            TransferBetweenSubAccounts
+        );
+        try_decode_event!(data, AccountActionLog, AccountAction);
+        try_decode_event!(data, SubAccountActionLog, SubAccountAction);
+        try_decode_event!(data, OrdersAccountActionLog, OrdersAccountAction);
+        try_decode_event!(data, ClearingActionLog, ClearingAction);
+        try_decode_event!(data, MarketActionLog, MarketAction);
+        try_decode_event!(data, PoolActionLog, PoolAction);
+        try_decode_event!(data, WhitelistCreationLog, WhitelistCreation);
+        None
+    }
+
+    /// Decodes the event from the given `Program data: ` log message.
+    ///
+    /// Returns `None` if the message does not hold a known event.
+    pub fn from_log(log: &str) -> Option<Self> {
+        let data = decode_program_data(log)?;
+        Self::from_bytes(&data)
+    }
+
+    /// The name of the event, as in the IDL.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OrderFill(_) => "OrderFillLog",
+            Self::LiquidatePosition(_) => "LiquidatePositionLog",
+            Self::DepositOrWithdraw(_) => "DepositOrWithdrawLog",
+            Self::FundingPayment(_) => "FundingPaymentLog",
+            Self::SettlePosition(_) => "SettlePositionLog",
+            Self::TransferBetweenSubAccounts(_) => "TransferBetweenSubAccountsLog",
+            Self::AccountAction(_) => "AccountActionLog",
+            Self::SubAccountAction(_) => "SubAccountActionLog",
+            Self::OrdersAccountAction(_) => "OrdersAccountActionLog",
+            Self::ClearingAction(_) => "ClearingActionLog",
+            Self::MarketAction(_) => "MarketActionLog",
+            Self::PoolAction(_) => "PoolActionLog",
+            Self::WhitelistCreation(_) => "WhitelistCreationLog",
+        }
+    }
+}
+
+impl fmt::Debug for CypherEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CypherEvent").field(&self.name()).finish()
+    }
+}
+
+/// An event decoded from a transaction's log messages.
+#[derive(Debug)]
+pub struct DecodedEvent {
+    /// The name of the cypher instruction that emitted the event, if it was logged.
+    pub instruction: Option<String>,
+    /// The event.
+    pub event: CypherEvent,
+}
+
+/// Decodes the base64 data of the given `Program data: ` log message.
+///
+/// Returns `None` if the message does not hold program data or it is not valid base64.
+pub fn decode_program_data(log: &str) -> Option<Vec<u8>> {
+    let data = log.strip_prefix(PROGRAM_DATA_LOG_PREFIX)?;
+    base64::decode(data).ok()
+}
+
+/// A log message of the cypher program.
+enum CypherLog<'a> {
+    /// The name of an instruction which started executing.
+    Instruction(&'a str),
+    /// A `Program data: ` log message, along with the name of the executing instruction, if it was logged.
+    Data(Option<&'a str>, &'a str),
+}
+
+/// Walks the given transaction log messages, returning those emitted by the cypher program.
+///
+/// Program invocations are tracked so that messages logged by other programs, e.g. programs invoking cypher through CPI,
+/// are not mistaken for cypher messages.
+fn get_cypher_logs(logs: &[String]) -> Vec<CypherLog> {
+    let program_id = crate::id().to_string();
+    // the invoked programs and the instruction each of them is executing
+    let mut invocations: Vec<(bool, Option<&str>)> = Vec::new();
+    let mut cypher_logs = Vec::new();
+
+    for log in logs.iter() {
+        if let Some(name) = log.strip_prefix(INSTRUCTION_LOG_PREFIX) {
+            if let Some((is_cypher, instruction)) = invocations.last_mut() {
+                *instruction = Some(name);
+                if *is_cypher {
+                    cypher_logs.push(CypherLog::Instruction(name));
+                }
+            }
+            continue;
+        }
+        if log.starts_with(PROGRAM_DATA_LOG_PREFIX) {
+            if let Some((true, instruction)) = invocations.last() {
+                cypher_logs.push(CypherLog::Data(*instruction, log));
+            }
+            continue;
+        }
+        if let Some(rest) = log.strip_prefix(PROGRAM_LOG_PREFIX) {
+            let mut parts = rest.split_whitespace();
+            let program = parts.next();
+            match parts.next() {
+                Some("invoke") => {
+                    invocations.push((program == Some(program_id.as_str()), None));
+                }
+                Some("success") | Some("failed:") => {
+                    invocations.pop();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    cypher_logs
+}
+
+/// Decodes the events emitted by the cypher program in the given transaction log messages, in the order they were emitted.
+///
+/// Program invocations are tracked so that data logged by other programs, e.g. programs invoking cypher through CPI,
+/// is not mistaken for cypher events.
+pub fn decode_logs(logs: &[String]) -> Vec<DecodedEvent> {
+    get_cypher_logs(logs)
+        .into_iter()
+        .filter_map(|l| match l {
+            CypherLog::Data(instruction, log) => {
+                CypherEvent::from_log(log).map(|event| DecodedEvent {
+                    instruction: instruction.map(|i| i.to_string()),
+                    event,
+                })
+            }
+            CypherLog::Instruction(_) => None,
+        })
+        .collect()
+}
+
+/// Decodes the names of the cypher instructions executed in the given transaction log messages, in the order they were executed.
+pub fn decode_instruction_names(logs: &[String]) -> Vec<String> {
+    get_cypher_logs(logs)
+        .into_iter()
+        .filter_map(|l| match l {
+            CypherLog::Instruction(name) => Some(name.to_string()),
+            CypherLog::Data(_, _) => None,
+        })
+        .collect()
+}
+
+/// Decodes the [`OrderFillLog`]s emitted in the given transaction log messages.
+pub fn decode_fill_logs(logs: &[String]) -> Vec<OrderFillLog> {
+    decode_logs(logs)
+        .into_iter()
+        .filter_map(|e| match e.event {
+            CypherEvent::OrderFill(f) => Some(f),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Decodes the [`LiquidatePositionLog`]s emitted in the given transaction log messages,
+/// along with the name of the instruction that emitted them.
+pub fn decode_liquidation_logs(logs: &[String]) -> Vec<(Option<String>, LiquidatePositionLog)> {
+    decode_logs(logs)
+        .into_iter()
+        .filter_map(|e| match e.event {
+            CypherEvent::LiquidatePosition(l) => Some((e.instruction, l)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Decodes the [`DepositOrWithdrawLog`]s emitted in the given transaction log messages.
+pub fn decode_deposit_or_withdraw_logs(logs: &[String]) -> Vec<DepositOrWithdrawLog> {
+    decode_logs(logs)
+        .into_iter()
+        .filter_map(|e| match e.event {
+            CypherEvent::DepositOrWithdraw(d) => Some(d),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Decodes the [`FundingPaymentLog`]s emitted in the given transaction log messages.
+pub fn decode_funding_payment_logs(logs: &[String]) -> Vec<FundingPaymentLog> {
+    decode_logs(logs)
+        .into_iter()
+        .filter_map(|e| match e.event {
+            CypherEvent::FundingPayment(p) => Some(p),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,42 @@
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+
+use crate::index::IndexError;
+
+/// An error returned when margin math can not be computed from the given account state,
+/// e.g. because a cache entry is corrupted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    /// A checked arithmetic operation overflowed while computing the given value.
+    Overflow(&'static str),
+    /// The number of decimals is not supported.
+    UnsupportedDecimals(u8),
+    /// The market type of the derivative position on the given market is not supported.
+    UnsupportedMarketType(Pubkey),
+    /// An index is out of bounds.
+    Index(IndexError),
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::Overflow(value) => write!(f, "Overflow while computing {}.", value),
+            MathError::UnsupportedDecimals(decimals) => {
+                write!(f, "Unsupported number of decimals {}.", decimals)
+            }
+            MathError::UnsupportedMarketType(market) => {
+                write!(f, "Unsupported market type for market {}.", market)
+            }
+            MathError::Index(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+impl From<IndexError> for MathError {
+    fn from(e: IndexError) -> Self {
+        MathError::Index(e)
+    }
+}
@@ -1,6 +1,7 @@
 use {
     anchor_spl::dex::serum_dex::state::{
-        Event, EventQueueHeader, QueueHeader, ACCOUNT_HEAD_PADDING, ACCOUNT_TAIL_PADDING,
+        AccountFlag, Event, EventQueueHeader, QueueHeader, ACCOUNT_HEAD_PADDING,
+        ACCOUNT_TAIL_PADDING,
     },
     arrayref::array_refs,
     bytemuck::{cast_mut, cast_ref, cast_slice, from_bytes, Pod, Zeroable},
@@ -31,6 +32,30 @@ pub fn parse_dex_event_queue(data_words: &[u64]) -> (EventQueueHeader, &[Event],
     (header, &head_seg[..head_len], &tail_seg[..tail_len])
 }
 
+/// Parses the events pushed to the Serum event queue after the given sequence number,
+/// ordered from oldest to newest, without copying the remaining events in the queue.
+///
+/// Returns the header, the sequence number of the queue and the new events.
+/// Events that were pushed and already consumed since the given sequence number can not be recovered.
+pub fn parse_dex_event_queue_since(
+    data_words: &[u64],
+    seq_num: u64,
+) -> (EventQueueHeader, u64, Vec<Event>) {
+    // the header is laid out as `account_flags`, `head`, `count` and `seq_num`
+    let current_seq_num = data_words[3];
+    let (header, seg0, seg1) = parse_dex_event_queue(data_words);
+    let new_events_len =
+        (current_seq_num.saturating_sub(seq_num) as usize).min(seg0.len() + seg1.len());
+    let skip = seg0.len() + seg1.len() - new_events_len;
+    let events = seg0
+        .iter()
+        .chain(seg1.iter())
+        .skip(skip)
+        .copied()
+        .collect::<Vec<Event>>();
+    (header, current_seq_num, events)
+}
+
 pub fn remove_dex_account_padding<'a>(data: &'a [u8]) -> Cow<'a, [u64]> {
     let head = &data[..ACCOUNT_HEAD_PADDING.len()];
     if data.len() < ACCOUNT_HEAD_PADDING.len() + ACCOUNT_TAIL_PADDING.len() {
@@ -105,6 +130,27 @@ unsafe impl Zeroable for LeafNode {}
 unsafe impl Pod for LeafNode {}
 
 impl LeafNode {
+    /// Creates a new [`LeafNode`] for an order with the given key, see [`LeafNode::order_id`].
+    pub fn new(
+        key: u128,
+        owner: [u64; 4],
+        owner_slot: u8,
+        fee_tier: u8,
+        quantity: u64,
+        client_order_id: u64,
+    ) -> Self {
+        Self {
+            tag: NodeTag::LeafNode.into(),
+            owner_slot,
+            fee_tier,
+            padding: [0; 2],
+            key,
+            owner,
+            quantity,
+            client_order_id,
+        }
+    }
+
     #[inline]
     pub fn price(&self) -> u64 {
         (self.key >> 64) as u64
@@ -393,3 +439,122 @@ impl SlabView<AnyNode> for Slab {
         }
     }
 }
+
+/// Builds the account data of a Serum book side with the given node capacity, holding the given leaves,
+/// which is the inverse of loading it with [`Slab::new`] after removing the account padding and flags.
+///
+/// A book side holding `n` leaves needs a capacity of at least `2 * n - 1` nodes, for the leaves and the inner nodes.
+/// Returns `None` if the leaves do not fit in the book side or two leaves have the same key.
+pub fn build_dex_book_side(is_asks: bool, capacity: usize, leaves: &[LeafNode]) -> Option<Vec<u8>> {
+    let mut leaves = leaves.to_vec();
+    leaves.sort_by_key(|l| l.order_id());
+    if leaves
+        .windows(2)
+        .any(|w| w[0].order_id() == w[1].order_id())
+        || (leaves.len() * 2).saturating_sub(1) > capacity
+    {
+        return None;
+    }
+
+    let mut nodes: Vec<AnyNode> = Vec::with_capacity(capacity);
+    let root_node = if leaves.is_empty() {
+        0
+    } else {
+        push_critbit_nodes(&leaves, &mut nodes)
+    };
+    let header = SlabHeader {
+        bump_index: nodes.len() as u64,
+        free_list_len: 0,
+        free_list_head: 0,
+        root_node,
+        leaf_count: leaves.len() as u64,
+    };
+    nodes.resize(capacity, AnyNode::zeroed());
+
+    let side_flag = if is_asks {
+        AccountFlag::Asks
+    } else {
+        AccountFlag::Bids
+    };
+    let account_flags = (AccountFlag::Initialized | side_flag).bits();
+    let mut account_data = Vec::with_capacity(
+        ACCOUNT_HEAD_PADDING.len()
+            + 8
+            + SLAB_HEADER_LEN
+            + capacity * size_of::<AnyNode>()
+            + ACCOUNT_TAIL_PADDING.len(),
+    );
+    account_data.extend_from_slice(ACCOUNT_HEAD_PADDING);
+    account_data.extend_from_slice(&account_flags.to_le_bytes());
+    account_data.extend_from_slice(bytemuck::bytes_of(&header));
+    account_data.extend_from_slice(cast_slice(&nodes));
+    account_data.extend_from_slice(ACCOUNT_TAIL_PADDING);
+    Some(account_data)
+}
+
+/// Pushes the nodes of the critbit tree holding the given leaves, which must be sorted by key and have distinct keys,
+/// returning the handle of the tree's root.
+fn push_critbit_nodes(leaves: &[LeafNode], nodes: &mut Vec<AnyNode>) -> NodeHandle {
+    let first_key = leaves[0].order_id();
+    if leaves.len() == 1 {
+        nodes.push(*leaves[0].as_ref());
+        return (nodes.len() - 1) as NodeHandle;
+    }
+    // the leaves are sorted, so the prefix shared by the first and last keys is shared by all of them
+    // and the keys with the critical bit set are all at the end
+    let prefix_len = (first_key ^ leaves[leaves.len() - 1].order_id()).leading_zeros();
+    let crit_bit_mask = 1u128 << (127 - prefix_len);
+    let split = leaves
+        .iter()
+        .position(|l| l.order_id() & crit_bit_mask != 0)
+        .unwrap();
+
+    let left = push_critbit_nodes(&leaves[..split], nodes);
+    let right = push_critbit_nodes(&leaves[split..], nodes);
+    let inner = InnerNode {
+        tag: NodeTag::InnerNode.into(),
+        prefix_len,
+        key: first_key,
+        children: [left, right],
+        _padding: [0; 5],
+    };
+    nodes.push(*inner.as_ref());
+    (nodes.len() - 1) as NodeHandle
+}
+
+/// Builds the account data of a Serum event queue with the given event capacity, holding the given events,
+/// which is the inverse of [`parse_dex_event_queue`] after removing the account padding.
+///
+/// The events are ordered from oldest to newest and the newest event has the sequence number `seq_num - 1`,
+/// the events are placed in the ring buffer where the program would have pushed them, so they may wrap around.
+/// Returns `None` if the events do not fit in the event queue or more events are given than were ever pushed.
+pub fn build_dex_event_queue(capacity: usize, seq_num: u64, events: &[Event]) -> Option<Vec<u8>> {
+    if capacity == 0 || events.len() > capacity || (events.len() as u64) > seq_num {
+        return None;
+    }
+
+    let head = ((seq_num - events.len() as u64) % capacity as u64) as usize;
+    let event_len = size_of::<Event>();
+    let mut events_data = vec![0u8; capacity * event_len];
+    for (i, event) in events.iter().enumerate() {
+        let idx = (head + i) % capacity;
+        events_data[idx * event_len..(idx + 1) * event_len]
+            .copy_from_slice(transmute_to_bytes(std::slice::from_ref(event)));
+    }
+
+    // the header is laid out as `account_flags`, `head`, `count` and `seq_num`
+    let account_flags = (AccountFlag::Initialized | AccountFlag::EventQueue).bits();
+    let mut account_data = Vec::with_capacity(
+        ACCOUNT_HEAD_PADDING.len()
+            + size_of::<EventQueueHeader>()
+            + events_data.len()
+            + ACCOUNT_TAIL_PADDING.len(),
+    );
+    account_data.extend_from_slice(ACCOUNT_HEAD_PADDING);
+    for word in [account_flags, head as u64, events.len() as u64, seq_num] {
+        account_data.extend_from_slice(&word.to_le_bytes());
+    }
+    account_data.extend_from_slice(&events_data);
+    account_data.extend_from_slice(ACCOUNT_TAIL_PADDING);
+    Some(account_data)
+}
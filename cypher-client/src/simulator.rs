@@ -0,0 +1,357 @@
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use fixed::types::I80F48;
+
+use crate::{
+    index::IndexError,
+    math::MathError,
+    quote_mint,
+    valuation::{get_sub_account_health_from_state, SubAccountHealth, ValuationError},
+    AgnosticMarket, CacheAccount, Clearing, CypherSubAccount, MarginCollateralRatioType,
+    MarketType, Side,
+};
+
+/// An error returned when a hypothetical action can not be applied to a sub account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulationError {
+    /// There is no position slot available for the given token mint or market.
+    NoPositionSlot(Pubkey),
+    /// There is no spot position of the given token mint, e.g. to withdraw from or to settle a fill against.
+    NoSpotPosition(Pubkey),
+    /// The margin math could not be computed.
+    Math(MathError),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::NoPositionSlot(identifier) => {
+                write!(f, "No position slot available for {}.", identifier)
+            }
+            SimulationError::NoSpotPosition(token_mint) => {
+                write!(f, "No spot position for token mint {}.", token_mint)
+            }
+            SimulationError::Math(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl From<MathError> for SimulationError {
+    fn from(e: MathError) -> Self {
+        SimulationError::Math(e)
+    }
+}
+
+impl From<IndexError> for SimulationError {
+    fn from(e: IndexError) -> Self {
+        SimulationError::Math(MathError::Index(e))
+    }
+}
+
+/// A hypothetical action applied to a sub account by the [`MarginSimulator`].
+#[derive(Debug, Clone, Copy)]
+pub enum MarginAction {
+    /// A deposit of the given native amount of a token.
+    Deposit {
+        /// The token mint.
+        token_mint: Pubkey,
+        /// The index of the token's price cache, only used if the sub account has no position of the token.
+        cache_index: u16,
+        /// The native amount.
+        amount: u64,
+    },
+    /// A withdrawal of the given native amount of a token, borrowing it if the deposit is insufficient.
+    Withdraw {
+        /// The token mint.
+        token_mint: Pubkey,
+        /// The native amount.
+        amount: u64,
+    },
+    /// A fill on a derivatives market, fees are not accounted for.
+    Fill {
+        /// The market.
+        market: Pubkey,
+        /// The market type.
+        market_type: MarketType,
+        /// The index of the market's price cache.
+        cache_index: u16,
+        /// The side of the sub account.
+        side: Side,
+        /// The filled base quantity, in native units.
+        base_quantity: u64,
+        /// The filled quote quantity, in native units, i.e. the base quantity at the fill price.
+        quote_quantity: u64,
+    },
+    /// A new resting order on a derivatives market.
+    NewOrder {
+        /// The market.
+        market: Pubkey,
+        /// The market type.
+        market_type: MarketType,
+        /// The index of the market's price cache.
+        cache_index: u16,
+        /// The side of the order.
+        side: Side,
+        /// The base quantity, in native units.
+        base_quantity: u64,
+        /// The quote quantity locked by a bid, in native units.
+        quote_quantity: u64,
+    },
+}
+
+impl MarginAction {
+    /// Creates a [`MarginAction::NewOrder`] for an order with the given limit price, in quote lots per base lot,
+    /// and base quantity, in base lots, on the given market.
+    ///
+    /// Returns `None` if the order's native quantities overflow.
+    pub fn new_order(
+        market: &Pubkey,
+        market_state: &AgnosticMarket,
+        side: Side,
+        limit_price: u64,
+        base_qty: u64,
+    ) -> Option<Self> {
+        let (base_quantity, quote_quantity) =
+            get_native_quantities(market_state, limit_price, base_qty)?;
+        Some(MarginAction::NewOrder {
+            market: *market,
+            market_type: market_state.config.market_type,
+            cache_index: market_state.config.cache_index,
+            side,
+            base_quantity,
+            quote_quantity,
+        })
+    }
+
+    /// Creates a [`MarginAction::Fill`] for a fill of the given base quantity, in base lots,
+    /// at the given price, in quote lots per base lot, on the given market.
+    ///
+    /// Returns `None` if the fill's native quantities overflow.
+    pub fn fill(
+        market: &Pubkey,
+        market_state: &AgnosticMarket,
+        side: Side,
+        price: u64,
+        base_qty: u64,
+    ) -> Option<Self> {
+        let (base_quantity, quote_quantity) = get_native_quantities(market_state, price, base_qty)?;
+        Some(MarginAction::Fill {
+            market: *market,
+            market_type: market_state.config.market_type,
+            cache_index: market_state.config.cache_index,
+            side,
+            base_quantity,
+            quote_quantity,
+        })
+    }
+}
+
+fn get_native_quantities(
+    market_state: &AgnosticMarket,
+    price: u64,
+    base_qty: u64,
+) -> Option<(u64, u64)> {
+    let base_quantity = base_qty.checked_mul(market_state.base_multiplier)?;
+    let quote_quantity = base_qty
+        .checked_mul(price)?
+        .checked_mul(market_state.quote_multiplier)?;
+    Some((base_quantity, quote_quantity))
+}
+
+/// Simulates the effect of hypothetical actions on the margin of a sub account.
+///
+/// The simulator works on a copy of the [`CypherSubAccount`], so actions can be applied and the resulting
+/// c-ratio inspected to pre-validate a deposit, withdrawal or order client-side before submitting it.
+/// Prices are held constant at those of the given [`CacheAccount`].
+pub struct MarginSimulator<'a> {
+    cache_account: &'a CacheAccount,
+    sub_account: Box<CypherSubAccount>,
+}
+
+impl<'a> fmt::Debug for MarginSimulator<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MarginSimulator")
+            .field(
+                "master_account",
+                &format!("{}", self.sub_account.master_account),
+            )
+            .finish()
+    }
+}
+
+impl<'a> MarginSimulator<'a> {
+    /// Creates a new [`MarginSimulator`] from a copy of the given [`CypherSubAccount`].
+    pub fn new(sub_account: &CypherSubAccount, cache_account: &'a CacheAccount) -> Self {
+        Self {
+            cache_account,
+            sub_account: Box::new(*sub_account),
+        }
+    }
+
+    /// Gets the simulated [`CypherSubAccount`].
+    pub fn sub_account(&self) -> &CypherSubAccount {
+        &self.sub_account
+    }
+
+    /// Applies the given action to the simulated sub account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the action can not be applied,
+    /// in which case the simulated sub account is left unchanged.
+    pub fn apply(&mut self, action: &MarginAction) -> Result<&mut Self, SimulationError> {
+        let mut sub_account = *self.sub_account;
+        match *action {
+            MarginAction::Deposit {
+                token_mint,
+                cache_index,
+                amount,
+            } => {
+                let spot = match sub_account.get_or_init_spot_position(&token_mint, cache_index) {
+                    Some(s) => s,
+                    None => return Err(SimulationError::NoPositionSlot(token_mint)),
+                };
+                let cache = self
+                    .cache_account
+                    .try_get_price_cache(spot.cache_index as usize)?;
+                spot.add_to_total_position(cache, I80F48::from(amount))?;
+            }
+            MarginAction::Withdraw { token_mint, amount } => {
+                let spot = match sub_account
+                    .positions
+                    .iter_mut()
+                    .find(|p| p.spot.token_mint == token_mint)
+                {
+                    Some(p) => &mut p.spot,
+                    None => return Err(SimulationError::NoSpotPosition(token_mint)),
+                };
+                let cache = self
+                    .cache_account
+                    .try_get_price_cache(spot.cache_index as usize)?;
+                spot.add_to_total_position(cache, -I80F48::from(amount))?;
+            }
+            MarginAction::Fill {
+                market,
+                market_type,
+                cache_index,
+                side,
+                base_quantity,
+                quote_quantity,
+            } => {
+                let derivative = match sub_account.get_or_init_derivative_position(
+                    &market,
+                    market_type,
+                    cache_index,
+                ) {
+                    Some(d) => d,
+                    None => return Err(SimulationError::NoPositionSlot(market)),
+                };
+                let (base_delta, quote_delta) = match side {
+                    Side::Bid => (I80F48::from(base_quantity), -I80F48::from(quote_quantity)),
+                    Side::Ask => (-I80F48::from(base_quantity), I80F48::from(quote_quantity)),
+                };
+                derivative.base_position = derivative
+                    .base_position()
+                    .checked_add(base_delta)
+                    .ok_or(MathError::Overflow("derivative position"))?
+                    .to_bits();
+                let quote_position = match sub_account.get_quote_position_mut() {
+                    Some(q) => q,
+                    None => return Err(SimulationError::NoSpotPosition(quote_mint::ID)),
+                };
+                let quote_cache = self
+                    .cache_account
+                    .try_get_price_cache(quote_position.cache_index as usize)?;
+                quote_position.add_to_total_position(quote_cache, quote_delta)?;
+            }
+            MarginAction::NewOrder {
+                market,
+                market_type,
+                cache_index,
+                side,
+                base_quantity,
+                quote_quantity,
+            } => {
+                let placed = sub_account.apply_order(
+                    self.cache_account,
+                    &market,
+                    market_type,
+                    cache_index,
+                    side,
+                    base_quantity,
+                    quote_quantity,
+                )?;
+                if !placed {
+                    return Err(SimulationError::NoPositionSlot(market));
+                }
+            }
+        }
+        *self.sub_account = sub_account;
+        Ok(self)
+    }
+
+    /// Applies the given actions in order to the simulated sub account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if one of the actions can not be applied,
+    /// in which case the actions before it remain applied.
+    pub fn apply_all(&mut self, actions: &[MarginAction]) -> Result<&mut Self, SimulationError> {
+        for action in actions.iter() {
+            self.apply(action)?;
+        }
+        Ok(self)
+    }
+
+    /// Gets the c-ratio, assets value and liabilities value of the simulated sub account respectively.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the margin math can not be computed.
+    pub fn get_margin_c_ratio_components(
+        &self,
+        mcr_type: MarginCollateralRatioType,
+    ) -> Result<(I80F48, I80F48, I80F48), MathError> {
+        self.sub_account
+            .get_margin_c_ratio_components(self.cache_account, mcr_type)
+    }
+
+    /// Gets the health metrics of the simulated sub account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the margin math can not be computed.
+    pub fn get_health(&self) -> Result<SubAccountHealth, ValuationError> {
+        get_sub_account_health_from_state(&self.sub_account, self.cache_account)
+    }
+
+    /// Whether the simulated sub account meets the clearing's initialization margin requirement,
+    /// which new orders and withdrawals are checked against.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the margin math can not be computed.
+    pub fn meets_init_margin(&self, clearing: &Clearing) -> Result<bool, MathError> {
+        let c_ratio = self.sub_account.get_margin_c_ratio(
+            self.cache_account,
+            MarginCollateralRatioType::Initialization,
+        )?;
+        Ok(c_ratio >= clearing.init_margin_ratio())
+    }
+
+    /// Whether the simulated sub account meets the clearing's maintenance margin requirement,
+    /// below which it can be liquidated.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the margin math can not be computed.
+    pub fn meets_maint_margin(&self, clearing: &Clearing) -> Result<bool, MathError> {
+        let c_ratio = self
+            .sub_account
+            .get_margin_c_ratio(self.cache_account, MarginCollateralRatioType::Maintenance)?;
+        Ok(c_ratio >= clearing.maint_margin_ratio())
+    }
+}
@@ -7,11 +7,11 @@ use {
     anchor_lang::{prelude::*, Discriminator, ZeroCopy},
     anchor_spl::{associated_token, token::spl_token},
     arrayref::array_ref,
-    bytemuck::{bytes_of, from_bytes},
+    bytemuck::{bytes_of, from_bytes, pod_read_unaligned},
     fixed::types::I80F48,
 };
 
-use crate::{constants::*, dex, ClearingType};
+use crate::{constants::*, dex, math::MathError, ClearingType};
 
 pub fn adjust_decimals(value: I80F48, decimals: u8) -> I80F48 {
     match decimals {
@@ -29,6 +29,26 @@ pub fn adjust_decimals(value: I80F48, decimals: u8) -> I80F48 {
     }
 }
 
+/// adjusts the given value by the given decimals, returning an error instead of panicking
+pub fn try_adjust_decimals(value: I80F48, decimals: u8) -> std::result::Result<I80F48, MathError> {
+    let inv_decimal_adj = match decimals {
+        0 => return Ok(value),
+        1 => INV_ONE_DECIMAL_ADJ_FIXED,
+        2 => INV_TWO_DECIMAL_ADJ_FIXED,
+        3 => INV_THREE_DECIMAL_ADJ_FIXED,
+        4 => INV_FOUR_DECIMAL_ADJ_FIXED,
+        5 => INV_FIVE_DECIMAL_ADJ_FIXED,
+        6 => INV_SIX_DECIMAL_ADJ_FIXED,
+        7 => INV_SEVEN_DECIMAL_ADJ_FIXED,
+        8 => INV_EIGHT_DECIMAL_ADJ_FIXED,
+        9 => INV_NINE_DECIMAL_ADJ_FIXED,
+        _ => return Err(MathError::UnsupportedDecimals(decimals)),
+    };
+    value
+        .checked_mul(inv_decimal_adj)
+        .ok_or(MathError::Overflow("decimals adjustment"))
+}
+
 #[inline(always)]
 pub fn convert_price_to_lots(
     price: u64,
@@ -154,6 +174,17 @@ pub fn get_zero_copy_account<T: ZeroCopy + Owner>(account_data: &[u8]) -> Box<T>
     ))
 }
 
+/// Deserializes a zero copy account from the given account data without panicking.
+///
+/// Returns `None` if the data is too short or does not start with the account's discriminator.
+pub fn try_get_zero_copy_account<T: ZeroCopy + Owner>(account_data: &[u8]) -> Option<Box<T>> {
+    let size = std::mem::size_of::<T>() + 8;
+    if account_data.len() < size || account_data[..8] != T::discriminator() {
+        return None;
+    }
+    Some(Box::new(pod_read_unaligned::<T>(&account_data[8..size])))
+}
+
 pub fn get_program_account<
     T: AccountSerialize + AccountDeserialize + Discriminator + Clone + Owner,
 >(
@@ -0,0 +1,193 @@
+use std::fmt;
+
+use anchor_lang::prelude::Pubkey;
+use fixed::types::I80F48;
+
+use crate::{
+    math::MathError, utils::try_get_zero_copy_account, CacheAccount, Clearing, CypherSubAccount,
+    MarginCollateralRatioType,
+};
+
+/// An error returned when a sub account can not be valued from raw account data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuationError {
+    /// The account data of the given account type is invalid.
+    InvalidAccountData(&'static str),
+    /// The margin math could not be computed.
+    Math(MathError),
+}
+
+impl fmt::Display for ValuationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValuationError::InvalidAccountData(account) => {
+                write!(f, "Invalid account data for {}.", account)
+            }
+            ValuationError::Math(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ValuationError {}
+
+impl From<MathError> for ValuationError {
+    fn from(e: MathError) -> Self {
+        ValuationError::Math(e)
+    }
+}
+
+/// The value of a sub account under a given margin collateral ratio type.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MarginHealth {
+    /// The c-ratio, `I80F48::MAX` if the sub account has no liabilities.
+    pub c_ratio: I80F48,
+    /// The weighted assets value.
+    pub assets_value: I80F48,
+    /// The unweighted assets value.
+    pub assets_value_unweighted: I80F48,
+    /// The weighted liabilities value.
+    pub liabilities_value: I80F48,
+    /// The unweighted liabilities value.
+    pub liabilities_value_unweighted: I80F48,
+}
+
+/// The health metrics of a sub account.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SubAccountHealth {
+    /// The value under initialization weights, which bound new positions and withdrawals.
+    pub initialization: MarginHealth,
+    /// The value under maintenance weights, which determine liquidations.
+    pub maintenance: MarginHealth,
+}
+
+/// The estimated liquidation price of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionLiquidationPrice {
+    /// The token mint of a spot position or the market of a derivative position.
+    pub identifier: Pubkey,
+    /// Whether the position is a spot position.
+    pub is_spot: bool,
+    /// The current price the position is valued at.
+    pub price: I80F48,
+    /// The price at which the sub account becomes liquidatable, holding every other price constant,
+    /// `None` if there is no such price.
+    pub liquidation_price: Option<I80F48>,
+}
+
+/// Computes the health metrics of a sub account from the raw account data of the [`CypherSubAccount`]
+/// and the [`CacheAccount`], without any RPC requests or contexts.
+///
+/// ### Errors
+///
+/// This function will return an error if one of the account datas is invalid or the margin math can not be computed.
+pub fn get_sub_account_health(
+    sub_account_data: &[u8],
+    cache_account_data: &[u8],
+) -> Result<SubAccountHealth, ValuationError> {
+    let sub_account = match try_get_zero_copy_account::<CypherSubAccount>(sub_account_data) {
+        Some(sa) => sa,
+        None => {
+            return Err(ValuationError::InvalidAccountData("CypherSubAccount"));
+        }
+    };
+    let cache_account = match try_get_zero_copy_account::<CacheAccount>(cache_account_data) {
+        Some(c) => c,
+        None => {
+            return Err(ValuationError::InvalidAccountData("CacheAccount"));
+        }
+    };
+    get_sub_account_health_from_state(&sub_account, &cache_account)
+}
+
+/// Computes the health metrics of the given [`CypherSubAccount`].
+///
+/// ### Errors
+///
+/// This function will return an error if the margin math can not be computed.
+pub fn get_sub_account_health_from_state(
+    sub_account: &CypherSubAccount,
+    cache_account: &CacheAccount,
+) -> Result<SubAccountHealth, ValuationError> {
+    Ok(SubAccountHealth {
+        initialization: get_margin_health(
+            sub_account,
+            cache_account,
+            MarginCollateralRatioType::Initialization,
+        )?,
+        maintenance: get_margin_health(
+            sub_account,
+            cache_account,
+            MarginCollateralRatioType::Maintenance,
+        )?,
+    })
+}
+
+fn get_margin_health(
+    sub_account: &CypherSubAccount,
+    cache_account: &CacheAccount,
+    mcr_type: MarginCollateralRatioType,
+) -> Result<MarginHealth, ValuationError> {
+    let (assets_value, assets_value_unweighted) =
+        sub_account.get_assets_value(cache_account, mcr_type)?;
+    let (liabilities_value, liabilities_value_unweighted) =
+        sub_account.get_liabilities_value(cache_account, mcr_type)?;
+    let c_ratio = if liabilities_value == I80F48::ZERO {
+        I80F48::MAX
+    } else {
+        assets_value.saturating_div(liabilities_value)
+    };
+
+    Ok(MarginHealth {
+        c_ratio,
+        assets_value,
+        assets_value_unweighted,
+        liabilities_value,
+        liabilities_value_unweighted,
+    })
+}
+
+/// Estimates the liquidation price of every spot and derivative position of the given [`CypherSubAccount`].
+///
+/// ### Errors
+///
+/// This function will return an error if the margin math can not be computed.
+pub fn get_liquidation_prices(
+    sub_account: &CypherSubAccount,
+    clearing: &Clearing,
+    cache_account: &CacheAccount,
+) -> Result<Vec<PositionLiquidationPrice>, ValuationError> {
+    let mut liquidation_prices = Vec::new();
+
+    for position in sub_account.get_spot_positions() {
+        let cache = cache_account
+            .try_get_price_cache(position.cache_index as usize)
+            .map_err(MathError::from)?;
+        liquidation_prices.push(PositionLiquidationPrice {
+            identifier: position.token_mint,
+            is_spot: true,
+            price: cache.oracle_price(),
+            liquidation_price: sub_account.get_spot_liquidation_price(
+                clearing,
+                cache_account,
+                &position,
+            )?,
+        });
+    }
+    for position in sub_account.get_derivative_positions() {
+        let cache = cache_account
+            .try_get_price_cache(position.cache_index as usize)
+            .map_err(MathError::from)?;
+        liquidation_prices.push(PositionLiquidationPrice {
+            identifier: position.market,
+            is_spot: false,
+            price: cache.price_for(position.market_type),
+            liquidation_price: sub_account.get_derivative_liquidation_price(
+                clearing,
+                cache_account,
+                &position,
+            )?,
+        });
+    }
+
+    Ok(liquidation_prices)
+}
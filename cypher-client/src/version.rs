@@ -0,0 +1,25 @@
+use anchor_lang::solana_program::hash::{hash, Hash};
+
+/// The IDL this crate was generated from.
+pub const IDL_JSON: &str = include_str!("../idl.json");
+
+/// The version of the program this crate's IDL was generated from.
+///
+/// This must be kept in sync with the `version` field of the IDL, which is checked by the tests.
+pub const IDL_VERSION: &str = "3.0.8";
+
+/// Gets the hash of the IDL this crate was generated from.
+pub fn idl_hash() -> Hash {
+    hash(IDL_JSON.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idl_version_matches_idl() {
+        let idl: serde_json::Value = serde_json::from_str(IDL_JSON).unwrap();
+        assert_eq!(idl["version"].as_str(), Some(IDL_VERSION));
+    }
+}
@@ -0,0 +1,39 @@
+use cypher_utils::{constants::JSON_RPC_URL, test_utils::MarginFixture};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{path::Path, str::FromStr, sync::Arc};
+
+/// Captures an account, it's sub accounts, clearing and the cache account into a [`MarginFixture`] along with the
+/// golden margin values computed from them, e.g. to be verified with `verify_margin_fixtures` after a refactor.
+///
+/// Usage: `cargo run --example capture_margin_fixture --features test-utils -- <authority> <account number> <path>`
+#[tokio::main]
+async fn main() {
+    let usage = "Usage: capture_margin_fixture <authority> <account number> <path>";
+    let mut args = std::env::args().skip(1);
+    let authority = Pubkey::from_str(&args.next().expect(usage)).expect("Invalid authority.");
+    let account_number = args
+        .next()
+        .expect(usage)
+        .parse::<u8>()
+        .expect("Invalid account number.");
+    let path = args.next().expect(usage);
+    let name = Path::new(&path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| authority.to_string());
+
+    let rpc_client = Arc::new(RpcClient::new(JSON_RPC_URL.to_string()));
+    let fixture = MarginFixture::capture(&rpc_client, &name, &authority, account_number)
+        .await
+        .unwrap();
+    fixture.save(Path::new(&path)).unwrap();
+
+    println!(
+        "Captured {} sub accounts of {} at slot {} into {}.",
+        fixture.sub_accounts.len(),
+        authority,
+        fixture.slot,
+        path
+    );
+}
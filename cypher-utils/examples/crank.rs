@@ -0,0 +1,35 @@
+use cypher_utils::{config::ServiceConfig, cranks::CrankService, logging::init_logger};
+use log::info;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signature::read_keypair_file;
+use std::{path::Path, sync::Arc};
+use tokio::sync::broadcast::channel;
+
+/// Runs the permissionless cranks configured in the `cranker` section of a service configuration file.
+///
+/// Usage: `cargo run --example crank -- <config path>`
+#[tokio::main]
+async fn main() {
+    init_logger().unwrap();
+
+    let path = std::env::args().nth(1).expect("Usage: crank <config path>");
+    let config = ServiceConfig::load(Path::new(&path)).unwrap();
+    let cranker = config
+        .cranker
+        .as_ref()
+        .expect("The configuration does not have a cranker section.");
+    let signer = read_keypair_file(&config.keypair_path).unwrap();
+
+    let rpc_client = Arc::new(RpcClient::new(config.rpc.http_url.clone()));
+    let shutdown = Arc::new(channel::<bool>(1).0);
+    let crank_service = Arc::new(CrankService::new(
+        rpc_client,
+        Arc::new(signer),
+        cranker.get_crank_config(&config.fees),
+        shutdown.clone(),
+    ));
+
+    // the targets are discovered on-chain when the service starts, and periodically after that
+    info!("Starting crank service.");
+    crank_service.start_service().await;
+}
@@ -0,0 +1,294 @@
+use cypher_client::{
+    cache_account,
+    constants::QUOTE_TOKEN_DECIMALS,
+    instructions::liquidate_perp_position,
+    quote_mint,
+    utils::{
+        adjust_decimals, derive_market_address, derive_pool_node_address,
+        derive_public_clearing_address,
+    },
+    MarginCollateralRatioType, PerpetualMarket, SubAccountMargining,
+};
+use cypher_utils::{
+    config::ServiceConfig,
+    contexts::{
+        AccountContext, CacheContext, ClearingContext, GenericDerivativeMarket, MarketContext,
+        ReloadPolicy, ResilientContext, SubAccountContext, UserContext,
+    },
+    logging::init_logger,
+    scanners::{get_clearing_accounts, get_sub_accounts},
+    utils::{encode_string, send_transactions},
+};
+use fixed::types::I80F48;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::read_keypair_file, signer::Signer,
+};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The maximum age of a sub account cache before the account is checked with the full computation, in seconds.
+const MAX_CACHE_AGE: u64 = 60;
+
+/// Liquidates the perpetual positions of the accounts of the public clearing whose maintenance c-ratio is below the
+/// clearing's maintenance margin ratio, as configured in the `liquidator` section of a service configuration file.
+///
+/// Accounts are screened with their cached health, see [`UserContext::get_cached_health`], and only loaded in full
+/// if the cached values are stale or below the threshold. Short positions are liquidated for quote tokens and long
+/// positions against quote token borrows, the program rejects liquidations the liqee's positions do not allow.
+///
+/// The cache account is reloaded with retries, accounts are still screened while it is degraded but only liquidated
+/// once it is fresh again, and nothing is done once it is stale, see [`ResilientContext`].
+///
+/// Usage: `cargo run --example liquidator -- <config path>`
+#[tokio::main]
+async fn main() {
+    init_logger().unwrap();
+
+    let path = std::env::args()
+        .nth(1)
+        .expect("Usage: liquidator <config path>");
+    let config = ServiceConfig::load(Path::new(&path)).unwrap();
+    let liquidator = config
+        .liquidator
+        .as_ref()
+        .expect("The configuration does not have a liquidator section.");
+    let signer = read_keypair_file(&config.keypair_path).unwrap();
+    let rpc_client = Arc::new(RpcClient::new(config.rpc.http_url.clone()));
+
+    let (clearing, _) = derive_public_clearing_address();
+    let clearing_ctx = ClearingContext::load(&rpc_client, &clearing).await.unwrap();
+    let maint_margin_ratio = clearing_ctx.state.maint_margin_ratio();
+
+    let liqor_ctx = UserContext::load(&rpc_client, &signer.pubkey(), None)
+        .await
+        .unwrap();
+    let liqor_sub_account = liqor_ctx
+        .get_sub_account_ctx(liquidator.sub_account_number)
+        .expect("The liquidator sub account does not exist.")
+        .address;
+
+    let configured_markets = liquidator
+        .markets
+        .iter()
+        .map(|name| derive_market_address(&encode_string(name)).0)
+        .collect::<Vec<Pubkey>>();
+    let markets = MarketContext::<PerpetualMarket>::load_all(&rpc_client)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|m| configured_markets.is_empty() || configured_markets.contains(&m.address))
+        .map(|m| (m.address, m))
+        .collect::<HashMap<Pubkey, MarketContext<PerpetualMarket>>>();
+    info!(
+        "Liquidating positions on {} perpetual markets.",
+        markets.len()
+    );
+
+    let mut cache_ctx = ResilientContext::new(
+        CacheContext::load(&rpc_client).await.unwrap(),
+        ReloadPolicy::default(),
+    );
+    let mut interval = tokio::time::interval(Duration::from_millis(liquidator.check_interval_ms));
+
+    loop {
+        interval.tick().await;
+
+        cache_ctx.reload(&rpc_client).await;
+        let fresh_cache_ctx = match cache_ctx.get_unless_stale() {
+            Some(c) => c,
+            None => {
+                warn!(
+                    "Cache account is stale, last reloaded {}ms ago, skipping cycle.",
+                    cache_ctx.age().as_millis()
+                );
+                continue;
+            }
+        };
+        let accounts = match get_clearing_accounts(&rpc_client, &clearing).await {
+            Ok(a) => a,
+            Err(e) => {
+                warn!("Failed to get clearing accounts: {}", e.to_string());
+                continue;
+            }
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        for (address, state) in accounts.into_iter() {
+            let authority = state.authority;
+            let account_ctx = AccountContext::new(address, state);
+            // accounts whose cached values can not be summed are checked with the full computation
+            let needs_full_check = match account_ctx.get_cached_health(now, MAX_CACHE_AGE) {
+                Ok(h) => h.needs_full_check(maint_margin_ratio),
+                Err(e) => {
+                    warn!("Failed to compute cached health of {}: {:?}", address, e);
+                    true
+                }
+            };
+            if !needs_full_check {
+                continue;
+            }
+
+            let sub_account_ctxs = match get_sub_accounts(&rpc_client, &address).await {
+                Ok(s) => s
+                    .into_iter()
+                    .map(|(pubkey, state)| SubAccountContext::new(pubkey, state))
+                    .collect(),
+                Err(e) => {
+                    warn!(
+                        "Failed to get sub accounts of {}: {}",
+                        address,
+                        e.to_string()
+                    );
+                    continue;
+                }
+            };
+            let liqee_ctx = UserContext::new(authority, account_ctx, sub_account_ctxs);
+            let c_ratio = match liqee_ctx
+                .get_margin_c_ratio(fresh_cache_ctx, MarginCollateralRatioType::Maintenance)
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!("Failed to compute c-ratio of {}: {:?}", address, e);
+                    continue;
+                }
+            };
+            if c_ratio >= maint_margin_ratio {
+                continue;
+            }
+            info!(
+                "Account {} is liquidatable with a c-ratio of {}.",
+                address, c_ratio
+            );
+            if cache_ctx.is_degraded() {
+                warn!(
+                    "Cache account is degraded, not liquidating account {}.",
+                    address
+                );
+                continue;
+            }
+
+            let ixs = get_liquidation_ixs(
+                &liqee_ctx,
+                &liqor_ctx,
+                &liqor_sub_account,
+                fresh_cache_ctx,
+                &clearing_ctx,
+                &markets,
+                liquidator.min_profit,
+            );
+            if ixs.is_empty() {
+                continue;
+            }
+            match send_transactions(
+                &rpc_client,
+                ixs,
+                &signer,
+                true,
+                Some(config.fees.get_compute_unit_info()),
+                None,
+            )
+            .await
+            {
+                Ok(s) => info!("Liquidated account {}: {:?}", address, s),
+                Err(e) => warn!("Failed to liquidate account {}: {}", address, e.to_string()),
+            }
+        }
+    }
+}
+
+/// Gets the instructions liquidating the perpetual positions of the given liqee's cross margined sub accounts
+/// on the given markets, skipping positions whose expected liquidation fee is below the given minimum profit.
+fn get_liquidation_ixs(
+    liqee_ctx: &UserContext,
+    liqor_ctx: &UserContext,
+    liqor_sub_account: &Pubkey,
+    cache_ctx: &CacheContext,
+    clearing_ctx: &ClearingContext,
+    markets: &HashMap<Pubkey, MarketContext<PerpetualMarket>>,
+    min_profit: u64,
+) -> Vec<Instruction> {
+    let mut ixs = Vec::new();
+    for sub_account_ctx in liqee_ctx.sub_account_ctxs.iter() {
+        if sub_account_ctx.state.margining_type != SubAccountMargining::Cross {
+            continue;
+        }
+        for position in sub_account_ctx.state.positions.iter() {
+            let derivative = &position.derivative;
+            let market_ctx = match markets.get(&derivative.market) {
+                Some(m) => m,
+                None => continue,
+            };
+            let base_position = derivative.base_position();
+            if base_position == I80F48::ZERO {
+                continue;
+            }
+
+            // the liqor is paid a fee on the value of the position it takes over,
+            // the clearing's liqor fee is the multiplier applied to that value
+            let cache = cache_ctx
+                .state
+                .get_price_cache(derivative.cache_index as usize);
+            let value = adjust_decimals(
+                base_position.abs(),
+                cache.decimals_for(derivative.market_type),
+            ) * cache.price_for(derivative.market_type);
+            let expected_profit = value
+                * (clearing_ctx.state.liq_liqor_fee() - I80F48::ONE)
+                * I80F48::from(10u64.pow(QUOTE_TOKEN_DECIMALS as u32));
+            if expected_profit < I80F48::from(min_profit) {
+                continue;
+            }
+
+            let market = market_ctx.state.agnostic_market();
+            let (asset, asset_market, liability, liability_market) = if base_position.is_negative()
+            {
+                (
+                    quote_mint::id(),
+                    Pubkey::default(),
+                    market_ctx.address,
+                    market_ctx.address,
+                )
+            } else {
+                (
+                    market_ctx.address,
+                    market_ctx.address,
+                    quote_mint::id(),
+                    Pubkey::default(),
+                )
+            };
+            match liquidate_perp_position(
+                &cache_account::id(),
+                &liqor_ctx.account_ctx.state.clearing,
+                &liqor_ctx.account_ctx.address,
+                liqor_sub_account,
+                &liqee_ctx.account_ctx.state.clearing,
+                &liqee_ctx.account_ctx.address,
+                &sub_account_ctx.address,
+                &asset,
+                &asset_market,
+                &liability,
+                &liability_market,
+                &market.quote_pool,
+                &derive_pool_node_address(&market.quote_pool, 0).0,
+                &liqor_ctx.authority,
+            ) {
+                Ok(ix) => ixs.push(ix),
+                Err(e) => warn!(
+                    "Failed to create liquidation instruction for {}: {}",
+                    sub_account_ctx.address,
+                    e.to_string()
+                ),
+            }
+        }
+    }
+    ixs
+}
@@ -0,0 +1,122 @@
+use cypher_client::{quote_mint, MarginCollateralRatioType};
+use cypher_utils::{
+    constants::JSON_RPC_URL,
+    contexts::{CacheContext, UserContext},
+    display::DisplayCurrency,
+    risk::get_margin_attribution_report,
+};
+use fixed::types::I80F48;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The maximum age of a sub account cache for the cached health to be considered fresh, in seconds.
+const MAX_CACHE_AGE: u64 = 60;
+
+/// Prints the positions, health and margin usage of an account.
+///
+/// Usage: `cargo run --example portfolio -- <authority> [account number]`
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let authority = Pubkey::from_str(
+        &args
+            .next()
+            .expect("Usage: portfolio <authority> [account number]"),
+    )
+    .expect("Invalid authority.");
+    let account_number = args
+        .next()
+        .map(|n| n.parse::<u8>().expect("Invalid account number."));
+
+    let rpc_client = Arc::new(RpcClient::new(JSON_RPC_URL.to_string()));
+
+    let cache_ctx = CacheContext::load(&rpc_client).await.unwrap();
+    let user_ctx = UserContext::load(&rpc_client, &authority, account_number)
+        .await
+        .unwrap();
+    let quote_cache_index = user_ctx
+        .sub_account_ctxs
+        .iter()
+        .find_map(|s| s.get_spot_position(&quote_mint::ID))
+        .expect("Account has no quote position.")
+        .cache_index()
+        .unwrap();
+    let quote = DisplayCurrency::quote("USDC", quote_cache_index);
+    let display = |value: I80F48| match quote.convert(value, &cache_ctx.state) {
+        Some(v) => v.to_string(),
+        None => format!("{} (unpriced)", value),
+    };
+
+    println!("Account: {}", user_ctx.account_ctx.address);
+    for sub_account_ctx in user_ctx.sub_account_ctxs.iter() {
+        println!(
+            "  Sub account #{}: {}",
+            sub_account_ctx.account_number(),
+            sub_account_ctx.address
+        );
+        for position in sub_account_ctx.state.positions.iter() {
+            if position.spot.token_mint != Pubkey::default() {
+                let cache = cache_ctx
+                    .state
+                    .get_price_cache(position.spot.cache_index as usize);
+                println!(
+                    "    Spot {}: {} (native units)",
+                    position.spot.token_mint,
+                    position.spot.total_position(cache)
+                );
+            }
+            if position.derivative.market != Pubkey::default() {
+                println!(
+                    "    Derivative {}: {} (native units)",
+                    position.derivative.market,
+                    position.derivative.total_position()
+                );
+            }
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let cached_health = user_ctx.get_cached_health(now, MAX_CACHE_AGE).unwrap();
+    println!(
+        "Cached equity: {}, cached c-ratio: {}{}",
+        display(cached_health.equity()),
+        cached_health.c_ratio,
+        if cached_health.is_fresh() {
+            ""
+        } else {
+            " (stale)"
+        }
+    );
+    let c_ratio = user_ctx
+        .get_margin_c_ratio(&cache_ctx, MarginCollateralRatioType::Maintenance)
+        .unwrap();
+    println!("Maintenance c-ratio: {}", c_ratio);
+
+    for interest in user_ctx.get_accrued_interest(&cache_ctx).iter() {
+        println!(
+            "Accrued interest of {}: {} (native units)",
+            interest.token_mint,
+            interest.net_interest()
+        );
+    }
+
+    let report = get_margin_attribution_report(&rpc_client, &user_ctx.account_ctx.address)
+        .await
+        .unwrap();
+    println!("Init margin used: {}", display(report.total_margin_used()));
+    for (source, share) in report.get_margin_shares().iter() {
+        println!(
+            "  {:?}: {:.2}%",
+            source,
+            share.saturating_mul(I80F48::from(100))
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use cypher_client::{
+    cache_account, quote_mint,
+    utils::{adjust_decimals, convert_coin_to_decimals_fixed, convert_price_to_lots_fixed},
+    PerpetualMarket,
+};
+use cypher_utils::{
+    config::{FeeBudgetConfig, MarketKind, QuotingMarketConfig, ServiceConfig},
+    contexts::{
+        CacheContext, ContextError, GenericDerivativeMarket, MarketContext, ReloadPolicy,
+        ResilientContext, UserContext,
+    },
+    logging::init_logger,
+    oms::{
+        get_quoting_ladder, LadderConfig, OrderRiskCheck, RiskLimitsRegistry, StrategyId,
+        StrategyRiskLimits,
+    },
+    services::MarketParams,
+    utils::send_transactions,
+};
+use fixed::types::I80F48;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    signature::{read_keypair_file, Keypair},
+    signer::Signer,
+};
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The strategy the risk limits of the maker are registered under.
+const STRATEGY_ID: StrategyId = 0;
+
+/// Quotes a symmetric ladder around the oracle price on every perpetual market configured in the `market_maker`
+/// section of a service configuration file, replacing the quotes at the configured interval.
+///
+/// This is a template rather than a strategy, it has no inventory skew, fair value model or fill handling.
+///
+/// The cache and user accounts are reloaded with retries and the maker stops quoting while either of them is degraded,
+/// leaving the resting quotes to expire, see [`ResilientContext`].
+///
+/// Usage: `cargo run --example simple_maker -- <config path>`
+#[tokio::main]
+async fn main() {
+    init_logger().unwrap();
+
+    let path = std::env::args()
+        .nth(1)
+        .expect("Usage: simple_maker <config path>");
+    let config = ServiceConfig::load(Path::new(&path)).unwrap();
+    let maker = config
+        .market_maker
+        .as_ref()
+        .expect("The configuration does not have a market maker section.");
+    let signer = read_keypair_file(&config.keypair_path).unwrap();
+    let rpc_client = Arc::new(RpcClient::new(config.rpc.http_url.clone()));
+
+    let mut limits = StrategyRiskLimits::default();
+    let mut markets = Vec::new();
+    for market_config in maker.markets.iter() {
+        if market_config.kind != MarketKind::Perpetual {
+            warn!(
+                "Skipping market {}, only perpetual markets are quoted.",
+                market_config.name
+            );
+            continue;
+        }
+        let address = market_config.get_address().unwrap();
+        let market_ctx = MarketContext::<PerpetualMarket>::load(&rpc_client, &address)
+            .await
+            .unwrap();
+        limits
+            .markets
+            .insert(address, market_config.get_risk_limits());
+        markets.push((market_config, market_ctx));
+    }
+    let mut registry = RiskLimitsRegistry::new();
+    registry.set_limits(STRATEGY_ID, limits);
+
+    let mut cache_ctx = ResilientContext::new(
+        CacheContext::load(&rpc_client).await.unwrap(),
+        ReloadPolicy::default(),
+    );
+    let mut user_ctx = ResilientContext::new(
+        UserContext::load(&rpc_client, &signer.pubkey(), None)
+            .await
+            .unwrap(),
+        ReloadPolicy::default(),
+    );
+    let quote_interval = maker.get_quote_interval();
+    let mut interval = tokio::time::interval(quote_interval);
+
+    loop {
+        interval.tick().await;
+
+        cache_ctx.reload(&rpc_client).await;
+        user_ctx.reload(&rpc_client).await;
+        // quoting off a degraded state could place orders against outdated prices or positions
+        if cache_ctx.is_degraded() || user_ctx.is_degraded() {
+            warn!(
+                "Not quoting, cache account health: {:?} - user account health: {:?}",
+                cache_ctx.health(),
+                user_ctx.health()
+            );
+            continue;
+        }
+        // quotes which outlive a few quote intervals, e.g. because the maker stopped, expire on their own
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let max_ts = now + quote_interval.as_secs().max(1) * 3;
+
+        for (idx, (market_config, market_ctx)) in markets.iter().enumerate() {
+            // every market gets it's own range of client order ids so fills can be attributed
+            let client_order_id = (idx as u64) << 32;
+            match requote(
+                &rpc_client,
+                &signer,
+                user_ctx.get(),
+                cache_ctx.get(),
+                &registry,
+                &config.fees,
+                market_config,
+                market_ctx,
+                client_order_id,
+                max_ts,
+            )
+            .await
+            {
+                Ok(n) => info!("Quoted {} orders on {}.", n, market_config.name),
+                Err(e) => warn!("Failed to quote {}: {}", market_config.name, e.to_string()),
+            }
+        }
+    }
+}
+
+/// Cancels the maker's orders on the given market and places a new ladder around the oracle price,
+/// returning the number of orders placed.
+#[allow(clippy::too_many_arguments)]
+async fn requote(
+    rpc_client: &Arc<RpcClient>,
+    signer: &Keypair,
+    user_ctx: &UserContext,
+    cache_ctx: &CacheContext,
+    registry: &RiskLimitsRegistry,
+    fees: &FeeBudgetConfig,
+    market_config: &QuotingMarketConfig,
+    market_ctx: &MarketContext<PerpetualMarket>,
+    client_order_id: u64,
+    max_ts: u64,
+) -> Result<usize, ContextError> {
+    let market = market_ctx.state.agnostic_market();
+    let params = MarketParams::new(&market_ctx.address, market);
+    let decimals = market.config.decimals;
+
+    let market_price = cache_ctx
+        .state
+        .get_price_cache(market.config.cache_index as usize)
+        .oracle_price();
+    // the quote token's price cache is the one of the quote position, whichever slot of the cache it is in
+    let quote_cache_index = match user_ctx
+        .sub_account_ctxs
+        .iter()
+        .find_map(|s| s.get_spot_position(&quote_mint::ID))
+    {
+        Some(p) => p.cache_index as usize,
+        None => {
+            warn!(
+                "Account has no quote position, not quoting {}.",
+                market_config.name
+            );
+            return Ok(0);
+        }
+    };
+    let quote_price = cache_ctx
+        .state
+        .get_price_cache(quote_cache_index)
+        .oracle_price();
+    let mid_price = match market_price.checked_div(quote_price) {
+        Some(p) if p.is_positive() => p,
+        _ => {
+            warn!("Oracle price of {} is not set.", market_config.name);
+            return Ok(0);
+        }
+    };
+
+    let ladder_config = LadderConfig {
+        spread_bps: market_config.spread_bps,
+        level_spacing_bps: market_config.layer_spacing_bps,
+        levels: market_config.layers as usize,
+        sizes: vec![adjust_decimals(
+            convert_coin_to_decimals_fixed(market_config.order_size, params.base_multiplier),
+            decimals,
+        )],
+        client_order_id,
+        max_ts,
+        ..Default::default()
+    };
+    let orders = get_quoting_ladder(&params, decimals, mid_price, &ladder_config);
+
+    let base_position = user_ctx
+        .get_sub_account_with_position(&market_ctx.address)
+        .and_then(|sa| sa.get_derivative_position(&market_ctx.address))
+        .map(|p| {
+            (p.base_position() / I80F48::from(params.base_multiplier)).saturating_to_num::<i64>()
+        })
+        .unwrap_or(0);
+    let reference_price = convert_price_to_lots_fixed(
+        mid_price,
+        params.base_multiplier,
+        10u64.pow(decimals as u32),
+        params.quote_multiplier,
+    );
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let orders = orders
+        .into_iter()
+        .filter(|args| {
+            let check = OrderRiskCheck {
+                market: &market_ctx.address,
+                args,
+                base_position,
+                reference_price,
+            };
+            match registry.check_order(STRATEGY_ID, &check, now) {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!(
+                        "Dropping order on {}: {}",
+                        market_config.name,
+                        e.to_string()
+                    );
+                    false
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // the old quotes are pulled before the new ones are placed, so there is a short window without quotes
+    let compute_unit_info = Some(fees.get_compute_unit_info());
+    match user_ctx
+        .cancel_all_orders(rpc_client, signer, market_ctx, compute_unit_info)
+        .await
+    {
+        Ok(_) => (),
+        Err(e) => {
+            return Err(e);
+        }
+    }
+    if orders.is_empty() {
+        return Ok(0);
+    }
+
+    let accounts = match user_ctx.get_derivative_order_accounts(&cache_account::id(), market_ctx) {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let count = orders.len();
+    let ixs = orders
+        .into_iter()
+        .map(|args| market_ctx.state.new_order_ix(&accounts, args))
+        .collect();
+    match send_transactions(rpc_client, ixs, signer, false, compute_unit_info, None).await {
+        Ok(_) => Ok(count),
+        Err(e) => Err(ContextError::ClientError(e)),
+    }
+}
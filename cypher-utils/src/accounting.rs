@@ -0,0 +1,239 @@
+use agnostic_orderbook::state::{event_queue::FillEvent, Side as AobSide};
+use cypher_client::{
+    aob::{zip_event_callback_infos, CallBackInfo},
+    Market, OrderFillLog, Side,
+};
+use fixed::types::I80F48;
+use num_traits::cast::FromPrimitive;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// The reconstructed accounting of a derivative position.
+///
+/// Quantities are in native units, prices in native quote units per native base unit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PositionAccounting {
+    /// The base position, positive for longs and negative for shorts.
+    pub base_position: I80F48,
+    /// The average price at which the current position was entered, zero if the position is flat.
+    pub average_entry_price: I80F48,
+    /// The PnL realized by reducing or closing positions, in native quote units.
+    pub realized_pnl: I80F48,
+    /// The traded base quantity.
+    pub base_volume: u64,
+    /// The traded quote quantity.
+    pub quote_volume: u64,
+}
+
+impl PositionAccounting {
+    /// Applies a fill of the given native base and quote quantities on the given side.
+    ///
+    /// Fills which increase the position move the average entry price, fills which reduce it realize PnL
+    /// against the average entry price, a fill that flips the position realizes PnL on the closed part
+    /// and enters the remainder at the fill price.
+    pub fn apply_fill(&mut self, side: Side, base_quantity: u64, quote_quantity: u64) {
+        if base_quantity == 0 {
+            return;
+        }
+        let fill_base = I80F48::from(base_quantity);
+        let fill_price = I80F48::from(quote_quantity) / fill_base;
+        let signed_fill = if side == Side::Bid {
+            fill_base
+        } else {
+            -fill_base
+        };
+        self.base_volume = self.base_volume.saturating_add(base_quantity);
+        self.quote_volume = self.quote_volume.saturating_add(quote_quantity);
+
+        let is_increasing = self.base_position == I80F48::ZERO
+            || self.base_position.is_positive() == signed_fill.is_positive();
+        if is_increasing {
+            let new_position = self.base_position + signed_fill;
+            self.average_entry_price = (self.average_entry_price * self.base_position.abs()
+                + fill_price * fill_base)
+                / new_position.abs();
+            self.base_position = new_position;
+            return;
+        }
+
+        let closed = fill_base.min(self.base_position.abs());
+        let pnl_per_unit = if self.base_position.is_positive() {
+            fill_price - self.average_entry_price
+        } else {
+            self.average_entry_price - fill_price
+        };
+        self.realized_pnl += pnl_per_unit * closed;
+        self.base_position += signed_fill;
+        if self.base_position == I80F48::ZERO {
+            self.average_entry_price = I80F48::ZERO;
+        } else if fill_base > closed {
+            // the position flipped, the remainder was entered at the fill price
+            self.average_entry_price = fill_price;
+        }
+    }
+
+    /// Gets the unrealized PnL of the position at the given mark price, in native quote units.
+    pub fn unrealized_pnl(&self, mark_price: I80F48) -> I80F48 {
+        (mark_price - self.average_entry_price) * self.base_position
+    }
+
+    /// Gets the realized and unrealized PnL of the position at the given mark price, in native quote units.
+    pub fn total_pnl(&self, mark_price: I80F48) -> I80F48 {
+        self.realized_pnl + self.unrealized_pnl(mark_price)
+    }
+}
+
+/// Converts a price in quote lots per base lot, as on the book, into native quote units per native base unit.
+pub fn lot_price_to_native(market: &dyn Market, price: u64) -> I80F48 {
+    I80F48::from(price) * I80F48::from(market.quote_multiplier())
+        / I80F48::from(market.base_multiplier())
+}
+
+/// Reconstructs the realized and unrealized PnL and the average entry price per market of a sub account from it's fills.
+///
+/// The on-chain state only stores the base position and funding, so these are tracked client-side. Fees and funding
+/// are not accounted for, positions are tracked from the first recorded fill so the tracker should be started
+/// while the sub account is flat or seeded with [`PnlTracker::set_position`].
+#[derive(Debug, Clone)]
+pub struct PnlTracker {
+    /// The master account.
+    pub master_account: Pubkey,
+    /// The index of the sub account.
+    pub sub_account_idx: u8,
+    positions: HashMap<Pubkey, PositionAccounting>,
+}
+
+impl PnlTracker {
+    /// Creates a new [`PnlTracker`] for the sub account with the given index of the given master account.
+    pub fn new(master_account: &Pubkey, sub_account_idx: u8) -> Self {
+        Self {
+            master_account: *master_account,
+            sub_account_idx,
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Seeds the position on the given market, e.g. with the on-chain base position and a known entry price.
+    pub fn set_position(&mut self, market: &Pubkey, base_position: I80F48, entry_price: I80F48) {
+        let position = self.positions.entry(*market).or_default();
+        position.base_position = base_position;
+        position.average_entry_price = if base_position == I80F48::ZERO {
+            I80F48::ZERO
+        } else {
+            entry_price
+        };
+    }
+
+    /// Records a fill of the sub account on the given market, with native base and quote quantities.
+    pub fn record_fill(
+        &mut self,
+        market: &Pubkey,
+        side: Side,
+        base_quantity: u64,
+        quote_quantity: u64,
+    ) {
+        self.positions
+            .entry(*market)
+            .or_default()
+            .apply_fill(side, base_quantity, quote_quantity);
+    }
+
+    /// Records the fills of the sub account among the given AOB events of the given market.
+    ///
+    /// The callback infos must be interleaved as in the event queue, see [`zip_event_callback_infos`].
+    /// Callee must make sure that the same events are not recorded more than once,
+    /// e.g. by only recording the events returned by [`parse_aob_event_queue_since`](cypher_client::aob::parse_aob_event_queue_since).
+    ///
+    /// Returns the number of recorded fills.
+    pub fn record_aob_events(
+        &mut self,
+        market: &Pubkey,
+        market_state: &dyn Market,
+        events: &[FillEvent],
+        callback_infos: &[CallBackInfo],
+    ) -> usize {
+        let mut recorded = 0;
+        for (event, maker, taker) in zip_event_callback_infos(events, callback_infos) {
+            if event.maker_order_id == u128::default() || event.base_size == 0 {
+                continue;
+            }
+            let taker_side = match AobSide::from_u8(event.taker_side) {
+                Some(AobSide::Ask) => Side::Ask,
+                Some(AobSide::Bid) => Side::Bid,
+                None => continue,
+            };
+            let (base_quantity, quote_quantity) = match (
+                market_state.unscale_base_amount(event.base_size),
+                market_state.unscale_quote_amount(event.quote_size),
+            ) {
+                (Some(b), Some(q)) => (b, q),
+                _ => continue,
+            };
+            let sub_account_idx = Some(self.sub_account_idx);
+            if maker.is_owned_by(&self.master_account, sub_account_idx) {
+                let maker_side = if taker_side == Side::Bid {
+                    Side::Ask
+                } else {
+                    Side::Bid
+                };
+                self.record_fill(market, maker_side, base_quantity, quote_quantity);
+                recorded += 1;
+            }
+            if taker.is_owned_by(&self.master_account, sub_account_idx) {
+                self.record_fill(market, taker_side, base_quantity, quote_quantity);
+                recorded += 1;
+            }
+        }
+        recorded
+    }
+
+    /// Records the given [`OrderFillLog`], e.g. as delivered by the [`DropCopyService`](crate::services::DropCopyService),
+    /// if the sub account is it's maker or taker.
+    ///
+    /// The logged side is the taker's side. Returns whether the fill was recorded.
+    pub fn record_fill_log(&mut self, log: &OrderFillLog) -> bool {
+        if log.sub_account_idx != self.sub_account_idx {
+            return false;
+        }
+        let side = if log.taker == self.master_account {
+            log.side
+        } else if log.maker == self.master_account {
+            if log.side == Side::Bid {
+                Side::Ask
+            } else {
+                Side::Bid
+            }
+        } else {
+            return false;
+        };
+        self.record_fill(&log.market, side, log.coin_qty, log.pc_qty);
+        true
+    }
+
+    /// Gets the accounting of the position on the given market.
+    pub fn get_position(&self, market: &Pubkey) -> Option<&PositionAccounting> {
+        self.positions.get(market)
+    }
+
+    /// Gets the accounting of every position with recorded fills.
+    pub fn get_positions(&self) -> Vec<(Pubkey, PositionAccounting)> {
+        self.positions.iter().map(|(m, p)| (*m, *p)).collect()
+    }
+
+    /// Gets the PnL realized across all markets, in native quote units.
+    pub fn get_realized_pnl(&self) -> I80F48 {
+        self.positions
+            .values()
+            .fold(I80F48::ZERO, |acc, p| acc + p.realized_pnl)
+    }
+
+    /// Gets the unrealized PnL across all markets at the given mark prices, in native quote units.
+    ///
+    /// Positions on markets without a mark price are not included.
+    pub fn get_unrealized_pnl(&self, mark_prices: &HashMap<Pubkey, I80F48>) -> I80F48 {
+        self.positions
+            .iter()
+            .filter_map(|(market, p)| mark_prices.get(market).map(|mp| p.unrealized_pnl(*mp)))
+            .fold(I80F48::ZERO, |acc, pnl| acc + pnl)
+    }
+}
@@ -0,0 +1,130 @@
+use cypher_client::{FeeTier, Pool};
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+
+/// The number of hours in a year, used to annualize hourly funding rates.
+const HOURS_PER_YEAR: u64 = 8_760;
+
+/// The number of basis points in one unit.
+const BPS_UNIT: u64 = 10_000;
+
+/// The number of taker fills needed to open and close both legs of a carry trade.
+const CARRY_TRADE_FILLS: u64 = 4;
+
+/// The direction of a cash-and-carry trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CarryDirection {
+    /// Long the spot token and short the perpetual, collecting positive funding paid by longs.
+    LongSpotShortPerp,
+    /// Short the spot token by borrowing it and long the perpetual, collecting negative funding paid by shorts.
+    ShortSpotLongPerp,
+}
+
+/// The inputs of the net-carry estimate of a perpetual market.
+#[derive(Debug, Clone, Copy)]
+pub struct CarryInputs {
+    /// The perpetual market.
+    pub market: Pubkey,
+    /// The predicted hourly funding rate, as a fraction of the index price, positive if longs pay shorts.
+    ///
+    /// This can be projected from the book, see [`crate::contexts::MarketContext::get_projected_funding_rate`],
+    /// or averaged from recorded observations, e.g. by the `FundingRecorder`.
+    pub hourly_funding_rate: I80F48,
+    /// The deposit APR of the underlying spot token, earned when holding it.
+    pub spot_deposit_apr: I80F48,
+    /// The borrow APR of the underlying spot token, paid when shorting it.
+    pub spot_borrow_apr: I80F48,
+    /// The taker fee, in bps, paid on every fill.
+    pub taker_fee_bps: u64,
+    /// The number of hours the trade is expected to be held, over which the fees are amortized.
+    pub holding_period_hours: u64,
+}
+
+impl CarryInputs {
+    /// Creates the [`CarryInputs`] of the given market from the underlying spot token's [`Pool`] and the account's [`FeeTier`].
+    pub fn new(
+        market: &Pubkey,
+        hourly_funding_rate: I80F48,
+        pool: &Pool,
+        fee_tier: &FeeTier,
+        holding_period_hours: u64,
+    ) -> Self {
+        Self {
+            market: *market,
+            hourly_funding_rate,
+            spot_deposit_apr: pool.deposit_rate(),
+            spot_borrow_apr: pool.borrow_rate(),
+            taker_fee_bps: fee_tier.taker_bps as u64,
+            holding_period_hours,
+        }
+    }
+}
+
+/// A cash-and-carry opportunity on a perpetual market, with all rates annualized as fractions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CarryOpportunity {
+    /// The perpetual market.
+    pub market: Pubkey,
+    /// The direction of the trade.
+    pub direction: CarryDirection,
+    /// The funding collected by the perpetual leg.
+    pub funding_apr: I80F48,
+    /// The interest earned, if positive, or paid, if negative, by the spot leg.
+    pub interest_apr: I80F48,
+    /// The fees paid to open and close both legs, amortized over the holding period.
+    pub fee_apr: I80F48,
+}
+
+impl CarryOpportunity {
+    /// The net carry, i.e. the funding collected plus the interest earned minus the fees paid.
+    pub fn net_apr(&self) -> I80F48 {
+        self.funding_apr + self.interest_apr - self.fee_apr
+    }
+
+    /// Whether the trade is expected to be profitable over the holding period.
+    pub fn is_profitable(&self) -> bool {
+        self.net_apr().is_positive()
+    }
+}
+
+/// Estimates the net carry of the given market, taking the direction which collects the predicted funding.
+///
+/// Returns `None` if no funding is predicted or the holding period is zero.
+pub fn get_carry_opportunity(inputs: &CarryInputs) -> Option<CarryOpportunity> {
+    if inputs.hourly_funding_rate == I80F48::ZERO || inputs.holding_period_hours == 0 {
+        return None;
+    }
+    let funding_apr = inputs.hourly_funding_rate.abs() * I80F48::from(HOURS_PER_YEAR);
+    let (direction, interest_apr) = if inputs.hourly_funding_rate.is_positive() {
+        (CarryDirection::LongSpotShortPerp, inputs.spot_deposit_apr)
+    } else {
+        (CarryDirection::ShortSpotLongPerp, -inputs.spot_borrow_apr)
+    };
+    let fee_apr = I80F48::from(inputs.taker_fee_bps * CARRY_TRADE_FILLS) / I80F48::from(BPS_UNIT)
+        * I80F48::from(HOURS_PER_YEAR)
+        / I80F48::from(inputs.holding_period_hours);
+
+    Some(CarryOpportunity {
+        market: inputs.market,
+        direction,
+        funding_apr,
+        interest_apr,
+        fee_apr,
+    })
+}
+
+/// Estimates the net carry of the given markets and ranks them from the highest to the lowest net carry.
+///
+/// If `profitable_only` is set, opportunities with a non-positive net carry are discarded.
+pub fn rank_carry_opportunities(
+    inputs: &[CarryInputs],
+    profitable_only: bool,
+) -> Vec<CarryOpportunity> {
+    let mut opportunities = inputs
+        .iter()
+        .filter_map(get_carry_opportunity)
+        .filter(|o| !profitable_only || o.is_profitable())
+        .collect::<Vec<CarryOpportunity>>();
+    opportunities.sort_by(|a, b| b.net_apr().cmp(&a.net_apr()));
+    opportunities
+}
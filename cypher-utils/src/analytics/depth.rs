@@ -0,0 +1,255 @@
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::contexts::{ContextManager, Order, OrderBook};
+
+/// The depth of the book within a distance from the mid price.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BookDepth {
+    /// The base quantity of the bids, in native units.
+    pub bid_base: u64,
+    /// The quote quantity of the bids, in native units.
+    pub bid_quote: u64,
+    /// The base quantity of the asks, in native units.
+    pub ask_base: u64,
+    /// The quote quantity of the asks, in native units.
+    pub ask_quote: u64,
+}
+
+impl BookDepth {
+    /// The quote quantity of both sides, in native units.
+    pub fn total_quote(&self) -> u64 {
+        self.bid_quote.saturating_add(self.ask_quote)
+    }
+}
+
+/// Gets the mid price between the given best bid and best ask, in quote lots per base lot.
+///
+/// Returns `None` if either side is empty.
+pub fn get_mid_price(bids: &[Order], asks: &[Order]) -> Option<I80F48> {
+    match (bids.first(), asks.first()) {
+        (Some(bid), Some(ask)) => {
+            Some((I80F48::from(bid.price) + I80F48::from(ask.price)) / I80F48::from(2))
+        }
+        _ => None,
+    }
+}
+
+/// Gets the spread between the given best bid and best ask relative to the mid price, in basis points.
+///
+/// Returns `None` if either side is empty.
+pub fn get_spread_bps(bids: &[Order], asks: &[Order]) -> Option<I80F48> {
+    let mid_price = get_mid_price(bids, asks)?;
+    if mid_price == I80F48::ZERO {
+        return None;
+    }
+    let spread = I80F48::from(asks[0].price) - I80F48::from(bids[0].price);
+    Some(spread / mid_price * I80F48::from(10_000))
+}
+
+/// Gets the depth of the given orders within `bps` basis points of the given mid price.
+///
+/// The orders are expected to be sorted from best to worst price, as they are on an [`OrderBook`].
+pub fn get_depth_within_bps(
+    bids: &[Order],
+    asks: &[Order],
+    mid_price: I80F48,
+    bps: u16,
+) -> BookDepth {
+    let max_distance = mid_price * I80F48::from(bps) / I80F48::from(10_000);
+    let mut depth = BookDepth::default();
+    for bid in bids
+        .iter()
+        .take_while(|o| mid_price - I80F48::from(o.price) <= max_distance)
+    {
+        depth.bid_base = depth.bid_base.saturating_add(bid.base_quantity);
+        depth.bid_quote = depth.bid_quote.saturating_add(bid.quote_quantity);
+    }
+    for ask in asks
+        .iter()
+        .take_while(|o| I80F48::from(o.price) - mid_price <= max_distance)
+    {
+        depth.ask_base = depth.ask_base.saturating_add(ask.base_quantity);
+        depth.ask_quote = depth.ask_quote.saturating_add(ask.quote_quantity);
+    }
+    depth
+}
+
+/// The state of the book at a sample.
+#[derive(Debug, Default, Clone, Copy)]
+struct DepthSample {
+    timestamp: u64,
+    spread_bps: Option<I80F48>,
+    depth: BookDepth,
+}
+
+/// The time-weighted depth quality metrics of a market's book.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DepthQuality {
+    /// The number of seconds covered by the samples.
+    pub sampled_seconds: u64,
+    /// The number of seconds during which the book was two sided.
+    pub two_sided_seconds: u64,
+    /// The time-weighted spread while the book was two sided, in basis points.
+    pub time_weighted_spread_bps: Option<I80F48>,
+    /// The time-weighted quote quantity of the bids within the tracked distance from the mid price, in native units.
+    pub time_weighted_bid_depth: I80F48,
+    /// The time-weighted quote quantity of the asks within the tracked distance from the mid price, in native units.
+    pub time_weighted_ask_depth: I80F48,
+}
+
+impl DepthQuality {
+    /// The ratio of the sampled time during which the book was two sided.
+    pub fn two_sided_uptime(&self) -> I80F48 {
+        if self.sampled_seconds == 0 {
+            I80F48::ZERO
+        } else {
+            I80F48::from(self.two_sided_seconds) / I80F48::from(self.sampled_seconds)
+        }
+    }
+}
+
+/// Accumulates the depth quality metrics of one market.
+#[derive(Debug, Default, Clone)]
+struct MarketDepthAccumulator {
+    last_sample: Option<DepthSample>,
+    sampled_seconds: u64,
+    two_sided_seconds: u64,
+    spread_seconds_sum: I80F48,
+    bid_depth_seconds_sum: I80F48,
+    ask_depth_seconds_sum: I80F48,
+}
+
+impl MarketDepthAccumulator {
+    fn record(&mut self, sample: DepthSample) {
+        if let Some(last) = self.last_sample {
+            if sample.timestamp <= last.timestamp {
+                return;
+            }
+            // the previous sample is assumed to hold until this one
+            let elapsed = sample.timestamp - last.timestamp;
+            let elapsed_fixed = I80F48::from(elapsed);
+            self.sampled_seconds += elapsed;
+            if let Some(spread_bps) = last.spread_bps {
+                self.two_sided_seconds += elapsed;
+                self.spread_seconds_sum += spread_bps.saturating_mul(elapsed_fixed);
+            }
+            self.bid_depth_seconds_sum +=
+                I80F48::from(last.depth.bid_quote).saturating_mul(elapsed_fixed);
+            self.ask_depth_seconds_sum +=
+                I80F48::from(last.depth.ask_quote).saturating_mul(elapsed_fixed);
+        }
+        self.last_sample = Some(sample);
+    }
+
+    fn get_quality(&self) -> DepthQuality {
+        let time_weighted = |sum: I80F48, seconds: u64| {
+            if seconds == 0 {
+                I80F48::ZERO
+            } else {
+                sum / I80F48::from(seconds)
+            }
+        };
+        DepthQuality {
+            sampled_seconds: self.sampled_seconds,
+            two_sided_seconds: self.two_sided_seconds,
+            time_weighted_spread_bps: if self.two_sided_seconds == 0 {
+                None
+            } else {
+                Some(time_weighted(
+                    self.spread_seconds_sum,
+                    self.two_sided_seconds,
+                ))
+            },
+            time_weighted_bid_depth: time_weighted(
+                self.bid_depth_seconds_sum,
+                self.sampled_seconds,
+            ),
+            time_weighted_ask_depth: time_weighted(
+                self.ask_depth_seconds_sum,
+                self.sampled_seconds,
+            ),
+        }
+    }
+}
+
+/// Tracks the depth quality of market books from periodic snapshots, i.e. the depth within a distance
+/// from the mid price, the time-weighted spread and the uptime of two sided quotes.
+///
+/// Each snapshot is assumed to represent the book until the following snapshot of the same market.
+/// Makers can verify they meet the requirements of an incentive program by recording only their own orders
+/// with [`DepthQualityTracker::record_orders`], measured against the mid price of the full book.
+#[derive(Debug, Clone)]
+pub struct DepthQualityTracker {
+    /// The maximum distance from the mid price, in basis points, within which depth is measured.
+    pub depth_bps: u16,
+    markets: HashMap<Pubkey, MarketDepthAccumulator>,
+}
+
+impl DepthQualityTracker {
+    /// Creates a new [`DepthQualityTracker`] which measures depth within the given basis points of the mid price.
+    pub fn new(depth_bps: u16) -> Self {
+        Self {
+            depth_bps,
+            markets: HashMap::new(),
+        }
+    }
+
+    /// Records a snapshot of the given market's book at the given unix timestamp.
+    pub fn record_book(&mut self, market: &Pubkey, timestamp: u64, book: &OrderBook) {
+        self.record_orders(market, timestamp, book, &book.bids, &book.asks);
+    }
+
+    /// Records a snapshot of the given orders on the given market's book at the given unix timestamp.
+    ///
+    /// The spread and depth are measured from the given orders against the mid price of the full book,
+    /// the given orders are expected to be sorted from best to worst price.
+    pub fn record_orders(
+        &mut self,
+        market: &Pubkey,
+        timestamp: u64,
+        book: &OrderBook,
+        bids: &[Order],
+        asks: &[Order],
+    ) {
+        let sample = match book.mid_price() {
+            Some(mid_price) => DepthSample {
+                timestamp,
+                spread_bps: get_spread_bps(bids, asks),
+                depth: get_depth_within_bps(bids, asks, mid_price, self.depth_bps),
+            },
+            None => DepthSample {
+                timestamp,
+                ..Default::default()
+            },
+        };
+        self.markets.entry(*market).or_default().record(sample);
+    }
+
+    /// Records a snapshot of every orderbook of the given [`ContextManager`] at the given unix timestamp,
+    /// which is meant to be called after every reload.
+    pub fn record_manager(&mut self, manager: &ContextManager, timestamp: u64) {
+        for orderbook in manager.orderbooks.iter() {
+            self.record_book(&orderbook.market, timestamp, &orderbook.state);
+        }
+    }
+
+    /// Gets the depth quality of the given market, if it was sampled.
+    pub fn get_quality(&self, market: &Pubkey) -> Option<DepthQuality> {
+        self.markets.get(market).map(|m| m.get_quality())
+    }
+
+    /// Gets the depth quality of every sampled market.
+    pub fn get_qualities(&self) -> Vec<(Pubkey, DepthQuality)> {
+        self.markets
+            .iter()
+            .map(|(market, m)| (*market, m.get_quality()))
+            .collect()
+    }
+
+    /// Clears the samples of every market, e.g. at the start of a new incentive epoch.
+    pub fn reset(&mut self) {
+        self.markets.clear();
+    }
+}
@@ -0,0 +1,7 @@
+pub mod carry;
+pub mod depth;
+pub mod open_interest;
+
+pub use carry::*;
+pub use depth::*;
+pub use open_interest::*;
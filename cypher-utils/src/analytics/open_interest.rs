@@ -0,0 +1,77 @@
+use cypher_client::CypherSubAccount;
+use fixed::types::I80F48;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{contexts::ContextError, risk::MarketRiskMetrics, scanners::get_all_sub_accounts};
+
+/// The open interest of every perpetual and futures market, aggregated from the positions of all sub accounts.
+#[derive(Debug, Default, Clone)]
+pub struct OpenInterestReport {
+    /// The open interest and long/short skew per market.
+    pub markets: HashMap<Pubkey, MarketRiskMetrics>,
+    /// The number of sub accounts scanned.
+    pub sub_accounts_count: u64,
+}
+
+impl OpenInterestReport {
+    /// Computes the [`OpenInterestReport`] from the derivative positions of the given sub accounts.
+    pub fn from_sub_accounts(sub_accounts: &[(Pubkey, Box<CypherSubAccount>)]) -> Self {
+        let mut report = Self {
+            sub_accounts_count: sub_accounts.len() as u64,
+            ..Default::default()
+        };
+
+        for (_, sub_account) in sub_accounts.iter() {
+            for position in sub_account.iter_position_slots() {
+                if position.derivative.market == Pubkey::default() {
+                    continue;
+                }
+                report
+                    .markets
+                    .entry(position.derivative.market)
+                    .or_insert_with(|| MarketRiskMetrics {
+                        market: position.derivative.market,
+                        ..Default::default()
+                    })
+                    .add_position(position.derivative.base_position());
+            }
+        }
+
+        report
+    }
+
+    /// Gets the open interest of the given market, in native units.
+    pub fn get_open_interest(&self, market: &Pubkey) -> I80F48 {
+        self.markets
+            .get(market)
+            .map(|m| m.open_interest())
+            .unwrap_or(I80F48::ZERO)
+    }
+
+    /// Gets the markets sorted by open interest, from largest to smallest.
+    pub fn get_sorted_markets(&self) -> Vec<&MarketRiskMetrics> {
+        let mut markets = self.markets.values().collect::<Vec<&MarketRiskMetrics>>();
+        markets.sort_by(|a, b| b.open_interest().cmp(&a.open_interest()));
+        markets
+    }
+}
+
+/// Loads all [`CypherSubAccount`]s and computes the [`OpenInterestReport`].
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_open_interest_report(
+    rpc_client: &Arc<RpcClient>,
+) -> Result<OpenInterestReport, ContextError> {
+    let sub_accounts = match get_all_sub_accounts(rpc_client).await {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    Ok(OpenInterestReport::from_sub_accounts(&sub_accounts))
+}
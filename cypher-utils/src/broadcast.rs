@@ -0,0 +1,200 @@
+use {
+    futures::future::{select_ok, BoxFuture, FutureExt},
+    log::warn,
+    solana_client::{
+        client_error::ClientError, nonblocking::rpc_client::RpcClient,
+        rpc_client::SerializableTransaction, rpc_config::RpcSendTransactionConfig,
+    },
+    solana_sdk::{commitment_config::CommitmentLevel, signature::Signature},
+    std::sync::Arc,
+};
+
+use crate::utils::send_transaction;
+
+/// The strategy used to dispatch signed transactions.
+#[derive(Debug, Clone)]
+pub enum DispatchStrategy {
+    /// Send the transaction to a single RPC endpoint.
+    Single(Arc<RpcClient>),
+    /// Send the transaction to several RPC endpoints concurrently, see [`BroadcastSender`].
+    Broadcast(BroadcastSender),
+}
+
+impl DispatchStrategy {
+    /// Dispatches the given signed transaction according to the strategy.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction could not be sent to any endpoint.
+    pub async fn send_transaction(
+        &self,
+        tx: &(impl SerializableTransaction + Sync),
+        confirm: bool,
+    ) -> Result<Signature, ClientError> {
+        match self {
+            DispatchStrategy::Single(rpc_client) => send_transaction(rpc_client, tx, confirm).await,
+            DispatchStrategy::Broadcast(sender) => {
+                if confirm {
+                    sender.send_and_confirm_transaction(tx).await
+                } else {
+                    sender.send_transaction(tx).await
+                }
+            }
+        }
+    }
+}
+
+/// A sender which broadcasts the same signed transaction to several RPC endpoints concurrently and returns on
+/// the first endpoint which accepts it.
+///
+/// Sending to a single endpoint regularly drops transactions during leader congestion, since each endpoint only
+/// forwards the transaction to the leaders through it's own connections. Since all endpoints receive the same
+/// signed transaction, it can only land once.
+#[derive(Clone)]
+pub struct BroadcastSender {
+    rpc_clients: Vec<Arc<RpcClient>>,
+    config: RpcSendTransactionConfig,
+}
+
+impl std::fmt::Debug for BroadcastSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BroadcastSender")
+            .field(
+                "endpoints",
+                &self
+                    .rpc_clients
+                    .iter()
+                    .map(|c| c.url())
+                    .collect::<Vec<String>>(),
+            )
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl BroadcastSender {
+    /// Creates a new [`BroadcastSender`] for the given RPC endpoints.
+    ///
+    /// Transactions are sent with preflight checks at the processed commitment level, like [`send_transaction`].
+    ///
+    /// ### Panics
+    ///
+    /// This function will panic if no endpoint is given.
+    pub fn new(rpc_clients: Vec<Arc<RpcClient>>) -> Self {
+        Self::new_with_config(
+            rpc_clients,
+            RpcSendTransactionConfig {
+                preflight_commitment: Some(CommitmentLevel::Processed),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Creates a new [`BroadcastSender`] for the given RPC endpoints with the given send config,
+    /// e.g. to skip preflight checks.
+    ///
+    /// ### Panics
+    ///
+    /// This function will panic if no endpoint is given.
+    pub fn new_with_config(
+        rpc_clients: Vec<Arc<RpcClient>>,
+        config: RpcSendTransactionConfig,
+    ) -> Self {
+        assert!(
+            !rpc_clients.is_empty(),
+            "BroadcastSender requires at least one endpoint"
+        );
+        Self {
+            rpc_clients,
+            config,
+        }
+    }
+
+    /// Creates a new [`BroadcastSender`] for the given RPC endpoint urls.
+    ///
+    /// ### Panics
+    ///
+    /// This function will panic if no url is given.
+    pub fn from_urls(urls: &[String]) -> Self {
+        Self::new(
+            urls.iter()
+                .map(|u| Arc::new(RpcClient::new(u.to_string())))
+                .collect(),
+        )
+    }
+
+    /// Gets the urls of the endpoints.
+    pub fn endpoints(&self) -> Vec<String> {
+        self.rpc_clients.iter().map(|c| c.url()).collect()
+    }
+
+    /// Gets the first endpoint, which can be used for requests other than sending transactions.
+    pub fn primary(&self) -> &Arc<RpcClient> {
+        &self.rpc_clients[0]
+    }
+
+    /// Sends the given signed transaction to every endpoint concurrently, returning the signature once the first
+    /// endpoint accepts it.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return the last error if every endpoint failed to accept the transaction.
+    pub async fn send_transaction(
+        &self,
+        tx: &(impl SerializableTransaction + Sync),
+    ) -> Result<Signature, ClientError> {
+        let config = self.config;
+        self.broadcast(move |rpc_client| {
+            async move { rpc_client.send_transaction_with_config(tx, config).await }.boxed()
+        })
+        .await
+    }
+
+    /// Sends the given signed transaction to every endpoint concurrently, returning the signature once the first
+    /// endpoint confirms it.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return the last error if no endpoint could confirm the transaction.
+    pub async fn send_and_confirm_transaction(
+        &self,
+        tx: &(impl SerializableTransaction + Sync),
+    ) -> Result<Signature, ClientError> {
+        self.broadcast(move |rpc_client| {
+            async move { rpc_client.send_and_confirm_transaction(tx).await }.boxed()
+        })
+        .await
+    }
+
+    async fn broadcast<'a, F>(&'a self, send: F) -> Result<Signature, ClientError>
+    where
+        F: Fn(&'a RpcClient) -> BoxFuture<'a, Result<Signature, ClientError>>,
+    {
+        let futures = self
+            .rpc_clients
+            .iter()
+            .enumerate()
+            .map(|(i, rpc_client)| {
+                let url = rpc_client.url();
+                send(rpc_client.as_ref())
+                    .map(move |res| {
+                        if let Err(e) = &res {
+                            warn!(
+                                "Endpoint {} ({}) failed to send transaction: {}",
+                                i,
+                                url,
+                                e.to_string()
+                            );
+                        }
+                        res
+                    })
+                    .boxed()
+            })
+            .collect::<Vec<_>>();
+
+        match select_ok(futures).await {
+            Ok((signature, _)) => Ok(signature),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,591 @@
+use {
+    crate::{contexts::ReloadPolicy, cranks::CrankConfig, oms::RiskLimits, utils::encode_string},
+    cypher_client::utils::derive_market_address,
+    log::{info, warn},
+    serde::{Deserialize, Serialize},
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::HashSet,
+        fs,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    },
+    thiserror::Error,
+    tokio::sync::{
+        broadcast::{channel, Receiver, Sender},
+        RwLock,
+    },
+};
+
+/// The number of basis points in one unit.
+const BPS_UNIT: u64 = 10_000;
+
+/// The maximum compute units that can be requested by a transaction.
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// The default interval at which the [`ConfigWatcher`] checks the file for changes.
+pub const DEFAULT_CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// The RPC endpoints used by a service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcConfig {
+    /// The HTTP endpoint.
+    pub http_url: String,
+    /// The WebSocket endpoint, used by the subscription services.
+    #[serde(default)]
+    pub ws_url: Option<String>,
+    /// Additional HTTP endpoints transactions are also sent to.
+    #[serde(default)]
+    pub fallback_http_urls: Vec<String>,
+}
+
+impl RpcConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        for url in std::iter::once(&self.http_url).chain(self.fallback_http_urls.iter()) {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(invalid(format!("invalid HTTP endpoint {}", url)));
+            }
+        }
+        if let Some(url) = &self.ws_url {
+            if !url.starts_with("ws://") && !url.starts_with("wss://") {
+                return Err(invalid(format!("invalid WebSocket endpoint {}", url)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The transaction fee budget of a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeBudgetConfig {
+    /// The compute units requested by each transaction.
+    pub compute_unit_limit: u32,
+    /// The compute unit price of each transaction, in micro lamports.
+    pub compute_unit_price: u64,
+    /// The maximum fees spent per UTC day, in lamports.
+    #[serde(default)]
+    pub max_daily_fees: Option<u64>,
+}
+
+impl Default for FeeBudgetConfig {
+    fn default() -> Self {
+        Self {
+            compute_unit_limit: 200_000,
+            compute_unit_price: 1,
+            max_daily_fees: None,
+        }
+    }
+}
+
+impl FeeBudgetConfig {
+    /// Gets the compute unit limit and price, as expected by [`send_transactions`](crate::utils::send_transactions).
+    pub fn get_compute_unit_info(&self) -> (u32, u64) {
+        (self.compute_unit_limit, self.compute_unit_price)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.compute_unit_limit == 0 || self.compute_unit_limit > MAX_COMPUTE_UNIT_LIMIT {
+            return Err(invalid(format!(
+                "compute unit limit {} must be between 1 and {}",
+                self.compute_unit_limit, MAX_COMPUTE_UNIT_LIMIT
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The kind of a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketKind {
+    Perpetual,
+    Futures,
+    Spot,
+}
+
+/// The quoting parameters of a market.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotingMarketConfig {
+    /// The name of the market.
+    pub name: String,
+    /// The kind of market.
+    pub kind: MarketKind,
+    /// The address of the market, required for spot markets whose address can not be derived from their name.
+    #[serde(default)]
+    pub address: Option<String>,
+    /// The base quantity of each order, in lots.
+    pub order_size: u64,
+    /// The maximum absolute base position, in lots.
+    pub max_position: u64,
+    /// The spread around the reference price of the innermost orders, in bps.
+    pub spread_bps: u64,
+    /// The number of orders quoted on each side.
+    #[serde(default = "default_layers")]
+    pub layers: u8,
+    /// The spacing between consecutive orders on the same side, in bps.
+    #[serde(default)]
+    pub layer_spacing_bps: u64,
+    /// The maximum deviation of an order's limit price from the reference price, in bps.
+    #[serde(default)]
+    pub price_collar_bps: Option<u64>,
+}
+
+fn default_layers() -> u8 {
+    1
+}
+
+impl QuotingMarketConfig {
+    /// Gets the address of the market, either the configured one or the one derived from its name.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the configured address is invalid or the market is a spot market
+    /// without a configured address.
+    pub fn get_address(&self) -> Result<Pubkey, ConfigError> {
+        match &self.address {
+            Some(a) => match Pubkey::from_str(a) {
+                Ok(pk) => Ok(pk),
+                Err(_) => Err(invalid(format!(
+                    "invalid address {} for market {}",
+                    a, self.name
+                ))),
+            },
+            None if self.kind == MarketKind::Spot => Err(invalid(format!(
+                "spot market {} requires an address",
+                self.name
+            ))),
+            None => Ok(derive_market_address(&encode_string(&self.name)).0),
+        }
+    }
+
+    /// Gets the [`RiskLimits`] implied by the quoting parameters.
+    pub fn get_risk_limits(&self) -> RiskLimits {
+        RiskLimits {
+            max_order_base_qty: Some(self.order_size),
+            max_position: Some(self.max_position),
+            price_collar_bps: self.price_collar_bps,
+            ..Default::default()
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.get_address()?;
+        if self.order_size == 0 {
+            return Err(invalid(format!(
+                "order size of {} must be positive",
+                self.name
+            )));
+        }
+        if self.max_position < self.order_size {
+            return Err(invalid(format!(
+                "max position of {} must be at least the order size",
+                self.name
+            )));
+        }
+        if self.spread_bps == 0 || self.spread_bps >= BPS_UNIT {
+            return Err(invalid(format!(
+                "spread of {} must be between 1 and {} bps",
+                self.name,
+                BPS_UNIT - 1
+            )));
+        }
+        if self.layers == 0 {
+            return Err(invalid(format!(
+                "{} must quote at least one layer",
+                self.name
+            )));
+        }
+        if self
+            .layer_spacing_bps
+            .saturating_mul(self.layers as u64 - 1)
+            .saturating_add(self.spread_bps)
+            >= BPS_UNIT
+        {
+            return Err(invalid(format!(
+                "outermost layer of {} is further than {} bps from the reference price",
+                self.name, BPS_UNIT
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of a market maker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketMakerConfig {
+    /// The number of the sub account used to quote.
+    #[serde(default)]
+    pub sub_account_number: u8,
+    /// The interval between quote updates, in milliseconds.
+    pub quote_interval_ms: u64,
+    /// The quoted markets.
+    pub markets: Vec<QuotingMarketConfig>,
+}
+
+impl MarketMakerConfig {
+    /// Gets the quote update interval.
+    pub fn get_quote_interval(&self) -> Duration {
+        Duration::from_millis(self.quote_interval_ms)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.quote_interval_ms == 0 {
+            return Err(invalid("quote interval must be positive".to_string()));
+        }
+        let mut names = HashSet::new();
+        for market in self.markets.iter() {
+            if !names.insert(&market.name) {
+                return Err(invalid(format!("market {} is quoted twice", market.name)));
+            }
+            market.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of a cranker, see [`CrankConfig`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrankerConfig {
+    /// The interval at which oracle prices are cached, in milliseconds, the crank is disabled if not set.
+    #[serde(default)]
+    pub oracle_interval_ms: Option<u64>,
+    /// The interval at which funding rates are updated, in milliseconds, the crank is disabled if not set.
+    #[serde(default)]
+    pub funding_interval_ms: Option<u64>,
+    /// The interval at which event queues are consumed, in milliseconds, the crank is disabled if not set.
+    #[serde(default)]
+    pub events_interval_ms: Option<u64>,
+    /// The maximum number of events consumed per instruction.
+    pub events_limit: u16,
+    /// The time after which an events crank which has not landed is presumed dropped, in milliseconds.
+    #[serde(default = "default_events_in_flight_timeout_ms")]
+    pub events_in_flight_timeout_ms: u64,
+    /// The interval at which the sub account caches of the margin accounts are refreshed if stale, in milliseconds,
+    /// the crank is disabled if not set.
+    #[serde(default)]
+    pub margin_interval_ms: Option<u64>,
+    /// The master accounts whose sub account caches are kept fresh.
+    #[serde(default)]
+    pub margin_accounts: Vec<String>,
+    /// The maximum age of a sub account cache relative to the most recent cache price update, in seconds.
+    #[serde(default = "default_margin_max_age_secs")]
+    pub margin_max_age_secs: u64,
+    /// The interval at which the crank targets are discovered again, in milliseconds.
+    #[serde(default)]
+    pub discovery_interval_ms: Option<u64>,
+}
+
+fn default_events_in_flight_timeout_ms() -> u64 {
+    60_000
+}
+
+fn default_margin_max_age_secs() -> u64 {
+    60
+}
+
+impl CrankerConfig {
+    /// Gets the [`CrankConfig`] with the given fee budget.
+    pub fn get_crank_config(&self, fees: &FeeBudgetConfig) -> CrankConfig {
+        CrankConfig {
+            oracle_interval: self.oracle_interval_ms.map(Duration::from_millis),
+            funding_interval: self.funding_interval_ms.map(Duration::from_millis),
+            events_interval: self.events_interval_ms.map(Duration::from_millis),
+            events_limit: self.events_limit,
+            events_in_flight_timeout: Duration::from_millis(self.events_in_flight_timeout_ms),
+            margin_interval: self.margin_interval_ms.map(Duration::from_millis),
+            // the accounts are checked when the configuration is validated
+            margin_accounts: self
+                .margin_accounts
+                .iter()
+                .filter_map(|a| Pubkey::from_str(a).ok())
+                .collect(),
+            margin_max_age: self.margin_max_age_secs,
+            margin_reload_policy: ReloadPolicy::default(),
+            discovery_interval: self.discovery_interval_ms.map(Duration::from_millis),
+            compute_unit_price: fees.compute_unit_price,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.events_interval_ms.is_some() && self.events_limit == 0 {
+            return Err(invalid("events limit must be positive".to_string()));
+        }
+        if self.events_interval_ms.is_some() && self.events_in_flight_timeout_ms == 0 {
+            return Err(invalid(
+                "events in flight timeout must be positive".to_string(),
+            ));
+        }
+        let intervals = [
+            self.oracle_interval_ms,
+            self.funding_interval_ms,
+            self.events_interval_ms,
+            self.margin_interval_ms,
+            self.discovery_interval_ms,
+        ];
+        if intervals.iter().any(|i| *i == Some(0)) {
+            return Err(invalid("crank intervals must be positive".to_string()));
+        }
+        for account in self.margin_accounts.iter() {
+            if Pubkey::from_str(account).is_err() {
+                return Err(invalid(format!("invalid margin account {}", account)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of a liquidator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquidatorConfig {
+    /// The number of the sub account used to liquidate.
+    #[serde(default)]
+    pub sub_account_number: u8,
+    /// The interval at which accounts are checked, in milliseconds.
+    pub check_interval_ms: u64,
+    /// The minimum expected profit of a liquidation, in native quote units.
+    #[serde(default)]
+    pub min_profit: u64,
+    /// The markets in which positions are liquidated, all markets if empty.
+    #[serde(default)]
+    pub markets: Vec<String>,
+}
+
+impl LiquidatorConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.check_interval_ms == 0 {
+            return Err(invalid("check interval must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of the market maker, crank and liquidator services.
+///
+/// Services which are not configured are not run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    /// The RPC endpoints.
+    pub rpc: RpcConfig,
+    /// The path of the signer's keypair file.
+    pub keypair_path: String,
+    /// The transaction fee budget.
+    #[serde(default)]
+    pub fees: FeeBudgetConfig,
+    /// The market maker configuration.
+    #[serde(default)]
+    pub market_maker: Option<MarketMakerConfig>,
+    /// The cranker configuration.
+    #[serde(default)]
+    pub cranker: Option<CrankerConfig>,
+    /// The liquidator configuration.
+    #[serde(default)]
+    pub liquidator: Option<LiquidatorConfig>,
+}
+
+impl ServiceConfig {
+    /// Loads and validates the [`ServiceConfig`] from the given JSON file.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be read, is not valid JSON
+    /// or the configuration is invalid.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(ConfigError::Io(e));
+            }
+        };
+        contents.parse::<Self>()
+    }
+
+    /// Saves the [`ServiceConfig`] to the given file as JSON.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be written.
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let contents = match serde_json::to_string_pretty(self) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(ConfigError::Json(e));
+            }
+        };
+        match fs::write(path, contents) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(ConfigError::Io(e)),
+        }
+    }
+
+    /// Validates the configuration.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error describing the first invalid parameter.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.keypair_path.is_empty() {
+            return Err(invalid("keypair path must be set".to_string()));
+        }
+        self.rpc.validate()?;
+        self.fees.validate()?;
+        if let Some(market_maker) = &self.market_maker {
+            market_maker.validate()?;
+        }
+        if let Some(cranker) = &self.cranker {
+            cranker.validate()?;
+        }
+        if let Some(liquidator) = &self.liquidator {
+            liquidator.validate()?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ServiceConfig {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config = match serde_json::from_str::<Self>(s) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(ConfigError::Json(e));
+            }
+        };
+        match config.validate() {
+            Ok(()) => Ok(config),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn invalid(reason: String) -> ConfigError {
+    ConfigError::Invalid(reason)
+}
+
+/// Watches a [`ServiceConfig`] file and reloads it when it changes.
+///
+/// Reloaded configurations are only applied if they are valid, otherwise the previous configuration is kept.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    config: RwLock<Arc<ServiceConfig>>,
+    poll_interval: Duration,
+    sender: Arc<Sender<Arc<ServiceConfig>>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for ConfigWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigWatcher")
+            .field("path", &self.path)
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
+
+impl ConfigWatcher {
+    /// Creates a new [`ConfigWatcher`], loading the configuration from the given file.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the initial configuration can not be loaded.
+    pub fn new(
+        path: &Path,
+        poll_interval: Duration,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Result<Self, ConfigError> {
+        let config = match ServiceConfig::load(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            config: RwLock::new(Arc::new(config)),
+            poll_interval,
+            sender: Arc::new(channel::<Arc<ServiceConfig>>(16).0),
+            shutdown,
+        })
+    }
+
+    /// Gets the current configuration.
+    pub async fn get_config(&self) -> Arc<ServiceConfig> {
+        self.config.read().await.clone()
+    }
+
+    /// Subscribes to configuration reloads.
+    pub fn subscribe(&self) -> Receiver<Arc<ServiceConfig>> {
+        self.sender.subscribe()
+    }
+
+    /// Starts the service's work cycle.
+    /// Checks the file for changes at the poll interval and reloads it until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut last_modified = self.get_modified();
+        let mut interval = tokio::time::interval(self.poll_interval);
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let modified = self.get_modified();
+                    if modified.is_some() && modified != last_modified {
+                        last_modified = modified;
+                        self.reload().await;
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down config watcher.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Reloads the configuration from the file, applying and broadcasting it if it is valid and has changed.
+    pub async fn reload(&self) {
+        let config = match ServiceConfig::load(&self.path) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!(
+                    "Failed to reload configuration from {}, keeping the previous one: {}",
+                    self.path.display(),
+                    e.to_string()
+                );
+                return;
+            }
+        };
+        let config = Arc::new(config);
+        {
+            let mut current = self.config.write().await;
+            if **current == *config {
+                return;
+            }
+            *current = config.clone();
+        }
+        info!("Reloaded configuration from {}.", self.path.display());
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(config);
+        }
+    }
+
+    fn get_modified(&self) -> Option<SystemTime> {
+        match fs::metadata(&self.path) {
+            Ok(m) => m.modified().ok(),
+            Err(_) => None,
+        }
+    }
+}
@@ -1,9 +1,12 @@
-use cypher_client::{cache_account, utils::get_zero_copy_account, Cache, CacheAccount};
-use solana_client::nonblocking::rpc_client::RpcClient;
+use cypher_client::{
+    cache_account, constants::CACHES_MAX_CNT, utils::get_zero_copy_account, Cache, CacheAccount,
+};
 use solana_sdk::pubkey::Pubkey;
 use std::{fmt::Debug, sync::Arc};
 
-use crate::{accounts_cache::AccountsCache, utils::get_cypher_zero_copy_account};
+use crate::{
+    accounts_cache::AccountsCache, transport::AccountFetcher, utils::get_cypher_zero_copy_account,
+};
 
 use super::ContextError;
 
@@ -25,7 +28,7 @@ impl Default for CacheContext {
         Self {
             state: Box::new(CacheAccount {
                 authority: Pubkey::default(),
-                caches: [Cache::default(); 512],
+                caches: [Cache::default(); CACHES_MAX_CNT],
             }),
         }
     }
@@ -38,7 +41,7 @@ impl CacheContext {
     }
 
     /// Loads the cache account.
-    pub async fn load(rpc_client: &Arc<RpcClient>) -> Result<Self, ContextError> {
+    pub async fn load(rpc_client: &impl AccountFetcher) -> Result<Self, ContextError> {
         match get_cypher_zero_copy_account::<CacheAccount>(rpc_client, &cache_account::id()).await {
             Ok(s) => Ok(Self::new(s)),
             Err(e) => Err(ContextError::ClientError(e)),
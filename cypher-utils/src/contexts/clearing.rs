@@ -0,0 +1,9 @@
+use cypher_client::Clearing;
+
+use super::macros::account_context;
+
+account_context!(
+    /// Represents a [`Clearing`].
+    ClearingContext,
+    Clearing
+);
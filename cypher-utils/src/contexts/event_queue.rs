@@ -4,16 +4,16 @@ use anchor_spl::dex::serum_dex::{
     state::{Event, EventView, QueueHeader},
 };
 use cypher_client::{
-    aob::{parse_aob_event_queue, CallBackInfo},
-    serum::{parse_dex_event_queue, remove_dex_account_padding},
+    aob::{get_event_callback_infos, parse_aob_event_queue_since, CallBackInfo},
+    serum::{parse_dex_event_queue, parse_dex_event_queue_since, remove_dex_account_padding},
+    utils::derive_orders_account_address,
     Side,
 };
 use num_traits::cast::FromPrimitive;
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use crate::accounts_cache::AccountsCache;
+use crate::{accounts_cache::AccountsCache, transport::AccountFetcher};
 
 use super::ContextError;
 
@@ -30,31 +30,92 @@ pub struct Fill {
     pub taker_side: Side,
     /// The maker order id.
     pub maker_order_id: u128,
+    /// The maker's orders account, only known for AOB fills with the event's callback info.
+    pub maker: Option<Pubkey>,
+    /// The taker's orders account, only known for AOB fills with the event's callback info.
+    pub taker: Option<Pubkey>,
 }
 
 /// A trait that can be used to generically get data for both AOB and Serum Event Queues.
+///
+/// The events held by an Event Queue context are ordered from oldest to newest and the newest event
+/// has the sequence number `seq_num - 1`, which allows consumers to only process each event once:
+///
+/// ```ignore
+/// let fills = event_queue.get_fills_since(last_seq_num);
+/// last_seq_num = event_queue.get_seq_num();
+/// ```
 pub trait GenericEventQueue: Send + Sync {
+    /// Gets the sequence number of the Event Queue, i.e. the number of events ever pushed to it,
+    /// which is the sequence number the next event will have.
+    fn get_seq_num(&self) -> u64;
+
+    /// Gets the slot at which the Event Queue's account state was last loaded from the [`AccountsCache`],
+    /// which is the slot at which it's newest fills were observed, or zero if it is not known.
+    fn get_slot(&self) -> u64;
+
+    /// Gets the fills of the events with a sequence number greater than or equal to the given one.
+    ///
+    /// Events which were pushed after the given sequence number but are no longer held by the context,
+    /// e.g. because they were consumed in between reloads, can not be recovered.
+    fn get_fills_since(&self, seq_num: u64) -> Vec<Fill>;
+
     /// Gets the fills in the Event Queue.
-    fn get_fills(&self) -> Vec<Fill>;
+    fn get_fills(&self) -> Vec<Fill> {
+        self.get_fills_since(0)
+    }
+}
+
+/// Gets the index of the first held event with a sequence number greater than or equal to the given one,
+/// given the Event Queue's sequence number and the number of held events, which are the newest events.
+fn get_first_event_idx(queue_seq_num: u64, events_len: usize, seq_num: u64) -> usize {
+    let first_seq_num = queue_seq_num.saturating_sub(events_len as u64);
+    (seq_num.saturating_sub(first_seq_num) as usize).min(events_len)
 }
 
 /// Represents an AOB Event Queue.
+///
+/// The events and callback infos are ordered from oldest to newest, with the callback infos interleaved
+/// as described in [`parse_aob_event_queue_since`].
 #[derive(Default)]
 pub struct AgnosticEventQueueContext {
     pub market: Pubkey,
     pub event_queue: Pubkey,
     pub count: u64,
     pub head: u64,
+    pub seq_num: u64,
+    pub slot: u64,
     pub events: Vec<FillEvent>,
     pub callbacks: Vec<CallBackInfo>,
 }
 
 impl GenericEventQueue for AgnosticEventQueueContext {
-    fn get_fills(&self) -> Vec<Fill> {
+    fn get_seq_num(&self) -> u64 {
+        self.seq_num
+    }
+
+    fn get_slot(&self) -> u64 {
+        self.slot
+    }
+
+    fn get_fills_since(&self, seq_num: u64) -> Vec<Fill> {
         let events = &self.events;
+        let first_idx = get_first_event_idx(self.seq_num, events.len(), seq_num);
         let mut fills = Vec::new();
+        // deriving the orders account is expensive and the same accounts usually fill repeatedly
+        let mut orders_accounts: HashMap<Pubkey, Pubkey> = HashMap::new();
+        let mut get_orders_account =
+            |callback_info: &CallBackInfo| {
+                if callback_info.is_empty() {
+                    return None;
+                }
+                let (master_account, _) = callback_info.owner();
+                Some(*orders_accounts.entry(master_account).or_insert_with(|| {
+                    derive_orders_account_address(&self.market, &master_account).0
+                }))
+            };
 
-        for event in events.iter() {
+        for (idx, event) in events.iter().enumerate().skip(first_idx) {
             if event.maker_order_id != u128::default()
                 && event.base_size != 0
                 && event.quote_size != 0
@@ -65,12 +126,18 @@ impl GenericEventQueue for AgnosticEventQueueContext {
                 } else {
                     Side::Bid
                 };
+                let (maker, taker) = match get_event_callback_infos(&self.callbacks, idx) {
+                    Some((maker, taker)) => (get_orders_account(maker), get_orders_account(taker)),
+                    None => (None, None),
+                };
                 fills.push(Fill {
                     base_quantity: event.base_size,
                     quote_quantity: event.quote_size,
                     price: event.quote_size / event.base_size,
                     taker_side,
                     maker_order_id: event.maker_order_id,
+                    maker,
+                    taker,
                 });
             }
         }
@@ -94,6 +161,8 @@ impl AgnosticEventQueueContext {
             event_queue: *event_queue,
             count,
             head,
+            seq_num: 0,
+            slot: 0,
             events,
             callbacks,
         }
@@ -106,7 +175,7 @@ impl AgnosticEventQueueContext {
     /// This function will return an error if something goes wrong during the RPC request
     /// or the [`Pubkey`]s given are not valid AOB Event Queue Accounts.
     pub async fn load(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         market: &Pubkey,
         event_queue: &Pubkey,
     ) -> Result<Self, ContextError> {
@@ -116,15 +185,7 @@ impl AgnosticEventQueueContext {
                 return Err(ContextError::ClientError(e));
             }
         };
-        let (eq_header, fills, callbacks) = parse_aob_event_queue(&account_data);
-        Ok(Self::new(
-            market,
-            event_queue,
-            eq_header.count,
-            eq_header.head,
-            fills.to_vec(),
-            callbacks.to_vec(),
-        ))
+        Ok(Self::from_account_data(market, event_queue, &account_data))
     }
 
     /// Loads the [`AgnosticEventQueueContext`] from the given account data.
@@ -133,16 +194,20 @@ impl AgnosticEventQueueContext {
     ///
     /// This function will return an error if the account state does not exist in the cache.
     pub fn from_account_data(market: &Pubkey, event_queue: &Pubkey, data: &[u8]) -> Self {
-        let (eq_header, fills, callbacks) = parse_aob_event_queue(data);
-
-        Self::new(
-            market,
-            event_queue,
-            eq_header.count,
-            eq_header.head,
-            fills.to_vec(),
-            callbacks.to_vec(),
-        )
+        // the queue is a ring buffer, parsing the events since the first one orders them from oldest to newest
+        let (eq_header, fills, callbacks) = parse_aob_event_queue_since(data, 0);
+
+        Self {
+            seq_num: eq_header.seq_num,
+            ..Self::new(
+                market,
+                event_queue,
+                eq_header.count,
+                eq_header.head,
+                fills,
+                callbacks,
+            )
+        }
     }
 
     /// Loads the [`AgnosticEventQueueContext`] from the given [`AccountsCache`],
@@ -163,16 +228,10 @@ impl AgnosticEventQueueContext {
             }
         };
 
-        let (eq_header, fills, callbacks) = parse_aob_event_queue(&eq_state.data);
-
-        Ok(Self::new(
-            market,
-            event_queue,
-            eq_header.count,
-            eq_header.head,
-            fills.to_vec(),
-            callbacks.to_vec(),
-        ))
+        Ok(Self {
+            slot: eq_state.slot,
+            ..Self::from_account_data(market, event_queue, &eq_state.data)
+        })
     }
 
     /// Reloads the [`AgnosticEventQueueContext`] from the given account data.
@@ -181,12 +240,13 @@ impl AgnosticEventQueueContext {
     ///
     /// This function will return an error if the account state does not exist in the cache.
     pub fn reload_from_account_data(&mut self, data: &[u8]) {
-        let (eq_header, new_fills, new_callbacks) = parse_aob_event_queue(data);
+        let (eq_header, new_fills, new_callbacks) = parse_aob_event_queue_since(data, 0);
 
         self.count = eq_header.count;
         self.head = eq_header.head;
-        self.callbacks = new_callbacks.to_vec();
-        self.events = new_fills.to_vec();
+        self.seq_num = eq_header.seq_num;
+        self.callbacks = new_callbacks;
+        self.events = new_fills;
     }
 
     /// Reloads the [`AgnosticEventQueueContext`] from the given [`AccountsCache`],
@@ -203,33 +263,92 @@ impl AgnosticEventQueueContext {
             }
         };
 
-        let (eq_header, new_fills, new_callbacks) = parse_aob_event_queue(&eq_state.data);
+        self.reload_from_account_data(&eq_state.data);
+        self.slot = eq_state.slot;
+
+        Ok(())
+    }
+
+    /// Reloads the [`AgnosticEventQueueContext`] from the given account data, only decoding
+    /// the events that were pushed to the queue since the previous reload.
+    ///
+    /// After this call `events` and `callbacks` only hold the new events, ordered from oldest to newest,
+    /// so the fills given by [`GenericEventQueue::get_fills`] are the fills since the previous reload.
+    /// Consumers which reload with [`AgnosticEventQueueContext::reload_from_account_data`] should use
+    /// [`GenericEventQueue::get_fills_since`] instead.
+    ///
+    /// Returns the number of events that were pushed and consumed in between reloads and could not be decoded.
+    pub fn reload_new_events_from_account_data(&mut self, data: &[u8]) -> u64 {
+        let (eq_header, new_fills, new_callbacks) = parse_aob_event_queue_since(data, self.seq_num);
+        let missed = eq_header
+            .seq_num
+            .saturating_sub(self.seq_num)
+            .saturating_sub(new_fills.len() as u64);
 
         self.count = eq_header.count;
         self.head = eq_header.head;
-        self.callbacks = new_callbacks.to_vec();
-        self.events = new_fills.to_vec();
+        self.seq_num = eq_header.seq_num;
+        self.callbacks = new_callbacks;
+        self.events = new_fills;
 
-        Ok(())
+        missed
+    }
+
+    /// Reloads the [`AgnosticEventQueueContext`] from the given [`AccountsCache`], only decoding
+    /// the events that were pushed to the queue since the previous reload.
+    ///
+    /// See [`AgnosticEventQueueContext::reload_new_events_from_account_data`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account state does not exist in the cache.
+    pub fn reload_new_events_from_cache(
+        &mut self,
+        cache: Arc<AccountsCache>,
+    ) -> Result<u64, ContextError> {
+        let eq_state = match cache.get(&self.event_queue) {
+            Some(a) => a,
+            None => {
+                return Err(ContextError::MissingAccountState);
+            }
+        };
+
+        let missed = self.reload_new_events_from_account_data(&eq_state.data);
+        self.slot = eq_state.slot;
+
+        Ok(missed)
     }
 }
 
 /// Represents a Serum Event Queue.
+///
+/// The events are ordered from oldest to newest.
 #[derive(Default)]
 pub struct SerumEventQueueContext {
     pub market: Pubkey,
     pub event_queue: Pubkey,
     pub count: u64,
     pub head: u64,
+    pub seq_num: u64,
+    pub slot: u64,
     pub events: Vec<Event>,
 }
 
 impl GenericEventQueue for SerumEventQueueContext {
-    fn get_fills(&self) -> Vec<Fill> {
+    fn get_seq_num(&self) -> u64 {
+        self.seq_num
+    }
+
+    fn get_slot(&self) -> u64 {
+        self.slot
+    }
+
+    fn get_fills_since(&self, seq_num: u64) -> Vec<Fill> {
         let events = &self.events;
+        let first_idx = get_first_event_idx(self.seq_num, events.len(), seq_num);
         let mut fills = Vec::new();
 
-        for event in events.iter() {
+        for event in events.iter().skip(first_idx) {
             match event.as_view() {
                 Ok(a) => {
                     match a {
@@ -273,6 +392,8 @@ impl GenericEventQueue for SerumEventQueueContext {
                                     price: quote_quantity / base_quantity,
                                     taker_side,
                                     maker_order_id: order_id,
+                                    maker: None,
+                                    taker: None,
                                 });
                             }
                         }
@@ -301,6 +422,8 @@ impl SerumEventQueueContext {
             event_queue: *event_queue,
             count,
             head,
+            seq_num: 0,
+            slot: 0,
             events,
         }
     }
@@ -312,7 +435,7 @@ impl SerumEventQueueContext {
     /// This function will return an error if something goes wrong during the RPC request
     /// or the [`Pubkey`]s given are not valid Serum Event Queue Accounts.
     pub async fn load(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         market: &Pubkey,
         event_queue: &Pubkey,
     ) -> Result<Self, ContextError> {
@@ -323,15 +446,19 @@ impl SerumEventQueueContext {
             }
         };
         let data_words = remove_dex_account_padding(&account_data);
+        let seq_num = get_dex_event_queue_seq_num(&data_words);
         let (header, seg0, seg1) = parse_dex_event_queue(&data_words);
 
-        Ok(Self::new(
-            market,
-            event_queue,
-            header.count(),
-            header.head(),
-            [seg0, seg1].concat(),
-        ))
+        Ok(Self {
+            seq_num,
+            ..Self::new(
+                market,
+                event_queue,
+                header.count(),
+                header.head(),
+                [seg0, seg1].concat(),
+            )
+        })
     }
 
     /// Loads the [`SerumEventQueueContext`] from the given account data.
@@ -341,15 +468,19 @@ impl SerumEventQueueContext {
     /// This function will return an error if the account state does not exist in the cache.
     pub fn from_account_data(market: &Pubkey, event_queue: &Pubkey, data: &[u8]) -> Self {
         let data_words = remove_dex_account_padding(data);
+        let seq_num = get_dex_event_queue_seq_num(&data_words);
         let (header, seg0, seg1) = parse_dex_event_queue(&data_words);
 
-        Self::new(
-            market,
-            event_queue,
-            header.count(),
-            header.head(),
-            [seg0, seg1].concat(),
-        )
+        Self {
+            seq_num,
+            ..Self::new(
+                market,
+                event_queue,
+                header.count(),
+                header.head(),
+                [seg0, seg1].concat(),
+            )
+        }
     }
 
     /// Loads the [`SerumEventQueueContext`] from the given [`AccountsCache`], if the given EventQueue's
@@ -370,17 +501,22 @@ impl SerumEventQueueContext {
             }
         };
         let data_words = remove_dex_account_padding(eq_state.data.as_slice());
+        let seq_num = get_dex_event_queue_seq_num(&data_words);
         let (header, seg0, seg1) = parse_dex_event_queue(&data_words);
 
-        Ok(Self::new(
-            market,
-            event_queue,
-            header.count(),
-            header.head(),
-            [seg0, seg1].concat(),
-            // This appears to be more efficient than doing
-            // seg0.into_ter().chain(seg1.into_iter()).collect::<Vec<Event>>()
-        ))
+        Ok(Self {
+            seq_num,
+            slot: eq_state.slot,
+            ..Self::new(
+                market,
+                event_queue,
+                header.count(),
+                header.head(),
+                [seg0, seg1].concat(),
+                // This appears to be more efficient than doing
+                // seg0.into_ter().chain(seg1.into_iter()).collect::<Vec<Event>>()
+            )
+        })
     }
 
     /// Reloads the [`SerumEventQueueContext`] from the given account data.
@@ -390,10 +526,12 @@ impl SerumEventQueueContext {
     /// This function will return an error if the account state does not exist in the cache.
     pub fn reload_from_account_data(&mut self, data: &[u8]) {
         let data_words = remove_dex_account_padding(data);
+        let seq_num = get_dex_event_queue_seq_num(&data_words);
         let (header, seg0, seg1) = parse_dex_event_queue(&data_words);
 
         self.count = header.count();
         self.head = header.head();
+        self.seq_num = seq_num;
         self.events = [seg0, seg1].concat();
     }
 
@@ -412,12 +550,70 @@ impl SerumEventQueueContext {
         };
 
         let data_words = remove_dex_account_padding(eq_state.data.as_slice());
+        let seq_num = get_dex_event_queue_seq_num(&data_words);
         let (header, seg0, seg1) = parse_dex_event_queue(&data_words);
 
         self.count = header.count();
         self.head = header.head();
+        self.seq_num = seq_num;
+        self.slot = eq_state.slot;
         self.events = [seg0, seg1].concat();
 
         Ok(())
     }
+
+    /// Reloads the [`SerumEventQueueContext`] from the given account data, only decoding
+    /// the events that were pushed to the queue since the previous reload.
+    ///
+    /// After this call `events` only holds the new events, ordered from oldest to newest,
+    /// so the fills given by [`GenericEventQueue::get_fills`] are the fills since the previous reload.
+    /// Consumers which reload with [`SerumEventQueueContext::reload_from_account_data`] should use
+    /// [`GenericEventQueue::get_fills_since`] instead.
+    ///
+    /// Returns the number of events that were pushed and consumed in between reloads and could not be decoded.
+    pub fn reload_new_events_from_account_data(&mut self, data: &[u8]) -> u64 {
+        let data_words = remove_dex_account_padding(data);
+        let (header, seq_num, new_events) = parse_dex_event_queue_since(&data_words, self.seq_num);
+        let missed = seq_num
+            .saturating_sub(self.seq_num)
+            .saturating_sub(new_events.len() as u64);
+
+        self.count = header.count();
+        self.head = header.head();
+        self.seq_num = seq_num;
+        self.events = new_events;
+
+        missed
+    }
+
+    /// Reloads the [`SerumEventQueueContext`] from the given [`AccountsCache`], only decoding
+    /// the events that were pushed to the queue since the previous reload.
+    ///
+    /// See [`SerumEventQueueContext::reload_new_events_from_account_data`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account state does not exist in the cache.
+    pub fn reload_new_events_from_cache(
+        &mut self,
+        cache: Arc<AccountsCache>,
+    ) -> Result<u64, ContextError> {
+        let eq_state = match cache.get(&self.event_queue) {
+            Some(a) => a,
+            None => {
+                return Err(ContextError::MissingAccountState);
+            }
+        };
+
+        let missed = self.reload_new_events_from_account_data(&eq_state.data);
+        self.slot = eq_state.slot;
+
+        Ok(missed)
+    }
+}
+
+/// Gets the sequence number of a Serum Event Queue from it's unpadded account data.
+fn get_dex_event_queue_seq_num(data_words: &[u64]) -> u64 {
+    // the header is laid out as `account_flags`, `head`, `count` and `seq_num`
+    data_words[3]
 }
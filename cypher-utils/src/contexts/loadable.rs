@@ -0,0 +1,180 @@
+use anchor_lang::{Owner, ZeroCopy};
+use async_trait::async_trait;
+use cypher_client::OrdersAccount;
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+use crate::utils::get_cypher_zero_copy_account;
+
+use super::{CacheContext, ContextError, MarketContext, OrdersAccountContext, PoolContext};
+
+/// The maximum number of accounts per `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// An object-safe interface to load and reload contexts which are backed by a single account.
+///
+/// Service code can hold heterogeneous collections of contexts, e.g. `Vec<Box<dyn Loadable>>`, and reload
+/// all of them at once with [`reload_all`] instead of handling each context type separately.
+/// Contexts which are backed by more than one account or need other state to be reloaded, such as order books,
+/// event queues and the [`UserContext`](super::UserContext), are reloaded by the [`ContextManager`](super::ContextManager).
+#[async_trait]
+pub trait Loadable: Send + Sync {
+    /// Loads the context backed by the given account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request
+    /// or the account is not valid for the context.
+    async fn load(rpc_client: &Arc<RpcClient>, address: &Pubkey) -> Result<Self, ContextError>
+    where
+        Self: Sized;
+
+    /// Gets the address of the account backing the context.
+    fn address(&self) -> Pubkey;
+
+    /// Reloads the context's state.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError>;
+
+    /// Reloads the context's state from the given account data.
+    fn reload_from_account_data(&mut self, account_data: &[u8]);
+}
+
+/// Reloads all of the given contexts with as few `getMultipleAccounts` requests as possible.
+///
+/// Contexts whose account does not exist are left unchanged. Returns the number of reloaded contexts.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests,
+/// in which case the contexts may be partially reloaded.
+pub async fn reload_all(
+    rpc_client: &Arc<RpcClient>,
+    contexts: &mut [Box<dyn Loadable>],
+) -> Result<usize, ContextError> {
+    let keys = contexts
+        .iter()
+        .map(|c| c.address())
+        .collect::<Vec<Pubkey>>();
+    let mut reloaded = 0;
+
+    for (chunk_idx, chunk) in keys.chunks(MAX_MULTIPLE_ACCOUNTS).enumerate() {
+        let accounts = match rpc_client.get_multiple_accounts(chunk).await {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+        for (idx, account) in accounts.into_iter().enumerate() {
+            let context_idx = chunk_idx * MAX_MULTIPLE_ACCOUNTS + idx;
+            match account {
+                Some(a) => {
+                    contexts[context_idx].reload_from_account_data(&a.data);
+                    reloaded += 1;
+                }
+                None => {
+                    warn!("Account {} does not exist.", keys[context_idx]);
+                }
+            }
+        }
+    }
+
+    Ok(reloaded)
+}
+
+#[async_trait]
+impl Loadable for CacheContext {
+    async fn load(rpc_client: &Arc<RpcClient>, address: &Pubkey) -> Result<Self, ContextError> {
+        match get_cypher_zero_copy_account(rpc_client, address).await {
+            Ok(s) => Ok(Self::new(s)),
+            Err(e) => Err(ContextError::ClientError(e)),
+        }
+    }
+
+    fn address(&self) -> Pubkey {
+        cypher_client::cache_account::id()
+    }
+
+    async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        let address = Loadable::address(self);
+        self.state = match get_cypher_zero_copy_account(rpc_client, &address).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+        Ok(())
+    }
+
+    fn reload_from_account_data(&mut self, account_data: &[u8]) {
+        CacheContext::reload_from_account_data(self, account_data)
+    }
+}
+
+#[async_trait]
+impl<T> Loadable for MarketContext<T>
+where
+    T: ZeroCopy + Owner + Default + Send + Sync,
+{
+    async fn load(rpc_client: &Arc<RpcClient>, address: &Pubkey) -> Result<Self, ContextError> {
+        MarketContext::<T>::load(rpc_client, address).await
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        MarketContext::<T>::reload(self, rpc_client).await
+    }
+
+    fn reload_from_account_data(&mut self, account_data: &[u8]) {
+        MarketContext::<T>::reload_from_account_data(self, account_data)
+    }
+}
+
+#[async_trait]
+impl Loadable for PoolContext {
+    async fn load(rpc_client: &Arc<RpcClient>, address: &Pubkey) -> Result<Self, ContextError> {
+        PoolContext::load(rpc_client, address).await
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        PoolContext::reload(self, rpc_client).await
+    }
+
+    fn reload_from_account_data(&mut self, account_data: &[u8]) {
+        PoolContext::reload_from_account_data(self, account_data)
+    }
+}
+
+#[async_trait]
+impl Loadable for OrdersAccountContext {
+    async fn load(rpc_client: &Arc<RpcClient>, address: &Pubkey) -> Result<Self, ContextError> {
+        match get_cypher_zero_copy_account::<OrdersAccount>(rpc_client, address).await {
+            Ok(s) => Ok(Self::new(address, s)),
+            Err(e) => Err(ContextError::ClientError(e)),
+        }
+    }
+
+    fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        OrdersAccountContext::reload(self, rpc_client).await
+    }
+
+    fn reload_from_account_data(&mut self, account_data: &[u8]) {
+        OrdersAccountContext::reload_from_account_data(self, account_data)
+    }
+}
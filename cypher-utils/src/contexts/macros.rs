@@ -0,0 +1,211 @@
+/// Generates a context for a cypher account type which is zero-copy and backed by a single account.
+///
+/// The context holds the account's address and state, and implements the methods to load it from the RPC,
+/// an [`AccountsCache`](crate::accounts_cache::AccountsCache) or account data, along with [`Loadable`](super::Loadable).
+/// Additional attributes, e.g. derives, are applied to the generated struct.
+///
+/// ```ignore
+/// account_context!(
+///     /// Represents the [`Clearing`].
+///     #[derive(Default)]
+///     ClearingContext,
+///     Clearing
+/// );
+/// ```
+macro_rules! account_context {
+    ($(#[$meta:meta])* $name:ident, $account:ty) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        pub struct $name {
+            pub address: ::solana_sdk::pubkey::Pubkey,
+            pub state: Box<$account>,
+        }
+
+        impl ::std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("address", &format!("{}", self.address))
+                    .finish()
+            }
+        }
+
+        impl $name {
+            #[doc = concat!("Creates a new [`", stringify!($name), "`].")]
+            pub fn new(address: &::solana_sdk::pubkey::Pubkey, state: Box<$account>) -> Self {
+                Self {
+                    address: *address,
+                    state,
+                }
+            }
+
+            #[doc = concat!("Loads the [`", stringify!($account), "`] from the given account data.")]
+            pub fn from_account_data(
+                account_data: &[u8],
+                address: &::solana_sdk::pubkey::Pubkey,
+            ) -> Self {
+                let state = ::cypher_client::utils::get_zero_copy_account::<$account>(account_data);
+
+                Self::new(address, state)
+            }
+
+            #[doc = concat!("Loads the [`", stringify!($account), "`] from the given [`AccountsCache`](crate::accounts_cache::AccountsCache), if it's account state exists in the cache.")]
+            ///
+            /// ### Errors
+            ///
+            /// This function will return an error if the account state does not exist in the cache.
+            pub fn from_cache(
+                cache: ::std::sync::Arc<$crate::accounts_cache::AccountsCache>,
+                address: &::solana_sdk::pubkey::Pubkey,
+            ) -> Result<Self, $crate::contexts::ContextError> {
+                let account_state = match cache.get(address) {
+                    Some(a) => a,
+                    None => {
+                        return Err($crate::contexts::ContextError::MissingAccountState);
+                    }
+                };
+
+                Ok(Self::from_account_data(&account_state.data, address))
+            }
+
+            #[doc = concat!("Loads the given [`", stringify!($account), "`].")]
+            ///
+            /// ### Errors
+            ///
+            /// This function will return an error if something goes wrong during the RPC request,
+            /// the [`Pubkey`](solana_sdk::pubkey::Pubkey) given is not a valid account of this type or the
+            /// underlying account does not have the correct Anchor discriminator.
+            pub async fn load(
+                rpc_client: &impl $crate::transport::AccountFetcher,
+                address: &::solana_sdk::pubkey::Pubkey,
+            ) -> Result<Self, $crate::contexts::ContextError> {
+                match $crate::utils::get_cypher_zero_copy_account::<$account>(rpc_client, address)
+                    .await
+                {
+                    Ok(s) => Ok(Self::new(address, s)),
+                    Err(e) => Err($crate::contexts::ContextError::ClientError(e)),
+                }
+            }
+
+            #[doc = concat!("Loads the given [`", stringify!($account), "`]s, if they exist.")]
+            ///
+            /// ### Errors
+            ///
+            /// This function will return an error if something goes wrong during the RPC request,
+            /// the [`Pubkey`](solana_sdk::pubkey::Pubkey)s given are not valid accounts of this type or the
+            /// underlying accounts do not have the correct Anchor discriminator.
+            pub async fn load_many(
+                rpc_client: &impl $crate::transport::AccountFetcher,
+                addresses: &[::solana_sdk::pubkey::Pubkey],
+            ) -> Result<Vec<Self>, $crate::contexts::ContextError> {
+                match $crate::utils::get_multiple_cypher_zero_copy_accounts::<$account>(
+                    rpc_client, addresses,
+                )
+                .await
+                {
+                    Ok(s) => Ok(s
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, state)| Self::new(&addresses[idx], state.clone()))
+                        .collect()),
+                    Err(e) => Err($crate::contexts::ContextError::ClientError(e)),
+                }
+            }
+
+            #[doc = concat!("Loads all [`", stringify!($account), "`]s, if they exist.")]
+            ///
+            /// ### Errors
+            ///
+            /// This function will return an error if something goes wrong during the RPC request.
+            pub async fn load_all(
+                rpc_client: &::std::sync::Arc<::solana_client::nonblocking::rpc_client::RpcClient>,
+            ) -> Result<Vec<Self>, $crate::contexts::ContextError> {
+                let filters = vec![$crate::layouts::get_data_size_filter::<$account>()];
+                match $crate::utils::get_program_accounts(rpc_client, filters, &::cypher_client::id())
+                    .await
+                {
+                    Ok(s) => Ok(s
+                        .iter()
+                        .map(|state| Self::from_account_data(&state.1.data, &state.0))
+                        .collect()),
+                    Err(e) => Err($crate::contexts::ContextError::ClientError(e)),
+                }
+            }
+
+            #[doc = concat!("Reloads the [`", stringify!($account), "`]'s state.")]
+            ///
+            /// ### Errors
+            ///
+            /// This function will return an error if something goes wrong during the RPC request.
+            pub async fn reload(
+                &mut self,
+                rpc_client: &impl $crate::transport::AccountFetcher,
+            ) -> Result<(), $crate::contexts::ContextError> {
+                self.state = match $crate::utils::get_cypher_zero_copy_account::<$account>(
+                    rpc_client,
+                    &self.address,
+                )
+                .await
+                {
+                    Ok(s) => s,
+                    Err(e) => {
+                        return Err($crate::contexts::ContextError::ClientError(e));
+                    }
+                };
+                Ok(())
+            }
+
+            #[doc = concat!("Reloads the [`", stringify!($account), "`]'s state from the given account data.")]
+            pub fn reload_from_account_data(&mut self, account_data: &[u8]) {
+                self.state = ::cypher_client::utils::get_zero_copy_account::<$account>(account_data);
+            }
+
+            #[doc = concat!("Reloads the [`", stringify!($account), "`]'s state from the given [`AccountsCache`](crate::accounts_cache::AccountsCache), if it's account state exists in the cache.")]
+            ///
+            /// ### Errors
+            ///
+            /// This function will return an error if the account state does not exist in the cache.
+            pub fn reload_from_cache(
+                &mut self,
+                cache: ::std::sync::Arc<$crate::accounts_cache::AccountsCache>,
+            ) -> Result<(), $crate::contexts::ContextError> {
+                let account_state = match cache.get(&self.address) {
+                    Some(a) => a,
+                    None => {
+                        return Err($crate::contexts::ContextError::MissingAccountState);
+                    }
+                };
+
+                self.reload_from_account_data(&account_state.data);
+
+                Ok(())
+            }
+        }
+
+        #[::async_trait::async_trait]
+        impl $crate::contexts::Loadable for $name {
+            async fn load(
+                rpc_client: &::std::sync::Arc<::solana_client::nonblocking::rpc_client::RpcClient>,
+                address: &::solana_sdk::pubkey::Pubkey,
+            ) -> Result<Self, $crate::contexts::ContextError> {
+                $name::load(rpc_client, address).await
+            }
+
+            fn address(&self) -> ::solana_sdk::pubkey::Pubkey {
+                self.address
+            }
+
+            async fn reload(
+                &mut self,
+                rpc_client: &::std::sync::Arc<::solana_client::nonblocking::rpc_client::RpcClient>,
+            ) -> Result<(), $crate::contexts::ContextError> {
+                $name::reload(self, rpc_client).await
+            }
+
+            fn reload_from_account_data(&mut self, account_data: &[u8]) {
+                $name::reload_from_account_data(self, account_data)
+            }
+        }
+    };
+}
+
+pub(crate) use account_context;
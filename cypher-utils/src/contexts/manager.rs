@@ -0,0 +1,313 @@
+use cypher_client::{
+    cache_account, utils::get_zero_copy_account, Clearing, FuturesMarket, Market, PerpetualMarket,
+    Side,
+};
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{fmt::Debug, sync::Arc};
+
+use super::{
+    AgnosticOrderBookContext, CacheContext, ContextError, MarketContext, OrderBook, PoolContext,
+    UserContext,
+};
+
+/// The maximum number of accounts per `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// The context an account is reloaded into.
+#[derive(Debug, Clone, Copy)]
+enum ReloadTarget {
+    Cache,
+    Clearing,
+    Pool(usize),
+    PoolNode(usize, usize),
+    PerpetualMarket(usize),
+    FuturesMarket(usize),
+    OrderBook(usize, Side),
+    Account,
+    SubAccount,
+}
+
+/// Owns the contexts needed to operate on cypher and reloads all of them at once.
+///
+/// Instead of each context reloading independently, the [`ContextManager`] gathers the accounts of every context
+/// and fetches them with as few `getMultipleAccounts` requests as possible, all of the contexts are then
+/// reloaded from the fetched account data.
+pub struct ContextManager {
+    rpc_client: Arc<RpcClient>,
+    /// The cache account.
+    pub cache: CacheContext,
+    /// The clearing.
+    pub clearing: Pubkey,
+    /// The clearing's state.
+    pub clearing_state: Box<Clearing>,
+    /// The pools and their pool nodes.
+    pub pools: Vec<PoolContext>,
+    /// The perpetual markets.
+    pub perp_markets: Vec<MarketContext<PerpetualMarket>>,
+    /// The futures markets.
+    pub futures_markets: Vec<MarketContext<FuturesMarket>>,
+    /// The orderbooks of the perpetual and futures markets.
+    pub orderbooks: Vec<AgnosticOrderBookContext>,
+    /// The user, if one is managed.
+    pub user: Option<UserContext>,
+    /// The slot of the most recent reload.
+    pub slot: u64,
+}
+
+impl Debug for ContextManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContextManager")
+            .field("clearing", &format!("{}", self.clearing))
+            .field("pools", &self.pools.len())
+            .field("perp_markets", &self.perp_markets.len())
+            .field("futures_markets", &self.futures_markets.len())
+            .field("orderbooks", &self.orderbooks.len())
+            .field("user", &self.user)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
+impl ContextManager {
+    /// Creates a new [`ContextManager`] with the given contexts.
+    ///
+    /// An orderbook context is created for each of the given markets, the contexts are only
+    /// populated once [`ContextManager::reload`] is called.
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        clearing: &Pubkey,
+        pools: Vec<PoolContext>,
+        perp_markets: Vec<MarketContext<PerpetualMarket>>,
+        futures_markets: Vec<MarketContext<FuturesMarket>>,
+        user: Option<UserContext>,
+    ) -> Self {
+        let orderbooks = perp_markets
+            .iter()
+            .map(|m| (m.address, m.state.inner.bids, m.state.inner.asks))
+            .chain(
+                futures_markets
+                    .iter()
+                    .map(|m| (m.address, m.state.inner.bids, m.state.inner.asks)),
+            )
+            .map(|(market, bids, asks)| {
+                AgnosticOrderBookContext::new(&market, &bids, &asks, OrderBook::default())
+            })
+            .collect();
+        Self {
+            rpc_client,
+            cache: CacheContext::default(),
+            clearing: *clearing,
+            clearing_state: Box::default(),
+            pools,
+            perp_markets,
+            futures_markets,
+            orderbooks,
+            user,
+            slot: 0,
+        }
+    }
+
+    /// Loads the [`ContextManager`] with every pool, the given perpetual and futures markets and optionally
+    /// the user with the given authority and account number, then reloads all of the contexts.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests.
+    pub async fn load(
+        rpc_client: &Arc<RpcClient>,
+        clearing: &Pubkey,
+        perp_markets: &[Pubkey],
+        futures_markets: &[Pubkey],
+        authority: Option<&Pubkey>,
+        account_number: Option<u8>,
+    ) -> Result<Self, ContextError> {
+        let pools = match PoolContext::load_all(rpc_client).await {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let perp_markets =
+            match MarketContext::<PerpetualMarket>::load_many(rpc_client, perp_markets).await {
+                Ok(m) => m,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let futures_markets =
+            match MarketContext::<FuturesMarket>::load_many(rpc_client, futures_markets).await {
+                Ok(m) => m,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let user = match authority {
+            Some(authority) => match UserContext::load(rpc_client, authority, account_number).await
+            {
+                Ok(u) => Some(u),
+                Err(e) => {
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+
+        let mut manager = Self::new(
+            Arc::clone(rpc_client),
+            clearing,
+            pools,
+            perp_markets,
+            futures_markets,
+            user,
+        );
+        match manager.reload().await {
+            Ok(_) => Ok(manager),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Gets the accounts which are fetched on every reload, in the order they are fetched.
+    pub fn get_accounts(&self) -> Vec<Pubkey> {
+        self.get_reload_targets()
+            .iter()
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    /// Gets the orderbook of the given market, if it is managed.
+    pub fn get_orderbook(&self, market: &Pubkey) -> Option<&AgnosticOrderBookContext> {
+        self.orderbooks.iter().find(|ob| ob.market == *market)
+    }
+
+    /// Reloads every context with one `getMultipleAccounts` request per [`MAX_MULTIPLE_ACCOUNTS`] accounts.
+    ///
+    /// Sub accounts which were created since the previous reload are picked up from the account's state
+    /// on the following reload. Returns the slot at which the accounts were fetched.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests,
+    /// in which case the contexts may be partially reloaded.
+    pub async fn reload(&mut self) -> Result<u64, ContextError> {
+        let targets = self.get_reload_targets();
+        let keys = targets.iter().map(|(key, _)| *key).collect::<Vec<Pubkey>>();
+        let mut slot = 0;
+
+        for (chunk_idx, chunk) in keys.chunks(MAX_MULTIPLE_ACCOUNTS).enumerate() {
+            let res = match self
+                .rpc_client
+                .get_multiple_accounts_with_commitment(chunk, self.rpc_client.commitment())
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    return Err(ContextError::ClientError(e));
+                }
+            };
+            slot = slot.max(res.context.slot);
+            for (idx, account) in res.value.into_iter().enumerate() {
+                let (key, target) = targets[chunk_idx * MAX_MULTIPLE_ACCOUNTS + idx];
+                match account {
+                    Some(a) => self.reload_target(&key, target, &a.data),
+                    None => {
+                        warn!("Account {} does not exist.", key);
+                    }
+                }
+            }
+        }
+
+        self.slot = slot;
+        Ok(slot)
+    }
+
+    fn get_reload_targets(&self) -> Vec<(Pubkey, ReloadTarget)> {
+        let mut targets = vec![
+            (cache_account::id(), ReloadTarget::Cache),
+            (self.clearing, ReloadTarget::Clearing),
+        ];
+        for (pool_idx, pool) in self.pools.iter().enumerate() {
+            targets.push((pool.address, ReloadTarget::Pool(pool_idx)));
+            for (node_idx, pool_node) in pool.pool_nodes.iter().enumerate() {
+                targets.push((
+                    pool_node.address,
+                    ReloadTarget::PoolNode(pool_idx, node_idx),
+                ));
+            }
+        }
+        for (idx, market) in self.perp_markets.iter().enumerate() {
+            targets.push((market.address, ReloadTarget::PerpetualMarket(idx)));
+        }
+        for (idx, market) in self.futures_markets.iter().enumerate() {
+            targets.push((market.address, ReloadTarget::FuturesMarket(idx)));
+        }
+        for (idx, orderbook) in self.orderbooks.iter().enumerate() {
+            targets.push((orderbook.bids, ReloadTarget::OrderBook(idx, Side::Bid)));
+            targets.push((orderbook.asks, ReloadTarget::OrderBook(idx, Side::Ask)));
+        }
+        if let Some(user) = &self.user {
+            targets.push((user.account_ctx.address, ReloadTarget::Account));
+            // include sub accounts created since the user was loaded
+            for cache in user.account_ctx.state.sub_account_caches.iter() {
+                if cache.sub_account != Pubkey::default() {
+                    targets.push((cache.sub_account, ReloadTarget::SubAccount));
+                }
+            }
+            for sub_account_ctx in user.sub_account_ctxs.iter() {
+                if !targets.iter().any(|(k, _)| *k == sub_account_ctx.address) {
+                    targets.push((sub_account_ctx.address, ReloadTarget::SubAccount));
+                }
+            }
+        }
+        targets
+    }
+
+    fn reload_target(&mut self, key: &Pubkey, target: ReloadTarget, data: &[u8]) {
+        match target {
+            ReloadTarget::Cache => self.cache.reload_from_account_data(data),
+            ReloadTarget::Clearing => {
+                self.clearing_state = get_zero_copy_account::<Clearing>(data);
+            }
+            ReloadTarget::Pool(idx) => self.pools[idx].reload_from_account_data(data),
+            ReloadTarget::PoolNode(pool_idx, _) => {
+                self.pools[pool_idx].reload_pool_node_from_account_data(key, data)
+            }
+            ReloadTarget::PerpetualMarket(idx) => {
+                self.perp_markets[idx].reload_from_account_data(data)
+            }
+            ReloadTarget::FuturesMarket(idx) => {
+                self.futures_markets[idx].reload_from_account_data(data)
+            }
+            ReloadTarget::OrderBook(idx, side) => {
+                let orderbook = &mut self.orderbooks[idx];
+                let market_state: &dyn Market = match self
+                    .perp_markets
+                    .iter()
+                    .find(|m| m.address == orderbook.market)
+                {
+                    Some(m) => m.state.as_ref(),
+                    None => match self
+                        .futures_markets
+                        .iter()
+                        .find(|m| m.address == orderbook.market)
+                    {
+                        Some(m) => m.state.as_ref(),
+                        None => return,
+                    },
+                };
+                orderbook.reload_from_account_data(market_state, data, side);
+            }
+            ReloadTarget::Account => {
+                if let Some(user) = self.user.as_mut() {
+                    user.reload_account_from_account_data(key, data);
+                }
+            }
+            ReloadTarget::SubAccount => {
+                if let Some(user) = self.user.as_mut() {
+                    user.reload_sub_account_from_account_data(key, data);
+                }
+            }
+        }
+    }
+}
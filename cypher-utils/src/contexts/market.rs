@@ -3,22 +3,27 @@ use anchor_lang::{Owner, ZeroCopy};
 use anchor_spl::dex::serum_dex::state::MarketState;
 use bytemuck::bytes_of;
 use cypher_client::{
+    math::MathError,
     serum::parse_dex_account,
     utils::{derive_market_address, get_zero_copy_account},
+    Market, PerpetualMarket, Side,
 };
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_filter::RpcFilterType};
+use fixed::types::I80F48;
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use std::{fmt::Debug, sync::Arc};
 
 use crate::{
     accounts_cache::AccountsCache,
+    layouts::get_data_size_filter,
+    transport::AccountFetcher,
     utils::{
         encode_string, get_cypher_zero_copy_account, get_multiple_cypher_zero_copy_accounts,
         get_program_accounts,
     },
 };
 
-use super::ContextError;
+use super::{ContextError, OrderBook};
 
 /// A generic market context.
 #[derive(Default, Clone)]
@@ -84,7 +89,7 @@ where
     /// This function will return an error if something goes wrong during the RPC request
     /// or the Pool's [`Pubkey`] given is not a valid [`T`] Account.
     pub async fn load_with_name(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         market_name: &str,
     ) -> Result<Self, ContextError> {
         let market_name_bytes = encode_string(market_name);
@@ -100,7 +105,10 @@ where
     /// This function will return an error if something goes wrong during the RPC request,
     /// the [`Pubkey`] given is not a valid [`T`] Account or the underlying account does not
     /// have the correct Anchor discriminator for the provided type.
-    pub async fn load(rpc_client: &Arc<RpcClient>, market: &Pubkey) -> Result<Self, ContextError> {
+    pub async fn load(
+        rpc_client: &impl AccountFetcher,
+        market: &Pubkey,
+    ) -> Result<Self, ContextError> {
         match get_cypher_zero_copy_account::<T>(rpc_client, market).await {
             Ok(s) => Ok(Self::new(market, s)),
             Err(e) => Err(ContextError::ClientError(e)),
@@ -115,7 +123,7 @@ where
     /// the [`Pubkey`] given is not a valid [`T`] Account or the underlying account does not
     /// have the correct Anchor discriminator for the provided type.
     pub async fn load_many(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         markets: &[Pubkey],
     ) -> Result<Vec<Self>, ContextError> {
         match get_multiple_cypher_zero_copy_accounts::<T>(rpc_client, markets).await {
@@ -134,7 +142,7 @@ where
     ///
     /// This function will return an error if something goes wrong during the RPC request.
     pub async fn load_all(rpc_client: &Arc<RpcClient>) -> Result<Vec<Self>, ContextError> {
-        let filters = vec![RpcFilterType::DataSize(std::mem::size_of::<T>() as u64 + 8)];
+        let filters = vec![get_data_size_filter::<T>()];
         match get_program_accounts(rpc_client, filters, &cypher_client::id()).await {
             Ok(s) => Ok(s
                 .iter()
@@ -149,7 +157,7 @@ where
     /// # Errors
     ///
     /// This function will return an error if something goes wrong during the RPC request.
-    pub async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+    pub async fn reload(&mut self, rpc_client: &impl AccountFetcher) -> Result<(), ContextError> {
         let state_res = get_cypher_zero_copy_account::<T>(rpc_client, &self.address).await;
         self.state = match state_res {
             Ok(s) => s,
@@ -185,6 +193,45 @@ where
     }
 }
 
+impl MarketContext<PerpetualMarket> {
+    /// Gets the projected hourly funding rate of the perpetual market from the impact prices of the given book,
+    /// see [`PerpetualMarket::get_projected_funding_rate`].
+    ///
+    /// The impact prices are the prices at which the market's impact quantity would be filled on each side,
+    /// the index price must be in quote lots per base lot, like the book prices.
+    ///
+    /// Returns `None` if there is not enough liquidity on either side of the book to fill the impact quantity.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the funding rate can not be computed.
+    pub fn get_projected_funding_rate(
+        &self,
+        book: &OrderBook,
+        index_price: I80F48,
+    ) -> Result<Option<I80F48>, MathError> {
+        let impact_quantity = match self.state.unscale_base_amount(self.state.impact_quantity) {
+            Some(q) => q,
+            None => {
+                return Err(MathError::Overflow("impact quantity"));
+            }
+        };
+        let impact_bid_price = match book.get_impact_price(impact_quantity, Side::Ask) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let impact_ask_price = match book.get_impact_price(impact_quantity, Side::Bid) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        self.state.get_projected_funding_rate(
+            I80F48::from(impact_bid_price),
+            I80F48::from(impact_ask_price),
+            index_price,
+        )
+    }
+}
+
 /// Represents a Serum Market
 #[derive(Clone, Copy, Debug)]
 pub struct SpotMarketContext {
@@ -312,7 +359,10 @@ impl SpotMarketContext {
     ///
     /// This function will return an error if something goes wrong during the RPC request,
     /// the [`Pubkey`] given is not a valid [`MarketState`] Account.
-    pub async fn load(rpc_client: &Arc<RpcClient>, market: &Pubkey) -> Result<Self, ContextError> {
+    pub async fn load(
+        rpc_client: &impl AccountFetcher,
+        market: &Pubkey,
+    ) -> Result<Self, ContextError> {
         let state = match rpc_client.get_account_data(market).await {
             Ok(a) => parse_dex_account::<MarketState>(&a),
             Err(e) => {
@@ -354,7 +404,7 @@ impl SpotMarketContext {
     /// the [`Pubkey`] given is not a valid [`MarketState`] Account or the underlying account does not
     /// have the correct Anchor discriminator for the provided type.
     pub async fn load_many(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         markets: &[Pubkey],
     ) -> Result<Vec<Self>, ContextError> {
         match rpc_client.get_multiple_accounts(markets).await {
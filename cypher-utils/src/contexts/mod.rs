@@ -1,22 +1,43 @@
 pub mod cache;
+pub mod clearing;
 pub mod cypher;
 pub mod event_queue;
+pub mod loadable;
+mod macros;
+pub mod manager;
 pub mod market;
 pub mod open_orders;
 pub mod orderbook;
+pub mod orders_account;
 pub mod pool;
+pub mod price_history;
+#[cfg(feature = "streaming")]
+pub mod resilient;
+pub mod snapshot;
 pub mod user;
+pub mod whitelist;
 
 pub use cache::*;
+pub use clearing::*;
 pub use cypher::*;
 pub use event_queue::*;
+pub use loadable::*;
+pub use manager::*;
 pub use market::*;
 pub use open_orders::*;
 pub use orderbook::*;
+pub use orders_account::*;
 pub use pool::*;
+pub use price_history::*;
+#[cfg(feature = "streaming")]
+pub use resilient::*;
+pub use snapshot::*;
 pub use user::*;
+pub use whitelist::*;
 
+use cypher_client::math::MathError;
 use solana_client::client_error::ClientError;
+use solana_sdk::pubkey::Pubkey;
 use thiserror::Error;
 
 #[allow(clippy::large_enum_variant)]
@@ -26,6 +47,30 @@ pub enum ContextError {
     MissingAccountState,
     #[error("Account not found: {0}")]
     AccountNotFound(String),
+    #[error("Too many accounts to read in one request: {0}")]
+    TooManyAccounts(usize),
+    #[error("Insufficient funds of {token_mint}: required {required}, available {available}.")]
+    InsufficientFunds {
+        token_mint: Pubkey,
+        required: u64,
+        available: u64,
+    },
     #[error(transparent)]
     ClientError(#[from] ClientError),
+    #[error(transparent)]
+    MathError(#[from] MathError),
+}
+
+impl ContextError {
+    /// Gets the amount by which the funds fall short, in native units, if this is an insufficient funds error.
+    pub fn shortfall(&self) -> Option<u64> {
+        match self {
+            ContextError::InsufficientFunds {
+                required,
+                available,
+                ..
+            } => Some(required.saturating_sub(*available)),
+            _ => None,
+        }
+    }
 }
@@ -65,6 +65,7 @@ impl GenericOpenOrders for AgnosticOpenOrdersContext {
                         base_quantity: ob_order.base_quantity,
                         quote_quantity: ob_order.quote_quantity,
                         max_ts: ob_order.max_ts,
+                        maker: ob_order.maker,
                     })
                 }
             }
@@ -157,6 +158,7 @@ impl GenericOpenOrders for &Box<OpenOrders> {
                         base_quantity: ob_order.base_quantity,
                         quote_quantity: ob_order.quote_quantity,
                         max_ts: ob_order.max_ts,
+                        maker: ob_order.maker,
                     })
                 }
             }
@@ -191,6 +193,7 @@ impl GenericOpenOrders for SerumOpenOrdersContext {
                         base_quantity: ob_order.base_quantity,
                         quote_quantity: ob_order.quote_quantity,
                         max_ts: ob_order.max_ts,
+                        maker: ob_order.maker,
                     })
                 }
             }
@@ -8,15 +8,16 @@ use arrayref::array_refs;
 use cypher_client::{
     aob::{load_book_side, CallBackInfo},
     serum::Slab,
-    Market, Side,
+    utils::derive_orders_account_address,
+    CancelOrderArgs, Market, OpenOrder, Side,
 };
-use solana_client::nonblocking::rpc_client::RpcClient;
+use fixed::types::I80F48;
 use solana_sdk::pubkey::Pubkey;
-use std::{fmt::Debug, sync::Arc};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
-use crate::accounts_cache::AccountsCache;
+use crate::{accounts_cache::AccountsCache, transport::AccountFetcher};
 
-use super::ContextError;
+use super::{ContextError, OrdersAccountContext};
 
 /// A trait that can be used to generically get data for both AOB and Serum Order Books.
 pub trait GenericOrderBook: Send + Sync {
@@ -44,6 +45,9 @@ pub struct Order {
     pub client_order_id: u64,
     /// The maximum timestamp at which it can be filled.
     pub max_ts: u64,
+    /// The maker's orders account, only known for AOB orders loaded in L3 mode,
+    /// see [`AgnosticOrderBookContext::l3`].
+    pub maker: Option<Pubkey>,
 }
 
 impl Debug for Order {
@@ -56,17 +60,139 @@ impl Debug for Order {
             .field("order_id", &format!("{}", self.order_id))
             .field("client_order_id", &format!("{}", self.client_order_id))
             .field("max_ts", &format!("{}", self.max_ts))
+            .field("maker", &format!("{:?}", self.maker))
             .finish()
     }
 }
 
+/// Helpers to create [`CancelOrderArgs`] from orders on the book.
+pub trait CancelOrderArgsExt {
+    /// Creates the args to cancel the given order on the book by it's order id.
+    ///
+    /// The order's side is the side it rests on, which AOB markets require to find the order id.
+    fn from_book_order(order: &Order) -> Self;
+}
+
+impl CancelOrderArgsExt for CancelOrderArgs {
+    fn from_book_order(order: &Order) -> Self {
+        Self {
+            order_id: order.order_id,
+            side: order.side,
+            is_client_id: false,
+        }
+    }
+}
+
 /// Gets orders from the AOB's [`Slab`] for a given [`Market`].
 ///
+/// If the market's address is given, the orders carry their maker's orders account.
+///
 /// ### Panics
 ///
 /// Panics if there is an overflow doing conversions from lots.
-fn get_aob_orders(market: &dyn Market, slab: AobSlab<CallBackInfo>, side: Side) -> Vec<Order> {
+fn get_aob_orders(
+    market: &dyn Market,
+    slab: AobSlab<CallBackInfo>,
+    side: Side,
+    l3_market: Option<&Pubkey>,
+) -> Vec<Order> {
     let mut vec: Vec<Order> = Vec::new();
+    extend_aob_orders(market, slab, side, l3_market, &mut vec);
+    vec
+}
+
+/// Pushes the orders from the AOB's [`Slab`] for a given [`Market`] into the given [`Vec`].
+///
+/// If the market's address is given, the orders carry their maker's orders account.
+///
+/// ### Panics
+///
+/// Panics if there is an overflow doing conversions from lots.
+fn extend_aob_orders(
+    market: &dyn Market,
+    slab: AobSlab<CallBackInfo>,
+    side: Side,
+    l3_market: Option<&Pubkey>,
+    vec: &mut Vec<Order>,
+) {
+    match l3_market {
+        Some(market_address) => {
+            // deriving the orders account is expensive and makers usually have several orders
+            let mut makers: HashMap<Pubkey, Pubkey> = HashMap::new();
+            visit_aob_orders(market, &slab, side, |mut order, callback_info| {
+                if !callback_info.is_empty() {
+                    let (master_account, _) = callback_info.owner();
+                    let maker = makers.entry(master_account).or_insert_with(|| {
+                        derive_orders_account_address(market_address, &master_account).0
+                    });
+                    order.maker = Some(*maker);
+                }
+                vec.push(order)
+            });
+        }
+        None => visit_aob_orders(market, &slab, side, |order, _| vec.push(order)),
+    }
+}
+
+/// Gets the orders from one side of the AOB for a given [`Market`] from the given account data,
+/// with each order carrying it's maker's orders account.
+///
+/// ### Panics
+///
+/// Panics if there is an overflow doing conversions from lots.
+pub fn get_aob_l3_orders(
+    market_state: &dyn Market,
+    market: &Pubkey,
+    data: &[u8],
+    side: Side,
+) -> Vec<Order> {
+    let account_tag = if side == Side::Bid {
+        AccountTag::Bids
+    } else {
+        AccountTag::Asks
+    };
+    let mut data = data.to_vec();
+    let slab: AobSlab<CallBackInfo> = load_book_side(&mut data, account_tag);
+    get_aob_orders(market_state, slab, side, Some(market))
+}
+
+/// Gets the orders from one side of the AOB for a given [`Market`] from the given account data,
+/// along with the [`CallBackInfo`] identifying the owner of each order.
+///
+/// ### Panics
+///
+/// Panics if there is an overflow doing conversions from lots.
+pub fn get_aob_orders_with_callback_infos(
+    market: &dyn Market,
+    data: &[u8],
+    side: Side,
+) -> Vec<(Order, CallBackInfo)> {
+    let account_tag = if side == Side::Bid {
+        AccountTag::Bids
+    } else {
+        AccountTag::Asks
+    };
+    let mut data = data.to_vec();
+    let slab: AobSlab<CallBackInfo> = load_book_side(&mut data, account_tag);
+    let mut vec = Vec::new();
+    visit_aob_orders(market, &slab, side, |order, callback_info| {
+        vec.push((order, *callback_info))
+    });
+    vec
+}
+
+/// Visits the orders from the AOB's [`Slab`] for a given [`Market`] from best to worst price,
+/// along with the [`CallBackInfo`] of each order.
+///
+/// ### Panics
+///
+/// Panics if there is an overflow doing conversions from lots.
+fn visit_aob_orders<F: FnMut(Order, &CallBackInfo)>(
+    market: &dyn Market,
+    slab: &AobSlab<CallBackInfo>,
+    side: Side,
+    mut f: F,
+) {
     let ascending = side == Side::Ask;
 
     let mut search_stack: Vec<u32> = if slab.header.leaf_count == 0 {
@@ -83,15 +209,19 @@ fn get_aob_orders(market: &dyn Market, slab: AobSlab<CallBackInfo>, side: Side)
             let quote_quantity = market
                 .get_quote_from_base(base_quantity, scaled_price)
                 .unwrap();
-            vec.push(Order {
-                side,
-                price: scaled_price >> 32,
-                base_quantity,
-                quote_quantity,
-                order_id: node.key,
-                client_order_id: u64::default(), // The AOB does not store `client_order_id`, cypher stores it in the `OrdersAccount`.
-                max_ts: node.max_ts,
-            })
+            f(
+                Order {
+                    side,
+                    price: scaled_price >> 32,
+                    base_quantity,
+                    quote_quantity,
+                    order_id: node.key,
+                    client_order_id: u64::default(), // The AOB does not store `client_order_id`, cypher stores it in the `OrdersAccount`.
+                    max_ts: node.max_ts,
+                    maker: None,
+                },
+                &slab.callback_infos[current as usize],
+            )
         } else {
             let n = &slab.inner_nodes[(!current) as usize];
             search_stack.push(n.children[ascending as usize]);
@@ -99,7 +229,6 @@ fn get_aob_orders(market: &dyn Market, slab: AobSlab<CallBackInfo>, side: Side)
             continue;
         }
     }
-    vec
 }
 
 /// Gets orders from Serum's [`Slab`] for a given [`MarketState`].
@@ -125,11 +254,125 @@ fn get_serum_orders(market: &MarketState, slab: &Slab, side: Side) -> Vec<Order>
                 order_id: l.order_id(),
                 client_order_id: l.client_order_id(),
                 max_ts: u64::MAX, // This version of Serum does not have TIF capability.
+                maker: None,
             }
         })
         .collect::<Vec<Order>>()
 }
 
+/// The queue priority of a resting order at it's price level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueuePosition {
+    /// The price of the order's price level.
+    pub price: u64,
+    /// The number of orders with higher priority at the same price level.
+    pub orders_ahead: usize,
+    /// The base quantity of the orders with higher priority at the same price level.
+    pub size_ahead: u64,
+    /// The number of orders at the price level, including the order itself.
+    pub level_orders: usize,
+    /// The total base quantity at the price level, including the order itself.
+    pub level_size: u64,
+}
+
+impl QueuePosition {
+    /// Whether the order is the first to be matched at it's price level.
+    pub fn is_first(&self) -> bool {
+        self.orders_ahead == 0
+    }
+
+    /// The fraction of the price level's base quantity that has priority over the order.
+    pub fn size_ahead_ratio(&self) -> f64 {
+        if self.level_size == 0 {
+            0.0
+        } else {
+            self.size_ahead as f64 / self.level_size as f64
+        }
+    }
+}
+
+/// Reusable buffers for the account data of both sides of an order book.
+///
+/// Loading a book side requires a mutable copy of it's account data, reusing these buffers
+/// across reloads avoids allocating a new copy every time, after the first reload.
+#[derive(Debug, Default, Clone)]
+pub struct BookBuffers {
+    /// The buffer for the bids account data.
+    pub bids: Vec<u8>,
+    /// The buffer for the asks account data.
+    pub asks: Vec<u8>,
+}
+
+impl BookBuffers {
+    /// Creates new [`BookBuffers`] with the given capacity for each side, e.g. the size of the slab accounts.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            bids: Vec::with_capacity(capacity),
+            asks: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Gets the buffer for the given [`Side`].
+    pub fn get_mut(&mut self, side: Side) -> &mut Vec<u8> {
+        if side == Side::Bid {
+            &mut self.bids
+        } else {
+            &mut self.asks
+        }
+    }
+}
+
+/// Copies the given data into the buffer, reusing it's allocation, and returns the copy.
+pub fn copy_into_buffer<'a>(buffer: &'a mut Vec<u8>, data: &[u8]) -> &'a mut [u8] {
+    buffer.clear();
+    buffer.extend_from_slice(data);
+    buffer.as_mut_slice()
+}
+
+/// The size of a taker order, denominated in either token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSize {
+    /// A size in native base units.
+    Base(u64),
+    /// A size in native quote units.
+    Quote(u64),
+}
+
+/// The expected fill of a taker order walking one side of an [`OrderBook`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImpactFill {
+    /// The volume weighted average fill price, in quote lots per base lot, none if nothing was filled.
+    pub average_price: Option<I80F48>,
+    /// The price of the worst level touched, in quote lots per base lot, none if nothing was filled.
+    pub worst_price: Option<u64>,
+    /// The filled base quantity, in native units.
+    pub filled_base_quantity: u64,
+    /// The filled quote quantity, in native units.
+    pub filled_quote_quantity: u64,
+    /// The quantity that could not be filled due to insufficient liquidity, in native units of the requested size's token.
+    pub unfilled_quantity: u64,
+}
+
+impl ImpactFill {
+    /// Whether the requested size was fully filled.
+    pub fn is_complete(&self) -> bool {
+        self.unfilled_quantity == 0
+    }
+}
+
+/// A price level of one side of an [`OrderBook`] along with the depth up to and including it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DepthLevel {
+    /// The price of the level, in quote lots per base lot.
+    pub price: u64,
+    /// The base quantity at the level, in native units.
+    pub base_quantity: u64,
+    /// The base quantity from the top of the book up to and including the level, in native units.
+    pub cumulative_base_quantity: u64,
+    /// The quote quantity from the top of the book up to and including the level, in native units.
+    pub cumulative_quote_quantity: u64,
+}
+
 /// Represents an orderbook state.
 #[derive(Default, Clone)]
 pub struct OrderBook {
@@ -180,6 +423,327 @@ impl OrderBook {
 
         None
     }
+
+    /// Gets the best bid on the book, if any.
+    pub fn best_bid(&self) -> Option<&Order> {
+        self.bids.first()
+    }
+
+    /// Gets the best ask on the book, if any.
+    pub fn best_ask(&self) -> Option<&Order> {
+        self.asks.first()
+    }
+
+    /// Gets the mid price between the best bid and the best ask, in quote lots per base lot.
+    ///
+    /// If either side of the book is empty, this method returns none.
+    pub fn mid_price(&self) -> Option<I80F48> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => {
+                Some((I80F48::from(bid.price) + I80F48::from(ask.price)) / I80F48::from(2))
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets the spread between the best ask and the best bid, in quote lots per base lot.
+    ///
+    /// If either side of the book is empty, this method returns none.
+    /// A crossed book has a spread of zero.
+    pub fn spread(&self) -> Option<u64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some(ask.price.saturating_sub(bid.price)),
+            _ => None,
+        }
+    }
+
+    /// Gets the volume weighted average price at which a taker order of the given native size
+    /// and order side would be filled, in quote lots per base lot.
+    ///
+    /// If not enough liquidity is available on the book to match the requested size,
+    /// this method returns none.
+    pub fn vwap(&self, size: u64, side: Side) -> Option<I80F48> {
+        if size == 0 {
+            return None;
+        }
+        let impact_fill = self.get_impact_fill(OrderSize::Base(size), side);
+        if !impact_fill.is_complete() {
+            return None;
+        }
+        impact_fill.average_price
+    }
+
+    /// Gets the expected fill of a taker order of the given size, in either token, and order side,
+    /// with both the average and the worst fill price along with the filled and unfilled quantities.
+    ///
+    /// Orders which are only partially needed to fill a quote-denominated size are assumed to fill
+    /// proportionally to their quote quantity.
+    pub fn get_impact_fill(&self, size: OrderSize, side: Side) -> ImpactFill {
+        let orders = if side == Side::Ask {
+            &self.bids
+        } else {
+            &self.asks
+        };
+        let mut remaining_size = match size {
+            OrderSize::Base(s) => s,
+            OrderSize::Quote(s) => s,
+        };
+        let mut impact_fill = ImpactFill::default();
+        let mut notional = I80F48::ZERO;
+
+        for order in orders.iter() {
+            if remaining_size == 0 {
+                break;
+            }
+            let (fill_base, fill_quote) = match size {
+                OrderSize::Base(_) => {
+                    let fill_base = order.base_quantity.min(remaining_size);
+                    let fill_quote = if fill_base == order.base_quantity {
+                        order.quote_quantity
+                    } else {
+                        (order.quote_quantity as u128 * fill_base as u128
+                            / order.base_quantity as u128) as u64
+                    };
+                    remaining_size -= fill_base;
+                    (fill_base, fill_quote)
+                }
+                OrderSize::Quote(_) => {
+                    let fill_quote = order.quote_quantity.min(remaining_size);
+                    let fill_base = if fill_quote == order.quote_quantity {
+                        order.base_quantity
+                    } else {
+                        (order.base_quantity as u128 * fill_quote as u128
+                            / order.quote_quantity as u128) as u64
+                    };
+                    remaining_size -= fill_quote;
+                    (fill_base, fill_quote)
+                }
+            };
+            if fill_base == 0 {
+                continue;
+            }
+            notional += I80F48::from(order.price) * I80F48::from(fill_base);
+            impact_fill.worst_price = Some(order.price);
+            impact_fill.filled_base_quantity += fill_base;
+            impact_fill.filled_quote_quantity += fill_quote;
+        }
+
+        if impact_fill.filled_base_quantity != 0 {
+            impact_fill.average_price =
+                Some(notional / I80F48::from(impact_fill.filled_base_quantity));
+        }
+        impact_fill.unfilled_quantity = remaining_size;
+        impact_fill
+    }
+
+    /// Gets up to the given number of price levels from the top of each side of the book, along with
+    /// the cumulative depth up to each level, as the bids and asks respectively.
+    pub fn cumulative_depth(&self, levels: usize) -> (Vec<DepthLevel>, Vec<DepthLevel>) {
+        (
+            get_depth_levels(&self.bids, levels),
+            get_depth_levels(&self.asks, levels),
+        )
+    }
+
+    /// Gets the resting orders of the given maker's orders account on the given side of the book.
+    ///
+    /// Only orders loaded in L3 mode carry their maker, see [`AgnosticOrderBookContext::l3`].
+    pub fn get_maker_orders(&self, maker: &Pubkey, side: Side) -> Vec<&Order> {
+        let orders = if side == Side::Bid {
+            &self.bids
+        } else {
+            &self.asks
+        };
+        orders
+            .iter()
+            .filter(|o| o.maker.as_ref() == Some(maker))
+            .collect()
+    }
+
+    /// Whether a taker order of the given order side and limit price, in quote lots per base lot,
+    /// would match against a resting order of the given maker's orders account.
+    ///
+    /// Only orders loaded in L3 mode carry their maker, see [`AgnosticOrderBookContext::l3`].
+    pub fn would_self_match(&self, maker: &Pubkey, side: Side, limit_price: u64) -> bool {
+        if side == Side::Bid {
+            self.asks
+                .iter()
+                .take_while(|o| o.price <= limit_price)
+                .any(|o| o.maker.as_ref() == Some(maker))
+        } else {
+            self.bids
+                .iter()
+                .take_while(|o| o.price >= limit_price)
+                .any(|o| o.maker.as_ref() == Some(maker))
+        }
+    }
+
+    /// Estimates the queue priority of the order with the given id at it's price level.
+    ///
+    /// The book side is walked in key order, which for both the AOB and Serum is the matching priority,
+    /// so every order at the same price that comes before the given order will be matched first.
+    ///
+    /// If the order is not resting on the given side of the book, this method returns none.
+    pub fn get_queue_position(&self, order_id: u128, side: Side) -> Option<QueuePosition> {
+        let orders = if side == Side::Bid {
+            &self.bids
+        } else {
+            &self.asks
+        };
+        let order = orders.iter().find(|o| o.order_id == order_id)?;
+
+        let mut queue_position = QueuePosition {
+            price: order.price,
+            ..Default::default()
+        };
+        let mut found = false;
+
+        for o in orders.iter().filter(|o| o.price == order.price) {
+            if o.order_id == order_id {
+                found = true;
+            } else if !found {
+                queue_position.orders_ahead += 1;
+                queue_position.size_ahead += o.base_quantity;
+            }
+            queue_position.level_orders += 1;
+            queue_position.level_size += o.base_quantity;
+        }
+
+        Some(queue_position)
+    }
+}
+
+fn get_depth_levels(orders: &[Order], levels: usize) -> Vec<DepthLevel> {
+    let mut depth_levels: Vec<DepthLevel> = Vec::with_capacity(levels);
+    let mut cumulative_base_quantity: u64 = 0;
+    let mut cumulative_quote_quantity: u64 = 0;
+
+    for order in orders.iter() {
+        let is_new_level = match depth_levels.last() {
+            Some(l) => l.price != order.price,
+            None => true,
+        };
+        if is_new_level && depth_levels.len() == levels {
+            break;
+        }
+        cumulative_base_quantity = cumulative_base_quantity.saturating_add(order.base_quantity);
+        cumulative_quote_quantity = cumulative_quote_quantity.saturating_add(order.quote_quantity);
+        if is_new_level {
+            depth_levels.push(DepthLevel {
+                price: order.price,
+                ..Default::default()
+            });
+        }
+        // the level was pushed above if it did not exist
+        let level = depth_levels.last_mut().unwrap();
+        level.base_quantity = level.base_quantity.saturating_add(order.base_quantity);
+        level.cumulative_base_quantity = cumulative_base_quantity;
+        level.cumulative_quote_quantity = cumulative_quote_quantity;
+    }
+
+    depth_levels
+}
+
+/// An order on the book flagged with whether it belongs to the caller's [`OrdersAccount`](cypher_client::OrdersAccount).
+#[derive(Debug, Clone)]
+pub struct FlaggedOrder {
+    /// The order, with the client order id of the caller's own orders.
+    pub order: Order,
+    /// The index of the sub account that placed the order, if it belongs to the caller.
+    pub sub_account_idx: Option<u8>,
+}
+
+impl FlaggedOrder {
+    /// Whether the order belongs to the caller.
+    pub fn is_own(&self) -> bool {
+        self.sub_account_idx.is_some()
+    }
+}
+
+/// A price level of one side of the book split between the caller's own orders and everyone else's.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FlaggedLevel {
+    /// The price of the level, in quote lots per base lot.
+    pub price: u64,
+    /// The number of orders at the level.
+    pub orders: usize,
+    /// The number of the caller's own orders at the level.
+    pub own_orders: usize,
+    /// The base quantity at the level, in native units.
+    pub base_quantity: u64,
+    /// The base quantity of the caller's own orders at the level, in native units.
+    pub own_base_quantity: u64,
+}
+
+impl FlaggedLevel {
+    /// The base quantity of other participants' orders at the level, in native units.
+    pub fn other_base_quantity(&self) -> u64 {
+        self.base_quantity.saturating_sub(self.own_base_quantity)
+    }
+}
+
+/// An order book whose orders are flagged with whether they belong to the caller.
+#[derive(Debug, Default, Clone)]
+pub struct FlaggedOrderBook {
+    pub bids: Vec<FlaggedOrder>,
+    pub asks: Vec<FlaggedOrder>,
+}
+
+impl FlaggedOrderBook {
+    /// Gets the flagged orders of the given side.
+    pub fn get_orders(&self, side: Side) -> &[FlaggedOrder] {
+        if side == Side::Bid {
+            &self.bids
+        } else {
+            &self.asks
+        }
+    }
+
+    /// Gets the caller's own orders on the given side.
+    pub fn get_own_orders(&self, side: Side) -> Vec<&FlaggedOrder> {
+        self.get_orders(side)
+            .iter()
+            .filter(|o| o.is_own())
+            .collect()
+    }
+
+    /// Gets the base quantity of the caller's own orders on the given side, in native units.
+    pub fn get_own_base_quantity(&self, side: Side) -> u64 {
+        self.get_orders(side)
+            .iter()
+            .filter(|o| o.is_own())
+            .fold(0u64, |acc, o| acc.saturating_add(o.order.base_quantity))
+    }
+
+    /// Gets the price levels of the given side from best to worst price.
+    pub fn get_levels(&self, side: Side) -> Vec<FlaggedLevel> {
+        let mut levels: Vec<FlaggedLevel> = Vec::new();
+        for flagged in self.get_orders(side).iter() {
+            let is_new_level = match levels.last() {
+                Some(l) => l.price != flagged.order.price,
+                None => true,
+            };
+            if is_new_level {
+                levels.push(FlaggedLevel {
+                    price: flagged.order.price,
+                    ..Default::default()
+                });
+            }
+            // the level was pushed above if it did not exist
+            let level = levels.last_mut().unwrap();
+            level.orders += 1;
+            level.base_quantity = level
+                .base_quantity
+                .saturating_add(flagged.order.base_quantity);
+            if flagged.is_own() {
+                level.own_orders += 1;
+                level.own_base_quantity = level
+                    .own_base_quantity
+                    .saturating_add(flagged.order.base_quantity);
+            }
+        }
+        levels
+    }
 }
 
 /// Represents an AOB [`OrderBook`].
@@ -189,6 +753,9 @@ pub struct AgnosticOrderBookContext {
     pub bids: Pubkey,
     pub asks: Pubkey,
     pub state: OrderBook,
+    /// Whether the orders carry their maker's orders account when reloaded, i.e. an L3 book,
+    /// which enables maker flow analytics and self-match prevention at the cost of slower reloads.
+    pub l3: bool,
 }
 
 impl GenericOrderBook for AgnosticOrderBookContext {
@@ -209,6 +776,21 @@ impl AgnosticOrderBookContext {
             bids: *bids,
             asks: *asks,
             state,
+            l3: false,
+        }
+    }
+
+    /// Sets whether the orders carry their maker's orders account, which takes effect on the following reload.
+    pub fn with_l3(mut self, l3: bool) -> Self {
+        self.l3 = l3;
+        self
+    }
+
+    fn l3_market(&self) -> Option<Pubkey> {
+        if self.l3 {
+            Some(self.market)
+        } else {
+            None
         }
     }
 
@@ -219,7 +801,7 @@ impl AgnosticOrderBookContext {
     /// This function will return an error if something goes wrong during the RPC request
     /// or the [`Pubkey`]s given are not valid AOB Slab Accounts.
     pub async fn load(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         market_state: &dyn Market,
         market: &Pubkey,
         bids: &Pubkey,
@@ -248,8 +830,8 @@ impl AgnosticOrderBookContext {
         let mut asks_data = asks_account.data.clone();
         let asks_state: AobSlab<CallBackInfo> = load_book_side(&mut asks_data, AccountTag::Asks);
 
-        let bid_orders = get_aob_orders(market_state, bids_state, Side::Bid);
-        let ask_orders = get_aob_orders(market_state, asks_state, Side::Ask);
+        let bid_orders = get_aob_orders(market_state, bids_state, Side::Bid, None);
+        let ask_orders = get_aob_orders(market_state, asks_state, Side::Ask, None);
 
         Ok(Self::new(
             market,
@@ -290,8 +872,8 @@ impl AgnosticOrderBookContext {
         let mut asks_data = asks_account_state.data.clone();
         let asks_state: AobSlab<CallBackInfo> = load_book_side(&mut asks_data, AccountTag::Asks);
 
-        let bid_orders = get_aob_orders(market_state, bids_state, Side::Bid);
-        let ask_orders = get_aob_orders(market_state, asks_state, Side::Ask);
+        let bid_orders = get_aob_orders(market_state, bids_state, Side::Bid, None);
+        let ask_orders = get_aob_orders(market_state, asks_state, Side::Ask, None);
 
         Ok(Self::new(
             market,
@@ -318,7 +900,7 @@ impl AgnosticOrderBookContext {
         let mut data = data.to_vec();
         let side_state: AobSlab<CallBackInfo> = load_book_side(&mut data, account_tag);
 
-        let orders = get_aob_orders(market_state, side_state, side);
+        let orders = get_aob_orders(market_state, side_state, side, None);
 
         let book = if side == Side::Bid {
             OrderBook::new(orders, Vec::new())
@@ -348,7 +930,7 @@ impl AgnosticOrderBookContext {
             self.state.bids.clone() // take the bids if we're updating the asks
         };
 
-        let orders = get_aob_orders(market_state, side_state, side);
+        let orders = get_aob_orders(market_state, side_state, side, self.l3_market().as_ref());
         self.state = if side == Side::Bid {
             OrderBook::new(orders, opposite_side_state)
         } else {
@@ -385,14 +967,86 @@ impl AgnosticOrderBookContext {
         let mut asks_data = asks_account_state.data.clone();
         let asks_state: AobSlab<CallBackInfo> = load_book_side(&mut asks_data, AccountTag::Asks);
 
-        let bid_orders = get_aob_orders(market_state, bids_state, Side::Bid);
-        let ask_orders = get_aob_orders(market_state, asks_state, Side::Ask);
+        let bid_orders = get_aob_orders(
+            market_state,
+            bids_state,
+            Side::Bid,
+            self.l3_market().as_ref(),
+        );
+        let ask_orders = get_aob_orders(
+            market_state,
+            asks_state,
+            Side::Ask,
+            self.l3_market().as_ref(),
+        );
 
         self.state = OrderBook::new(bid_orders, ask_orders);
 
         Ok(())
     }
 
+    /// Reloads one [`Side`] of the [`AgnosticOrderBookContext`] from the given account data,
+    /// copying it into the given buffer and reusing the existing orders' allocation.
+    pub fn reload_from_account_data_with_buffer(
+        &mut self,
+        market_state: &dyn Market,
+        data: &[u8],
+        side: Side,
+        buffer: &mut Vec<u8>,
+    ) {
+        let account_tag = if side == Side::Bid {
+            AccountTag::Bids
+        } else {
+            AccountTag::Asks
+        };
+        let side_state: AobSlab<CallBackInfo> =
+            load_book_side(copy_into_buffer(buffer, data), account_tag);
+
+        let l3_market = self.l3_market();
+        let orders = if side == Side::Bid {
+            &mut self.state.bids
+        } else {
+            &mut self.state.asks
+        };
+        orders.clear();
+        extend_aob_orders(market_state, side_state, side, l3_market.as_ref(), orders);
+    }
+
+    /// Reloads the [`AgnosticOrderBookContext`] from the given [`AccountsCache`], copying the account data
+    /// into the given buffers and reusing the existing orders' allocations.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account state does not exist in the cache.
+    pub fn reload_from_cache_with_buffers(
+        &mut self,
+        cache: Arc<AccountsCache>,
+        market_state: &dyn Market,
+        buffers: &mut BookBuffers,
+    ) -> Result<(), ContextError> {
+        for side in [Side::Bid, Side::Ask] {
+            let key = if side == Side::Bid {
+                self.bids
+            } else {
+                self.asks
+            };
+            let account_state = match cache.get(&key) {
+                Some(a) => a,
+                None => {
+                    return Err(ContextError::MissingAccountState);
+                }
+            };
+            self.reload_from_account_data_with_buffer(
+                market_state,
+                &account_state.data,
+                side,
+                buffers.get_mut(side),
+            );
+        }
+
+        Ok(())
+    }
+
     /// Gets the native impact price for the given size and order side.
     /// The returning value, if it exists, already represents the lot price.
     ///
@@ -401,6 +1055,59 @@ impl AgnosticOrderBookContext {
     pub fn get_impact_price(&self, size: u64, side: Side) -> Option<u64> {
         self.state.get_impact_price(size, side)
     }
+
+    /// Gets the expected fill of a taker order of the given size, in either token, and order side.
+    ///
+    /// See [`OrderBook::get_impact_fill`].
+    pub fn get_impact_fill(&self, size: OrderSize, side: Side) -> ImpactFill {
+        self.state.get_impact_fill(size, side)
+    }
+
+    /// Estimates the queue priority of the order with the given id at it's price level.
+    ///
+    /// If the order is not resting on the given side of the book, this method returns none.
+    pub fn get_queue_position(&self, order_id: u128, side: Side) -> Option<QueuePosition> {
+        self.state.get_queue_position(order_id, side)
+    }
+
+    /// Flags the orders on the book which belong to the given [`OrdersAccountContext`], by cross-referencing
+    /// the order ids of it's open orders, and fills in their client order ids which the AOB does not store.
+    ///
+    /// If the orders account is not of this context's market, no orders are flagged.
+    pub fn flag_own_orders(&self, orders_account: &OrdersAccountContext) -> FlaggedOrderBook {
+        let open_orders: HashMap<u128, &OpenOrder> = if orders_account.market == self.market {
+            orders_account
+                .state
+                .get_orders()
+                .into_iter()
+                .map(|o| (o.order_id, o))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let flag = |orders: &[Order]| {
+            orders
+                .iter()
+                .map(|order| match open_orders.get(&order.order_id) {
+                    Some(open_order) if open_order.side == order.side => FlaggedOrder {
+                        order: Order {
+                            client_order_id: open_order.client_order_id,
+                            ..order.clone()
+                        },
+                        sub_account_idx: Some(open_order.sub_account_idx),
+                    },
+                    _ => FlaggedOrder {
+                        order: order.clone(),
+                        sub_account_idx: None,
+                    },
+                })
+                .collect::<Vec<FlaggedOrder>>()
+        };
+        FlaggedOrderBook {
+            bids: flag(&self.state.bids),
+            asks: flag(&self.state.asks),
+        }
+    }
 }
 
 /// Represents a Serum [OrderBook].
@@ -441,7 +1148,7 @@ impl SerumOrderBookContext {
     /// or the [`Pubkey`]s given are not valid Serum Slab Accounts.
     #[allow(clippy::ptr_offset_with_cast)]
     pub async fn load(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         market_state: &MarketState,
         market: &Pubkey,
         bids: &Pubkey,
@@ -622,6 +1329,62 @@ impl SerumOrderBookContext {
         Ok(())
     }
 
+    /// Reloads one [`Side`] of the [`SerumOrderBookContext`] from the given account data,
+    /// copying it into the given buffer instead of allocating a new copy.
+    #[allow(clippy::ptr_offset_with_cast)]
+    pub fn reload_from_account_data_with_buffer(
+        &mut self,
+        market_state: &MarketState,
+        data: &[u8],
+        side: Side,
+        buffer: &mut Vec<u8>,
+    ) {
+        let (_side_head, side_data, _side_tail) = array_refs![&data, 5; ..; 7];
+        let side_state = Slab::new(copy_into_buffer(buffer, &side_data[8..]));
+
+        let orders = get_serum_orders(market_state, side_state, side);
+        if side == Side::Bid {
+            self.state.bids = orders;
+        } else {
+            self.state.asks = orders;
+        }
+    }
+
+    /// Reloads the [`SerumOrderBookContext`] from the given [`AccountsCache`], copying the account data
+    /// into the given buffers instead of allocating new copies.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account state does not exist in the cache.
+    pub fn reload_from_cache_with_buffers(
+        &mut self,
+        market_state: &MarketState,
+        cache: Arc<AccountsCache>,
+        buffers: &mut BookBuffers,
+    ) -> Result<(), ContextError> {
+        for side in [Side::Bid, Side::Ask] {
+            let key = if side == Side::Bid {
+                self.bids
+            } else {
+                self.asks
+            };
+            let account_state = match cache.get(&key) {
+                Some(a) => a,
+                None => {
+                    return Err(ContextError::MissingAccountState);
+                }
+            };
+            self.reload_from_account_data_with_buffer(
+                market_state,
+                &account_state.data,
+                side,
+                buffers.get_mut(side),
+            );
+        }
+
+        Ok(())
+    }
+
     /// Gets the native impact price for the given size and order side.
     /// The returning value, if it exists, already represents the lot price.
     ///
@@ -630,4 +1393,18 @@ impl SerumOrderBookContext {
     pub fn get_impact_price(&self, size: u64, side: Side) -> Option<u64> {
         self.state.get_impact_price(size, side)
     }
+
+    /// Gets the expected fill of a taker order of the given size, in either token, and order side.
+    ///
+    /// See [`OrderBook::get_impact_fill`].
+    pub fn get_impact_fill(&self, size: OrderSize, side: Side) -> ImpactFill {
+        self.state.get_impact_fill(size, side)
+    }
+
+    /// Estimates the queue priority of the order with the given id at it's price level.
+    ///
+    /// If the order is not resting on the given side of the book, this method returns none.
+    pub fn get_queue_position(&self, order_id: u128, side: Side) -> Option<QueuePosition> {
+        self.state.get_queue_position(order_id, side)
+    }
 }
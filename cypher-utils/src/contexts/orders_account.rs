@@ -0,0 +1,334 @@
+use cypher_client::{
+    constants::QUOTE_TOKEN_DECIMALS,
+    math::MathError,
+    utils::{derive_orders_account_address, get_zero_copy_account, try_adjust_decimals},
+    Cache, Market, OpenOrder, OpenOrdersCache, OrdersAccount, Side,
+};
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use crate::{
+    accounts_cache::AccountsCache, transport::AccountFetcher, utils::get_cypher_zero_copy_account,
+};
+
+use super::{ContextError, GenericOrderBook, Order, OrderBook, QueuePosition};
+
+/// An open order in the [`OrdersAccount`] joined with it's state on the order book.
+#[derive(Debug, Clone)]
+pub struct OrdersAccountOrder {
+    /// The open order as stored in the [`OrdersAccount`].
+    pub open_order: OpenOrder,
+    /// The order as it is currently resting on the book, if it is still resting.
+    pub book_order: Option<Order>,
+    /// The queue priority of the order at it's price level, if it is still resting.
+    pub queue_position: Option<QueuePosition>,
+}
+
+impl OrdersAccountOrder {
+    /// Whether the order is still resting on the book.
+    pub fn is_resting(&self) -> bool {
+        self.book_order.is_some()
+    }
+}
+
+/// The remaining unfilled size of an open order in the [`OrdersAccount`].
+#[derive(Debug, Clone)]
+pub struct RemainingOrderSize {
+    /// The open order as stored in the [`OrdersAccount`].
+    pub open_order: OpenOrder,
+    /// The base quantity the order was placed with, in native units, if known.
+    pub original_base_qty: Option<u64>,
+    /// The remaining unfilled base quantity, in native units, if it can be determined.
+    pub remaining_base_qty: Option<u64>,
+}
+
+impl RemainingOrderSize {
+    /// The filled base quantity, in native units, if it can be determined.
+    pub fn filled_base_qty(&self) -> Option<u64> {
+        match (self.original_base_qty, self.remaining_base_qty) {
+            (Some(original), Some(remaining)) => Some(original.saturating_sub(remaining)),
+            _ => None,
+        }
+    }
+
+    /// Whether the order has been partially filled.
+    pub fn is_partially_filled(&self) -> bool {
+        matches!(self.filled_base_qty(), Some(filled) if filled != 0)
+    }
+}
+
+/// Represents a user's [`OrdersAccount`] for a given derivatives market.
+#[derive(Clone)]
+pub struct OrdersAccountContext {
+    /// The orders account's pubkey.
+    pub address: Pubkey,
+    /// The derivatives market.
+    pub market: Pubkey,
+    /// The master account that owns the orders account.
+    pub master_account: Pubkey,
+    /// The orders account's state.
+    pub state: Box<OrdersAccount>,
+}
+
+impl Debug for OrdersAccountContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrdersAccountContext")
+            .field("address", &format!("{}", self.address))
+            .field("market", &format!("{}", self.market))
+            .field("master_account", &format!("{}", self.master_account))
+            .field("order_count", &format!("{}", self.state.order_count))
+            .finish()
+    }
+}
+
+impl OrdersAccountContext {
+    /// Creates a new [`OrdersAccountContext`].
+    pub fn new(address: &Pubkey, state: Box<OrdersAccount>) -> Self {
+        Self {
+            address: *address,
+            market: state.market,
+            master_account: state.master_account,
+            state,
+        }
+    }
+
+    /// Loads the [`OrdersAccount`] of the given master account for the given derivatives market.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request
+    /// or the orders account does not exist.
+    pub async fn load(
+        rpc_client: &impl AccountFetcher,
+        market: &Pubkey,
+        master_account: &Pubkey,
+    ) -> Result<Self, ContextError> {
+        let (address, _) = derive_orders_account_address(market, master_account);
+        match get_cypher_zero_copy_account::<OrdersAccount>(rpc_client, &address).await {
+            Ok(s) => Ok(Self::new(&address, s)),
+            Err(e) => Err(ContextError::ClientError(e)),
+        }
+    }
+
+    /// Loads the [`OrdersAccountContext`] from the given account data.
+    pub fn from_account_data(address: &Pubkey, account_data: &[u8]) -> Self {
+        Self::new(
+            address,
+            get_zero_copy_account::<OrdersAccount>(account_data),
+        )
+    }
+
+    /// Reloads the [`OrdersAccount`]'s state.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn reload(&mut self, rpc_client: &impl AccountFetcher) -> Result<(), ContextError> {
+        self.state =
+            match get_cypher_zero_copy_account::<OrdersAccount>(rpc_client, &self.address).await {
+                Ok(s) => s,
+                Err(e) => {
+                    return Err(ContextError::ClientError(e));
+                }
+            };
+        Ok(())
+    }
+
+    /// Reloads the [`OrdersAccount`]'s state from the given account data.
+    pub fn reload_from_account_data(&mut self, account_data: &[u8]) {
+        self.state = get_zero_copy_account::<OrdersAccount>(account_data);
+    }
+
+    /// Reloads the [`OrdersAccountContext`] from the given [`AccountsCache`],
+    /// if the corresponding OrdersAccount's account state exists in the cache.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account state does not exist in the cache.
+    pub fn reload_from_cache(&mut self, cache: Arc<AccountsCache>) -> Result<(), ContextError> {
+        let account_state = match cache.get(&self.address) {
+            Some(a) => a,
+            None => {
+                return Err(ContextError::MissingAccountState);
+            }
+        };
+
+        self.state = get_zero_copy_account::<OrdersAccount>(&account_state.data);
+
+        Ok(())
+    }
+
+    /// Gets the open orders in the [`OrdersAccount`] joined with their state on the given order book.
+    ///
+    /// Callee must make sure that the given [`GenericOrderBook`] belongs to this context's market.
+    pub fn get_orders(&self, orderbook: &dyn GenericOrderBook) -> Vec<OrdersAccountOrder> {
+        let book = OrderBook::new(orderbook.get_bids(), orderbook.get_asks());
+
+        self.state
+            .get_orders()
+            .into_iter()
+            .map(|open_order| {
+                let queue_position = book.get_queue_position(open_order.order_id, open_order.side);
+                let book_side = if open_order.side == Side::Bid {
+                    &book.bids
+                } else {
+                    &book.asks
+                };
+                let book_order = match queue_position {
+                    Some(_) => book_side
+                        .iter()
+                        .find(|o| o.order_id == open_order.order_id)
+                        .cloned(),
+                    None => None,
+                };
+                OrdersAccountOrder {
+                    open_order: *open_order,
+                    book_order,
+                    queue_position,
+                }
+            })
+            .collect()
+    }
+
+    /// Gets the base and quote amounts locked in open orders by the sub account at the given index, in native units.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    pub fn get_locked_amounts(&self, sub_account_idx: usize) -> Option<(u64, u64)> {
+        let base_locked = self.state.base_token_locked.get(sub_account_idx)?;
+        let quote_locked = self.state.quote_token_locked.get(sub_account_idx)?;
+        Some((*base_locked, *quote_locked))
+    }
+
+    /// Gets the total value locked in open orders across all sub accounts, valued at oracle prices.
+    ///
+    /// The `market_cache` should be the [`Cache`] of this context's market and the `quote_cache`
+    /// the [`Cache`] of the quote token.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value overflows or the market's decimals are not supported.
+    pub fn get_locked_value(
+        &self,
+        market_state: &dyn Market,
+        market_cache: &Cache,
+        quote_cache: &Cache,
+    ) -> Result<I80F48, MathError> {
+        let mut base_locked = I80F48::ZERO;
+        for locked in self.state.base_token_locked.iter() {
+            base_locked = base_locked
+                .checked_add(I80F48::from(*locked))
+                .ok_or(MathError::Overflow("base locked"))?;
+        }
+        let mut quote_locked = I80F48::ZERO;
+        for locked in self.state.quote_token_locked.iter() {
+            quote_locked = quote_locked
+                .checked_add(I80F48::from(*locked))
+                .ok_or(MathError::Overflow("quote locked"))?;
+        }
+
+        let base_locked_value = try_adjust_decimals(base_locked, market_state.decimals())?
+            .checked_mul(market_cache.oracle_price())
+            .ok_or(MathError::Overflow("base locked value"))?;
+        let quote_locked_value = try_adjust_decimals(quote_locked, QUOTE_TOKEN_DECIMALS)?
+            .checked_mul(quote_cache.oracle_price())
+            .ok_or(MathError::Overflow("quote locked value"))?;
+
+        base_locked_value
+            .checked_add(quote_locked_value)
+            .ok_or(MathError::Overflow("locked value"))
+    }
+
+    /// Gets the remaining unfilled size of each open order of the sub account at the given index.
+    ///
+    /// The amounts locked in the sub account's [`OpenOrdersCache`] for this context's market are
+    /// attributed to the open orders, given the base quantity, in native units, each order was placed with.
+    /// Fills are assumed to happen in price-time priority, so the best priced and oldest orders on each side
+    /// are considered filled first.
+    ///
+    /// If the original sizes of the orders on a side are not all known, the remaining size can only be
+    /// determined for a single resting ask, otherwise it is `None`.
+    pub fn get_remaining_sizes(
+        &self,
+        market_state: &dyn Market,
+        sub_account_idx: u8,
+        open_orders_cache: &OpenOrdersCache,
+        original_sizes: &HashMap<u128, u64>,
+    ) -> Vec<RemainingOrderSize> {
+        let mut remaining_sizes = Vec::new();
+        for side in [Side::Bid, Side::Ask] {
+            let mut orders = self
+                .state
+                .get_orders()
+                .into_iter()
+                .filter(|o| o.sub_account_idx == sub_account_idx && o.side == side)
+                .collect::<Vec<&OpenOrder>>();
+            // best priced orders first, then oldest
+            orders.sort_by(|a, b| {
+                let (a_price, b_price) = ((a.order_id >> 64) as u64, (b.order_id >> 64) as u64);
+                let price_ordering = if side == Side::Bid {
+                    b_price.cmp(&a_price)
+                } else {
+                    a_price.cmp(&b_price)
+                };
+                price_ordering.then(a.timestamp.cmp(&b.timestamp))
+            });
+
+            // the amount locked on this side, in base units for asks and quote units for bids
+            let locked = if side == Side::Bid {
+                open_orders_cache
+                    .pc_total
+                    .saturating_sub(open_orders_cache.pc_free)
+            } else {
+                open_orders_cache.coin_locked()
+            };
+            // the original amount of each order, in the same units as the locked amount
+            let originals = orders
+                .iter()
+                .map(|o| {
+                    let base_qty = original_sizes.get(&o.order_id).copied()?;
+                    let locked_qty = if side == Side::Bid {
+                        market_state.get_quote_from_base(base_qty, (o.order_id >> 64) as u64)?
+                    } else {
+                        base_qty
+                    };
+                    Some((base_qty, locked_qty))
+                })
+                .collect::<Vec<Option<(u64, u64)>>>();
+
+            if originals.iter().all(|o| o.is_some()) {
+                let total = originals
+                    .iter()
+                    .flatten()
+                    .fold(0u64, |acc, (_, l)| acc.saturating_add(*l));
+                let mut filled = total.saturating_sub(locked);
+                for (order, original) in orders.iter().zip(originals.iter().flatten()) {
+                    let (base_qty, locked_qty) = *original;
+                    let order_filled = filled.min(locked_qty);
+                    filled -= order_filled;
+                    let remaining_locked = locked_qty - order_filled;
+                    let remaining_base_qty = if side == Side::Bid && locked_qty != 0 {
+                        (base_qty as u128 * remaining_locked as u128 / locked_qty as u128) as u64
+                    } else {
+                        remaining_locked
+                    };
+                    remaining_sizes.push(RemainingOrderSize {
+                        open_order: **order,
+                        original_base_qty: Some(base_qty),
+                        remaining_base_qty: Some(remaining_base_qty),
+                    });
+                }
+            } else {
+                let single_ask = side == Side::Ask && orders.len() == 1;
+                for (order, original) in orders.iter().zip(originals.iter()) {
+                    remaining_sizes.push(RemainingOrderSize {
+                        open_order: **order,
+                        original_base_qty: original.map(|(b, _)| b),
+                        remaining_base_qty: if single_ask { Some(locked) } else { None },
+                    });
+                }
+            }
+        }
+        remaining_sizes
+    }
+}
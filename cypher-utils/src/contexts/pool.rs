@@ -1,147 +1,25 @@
 use cypher_client::{utils::get_zero_copy_account, Pool, PoolNode};
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_filter::RpcFilterType};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 use std::{fmt::Debug, sync::Arc};
 
 use crate::{
     accounts_cache::AccountsCache,
+    layouts::get_data_size_filter,
+    transport::AccountFetcher,
     utils::{
         get_cypher_zero_copy_account, get_multiple_cypher_zero_copy_accounts, get_program_accounts,
     },
 };
 
-use super::ContextError;
+use super::{macros::account_context, ContextError};
 
-/// Represents a [PoolNode]
-#[derive(Default, Clone)]
-pub struct PoolNodeContext {
-    pub address: Pubkey,
-    pub state: Box<PoolNode>,
-}
-
-impl Debug for PoolNodeContext {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("PoolNodeContext")
-            .field("address", &format!("{}", self.address))
-            .finish()
-    }
-}
-
-impl PoolNodeContext {
-    /// Creates a new [`PoolNodeContext`]
-    pub fn new(address: &Pubkey, state: Box<PoolNode>) -> Self {
-        Self {
-            address: *address,
-            state,
-        }
-    }
-
-    /// Loads the [`PoolNode`] from the given account data.
-    ///
-    /// ### Errors
-    ///
-    /// This function will return an error if the account data is invalid.
-    pub fn from_account_data(account_data: &[u8], pool_node: &Pubkey) -> Self {
-        let state = get_zero_copy_account::<PoolNode>(account_data);
-
-        Self::new(pool_node, state)
-    }
-
-    /// Loads the given [`PoolNode`].
-    ///
-    /// ### Errors
-    ///
-    /// This function will return an error if something goes wrong during the RPC request
-    /// or the Pool's [`Pubkey`] given is not a valid [`PoolNode`] Account.
-    pub async fn load(
-        rpc_client: &Arc<RpcClient>,
-        pool_node: &Pubkey,
-    ) -> Result<Self, ContextError> {
-        match get_cypher_zero_copy_account::<PoolNode>(rpc_client, pool_node).await {
-            Ok(s) => Ok(Self::new(pool_node, s)),
-            Err(e) => Err(ContextError::ClientError(e)),
-        }
-    }
-
-    /// Loads the given [`PoolNode`]s, if they exist.
-    ///
-    /// ### Errors
-    ///
-    /// This function will return an error if something goes wrong during the RPC request,
-    /// the [`Pubkey`] given is not a valid [`PoolNode`] Account or the underlying account does not
-    /// have the correct Anchor discriminator for the provided type.
-    pub async fn load_many(
-        rpc_client: &Arc<RpcClient>,
-        pool_nodes: &[Pubkey],
-    ) -> Result<Vec<Self>, ContextError> {
-        match get_multiple_cypher_zero_copy_accounts::<PoolNode>(rpc_client, pool_nodes).await {
-            Ok(s) => Ok(s
-                .iter()
-                .enumerate()
-                .map(|(idx, state)| Self::new(&pool_nodes[idx], state.clone()))
-                .collect()),
-            Err(e) => Err(ContextError::ClientError(e)),
-        }
-    }
-
-    /// Loads all [`PoolNode`], if they exist.
-    ///
-    /// ### Errors
-    ///
-    /// This function will return an error if something goes wrong during the RPC request.
-    pub async fn load_all(rpc_client: &Arc<RpcClient>) -> Result<Vec<Self>, ContextError> {
-        let filters = vec![RpcFilterType::DataSize(
-            std::mem::size_of::<PoolNode>() as u64 + 8,
-        )];
-        match get_program_accounts(rpc_client, filters, &cypher_client::id()).await {
-            Ok(s) => Ok(s
-                .iter()
-                .map(|state| Self::new(&state.0, get_zero_copy_account::<PoolNode>(&state.1.data)))
-                .collect()),
-            Err(e) => Err(ContextError::ClientError(e)),
-        }
-    }
-
-    /// Reloads the [`PoolNode`]'s state.
-    ///
-    /// # Errors
-    ///
-    /// This function will return an error if something goes wrong during the RPC request.
-    pub async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
-        self.state = match get_cypher_zero_copy_account::<PoolNode>(rpc_client, &self.address).await
-        {
-            Ok(s) => s,
-            Err(e) => {
-                return Err(ContextError::ClientError(e));
-            }
-        };
-        Ok(())
-    }
-
-    /// Reloads the [`PoolNode`]'s state from the given account data.
-    pub fn reload_from_account_data(&mut self, account_data: &[u8]) {
-        self.state = get_zero_copy_account::<PoolNode>(account_data);
-    }
-
-    /// Reloads the [`PoolNode`] from the given [`AccountsCache`],
-    /// if the corresponding EventQueue's account state exists in the cache.
-    ///
-    /// ### Errors
-    ///
-    /// This function will return an error if the account state does not exist in the cache.
-    pub fn reload_from_cache(&mut self, cache: Arc<AccountsCache>) -> Result<(), ContextError> {
-        let cache_state = match cache.get(&self.address) {
-            Some(a) => a,
-            None => {
-                return Err(ContextError::MissingAccountState);
-            }
-        };
-
-        self.state = get_zero_copy_account(&cache_state.data);
-
-        Ok(())
-    }
-}
+account_context!(
+    /// Represents a [PoolNode]
+    #[derive(Default)]
+    PoolNodeContext,
+    PoolNode
+);
 
 /// Represents a [Pool].
 #[derive(Default, Clone)]
@@ -186,7 +64,10 @@ impl PoolContext {
     ///
     /// This function will return an error if something goes wrong during the RPC request
     /// or the Pool's [`Pubkey`] given is not a valid [`Pool`] Account.
-    pub async fn load(rpc_client: &Arc<RpcClient>, pool: &Pubkey) -> Result<Self, ContextError> {
+    pub async fn load(
+        rpc_client: &impl AccountFetcher,
+        pool: &Pubkey,
+    ) -> Result<Self, ContextError> {
         let pool_state = match get_cypher_zero_copy_account::<Pool>(rpc_client, pool).await {
             Ok(s) => s,
             Err(e) => {
@@ -216,7 +97,7 @@ impl PoolContext {
     /// the [`Pubkey`] given is not a valid [`Pool`] Account or the underlying account does not
     /// have the correct Anchor discriminator for the provided type.
     pub async fn load_many(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         pools: &[Pubkey],
     ) -> Result<Vec<Self>, ContextError> {
         match get_multiple_cypher_zero_copy_accounts::<Pool>(rpc_client, pools).await {
@@ -235,9 +116,7 @@ impl PoolContext {
     ///
     /// This function will return an error if something goes wrong during the RPC request.
     pub async fn load_all(rpc_client: &Arc<RpcClient>) -> Result<Vec<Self>, ContextError> {
-        let filters = vec![RpcFilterType::DataSize(
-            std::mem::size_of::<Pool>() as u64 + 8,
-        )];
+        let filters = vec![get_data_size_filter::<Pool>()];
         let mut pools = match get_program_accounts(rpc_client, filters, &cypher_client::id()).await
         {
             Ok(s) => s
@@ -278,7 +157,7 @@ impl PoolContext {
     /// # Errors
     ///
     /// This function will return an error if something goes wrong during the RPC request.
-    pub async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+    pub async fn reload(&mut self, rpc_client: &impl AccountFetcher) -> Result<(), ContextError> {
         self.state = match get_cypher_zero_copy_account::<Pool>(rpc_client, &self.address).await {
             Ok(s) => s,
             Err(e) => {
@@ -0,0 +1,163 @@
+use cypher_client::{Cache, PriceHistory};
+use fixed::types::I80F48;
+
+use super::macros::account_context;
+
+account_context!(
+    /// Represents a [`PriceHistory`], which stores the prices used to compute the on-chain TWAP of a futures market
+    /// or the rolling TWAP of an oracle price feed.
+    PriceHistoryContext,
+    PriceHistory
+);
+
+/// A price stored in a [`PriceHistory`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PricePoint {
+    /// The price.
+    pub price: I80F48,
+    /// The unix timestamp at which the price was collected.
+    pub timestamp: u64,
+}
+
+/// The TWAPs of a [`PriceHistory`] joined with the prices of it's [`Cache`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TwapInspection {
+    /// The oracle price of the cache.
+    pub oracle_price: I80F48,
+    /// The market price of the cache, i.e. the TWAP last written by the program.
+    pub market_price: I80F48,
+    /// The TWAP over the shorter time horizon, computed from the price history.
+    pub shorter_twap: Option<I80F48>,
+    /// The TWAP over the longer time horizon, computed from the price history.
+    pub longer_twap: Option<I80F48>,
+    /// The timestamp of the most recently collected price.
+    pub last_collected_at: Option<u64>,
+}
+
+impl TwapInspection {
+    /// Gets the deviation of the shorter TWAP from the longer TWAP, in basis points.
+    ///
+    /// Returns `None` if either TWAP can not be computed or the longer TWAP is zero.
+    pub fn twap_deviation_bps(&self) -> Option<I80F48> {
+        get_deviation_bps(self.shorter_twap?, self.longer_twap?)
+    }
+
+    /// Gets the deviation of the cache's market price from the longer TWAP, in basis points.
+    ///
+    /// Returns `None` if the longer TWAP can not be computed or is zero.
+    pub fn market_price_deviation_bps(&self) -> Option<I80F48> {
+        get_deviation_bps(self.market_price, self.longer_twap?)
+    }
+
+    /// Gets the deviation of the cache's oracle price from the longer TWAP, in basis points.
+    ///
+    /// Returns `None` if the longer TWAP can not be computed or is zero.
+    pub fn oracle_price_deviation_bps(&self) -> Option<I80F48> {
+        get_deviation_bps(self.oracle_price, self.longer_twap?)
+    }
+
+    /// Whether the shorter TWAP deviates from the longer TWAP by more than the given threshold, in basis points.
+    pub fn exceeds_threshold(&self, threshold_bps: u16) -> bool {
+        match self.twap_deviation_bps() {
+            Some(d) => d.abs() > I80F48::from(threshold_bps),
+            None => false,
+        }
+    }
+}
+
+/// Gets the signed deviation of the given price from the given reference price, in basis points.
+fn get_deviation_bps(price: I80F48, reference: I80F48) -> Option<I80F48> {
+    if reference == I80F48::ZERO {
+        return None;
+    }
+    Some((price - reference) / reference * I80F48::from(10_000))
+}
+
+impl PriceHistoryContext {
+    /// Gets the stored price points from the given head up to the tail, i.e. the most recently collected price,
+    /// sorted from oldest to newest.
+    ///
+    /// Slots which have never been written to are skipped.
+    fn get_price_points_from(&self, head: u16) -> Vec<PricePoint> {
+        let len = self.state.data.len();
+        let head = head as usize % len;
+        let tail = self.state.tail as usize % len;
+        let count = if tail >= head {
+            tail - head + 1
+        } else {
+            len - head + tail + 1
+        };
+
+        (0..count)
+            .map(|i| &self.state.data[(head + i) % len])
+            .filter(|p| p.timestamp != 0)
+            .map(|p| PricePoint {
+                price: I80F48::from_bits(p.price),
+                timestamp: p.timestamp,
+            })
+            .collect()
+    }
+
+    /// Gets the price points within the longer time horizon, sorted from oldest to newest.
+    pub fn get_price_points(&self) -> Vec<PricePoint> {
+        self.get_price_points_from(self.state.longer_head)
+    }
+
+    /// Gets the price points within the shorter time horizon, sorted from oldest to newest.
+    pub fn get_shorter_price_points(&self) -> Vec<PricePoint> {
+        self.get_price_points_from(self.state.shorter_head)
+    }
+
+    /// Gets the most recently collected price point, if any price has been collected.
+    pub fn get_last_price_point(&self) -> Option<PricePoint> {
+        let point = &self.state.data[self.state.tail as usize % self.state.data.len()];
+        if point.timestamp == 0 {
+            return None;
+        }
+        Some(PricePoint {
+            price: I80F48::from_bits(point.price),
+            timestamp: point.timestamp,
+        })
+    }
+
+    /// Computes the TWAP over the longer time horizon.
+    ///
+    /// Prices are collected once per price collection tick, so the TWAP is the average of the stored prices.
+    /// Returns `None` if there are no prices within the time horizon.
+    pub fn longer_twap(&self) -> Option<I80F48> {
+        get_average_price(&self.get_price_points())
+    }
+
+    /// Computes the TWAP over the shorter time horizon.
+    ///
+    /// Prices are collected once per price collection tick, so the TWAP is the average of the stored prices.
+    /// Returns `None` if there are no prices within the time horizon.
+    pub fn shorter_twap(&self) -> Option<I80F48> {
+        get_average_price(&self.get_shorter_price_points())
+    }
+
+    /// Joins the computed TWAPs with the prices of the given [`Cache`], which is expected to be the cache
+    /// of the market or oracle products this price history belongs to.
+    ///
+    /// This is useful to debug why an order was rejected by the price bands.
+    pub fn inspect(&self, cache: &Cache) -> TwapInspection {
+        TwapInspection {
+            oracle_price: cache.oracle_price(),
+            market_price: cache.market_price(),
+            shorter_twap: self.shorter_twap(),
+            longer_twap: self.longer_twap(),
+            last_collected_at: self.get_last_price_point().map(|p| p.timestamp),
+        }
+    }
+}
+
+/// Gets the average price of the given price points.
+fn get_average_price(points: &[PricePoint]) -> Option<I80F48> {
+    if points.is_empty() {
+        return None;
+    }
+    let sum = points
+        .iter()
+        .fold(I80F48::ZERO, |acc, p| acc.saturating_add(p.price));
+    Some(sum / I80F48::from_num(points.len()))
+}
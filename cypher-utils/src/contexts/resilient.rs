@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::retry::{is_transient_error, RetryPolicy};
+
+use super::{ContextError, ContextManager, Loadable, UserContext};
+
+/// A context which can be reloaded in place, see [`ResilientContext`].
+#[async_trait]
+pub trait Reloadable: Send + Sync {
+    /// Reloads the context's state.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests.
+    async fn reload_context(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError>;
+}
+
+#[async_trait]
+impl<T: Loadable> Reloadable for T {
+    async fn reload_context(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        self.reload(rpc_client).await
+    }
+}
+
+#[async_trait]
+impl Reloadable for UserContext {
+    async fn reload_context(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        self.reload(rpc_client).await
+    }
+}
+
+#[async_trait]
+impl Reloadable for ContextManager {
+    /// Reloads every context of the [`ContextManager`], with it's own RPC client.
+    async fn reload_context(&mut self, _rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+        self.reload().await.map(|_| ())
+    }
+}
+
+/// Whether the given error is transient, i.e. the same reload may succeed if retried, see [`is_transient_error`].
+pub fn is_transient_context_error(error: &ContextError) -> bool {
+    match error {
+        ContextError::ClientError(e) => is_transient_error(e),
+        _ => false,
+    }
+}
+
+/// Reloads the given context, retrying it according to the given policy as long as it fails with a transient error.
+///
+/// ### Errors
+///
+/// This function will return the last error if the reload failed with a non-transient error
+/// or the policy's maximum number of attempts was reached.
+pub async fn reload_with_retry<T: Reloadable + ?Sized>(
+    context: &mut T,
+    rpc_client: &Arc<RpcClient>,
+    policy: &RetryPolicy,
+) -> Result<(), ContextError> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match context.reload_context(rpc_client).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                if attempt >= policy.max_attempts || !is_transient_context_error(&e) {
+                    return Err(e);
+                }
+                let backoff = policy.get_backoff(attempt - 1);
+                warn!(
+                    "Reload failed with a transient error on attempt {}/{}, retrying in {}ms: {}",
+                    attempt,
+                    policy.max_attempts,
+                    backoff.as_millis(),
+                    e.to_string()
+                );
+                tokio::time::sleep(backoff).await;
+            }
+        }
+    }
+}
+
+/// The policy of a [`ResilientContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReloadPolicy {
+    /// The policy used to retry a reload which failed with a transient error.
+    pub retry: RetryPolicy,
+    /// How long after the last successful reload the context's state may still be served, marked as degraded.
+    pub stale_tolerance: Duration,
+}
+
+impl Default for ReloadPolicy {
+    fn default() -> Self {
+        Self {
+            retry: RetryPolicy::default(),
+            stale_tolerance: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The health of a [`ResilientContext`]'s state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextHealth {
+    /// The last reload succeeded within the stale tolerance.
+    Fresh,
+    /// The last reloads failed, the last known good state is served and is within the stale tolerance.
+    Degraded,
+    /// The last successful reload is older than the stale tolerance, the state should not be acted upon.
+    Stale,
+}
+
+/// Wraps a context so that failed reloads do not interrupt the service using it.
+///
+/// Reloads are retried according to the [`ReloadPolicy`], if they still fail the last known good state
+/// keeps being served but is marked as [`ContextHealth::Degraded`], until the stale tolerance has passed
+/// since the last successful reload and it becomes [`ContextHealth::Stale`]. Services can then keep running,
+/// e.g. keep reporting, while refraining from actions which require fresh state, e.g. placing orders.
+///
+/// Contexts which may be partially reloaded, such as the [`ContextManager`], hold a mix of previous and newly
+/// fetched account states after a failed reload, each of which was valid when it was fetched.
+pub struct ResilientContext<T> {
+    context: T,
+    policy: ReloadPolicy,
+    last_success: Instant,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+impl<T: Debug> Debug for ResilientContext<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResilientContext")
+            .field("context", &self.context)
+            .field("health", &self.health())
+            .field("consecutive_failures", &self.consecutive_failures)
+            .field("last_error", &self.last_error)
+            .finish()
+    }
+}
+
+impl<T: Reloadable> ResilientContext<T> {
+    /// Creates a new [`ResilientContext`] for the given freshly loaded context.
+    pub fn new(context: T, policy: ReloadPolicy) -> Self {
+        Self {
+            context,
+            policy,
+            last_success: Instant::now(),
+            consecutive_failures: 0,
+            last_error: None,
+        }
+    }
+
+    /// Reloads the context, see [`reload_with_retry`], returning the resulting health of it's state.
+    ///
+    /// A failed reload is logged and recorded instead of being returned, see [`ResilientContext::last_error`].
+    pub async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> ContextHealth {
+        match reload_with_retry(&mut self.context, rpc_client, &self.policy.retry).await {
+            Ok(()) => {
+                self.last_success = Instant::now();
+                self.consecutive_failures = 0;
+                self.last_error = None;
+            }
+            Err(e) => {
+                self.consecutive_failures += 1;
+                warn!(
+                    "Failed to reload context {} times in a row, serving state from {}ms ago: {}",
+                    self.consecutive_failures,
+                    self.age().as_millis(),
+                    e.to_string()
+                );
+                self.last_error = Some(e.to_string());
+            }
+        }
+        self.health()
+    }
+
+    /// Gets the health of the context's state.
+    pub fn health(&self) -> ContextHealth {
+        if self.age() > self.policy.stale_tolerance {
+            ContextHealth::Stale
+        } else if self.consecutive_failures != 0 {
+            ContextHealth::Degraded
+        } else {
+            ContextHealth::Fresh
+        }
+    }
+
+    /// Whether the context's state is not fresh, i.e. it is degraded or stale.
+    pub fn is_degraded(&self) -> bool {
+        self.health() != ContextHealth::Fresh
+    }
+
+    /// Whether the context's state is stale.
+    pub fn is_stale(&self) -> bool {
+        self.health() == ContextHealth::Stale
+    }
+
+    /// Gets the time since the last successful reload.
+    pub fn age(&self) -> Duration {
+        self.last_success.elapsed()
+    }
+
+    /// Gets the number of reloads which failed since the last successful one.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Gets the error of the last reload, if it failed.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Gets the last known good state of the context, regardless of it's health.
+    pub fn get(&self) -> &T {
+        &self.context
+    }
+
+    /// Gets the last known good state of the context mutably, regardless of it's health.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.context
+    }
+
+    /// Gets the state of the context if it is not stale.
+    pub fn get_unless_stale(&self) -> Option<&T> {
+        if self.is_stale() {
+            return None;
+        }
+        Some(&self.context)
+    }
+
+    /// Consumes the [`ResilientContext`], returning the context.
+    pub fn into_inner(self) -> T {
+        self.context
+    }
+}
@@ -0,0 +1,270 @@
+use cypher_client::{
+    cache_account, utils::get_zero_copy_account, CypherSubAccount, FuturesMarket, Market,
+    PerpetualMarket, Side,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{fmt::Debug, sync::Arc};
+
+use super::{
+    AgnosticOrderBookContext, CacheContext, ContextError, MarketContext, OrderBook,
+    SubAccountContext,
+};
+
+/// The maximum number of accounts which can be read in one snapshot, i.e. in one `getMultipleAccounts` request.
+pub const MAX_SNAPSHOT_ACCOUNTS: usize = 100;
+
+/// A set of accounts which were all observed at the same slot.
+#[derive(Debug, Clone)]
+pub struct AccountsSnapshot {
+    /// The context slot of the request.
+    pub slot: u64,
+    /// The requested accounts, in the order they were requested, and their state if they exist.
+    pub accounts: Vec<(Pubkey, Option<Account>)>,
+}
+
+impl AccountsSnapshot {
+    /// Fetches the given accounts with one `getMultipleAccounts` request at the given commitment.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if more than [`MAX_SNAPSHOT_ACCOUNTS`] accounts are given,
+    /// since they could not be observed at the same slot, or if something goes wrong during the RPC request.
+    pub async fn fetch(
+        rpc_client: &Arc<RpcClient>,
+        accounts: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> Result<Self, ContextError> {
+        if accounts.len() > MAX_SNAPSHOT_ACCOUNTS {
+            return Err(ContextError::TooManyAccounts(accounts.len()));
+        }
+
+        let res = match rpc_client
+            .get_multiple_accounts_with_commitment(accounts, commitment)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+
+        Ok(Self {
+            slot: res.context.slot,
+            accounts: accounts.iter().copied().zip(res.value).collect(),
+        })
+    }
+
+    /// Gets the data of the given account, if it was requested and exists.
+    pub fn get_account_data(&self, account: &Pubkey) -> Option<&[u8]> {
+        self.accounts
+            .iter()
+            .find(|(key, _)| key == account)
+            .and_then(|(_, a)| a.as_ref())
+            .map(|a| a.data.as_slice())
+    }
+
+    /// Gets the data of the given account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account was not requested or does not exist.
+    fn get_existing_account_data(&self, account: &Pubkey) -> Result<&[u8], ContextError> {
+        match self.get_account_data(account) {
+            Some(d) => Ok(d),
+            None => Err(ContextError::AccountNotFound(format!(
+                "Account {} does not exist in snapshot at slot {}",
+                account, self.slot
+            ))),
+        }
+    }
+}
+
+/// The accounts needed to value sub accounts, i.e. the sub accounts, the cache account, the markets and their orderbooks,
+/// all observed at the same slot.
+///
+/// Valuations computed from this snapshot are consistent, unlike valuations computed from contexts which were
+/// reloaded independently and may have observed their accounts at different slots.
+pub struct ValuationSnapshot {
+    /// The slot at which all of the accounts were observed.
+    pub slot: u64,
+    /// The cache account.
+    pub cache: CacheContext,
+    /// The sub accounts.
+    pub sub_accounts: Vec<SubAccountContext>,
+    /// The perpetual markets.
+    pub perp_markets: Vec<MarketContext<PerpetualMarket>>,
+    /// The futures markets.
+    pub futures_markets: Vec<MarketContext<FuturesMarket>>,
+    /// The orderbooks of the perpetual and futures markets.
+    pub orderbooks: Vec<AgnosticOrderBookContext>,
+}
+
+impl Debug for ValuationSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValuationSnapshot")
+            .field("slot", &self.slot)
+            .field("sub_accounts", &self.sub_accounts.len())
+            .field("perp_markets", &self.perp_markets.len())
+            .field("futures_markets", &self.futures_markets.len())
+            .finish()
+    }
+}
+
+impl ValuationSnapshot {
+    /// Loads the given sub accounts, the cache account, the given markets and their orderbooks at the same slot.
+    ///
+    /// The given market contexts are only used to know the markets' orderbook accounts, their states are
+    /// fetched again as part of the snapshot.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the accounts do not fit in one snapshot, if something goes wrong
+    /// during the RPC request or if one of the accounts does not exist.
+    pub async fn load(
+        rpc_client: &Arc<RpcClient>,
+        commitment: CommitmentConfig,
+        sub_accounts: &[Pubkey],
+        perp_markets: &[MarketContext<PerpetualMarket>],
+        futures_markets: &[MarketContext<FuturesMarket>],
+    ) -> Result<Self, ContextError> {
+        let mut keys = vec![cache_account::id()];
+        keys.extend(sub_accounts.iter().copied());
+        for market in perp_markets.iter() {
+            keys.extend([
+                market.address,
+                market.state.inner.bids,
+                market.state.inner.asks,
+            ]);
+        }
+        for market in futures_markets.iter() {
+            keys.extend([
+                market.address,
+                market.state.inner.bids,
+                market.state.inner.asks,
+            ]);
+        }
+
+        let snapshot = match AccountsSnapshot::fetch(rpc_client, &keys, commitment).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        Self::from_accounts_snapshot(&snapshot, sub_accounts, perp_markets, futures_markets)
+    }
+
+    /// Builds the [`ValuationSnapshot`] from an [`AccountsSnapshot`] which contains the cache account,
+    /// the given sub accounts and the given markets and their orderbooks.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if one of the accounts is missing from the snapshot.
+    pub fn from_accounts_snapshot(
+        snapshot: &AccountsSnapshot,
+        sub_accounts: &[Pubkey],
+        perp_markets: &[MarketContext<PerpetualMarket>],
+        futures_markets: &[MarketContext<FuturesMarket>],
+    ) -> Result<Self, ContextError> {
+        let mut cache = CacheContext::default();
+        match snapshot.get_existing_account_data(&cache_account::id()) {
+            Ok(d) => cache.reload_from_account_data(d),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let mut sub_account_ctxs = Vec::with_capacity(sub_accounts.len());
+        for sub_account in sub_accounts.iter() {
+            match snapshot.get_existing_account_data(sub_account) {
+                Ok(d) => sub_account_ctxs.push(SubAccountContext::new(
+                    *sub_account,
+                    get_zero_copy_account::<CypherSubAccount>(d),
+                )),
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut orderbooks = Vec::with_capacity(perp_markets.len() + futures_markets.len());
+        let mut perp_market_ctxs = Vec::with_capacity(perp_markets.len());
+        for market in perp_markets.iter() {
+            let market_ctx = match snapshot.get_existing_account_data(&market.address) {
+                Ok(d) => MarketContext::<PerpetualMarket>::from_account_data(d, &market.address),
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            match load_orderbook(
+                snapshot,
+                &market.address,
+                market_ctx.state.as_ref(),
+                &market_ctx.state.inner.bids,
+                &market_ctx.state.inner.asks,
+            ) {
+                Ok(ob) => orderbooks.push(ob),
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+            perp_market_ctxs.push(market_ctx);
+        }
+
+        let mut futures_market_ctxs = Vec::with_capacity(futures_markets.len());
+        for market in futures_markets.iter() {
+            let market_ctx = match snapshot.get_existing_account_data(&market.address) {
+                Ok(d) => MarketContext::<FuturesMarket>::from_account_data(d, &market.address),
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            match load_orderbook(
+                snapshot,
+                &market.address,
+                market_ctx.state.as_ref(),
+                &market_ctx.state.inner.bids,
+                &market_ctx.state.inner.asks,
+            ) {
+                Ok(ob) => orderbooks.push(ob),
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+            futures_market_ctxs.push(market_ctx);
+        }
+
+        Ok(Self {
+            slot: snapshot.slot,
+            cache,
+            sub_accounts: sub_account_ctxs,
+            perp_markets: perp_market_ctxs,
+            futures_markets: futures_market_ctxs,
+            orderbooks,
+        })
+    }
+
+    /// Gets the orderbook of the given market, if it is part of the snapshot.
+    pub fn get_orderbook(&self, market: &Pubkey) -> Option<&AgnosticOrderBookContext> {
+        self.orderbooks.iter().find(|ob| ob.market == *market)
+    }
+}
+
+fn load_orderbook(
+    snapshot: &AccountsSnapshot,
+    market: &Pubkey,
+    market_state: &dyn Market,
+    bids: &Pubkey,
+    asks: &Pubkey,
+) -> Result<AgnosticOrderBookContext, ContextError> {
+    let mut orderbook = AgnosticOrderBookContext::new(market, bids, asks, OrderBook::default());
+    for (side, account) in [(Side::Bid, bids), (Side::Ask, asks)] {
+        match snapshot.get_existing_account_data(account) {
+            Ok(d) => orderbook.reload_from_account_data(market_state, d, side),
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+    Ok(orderbook)
+}
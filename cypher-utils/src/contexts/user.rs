@@ -1,17 +1,28 @@
 #![allow(clippy::too_many_arguments)]
-use anchor_spl::token::{spl_token, TokenAccount};
+use anchor_lang::{Owner, ZeroCopy};
+use anchor_spl::{
+    dex::serum_dex::state::OpenOrders,
+    token::{spl_token, TokenAccount},
+};
 use cypher_client::{
-    instructions::deposit_funds,
+    instructions::{
+        cancel_futures_order, cancel_perp_order, cancel_spot_order, deposit_funds,
+        new_futures_order, new_perp_order, new_spot_order, settle_futures_funds, settle_perp_funds,
+        settle_spot_funds,
+    },
+    math::MathError,
     utils::{
-        derive_pool_node_vault_address, derive_pool_node_vault_signer_address,
-        derive_token_address, get_zero_copy_account,
+        derive_orders_account_address, derive_pool_node_address, derive_pool_node_vault_address,
+        derive_pool_node_vault_signer_address, derive_spot_open_orders_address,
+        derive_token_address, gen_dex_vault_signer_key, get_zero_copy_account,
     },
-    wrapped_sol, DerivativePosition, MarginCollateralRatioType, PositionSlot, SpotPosition,
-    SubAccountMargining,
+    wrapped_sol, AgnosticMarket, CancelOrderArgs, DerivativePosition, FuturesMarket,
+    MarginCollateralRatioType, NewDerivativeOrderArgs, NewSpotOrderArgs, PerpetualMarket,
+    PositionSlot, Side, SpotPosition, SubAccountMargining,
 };
 use fixed::types::I80F48;
 use solana_sdk::{instruction::Instruction, signature::Signature};
-use std::fmt::Debug;
+use std::{collections::BTreeMap, fmt::Debug};
 use {
     cypher_client::{
         instructions::{create_account, create_sub_account, withdraw_funds},
@@ -23,12 +34,26 @@ use {
     std::sync::Arc,
 };
 
-use crate::utils::{
-    create_transaction, encode_string, get_create_account_ix, get_cypher_zero_copy_account,
-    get_multiple_cypher_zero_copy_accounts, send_transaction, send_transactions,
+#[cfg(feature = "services")]
+use crate::{
+    oms::{
+        debug_assert_derivative_order, debug_assert_spot_order, get_cancel_all_orders_ixs,
+        CancelOrdersAccounts,
+    },
+    services::MarketParams,
 };
+use crate::{
+    transport::AccountFetcher,
+    utils::{
+        create_transaction, encode_string, get_create_account_ix, get_cypher_zero_copy_account,
+        get_deposit_requirement, get_dex_account, get_wallet_balance, send_transaction,
+        send_transactions,
+    },
+};
+#[cfg(feature = "services")]
+use cypher_client::OrdersAccount;
 
-use super::{CacheContext, ContextError};
+use super::{CacheContext, ContextError, MarketContext, PoolContext, SpotMarketContext};
 
 /// Represents a [`CypherSubAccount`].
 #[derive(Default, Clone)]
@@ -44,6 +69,11 @@ impl SubAccountContext {
         Self { address, state }
     }
 
+    /// The on-chain sub account number, which the sub account's address is derived from.
+    pub fn account_number(&self) -> u8 {
+        self.state.account_number_seed[0]
+    }
+
     /// Gets the derivative position associated with the given identifier.
     ///
     /// The identifier should be the SPL Token Mint pubkey.
@@ -98,6 +128,92 @@ impl AccountContext {
     pub fn new(address: Pubkey, state: Box<CypherAccount>) -> Self {
         Self { address, state }
     }
+
+    /// Gets the approximate health of the account from the [`cypher_client::SubAccountCache`]s stored
+    /// in the [`CypherAccount`], see [`CachedHealth`].
+    ///
+    /// Caches updated more than `max_age` seconds before the given unix timestamp are considered stale.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the cached values overflow when summed or divided,
+    /// e.g. because of corrupted or tiny cached liabilities.
+    pub fn get_cached_health(&self, now: u64, max_age: u64) -> Result<CachedHealth, MathError> {
+        let mut health = CachedHealth {
+            c_ratio: I80F48::MAX,
+            oldest_updated_at: u64::MAX,
+            ..Default::default()
+        };
+
+        for cache in self.state.sub_account_caches.iter() {
+            if cache.sub_account == Pubkey::default()
+                || cache.margining != SubAccountMargining::Cross
+            {
+                continue;
+            }
+            health.assets_value = health
+                .assets_value
+                .checked_add(cache.assets_value())
+                .ok_or(MathError::Overflow("cached assets value"))?;
+            health.liabilities_value = health
+                .liabilities_value
+                .checked_add(cache.liabilities_value())
+                .ok_or(MathError::Overflow("cached liabilities value"))?;
+            health.oldest_updated_at = health.oldest_updated_at.min(cache.updated_at);
+            if now.saturating_sub(cache.updated_at) > max_age {
+                health.stale_sub_accounts.push(cache.sub_account);
+            }
+        }
+
+        if health.oldest_updated_at == u64::MAX {
+            health.oldest_updated_at = self.state.updated_at;
+        }
+        if health.liabilities_value != I80F48::ZERO {
+            health.c_ratio = health
+                .assets_value
+                .checked_div(health.liabilities_value)
+                .ok_or(MathError::Overflow("cached c-ratio"))?;
+        }
+
+        Ok(health)
+    }
+}
+
+/// The approximate health of a [`CypherAccount`], computed from the [`cypher_client::SubAccountCache`]s
+/// of it's cross margined sub accounts without loading them.
+///
+/// The cached values are only as recent as the last instruction which updated each sub account, they do not reflect
+/// price moves since then. This is meant for low-latency screening, e.g. to decide which accounts need the full
+/// computation with [`UserContext::get_margin_c_ratio`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CachedHealth {
+    /// The cached assets value.
+    pub assets_value: I80F48,
+    /// The cached liabilities value.
+    pub liabilities_value: I80F48,
+    /// The c-ratio derived from the cached values, [`I80F48::MAX`] if there are no liabilities.
+    pub c_ratio: I80F48,
+    /// The unix timestamp of the least recently updated cache.
+    pub oldest_updated_at: u64,
+    /// The sub accounts whose cache is older than the maximum age.
+    pub stale_sub_accounts: Vec<Pubkey>,
+}
+
+impl CachedHealth {
+    /// The cached equity.
+    pub fn equity(&self) -> I80F48 {
+        self.assets_value.saturating_sub(self.liabilities_value)
+    }
+
+    /// Whether every cache was updated within the maximum age.
+    pub fn is_fresh(&self) -> bool {
+        self.stale_sub_accounts.is_empty()
+    }
+
+    /// Whether the full computation is needed, i.e. the caches are stale or the cached c-ratio is below the given one.
+    pub fn needs_full_check(&self, min_c_ratio: I80F48) -> bool {
+        !self.is_fresh() || self.c_ratio < min_c_ratio
+    }
 }
 
 /// Represents a cypher user context.
@@ -112,6 +228,7 @@ pub struct UserContext {
     pub authority: Pubkey,
 
     pub account_ctx: AccountContext,
+    /// The sub accounts, ordered by their sub account number.
     pub sub_account_ctxs: Vec<SubAccountContext>,
 }
 
@@ -209,7 +326,7 @@ impl UserContext {
     /// This function will return an error if something goes wrong during the RPC
     /// request or any of the Accounts have an invalid Anchor discriminator.
     pub async fn load(
-        rpc_client: &Arc<RpcClient>,
+        rpc_client: &impl AccountFetcher,
         authority: &Pubkey,
         account_number: Option<u8>,
     ) -> Result<Self, ContextError> {
@@ -226,34 +343,11 @@ impl UserContext {
             }
         };
 
-        let sub_accounts = account_state
-            .sub_account_caches
-            .iter()
-            .filter(|a| a.sub_account != Pubkey::default())
-            .map(|a| a.sub_account)
-            .collect::<Vec<Pubkey>>();
-
-        let sub_account_ctxs = if !sub_accounts.is_empty() {
-            match get_multiple_cypher_zero_copy_accounts::<CypherSubAccount>(
-                rpc_client,
-                &sub_accounts,
-            )
-            .await
-            {
-                Ok(s) => s
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, a)| SubAccountContext {
-                        address: sub_accounts[idx],
-                        state: a.clone(),
-                    })
-                    .collect::<Vec<SubAccountContext>>(),
-                Err(e) => {
-                    return Err(ContextError::ClientError(e));
-                }
+        let sub_account_ctxs = match get_sub_account_ctxs(rpc_client, &account_state).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
             }
-        } else {
-            Vec::new()
         };
 
         Ok(Self::new(
@@ -266,6 +360,41 @@ impl UserContext {
         ))
     }
 
+    /// Loads only the [`CypherAccount`], without it's [`CypherSubAccount`]s,
+    /// if an account number is provided then that account will be loaded,
+    /// if not then the first account will be derived.
+    ///
+    /// This is meant for screening with [`UserContext::get_cached_health`], the sub accounts can be loaded
+    /// afterwards with [`UserContext::reload`] if the full computation is needed.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request or the Account has an invalid Anchor discriminator.
+    pub async fn load_account_only(
+        rpc_client: &impl AccountFetcher,
+        authority: &Pubkey,
+        account_number: Option<u8>,
+    ) -> Result<Self, ContextError> {
+        let account = derive_account_address(authority, account_number.unwrap_or_default()).0;
+
+        let account_state = match get_cypher_account(rpc_client, &account).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+
+        Ok(Self::new(
+            *authority,
+            AccountContext {
+                address: account,
+                state: account_state,
+            },
+            Vec::new(),
+        ))
+    }
+
     /// Reloads the [`CypherAccount`] from the given account data.
     ///
     /// ### Errors
@@ -298,18 +427,19 @@ impl UserContext {
             state: new_sub_account_state,
         };
 
-        if !self
+        match self
             .sub_account_ctxs
-            .iter()
-            .map(|sa| sa.address)
-            .any(|a| a == *sub_account)
+            .iter_mut()
+            .find(|sa| sa.address == *sub_account)
         {
-            self.sub_account_ctxs.push(new_sub_account_ctx.clone());
-        } else {
-            for sub_account_ctx in self.sub_account_ctxs.iter_mut() {
-                if sub_account_ctx.address == *sub_account {
-                    *sub_account_ctx = new_sub_account_ctx.clone();
-                }
+            Some(sub_account_ctx) => *sub_account_ctx = new_sub_account_ctx.clone(),
+            None => {
+                // keep the sub accounts ordered by their sub account number
+                let idx = self.sub_account_ctxs.partition_point(|sa| {
+                    sa.account_number() < new_sub_account_ctx.account_number()
+                });
+                self.sub_account_ctxs
+                    .insert(idx, new_sub_account_ctx.clone());
             }
         }
 
@@ -359,17 +489,48 @@ impl UserContext {
         self.reload(rpc_client).await
     }
 
+    /// Checks that the user's wallet holds enough of the given SPL Token Mint to deposit the given amount,
+    /// including the rent of the temporary token account used to deposit Wrapped SOL.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an [`ContextError::InsufficientFunds`] error with the required and available
+    /// amounts if the balance is insufficient, or an error if something goes wrong during the RPC request.
+    pub async fn check_deposit_balance(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        token_mint: &Pubkey,
+        amount: u64,
+    ) -> Result<(), ContextError> {
+        let available = match get_wallet_balance(rpc_client, &self.authority, token_mint).await {
+            Ok(b) => b,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+        let required = get_deposit_requirement(token_mint, amount);
+        if available < required {
+            return Err(ContextError::InsufficientFunds {
+                token_mint: *token_mint,
+                required,
+                available,
+            });
+        }
+        Ok(())
+    }
+
     /// Deposits the given SPL Token Mint.
     ///
     /// ### Assumptions
     ///
     /// - The amount specified is in the token's native units.
-    /// - The user already has an Associated Token Account for the given SPL Token Mint with sufficient balance.
+    /// - The user already has an Associated Token Account for the given SPL Token Mint.
     ///
     /// ### Error
     ///
     /// This function will return an error if something goes wrong during the RPC
-    /// request OR if it was unable to find a [`CypherSubAccount`] has a free spot position slot.
+    /// request OR if it was unable to find a [`CypherSubAccount`] has a free spot position slot
+    /// OR if the wallet's balance is insufficient, see [`UserContext::check_deposit_balance`].
     pub async fn deposit(
         &self,
         rpc_client: &Arc<RpcClient>,
@@ -390,6 +551,16 @@ impl UserContext {
             }
         };
 
+        match self
+            .check_deposit_balance(rpc_client, token_mint, amount)
+            .await
+        {
+            Ok(()) => (),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
         let mut ixs: Vec<Instruction> = Vec::new();
         let (pool_vault, _) = derive_pool_node_vault_address(pool_node);
 
@@ -576,13 +747,541 @@ impl UserContext {
         }
     }
 
+    /// Gets the accounts needed to manage orders on the given derivative market.
+    ///
+    /// The sub account used is the one which already holds a position on the market or, if there is none,
+    /// one with a free slot. The quote pool node is assumed to be the first node of the market's quote pool.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if it was unable to find a [`CypherSubAccount`] which
+    /// holds a position on the market or has a free slot for it.
+    pub fn get_derivative_order_accounts<T: GenericDerivativeMarket>(
+        &self,
+        cache_account: &Pubkey,
+        market_ctx: &MarketContext<T>,
+    ) -> Result<DerivativeOrderAccounts, ContextError> {
+        let sub_account = match self.get_sub_account_with_position(&market_ctx.address) {
+            Some(sa) => sa,
+            None => match self.get_sub_account_with_free_slot(false) {
+                Some(sa) => sa,
+                None => {
+                    return Err(ContextError::AccountNotFound(format!(
+                        "Could not find Sub Account with position or free slot for market: {}",
+                        market_ctx.address
+                    )))
+                }
+            },
+        };
+        let market = market_ctx.state.agnostic_market();
+        let (open_orders, _) =
+            derive_orders_account_address(&market_ctx.address, &self.account_ctx.address);
+        let (quote_pool_node, _) = derive_pool_node_address(&market.quote_pool, 0);
+
+        Ok(DerivativeOrderAccounts {
+            clearing: self.account_ctx.state.clearing,
+            cache_account: *cache_account,
+            master_account: self.account_ctx.address,
+            sub_account: sub_account.address,
+            market: market_ctx.address,
+            open_orders,
+            orderbook: market.orderbook,
+            event_queue: market.event_queue,
+            bids: market.bids,
+            asks: market.asks,
+            price_history: market.price_history,
+            quote_pool_node,
+            authority: self.authority,
+        })
+    }
+
+    /// Places an order on the given [`PerpetualMarket`].
+    ///
+    /// ### Assumptions
+    ///
+    /// - The user already has an orders account for the given market.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find a [`CypherSubAccount`] for the market.
+    pub async fn place_perp_order(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &MarketContext<PerpetualMarket>,
+        args: NewDerivativeOrderArgs,
+    ) -> Result<Signature, ContextError> {
+        self.place_derivative_order(rpc_client, signer, cache_account, market_ctx, args)
+            .await
+    }
+
+    /// Places an order on the given [`FuturesMarket`].
+    ///
+    /// ### Assumptions
+    ///
+    /// - The user already has an orders account for the given market.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find a [`CypherSubAccount`] for the market.
+    pub async fn place_futures_order(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &MarketContext<FuturesMarket>,
+        args: NewDerivativeOrderArgs,
+    ) -> Result<Signature, ContextError> {
+        self.place_derivative_order(rpc_client, signer, cache_account, market_ctx, args)
+            .await
+    }
+
+    /// Cancels an order on the given [`PerpetualMarket`] or [`FuturesMarket`].
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find a [`CypherSubAccount`] for the market.
+    pub async fn cancel_order<T: GenericDerivativeMarket>(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &MarketContext<T>,
+        args: CancelOrderArgs,
+    ) -> Result<Signature, ContextError> {
+        let accounts = match self.get_derivative_order_accounts(cache_account, market_ctx) {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let ix = market_ctx.state.cancel_order_ix(&accounts, args);
+        self.send_instructions(rpc_client, signer, &[ix]).await
+    }
+
+    /// Settles the funds of the orders account on the given [`PerpetualMarket`] or [`FuturesMarket`].
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find a [`CypherSubAccount`] for the market.
+    pub async fn settle_funds<T: GenericDerivativeMarket>(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &MarketContext<T>,
+    ) -> Result<Signature, ContextError> {
+        let accounts = match self.get_derivative_order_accounts(cache_account, market_ctx) {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let ix = market_ctx.state.settle_funds_ix(&accounts);
+        self.send_instructions(rpc_client, signer, &[ix]).await
+    }
+
+    /// Gets the accounts needed to manage orders on the given spot market.
+    ///
+    /// The sub account used is the one which already holds a position in the market's base token or, if there is none,
+    /// one with a free slot. The pool nodes used are the first pool nodes of the given pools.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if it was unable to find a [`CypherSubAccount`] which
+    /// holds a position in the base token or has a free slot for it, or if one of the pools has no pool nodes.
+    pub fn get_spot_order_accounts(
+        &self,
+        cache_account: &Pubkey,
+        market_ctx: &SpotMarketContext,
+        asset_pool: &PoolContext,
+        quote_pool: &PoolContext,
+    ) -> Result<SpotOrderAccounts, ContextError> {
+        let sub_account = match self.get_sub_account_with_position(&market_ctx.base_mint) {
+            Some(sa) => sa,
+            None => match self.get_sub_account_with_free_slot(true) {
+                Some(sa) => sa,
+                None => {
+                    return Err(ContextError::AccountNotFound(format!(
+                        "Could not find Sub Account with position or free slot for token mint: {}",
+                        market_ctx.base_mint
+                    )))
+                }
+            },
+        };
+        let asset_pool_node = match asset_pool.pool_nodes.first() {
+            Some(pn) => pn.address,
+            None => {
+                return Err(ContextError::AccountNotFound(format!(
+                    "Could not find Pool Node for pool: {}",
+                    asset_pool.address
+                )))
+            }
+        };
+        let quote_pool_node = match quote_pool.pool_nodes.first() {
+            Some(pn) => pn.address,
+            None => {
+                return Err(ContextError::AccountNotFound(format!(
+                    "Could not find Pool Node for pool: {}",
+                    quote_pool.address
+                )))
+            }
+        };
+        let (open_orders, _) = derive_spot_open_orders_address(
+            &market_ctx.address,
+            &self.account_ctx.address,
+            &sub_account.address,
+        );
+        let dex_vault_signer = match gen_dex_vault_signer_key(
+            market_ctx.state.vault_signer_nonce,
+            &market_ctx.address,
+        ) {
+            Ok(k) => k,
+            Err(_) => {
+                return Err(ContextError::AccountNotFound(format!(
+                    "Could not derive vault signer for dex market: {}",
+                    market_ctx.address
+                )))
+            }
+        };
+
+        Ok(SpotOrderAccounts {
+            clearing: self.account_ctx.state.clearing,
+            cache_account: *cache_account,
+            master_account: self.account_ctx.address,
+            sub_account: sub_account.address,
+            asset_pool_node,
+            quote_pool_node,
+            asset_mint: market_ctx.base_mint,
+            asset_vault: derive_pool_node_vault_address(&asset_pool_node).0,
+            quote_vault: derive_pool_node_vault_address(&quote_pool_node).0,
+            asset_vault_signer: derive_pool_node_vault_signer_address(&asset_pool_node).0,
+            quote_vault_signer: derive_pool_node_vault_signer_address(&quote_pool_node).0,
+            authority: self.authority,
+            market: market_ctx.address,
+            open_orders,
+            event_queue: market_ctx.event_queue,
+            request_queue: market_ctx.request_queue,
+            bids: market_ctx.bids,
+            asks: market_ctx.asks,
+            coin_vault: market_ctx.base_vault,
+            pc_vault: market_ctx.quote_vault,
+            dex_vault_signer,
+        })
+    }
+
+    /// Places an order on the given spot market.
+    ///
+    /// ### Assumptions
+    ///
+    /// - The user already has an open orders account for the given market.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find the accounts for the market.
+    pub async fn place_spot_order(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &SpotMarketContext,
+        asset_pool: &PoolContext,
+        quote_pool: &PoolContext,
+        args: NewSpotOrderArgs,
+    ) -> Result<Signature, ContextError> {
+        let accounts =
+            match self.get_spot_order_accounts(cache_account, market_ctx, asset_pool, quote_pool) {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        #[cfg(feature = "services")]
+        debug_assert_spot_order(
+            &market_ctx.address,
+            &market_ctx.state,
+            asset_pool.state.config.decimals,
+            &args,
+        );
+        // the vault signer is the one of the vault whose tokens are locked by the order
+        let vault_signer = match args.side {
+            Side::Bid => accounts.quote_vault_signer,
+            Side::Ask => accounts.asset_vault_signer,
+        };
+        let ix = new_spot_order(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.asset_pool_node,
+            &accounts.quote_pool_node,
+            &accounts.asset_mint,
+            &accounts.asset_vault,
+            &accounts.quote_vault,
+            &vault_signer,
+            &accounts.authority,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.event_queue,
+            &accounts.request_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.coin_vault,
+            &accounts.pc_vault,
+            &accounts.dex_vault_signer,
+            args,
+        );
+        self.send_instructions(rpc_client, signer, &[ix]).await
+    }
+
+    /// Cancels an order on the given spot market.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find the accounts for the market.
+    pub async fn cancel_spot_order(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &SpotMarketContext,
+        asset_pool: &PoolContext,
+        quote_pool: &PoolContext,
+        args: CancelOrderArgs,
+    ) -> Result<Signature, ContextError> {
+        let accounts =
+            match self.get_spot_order_accounts(cache_account, market_ctx, asset_pool, quote_pool) {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let ix = accounts.get_cancel_order_ix(args);
+        self.send_instructions(rpc_client, signer, &[ix]).await
+    }
+
+    /// Cancels all orders in the open orders account on the given spot market.
+    ///
+    /// Orders are cancelled with one instruction each, packed into as few transactions as possible.
+    /// Returns the signatures of the submitted transactions, which is empty if there were no orders to cancel.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// requests OR if it was unable to find the accounts for the market.
+    pub async fn cancel_all_spot_orders(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &SpotMarketContext,
+        asset_pool: &PoolContext,
+        quote_pool: &PoolContext,
+        compute_unit_info: Option<(u32, u64)>,
+    ) -> Result<Vec<Signature>, ContextError> {
+        let accounts =
+            match self.get_spot_order_accounts(cache_account, market_ctx, asset_pool, quote_pool) {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let open_orders =
+            match get_dex_account::<OpenOrders>(rpc_client, &accounts.open_orders).await {
+                Ok(oo) => oo,
+                Err(e) => {
+                    return Err(ContextError::ClientError(e));
+                }
+            };
+
+        let mut ixs = Vec::new();
+        for (i, order_id) in open_orders.orders.iter().enumerate() {
+            if *order_id == u128::default() {
+                continue;
+            }
+            let side = match open_orders.slot_side(i as u8) {
+                Some(s) => s,
+                None => continue,
+            };
+            ixs.push(accounts.get_cancel_order_ix(CancelOrderArgs {
+                order_id: *order_id,
+                side: side.into(),
+                is_client_id: false,
+            }));
+        }
+        if ixs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match send_transactions(rpc_client, ixs, signer, true, compute_unit_info, None).await {
+            Ok(s) => Ok(s),
+            Err(e) => Err(ContextError::ClientError(e)),
+        }
+    }
+
+    /// Cancels all orders in the orders account on the given [`PerpetualMarket`] or [`FuturesMarket`],
+    /// for every sub account.
+    ///
+    /// Orders are cancelled with batched cancel instructions of at most
+    /// [`MAX_CANCELS_PER_INSTRUCTION`](crate::oms::MAX_CANCELS_PER_INSTRUCTION) orders,
+    /// split across as many transactions as needed. Returns the signatures of the submitted transactions,
+    /// which is empty if there were no orders to cancel.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests.
+    #[cfg(feature = "services")]
+    pub async fn cancel_all_orders<T: GenericDerivativeMarket>(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        market_ctx: &MarketContext<T>,
+        compute_unit_info: Option<(u32, u64)>,
+    ) -> Result<Vec<Signature>, ContextError> {
+        let market = market_ctx.state.agnostic_market();
+        let (orders_account, _) =
+            derive_orders_account_address(&market_ctx.address, &self.account_ctx.address);
+        let orders_account_state = match get_cypher_zero_copy_account::<OrdersAccount>(
+            rpc_client,
+            &orders_account,
+        )
+        .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+
+        let accounts = CancelOrdersAccounts {
+            clearing: self.account_ctx.state.clearing,
+            master_account: self.account_ctx.address,
+            market: market_ctx.address,
+            market_type: market.config.market_type,
+            orders_account,
+            orderbook: market.orderbook,
+            event_queue: market.event_queue,
+            bids: market.bids,
+            asks: market.asks,
+            quote_pool_node: derive_pool_node_address(&market.quote_pool, 0).0,
+            authority: self.authority,
+        };
+        let ixs =
+            get_cancel_all_orders_ixs(&accounts, &self.account_ctx.state, &orders_account_state);
+        if ixs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match send_transactions(rpc_client, ixs, signer, true, compute_unit_info, None).await {
+            Ok(s) => Ok(s),
+            Err(e) => Err(ContextError::ClientError(e)),
+        }
+    }
+
+    /// Settles the funds of the open orders account on the given spot market.
+    ///
+    /// ### Error
+    ///
+    /// This function will return an error if something goes wrong during the RPC
+    /// request OR if it was unable to find the accounts for the market.
+    pub async fn settle_spot_funds(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &SpotMarketContext,
+        asset_pool: &PoolContext,
+        quote_pool: &PoolContext,
+    ) -> Result<Signature, ContextError> {
+        let accounts =
+            match self.get_spot_order_accounts(cache_account, market_ctx, asset_pool, quote_pool) {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let ix = settle_spot_funds(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.asset_pool_node,
+            &accounts.quote_pool_node,
+            &accounts.asset_mint,
+            &accounts.asset_vault,
+            &accounts.quote_vault,
+            &accounts.authority,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.coin_vault,
+            &accounts.pc_vault,
+            &accounts.dex_vault_signer,
+        );
+        self.send_instructions(rpc_client, signer, &[ix]).await
+    }
+
+    async fn place_derivative_order<T: GenericDerivativeMarket>(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        cache_account: &Pubkey,
+        market_ctx: &MarketContext<T>,
+        args: NewDerivativeOrderArgs,
+    ) -> Result<Signature, ContextError> {
+        let accounts = match self.get_derivative_order_accounts(cache_account, market_ctx) {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        #[cfg(feature = "services")]
+        {
+            let market = market_ctx.state.agnostic_market();
+            debug_assert_derivative_order(
+                &MarketParams::new(&market_ctx.address, market),
+                market.config.decimals,
+                &args,
+            );
+        }
+        let ix = market_ctx.state.new_order_ix(&accounts, args);
+        self.send_instructions(rpc_client, signer, &[ix]).await
+    }
+
+    async fn send_instructions(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        signer: &Keypair,
+        ixs: &[Instruction],
+    ) -> Result<Signature, ContextError> {
+        let blockhash = match rpc_client.get_latest_blockhash().await {
+            Ok(h) => h,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+
+        let tx = create_transaction(blockhash, ixs, signer, None);
+
+        match send_transaction(rpc_client, &tx, true).await {
+            Ok(s) => Ok(s),
+            Err(e) => Err(ContextError::ClientError(e)),
+        }
+    }
+
     /// Reloads this [`UserContext`] fetching all [`CypherAccount`] and [`CypherSubAccount`].
     ///
     /// ### Error
     ///
     /// This function will return an error if something goes wrong during the RPC
     /// request.
-    pub async fn reload(&mut self, rpc_client: &Arc<RpcClient>) -> Result<(), ContextError> {
+    pub async fn reload(&mut self, rpc_client: &impl AccountFetcher) -> Result<(), ContextError> {
         self.account_ctx.state =
             match get_cypher_account(rpc_client, &self.account_ctx.address).await {
                 Ok(s) => s,
@@ -591,41 +1290,49 @@ impl UserContext {
                 }
             };
 
-        let sub_accounts = self
-            .account_ctx
-            .state
-            .sub_account_caches
-            .iter()
-            .filter(|a| a.sub_account != Pubkey::default())
-            .map(|a| a.sub_account)
-            .collect::<Vec<Pubkey>>();
-
-        self.sub_account_ctxs = if !sub_accounts.is_empty() {
-            match get_multiple_cypher_zero_copy_accounts::<CypherSubAccount>(
-                rpc_client,
-                &sub_accounts,
-            )
-            .await
-            {
-                Ok(s) => s
-                    .iter()
-                    .enumerate()
-                    .map(|(idx, a)| SubAccountContext {
-                        address: sub_accounts[idx],
-                        state: a.clone(),
-                    })
-                    .collect::<Vec<SubAccountContext>>(),
+        self.sub_account_ctxs =
+            match get_sub_account_ctxs(rpc_client, &self.account_ctx.state).await {
+                Ok(s) => s,
                 Err(e) => {
                     return Err(ContextError::ClientError(e));
                 }
-            }
-        } else {
-            Vec::new()
-        };
+            };
 
         Ok(())
     }
 
+    /// Gets the sub account with the given sub account number, if it is currently loaded.
+    pub fn get_sub_account_ctx(&self, account_number: u8) -> Option<&SubAccountContext> {
+        self.sub_account_ctxs
+            .iter()
+            .find(|sa| sa.account_number() == account_number)
+    }
+
+    /// Gets the sub account with the given sub account number as mutable, if it is currently loaded.
+    pub fn get_sub_account_ctx_mut(
+        &mut self,
+        account_number: u8,
+    ) -> Option<&mut SubAccountContext> {
+        self.sub_account_ctxs
+            .iter_mut()
+            .find(|sa| sa.account_number() == account_number)
+    }
+
+    /// Gets the sub account with the given address, if it is currently loaded.
+    pub fn get_sub_account_ctx_by_address(&self, address: &Pubkey) -> Option<&SubAccountContext> {
+        self.sub_account_ctxs
+            .iter()
+            .find(|sa| sa.address == *address)
+    }
+
+    /// Gets the numbers of the currently loaded sub accounts, in ascending order.
+    pub fn get_sub_account_numbers(&self) -> Vec<u8> {
+        self.sub_account_ctxs
+            .iter()
+            .map(|sa| sa.account_number())
+            .collect()
+    }
+
     /// Gets the sub account with the position pertaining to the given identifier.
     ///
     /// The identifier should be the SPL Token Mint pubkey for a spot position and the
@@ -666,12 +1373,57 @@ impl UserContext {
         None
     }
 
+    /// Gets the interest accrued by the spot positions of all sub accounts, aggregated per token.
+    ///
+    /// Tokens are ordered by their SPL Token Mint pubkey, see [`SpotPosition::accrued_interest`] for how
+    /// the interest of each position is computed.
+    pub fn get_accrued_interest(&self, cache_ctx: &CacheContext) -> Vec<TokenAccruedInterest> {
+        let mut tokens: BTreeMap<Pubkey, TokenAccruedInterest> = BTreeMap::new();
+        for sub_account_ctx in self.sub_account_ctxs.iter() {
+            for position in sub_account_ctx.state.positions.iter() {
+                let spot = &position.spot;
+                if spot.token_mint == Pubkey::default() {
+                    continue;
+                }
+                let cache = cache_ctx.state.get_price_cache(spot.cache_index as usize);
+                let interest = spot.accrued_interest(cache);
+                let entry = tokens
+                    .entry(spot.token_mint)
+                    .or_insert(TokenAccruedInterest {
+                        token_mint: spot.token_mint,
+                        ..Default::default()
+                    });
+                if interest.is_negative() {
+                    entry.borrow_interest += interest;
+                } else {
+                    entry.deposit_interest += interest;
+                }
+            }
+        }
+        tokens.into_values().collect()
+    }
+
+    /// Gets the approximate health of the account from the cached sub account values, see [`CachedHealth`].
+    ///
+    /// Caches updated more than `max_age` seconds before the given unix timestamp are considered stale.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the cached values overflow, see [`AccountContext::get_cached_health`].
+    pub fn get_cached_health(&self, now: u64, max_age: u64) -> Result<CachedHealth, MathError> {
+        self.account_ctx.get_cached_health(now, max_age)
+    }
+
     /// gets the c-ratio for this account
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a cross margined sub account can not be computed.
     pub fn get_margin_c_ratio(
         &self,
         cache_ctx: &CacheContext,
         mcr_type: MarginCollateralRatioType,
-    ) -> I80F48 {
+    ) -> Result<I80F48, MathError> {
         let mut assets_value = I80F48::ZERO;
         let mut liabilities_value = I80F48::ZERO;
 
@@ -679,23 +1431,303 @@ impl UserContext {
             if sub_account_ctx.state.margining_type == SubAccountMargining::Cross {
                 let (av, _) = sub_account_ctx
                     .state
-                    .get_assets_value(cache_ctx.state.as_ref(), mcr_type);
+                    .get_assets_value(cache_ctx.state.as_ref(), mcr_type)?;
                 assets_value += av;
                 let (lv, _) = sub_account_ctx
                     .state
-                    .get_liabilities_value(cache_ctx.state.as_ref(), mcr_type);
+                    .get_liabilities_value(cache_ctx.state.as_ref(), mcr_type)?;
                 liabilities_value += lv;
             }
         }
 
         if liabilities_value == I80F48::ZERO {
-            I80F48::MAX
+            Ok(I80F48::MAX)
         } else {
-            assets_value / liabilities_value
+            Ok(assets_value / liabilities_value)
         }
     }
 }
 
+/// The interest accrued by the spot positions of a token, in the token's native units.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TokenAccruedInterest {
+    /// The SPL Token Mint.
+    pub token_mint: Pubkey,
+    /// The interest earned by deposits.
+    pub deposit_interest: I80F48,
+    /// The interest owed by borrows, which is negative.
+    pub borrow_interest: I80F48,
+}
+
+impl TokenAccruedInterest {
+    /// The net interest, positive if deposits earned more than borrows owe.
+    pub fn net_interest(&self) -> I80F48 {
+        self.deposit_interest + self.borrow_interest
+    }
+}
+
+/// The accounts needed to manage orders on a [`PerpetualMarket`] or [`FuturesMarket`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DerivativeOrderAccounts {
+    pub clearing: Pubkey,
+    pub cache_account: Pubkey,
+    pub master_account: Pubkey,
+    pub sub_account: Pubkey,
+    pub market: Pubkey,
+    pub open_orders: Pubkey,
+    pub orderbook: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    /// The price history, only used by [`FuturesMarket`]s.
+    pub price_history: Pubkey,
+    pub quote_pool_node: Pubkey,
+    pub authority: Pubkey,
+}
+
+/// The accounts needed to manage orders on a spot market.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SpotOrderAccounts {
+    pub clearing: Pubkey,
+    pub cache_account: Pubkey,
+    pub master_account: Pubkey,
+    pub sub_account: Pubkey,
+    pub asset_pool_node: Pubkey,
+    pub quote_pool_node: Pubkey,
+    pub asset_mint: Pubkey,
+    pub asset_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    /// The vault signer of the asset pool node, used by asks.
+    pub asset_vault_signer: Pubkey,
+    /// The vault signer of the quote pool node, used by bids.
+    pub quote_vault_signer: Pubkey,
+    pub authority: Pubkey,
+    pub market: Pubkey,
+    pub open_orders: Pubkey,
+    pub event_queue: Pubkey,
+    pub request_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub dex_vault_signer: Pubkey,
+}
+
+impl SpotOrderAccounts {
+    /// Creates the instruction to cancel an order.
+    pub fn get_cancel_order_ix(&self, args: CancelOrderArgs) -> Instruction {
+        cancel_spot_order(
+            &self.clearing,
+            &self.cache_account,
+            &self.master_account,
+            &self.sub_account,
+            &self.asset_pool_node,
+            &self.quote_pool_node,
+            &self.asset_mint,
+            &self.asset_vault,
+            &self.quote_vault,
+            &self.authority,
+            &self.market,
+            &self.open_orders,
+            &self.event_queue,
+            &self.bids,
+            &self.asks,
+            &self.coin_vault,
+            &self.pc_vault,
+            &self.dex_vault_signer,
+            args,
+        )
+    }
+}
+
+/// A derivative market whose orders can be managed through the [`UserContext`].
+pub trait GenericDerivativeMarket: ZeroCopy + Owner + Default {
+    /// The market's agnostic state.
+    fn agnostic_market(&self) -> &AgnosticMarket;
+    /// Creates the instruction to place an order.
+    fn new_order_ix(
+        &self,
+        accounts: &DerivativeOrderAccounts,
+        args: NewDerivativeOrderArgs,
+    ) -> Instruction;
+    /// Creates the instruction to cancel an order.
+    fn cancel_order_ix(
+        &self,
+        accounts: &DerivativeOrderAccounts,
+        args: CancelOrderArgs,
+    ) -> Instruction;
+    /// Creates the instruction to settle the funds of the orders account.
+    fn settle_funds_ix(&self, accounts: &DerivativeOrderAccounts) -> Instruction;
+}
+
+impl GenericDerivativeMarket for PerpetualMarket {
+    fn agnostic_market(&self) -> &AgnosticMarket {
+        &self.inner
+    }
+
+    fn new_order_ix(
+        &self,
+        accounts: &DerivativeOrderAccounts,
+        args: NewDerivativeOrderArgs,
+    ) -> Instruction {
+        new_perp_order(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    }
+
+    fn cancel_order_ix(
+        &self,
+        accounts: &DerivativeOrderAccounts,
+        args: CancelOrderArgs,
+    ) -> Instruction {
+        cancel_perp_order(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    }
+
+    fn settle_funds_ix(&self, accounts: &DerivativeOrderAccounts) -> Instruction {
+        settle_perp_funds(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.quote_pool_node,
+        )
+    }
+}
+
+impl GenericDerivativeMarket for FuturesMarket {
+    fn agnostic_market(&self) -> &AgnosticMarket {
+        &self.inner
+    }
+
+    fn new_order_ix(
+        &self,
+        accounts: &DerivativeOrderAccounts,
+        args: NewDerivativeOrderArgs,
+    ) -> Instruction {
+        new_futures_order(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.price_history,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    }
+
+    fn cancel_order_ix(
+        &self,
+        accounts: &DerivativeOrderAccounts,
+        args: CancelOrderArgs,
+    ) -> Instruction {
+        cancel_futures_order(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    }
+
+    fn settle_funds_ix(&self, accounts: &DerivativeOrderAccounts) -> Instruction {
+        settle_futures_funds(
+            &accounts.clearing,
+            &accounts.cache_account,
+            &accounts.master_account,
+            &accounts.sub_account,
+            &accounts.market,
+            &accounts.open_orders,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+        )
+    }
+}
+
+/// Fetches the [`CypherSubAccount`]s of the given [`CypherAccount`], ordered by their sub account number.
+///
+/// Each sub account is keyed by the pubkey it was fetched with, sub accounts that do not exist are skipped.
+///
+/// ### Error
+///
+/// This function will return an error if something goes wrong during the RPC request.
+async fn get_sub_account_ctxs(
+    rpc_client: &impl AccountFetcher,
+    account_state: &CypherAccount,
+) -> Result<Vec<SubAccountContext>, ClientError> {
+    let sub_accounts = account_state
+        .sub_account_caches
+        .iter()
+        .filter(|a| a.sub_account != Pubkey::default())
+        .map(|a| a.sub_account)
+        .collect::<Vec<Pubkey>>();
+    if sub_accounts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let accounts = match rpc_client.get_multiple_accounts(&sub_accounts).await {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    let mut sub_account_ctxs = sub_accounts
+        .iter()
+        .zip(accounts.iter())
+        .filter_map(|(address, account)| {
+            account.as_ref().map(|a| {
+                SubAccountContext::new(*address, get_zero_copy_account::<CypherSubAccount>(&a.data))
+            })
+        })
+        .collect::<Vec<SubAccountContext>>();
+    sub_account_ctxs.sort_by_key(|sa| sa.account_number());
+
+    Ok(sub_account_ctxs)
+}
+
 /// Fetches the [`CypherAccount`] with the given pubkey.
 ///
 /// ### Error
@@ -703,7 +1735,7 @@ impl UserContext {
 /// This function will return an error if something goes wrong during the RPC
 /// request or the Account has an invalid Anchor discriminator.
 pub async fn get_cypher_account(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     account: &Pubkey,
 ) -> Result<Box<CypherAccount>, ClientError> {
     match get_cypher_zero_copy_account::<CypherAccount>(rpc_client, account).await {
@@ -719,7 +1751,7 @@ pub async fn get_cypher_account(
 /// This function will return an error if something goes wrong during the RPC
 /// request or the Account has an invalid Anchor discriminator.
 pub async fn get_cypher_sub_account(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     account: &Pubkey,
 ) -> Result<Box<CypherSubAccount>, ClientError> {
     match get_cypher_zero_copy_account::<CypherSubAccount>(rpc_client, account).await {
@@ -0,0 +1,9 @@
+use cypher_client::Whitelist;
+
+use super::macros::account_context;
+
+account_context!(
+    /// Represents a [`Whitelist`].
+    WhitelistContext,
+    Whitelist
+);
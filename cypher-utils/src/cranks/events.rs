@@ -0,0 +1,333 @@
+use cypher_client::{
+    aob::get_event_callback_infos,
+    instructions::{consume_futures_events, consume_perp_events},
+    utils::{derive_orders_account_address, derive_public_clearing_address},
+    FuturesMarket, PerpetualMarket,
+};
+use dashmap::DashMap;
+use log::debug;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::contexts::{AgnosticEventQueueContext, MarketContext};
+
+use super::CrankError;
+
+/// The default maximum number of events consumed per instruction.
+///
+/// Each event can add two orders accounts to the instruction, this keeps it within the transaction's account limit.
+pub const DEFAULT_CONSUME_EVENTS_LIMIT: u16 = 10;
+
+/// The kind of market whose events are consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventsMarketKind {
+    Perpetual,
+    Futures,
+}
+
+/// The accounts needed to crank the event queue of a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventsCrankTarget {
+    /// The kind of market.
+    pub kind: EventsMarketKind,
+    /// The market.
+    pub market: Pubkey,
+    /// The market's orderbook.
+    pub orderbook: Pubkey,
+    /// The market's event queue.
+    pub event_queue: Pubkey,
+}
+
+impl EventsCrankTarget {
+    /// Creates a new [`EventsCrankTarget`] for the given perpetual market.
+    pub fn perpetual(market_ctx: &MarketContext<PerpetualMarket>) -> Self {
+        Self {
+            kind: EventsMarketKind::Perpetual,
+            market: market_ctx.address,
+            orderbook: market_ctx.state.inner.orderbook,
+            event_queue: market_ctx.state.inner.event_queue,
+        }
+    }
+
+    /// Creates a new [`EventsCrankTarget`] for the given futures market.
+    pub fn futures(market_ctx: &MarketContext<FuturesMarket>) -> Self {
+        Self {
+            kind: EventsMarketKind::Futures,
+            market: market_ctx.address,
+            orderbook: market_ctx.state.inner.orderbook,
+            event_queue: market_ctx.state.inner.event_queue,
+        }
+    }
+
+    /// Gets the consume events instruction for this target with the given orders accounts.
+    pub fn get_ix(&self, open_orders: &[Pubkey], limit: u16) -> Instruction {
+        let clearing = derive_public_clearing_address().0;
+        match self.kind {
+            EventsMarketKind::Perpetual => consume_perp_events(
+                &clearing,
+                &self.market,
+                &self.orderbook,
+                &self.event_queue,
+                open_orders,
+                limit,
+            ),
+            EventsMarketKind::Futures => consume_futures_events(
+                &clearing,
+                &self.market,
+                &self.orderbook,
+                &self.event_queue,
+                open_orders,
+                limit,
+            ),
+        }
+    }
+
+    /// Loads the event queue and gets the consume events instruction for up to `limit` of its pending events.
+    ///
+    /// Returns `None` if there are no pending events.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn get_consume_events_ix(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        limit: u16,
+    ) -> Result<Option<Instruction>, CrankError> {
+        let eq_ctx = match AgnosticEventQueueContext::load(
+            rpc_client,
+            &self.market,
+            &self.event_queue,
+        )
+        .await
+        {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                return Err(CrankError::ContextError(e));
+            }
+        };
+        if eq_ctx.count == 0 {
+            return Ok(None);
+        }
+        let open_orders = get_pending_orders_accounts(&eq_ctx, limit);
+        Ok(Some(self.get_ix(&open_orders, limit)))
+    }
+}
+
+/// Gets the orders accounts of the makers and takers of up to `limit` pending events in the given event queue,
+/// ordered as they are first referenced by the events and without duplicates.
+///
+/// The event queue must be loaded from it's full account data, e.g. with [`AgnosticEventQueueContext::load`],
+/// so that it's events are exactly the pending events, ordered from the consumed head onwards.
+pub fn get_pending_orders_accounts(eq_ctx: &AgnosticEventQueueContext, limit: u16) -> Vec<Pubkey> {
+    let mut orders_accounts: Vec<Pubkey> = Vec::new();
+    let pending = std::cmp::min(eq_ctx.events.len(), limit as usize);
+    for event_idx in 0..pending {
+        let (maker, taker) = match get_event_callback_infos(&eq_ctx.callbacks, event_idx) {
+            Some(c) => c,
+            None => continue,
+        };
+        for callback in [maker, taker] {
+            if callback.is_empty() {
+                continue;
+            }
+            let orders_account =
+                derive_orders_account_address(&eq_ctx.market, &callback.user_account).0;
+            if !orders_accounts.contains(&orders_account) {
+                orders_accounts.push(orders_account);
+            }
+        }
+    }
+    orders_accounts
+}
+
+/// The pending events of an event queue, found by the [`EventsScanner`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEvents {
+    /// The target whose event queue has pending events.
+    pub target: EventsCrankTarget,
+    /// The sequence number of the oldest pending event, i.e. the event queue's consumed head.
+    pub head_seq_num: u64,
+    /// The number of pending events.
+    pub count: u64,
+    /// The orders accounts referenced by the events consumed by the next instruction,
+    /// see [`get_pending_orders_accounts`].
+    pub orders_accounts: Vec<Pubkey>,
+}
+
+impl PendingEvents {
+    /// Gets the pending events of the given target from it's loaded event queue, or `None` if there are none.
+    pub fn from_event_queue(
+        target: &EventsCrankTarget,
+        eq_ctx: &AgnosticEventQueueContext,
+        limit: u16,
+    ) -> Option<Self> {
+        if eq_ctx.count == 0 {
+            return None;
+        }
+        Some(Self {
+            target: *target,
+            head_seq_num: eq_ctx.seq_num.saturating_sub(eq_ctx.count),
+            count: eq_ctx.count,
+            orders_accounts: get_pending_orders_accounts(eq_ctx, limit),
+        })
+    }
+
+    /// The number of events consumed by the instruction given by [`PendingEvents::get_ix`].
+    pub fn get_consumed_count(&self, limit: u16) -> u64 {
+        std::cmp::min(self.count, limit as u64)
+    }
+
+    /// Gets the consume events instruction for up to `limit` of the pending events.
+    pub fn get_ix(&self, limit: u16) -> Instruction {
+        self.target.get_ix(&self.orders_accounts, limit)
+    }
+}
+
+/// A crank submitted for an event queue which has not been observed to land yet.
+#[derive(Debug, Clone, Copy)]
+struct InFlightCrank {
+    /// The consumed head the event queue will have once the crank lands.
+    expected_head_seq_num: u64,
+    /// The time at which the crank was submitted.
+    submitted_at: Instant,
+}
+
+/// Finds the event queues with pending events and the orders accounts their consumption needs,
+/// loading every event queue in batched RPC requests.
+///
+/// The scanner keeps track of the cranks submitted for each event queue, so that events which are already being
+/// consumed by an in-flight transaction are not cranked again until that transaction either lands, which advances
+/// the consumed head past them, or is presumed dropped after the in-flight timeout.
+pub struct EventsScanner {
+    limit: u16,
+    in_flight_timeout: Duration,
+    in_flight: DashMap<Pubkey, InFlightCrank>,
+}
+
+impl std::fmt::Debug for EventsScanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventsScanner")
+            .field("limit", &self.limit)
+            .field("in_flight_timeout", &self.in_flight_timeout)
+            .field("in_flight", &format!("{}", self.in_flight.len()))
+            .finish()
+    }
+}
+
+impl EventsScanner {
+    /// Creates a new [`EventsScanner`] which consumes up to `limit` events per instruction and presumes
+    /// a submitted crank dropped if the consumed head has not advanced after the given timeout.
+    pub fn new(limit: u16, in_flight_timeout: Duration) -> Self {
+        Self {
+            limit,
+            in_flight_timeout,
+            in_flight: DashMap::new(),
+        }
+    }
+
+    /// Gets the maximum number of events consumed per instruction.
+    pub fn limit(&self) -> u16 {
+        self.limit
+    }
+
+    /// Loads the event queues of the given targets and gets the pending events of those which need cranking,
+    /// skipping the event queues whose pending events are already being consumed by an in-flight crank.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests.
+    pub async fn scan(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        targets: &[EventsCrankTarget],
+    ) -> Result<Vec<PendingEvents>, CrankError> {
+        let mut pending_events = Vec::new();
+        for chunk in targets.chunks(100) {
+            let event_queues = chunk.iter().map(|t| t.event_queue).collect::<Vec<Pubkey>>();
+            let accounts = match rpc_client.get_multiple_accounts(&event_queues).await {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(CrankError::ClientError(e));
+                }
+            };
+            for (target, account) in chunk.iter().zip(accounts.iter()) {
+                let account = match account {
+                    Some(a) => a,
+                    None => {
+                        debug!(
+                            "Event queue {} of market {} not found, skipping.",
+                            target.event_queue, target.market
+                        );
+                        continue;
+                    }
+                };
+                let eq_ctx = AgnosticEventQueueContext::from_account_data(
+                    &target.market,
+                    &target.event_queue,
+                    &account.data,
+                );
+                let pending = match PendingEvents::from_event_queue(target, &eq_ctx, self.limit) {
+                    Some(p) => p,
+                    None => {
+                        self.in_flight.remove(&target.event_queue);
+                        continue;
+                    }
+                };
+                if self.is_in_flight(&pending) {
+                    debug!(
+                        "Events of market {} from sequence number {} are already being consumed, skipping.",
+                        target.market, pending.head_seq_num
+                    );
+                    continue;
+                }
+                pending_events.push(pending);
+            }
+        }
+        Ok(pending_events)
+    }
+
+    /// Marks the given pending events as being consumed by a submitted crank.
+    pub fn mark_submitted(&self, pending: &PendingEvents) {
+        self.in_flight.insert(
+            pending.target.event_queue,
+            InFlightCrank {
+                expected_head_seq_num: pending.head_seq_num
+                    + pending.get_consumed_count(self.limit),
+                submitted_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Whether the given pending events are being consumed by an in-flight crank, forgetting the crank
+    /// if it has landed or is presumed dropped.
+    fn is_in_flight(&self, pending: &PendingEvents) -> bool {
+        let in_flight = match self.in_flight.get(&pending.target.event_queue) {
+            Some(c) => *c,
+            None => return false,
+        };
+        if pending.head_seq_num < in_flight.expected_head_seq_num
+            && in_flight.submitted_at.elapsed() < self.in_flight_timeout
+        {
+            return true;
+        }
+        self.in_flight.remove(&pending.target.event_queue);
+        false
+    }
+}
+
+/// Gets the [`EventsCrankTarget`]s of the given perpetual and futures markets.
+pub fn get_events_targets(
+    perp_markets: &[MarketContext<PerpetualMarket>],
+    futures_markets: &[MarketContext<FuturesMarket>],
+) -> Vec<EventsCrankTarget> {
+    perp_markets
+        .iter()
+        .map(EventsCrankTarget::perpetual)
+        .chain(futures_markets.iter().map(EventsCrankTarget::futures))
+        .collect()
+}
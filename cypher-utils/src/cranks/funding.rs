@@ -0,0 +1,47 @@
+use cypher_client::{cache_account, instructions::update_funding_rate, PerpetualMarket};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::contexts::MarketContext;
+
+/// The accounts needed to crank the funding rate of a perpetual market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundingCrankTarget {
+    /// The perpetual market.
+    pub market: Pubkey,
+    /// The market's orderbook.
+    pub orderbook: Pubkey,
+    /// The market's bids.
+    pub bids: Pubkey,
+    /// The market's asks.
+    pub asks: Pubkey,
+}
+
+impl FundingCrankTarget {
+    /// Creates a new [`FundingCrankTarget`] for the given perpetual market.
+    pub fn new(market_ctx: &MarketContext<PerpetualMarket>) -> Self {
+        Self {
+            market: market_ctx.address,
+            orderbook: market_ctx.state.inner.orderbook,
+            bids: market_ctx.state.inner.bids,
+            asks: market_ctx.state.inner.asks,
+        }
+    }
+
+    /// Gets the `update_funding_rate` instruction for this target.
+    pub fn get_ix(&self) -> Instruction {
+        update_funding_rate(
+            &cache_account::id(),
+            &self.market,
+            &self.orderbook,
+            &self.bids,
+            &self.asks,
+        )
+    }
+}
+
+/// Gets the [`FundingCrankTarget`]s of the given perpetual markets.
+pub fn get_funding_targets(
+    perp_markets: &[MarketContext<PerpetualMarket>],
+) -> Vec<FundingCrankTarget> {
+    perp_markets.iter().map(FundingCrankTarget::new).collect()
+}
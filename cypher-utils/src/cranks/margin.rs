@@ -0,0 +1,81 @@
+use cypher_client::{
+    cache_account, instructions::update_account_margin, CacheAccount, CypherAccount,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+/// The accounts needed to refresh the sub account caches of a master account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarginCrankTarget {
+    /// The master account.
+    pub master_account: Pubkey,
+    /// Every sub account of the master account, in the order of it's sub account caches.
+    pub sub_accounts: Vec<Pubkey>,
+    /// The sub accounts whose caches are stale.
+    pub stale_sub_accounts: Vec<Pubkey>,
+}
+
+impl MarginCrankTarget {
+    /// Gets the `update_account_margin` instruction for this target, signed by the given signer.
+    ///
+    /// The instruction recomputes the master account's caches from all of it's sub accounts,
+    /// so every sub account is passed even if only some of them are stale.
+    pub fn get_ix(&self, signer: &Pubkey) -> Instruction {
+        update_account_margin(
+            &cache_account::id(),
+            &self.master_account,
+            signer,
+            &self.sub_accounts,
+        )
+    }
+}
+
+/// Gets the unix timestamp of the most recent price update of any cache in use in the given [`CacheAccount`].
+pub fn get_latest_price_update(cache_account: &CacheAccount) -> u64 {
+    cache_account
+        .caches
+        .iter()
+        .filter(|c| c.oracle_products != Pubkey::default())
+        .map(|c| c.updated_at)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Gets the sub accounts of the given [`CypherAccount`] whose caches were last updated more than `max_age` seconds
+/// before the given most recent price update, i.e. whose cached values do not reflect the current cache prices.
+pub fn get_stale_sub_accounts(
+    account: &CypherAccount,
+    latest_price_update: u64,
+    max_age: u64,
+) -> Vec<Pubkey> {
+    account
+        .sub_account_caches
+        .iter()
+        .filter(|c| c.sub_account != Pubkey::default())
+        .filter(|c| latest_price_update.saturating_sub(c.updated_at) > max_age)
+        .map(|c| c.sub_account)
+        .collect()
+}
+
+/// Gets the [`MarginCrankTarget`] of the given master account if any of it's sub account caches are stale,
+/// see [`get_stale_sub_accounts`].
+pub fn get_margin_target(
+    master_account: &Pubkey,
+    account: &CypherAccount,
+    latest_price_update: u64,
+    max_age: u64,
+) -> Option<MarginCrankTarget> {
+    let stale_sub_accounts = get_stale_sub_accounts(account, latest_price_update, max_age);
+    if stale_sub_accounts.is_empty() {
+        return None;
+    }
+    Some(MarginCrankTarget {
+        master_account: *master_account,
+        sub_accounts: account
+            .sub_account_caches
+            .iter()
+            .filter(|c| c.sub_account != Pubkey::default())
+            .map(|c| c.sub_account)
+            .collect(),
+        stale_sub_accounts,
+    })
+}
@@ -0,0 +1,32 @@
+pub mod events;
+pub mod funding;
+pub mod margin;
+pub mod oracle;
+#[cfg(feature = "services")]
+pub mod service;
+
+pub use events::*;
+pub use funding::*;
+pub use margin::*;
+pub use oracle::*;
+#[cfg(feature = "services")]
+pub use service::*;
+
+use cypher_client::index::IndexError;
+use solana_client::client_error::ClientError;
+use solana_sdk::program_error::ProgramError;
+use thiserror::Error;
+
+use crate::contexts::ContextError;
+
+#[derive(Debug, Error)]
+pub enum CrankError {
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+    #[error(transparent)]
+    ContextError(#[from] ContextError),
+    #[error(transparent)]
+    ProgramError(#[from] ProgramError),
+    #[error(transparent)]
+    IndexError(#[from] IndexError),
+}
@@ -0,0 +1,157 @@
+use anchor_lang::AccountDeserialize;
+use cypher_client::{
+    cache_account, instructions::cache_oracle_prices, AccountVersion, CacheAccount, CacheIndex,
+    FuturesMarket, OracleProducts,
+};
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::contexts::{MarketContext, PoolContext};
+
+use super::CrankError;
+
+/// The accounts needed to crank the oracle price of a cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OracleCrankTarget {
+    /// The index of the cache.
+    pub cache_index: CacheIndex,
+    /// The oracle products account of the cache.
+    pub oracle_products: Pubkey,
+    /// The price accounts of the oracle products.
+    pub price_accounts: Vec<Pubkey>,
+    /// The pool priced by the cache, if any.
+    pub pool: Option<Pubkey>,
+    /// The futures market priced by the cache, if any.
+    pub futures_market: Option<Pubkey>,
+}
+
+impl OracleCrankTarget {
+    /// Creates a new [`OracleCrankTarget`] from the given [`OracleProducts`].
+    ///
+    /// The instruction accepts either a pool or a futures market, if both are given only the pool is used.
+    pub fn new(
+        cache_index: CacheIndex,
+        oracle_products: &Pubkey,
+        oracle_products_state: &OracleProducts,
+        pool: Option<Pubkey>,
+        futures_market: Option<Pubkey>,
+    ) -> Self {
+        Self {
+            cache_index,
+            oracle_products: *oracle_products,
+            price_accounts: oracle_products_state
+                .products
+                .iter()
+                .map(|p| Pubkey::new_from_array(*p))
+                .collect(),
+            futures_market: if pool.is_some() { None } else { futures_market },
+            pool,
+        }
+    }
+
+    /// Gets the `cache_oracle_prices` instruction for this target.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if both a pool and a futures market are set.
+    pub fn get_ix(&self) -> Result<Instruction, CrankError> {
+        match cache_oracle_prices(
+            &cache_account::id(),
+            &self.oracle_products,
+            &self.price_accounts,
+            self.cache_index,
+            &self.pool,
+            &self.futures_market,
+        ) {
+            Ok(ix) => Ok(ix),
+            Err(e) => Err(CrankError::ProgramError(e)),
+        }
+    }
+}
+
+/// Discovers the [`OracleCrankTarget`]s of every cache in use in the given [`CacheAccount`].
+///
+/// Each cache is matched with the pool and futures market whose config points at its index.
+/// Caches whose oracle products have been upgraded to [`AccountVersion::One`] do not store their feeds
+/// in the oracle products account and are skipped, as are caches without price accounts.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn discover_oracle_targets(
+    rpc_client: &RpcClient,
+    cache_account: &CacheAccount,
+    pools: &[PoolContext],
+    futures_markets: &[MarketContext<FuturesMarket>],
+) -> Result<Vec<OracleCrankTarget>, CrankError> {
+    let caches = cache_account
+        .caches
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.oracle_products != Pubkey::default())
+        .map(|(idx, c)| (idx, c.oracle_products))
+        .collect::<Vec<(usize, Pubkey)>>();
+    let oracle_products = caches.iter().map(|(_, op)| *op).collect::<Vec<Pubkey>>();
+
+    let mut accounts = Vec::new();
+    for chunk in oracle_products.chunks(100) {
+        match rpc_client.get_multiple_accounts(chunk).await {
+            Ok(a) => accounts.extend(a),
+            Err(e) => {
+                return Err(CrankError::ClientError(e));
+            }
+        }
+    }
+
+    let mut targets = Vec::new();
+    for ((idx, oracle_products), account) in caches.iter().zip(accounts.iter()) {
+        let account = match account {
+            Some(a) => a,
+            None => {
+                warn!(
+                    "Oracle products {} for cache {} not found, skipping.",
+                    oracle_products, idx
+                );
+                continue;
+            }
+        };
+        let state = match OracleProducts::try_deserialize(&mut account.data.as_slice()) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!(
+                    "Failed to deserialize oracle products {}: {}",
+                    oracle_products,
+                    e.to_string()
+                );
+                continue;
+            }
+        };
+        if matches!(state.version, AccountVersion::One) || state.products.is_empty() {
+            continue;
+        }
+        let cache_index = match CacheIndex::new(*idx) {
+            Ok(i) => i,
+            Err(e) => {
+                return Err(CrankError::IndexError(e));
+            }
+        };
+        let pool = pools
+            .iter()
+            .find(|p| p.state.config.cache_index as usize == *idx)
+            .map(|p| p.address);
+        let futures_market = futures_markets
+            .iter()
+            .find(|m| m.state.inner.config.cache_index as usize == *idx)
+            .map(|m| m.address);
+        targets.push(OracleCrankTarget::new(
+            cache_index,
+            oracle_products,
+            &state,
+            pool,
+            futures_market,
+        ));
+    }
+
+    Ok(targets)
+}
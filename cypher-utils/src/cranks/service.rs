@@ -0,0 +1,432 @@
+use {
+    super::{
+        discover_oracle_targets, get_events_targets, get_funding_targets, get_latest_price_update,
+        get_margin_target, CrankError, EventsCrankTarget, EventsScanner, FundingCrankTarget,
+        OracleCrankTarget, DEFAULT_CONSUME_EVENTS_LIMIT,
+    },
+    crate::{
+        contexts::{CacheContext, MarketContext, PoolContext, ReloadPolicy, ResilientContext},
+        utils::send_transactions,
+    },
+    cypher_client::{
+        utils::try_get_zero_copy_account, CypherAccount, FuturesMarket, PerpetualMarket,
+    },
+    log::{info, warn},
+    solana_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer},
+    std::{sync::Arc, time::Duration},
+    tokio::sync::{broadcast::Sender, Mutex, RwLock},
+};
+
+/// The compute units requested by crank transactions.
+const CRANK_COMPUTE_UNITS: u32 = 1_000_000;
+
+/// The configuration of the [`CrankService`].
+///
+/// Setting an interval to `None` disables the corresponding crank.
+#[derive(Debug, Clone)]
+pub struct CrankConfig {
+    /// The interval at which oracle prices are cached.
+    pub oracle_interval: Option<Duration>,
+    /// The interval at which perpetual market funding rates are updated.
+    pub funding_interval: Option<Duration>,
+    /// The interval at which event queues are consumed.
+    pub events_interval: Option<Duration>,
+    /// The maximum number of events consumed per instruction.
+    pub events_limit: u16,
+    /// The time after which an events crank which has not landed is presumed dropped and the events are cranked again.
+    pub events_in_flight_timeout: Duration,
+    /// The interval at which the sub account caches of the margin accounts are checked and refreshed if stale.
+    pub margin_interval: Option<Duration>,
+    /// The master accounts whose sub account caches are kept fresh.
+    pub margin_accounts: Vec<Pubkey>,
+    /// The maximum age, in seconds, of a sub account cache relative to the most recent cache price update.
+    pub margin_max_age: u64,
+    /// The policy used to reload the cache account for the margin crank, which is skipped while it is stale.
+    pub margin_reload_policy: ReloadPolicy,
+    /// The interval at which the crank targets are discovered again, e.g. to pick up new markets.
+    pub discovery_interval: Option<Duration>,
+    /// The compute unit price of crank transactions, in micro lamports.
+    pub compute_unit_price: u64,
+}
+
+impl Default for CrankConfig {
+    fn default() -> Self {
+        Self {
+            oracle_interval: Some(Duration::from_secs(5)),
+            funding_interval: Some(Duration::from_secs(60)),
+            events_interval: Some(Duration::from_millis(500)),
+            events_limit: DEFAULT_CONSUME_EVENTS_LIMIT,
+            events_in_flight_timeout: Duration::from_secs(60),
+            margin_interval: Some(Duration::from_secs(30)),
+            margin_accounts: Vec::new(),
+            margin_max_age: 60,
+            margin_reload_policy: ReloadPolicy::default(),
+            discovery_interval: Some(Duration::from_secs(3600)),
+            compute_unit_price: 1,
+        }
+    }
+}
+
+/// The targets of the [`CrankService`].
+#[derive(Debug, Default, Clone)]
+pub struct CrankTargets {
+    /// The caches whose oracle prices are cranked.
+    pub oracles: Vec<OracleCrankTarget>,
+    /// The perpetual markets whose funding rates are cranked.
+    pub funding: Vec<FundingCrankTarget>,
+    /// The markets whose event queues are cranked.
+    pub events: Vec<EventsCrankTarget>,
+}
+
+impl CrankTargets {
+    /// Discovers the targets of every cache, perpetual market and futures market.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests.
+    pub async fn discover(rpc_client: &Arc<RpcClient>) -> Result<Self, CrankError> {
+        let cache_ctx = match CacheContext::load(rpc_client).await {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(CrankError::ContextError(e));
+            }
+        };
+        let pools = match PoolContext::load_all(rpc_client).await {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(CrankError::ContextError(e));
+            }
+        };
+        let perp_markets = match MarketContext::<PerpetualMarket>::load_all(rpc_client).await {
+            Ok(m) => m,
+            Err(e) => {
+                return Err(CrankError::ContextError(e));
+            }
+        };
+        let futures_markets = match MarketContext::<FuturesMarket>::load_all(rpc_client).await {
+            Ok(m) => m,
+            Err(e) => {
+                return Err(CrankError::ContextError(e));
+            }
+        };
+        let oracles =
+            match discover_oracle_targets(rpc_client, &cache_ctx.state, &pools, &futures_markets)
+                .await
+            {
+                Ok(t) => t,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+
+        Ok(Self {
+            oracles,
+            funding: get_funding_targets(&perp_markets),
+            events: get_events_targets(&perp_markets, &futures_markets),
+        })
+    }
+}
+
+/// A Service which runs the recurring permissionless cranks of the protocol:
+/// caching oracle prices, updating perpetual market funding rates and consuming event queues,
+/// as well as refreshing the stale sub account caches of the configured margin accounts.
+///
+/// The targets are discovered on-chain, so the service only needs a signer to pay for the transactions.
+pub struct CrankService {
+    rpc_client: Arc<RpcClient>,
+    signer: Arc<Keypair>,
+    config: CrankConfig,
+    targets: RwLock<CrankTargets>,
+    events_scanner: EventsScanner,
+    cache_ctx: Mutex<Option<ResilientContext<CacheContext>>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for CrankService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CrankService")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl CrankService {
+    /// Creates a new [`CrankService`].
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        signer: Arc<Keypair>,
+        config: CrankConfig,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            rpc_client,
+            signer,
+            events_scanner: EventsScanner::new(
+                config.events_limit,
+                config.events_in_flight_timeout,
+            ),
+            config,
+            targets: RwLock::new(CrankTargets::default()),
+            cache_ctx: Mutex::new(None),
+            shutdown,
+        }
+    }
+
+    /// Creates a new [`CrankService`] with the given RPC URL and the default configuration.
+    pub fn from_url(rpc_url: &str, signer: Keypair, shutdown: Arc<Sender<bool>>) -> Self {
+        Self::new(
+            Arc::new(RpcClient::new(rpc_url.to_string())),
+            Arc::new(signer),
+            CrankConfig::default(),
+            shutdown,
+        )
+    }
+
+    /// Sets the targets of the service, replacing the discovered ones.
+    pub async fn set_targets(&self, targets: CrankTargets) {
+        *self.targets.write().await = targets;
+    }
+
+    /// Gets the current targets of the service.
+    pub async fn get_targets(&self) -> CrankTargets {
+        self.targets.read().await.clone()
+    }
+
+    /// Discovers the targets of the service.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests.
+    pub async fn discover(&self) -> Result<(), CrankError> {
+        let targets = match CrankTargets::discover(&self.rpc_client).await {
+            Ok(t) => t,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        info!(
+            "Discovered {} oracle, {} funding and {} event queue crank targets.",
+            targets.oracles.len(),
+            targets.funding.len(),
+            targets.events.len()
+        );
+        self.set_targets(targets).await;
+        Ok(())
+    }
+
+    /// Starts the service's work cycle.
+    /// Discovers the targets and runs each enabled crank at its interval until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        if let Err(e) = self.discover().await {
+            warn!("Failed to discover crank targets: {}", e.to_string());
+        }
+
+        let mut oracle_interval = new_interval(self.config.oracle_interval);
+        let mut funding_interval = new_interval(self.config.funding_interval);
+        let mut events_interval = new_interval(self.config.events_interval);
+        let mut margin_interval = new_interval(self.config.margin_interval);
+        let mut discovery_interval = new_interval(self.config.discovery_interval);
+        // the first tick completes immediately and we have just discovered the targets
+        discovery_interval.tick().await;
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                _ = oracle_interval.tick(), if self.config.oracle_interval.is_some() => {
+                    self.crank_oracles().await;
+                },
+                _ = funding_interval.tick(), if self.config.funding_interval.is_some() => {
+                    self.crank_funding().await;
+                },
+                _ = events_interval.tick(), if self.config.events_interval.is_some() => {
+                    self.crank_events().await;
+                },
+                _ = margin_interval.tick(), if self.config.margin_interval.is_some() => {
+                    self.crank_margin().await;
+                },
+                _ = discovery_interval.tick(), if self.config.discovery_interval.is_some() => {
+                    if let Err(e) = self.discover().await {
+                        warn!("Failed to discover crank targets: {}", e.to_string());
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down crank service.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Caches the oracle prices of every oracle target.
+    pub async fn crank_oracles(&self) {
+        let targets = self.targets.read().await.oracles.clone();
+        let mut ixs = Vec::new();
+        for target in targets.iter() {
+            match target.get_ix() {
+                Ok(ix) => ixs.push(ix),
+                Err(e) => {
+                    warn!(
+                        "Failed to build oracle crank for cache {}: {}",
+                        usize::from(target.cache_index),
+                        e.to_string()
+                    );
+                }
+            }
+        }
+        self.submit("oracle", ixs).await;
+    }
+
+    /// Updates the funding rates of every funding target.
+    pub async fn crank_funding(&self) {
+        let ixs = self
+            .targets
+            .read()
+            .await
+            .funding
+            .iter()
+            .map(|t| t.get_ix())
+            .collect::<Vec<Instruction>>();
+        self.submit("funding", ixs).await;
+    }
+
+    /// Consumes the pending events of every event queue target, see [`EventsScanner`].
+    ///
+    /// Only the event queues with pending events which are not already being consumed are cranked,
+    /// and each instruction only references the orders accounts of the events it consumes.
+    pub async fn crank_events(&self) {
+        let targets = self.targets.read().await.events.clone();
+        let pending_events = match self.events_scanner.scan(&self.rpc_client, &targets).await {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("Failed to scan event queues: {}", e.to_string());
+                return;
+            }
+        };
+        let ixs = pending_events
+            .iter()
+            .map(|p| p.get_ix(self.config.events_limit))
+            .collect::<Vec<Instruction>>();
+        if self.submit("events", ixs).await {
+            for pending in pending_events.iter() {
+                self.events_scanner.mark_submitted(pending);
+            }
+        }
+    }
+
+    /// Refreshes the sub account caches of every margin account whose caches are stale
+    /// relative to the current cache prices.
+    ///
+    /// The cache account is reloaded with retries, the crank is skipped while it is stale, see [`ResilientContext`].
+    pub async fn crank_margin(&self) {
+        if self.config.margin_accounts.is_empty() {
+            return;
+        }
+        let latest_price_update = {
+            let mut cache_ctx = self.cache_ctx.lock().await;
+            match cache_ctx.as_mut() {
+                Some(c) => {
+                    c.reload(&self.rpc_client).await;
+                }
+                None => {
+                    *cache_ctx = match CacheContext::load(&self.rpc_client).await {
+                        Ok(c) => Some(ResilientContext::new(c, self.config.margin_reload_policy)),
+                        Err(e) => {
+                            warn!("Failed to load cache account: {}", e.to_string());
+                            return;
+                        }
+                    };
+                }
+            }
+            match cache_ctx.as_ref().and_then(|c| c.get_unless_stale()) {
+                Some(c) => get_latest_price_update(&c.state),
+                None => {
+                    warn!("Cache account is stale, skipping margin crank.");
+                    return;
+                }
+            }
+        };
+
+        let mut ixs = Vec::new();
+        for chunk in self.config.margin_accounts.chunks(100) {
+            let accounts = match self.rpc_client.get_multiple_accounts(chunk).await {
+                Ok(a) => a,
+                Err(e) => {
+                    warn!("Failed to fetch margin accounts: {}", e.to_string());
+                    return;
+                }
+            };
+            for (address, account) in chunk.iter().zip(accounts.iter()) {
+                let account = match account {
+                    Some(a) => a,
+                    None => {
+                        warn!("Margin account {} not found, skipping.", address);
+                        continue;
+                    }
+                };
+                let state = match try_get_zero_copy_account::<CypherAccount>(&account.data) {
+                    Some(s) => s,
+                    None => {
+                        warn!(
+                            "Margin account {} is not a valid account, skipping.",
+                            address
+                        );
+                        continue;
+                    }
+                };
+                if let Some(target) = get_margin_target(
+                    address,
+                    &state,
+                    latest_price_update,
+                    self.config.margin_max_age,
+                ) {
+                    info!(
+                        "Refreshing {} stale sub account caches of account {}.",
+                        target.stale_sub_accounts.len(),
+                        address
+                    );
+                    ixs.push(target.get_ix(&self.signer.pubkey()));
+                }
+            }
+        }
+        self.submit("margin", ixs).await;
+    }
+
+    /// Submits the given crank instructions, returning whether they were submitted.
+    async fn submit(&self, crank: &str, ixs: Vec<Instruction>) -> bool {
+        if ixs.is_empty() {
+            return false;
+        }
+        let ixs_len = ixs.len();
+        match send_transactions(
+            &self.rpc_client,
+            ixs,
+            &self.signer,
+            false,
+            Some((CRANK_COMPUTE_UNITS, self.config.compute_unit_price)),
+            None,
+        )
+        .await
+        {
+            Ok(s) => {
+                info!(
+                    "Submitted {} {} crank instructions - Signatures: {:?}",
+                    ixs_len, crank, s
+                );
+                true
+            }
+            Err(e) => {
+                warn!("Failed to submit {} cranks: {}", crank, e.to_string());
+                false
+            }
+        }
+    }
+}
+
+fn new_interval(period: Option<Duration>) -> tokio::time::Interval {
+    let mut interval =
+        tokio::time::interval(period.unwrap_or(Duration::from_secs(u32::MAX as u64)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    interval
+}
@@ -0,0 +1,209 @@
+use cypher_client::{quote_mint, utils::adjust_decimals, CacheAccount, CacheIndex};
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt::Display;
+
+use crate::risk::ClearingRiskReport;
+
+/// The default number of decimal places used when displaying values.
+pub const DEFAULT_DISPLAY_PRECISION: usize = 2;
+
+/// The symbol used when displaying values in the oracle denomination.
+pub const ORACLE_DENOMINATION_SYMBOL: &str = "USD";
+
+/// The currency in which valuation reports are displayed.
+///
+/// Account and clearing values are computed in the oracle denomination, which is not necessarily
+/// the clearing's quote token, e.g. the devnet quote mint is not mainnet USDC, so values are
+/// converted with the cached oracle price of the selected token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplayCurrency {
+    /// The oracle denomination, values are displayed as computed.
+    Oracle,
+    /// A token priced by the [`CacheAccount`].
+    Token {
+        /// The token's symbol.
+        symbol: String,
+        /// The token's mint.
+        mint: Pubkey,
+        /// The index of the token's price cache.
+        cache_index: CacheIndex,
+    },
+}
+
+impl Default for DisplayCurrency {
+    fn default() -> Self {
+        Self::Oracle
+    }
+}
+
+impl DisplayCurrency {
+    /// Creates a [`DisplayCurrency`] for the clearing's quote token, whose price cache is at the given index.
+    pub fn quote(symbol: &str, cache_index: CacheIndex) -> Self {
+        Self::Token {
+            symbol: symbol.to_string(),
+            mint: quote_mint::id(),
+            cache_index,
+        }
+    }
+
+    /// Creates a [`DisplayCurrency`] for the given token, whose price cache is at the given index.
+    pub fn token(symbol: &str, mint: &Pubkey, cache_index: CacheIndex) -> Self {
+        Self::Token {
+            symbol: symbol.to_string(),
+            mint: *mint,
+            cache_index,
+        }
+    }
+
+    /// The symbol of the currency.
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Oracle => ORACLE_DENOMINATION_SYMBOL,
+            Self::Token { symbol, .. } => symbol,
+        }
+    }
+
+    /// Whether this is the clearing's quote token.
+    pub fn is_quote(&self) -> bool {
+        match self {
+            Self::Oracle => false,
+            Self::Token { mint, .. } => *mint == quote_mint::id(),
+        }
+    }
+
+    /// Gets the price of one unit of the currency in the oracle denomination.
+    ///
+    /// Returns `None` if the cached price is not set.
+    pub fn get_price(&self, cache_account: &CacheAccount) -> Option<I80F48> {
+        match self {
+            Self::Oracle => Some(I80F48::ONE),
+            Self::Token { cache_index, .. } => {
+                let price = cache_account.get_cache(*cache_index).oracle_price();
+                if price.is_positive() {
+                    Some(price)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Converts the given value, in the oracle denomination, into this currency.
+    ///
+    /// Returns `None` if the cached price is not set.
+    pub fn convert(&self, value: I80F48, cache_account: &CacheAccount) -> Option<DisplayValue> {
+        let price = self.get_price(cache_account)?;
+        Some(DisplayValue {
+            value: value.checked_div(price)?,
+            symbol: self.symbol().to_string(),
+            precision: DEFAULT_DISPLAY_PRECISION,
+        })
+    }
+
+    /// Converts the given amount of a token, in native units, into this currency.
+    ///
+    /// Returns `None` if one of the cached prices is not set.
+    pub fn convert_token_amount(
+        &self,
+        amount: I80F48,
+        token_cache_index: CacheIndex,
+        cache_account: &CacheAccount,
+    ) -> Option<DisplayValue> {
+        self.convert(
+            get_token_value(amount, token_cache_index, cache_account),
+            cache_account,
+        )
+    }
+}
+
+/// Gets the value of the given amount of a token, in native units, in the oracle denomination.
+pub fn get_token_value(
+    amount: I80F48,
+    token_cache_index: CacheIndex,
+    cache_account: &CacheAccount,
+) -> I80F48 {
+    let cache = cache_account.get_cache(token_cache_index);
+    adjust_decimals(amount, cache.decimals).saturating_mul(cache.oracle_price())
+}
+
+/// A value converted into a [`DisplayCurrency`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayValue {
+    /// The value.
+    pub value: I80F48,
+    /// The symbol of the currency.
+    pub symbol: String,
+    /// The number of decimal places displayed.
+    pub precision: usize,
+}
+
+impl DisplayValue {
+    /// Sets the number of decimal places displayed.
+    pub fn with_precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+}
+
+impl Display for DisplayValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.*} {}",
+            self.precision,
+            self.value.to_num::<f64>(),
+            self.symbol
+        )
+    }
+}
+
+/// The values of a [`ClearingRiskReport`] converted into a [`DisplayCurrency`].
+#[derive(Debug, Clone)]
+pub struct ClearingValuationDisplay {
+    /// The aggregate maintenance weighted assets value.
+    pub assets_value: DisplayValue,
+    /// The aggregate maintenance weighted liabilities value.
+    pub liabilities_value: DisplayValue,
+    /// The total deposits and borrows value per token mint, for tokens whose price cache is known.
+    pub tokens: Vec<(Pubkey, DisplayValue, DisplayValue)>,
+}
+
+impl ClearingRiskReport {
+    /// Converts the values of the report into the given [`DisplayCurrency`].
+    ///
+    /// The `token_caches` map token mints to the index of their price cache, tokens which are not
+    /// in it are not valued. Returns `None` if the display currency's cached price is not set.
+    pub fn get_valuation_display(
+        &self,
+        currency: &DisplayCurrency,
+        cache_account: &CacheAccount,
+        token_caches: &[(Pubkey, CacheIndex)],
+    ) -> Option<ClearingValuationDisplay> {
+        let assets_value = currency.convert(self.assets_value, cache_account)?;
+        let liabilities_value = currency.convert(self.liabilities_value, cache_account)?;
+        let mut tokens = Vec::new();
+        for (mint, cache_index) in token_caches.iter() {
+            let metrics = match self.tokens.get(mint) {
+                Some(m) => m,
+                None => continue,
+            };
+            let deposits = currency.convert_token_amount(
+                metrics.total_deposits,
+                *cache_index,
+                cache_account,
+            )?;
+            let borrows = currency.convert_token_amount(
+                metrics.total_borrows,
+                *cache_index,
+                cache_account,
+            )?;
+            tokens.push((*mint, deposits, borrows));
+        }
+        Some(ClearingValuationDisplay {
+            assets_value,
+            liabilities_value,
+            tokens,
+        })
+    }
+}
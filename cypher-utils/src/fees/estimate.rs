@@ -0,0 +1,268 @@
+use fixed::types::I80F48;
+use solana_client::rpc_response::RpcPrioritizationFee;
+use solana_sdk::{
+    compute_budget, instruction::Instruction, message::Message, native_token::lamports_to_sol,
+    pubkey::Pubkey, transaction::Transaction,
+};
+
+/// The base fee charged per transaction signature, in lamports.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// The compute units allotted to each instruction if a transaction does not request a compute unit limit.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u32 = 200_000;
+
+/// The maximum compute units a transaction can use.
+pub const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// The number of recent prioritization fees averaged into the recommended compute unit price.
+pub const RECOMMENDED_FEE_SAMPLES: usize = 10;
+
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// The instruction tags of the compute budget program.
+const SET_COMPUTE_UNIT_LIMIT_TAG: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+
+/// The expected fee of a transaction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionFeeEstimate {
+    /// The number of signatures.
+    pub signatures: u64,
+    /// The compute unit limit the priority fee is charged on.
+    pub compute_unit_limit: u32,
+    /// The compute unit price set by the transaction, in micro-lamports.
+    pub compute_unit_price: u64,
+    /// The recommended compute unit price at the time of the estimate, in micro-lamports.
+    pub recommended_compute_unit_price: Option<u64>,
+}
+
+impl TransactionFeeEstimate {
+    /// Creates a new [`TransactionFeeEstimate`].
+    pub fn new(
+        signatures: u64,
+        compute_unit_limit: u32,
+        compute_unit_price: u64,
+        recommended_compute_unit_price: Option<u64>,
+    ) -> Self {
+        Self {
+            signatures,
+            compute_unit_limit,
+            compute_unit_price,
+            recommended_compute_unit_price,
+        }
+    }
+
+    /// The fee charged for the signatures, in lamports.
+    pub fn base_fee(&self) -> u64 {
+        self.signatures.saturating_mul(LAMPORTS_PER_SIGNATURE)
+    }
+
+    /// The priority fee at the transaction's compute unit price, in lamports.
+    pub fn priority_fee(&self) -> u64 {
+        get_priority_fee(self.compute_unit_limit, self.compute_unit_price)
+    }
+
+    /// The total fee at the transaction's compute unit price, in lamports.
+    pub fn total_fee(&self) -> u64 {
+        self.base_fee().saturating_add(self.priority_fee())
+    }
+
+    /// The total fee at the recommended compute unit price, in lamports, if there is a recommendation.
+    pub fn recommended_total_fee(&self) -> Option<u64> {
+        self.recommended_compute_unit_price.map(|p| {
+            self.base_fee()
+                .saturating_add(get_priority_fee(self.compute_unit_limit, p))
+        })
+    }
+
+    /// The total fee at the transaction's compute unit price, in SOL.
+    pub fn total_fee_sol(&self) -> f64 {
+        lamports_to_sol(self.total_fee())
+    }
+
+    /// The total fee at the transaction's compute unit price, in quote tokens,
+    /// given the price of SOL in quote tokens.
+    pub fn total_fee_in_quote(&self, sol_price: I80F48) -> I80F48 {
+        I80F48::from_num(self.total_fee_sol()).saturating_mul(sol_price)
+    }
+}
+
+impl std::fmt::Display for TransactionFeeEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Signatures: {} - CU Limit: {} - CU Price: {} - Base Fee: {} - Priority Fee: {} - Total Fee: {} ({} SOL)",
+            self.signatures,
+            self.compute_unit_limit,
+            self.compute_unit_price,
+            self.base_fee(),
+            self.priority_fee(),
+            self.total_fee(),
+            self.total_fee_sol()
+        )?;
+        if let (Some(price), Some(fee)) = (
+            self.recommended_compute_unit_price,
+            self.recommended_total_fee(),
+        ) {
+            write!(
+                f,
+                " - Recommended CU Price: {} - Recommended Total Fee: {} ({} SOL)",
+                price,
+                fee,
+                lamports_to_sol(fee)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the priority fee, in lamports, for the given compute unit limit and price, in micro-lamports, rounded up.
+pub fn get_priority_fee(compute_unit_limit: u32, compute_unit_price: u64) -> u64 {
+    let micro_lamports = compute_unit_limit as u128 * compute_unit_price as u128;
+    let lamports = (micro_lamports + MICRO_LAMPORTS_PER_LAMPORT - 1) / MICRO_LAMPORTS_PER_LAMPORT;
+    u64::try_from(lamports).unwrap_or(u64::MAX)
+}
+
+/// Gets the recommended compute unit price, in micro-lamports, as the average of the most recent
+/// prioritization fees, e.g. as cached by the [`ChainMetaService`](crate::services::ChainMetaService).
+///
+/// Returns `None` if there are no prioritization fees.
+pub fn get_recommended_compute_unit_price(fees: &[RpcPrioritizationFee]) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    let mut fees = fees.to_vec();
+    fees.sort_by(|a, b| b.slot.cmp(&a.slot));
+    let recent = &fees[..fees.len().min(RECOMMENDED_FEE_SAMPLES)];
+    let sum = recent
+        .iter()
+        .fold(0u128, |acc, f| acc + f.prioritization_fee as u128);
+    Some((sum / recent.len() as u128) as u64)
+}
+
+/// A percentile of the recent prioritization fees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriorityFeePercentile {
+    P50,
+    P75,
+    P90,
+    P99,
+}
+
+impl PriorityFeePercentile {
+    /// Gets the percentile, between 0 and 100.
+    pub fn percentile(&self) -> u8 {
+        match self {
+            Self::P50 => 50,
+            Self::P75 => 75,
+            Self::P90 => 90,
+            Self::P99 => 99,
+        }
+    }
+}
+
+/// Gets the given percentile, between 0 and 100, of the given prioritization fees, in micro-lamports,
+/// using the nearest-rank method.
+///
+/// Unlike an average, a percentile is not skewed by a few outliers, e.g. a single slot with a very high fee.
+/// Returns `None` if there are no prioritization fees.
+pub fn get_priority_fee_percentile(fees: &[RpcPrioritizationFee], percentile: u8) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    let mut values = fees
+        .iter()
+        .map(|f| f.prioritization_fee)
+        .collect::<Vec<u64>>();
+    values.sort_unstable();
+    let rank = (percentile.min(100) as usize * values.len() + 99) / 100;
+    Some(values[rank.max(1) - 1])
+}
+
+/// Gets the exponential moving average of the given prioritization fees over the given span of slots,
+/// in micro-lamports, weighting recent slots more than older ones.
+///
+/// The smoothing factor is `2 / (span + 1)`. Returns `None` if there are no prioritization fees.
+pub fn get_priority_fee_ema(fees: &[RpcPrioritizationFee], span: u64) -> Option<u64> {
+    if fees.is_empty() {
+        return None;
+    }
+    let mut fees = fees.to_vec();
+    fees.sort_by_key(|f| f.slot);
+    let alpha = I80F48::from(2) / I80F48::from(span.saturating_add(1));
+    let mut ema = I80F48::from(fees[0].prioritization_fee);
+    for fee in fees.iter().skip(1) {
+        ema += alpha * (I80F48::from(fee.prioritization_fee) - ema);
+    }
+    Some(ema.to_num::<u64>())
+}
+
+/// Gets the compute unit limit and price, in micro-lamports, requested by the given message's compute budget instructions.
+///
+/// If no limit is requested, the default limit of [`DEFAULT_INSTRUCTION_COMPUTE_UNITS`] per instruction applies,
+/// if no price is requested, the price is zero.
+pub fn get_compute_budget(message: &Message) -> (u32, u64) {
+    let mut compute_unit_limit = None;
+    let mut compute_unit_price = 0;
+    let mut instructions: u32 = 0;
+
+    for ix in message.instructions.iter() {
+        let is_compute_budget = message
+            .account_keys
+            .get(ix.program_id_index as usize)
+            .map(|k| k == &compute_budget::id())
+            .unwrap_or(false);
+        if !is_compute_budget {
+            instructions += 1;
+            continue;
+        }
+        match ix.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_LIMIT_TAG, data)) if data.len() >= 4 => {
+                compute_unit_limit = Some(u32::from_le_bytes(data[..4].try_into().unwrap()));
+            }
+            Some((&SET_COMPUTE_UNIT_PRICE_TAG, data)) if data.len() >= 8 => {
+                compute_unit_price = u64::from_le_bytes(data[..8].try_into().unwrap());
+            }
+            _ => (),
+        }
+    }
+
+    let compute_unit_limit = compute_unit_limit
+        .unwrap_or_else(|| instructions.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNITS))
+        .min(MAX_TRANSACTION_COMPUTE_UNITS);
+    (compute_unit_limit, compute_unit_price)
+}
+
+/// Estimates the fee of the given message.
+pub fn estimate_message_fee(
+    message: &Message,
+    recommended_compute_unit_price: Option<u64>,
+) -> TransactionFeeEstimate {
+    let (compute_unit_limit, compute_unit_price) = get_compute_budget(message);
+    TransactionFeeEstimate::new(
+        message.header.num_required_signatures as u64,
+        compute_unit_limit,
+        compute_unit_price,
+        recommended_compute_unit_price,
+    )
+}
+
+/// Estimates the fee of the given built [`Transaction`].
+pub fn estimate_transaction_fee(
+    transaction: &Transaction,
+    recommended_compute_unit_price: Option<u64>,
+) -> TransactionFeeEstimate {
+    estimate_message_fee(&transaction.message, recommended_compute_unit_price)
+}
+
+/// Estimates the fee of a transaction with the given instructions and fee payer.
+pub fn estimate_instructions_fee(
+    ixs: &[Instruction],
+    payer: &Pubkey,
+    recommended_compute_unit_price: Option<u64>,
+) -> TransactionFeeEstimate {
+    estimate_message_fee(
+        &Message::new(ixs, Some(payer)),
+        recommended_compute_unit_price,
+    )
+}
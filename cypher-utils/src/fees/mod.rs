@@ -0,0 +1,15 @@
+pub mod estimate;
+#[cfg(feature = "storage")]
+pub mod revenue;
+pub mod rewards;
+pub mod sweep;
+pub mod tiers;
+pub mod volume;
+
+pub use estimate::*;
+#[cfg(feature = "storage")]
+pub use revenue::*;
+pub use rewards::*;
+pub use sweep::*;
+pub use tiers::*;
+pub use volume::*;
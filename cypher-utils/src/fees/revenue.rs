@@ -0,0 +1,366 @@
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::TokenAccount;
+use cypher_client::{utils::derive_token_address, FuturesMarket, PerpetualMarket};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    contexts::{ContextError, MarketContext, PoolContext},
+    storage::{append_events, query_events, Storage, StorageError},
+};
+
+use super::{FeeSweepReport, FeeSweepSource};
+
+/// The storage stream fee sweeps are persisted to.
+pub const FEE_SWEEPS_STREAM: &str = "fee_sweeps";
+
+/// A sweep of the fees accrued by a market or pool to the treasury.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSweepRecord {
+    /// The kind of account the fees were swept from.
+    pub source: FeeSweepSource,
+    /// The market or pool.
+    pub address: Pubkey,
+    /// The mint of the swept token.
+    pub token_mint: Pubkey,
+    /// The swept amount, in native units.
+    pub amount: u64,
+    /// The slot at which the sweep was submitted.
+    pub slot: u64,
+    /// The unix timestamp at which the sweep was submitted, in seconds.
+    pub timestamp: i64,
+}
+
+/// The fee revenue of a market or pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeRevenue {
+    /// The kind of account.
+    pub source: FeeSweepSource,
+    /// The market or pool.
+    pub address: Pubkey,
+    /// The mint of the token fees are accrued in.
+    pub token_mint: Pubkey,
+    /// The cumulative amount swept to the treasury, in native units.
+    pub swept: u64,
+    /// The amount currently accrued and not yet swept, in native units.
+    pub accrued: u64,
+    /// The unix timestamp of the most recent sweep, if any.
+    pub last_swept_at: Option<i64>,
+}
+
+impl FeeRevenue {
+    /// The total revenue, i.e. the swept and the accrued amount.
+    pub fn total(&self) -> u64 {
+        self.swept.saturating_add(self.accrued)
+    }
+}
+
+/// The fee revenue of the protocol in a given token.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TokenRevenue {
+    /// The cumulative amount swept to the treasury, in native units.
+    pub swept: u64,
+    /// The amount currently accrued and not yet swept, in native units.
+    pub accrued: u64,
+    /// The most recently observed balance of the treasury's token account, in native units.
+    pub treasury_balance: Option<u64>,
+}
+
+/// Tracks the cumulative fees swept to the treasury per market and pool, along with the fees
+/// currently accrued and the treasury's token balances, to report the protocol's fee revenue.
+///
+/// Sweeps can optionally be persisted to a [`Storage`] backend so the revenue history survives restarts.
+#[derive(Default)]
+pub struct FeeRevenueTracker {
+    revenues: HashMap<Pubkey, FeeRevenue>,
+    sweeps: Vec<FeeSweepRecord>,
+    treasury_balances: HashMap<Pubkey, u64>,
+    storage: Option<Box<dyn Storage>>,
+}
+
+impl std::fmt::Debug for FeeRevenueTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FeeRevenueTracker")
+            .field("revenues", &self.revenues.len())
+            .field("sweeps", &self.sweeps.len())
+            .field("storage", &self.storage.is_some())
+            .finish()
+    }
+}
+
+impl FeeRevenueTracker {
+    /// Creates a new [`FeeRevenueTracker`] which persists sweeps to the given [`Storage`].
+    pub fn new(storage: Box<dyn Storage>) -> Self {
+        Self {
+            storage: Some(storage),
+            ..Default::default()
+        }
+    }
+
+    /// Loads the sweeps persisted to the [`Storage`] with a timestamp between `start` and `end`, inclusive,
+    /// adding them to the cumulative swept amounts.
+    ///
+    /// Returns the number of loaded sweeps.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the sweeps could not be read.
+    pub fn load_history(&mut self, start: i64, end: i64) -> Result<usize, StorageError> {
+        let records = match &self.storage {
+            Some(storage) => {
+                match query_events::<FeeSweepRecord>(
+                    storage.as_ref(),
+                    FEE_SWEEPS_STREAM,
+                    start,
+                    end,
+                ) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+            }
+            None => return Ok(0),
+        };
+        let loaded = records.len();
+        for record in records {
+            self.add_sweep(record);
+        }
+        Ok(loaded)
+    }
+
+    /// Records the sweeps of the given [`FeeSweepReport`], dry runs are ignored.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the sweeps could not be persisted,
+    /// in which case they are still recorded in memory.
+    pub fn record_sweep(
+        &mut self,
+        report: &FeeSweepReport,
+        slot: u64,
+        timestamp: i64,
+    ) -> Result<(), StorageError> {
+        if report.dry_run || report.signatures.is_empty() {
+            return Ok(());
+        }
+        let records = report
+            .plan
+            .targets
+            .iter()
+            .map(|t| FeeSweepRecord {
+                source: t.source,
+                address: t.address,
+                token_mint: t.token_mint,
+                amount: t.amount,
+                slot,
+                timestamp,
+            })
+            .collect::<Vec<FeeSweepRecord>>();
+        for record in records.iter() {
+            self.add_sweep(record.clone());
+        }
+
+        match &self.storage {
+            Some(storage) => append_events(
+                storage.as_ref(),
+                FEE_SWEEPS_STREAM,
+                &records
+                    .into_iter()
+                    .map(|r| (slot, timestamp, r))
+                    .collect::<Vec<_>>(),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the fees currently accrued by the given markets and pools.
+    ///
+    /// Market fees are accrued in the token of the market's quote pool, markets whose quote pool
+    /// is not among the given pools are skipped.
+    pub fn record_accrued(
+        &mut self,
+        perp_markets: &[MarketContext<PerpetualMarket>],
+        futures_markets: &[MarketContext<FuturesMarket>],
+        pools: &[PoolContext],
+    ) {
+        let markets = perp_markets
+            .iter()
+            .map(|m| (FeeSweepSource::PerpetualMarket, m.address, &m.state.inner))
+            .chain(
+                futures_markets
+                    .iter()
+                    .map(|m| (FeeSweepSource::FuturesMarket, m.address, &m.state.inner)),
+            );
+        for (source, market, inner) in markets {
+            let token_mint = match pools.iter().find(|p| p.address == inner.quote_pool) {
+                Some(p) => p.state.token_mint,
+                None => {
+                    warn!(
+                        "Quote pool {} of market {} is not loaded, skipping.",
+                        inner.quote_pool, market
+                    );
+                    continue;
+                }
+            };
+            self.get_revenue_mut(source, &market, &token_mint).accrued = inner.protocol_fees;
+        }
+        for pool in pools.iter() {
+            self.get_revenue_mut(FeeSweepSource::Pool, &pool.address, &pool.state.token_mint)
+                .accrued = pool.state.protocol_fees;
+        }
+    }
+
+    /// Fetches and records the balances of the treasury's associated token accounts for the given token mints.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn fetch_treasury_balances(
+        &mut self,
+        rpc_client: &RpcClient,
+        treasury: &Pubkey,
+        token_mints: &[Pubkey],
+    ) -> Result<(), ContextError> {
+        let token_accounts = token_mints
+            .iter()
+            .map(|m| derive_token_address(treasury, m))
+            .collect::<Vec<Pubkey>>();
+        let mut accounts = Vec::with_capacity(token_accounts.len());
+        // the rpc limits the number of accounts per request
+        for chunk in token_accounts.chunks(100) {
+            match rpc_client.get_multiple_accounts(chunk).await {
+                Ok(a) => accounts.extend(a),
+                Err(e) => {
+                    return Err(ContextError::ClientError(e));
+                }
+            }
+        }
+
+        for ((token_mint, token_account), account) in token_mints
+            .iter()
+            .zip(token_accounts.iter())
+            .zip(accounts.iter())
+        {
+            let balance = match account {
+                Some(a) => match TokenAccount::try_deserialize(&mut a.data.as_slice()) {
+                    Ok(ta) => ta.amount,
+                    Err(e) => {
+                        warn!(
+                            "Failed to deserialize treasury token account {}: {}",
+                            token_account,
+                            e.to_string()
+                        );
+                        continue;
+                    }
+                },
+                None => 0,
+            };
+            self.treasury_balances.insert(*token_mint, balance);
+        }
+        Ok(())
+    }
+
+    /// Gets the fee revenue of the given market or pool.
+    pub fn get_revenue(&self, address: &Pubkey) -> Option<&FeeRevenue> {
+        self.revenues.get(address)
+    }
+
+    /// Gets the fee revenue of every tracked market and pool, highest total revenue first.
+    pub fn get_revenues(&self) -> Vec<FeeRevenue> {
+        let mut revenues = self.revenues.values().copied().collect::<Vec<FeeRevenue>>();
+        revenues.sort_by(|a, b| b.total().cmp(&a.total()));
+        revenues
+    }
+
+    /// Gets the fee revenue of the protocol per token mint.
+    pub fn get_revenue_by_token(&self) -> BTreeMap<Pubkey, TokenRevenue> {
+        let mut by_token: BTreeMap<Pubkey, TokenRevenue> = BTreeMap::new();
+        for revenue in self.revenues.values() {
+            let token_revenue = by_token.entry(revenue.token_mint).or_default();
+            token_revenue.swept = token_revenue.swept.saturating_add(revenue.swept);
+            token_revenue.accrued = token_revenue.accrued.saturating_add(revenue.accrued);
+        }
+        for (token_mint, balance) in self.treasury_balances.iter() {
+            by_token.entry(*token_mint).or_default().treasury_balance = Some(*balance);
+        }
+        by_token
+    }
+
+    /// Gets the amount swept per token mint by the recorded sweeps with a timestamp between `start` and `end`, inclusive.
+    pub fn get_swept_between(&self, start: i64, end: i64) -> BTreeMap<Pubkey, u64> {
+        let mut swept: BTreeMap<Pubkey, u64> = BTreeMap::new();
+        for record in self
+            .sweeps
+            .iter()
+            .filter(|r| r.timestamp >= start && r.timestamp <= end)
+        {
+            let amount = swept.entry(record.token_mint).or_default();
+            *amount = amount.saturating_add(record.amount);
+        }
+        swept
+    }
+
+    /// Gets the recorded sweeps, oldest first.
+    pub fn get_sweeps(&self) -> &[FeeSweepRecord] {
+        &self.sweeps
+    }
+
+    /// Gets a human readable revenue report, one line per token and per market or pool.
+    pub fn get_report(&self) -> String {
+        let mut report = String::from("Fee revenue");
+        for (token_mint, revenue) in self.get_revenue_by_token() {
+            report.push_str(&format!(
+                "\nToken {} - Swept: {} - Accrued: {} - Treasury: {}",
+                token_mint,
+                revenue.swept,
+                revenue.accrued,
+                revenue
+                    .treasury_balance
+                    .map(|b| b.to_string())
+                    .unwrap_or_else(|| "N/A".to_string())
+            ));
+        }
+        for revenue in self.get_revenues() {
+            report.push_str(&format!(
+                "\n{:?} {} - Swept: {} - Accrued: {} - Total: {}",
+                revenue.source,
+                revenue.address,
+                revenue.swept,
+                revenue.accrued,
+                revenue.total()
+            ));
+        }
+        report
+    }
+
+    fn add_sweep(&mut self, record: FeeSweepRecord) {
+        let revenue = self.get_revenue_mut(record.source, &record.address, &record.token_mint);
+        revenue.swept = revenue.swept.saturating_add(record.amount);
+        revenue.last_swept_at = Some(
+            revenue
+                .last_swept_at
+                .map_or(record.timestamp, |ts| ts.max(record.timestamp)),
+        );
+        self.sweeps.push(record);
+    }
+
+    fn get_revenue_mut(
+        &mut self,
+        source: FeeSweepSource,
+        address: &Pubkey,
+        token_mint: &Pubkey,
+    ) -> &mut FeeRevenue {
+        self.revenues.entry(*address).or_insert(FeeRevenue {
+            source,
+            address: *address,
+            token_mint: *token_mint,
+            swept: 0,
+            accrued: 0,
+            last_swept_at: None,
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use cypher_client::{aob::CallBackInfo, Market, Side};
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::contexts::{get_aob_orders_with_callback_infos, Order};
+
+/// The parameters of a liquidity mining epoch.
+#[derive(Debug, Clone, Copy)]
+pub struct LiquidityMiningConfig {
+    /// The unix timestamp at which the epoch starts, inclusive.
+    pub epoch_start: u64,
+    /// The unix timestamp at which the epoch ends, exclusive.
+    pub epoch_end: u64,
+    /// The maximum distance from the mid price, in basis points, at which resting orders earn points.
+    pub max_spread_bps: u16,
+    /// The minimum base quantity, in native units, an order must have to earn points.
+    pub min_base_quantity: u64,
+    /// The rewards distributed at the end of the epoch, in native units of the reward token.
+    pub total_rewards: u64,
+}
+
+impl LiquidityMiningConfig {
+    /// Whether the given unix timestamp is within the epoch.
+    pub fn is_within_epoch(&self, timestamp: u64) -> bool {
+        timestamp >= self.epoch_start && timestamp < self.epoch_end
+    }
+}
+
+/// The liquidity mining points accrued by a master account.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AccountRewardPoints {
+    /// The accrued points.
+    pub points: I80F48,
+    /// The number of samples in which the account had at least one qualifying bid.
+    pub bid_samples: u64,
+    /// The number of samples in which the account had at least one qualifying ask.
+    pub ask_samples: u64,
+}
+
+/// Tracks the liquidity mining points accrued by makers over an epoch by sampling the resting orders near the mid price.
+///
+/// On every sample each qualifying order earns points proportional to its base quantity, weighted linearly
+/// from one at the mid price down to zero at the maximum spread. The owner of each order is taken from it's
+/// [`CallBackInfo`], so points are accrued by master account. The expected rewards of an account are it's share
+/// of all accrued points, which is only an estimate of the on-chain distribution.
+#[derive(Debug, Clone)]
+pub struct LiquidityMiningTracker {
+    /// The epoch parameters.
+    pub config: LiquidityMiningConfig,
+    points: HashMap<Pubkey, AccountRewardPoints>,
+    total_points: I80F48,
+    samples: u64,
+}
+
+impl LiquidityMiningTracker {
+    /// Creates a new [`LiquidityMiningTracker`] for the given epoch.
+    pub fn new(config: LiquidityMiningConfig) -> Self {
+        Self {
+            config,
+            points: HashMap::new(),
+            total_points: I80F48::ZERO,
+            samples: 0,
+        }
+    }
+
+    /// Samples the orders of the given book sides, as returned by [`get_aob_orders_with_callback_infos`],
+    /// at the given unix timestamp.
+    ///
+    /// Returns `false` if the timestamp is outside of the epoch or the book is one sided, in which case nothing is accrued.
+    pub fn record_sample(
+        &mut self,
+        timestamp: u64,
+        bids: &[(Order, CallBackInfo)],
+        asks: &[(Order, CallBackInfo)],
+    ) -> bool {
+        if !self.config.is_within_epoch(timestamp) {
+            return false;
+        }
+        let mid_price = match (bids.first(), asks.first()) {
+            (Some((bid, _)), Some((ask, _))) => {
+                (I80F48::from(bid.price) + I80F48::from(ask.price)) / I80F48::from(2)
+            }
+            _ => return false,
+        };
+        if mid_price == I80F48::ZERO {
+            return false;
+        }
+        let max_spread = I80F48::from(self.config.max_spread_bps) / I80F48::from(10_000);
+
+        for (orders, is_bid) in [(bids, true), (asks, false)] {
+            let mut sampled_accounts: Vec<Pubkey> = Vec::new();
+            for (order, callback_info) in orders.iter() {
+                if callback_info.is_empty() || order.base_quantity < self.config.min_base_quantity {
+                    continue;
+                }
+                let spread = (I80F48::from(order.price) - mid_price).abs() / mid_price;
+                // orders are sorted from best to worst price, so the remaining ones are further away
+                if spread > max_spread {
+                    break;
+                }
+                let weight = if max_spread == I80F48::ZERO {
+                    I80F48::ONE
+                } else {
+                    I80F48::ONE - spread / max_spread
+                };
+                let points = I80F48::from(order.base_quantity).saturating_mul(weight);
+                let (master_account, _) = callback_info.owner();
+                let account_points = self.points.entry(master_account).or_default();
+                account_points.points = account_points.points.saturating_add(points);
+                self.total_points = self.total_points.saturating_add(points);
+                if !sampled_accounts.contains(&master_account) {
+                    sampled_accounts.push(master_account);
+                    if is_bid {
+                        account_points.bid_samples += 1;
+                    } else {
+                        account_points.ask_samples += 1;
+                    }
+                }
+            }
+        }
+
+        self.samples += 1;
+        true
+    }
+
+    /// Samples the book of the given [`Market`] from the raw account data of it's bids and asks at the given unix timestamp.
+    ///
+    /// See [`LiquidityMiningTracker::record_sample`].
+    pub fn record_sample_from_account_data(
+        &mut self,
+        timestamp: u64,
+        market_state: &dyn Market,
+        bids_data: &[u8],
+        asks_data: &[u8],
+    ) -> bool {
+        let bids = get_aob_orders_with_callback_infos(market_state, bids_data, Side::Bid);
+        let asks = get_aob_orders_with_callback_infos(market_state, asks_data, Side::Ask);
+        self.record_sample(timestamp, &bids, &asks)
+    }
+
+    /// Gets the number of recorded samples.
+    pub fn get_samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Gets the points accrued by the given master account.
+    pub fn get_points(&self, account: &Pubkey) -> AccountRewardPoints {
+        self.points.get(account).copied().unwrap_or_default()
+    }
+
+    /// Gets the points accrued by all master accounts.
+    pub fn get_total_points(&self) -> I80F48 {
+        self.total_points
+    }
+
+    /// Gets the share of all accrued points of the given master account.
+    pub fn get_share(&self, account: &Pubkey) -> I80F48 {
+        if self.total_points == I80F48::ZERO {
+            return I80F48::ZERO;
+        }
+        self.get_points(account).points / self.total_points
+    }
+
+    /// Gets the rewards the given master account is expected to receive at the end of the epoch,
+    /// in native units of the reward token, assuming it's share of points does not change.
+    pub fn get_expected_rewards(&self, account: &Pubkey) -> u64 {
+        self.get_share(account)
+            .saturating_mul(I80F48::from(self.config.total_rewards))
+            .to_num::<u64>()
+    }
+
+    /// Gets every master account that accrued points along with it's expected rewards, most points first.
+    pub fn get_leaderboard(&self) -> Vec<(Pubkey, AccountRewardPoints, u64)> {
+        let mut leaderboard = self
+            .points
+            .iter()
+            .map(|(account, points)| (*account, *points, self.get_expected_rewards(account)))
+            .collect::<Vec<_>>();
+        leaderboard.sort_by(|a, b| b.1.points.cmp(&a.1.points));
+        leaderboard
+    }
+
+    /// Clears the accrued points and starts tracking the given epoch.
+    pub fn reset(&mut self, config: LiquidityMiningConfig) {
+        self.config = config;
+        self.points.clear();
+        self.total_points = I80F48::ZERO;
+        self.samples = 0;
+    }
+}
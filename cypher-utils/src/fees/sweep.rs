@@ -0,0 +1,243 @@
+use cypher_client::{
+    instructions::{sweep_market_fees, sweep_pool_fees},
+    utils::derive_token_address,
+    FuturesMarket, PerpetualMarket,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signature::Signature,
+};
+
+use crate::{
+    contexts::{MarketContext, PoolContext},
+    utils::send_transactions,
+};
+
+use super::TransactionFeeEstimate;
+
+/// The compute units requested for each fee sweeping transaction.
+const SWEEP_COMPUTE_UNITS: u32 = 400_000;
+
+/// The compute unit price, in micro-lamports, of each fee sweeping transaction.
+const SWEEP_COMPUTE_UNIT_PRICE: u64 = 1;
+
+/// The kind of account whose fees are swept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeSweepSource {
+    /// A perpetual market, fees are accrued in the quote token.
+    PerpetualMarket,
+    /// A futures market, fees are accrued in the quote token.
+    FuturesMarket,
+    /// A pool, fees are accrued in the pool's token.
+    Pool,
+}
+
+/// An account with accrued fees above the sweeping threshold.
+#[derive(Debug, Clone)]
+pub struct FeeSweepTarget {
+    /// The kind of account.
+    pub source: FeeSweepSource,
+    /// The market or pool.
+    pub address: Pubkey,
+    /// The pool node whose vault the fees are swept from.
+    pub pool_node: Pubkey,
+    /// The mint of the swept token.
+    pub token_mint: Pubkey,
+    /// The treasury token account the fees are swept to.
+    pub destination: Pubkey,
+    /// The accrued fees, in native units of the swept token.
+    pub amount: u64,
+}
+
+/// The plan of a fee sweep, i.e. every account to sweep fees from along with the instructions to do so.
+#[derive(Debug, Default, Clone)]
+pub struct FeeSweepPlan {
+    /// The accounts to sweep fees from.
+    pub targets: Vec<FeeSweepTarget>,
+    /// The sweep instructions, one per target and in the same order.
+    pub ixs: Vec<Instruction>,
+}
+
+impl FeeSweepPlan {
+    /// Gets the total amount to be swept for the given token mint, in native units.
+    pub fn get_total_amount(&self, token_mint: &Pubkey) -> u64 {
+        self.targets
+            .iter()
+            .filter(|t| t.token_mint == *token_mint)
+            .map(|t| t.amount)
+            .sum()
+    }
+
+    /// Gets a human readable report of the amounts to be swept, one line per target.
+    pub fn get_report(&self) -> String {
+        let mut report = format!("Fee sweep - Targets: {}", self.targets.len());
+        for target in self.targets.iter() {
+            report.push_str(&format!(
+                "\n{:?} {} - Mint: {} - Amount: {} - Destination: {}",
+                target.source, target.address, target.token_mint, target.amount, target.destination
+            ));
+        }
+        report
+    }
+}
+
+/// The result of a fee sweep.
+#[derive(Debug, Default, Clone)]
+pub struct FeeSweepReport {
+    /// The plan that was executed.
+    pub plan: FeeSweepPlan,
+    /// The signatures of the submitted transactions, empty on a dry run.
+    pub signatures: Vec<Signature>,
+    /// Whether this was a dry run, in which case nothing was submitted.
+    pub dry_run: bool,
+}
+
+/// Gets the [`FeeSweepPlan`] to sweep the fees of every given market and pool whose accrued fees
+/// are at least `threshold`, in native units, to the treasury's associated token accounts.
+///
+/// Market fees are swept from the first pool node of the market's quote pool, pool fees from the pool's
+/// first pool node. Markets whose quote pool is not among the given pools and pools without pool nodes are skipped.
+pub fn get_fee_sweep_plan(
+    clearing: &Pubkey,
+    authority: &Pubkey,
+    treasury: &Pubkey,
+    perp_markets: &[MarketContext<PerpetualMarket>],
+    futures_markets: &[MarketContext<FuturesMarket>],
+    pools: &[PoolContext],
+    threshold: u64,
+) -> FeeSweepPlan {
+    let mut plan = FeeSweepPlan::default();
+    let markets = perp_markets
+        .iter()
+        .map(|m| (FeeSweepSource::PerpetualMarket, m.address, &m.state.inner))
+        .chain(
+            futures_markets
+                .iter()
+                .map(|m| (FeeSweepSource::FuturesMarket, m.address, &m.state.inner)),
+        );
+
+    for (source, market, inner) in markets {
+        if inner.protocol_fees == 0 || inner.protocol_fees < threshold {
+            continue;
+        }
+        let quote_pool_node = match pools
+            .iter()
+            .find(|p| p.address == inner.quote_pool)
+            .and_then(|p| p.pool_nodes.first())
+        {
+            Some(pn) => pn,
+            None => {
+                warn!(
+                    "Quote pool {} of market {} is not loaded, skipping.",
+                    inner.quote_pool, market
+                );
+                continue;
+            }
+        };
+        let destination = derive_token_address(treasury, &quote_pool_node.state.token_mint);
+        plan.ixs.push(sweep_market_fees(
+            clearing,
+            &market,
+            &quote_pool_node.address,
+            &quote_pool_node.state.token_vault,
+            &destination,
+            &quote_pool_node.state.vault_signer,
+            authority,
+        ));
+        plan.targets.push(FeeSweepTarget {
+            source,
+            address: market,
+            pool_node: quote_pool_node.address,
+            token_mint: quote_pool_node.state.token_mint,
+            destination,
+            amount: inner.protocol_fees,
+        });
+    }
+
+    for pool in pools.iter() {
+        if pool.state.protocol_fees == 0 || pool.state.protocol_fees < threshold {
+            continue;
+        }
+        let pool_node = match pool.pool_nodes.first() {
+            Some(pn) => pn,
+            None => {
+                warn!("Pool {} has no pool nodes loaded, skipping.", pool.address);
+                continue;
+            }
+        };
+        let destination = derive_token_address(treasury, &pool.state.token_mint);
+        plan.ixs.push(sweep_pool_fees(
+            &pool.address,
+            &pool_node.address,
+            &pool_node.state.token_vault,
+            &destination,
+            &pool_node.state.vault_signer,
+            authority,
+        ));
+        plan.targets.push(FeeSweepTarget {
+            source: FeeSweepSource::Pool,
+            address: pool.address,
+            pool_node: pool_node.address,
+            token_mint: pool.state.token_mint,
+            destination,
+            amount: pool.state.protocol_fees,
+        });
+    }
+
+    plan
+}
+
+/// Executes the given [`FeeSweepPlan`], batching the sweep instructions into as few transactions as possible.
+///
+/// On a dry run the plan's report and the expected fee of each transaction are logged and nothing is submitted.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong while submitting the transactions,
+/// in which case some of the transactions may have been submitted.
+pub async fn sweep_fees(
+    rpc_client: &RpcClient,
+    signer: &Keypair,
+    plan: FeeSweepPlan,
+    dry_run: bool,
+) -> Result<FeeSweepReport, ClientError> {
+    info!("{}", plan.get_report());
+    if dry_run {
+        info!(
+            "Expected fee per transaction: {}",
+            TransactionFeeEstimate::new(1, SWEEP_COMPUTE_UNITS, SWEEP_COMPUTE_UNIT_PRICE, None)
+        );
+    }
+    if dry_run || plan.ixs.is_empty() {
+        return Ok(FeeSweepReport {
+            plan,
+            signatures: Vec::new(),
+            dry_run,
+        });
+    }
+
+    let signatures = match send_transactions(
+        rpc_client,
+        plan.ixs.clone(),
+        signer,
+        true,
+        Some((SWEEP_COMPUTE_UNITS, SWEEP_COMPUTE_UNIT_PRICE)),
+        None,
+    )
+    .await
+    {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("Failed to sweep fees: {}", e.to_string());
+            return Err(e);
+        }
+    };
+
+    Ok(FeeSweepReport {
+        plan,
+        signatures,
+        dry_run,
+    })
+}
@@ -0,0 +1,85 @@
+use cypher_client::{Clearing, CypherAccount, FeeTier};
+use log::warn;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{AccountVolume, VolumeTracker};
+
+/// Gets the fee tier the given qualifying amount entitles to, according to the clearing's fee tiers.
+///
+/// This is the tier with the highest `balance` threshold that the qualifying amount meets,
+/// or the default [`FeeTier`] if it meets none.
+pub fn get_expected_fee_tier(clearing: &Clearing, qualifying_amount: u64) -> FeeTier {
+    clearing
+        .get_fee_tiers()
+        .into_iter()
+        .filter(|ft| ft.tier != 0 && ft.balance <= qualifying_amount)
+        .max_by_key(|ft| ft.balance)
+        .unwrap_or_default()
+}
+
+/// Gets the fees paid for the given volume at the given fee tier, in native quote units.
+///
+/// Returns the maker fees, the maker rebates and the taker fees.
+pub fn get_fees_for_volume(fee_tier: &FeeTier, volume: &AccountVolume) -> (u64, u64, u64) {
+    let bps = |amount: u64, bps: u8| (amount as u128 * bps as u128 / 10_000) as u64;
+    (
+        bps(volume.maker_volume, fee_tier.maker_bps),
+        bps(volume.maker_volume, fee_tier.rebate_bps),
+        bps(volume.taker_volume, fee_tier.taker_bps),
+    )
+}
+
+/// The result of verifying the fee tier assigned to an account against it's tracked volume.
+#[derive(Debug, Clone)]
+pub struct FeeTierVerification {
+    /// The master account.
+    pub account: Pubkey,
+    /// The rolling volume of the account.
+    pub volume: AccountVolume,
+    /// The fee tier assigned to the account by the clearing.
+    pub assigned: FeeTier,
+    /// The fee tier the account's rolling volume entitles it to.
+    pub expected: FeeTier,
+}
+
+impl FeeTierVerification {
+    /// Whether the assigned fee tier differs from the expected one.
+    pub fn is_discrepant(&self) -> bool {
+        self.assigned.tier != self.expected.tier
+    }
+
+    /// Whether the account is assigned a worse fee tier than the one it's volume entitles it to.
+    pub fn is_underqualified(&self) -> bool {
+        self.assigned.balance < self.expected.balance
+    }
+}
+
+/// Verifies the fee tier assigned to the given [`CypherAccount`] against it's rolling volume
+/// tracked by the given [`VolumeTracker`] at the given unix timestamp.
+pub fn verify_fee_tier(
+    clearing: &Clearing,
+    tracker: &VolumeTracker,
+    account: &Pubkey,
+    account_state: &CypherAccount,
+    now: u64,
+) -> FeeTierVerification {
+    let volume = tracker.get_volume(account, now);
+    let verification = FeeTierVerification {
+        account: *account,
+        volume,
+        assigned: clearing.get_fee_tier(account_state.fee_tier),
+        expected: get_expected_fee_tier(clearing, volume.total()),
+    };
+
+    if verification.is_discrepant() {
+        warn!(
+            "Fee tier discrepancy for account {} - Volume: {} - Assigned tier: {} - Expected tier: {}",
+            account,
+            volume.total(),
+            verification.assigned.tier,
+            verification.expected.tier
+        );
+    }
+
+    verification
+}
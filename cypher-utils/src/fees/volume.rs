@@ -0,0 +1,154 @@
+use agnostic_orderbook::state::event_queue::FillEvent;
+use cypher_client::aob::{zip_event_callback_infos, CallBackInfo};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+/// The number of seconds in a day.
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The default rolling window, in days, over which volume is tracked for fee tiers.
+pub const DEFAULT_VOLUME_WINDOW_DAYS: u64 = 30;
+
+/// The maker and taker volume of an account, in native quote units.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AccountVolume {
+    /// The volume of orders filled as maker.
+    pub maker_volume: u64,
+    /// The volume of orders filled as taker.
+    pub taker_volume: u64,
+}
+
+impl AccountVolume {
+    /// The total volume.
+    pub fn total(&self) -> u64 {
+        self.maker_volume.saturating_add(self.taker_volume)
+    }
+
+    fn add(&mut self, other: &AccountVolume) {
+        self.maker_volume = self.maker_volume.saturating_add(other.maker_volume);
+        self.taker_volume = self.taker_volume.saturating_add(other.taker_volume);
+    }
+}
+
+/// Tracks the rolling maker and taker volume of accounts from fills.
+///
+/// Volume is bucketed per UTC day, the rolling volume at a given timestamp is the sum
+/// of the buckets of the last `window_days` days, including the current one. Volume for
+/// a campaign or epoch can be queried with [`VolumeTracker::get_volume_between`] as long as
+/// the buckets have not been pruned yet.
+#[derive(Debug, Clone)]
+pub struct VolumeTracker {
+    /// The rolling window, in days.
+    pub window_days: u64,
+    volumes: HashMap<Pubkey, BTreeMap<u64, AccountVolume>>,
+}
+
+impl Default for VolumeTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_VOLUME_WINDOW_DAYS)
+    }
+}
+
+impl VolumeTracker {
+    /// Creates a new [`VolumeTracker`] with the given rolling window, in days.
+    pub fn new(window_days: u64) -> Self {
+        Self {
+            window_days: window_days.max(1),
+            volumes: HashMap::new(),
+        }
+    }
+
+    /// Records a fill of the given quote quantity, in native units, for the given account at the given unix timestamp.
+    pub fn record_fill(
+        &mut self,
+        account: &Pubkey,
+        is_maker: bool,
+        quote_quantity: u64,
+        timestamp: u64,
+    ) {
+        let bucket = self
+            .volumes
+            .entry(*account)
+            .or_default()
+            .entry(timestamp / SECONDS_PER_DAY)
+            .or_default();
+        if is_maker {
+            bucket.maker_volume = bucket.maker_volume.saturating_add(quote_quantity);
+        } else {
+            bucket.taker_volume = bucket.taker_volume.saturating_add(quote_quantity);
+        }
+    }
+
+    /// Records the fills in the given AOB events, attributing them to the maker and taker master accounts
+    /// found in the given callback infos.
+    ///
+    /// The callback infos must be interleaved as in the event queue, i.e. the event at index `i` has the maker's
+    /// callback info at index `2 * i` and the taker's callback info at index `2 * i + 1`.
+    ///
+    /// Callee must make sure that the same events are not recorded more than once,
+    /// e.g. by only recording the events returned by [`parse_aob_event_queue_since`](cypher_client::aob::parse_aob_event_queue_since).
+    pub fn record_aob_events(
+        &mut self,
+        events: &[FillEvent],
+        callback_infos: &[CallBackInfo],
+        timestamp: u64,
+    ) {
+        for (event, maker, taker) in zip_event_callback_infos(events, callback_infos) {
+            if event.maker_order_id == u128::default() || event.quote_size == 0 {
+                continue;
+            }
+            self.record_fill(&maker.user_account, true, event.quote_size, timestamp);
+            self.record_fill(&taker.user_account, false, event.quote_size, timestamp);
+        }
+    }
+
+    /// Gets the rolling volume of the given account at the given unix timestamp.
+    pub fn get_volume(&self, account: &Pubkey, now: u64) -> AccountVolume {
+        let today = now / SECONDS_PER_DAY;
+        let first_day = (today + 1).saturating_sub(self.window_days);
+        self.get_volume_between_days(account, first_day, today)
+    }
+
+    /// Gets the volume of the given account between the given unix timestamps, inclusive,
+    /// e.g. for the duration of a campaign or epoch.
+    ///
+    /// Volume is tracked per day, so the days containing the given timestamps are included in full.
+    pub fn get_volume_between(&self, account: &Pubkey, start: u64, end: u64) -> AccountVolume {
+        self.get_volume_between_days(account, start / SECONDS_PER_DAY, end / SECONDS_PER_DAY)
+    }
+
+    fn get_volume_between_days(
+        &self,
+        account: &Pubkey,
+        first_day: u64,
+        last_day: u64,
+    ) -> AccountVolume {
+        let mut volume = AccountVolume::default();
+        if first_day > last_day {
+            return volume;
+        }
+        if let Some(buckets) = self.volumes.get(account) {
+            for (_, v) in buckets.range(first_day..=last_day) {
+                volume.add(v);
+            }
+        }
+        volume
+    }
+
+    /// Gets the accounts with tracked volume.
+    pub fn get_accounts(&self) -> Vec<Pubkey> {
+        self.volumes.keys().copied().collect()
+    }
+
+    /// Prunes the volume buckets that fall outside of the rolling window at the given unix timestamp.
+    ///
+    /// Use `retain_days` to keep older buckets around, e.g. for the duration of a campaign.
+    pub fn prune(&mut self, now: u64, retain_days: u64) {
+        let today = now / SECONDS_PER_DAY;
+        let first_day = (today + 1).saturating_sub(self.window_days.max(retain_days));
+        self.volumes.retain(|_, buckets| {
+            *buckets = buckets.split_off(&first_day);
+            !buckets.is_empty()
+        });
+    }
+}
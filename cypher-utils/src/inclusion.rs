@@ -0,0 +1,296 @@
+use {
+    dashmap::DashMap,
+    fixed::types::I80F48,
+    solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient},
+    solana_sdk::signature::Signature,
+    std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// The default number of resolved submissions kept by the [`InclusionTracker`].
+pub const DEFAULT_INCLUSION_WINDOW: usize = 4096;
+
+/// The default number of slots after which a submission which has not landed is considered dropped.
+pub const DEFAULT_DROP_AFTER_SLOTS: u64 = 150;
+
+/// The maximum number of signatures per `getSignatureStatuses` request.
+const MAX_SIGNATURE_STATUSES: usize = 256;
+
+/// The outcome of a submitted transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionStatus {
+    /// The transaction has not landed yet.
+    Pending,
+    /// The transaction landed at the given slot.
+    Landed(u64),
+    /// The transaction did not land before its blockhash expired.
+    Dropped,
+}
+
+/// A record of a submitted transaction.
+#[derive(Debug, Clone, Copy)]
+pub struct SubmissionRecord {
+    /// The signature.
+    pub signature: Signature,
+    /// The priority fee paid, in micro lamports per compute unit.
+    pub priority_fee: u64,
+    /// The attempt number, zero for the first attempt and incremented on every retry.
+    pub attempt: u32,
+    /// The slot at which the transaction was submitted.
+    pub submitted_slot: u64,
+    /// The outcome.
+    pub status: SubmissionStatus,
+}
+
+impl SubmissionRecord {
+    /// Whether this was the first attempt at submitting the transaction.
+    pub fn is_first_attempt(&self) -> bool {
+        self.attempt == 0
+    }
+
+    /// Gets the number of slots it took the transaction to land, if it has.
+    pub fn get_slots_to_land(&self) -> Option<u64> {
+        match self.status {
+            SubmissionStatus::Landed(slot) => Some(slot.saturating_sub(self.submitted_slot)),
+            _ => None,
+        }
+    }
+}
+
+/// Inclusion statistics of the submissions within a priority fee level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FeeLevelStats {
+    /// The lowest priority fee of the level, inclusive.
+    pub min_fee: u64,
+    /// The highest priority fee of the level, exclusive, `u64::MAX` for the highest level.
+    pub max_fee: u64,
+    /// The number of resolved submissions.
+    pub submitted: u64,
+    /// The number of submissions which landed.
+    pub landed: u64,
+    /// The number of submissions which landed on their first attempt.
+    pub landed_first_attempt: u64,
+    /// The number of submissions which were dropped.
+    pub dropped: u64,
+    /// The total number of slots it took the landed submissions to land.
+    pub total_slots_to_land: u64,
+}
+
+impl FeeLevelStats {
+    /// Gets the ratio of resolved submissions which landed.
+    pub fn inclusion_rate(&self) -> I80F48 {
+        if self.submitted == 0 {
+            I80F48::ZERO
+        } else {
+            I80F48::from(self.landed) / I80F48::from(self.submitted)
+        }
+    }
+
+    /// Gets the average number of slots it took the landed submissions to land.
+    pub fn average_slots_to_land(&self) -> Option<I80F48> {
+        if self.landed == 0 {
+            None
+        } else {
+            Some(I80F48::from(self.total_slots_to_land) / I80F48::from(self.landed))
+        }
+    }
+}
+
+/// Tracks where submitted transactions land, how many attempts they took and the priority fee they paid,
+/// providing inclusion rates per priority fee level which can be used to tune the priority fee.
+///
+/// Outcomes are either reported by a confirmation service or polled with [`InclusionTracker::poll_statuses`],
+/// only the most recent resolved submissions are kept, according to the tracker's window.
+#[derive(Debug)]
+pub struct InclusionTracker {
+    window: usize,
+    pending: DashMap<Signature, SubmissionRecord>,
+    resolved: Mutex<VecDeque<SubmissionRecord>>,
+}
+
+impl Default for InclusionTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_INCLUSION_WINDOW)
+    }
+}
+
+impl InclusionTracker {
+    /// Creates a new [`InclusionTracker`] which keeps the given number of resolved submissions.
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            pending: DashMap::new(),
+            resolved: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Records the submission of a transaction.
+    pub fn record_submission(
+        &self,
+        signature: &Signature,
+        priority_fee: u64,
+        attempt: u32,
+        submitted_slot: u64,
+    ) {
+        self.pending.insert(
+            *signature,
+            SubmissionRecord {
+                signature: *signature,
+                priority_fee,
+                attempt,
+                submitted_slot,
+                status: SubmissionStatus::Pending,
+            },
+        );
+    }
+
+    /// Records that the transaction with the given signature landed at the given slot.
+    ///
+    /// Returns the resolved record, or `None` if the signature is not pending.
+    pub fn record_landed(
+        &self,
+        signature: &Signature,
+        landed_slot: u64,
+    ) -> Option<SubmissionRecord> {
+        self.resolve(signature, SubmissionStatus::Landed(landed_slot))
+    }
+
+    /// Records that the transaction with the given signature was dropped.
+    ///
+    /// Returns the resolved record, or `None` if the signature is not pending.
+    pub fn record_dropped(&self, signature: &Signature) -> Option<SubmissionRecord> {
+        self.resolve(signature, SubmissionStatus::Dropped)
+    }
+
+    /// Polls the signature statuses of the pending transactions, recording those which landed,
+    /// and records the transactions submitted more than `drop_after_slots` before the given slot as dropped.
+    ///
+    /// Returns the number of resolved transactions.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn poll_statuses(
+        &self,
+        rpc_client: &Arc<RpcClient>,
+        current_slot: u64,
+        drop_after_slots: u64,
+    ) -> Result<usize, ClientError> {
+        let pending = self
+            .pending
+            .iter()
+            .map(|e| (*e.key(), e.submitted_slot))
+            .collect::<Vec<(Signature, u64)>>();
+        let mut resolved = 0;
+
+        for chunk in pending.chunks(MAX_SIGNATURE_STATUSES) {
+            let signatures = chunk.iter().map(|(s, _)| *s).collect::<Vec<Signature>>();
+            let statuses = match rpc_client.get_signature_statuses(&signatures).await {
+                Ok(r) => r.value,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            for ((signature, submitted_slot), status) in chunk.iter().zip(statuses.iter()) {
+                let record = match status {
+                    Some(status) => self.record_landed(signature, status.slot),
+                    None if current_slot.saturating_sub(*submitted_slot) > drop_after_slots => {
+                        self.record_dropped(signature)
+                    }
+                    None => None,
+                };
+                if record.is_some() {
+                    resolved += 1;
+                }
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Gets the records of the pending submissions.
+    pub fn get_pending(&self) -> Vec<SubmissionRecord> {
+        self.pending.iter().map(|e| *e.value()).collect()
+    }
+
+    /// Gets the records of the resolved submissions, oldest first.
+    pub fn get_resolved(&self) -> Vec<SubmissionRecord> {
+        self.resolved.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Gets the inclusion statistics of the resolved submissions, bucketed by the given ascending priority fee levels.
+    ///
+    /// Each level spans from its fee up to the next level's fee, submissions with a fee lower than the first level
+    /// are counted in the first level.
+    pub fn get_stats_by_fee_level(&self, fee_levels: &[u64]) -> Vec<FeeLevelStats> {
+        let mut stats = fee_levels
+            .iter()
+            .enumerate()
+            .map(|(i, fee)| FeeLevelStats {
+                min_fee: *fee,
+                max_fee: fee_levels.get(i + 1).copied().unwrap_or(u64::MAX),
+                ..Default::default()
+            })
+            .collect::<Vec<FeeLevelStats>>();
+        if stats.is_empty() {
+            return stats;
+        }
+
+        for record in self.resolved.lock().unwrap().iter() {
+            let idx = fee_levels
+                .iter()
+                .rposition(|fee| record.priority_fee >= *fee)
+                .unwrap_or(0);
+            let level = &mut stats[idx];
+            level.submitted += 1;
+            match record.status {
+                SubmissionStatus::Landed(_) => {
+                    level.landed += 1;
+                    if record.is_first_attempt() {
+                        level.landed_first_attempt += 1;
+                    }
+                    level.total_slots_to_land += record.get_slots_to_land().unwrap_or_default();
+                }
+                SubmissionStatus::Dropped => level.dropped += 1,
+                SubmissionStatus::Pending => (),
+            }
+        }
+
+        stats
+    }
+
+    /// Suggests the lowest of the given priority fee levels whose inclusion rate reaches the target inclusion rate,
+    /// only considering levels with at least `min_samples` resolved submissions.
+    ///
+    /// Returns `None` if no level reaches the target, in which case the fee should be raised.
+    pub fn suggest_priority_fee(
+        &self,
+        fee_levels: &[u64],
+        target_inclusion_rate: I80F48,
+        min_samples: u64,
+    ) -> Option<u64> {
+        self.get_stats_by_fee_level(fee_levels)
+            .iter()
+            .find(|s| s.submitted >= min_samples && s.inclusion_rate() >= target_inclusion_rate)
+            .map(|s| s.min_fee)
+    }
+
+    /// Clears all pending and resolved submissions.
+    pub fn reset(&self) {
+        self.pending.clear();
+        self.resolved.lock().unwrap().clear();
+    }
+
+    fn resolve(&self, signature: &Signature, status: SubmissionStatus) -> Option<SubmissionRecord> {
+        let (_, mut record) = self.pending.remove(signature)?;
+        record.status = status;
+        let mut resolved = self.resolved.lock().unwrap();
+        if resolved.len() == self.window {
+            resolved.pop_front();
+        }
+        resolved.push_back(record);
+        Some(record)
+    }
+}
@@ -0,0 +1,295 @@
+use {
+    dashmap::DashMap,
+    log::info,
+    solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient},
+    solana_sdk::signature::Signature,
+    std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+};
+
+/// The default number of samples kept per metric by the [`LatencyTracker`].
+pub const DEFAULT_LATENCY_WINDOW: usize = 1024;
+
+/// The maximum number of signatures per `getSignatureStatuses` request.
+const MAX_SIGNATURE_STATUSES: usize = 256;
+
+/// A trace of a single pass through the hot path, from an account update being received to the
+/// resulting order being submitted.
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyTrace {
+    /// The slot of the account update which started the trace.
+    pub slot_received: u64,
+    /// The instant at which the account update was received.
+    pub received_at: Instant,
+    /// The instant at which local processing finished, if it has.
+    pub processed_at: Option<Instant>,
+}
+
+impl LatencyTrace {
+    /// Starts a new trace for an account update received at the given slot.
+    pub fn new(slot_received: u64) -> Self {
+        Self {
+            slot_received,
+            received_at: Instant::now(),
+            processed_at: None,
+        }
+    }
+
+    /// Marks the end of local processing, e.g. once the order instructions are built.
+    pub fn mark_processed(&mut self) {
+        self.processed_at = Some(Instant::now());
+    }
+
+    /// Gets the local processing time, if processing has finished.
+    pub fn get_processing_time(&self) -> Option<Duration> {
+        self.processed_at.map(|p| p - self.received_at)
+    }
+}
+
+/// Percentile statistics of a latency metric.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PercentileStats {
+    /// The number of samples.
+    pub count: usize,
+    /// The minimum value.
+    pub min: u64,
+    /// The mean value.
+    pub mean: u64,
+    /// The median value.
+    pub p50: u64,
+    /// The 90th percentile value.
+    pub p90: u64,
+    /// The 99th percentile value.
+    pub p99: u64,
+    /// The maximum value.
+    pub max: u64,
+}
+
+impl PercentileStats {
+    /// Computes the statistics of the given samples.
+    pub fn new(samples: &[u64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let percentile = |p: usize| sorted[((sorted.len() - 1) * p) / 100];
+        Self {
+            count: sorted.len(),
+            min: sorted[0],
+            mean: (sorted.iter().map(|s| *s as u128).sum::<u128>() / sorted.len() as u128) as u64,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+impl std::fmt::Display for PercentileStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "n: {} - min: {} - mean: {} - p50: {} - p90: {} - p99: {} - max: {}",
+            self.count, self.min, self.mean, self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+/// A report of the latencies measured by the [`LatencyTracker`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyReport {
+    /// The time from an account update being received to local processing finishing, in microseconds.
+    pub processing_us: PercentileStats,
+    /// The time from local processing finishing to the transaction being submitted, in microseconds.
+    pub submission_us: PercentileStats,
+    /// The time from an account update being received to the transaction being submitted, in microseconds.
+    pub total_us: PercentileStats,
+    /// The number of slots between the account update being received and the transaction landing.
+    pub slots_to_land: PercentileStats,
+    /// The number of submitted transactions which have not landed yet.
+    pub pending: usize,
+}
+
+impl std::fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Processing (us): {}", self.processing_us)?;
+        writeln!(f, "Submission (us): {}", self.submission_us)?;
+        writeln!(f, "Total (us): {}", self.total_us)?;
+        writeln!(f, "Slots to land: {}", self.slots_to_land)?;
+        write!(f, "Pending: {}", self.pending)
+    }
+}
+
+#[derive(Debug)]
+struct Samples {
+    window: usize,
+    processing_us: VecDeque<u64>,
+    submission_us: VecDeque<u64>,
+    total_us: VecDeque<u64>,
+    slots_to_land: VecDeque<u64>,
+}
+
+impl Samples {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            processing_us: VecDeque::with_capacity(window),
+            submission_us: VecDeque::with_capacity(window),
+            total_us: VecDeque::with_capacity(window),
+            slots_to_land: VecDeque::with_capacity(window),
+        }
+    }
+
+    fn push(window: usize, samples: &mut VecDeque<u64>, value: u64) {
+        if samples.len() == window {
+            samples.pop_front();
+        }
+        samples.push_back(value);
+    }
+}
+
+/// Measures the latency of the hot path, from an account update being received to the resulting transaction landing.
+///
+/// A [`LatencyTrace`] is started when an account update is received and handed to the tracker once the transaction is submitted,
+/// the landed slot is then either reported by a confirmation service via [`LatencyTracker::record_landed`] or polled with
+/// [`LatencyTracker::poll_landed`].
+///
+/// Only the most recent samples of each metric are kept, according to the tracker's window.
+#[derive(Debug)]
+pub struct LatencyTracker {
+    samples: Mutex<Samples>,
+    pending: DashMap<Signature, u64>,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_WINDOW)
+    }
+}
+
+impl LatencyTracker {
+    /// Creates a new [`LatencyTracker`] which keeps the given number of samples per metric.
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: Mutex::new(Samples::new(window.max(1))),
+            pending: DashMap::new(),
+        }
+    }
+
+    /// Records the submission of the transaction with the given signature at the end of the given trace.
+    ///
+    /// If the trace was not marked as processed, the submission instant is used as the end of processing.
+    pub fn record_submission(&self, trace: &LatencyTrace, signature: &Signature) {
+        let submitted_at = Instant::now();
+        let processed_at = trace.processed_at.unwrap_or(submitted_at);
+        let processing = processed_at - trace.received_at;
+        let submission = submitted_at - processed_at;
+        let total = submitted_at - trace.received_at;
+
+        {
+            let mut samples = self.samples.lock().unwrap();
+            let window = samples.window;
+            Samples::push(
+                window,
+                &mut samples.processing_us,
+                processing.as_micros() as u64,
+            );
+            Samples::push(
+                window,
+                &mut samples.submission_us,
+                submission.as_micros() as u64,
+            );
+            Samples::push(window, &mut samples.total_us, total.as_micros() as u64);
+        }
+        self.pending.insert(*signature, trace.slot_received);
+    }
+
+    /// Records the slot at which the transaction with the given signature landed.
+    ///
+    /// Returns the number of slots between the account update being received and the transaction landing,
+    /// or `None` if the signature is not pending.
+    pub fn record_landed(&self, signature: &Signature, landed_slot: u64) -> Option<u64> {
+        let (_, slot_received) = self.pending.remove(signature)?;
+        let slots_to_land = landed_slot.saturating_sub(slot_received);
+        let mut samples = self.samples.lock().unwrap();
+        let window = samples.window;
+        Samples::push(window, &mut samples.slots_to_land, slots_to_land);
+        Some(slots_to_land)
+    }
+
+    /// Stops tracking the transaction with the given signature, e.g. after it expired without landing.
+    pub fn record_dropped(&self, signature: &Signature) {
+        self.pending.remove(signature);
+    }
+
+    /// Polls the signature statuses of the pending transactions and records the landed slot of those which have landed.
+    ///
+    /// Returns the number of transactions which landed.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn poll_landed(&self, rpc_client: &Arc<RpcClient>) -> Result<usize, ClientError> {
+        let signatures = self
+            .pending
+            .iter()
+            .map(|e| *e.key())
+            .collect::<Vec<Signature>>();
+        let mut landed = 0;
+
+        for chunk in signatures.chunks(MAX_SIGNATURE_STATUSES) {
+            let statuses = match rpc_client.get_signature_statuses(chunk).await {
+                Ok(r) => r.value,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            for (signature, status) in chunk.iter().zip(statuses.iter()) {
+                if let Some(status) = status {
+                    if self.record_landed(signature, status.slot).is_some() {
+                        landed += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(landed)
+    }
+
+    /// Gets the number of submitted transactions which have not landed yet.
+    pub fn get_pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Gets a report of the measured latencies.
+    pub fn get_report(&self) -> LatencyReport {
+        let samples = self.samples.lock().unwrap();
+        LatencyReport {
+            processing_us: PercentileStats::new(&to_vec(&samples.processing_us)),
+            submission_us: PercentileStats::new(&to_vec(&samples.submission_us)),
+            total_us: PercentileStats::new(&to_vec(&samples.total_us)),
+            slots_to_land: PercentileStats::new(&to_vec(&samples.slots_to_land)),
+            pending: self.pending.len(),
+        }
+    }
+
+    /// Logs a report of the measured latencies.
+    pub fn log_report(&self) {
+        info!("Latency report:\n{}", self.get_report());
+    }
+
+    /// Clears all samples and pending transactions.
+    pub fn reset(&self) {
+        let mut samples = self.samples.lock().unwrap();
+        *samples = Samples::new(samples.window);
+        self.pending.clear();
+    }
+}
+
+fn to_vec(samples: &VecDeque<u64>) -> Vec<u64> {
+    samples.iter().copied().collect()
+}
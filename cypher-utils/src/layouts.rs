@@ -0,0 +1,145 @@
+use anchor_lang::{Discriminator, ZeroCopy};
+use cypher_client::{
+    CacheAccount, Clearing, CypherAccount, CypherSubAccount, FuturesMarket, OracleProducts,
+    OracleStub, OrdersAccount, PerpetualMarket, Pool, PoolNode, PriceHistory, Whitelist,
+};
+use solana_client::rpc_filter::RpcFilterType;
+use std::fmt::Display;
+
+/// The length of the Anchor discriminator at the start of every program account.
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Gets the size of the account data of a zero copy account of type `T`, including the discriminator.
+pub const fn get_account_size<T>() -> usize {
+    DISCRIMINATOR_LEN + std::mem::size_of::<T>()
+}
+
+/// Gets the filter matching the account data size of a zero copy account of type `T`.
+pub fn get_data_size_filter<T: ZeroCopy>() -> RpcFilterType {
+    RpcFilterType::DataSize(get_account_size::<T>() as u64)
+}
+
+/// The layout of an account type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountLayout {
+    /// The name of the account type.
+    pub name: &'static str,
+    /// The size of the account data, including the discriminator.
+    ///
+    /// This is `None` for Borsh accounts, which do not have a fixed size.
+    pub size: Option<usize>,
+    /// The Anchor discriminator.
+    pub discriminator: [u8; 8],
+}
+
+impl AccountLayout {
+    /// Gets the [`AccountLayout`] of the given zero copy account type.
+    pub fn of<T: ZeroCopy + Discriminator>(name: &'static str) -> Self {
+        Self {
+            name,
+            size: Some(get_account_size::<T>()),
+            discriminator: T::DISCRIMINATOR,
+        }
+    }
+
+    /// Gets the [`AccountLayout`] of the given Borsh account type, which is only identified by it's discriminator.
+    pub fn of_borsh<T: Discriminator>(name: &'static str) -> Self {
+        Self {
+            name,
+            size: None,
+            discriminator: T::DISCRIMINATOR,
+        }
+    }
+
+    /// Whether the given account data matches this layout's discriminator and size, if it has one.
+    pub fn matches(&self, account_data: &[u8]) -> bool {
+        if account_data.len() < DISCRIMINATOR_LEN
+            || account_data[..DISCRIMINATOR_LEN] != self.discriminator
+        {
+            return false;
+        }
+        match self.size {
+            Some(size) => account_data.len() == size,
+            None => true,
+        }
+    }
+
+    /// Gets the filter matching this layout's account data size.
+    ///
+    /// Returns `None` for Borsh accounts, which do not have a fixed size.
+    pub fn get_data_size_filter(&self) -> Option<RpcFilterType> {
+        self.size.map(|size| RpcFilterType::DataSize(size as u64))
+    }
+}
+
+macro_rules! account_layouts {
+    (zero_copy: [$($account:ident),* $(,)?], borsh: [$($borsh_account:ident),* $(,)?] $(,)?) => {
+        /// The account types of the cypher program.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum CypherAccountType {
+            $($account,)*
+            $($borsh_account,)*
+        }
+
+        impl CypherAccountType {
+            /// Every account type of the cypher program.
+            pub const ALL: &'static [CypherAccountType] = &[
+                $(CypherAccountType::$account,)*
+                $(CypherAccountType::$borsh_account,)*
+            ];
+
+            /// Gets the [`AccountLayout`] of this account type.
+            pub fn layout(&self) -> AccountLayout {
+                match self {
+                    $(CypherAccountType::$account => AccountLayout::of::<$account>(stringify!($account)),)*
+                    $(CypherAccountType::$borsh_account => {
+                        AccountLayout::of_borsh::<$borsh_account>(stringify!($borsh_account))
+                    })*
+                }
+            }
+        }
+    };
+}
+
+account_layouts!(
+    zero_copy: [
+        CacheAccount,
+        Clearing,
+        CypherAccount,
+        CypherSubAccount,
+        FuturesMarket,
+        OrdersAccount,
+        PerpetualMarket,
+        Pool,
+        PoolNode,
+        PriceHistory,
+        Whitelist,
+    ],
+    borsh: [OracleProducts, OracleStub],
+);
+
+impl CypherAccountType {
+    /// Identifies the account type of the given account data by it's discriminator and size, if it has one.
+    ///
+    /// Returns `None` if the data does not match any known layout, e.g. after an incompatible program upgrade.
+    pub fn from_account_data(account_data: &[u8]) -> Option<Self> {
+        if account_data.len() < DISCRIMINATOR_LEN {
+            return None;
+        }
+        Self::ALL
+            .iter()
+            .find(|t| t.layout().matches(account_data))
+            .copied()
+    }
+
+    /// Gets the table of every account type's [`AccountLayout`].
+    pub fn get_layouts() -> Vec<AccountLayout> {
+        Self::ALL.iter().map(|t| t.layout()).collect()
+    }
+}
+
+impl Display for CypherAccountType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.layout().name)
+    }
+}
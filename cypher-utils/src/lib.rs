@@ -1,7 +1,34 @@
+pub mod accounting;
 pub mod accounts_cache;
+pub mod analytics;
+pub mod broadcast;
+#[cfg(feature = "services")]
+pub mod config;
 pub mod constants;
 pub mod contexts;
+pub mod cranks;
+pub mod display;
+pub mod fees;
+pub mod inclusion;
+pub mod latency;
+pub mod layouts;
 pub mod logging;
+pub mod migration;
+#[cfg(feature = "services")]
+pub mod oms;
+#[cfg(feature = "streaming")]
+pub mod retry;
+pub mod risk;
+pub mod scanners;
+#[cfg(feature = "streaming")]
 pub mod services;
+pub mod simulation;
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod transaction_builder;
+pub mod transport;
 pub mod utils;
+pub mod version;
+pub mod whitelisting;
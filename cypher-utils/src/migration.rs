@@ -0,0 +1,491 @@
+use cypher_client::{
+    cache_account,
+    index::IndexError,
+    instructions::{
+        close_account, close_sub_account, create_sub_account, create_whitelisted_account,
+        deposit_funds, withdraw_funds,
+    },
+    utils::{
+        derive_account_address, derive_pool_node_vault_address,
+        derive_pool_node_vault_signer_address, derive_sub_account_address, derive_token_address,
+        derive_whitelist_address,
+    },
+};
+use fixed::types::I80F48;
+use log::{info, warn};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use std::{fmt::Display, fs, path::Path, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+use crate::{
+    contexts::{CacheContext, PoolContext, UserContext},
+    risk::get_max_withdrawable,
+    utils::send_transactions,
+};
+
+/// The compute units requested for each migration transaction.
+const MIGRATION_COMPUTE_UNITS: u32 = 400_000;
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Sub account {0} has an open derivative position or open orders.")]
+    OpenDerivativePosition(Pubkey),
+    #[error("Sub account {0} has an outstanding borrow of {1}.")]
+    OutstandingBorrow(Pubkey, Pubkey),
+    #[error("Could not find pool for token mint {0}.")]
+    PoolNotFound(Pubkey),
+    #[error("Insufficient liquidity to withdraw {1} of token mint {0}.")]
+    InsufficientLiquidity(Pubkey, u64),
+    #[error("Sub account {0} has a position with an invalid price cache: {1}")]
+    InvalidPriceCache(Pubkey, IndexError),
+    #[error("Invalid migration checkpoint: {0}")]
+    InvalidCheckpoint(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+}
+
+/// The steps of a cross-clearing migration, in the order they are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MigrationStep {
+    /// Withdraw all assets from the source account.
+    Withdraw,
+    /// Close the source sub accounts and master account.
+    Close,
+    /// Create the account and sub account in the target clearing.
+    Create,
+    /// Deposit the withdrawn assets into the target account.
+    Deposit,
+    /// The migration is complete.
+    Done,
+}
+
+impl Display for MigrationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationStep::Withdraw => write!(f, "withdraw"),
+            MigrationStep::Close => write!(f, "close"),
+            MigrationStep::Create => write!(f, "create"),
+            MigrationStep::Deposit => write!(f, "deposit"),
+            MigrationStep::Done => write!(f, "done"),
+        }
+    }
+}
+
+impl FromStr for MigrationStep {
+    type Err = MigrationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "withdraw" => Ok(MigrationStep::Withdraw),
+            "close" => Ok(MigrationStep::Close),
+            "create" => Ok(MigrationStep::Create),
+            "deposit" => Ok(MigrationStep::Deposit),
+            "done" => Ok(MigrationStep::Done),
+            _ => Err(MigrationError::InvalidCheckpoint(format!(
+                "unknown step {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// The progress of a cross-clearing migration, which allows resuming it after a partial failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationCheckpoint {
+    /// The target clearing.
+    pub target_clearing: Pubkey,
+    /// The account number of the master account, which is reused in the target clearing.
+    pub account_number: u8,
+    /// The next step to execute.
+    pub step: MigrationStep,
+    /// The token mints and amounts withdrawn from the source account, in native units.
+    pub withdrawn: Vec<(Pubkey, u64)>,
+    /// The token mints already deposited into the target account.
+    pub deposited: Vec<Pubkey>,
+}
+
+impl MigrationCheckpoint {
+    /// Creates a new [`MigrationCheckpoint`] for a migration that has not started.
+    pub fn new(target_clearing: &Pubkey, account_number: u8) -> Self {
+        Self {
+            target_clearing: *target_clearing,
+            account_number,
+            step: MigrationStep::Withdraw,
+            withdrawn: Vec::new(),
+            deposited: Vec::new(),
+        }
+    }
+
+    /// Loads the [`MigrationCheckpoint`] from the given file.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be read or is not a valid checkpoint.
+    pub fn load(path: &Path) -> Result<Self, MigrationError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(MigrationError::Io(e));
+            }
+        };
+        contents.parse::<Self>()
+    }
+
+    /// Saves the [`MigrationCheckpoint`] to the given file.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be written.
+    pub fn save(&self, path: &Path) -> Result<(), MigrationError> {
+        match fs::write(path, self.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(MigrationError::Io(e)),
+        }
+    }
+
+    fn record_withdrawal(&mut self, token_mint: &Pubkey, amount: u64) {
+        match self.withdrawn.iter_mut().find(|(m, _)| m == token_mint) {
+            Some((_, a)) => *a += amount,
+            None => self.withdrawn.push((*token_mint, amount)),
+        }
+    }
+}
+
+impl Display for MigrationCheckpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "target_clearing={}", self.target_clearing)?;
+        writeln!(f, "account_number={}", self.account_number)?;
+        writeln!(f, "step={}", self.step)?;
+        for (mint, amount) in self.withdrawn.iter() {
+            writeln!(f, "withdrawn={}:{}", mint, amount)?;
+        }
+        for mint in self.deposited.iter() {
+            writeln!(f, "deposited={}", mint)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MigrationCheckpoint {
+    type Err = MigrationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |line: &str| MigrationError::InvalidCheckpoint(line.to_string());
+        let parse_pubkey = |v: &str| Pubkey::from_str(v).map_err(|_| invalid(v));
+
+        let mut target_clearing = None;
+        let mut account_number = None;
+        let mut step = None;
+        let mut withdrawn = Vec::new();
+        let mut deposited = Vec::new();
+
+        for line in s.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+            let (key, value) = match line.split_once('=') {
+                Some(kv) => kv,
+                None => return Err(invalid(line)),
+            };
+            match key {
+                "target_clearing" => target_clearing = Some(parse_pubkey(value)?),
+                "account_number" => {
+                    account_number = Some(value.parse::<u8>().map_err(|_| invalid(line))?)
+                }
+                "step" => step = Some(value.parse::<MigrationStep>()?),
+                "withdrawn" => {
+                    let (mint, amount) = value.split_once(':').ok_or_else(|| invalid(line))?;
+                    withdrawn.push((
+                        parse_pubkey(mint)?,
+                        amount.parse::<u64>().map_err(|_| invalid(line))?,
+                    ));
+                }
+                "deposited" => deposited.push(parse_pubkey(value)?),
+                _ => return Err(invalid(line)),
+            }
+        }
+
+        Ok(Self {
+            target_clearing: target_clearing.ok_or_else(|| invalid("missing target_clearing"))?,
+            account_number: account_number.ok_or_else(|| invalid("missing account_number"))?,
+            step: step.ok_or_else(|| invalid("missing step"))?,
+            withdrawn,
+            deposited,
+        })
+    }
+}
+
+/// Migrates the given [`UserContext`] to another clearing, e.g. from the public clearing to a private clearing.
+///
+/// The migration withdraws all assets, closes the sub accounts and the master account, creates a whitelisted
+/// account and sub account in the target clearing and deposits the withdrawn assets into it. The user must
+/// already be whitelisted in the target clearing and all derivative positions and borrows must be closed.
+///
+/// Progress is recorded in the given [`MigrationCheckpoint`] after each step, and saved to the given path
+/// if one is provided, so that the migration can be resumed after a partial failure by calling this function
+/// again with the same checkpoint.
+///
+/// ### Assumptions
+///
+/// - The user has an Associated Token Account for every token mint held in the account.
+///
+/// ### Errors
+///
+/// This function will return an error if the account can not be migrated, something goes wrong during
+/// the RPC requests or the checkpoint can not be saved.
+pub async fn migrate_account(
+    rpc_client: &Arc<RpcClient>,
+    signer: &Keypair,
+    user_ctx: &mut UserContext,
+    cache_ctx: &CacheContext,
+    pools: &[PoolContext],
+    checkpoint: &mut MigrationCheckpoint,
+    checkpoint_path: Option<&Path>,
+) -> Result<Vec<Signature>, MigrationError> {
+    let authority = signer.pubkey();
+    let mut signatures = Vec::new();
+    let save = |checkpoint: &MigrationCheckpoint| match checkpoint_path {
+        Some(p) => checkpoint.save(p),
+        None => Ok(()),
+    };
+
+    if checkpoint.step == MigrationStep::Withdraw {
+        for sub_account_ctx in user_ctx.sub_account_ctxs.iter() {
+            for position in sub_account_ctx.state.iter_position_slots() {
+                if position.derivative.market != Pubkey::default()
+                    && (position.derivative.base_position() != I80F48::ZERO
+                        || position.derivative.open_orders_cache.coin_total != 0
+                        || position.derivative.open_orders_cache.pc_total != 0)
+                {
+                    return Err(MigrationError::OpenDerivativePosition(
+                        sub_account_ctx.address,
+                    ));
+                }
+                if position.spot.token_mint == Pubkey::default() {
+                    continue;
+                }
+                let token_mint = position.spot.token_mint;
+                let cache = match cache_ctx
+                    .state
+                    .try_get_price_cache(position.spot.cache_index as usize)
+                {
+                    Ok(c) => c,
+                    Err(e) => {
+                        return Err(MigrationError::InvalidPriceCache(
+                            sub_account_ctx.address,
+                            e,
+                        ));
+                    }
+                };
+                let total_position = position.spot.total_position(cache);
+                if total_position.is_negative() {
+                    return Err(MigrationError::OutstandingBorrow(
+                        sub_account_ctx.address,
+                        token_mint,
+                    ));
+                }
+                let amount = total_position.to_num::<u64>();
+                if amount == 0 {
+                    continue;
+                }
+                let pool_ctx = match pools.iter().find(|p| p.state.token_mint == token_mint) {
+                    Some(p) => p,
+                    None => return Err(MigrationError::PoolNotFound(token_mint)),
+                };
+                let pool_node = match get_max_withdrawable(pool_ctx) {
+                    Some(l) if l.withdrawable >= total_position => l.pool_node,
+                    _ => return Err(MigrationError::InsufficientLiquidity(token_mint, amount)),
+                };
+                let ix = withdraw_funds(
+                    &user_ctx.account_ctx.state.clearing,
+                    &cache_account::id(),
+                    &user_ctx.account_ctx.address,
+                    &sub_account_ctx.address,
+                    &pool_ctx.address,
+                    &pool_node,
+                    &derive_token_address(&authority, &token_mint),
+                    &derive_pool_node_vault_address(&pool_node).0,
+                    &derive_pool_node_vault_signer_address(&pool_node).0,
+                    &token_mint,
+                    &authority,
+                    amount,
+                    Some(true), // zero out any dust left in the position
+                );
+                match send_transactions(
+                    rpc_client,
+                    vec![ix],
+                    signer,
+                    true,
+                    Some((MIGRATION_COMPUTE_UNITS, 1)),
+                    None,
+                )
+                .await
+                {
+                    Ok(s) => signatures.extend(s),
+                    Err(e) => {
+                        return Err(MigrationError::ClientError(e));
+                    }
+                }
+                info!(
+                    "Withdrew {} of {} from sub account {}.",
+                    amount, token_mint, sub_account_ctx.address
+                );
+                checkpoint.record_withdrawal(&token_mint, amount);
+                save(checkpoint)?;
+            }
+        }
+        checkpoint.step = MigrationStep::Close;
+        save(checkpoint)?;
+    }
+
+    if checkpoint.step == MigrationStep::Close {
+        let mut ixs = user_ctx
+            .sub_account_ctxs
+            .iter()
+            .map(|sa| {
+                close_sub_account(
+                    &user_ctx.account_ctx.address,
+                    &sa.address,
+                    &authority,
+                    &authority,
+                )
+            })
+            .collect::<Vec<_>>();
+        ixs.push(close_account(
+            &user_ctx.account_ctx.address,
+            &authority,
+            &authority,
+        ));
+        match send_transactions(
+            rpc_client,
+            ixs,
+            signer,
+            true,
+            Some((MIGRATION_COMPUTE_UNITS, 1)),
+            None,
+        )
+        .await
+        {
+            Ok(s) => signatures.extend(s),
+            Err(e) => {
+                return Err(MigrationError::ClientError(e));
+            }
+        }
+        info!("Closed account {}.", user_ctx.account_ctx.address);
+        checkpoint.step = MigrationStep::Create;
+        save(checkpoint)?;
+    }
+
+    let (account, account_bump) = derive_account_address(&authority, checkpoint.account_number);
+    let (sub_account, sub_account_bump) = derive_sub_account_address(&account, 0);
+
+    if checkpoint.step == MigrationStep::Create {
+        let (whitelist, _) = derive_whitelist_address(&authority);
+        let ixs = vec![
+            create_whitelisted_account(
+                &checkpoint.target_clearing,
+                &whitelist,
+                &authority,
+                &authority,
+                &account,
+                account_bump,
+                checkpoint.account_number,
+            ),
+            create_sub_account(
+                &authority,
+                &authority,
+                &account,
+                &sub_account,
+                sub_account_bump,
+                0,
+                [0; 32],
+            ),
+        ];
+        match send_transactions(
+            rpc_client,
+            ixs,
+            signer,
+            true,
+            Some((MIGRATION_COMPUTE_UNITS, 1)),
+            None,
+        )
+        .await
+        {
+            Ok(s) => signatures.extend(s),
+            Err(e) => {
+                return Err(MigrationError::ClientError(e));
+            }
+        }
+        info!(
+            "Created account {} in clearing {}.",
+            account, checkpoint.target_clearing
+        );
+        checkpoint.step = MigrationStep::Deposit;
+        save(checkpoint)?;
+    }
+
+    if checkpoint.step == MigrationStep::Deposit {
+        for (token_mint, amount) in checkpoint.withdrawn.clone().iter() {
+            if checkpoint.deposited.contains(token_mint) {
+                continue;
+            }
+            let pool_ctx = match pools.iter().find(|p| p.state.token_mint == *token_mint) {
+                Some(p) => p,
+                None => return Err(MigrationError::PoolNotFound(*token_mint)),
+            };
+            let pool_node = match pool_ctx.pool_nodes.first() {
+                Some(pn) => pn.address,
+                None => return Err(MigrationError::PoolNotFound(*token_mint)),
+            };
+            let ix = deposit_funds(
+                &checkpoint.target_clearing,
+                &cache_account::id(),
+                &account,
+                &sub_account,
+                &pool_ctx.address,
+                &pool_node,
+                &derive_token_address(&authority, token_mint),
+                &derive_pool_node_vault_address(&pool_node).0,
+                token_mint,
+                &authority,
+                *amount,
+            );
+            match send_transactions(
+                rpc_client,
+                vec![ix],
+                signer,
+                true,
+                Some((MIGRATION_COMPUTE_UNITS, 1)),
+                None,
+            )
+            .await
+            {
+                Ok(s) => signatures.extend(s),
+                Err(e) => {
+                    return Err(MigrationError::ClientError(e));
+                }
+            }
+            info!(
+                "Deposited {} of {} into sub account {}.",
+                amount, token_mint, sub_account
+            );
+            checkpoint.deposited.push(*token_mint);
+            save(checkpoint)?;
+        }
+        checkpoint.step = MigrationStep::Done;
+        save(checkpoint)?;
+    }
+
+    match UserContext::load(rpc_client, &authority, Some(checkpoint.account_number)).await {
+        Ok(ctx) => *user_ctx = ctx,
+        Err(e) => {
+            warn!("Could not reload migrated account: {}", e.to_string());
+        }
+    }
+
+    Ok(signatures)
+}
@@ -0,0 +1,273 @@
+use cypher_client::{
+    cache_account,
+    instructions::{cancel_futures_orders, cancel_perp_orders},
+    CancelOrderArgs, CypherAccount, MarketType, OpenOrder, OrdersAccount, Side,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::collections::BTreeMap;
+
+/// The maximum number of orders cancelled by a single `cancel_perp_orders` or `cancel_futures_orders` instruction.
+///
+/// This keeps a single instruction, along with a compute budget instruction, within the transaction size limit.
+pub const MAX_CANCELS_PER_INSTRUCTION: usize = 16;
+
+/// A prefix matched against the most significant bits of an order's client order id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIdPrefix {
+    /// The prefix, right aligned.
+    pub prefix: u64,
+    /// The number of most significant bits the prefix spans.
+    pub bits: u32,
+}
+
+impl ClientIdPrefix {
+    /// Creates a new [`ClientIdPrefix`] spanning the given number of most significant bits.
+    pub fn new(prefix: u64, bits: u32) -> Self {
+        Self {
+            prefix,
+            bits: bits.min(u64::BITS),
+        }
+    }
+
+    /// Whether the given client order id starts with this prefix.
+    pub fn matches(&self, client_order_id: u64) -> bool {
+        if self.bits == 0 {
+            return true;
+        }
+        client_order_id >> (u64::BITS - self.bits) == self.prefix
+    }
+}
+
+/// A filter used to select the open orders that should be cancelled.
+///
+/// Every criteria that is set must match for an order to be selected, an empty filter matches all orders.
+#[derive(Debug, Default, Clone)]
+pub struct CancelOrderFilter {
+    /// Only select orders on this side of the book.
+    pub side: Option<Side>,
+    /// Only select orders priced at or above this price, in native lots.
+    pub min_price: Option<u64>,
+    /// Only select orders priced at or below this price, in native lots.
+    pub max_price: Option<u64>,
+    /// Only select orders placed at or before this unix timestamp.
+    pub placed_before: Option<u64>,
+    /// Only select orders whose client order id starts with this prefix.
+    pub client_id_prefix: Option<ClientIdPrefix>,
+    /// Only select orders of the sub account at this index of the master account.
+    pub sub_account_idx: Option<u8>,
+}
+
+impl CancelOrderFilter {
+    /// Creates a filter that selects all asks priced at or above the given price.
+    pub fn asks_above(price: u64) -> Self {
+        Self {
+            side: Some(Side::Ask),
+            min_price: Some(price),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a filter that selects all bids priced at or below the given price.
+    pub fn bids_below(price: u64) -> Self {
+        Self {
+            side: Some(Side::Bid),
+            max_price: Some(price),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a filter that selects all orders older than the given age, in seconds, at the given unix timestamp.
+    pub fn older_than(max_age: u64, now: u64) -> Self {
+        Self {
+            placed_before: Some(now.saturating_sub(max_age)),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a filter that selects all orders whose client order id starts with the given prefix.
+    pub fn with_client_id_prefix(prefix: ClientIdPrefix) -> Self {
+        Self {
+            client_id_prefix: Some(prefix),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the given [`OpenOrder`] matches this filter.
+    pub fn matches(&self, order: &OpenOrder) -> bool {
+        if order.order_id == u128::default() {
+            return false;
+        }
+        if let Some(side) = self.side {
+            if order.side != side {
+                return false;
+            }
+        }
+        if self.min_price.is_some() || self.max_price.is_some() {
+            let price = get_order_price(order.order_id);
+            if self.min_price.map(|p| price < p).unwrap_or(false)
+                || self.max_price.map(|p| price > p).unwrap_or(false)
+            {
+                return false;
+            }
+        }
+        if let Some(ts) = self.placed_before {
+            if order.timestamp > ts {
+                return false;
+            }
+        }
+        if let Some(prefix) = self.client_id_prefix {
+            if !prefix.matches(order.client_order_id) {
+                return false;
+            }
+        }
+        if let Some(idx) = self.sub_account_idx {
+            if order.sub_account_idx != idx {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// The accounts needed to cancel orders on a derivatives market.
+#[derive(Debug, Clone, Copy)]
+pub struct CancelOrdersAccounts {
+    /// The clearing.
+    pub clearing: Pubkey,
+    /// The master account.
+    pub master_account: Pubkey,
+    /// The market.
+    pub market: Pubkey,
+    /// The market type.
+    pub market_type: MarketType,
+    /// The master account's orders account for the market.
+    pub orders_account: Pubkey,
+    /// The market's orderbook.
+    pub orderbook: Pubkey,
+    /// The market's event queue.
+    pub event_queue: Pubkey,
+    /// The market's bids.
+    pub bids: Pubkey,
+    /// The market's asks.
+    pub asks: Pubkey,
+    /// The quote pool node.
+    pub quote_pool_node: Pubkey,
+    /// The authority of the master account, or it's delegate.
+    pub authority: Pubkey,
+}
+
+/// Gets the price of an AOB order from it's order id, in native lots.
+///
+/// The most significant 64 bits of the order id are the FP32 limit price of the order.
+pub fn get_order_price(order_id: u128) -> u64 {
+    ((order_id >> 64) as u64) >> 32
+}
+
+/// Selects the open orders that match the given filter.
+pub fn filter_orders<'a>(
+    orders: &[&'a OpenOrder],
+    filter: &CancelOrderFilter,
+) -> Vec<&'a OpenOrder> {
+    orders
+        .iter()
+        .filter(|o| filter.matches(o))
+        .copied()
+        .collect()
+}
+
+/// Builds the minimal set of cancel instructions for the open orders that match the given filter.
+///
+/// Orders are grouped by sub account, since each cancel instruction operates on a single sub account,
+/// and each group is split into batches of at most [`MAX_CANCELS_PER_INSTRUCTION`] orders.
+/// Orders of sub accounts that can not be found in the given master account are skipped.
+pub fn get_cancel_orders_ixs(
+    accounts: &CancelOrdersAccounts,
+    master_account_state: &CypherAccount,
+    orders: &[&OpenOrder],
+    filter: &CancelOrderFilter,
+) -> Vec<Instruction> {
+    let mut orders_by_sub_account: BTreeMap<u8, Vec<CancelOrderArgs>> = BTreeMap::new();
+    for order in filter_orders(orders, filter) {
+        orders_by_sub_account
+            .entry(order.sub_account_idx)
+            .or_default()
+            .push(CancelOrderArgs::from_open_order(order));
+    }
+
+    let mut ixs = Vec::new();
+    for (sub_account_idx, args) in orders_by_sub_account.into_iter() {
+        let sub_account = match master_account_state
+            .sub_account_caches
+            .get(sub_account_idx as usize)
+        {
+            Some(c) if c.sub_account != Pubkey::default() => c.sub_account,
+            _ => continue,
+        };
+        for chunk in args.chunks(MAX_CANCELS_PER_INSTRUCTION) {
+            ixs.push(get_cancel_orders_ix(accounts, &sub_account, chunk.to_vec()));
+        }
+    }
+    ixs
+}
+
+/// Builds the cancel instructions for every open order in the given [`OrdersAccount`], across all sub accounts.
+///
+/// This is the one-call panic button for a market, see [`get_cancel_orders_ixs`] for how the orders are batched.
+pub fn get_cancel_all_orders_ixs(
+    accounts: &CancelOrdersAccounts,
+    master_account_state: &CypherAccount,
+    orders_account_state: &OrdersAccount,
+) -> Vec<Instruction> {
+    let orders = orders_account_state
+        .open_orders
+        .iter()
+        .collect::<Vec<&OpenOrder>>();
+    get_cancel_orders_ixs(
+        accounts,
+        master_account_state,
+        &orders,
+        &CancelOrderFilter::default(),
+    )
+}
+
+/// Builds a single cancel instruction for the given sub account and orders,
+/// according to the market type.
+pub fn get_cancel_orders_ix(
+    accounts: &CancelOrdersAccounts,
+    sub_account: &Pubkey,
+    args: Vec<CancelOrderArgs>,
+) -> Instruction {
+    if accounts.market_type == MarketType::PerpetualFuture {
+        cancel_perp_orders(
+            &accounts.clearing,
+            &cache_account::id(),
+            &accounts.master_account,
+            sub_account,
+            &accounts.market,
+            &accounts.orders_account,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    } else {
+        cancel_futures_orders(
+            &accounts.clearing,
+            &cache_account::id(),
+            &accounts.master_account,
+            sub_account,
+            &accounts.market,
+            &accounts.orders_account,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    }
+}
@@ -0,0 +1,333 @@
+use cypher_client::{CypherAccount, OpenOrder};
+use dashmap::DashMap;
+use log::{info, warn};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_instruction::advance_nonce_account,
+    transaction::Transaction,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::Sender;
+
+use crate::utils::send_transactions;
+
+use super::{get_cancel_orders_ixs, CancelOrderFilter, CancelOrdersAccounts};
+
+/// The compute units requested for each dead man switch cancel transaction.
+const DEAD_MAN_SWITCH_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// The length of an initialized nonce account's data.
+const NONCE_ACCOUNT_LEN: usize = 80;
+
+/// Parses the durable nonce from the given nonce account data.
+///
+/// Returns `None` if the data is too short or the nonce account is not initialized.
+pub fn parse_durable_nonce(account_data: &[u8]) -> Option<Hash> {
+    if account_data.len() < NONCE_ACCOUNT_LEN {
+        return None;
+    }
+    // the account is laid out as the `version` and `state` tags, `authority`, `durable_nonce` and fee calculator
+    let state = u32::from_le_bytes(account_data[4..8].try_into().unwrap());
+    if state != 1 {
+        return None;
+    }
+    Some(Hash::new(&account_data[40..72]))
+}
+
+/// A market protected by the [`DeadManSwitch`].
+#[derive(Debug, Clone)]
+pub struct DeadManSwitchMarket {
+    /// The accounts needed to cancel orders on the market.
+    pub accounts: CancelOrdersAccounts,
+    /// The nonce accounts used to pre-build the market's cancel transactions, one per transaction.
+    ///
+    /// The nonce authority must be the switch's signer. Cancel instructions that do not fit in the pre-built
+    /// transactions are sent with a recent blockhash when the switch triggers.
+    pub nonce_accounts: Vec<Pubkey>,
+}
+
+/// The configuration of the [`DeadManSwitch`].
+#[derive(Debug, Clone)]
+pub struct DeadManSwitchConfig {
+    /// The time without a heartbeat after which the switch triggers.
+    pub timeout: Duration,
+    /// The interval at which the watchdog checks the last heartbeat.
+    pub check_interval: Duration,
+    /// The protected markets.
+    pub markets: Vec<DeadManSwitchMarket>,
+}
+
+/// The cancel transactions prepared for a market.
+#[derive(Debug, Default)]
+struct PreparedCancels {
+    /// The signed durable nonce transactions.
+    transactions: Vec<Transaction>,
+    /// The cancel instructions which did not fit in the pre-built transactions.
+    remaining_ixs: Vec<Instruction>,
+}
+
+/// A dead man switch, which cancels all orders on the configured markets if the strategy process
+/// fails to heartbeat within the configured timeout, protecting makers from stale quotes after a crash.
+///
+/// The cancel transactions are pre-built and signed with durable nonces whenever the strategy's orders change,
+/// see [`DeadManSwitch::prepare`], so the watchdog does not depend on fetching a blockhash to send them.
+/// Once triggered, the nonces are advanced and the switch is disarmed until the nonces are reloaded
+/// with [`DeadManSwitch::load_nonces`] and the cancels prepared again.
+pub struct DeadManSwitch {
+    rpc_client: Arc<RpcClient>,
+    signer: Arc<Keypair>,
+    config: DeadManSwitchConfig,
+    last_heartbeat: Mutex<Instant>,
+    triggered: AtomicBool,
+    nonces: DashMap<Pubkey, Hash>,
+    prepared: DashMap<Pubkey, PreparedCancels>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for DeadManSwitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeadManSwitch")
+            .field("config", &self.config)
+            .field("triggered", &self.triggered.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl DeadManSwitch {
+    /// Creates a new [`DeadManSwitch`].
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        signer: Arc<Keypair>,
+        config: DeadManSwitchConfig,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            rpc_client,
+            signer,
+            config,
+            last_heartbeat: Mutex::new(Instant::now()),
+            triggered: AtomicBool::new(false),
+            nonces: DashMap::new(),
+            prepared: DashMap::new(),
+            shutdown,
+        }
+    }
+
+    /// Signals that the strategy is alive.
+    pub fn heartbeat(&self) {
+        *self.last_heartbeat.lock().unwrap() = Instant::now();
+    }
+
+    /// Whether the switch triggered and has not been re-armed since.
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::Relaxed)
+    }
+
+    /// Loads the durable nonces of every configured nonce account and re-arms the switch.
+    ///
+    /// Returns the number of loaded nonces, nonce accounts that do not exist or are not initialized are skipped.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn load_nonces(&self) -> Result<usize, ClientError> {
+        let nonce_accounts = self
+            .config
+            .markets
+            .iter()
+            .flat_map(|m| m.nonce_accounts.iter().copied())
+            .collect::<Vec<Pubkey>>();
+        let mut loaded = 0;
+
+        for chunk in nonce_accounts.chunks(100) {
+            let accounts = match self.rpc_client.get_multiple_accounts(chunk).await {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+            for (nonce_account, account) in chunk.iter().zip(accounts.iter()) {
+                match account.as_ref().and_then(|a| parse_durable_nonce(&a.data)) {
+                    Some(nonce) => {
+                        self.nonces.insert(*nonce_account, nonce);
+                        loaded += 1;
+                    }
+                    None => {
+                        warn!("Nonce account {} is not initialized.", nonce_account);
+                        self.nonces.remove(nonce_account);
+                    }
+                }
+            }
+        }
+
+        self.triggered.store(false, Ordering::Relaxed);
+        self.heartbeat();
+        Ok(loaded)
+    }
+
+    /// Prepares the cancel transactions for the given open orders on the given market,
+    /// which should be called whenever the strategy's orders on the market change.
+    ///
+    /// Returns the number of pre-built transactions, or `None` if the market is not configured.
+    pub fn prepare(
+        &self,
+        market: &Pubkey,
+        master_account_state: &CypherAccount,
+        orders: &[&OpenOrder],
+    ) -> Option<usize> {
+        let market_config = self
+            .config
+            .markets
+            .iter()
+            .find(|m| &m.accounts.market == market)?;
+        let mut ixs = get_cancel_orders_ixs(
+            &market_config.accounts,
+            master_account_state,
+            orders,
+            &CancelOrderFilter::default(),
+        )
+        .into_iter();
+
+        let mut prepared = PreparedCancels::default();
+        for nonce_account in market_config.nonce_accounts.iter() {
+            let nonce = match self.nonces.get(nonce_account) {
+                Some(n) => *n,
+                None => continue,
+            };
+            let ix = match ixs.next() {
+                Some(ix) => ix,
+                None => break,
+            };
+            prepared
+                .transactions
+                .push(Transaction::new_signed_with_payer(
+                    &[
+                        advance_nonce_account(nonce_account, &self.signer.pubkey()),
+                        ComputeBudgetInstruction::set_compute_unit_limit(
+                            DEAD_MAN_SWITCH_COMPUTE_UNITS,
+                        ),
+                        ix,
+                    ],
+                    Some(&self.signer.pubkey()),
+                    &[self.signer.as_ref()],
+                    nonce,
+                ));
+        }
+        prepared.remaining_ixs = ixs.collect();
+
+        if !prepared.remaining_ixs.is_empty() {
+            warn!(
+                "Not enough nonces to pre-build the cancels for market {}, {} instructions will need a recent blockhash.",
+                market,
+                prepared.remaining_ixs.len()
+            );
+        }
+        let count = prepared.transactions.len();
+        self.prepared.insert(*market, prepared);
+        Some(count)
+    }
+
+    /// Starts the service's work cycle.
+    /// Checks the last heartbeat at the configured interval and triggers the switch on timeout, until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.check_interval);
+        let mut shutdown_receiver = self.shutdown.subscribe();
+        self.heartbeat();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let elapsed = self.last_heartbeat.lock().unwrap().elapsed();
+                    if elapsed > self.config.timeout && !self.is_triggered() {
+                        warn!("No heartbeat for {:?}, triggering dead man switch.", elapsed);
+                        self.trigger().await;
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down dead man switch.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Sends the prepared cancel transactions of every market and disarms the switch.
+    ///
+    /// Returns the signatures of the sent transactions.
+    pub async fn trigger(&self) -> Vec<Signature> {
+        self.triggered.store(true, Ordering::Relaxed);
+        let markets = self
+            .prepared
+            .iter()
+            .map(|p| *p.key())
+            .collect::<Vec<Pubkey>>();
+        let mut signatures = Vec::new();
+
+        for market in markets.iter() {
+            let prepared = match self.prepared.remove(market) {
+                Some((_, p)) => p,
+                None => continue,
+            };
+            for transaction in prepared.transactions.iter() {
+                match self.rpc_client.send_transaction(transaction).await {
+                    Ok(s) => {
+                        info!("Sent cancel transaction for market {}: {}", market, s);
+                        signatures.push(s);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to send cancel transaction for market {}: {}",
+                            market,
+                            e.to_string()
+                        );
+                    }
+                }
+            }
+            if prepared.remaining_ixs.is_empty() {
+                continue;
+            }
+            match send_transactions(
+                &self.rpc_client,
+                prepared.remaining_ixs,
+                &self.signer,
+                false,
+                Some((DEAD_MAN_SWITCH_COMPUTE_UNITS, 1)),
+                None,
+            )
+            .await
+            {
+                Ok(s) => {
+                    info!(
+                        "Sent remaining cancel transactions for market {}: {:?}",
+                        market, s
+                    );
+                    signatures.extend(s);
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to send remaining cancel transactions for market {}: {}",
+                        market,
+                        e.to_string()
+                    );
+                }
+            }
+        }
+
+        // the nonces were advanced by the sent transactions
+        self.nonces.clear();
+        signatures
+    }
+}
@@ -0,0 +1,246 @@
+use cypher_client::{
+    cache_account,
+    instructions::{settle_position, settle_position_with_delivery},
+    FuturesMarket, NewDerivativeOrderArgs, SettlementType, Side,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::collections::HashMap;
+
+use crate::{contexts::UserContext, services::ClockService};
+
+use super::OmsError;
+
+/// The default window before expiration during which new positions can not be opened, in seconds.
+pub const DEFAULT_NO_NEW_POSITIONS_WINDOW: u64 = 30 * 60;
+
+/// The expiry guard configuration of a futures market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryGuardConfig {
+    /// The window before expiration during which orders that open or increase a position are rejected, in seconds.
+    pub no_new_positions_window: u64,
+    /// Whether settlement instructions should be built for open positions once the market expires.
+    pub settle_at_expiry: bool,
+}
+
+impl Default for ExpiryGuardConfig {
+    fn default() -> Self {
+        Self {
+            no_new_positions_window: DEFAULT_NO_NEW_POSITIONS_WINDOW,
+            settle_at_expiry: true,
+        }
+    }
+}
+
+/// The trading phase of a futures market relative to its activation and expiration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryPhase {
+    /// The market is not active yet.
+    NotActive,
+    /// Orders are allowed.
+    Trading,
+    /// The market expires within the configured window, only orders that reduce a position are allowed.
+    ReduceOnly,
+    /// The market has expired and positions can be settled.
+    Expired,
+}
+
+/// The expiration information of a futures market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuturesExpiry {
+    /// The market.
+    pub market: Pubkey,
+    /// The unix timestamp at which the market activates.
+    pub activates_at: u64,
+    /// The unix timestamp at which the market expires.
+    pub expires_at: u64,
+    /// Whether positions are settled with delivery of the underlying.
+    pub is_physical_delivery: bool,
+    /// The market's quote pool node, used to settle positions.
+    pub quote_pool_node: Pubkey,
+}
+
+impl FuturesExpiry {
+    /// Creates the [`FuturesExpiry`] of the given market.
+    pub fn new(market: &Pubkey, market_state: &FuturesMarket, quote_pool_node: &Pubkey) -> Self {
+        Self {
+            market: *market,
+            activates_at: market_state.activates_at,
+            expires_at: market_state.expires_at,
+            is_physical_delivery: market_state.inner.config.settlement_type
+                == SettlementType::PhysicalDelivery,
+            quote_pool_node: *quote_pool_node,
+        }
+    }
+
+    /// Gets the [`ExpiryPhase`] at the given unix timestamp with the given configuration.
+    pub fn get_phase(&self, config: &ExpiryGuardConfig, now: u64) -> ExpiryPhase {
+        if now < self.activates_at {
+            ExpiryPhase::NotActive
+        } else if now >= self.expires_at {
+            ExpiryPhase::Expired
+        } else if now
+            >= self
+                .expires_at
+                .saturating_sub(config.no_new_positions_window)
+        {
+            ExpiryPhase::ReduceOnly
+        } else {
+            ExpiryPhase::Trading
+        }
+    }
+}
+
+/// Guards against opening futures positions close to expiration and builds settlement instructions at expiry.
+#[derive(Debug, Default, Clone)]
+pub struct ExpiryGuard {
+    default: ExpiryGuardConfig,
+    configs: HashMap<Pubkey, ExpiryGuardConfig>,
+    markets: HashMap<Pubkey, FuturesExpiry>,
+}
+
+impl ExpiryGuard {
+    /// Creates a new [`ExpiryGuard`] with the given default configuration.
+    pub fn new(default: ExpiryGuardConfig) -> Self {
+        Self {
+            default,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the configuration of the given market, overriding the default configuration.
+    pub fn set_config(&mut self, market: &Pubkey, config: ExpiryGuardConfig) {
+        self.configs.insert(*market, config);
+    }
+
+    /// Gets the configuration of the given market.
+    pub fn get_config(&self, market: &Pubkey) -> &ExpiryGuardConfig {
+        self.configs.get(market).unwrap_or(&self.default)
+    }
+
+    /// Tracks the expiration of the given futures market.
+    pub fn track_market(&mut self, expiry: FuturesExpiry) {
+        self.markets.insert(expiry.market, expiry);
+    }
+
+    /// Gets the [`ExpiryPhase`] of the given market at the given unix timestamp, if it is tracked.
+    pub fn get_phase(&self, market: &Pubkey, now: u64) -> Option<ExpiryPhase> {
+        self.markets
+            .get(market)
+            .map(|e| e.get_phase(self.get_config(market), now))
+    }
+
+    /// Gets the tracked markets that have expired at the given unix timestamp and are configured to be settled.
+    pub fn get_expired_markets(&self, now: u64) -> Vec<&FuturesExpiry> {
+        self.markets
+            .values()
+            .filter(|e| {
+                let config = self.get_config(&e.market);
+                config.settle_at_expiry && e.get_phase(config, now) == ExpiryPhase::Expired
+            })
+            .collect()
+    }
+
+    /// Checks the given order on the given market at the given unix timestamp.
+    ///
+    /// Orders on markets that are not tracked are always accepted.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the market is not active yet or has expired,
+    /// or if the market expires within the configured window and the order would open or increase a position.
+    pub fn check_order(
+        &self,
+        market: &Pubkey,
+        args: &NewDerivativeOrderArgs,
+        base_position: i64,
+        now: u64,
+    ) -> Result<(), OmsError> {
+        let expiry = match self.markets.get(market) {
+            Some(e) => e,
+            None => return Ok(()),
+        };
+        match expiry.get_phase(self.get_config(market), now) {
+            ExpiryPhase::Trading => Ok(()),
+            ExpiryPhase::NotActive => Err(OmsError::MarketNotActive(*market, expiry.activates_at)),
+            ExpiryPhase::Expired => Err(OmsError::MarketExpired(*market, expiry.expires_at)),
+            ExpiryPhase::ReduceOnly => {
+                let reduces = (args.side == Side::Ask && base_position > 0
+                    || args.side == Side::Bid && base_position < 0)
+                    && args.max_base_qty <= base_position.unsigned_abs();
+                if reduces {
+                    Ok(())
+                } else {
+                    Err(OmsError::MarketExpiring(*market, expiry.expires_at))
+                }
+            }
+        }
+    }
+
+    /// Checks the given order on the given market at the current cluster time of the given [`ClockService`].
+    ///
+    /// See [`ExpiryGuard::check_order`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the order is rejected by the guard.
+    pub async fn check_order_with_clock(
+        &self,
+        market: &Pubkey,
+        args: &NewDerivativeOrderArgs,
+        base_position: i64,
+        clock: &ClockService,
+    ) -> Result<(), OmsError> {
+        let now = clock.get_unix_timestamp().await.max(0) as u64;
+        self.check_order(market, args, base_position, now)
+    }
+
+    /// Gets the instructions that settle every position of the given [`UserContext`] on the given expired market.
+    ///
+    /// The `underlying_pool_node` is only needed for markets settled with delivery of the underlying,
+    /// for which no instructions are built if it is not given. Returns no instructions if the market is
+    /// not tracked, has not expired at the given unix timestamp or is not configured to be settled.
+    pub fn get_settlement_ixs(
+        &self,
+        market: &Pubkey,
+        user_ctx: &UserContext,
+        underlying_pool_node: Option<&Pubkey>,
+        now: u64,
+    ) -> Vec<Instruction> {
+        let expiry = match self.markets.get(market) {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+        let config = self.get_config(market);
+        if !config.settle_at_expiry || expiry.get_phase(config, now) != ExpiryPhase::Expired {
+            return Vec::new();
+        }
+
+        let mut ixs = Vec::new();
+        for sub_account_ctx in user_ctx.sub_account_ctxs.iter() {
+            if sub_account_ctx.get_derivative_position(market).is_none() {
+                continue;
+            }
+            if expiry.is_physical_delivery {
+                if let Some(underlying_pool_node) = underlying_pool_node {
+                    ixs.push(settle_position_with_delivery(
+                        &cache_account::id(),
+                        &user_ctx.account_ctx.address,
+                        &sub_account_ctx.address,
+                        market,
+                        &expiry.quote_pool_node,
+                        underlying_pool_node,
+                    ));
+                }
+            } else {
+                ixs.push(settle_position(
+                    &cache_account::id(),
+                    &user_ctx.account_ctx.address,
+                    &sub_account_ctx.address,
+                    market,
+                    &expiry.quote_pool_node,
+                ));
+            }
+        }
+        ixs
+    }
+}
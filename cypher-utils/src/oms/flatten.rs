@@ -0,0 +1,302 @@
+use cypher_client::{
+    cache_account,
+    instructions::{new_futures_order, new_perp_order, settle_futures_funds, settle_perp_funds},
+    DerivativeOrderType, MarketType, NewDerivativeOrderArgs, OpenOrder, Side,
+};
+use fixed::types::I80F48;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signature::Signature,
+};
+use std::sync::Arc;
+
+use crate::{
+    contexts::{reload_with_retry, ContextError, UserContext},
+    retry::RetryPolicy,
+    utils::send_transactions,
+};
+
+use super::{get_cancel_orders_ixs, CancelOrderFilter, CancelOrdersAccounts};
+
+/// The compute units requested for each flatten transaction.
+const FLATTEN_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// A derivatives market that should be flattened.
+#[derive(Debug, Clone)]
+pub struct FlattenMarket {
+    /// The accounts of the market.
+    pub accounts: CancelOrdersAccounts,
+    /// The market's price history, only needed for futures markets.
+    pub price_history: Pubkey,
+    /// The market's base multiplier, used to convert positions to lots.
+    pub base_multiplier: u64,
+    /// The open orders in the master account's orders account for this market.
+    pub open_orders: Vec<OpenOrder>,
+    /// The maximum FP32 lot price at which short positions are bought back.
+    pub max_bid_price: u64,
+    /// The minimum FP32 lot price at which long positions are sold.
+    pub min_ask_price: u64,
+}
+
+/// Something that could not be flattened.
+#[derive(Debug, Clone)]
+pub struct FlattenFailure {
+    /// The market.
+    pub market: Pubkey,
+    /// The sub account, if the failure pertains to a specific sub account.
+    pub sub_account: Option<Pubkey>,
+    /// The reason.
+    pub reason: String,
+}
+
+/// The result of flattening an account.
+#[derive(Debug, Default, Clone)]
+pub struct FlattenReport {
+    /// The signatures of the submitted transactions.
+    pub signatures: Vec<Signature>,
+    /// The number of orders that cancellation was requested for.
+    pub cancelled_orders: usize,
+    /// The number of positions that a closing order was submitted for.
+    pub closed_positions: usize,
+    /// Everything that could not be flattened.
+    pub failures: Vec<FlattenFailure>,
+}
+
+impl FlattenReport {
+    /// Whether everything was successfully flattened.
+    pub fn is_flat(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Gets the instructions that cancel all orders, close all positions with IOC orders
+/// and settle funds for all sub accounts of the given [`UserContext`] on the given market.
+///
+/// Returns the instructions along with the number of cancelled orders and closed positions.
+pub fn get_flatten_market_ixs(
+    user_ctx: &UserContext,
+    market: &FlattenMarket,
+) -> (Vec<Instruction>, usize, usize) {
+    let accounts = &market.accounts;
+    let open_orders = market.open_orders.iter().collect::<Vec<&OpenOrder>>();
+    let mut ixs = get_cancel_orders_ixs(
+        accounts,
+        &user_ctx.account_ctx.state,
+        &open_orders,
+        &CancelOrderFilter::default(),
+    );
+    let cancelled_orders = open_orders
+        .iter()
+        .filter(|o| o.order_id != u128::default())
+        .count();
+    let mut closed_positions = 0;
+
+    for sub_account_ctx in user_ctx.sub_account_ctxs.iter() {
+        let position = match sub_account_ctx.get_derivative_position(&accounts.market) {
+            Some(p) => p,
+            None => continue,
+        };
+        let base_position = position.base_position();
+        let max_base_qty =
+            (base_position.abs() / I80F48::from(market.base_multiplier.max(1))).to_num::<u64>();
+        if max_base_qty != 0 {
+            let (side, limit_price) = if base_position.is_positive() {
+                (Side::Ask, market.min_ask_price)
+            } else {
+                (Side::Bid, market.max_bid_price)
+            };
+            let args = NewDerivativeOrderArgs {
+                side,
+                limit_price,
+                max_base_qty,
+                max_quote_qty: u64::MAX,
+                order_type: DerivativeOrderType::ImmediateOrCancel,
+                client_order_id: u64::default(),
+                limit: u16::MAX,
+                max_ts: u64::MAX,
+            };
+            ixs.push(get_new_order_ix(
+                market,
+                &user_ctx.account_ctx.address,
+                &sub_account_ctx.address,
+                args,
+            ));
+            closed_positions += 1;
+        }
+        ixs.push(get_settle_funds_ix(
+            market,
+            &user_ctx.account_ctx.address,
+            &sub_account_ctx.address,
+        ));
+    }
+
+    (ixs, cancelled_orders, closed_positions)
+}
+
+/// Flattens the given [`UserContext`] on all of the given markets.
+///
+/// For each market this cancels all orders, closes all derivative positions with IOC orders
+/// and settles funds. Each market is submitted separately so a failure on one market does not
+/// prevent the others from being flattened. Afterwards the [`UserContext`] is reloaded and every
+/// position or order that is still open is reported as a failure.
+///
+/// ### Errors
+///
+/// This function will only return an error if reloading the [`UserContext`] fails after retrying transient errors,
+/// errors while submitting transactions are reported in the [`FlattenReport`].
+pub async fn flatten_account(
+    rpc_client: &Arc<RpcClient>,
+    signer: &Keypair,
+    user_ctx: &mut UserContext,
+    markets: &[FlattenMarket],
+) -> Result<FlattenReport, ContextError> {
+    let mut report = FlattenReport::default();
+
+    for market in markets.iter() {
+        let (ixs, cancelled_orders, closed_positions) = get_flatten_market_ixs(user_ctx, market);
+        if ixs.is_empty() {
+            continue;
+        }
+        match send_transactions(
+            rpc_client,
+            ixs,
+            signer,
+            true,
+            Some((FLATTEN_COMPUTE_UNITS, 1)),
+            None,
+        )
+        .await
+        {
+            Ok(signatures) => {
+                info!(
+                    "Flattened market {} - Cancelled orders: {} - Closed positions: {}",
+                    market.accounts.market, cancelled_orders, closed_positions
+                );
+                report.signatures.extend(signatures);
+                report.cancelled_orders += cancelled_orders;
+                report.closed_positions += closed_positions;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to flatten market {}: {}",
+                    market.accounts.market,
+                    e.to_string()
+                );
+                report.failures.push(FlattenFailure {
+                    market: market.accounts.market,
+                    sub_account: None,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    // a failed reload would leave the outcome of the flatten unknown, so transient errors are retried
+    match reload_with_retry(user_ctx, rpc_client, &RetryPolicy::default()).await {
+        Ok(()) => (),
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    for market in markets.iter() {
+        for sub_account_ctx in user_ctx.sub_account_ctxs.iter() {
+            let position = match sub_account_ctx.get_derivative_position(&market.accounts.market) {
+                Some(p) => p,
+                None => continue,
+            };
+            if position.base_position() != I80F48::ZERO {
+                report.failures.push(FlattenFailure {
+                    market: market.accounts.market,
+                    sub_account: Some(sub_account_ctx.address),
+                    reason: format!("Position of {} remains open", position.base_position()),
+                });
+            }
+            if position.open_orders_cache.coin_locked() != 0
+                || position.open_orders_cache.pc_total != position.open_orders_cache.pc_free
+            {
+                report.failures.push(FlattenFailure {
+                    market: market.accounts.market,
+                    sub_account: Some(sub_account_ctx.address),
+                    reason: "Orders remain open".to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn get_new_order_ix(
+    market: &FlattenMarket,
+    master_account: &Pubkey,
+    sub_account: &Pubkey,
+    args: NewDerivativeOrderArgs,
+) -> Instruction {
+    let accounts = &market.accounts;
+    if accounts.market_type == MarketType::PerpetualFuture {
+        new_perp_order(
+            &accounts.clearing,
+            &cache_account::id(),
+            master_account,
+            sub_account,
+            &accounts.market,
+            &accounts.orders_account,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    } else {
+        new_futures_order(
+            &accounts.clearing,
+            &cache_account::id(),
+            master_account,
+            sub_account,
+            &accounts.market,
+            &accounts.orders_account,
+            &market.price_history,
+            &accounts.orderbook,
+            &accounts.event_queue,
+            &accounts.bids,
+            &accounts.asks,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+            args,
+        )
+    }
+}
+
+fn get_settle_funds_ix(
+    market: &FlattenMarket,
+    master_account: &Pubkey,
+    sub_account: &Pubkey,
+) -> Instruction {
+    let accounts = &market.accounts;
+    if accounts.market_type == MarketType::PerpetualFuture {
+        settle_perp_funds(
+            &accounts.clearing,
+            &cache_account::id(),
+            master_account,
+            sub_account,
+            &accounts.market,
+            &accounts.orders_account,
+            &accounts.quote_pool_node,
+        )
+    } else {
+        settle_futures_funds(
+            &accounts.clearing,
+            &cache_account::id(),
+            master_account,
+            sub_account,
+            &accounts.market,
+            &accounts.orders_account,
+            &accounts.quote_pool_node,
+            &accounts.authority,
+        )
+    }
+}
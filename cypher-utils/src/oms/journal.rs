@@ -0,0 +1,478 @@
+use cypher_client::Side;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::storage::{append_events, query_events, Storage, StorageError};
+
+use super::{ExpectedOrder, StrategyFill};
+
+/// The storage stream the OMS journal is persisted to.
+pub const JOURNAL_STREAM: &str = "oms_journal";
+
+/// The side of a journaled order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalSide {
+    Bid,
+    Ask,
+}
+
+impl From<Side> for JournalSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Bid => JournalSide::Bid,
+            Side::Ask => JournalSide::Ask,
+        }
+    }
+}
+
+impl From<JournalSide> for Side {
+    fn from(side: JournalSide) -> Self {
+        match side {
+            JournalSide::Bid => Side::Bid,
+            JournalSide::Ask => Side::Ask,
+        }
+    }
+}
+
+/// An entry of the OMS journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// The OMS decided to place an order.
+    Intent {
+        market: Pubkey,
+        sub_account_idx: u8,
+        client_order_id: u64,
+        side: JournalSide,
+        limit_price: u64,
+        max_base_quantity: u64,
+    },
+    /// The transaction placing the given orders was submitted.
+    Submission {
+        market: Pubkey,
+        client_order_ids: Vec<u64>,
+        signature: Signature,
+    },
+    /// The order was acknowledged on-chain under the given order id.
+    Ack {
+        market: Pubkey,
+        client_order_id: u64,
+        order_id: u128,
+    },
+    /// The order was filled, `completed` is set if the order has no remaining quantity.
+    Fill {
+        market: Pubkey,
+        client_order_id: u64,
+        price: u64,
+        base_quantity: u64,
+        completed: bool,
+    },
+    /// The order was cancelled.
+    Cancel {
+        market: Pubkey,
+        client_order_id: u64,
+    },
+    /// The order was rejected or it's transaction failed, it never rested on the book.
+    Rejected {
+        market: Pubkey,
+        client_order_id: u64,
+        reason: String,
+    },
+}
+
+/// A record of the OMS journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalRecord {
+    /// The sequence number of the record, which is strictly increasing across restarts.
+    pub sequence: u64,
+    /// The slot at which the entry was recorded, if known.
+    pub slot: u64,
+    /// The local unix timestamp at which the entry was recorded, in seconds.
+    pub timestamp: i64,
+    /// The entry.
+    pub entry: JournalEntry,
+}
+
+/// The state of the OMS recovered from it's journal.
+#[derive(Debug, Default, Clone)]
+pub struct JournalRecovery {
+    /// Every record of the journal, ordered by sequence number.
+    pub records: Vec<JournalRecord>,
+    /// The orders that were placed and are not known to be filled, cancelled or rejected.
+    ///
+    /// These are meant to be reconciled against the on-chain state, see [`super::reconcile_startup`].
+    pub expected_orders: Vec<ExpectedOrder>,
+}
+
+/// Folds the given records into the orders expected to be open.
+pub fn get_expected_orders(records: &[JournalRecord]) -> Vec<ExpectedOrder> {
+    let mut orders: HashMap<(Pubkey, u64), ExpectedOrder> = HashMap::new();
+    let mut order_keys = Vec::new();
+
+    for record in records.iter() {
+        match &record.entry {
+            JournalEntry::Intent {
+                market,
+                sub_account_idx,
+                client_order_id,
+                side,
+                ..
+            } => {
+                let key = (*market, *client_order_id);
+                if orders
+                    .insert(
+                        key,
+                        ExpectedOrder {
+                            market: *market,
+                            sub_account_idx: *sub_account_idx,
+                            side: (*side).into(),
+                            client_order_id: *client_order_id,
+                            order_id: None,
+                        },
+                    )
+                    .is_none()
+                {
+                    order_keys.push(key);
+                }
+            }
+            JournalEntry::Ack {
+                market,
+                client_order_id,
+                order_id,
+            } => {
+                if let Some(order) = orders.get_mut(&(*market, *client_order_id)) {
+                    order.order_id = Some(*order_id);
+                }
+            }
+            JournalEntry::Fill {
+                market,
+                client_order_id,
+                completed,
+                ..
+            } => {
+                if *completed {
+                    orders.remove(&(*market, *client_order_id));
+                }
+            }
+            JournalEntry::Cancel {
+                market,
+                client_order_id,
+            }
+            | JournalEntry::Rejected {
+                market,
+                client_order_id,
+                ..
+            } => {
+                orders.remove(&(*market, *client_order_id));
+            }
+            JournalEntry::Submission { .. } => (),
+        }
+    }
+
+    // keep the order in which the orders were placed
+    order_keys.iter().filter_map(|k| orders.remove(k)).collect()
+}
+
+/// An append-only journal of every order intent, submission, ack and fill processed by the OMS,
+/// persisted to a [`Storage`] backend so the client's actions can be audited independently of RPC history.
+///
+/// Entries are written ahead, i.e. an entry is persisted before the call to record it returns, which allows
+/// the OMS state to be recovered after a crash with [`OmsJournal::recover`].
+pub struct OmsJournal {
+    storage: Box<dyn Storage>,
+    next_sequence: Mutex<u64>,
+}
+
+impl std::fmt::Debug for OmsJournal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OmsJournal")
+            .field("next_sequence", &self.next_sequence.lock().unwrap())
+            .finish()
+    }
+}
+
+impl OmsJournal {
+    /// Opens the journal persisted to the given [`Storage`] and recovers it's state.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the journal could not be read.
+    pub fn open(storage: Box<dyn Storage>) -> Result<(Self, JournalRecovery), StorageError> {
+        let journal = Self {
+            storage,
+            next_sequence: Mutex::new(0),
+        };
+        let recovery = match journal.recover() {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        Ok((journal, recovery))
+    }
+
+    /// Reads every record of the journal and recovers the orders expected to be open.
+    ///
+    /// Records appended after this journal's last known sequence number, e.g. by a previous process, advance it.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the journal could not be read.
+    pub fn recover(&self) -> Result<JournalRecovery, StorageError> {
+        let mut records = match query_events::<JournalRecord>(
+            self.storage.as_ref(),
+            JOURNAL_STREAM,
+            i64::MIN,
+            i64::MAX,
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        records.sort_by_key(|r| r.sequence);
+
+        let mut next_sequence = self.next_sequence.lock().unwrap();
+        if let Some(last) = records.last() {
+            *next_sequence = u64::max(*next_sequence, last.sequence + 1);
+        }
+        let expected_orders = get_expected_orders(&records);
+        info!(
+            "Recovered {} journal records with {} expected open orders.",
+            records.len(),
+            expected_orders.len()
+        );
+
+        Ok(JournalRecovery {
+            records,
+            expected_orders,
+        })
+    }
+
+    /// Records the given entries at the given slot, returning once they are persisted.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entries could not be persisted, in which case the OMS
+    /// should not proceed with the action they describe.
+    pub fn record(
+        &self,
+        slot: u64,
+        entries: Vec<JournalEntry>,
+    ) -> Result<Vec<JournalRecord>, StorageError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        // hold the lock while appending so records are persisted in sequence order
+        let mut next_sequence = self.next_sequence.lock().unwrap();
+        let records = entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, entry)| JournalRecord {
+                sequence: *next_sequence + i as u64,
+                slot,
+                timestamp,
+                entry,
+            })
+            .collect::<Vec<JournalRecord>>();
+
+        match append_events(
+            self.storage.as_ref(),
+            JOURNAL_STREAM,
+            &records
+                .iter()
+                .map(|r| (r.slot, r.timestamp, r))
+                .collect::<Vec<_>>(),
+        ) {
+            Ok(()) => {
+                *next_sequence += records.len() as u64;
+                Ok(records)
+            }
+            Err(e) => {
+                warn!("Failed to persist journal entries: {}", e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Records the intent to place an order.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record_intent(
+        &self,
+        slot: u64,
+        market: &Pubkey,
+        sub_account_idx: u8,
+        client_order_id: u64,
+        side: Side,
+        limit_price: u64,
+        max_base_quantity: u64,
+    ) -> Result<(), StorageError> {
+        self.record(
+            slot,
+            vec![JournalEntry::Intent {
+                market: *market,
+                sub_account_idx,
+                client_order_id,
+                side: side.into(),
+                limit_price,
+                max_base_quantity,
+            }],
+        )
+        .map(|_| ())
+    }
+
+    /// Records the submission of the transaction placing the given orders.
+    ///
+    /// The slot should be the slot at which the transaction was sent, see [`super::OrderLifecycle`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    pub fn record_submission(
+        &self,
+        slot: u64,
+        market: &Pubkey,
+        client_order_ids: &[u64],
+        signature: &Signature,
+    ) -> Result<(), StorageError> {
+        self.record(
+            slot,
+            vec![JournalEntry::Submission {
+                market: *market,
+                client_order_ids: client_order_ids.to_vec(),
+                signature: *signature,
+            }],
+        )
+        .map(|_| ())
+    }
+
+    /// Records the acknowledgement of an order under the given order id.
+    ///
+    /// The slot should be the slot at which the transaction placing the order landed.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    pub fn record_ack(
+        &self,
+        slot: u64,
+        market: &Pubkey,
+        client_order_id: u64,
+        order_id: u128,
+    ) -> Result<(), StorageError> {
+        self.record(
+            slot,
+            vec![JournalEntry::Ack {
+                market: *market,
+                client_order_id,
+                order_id,
+            }],
+        )
+        .map(|_| ())
+    }
+
+    /// Records a fill of an order.
+    ///
+    /// The slot should be the slot at which the fill was observed, e.g. [`StrategyFill::slot`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    pub fn record_fill(
+        &self,
+        slot: u64,
+        market: &Pubkey,
+        client_order_id: u64,
+        price: u64,
+        base_quantity: u64,
+        completed: bool,
+    ) -> Result<(), StorageError> {
+        self.record(
+            slot,
+            vec![JournalEntry::Fill {
+                market: *market,
+                client_order_id,
+                price,
+                base_quantity,
+                completed,
+            }],
+        )
+        .map(|_| ())
+    }
+
+    /// Records the given fill routed by the [`super::FillRouter`] at the slot it was observed at.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    pub fn record_strategy_fill(
+        &self,
+        fill: &StrategyFill,
+        completed: bool,
+    ) -> Result<(), StorageError> {
+        self.record_fill(
+            fill.slot,
+            &fill.market,
+            fill.client_order_id,
+            fill.fill.price,
+            fill.fill.base_quantity,
+            completed,
+        )
+    }
+
+    /// Records the cancellation of an order.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    pub fn record_cancel(
+        &self,
+        slot: u64,
+        market: &Pubkey,
+        client_order_id: u64,
+    ) -> Result<(), StorageError> {
+        self.record(
+            slot,
+            vec![JournalEntry::Cancel {
+                market: *market,
+                client_order_id,
+            }],
+        )
+        .map(|_| ())
+    }
+
+    /// Records the rejection of an order.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the entry could not be persisted.
+    pub fn record_rejection(
+        &self,
+        slot: u64,
+        market: &Pubkey,
+        client_order_id: u64,
+        reason: String,
+    ) -> Result<(), StorageError> {
+        self.record(
+            slot,
+            vec![JournalEntry::Rejected {
+                market: *market,
+                client_order_id,
+                reason,
+            }],
+        )
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,431 @@
+use cypher_client::{
+    utils::get_zero_copy_account, FuturesMarket, OperatingStatus, PerpetualMarket, Pool, PoolNode,
+};
+use dashmap::DashMap;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use std::sync::Arc;
+use tokio::sync::broadcast::{channel, error::RecvError, Receiver, Sender};
+
+use crate::{
+    accounts_cache::{AccountState, AccountsCache},
+    contexts::{get_cypher_account, OrdersAccountContext},
+    utils::send_transactions,
+};
+
+use super::{get_cancel_orders_ixs, CancelOrderFilter, CancelOrdersAccounts};
+
+/// The compute units requested for each kill switch cancel transaction.
+const KILL_SWITCH_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// The operating status of a market, pool or pool node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Active,
+    ReduceOnly,
+    CancelOnly,
+    Halted,
+}
+
+impl From<&OperatingStatus> for Status {
+    fn from(status: &OperatingStatus) -> Self {
+        match status {
+            OperatingStatus::Active => Status::Active,
+            OperatingStatus::ReduceOnly => Status::ReduceOnly,
+            OperatingStatus::CancelOnly => Status::CancelOnly,
+            OperatingStatus::Halted => Status::Halted,
+        }
+    }
+}
+
+/// The kind of account watched by the [`KillSwitch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchedAccountKind {
+    PerpetualMarket,
+    FuturesMarket,
+    Pool,
+    PoolNode,
+}
+
+impl WatchedAccountKind {
+    /// Parses the operating status from the given account data.
+    pub fn get_status(&self, account_data: &[u8]) -> Status {
+        match self {
+            WatchedAccountKind::PerpetualMarket => Status::from(
+                &get_zero_copy_account::<PerpetualMarket>(account_data)
+                    .inner
+                    .config
+                    .operating_status,
+            ),
+            WatchedAccountKind::FuturesMarket => Status::from(
+                &get_zero_copy_account::<FuturesMarket>(account_data)
+                    .inner
+                    .config
+                    .operating_status,
+            ),
+            WatchedAccountKind::Pool => {
+                Status::from(&get_zero_copy_account::<Pool>(account_data).operating_status)
+            }
+            WatchedAccountKind::PoolNode => {
+                Status::from(&get_zero_copy_account::<PoolNode>(account_data).operating_status)
+            }
+        }
+    }
+}
+
+/// An action triggered by the [`KillSwitch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillSwitchAction {
+    /// Cancel all orders on the market.
+    CancelAllOrders,
+    /// Stop quoting the market, see [`KillSwitch::is_quoting_enabled`].
+    StopQuoting,
+    /// Emit an alert, this is always done for every status change.
+    Alert,
+}
+
+/// The actions the [`KillSwitch`] triggers for each status.
+#[derive(Debug, Clone)]
+pub struct KillSwitchConfig {
+    /// The actions triggered when an account flips to [`Status::ReduceOnly`].
+    pub reduce_only: Vec<KillSwitchAction>,
+    /// The actions triggered when an account flips to [`Status::CancelOnly`].
+    pub cancel_only: Vec<KillSwitchAction>,
+    /// The actions triggered when an account flips to [`Status::Halted`].
+    pub halted: Vec<KillSwitchAction>,
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            reduce_only: vec![KillSwitchAction::Alert],
+            cancel_only: vec![
+                KillSwitchAction::CancelAllOrders,
+                KillSwitchAction::StopQuoting,
+                KillSwitchAction::Alert,
+            ],
+            halted: vec![KillSwitchAction::StopQuoting, KillSwitchAction::Alert],
+        }
+    }
+}
+
+impl KillSwitchConfig {
+    /// Gets the actions triggered for the given status.
+    pub fn get_actions(&self, status: Status) -> &[KillSwitchAction] {
+        match status {
+            Status::Active => &[],
+            Status::ReduceOnly => &self.reduce_only,
+            Status::CancelOnly => &self.cancel_only,
+            Status::Halted => &self.halted,
+        }
+    }
+}
+
+/// A change in the operating status of a watched account.
+#[derive(Debug, Clone)]
+pub struct KillSwitchEvent {
+    /// The account.
+    pub account: Pubkey,
+    /// The kind of account.
+    pub kind: WatchedAccountKind,
+    /// The previous status, if it was known.
+    pub previous_status: Option<Status>,
+    /// The new status.
+    pub status: Status,
+    /// The actions triggered.
+    pub actions: Vec<KillSwitchAction>,
+    /// The slot at which the change was observed.
+    pub slot: u64,
+}
+
+struct WatchedAccount {
+    kind: WatchedAccountKind,
+    status: Option<Status>,
+    /// The markets affected by this account, e.g. the markets quoted against a pool.
+    markets: Vec<Pubkey>,
+}
+
+/// Watches the operating status of markets, pools and pool nodes and triggers the configured
+/// actions whenever one of them changes status, e.g. to cancel all orders and stop quoting a market
+/// that flipped to [`Status::CancelOnly`].
+///
+/// Market makers should check [`KillSwitch::is_quoting_enabled`] before quoting a market.
+pub struct KillSwitch {
+    cache: Arc<AccountsCache>,
+    rpc_client: Arc<RpcClient>,
+    signer: Arc<Keypair>,
+    config: KillSwitchConfig,
+    watched: DashMap<Pubkey, WatchedAccount>,
+    cancel_accounts: DashMap<Pubkey, CancelOrdersAccounts>,
+    quoting_disabled: DashMap<Pubkey, Status>,
+    sender: Arc<Sender<KillSwitchEvent>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for KillSwitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KillSwitch")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl KillSwitch {
+    /// Creates a new [`KillSwitch`].
+    pub fn new(
+        cache: Arc<AccountsCache>,
+        rpc_client: Arc<RpcClient>,
+        signer: Arc<Keypair>,
+        config: KillSwitchConfig,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            cache,
+            rpc_client,
+            signer,
+            config,
+            watched: DashMap::new(),
+            cancel_accounts: DashMap::new(),
+            quoting_disabled: DashMap::new(),
+            sender: Arc::new(channel::<KillSwitchEvent>(64).0),
+            shutdown,
+        }
+    }
+
+    /// Subscribes to the [`KillSwitchEvent`]s.
+    pub fn subscribe(&self) -> Receiver<KillSwitchEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Watches the given derivatives market, cancelling orders with the given accounts when configured to.
+    pub fn watch_market(
+        &self,
+        market: &Pubkey,
+        kind: WatchedAccountKind,
+        cancel_accounts: Option<CancelOrdersAccounts>,
+    ) {
+        self.watched.insert(
+            *market,
+            WatchedAccount {
+                kind,
+                status: None,
+                markets: vec![*market],
+            },
+        );
+        if let Some(accounts) = cancel_accounts {
+            self.cancel_accounts.insert(*market, accounts);
+        }
+    }
+
+    /// Watches the given pool or pool node, triggering the configured actions for the given markets
+    /// whenever it changes status.
+    pub fn watch_pool(&self, account: &Pubkey, kind: WatchedAccountKind, markets: &[Pubkey]) {
+        self.watched.insert(
+            *account,
+            WatchedAccount {
+                kind,
+                status: None,
+                markets: markets.to_vec(),
+            },
+        );
+    }
+
+    /// Whether the given market can be quoted.
+    pub fn is_quoting_enabled(&self, market: &Pubkey) -> bool {
+        !self.quoting_disabled.contains_key(market)
+    }
+
+    /// Re-enables quoting the given market, e.g. after an operator acknowledged the status change.
+    pub fn enable_quoting(&self, market: &Pubkey) {
+        self.quoting_disabled.remove(market);
+    }
+
+    /// Starts the service's work cycle.
+    /// Processes updates to the watched accounts in the [`AccountsCache`] until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let accounts = self
+            .watched
+            .iter()
+            .map(|w| *w.key())
+            .collect::<Vec<Pubkey>>();
+        for account in accounts.iter() {
+            let state = self.cache.get(account).map(|s| s.clone());
+            if let Some(state) = state {
+                self.process_update(&state).await;
+            }
+        }
+
+        let mut receiver = self.cache.subscribe(&accounts).await;
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => {
+                    match update {
+                        Ok(state) => self.process_update(&state).await,
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Kill switch lagged behind {} account updates.", n);
+                        }
+                        Err(RecvError::Closed) => {
+                            warn!("Kill switch account updates channel closed.");
+                            break;
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down kill switch.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Processes an update to a watched account, triggering the configured actions if its status changed.
+    pub async fn process_update(&self, state: &AccountState) {
+        let (kind, previous_status, status, markets) = {
+            let mut watched = match self.watched.get_mut(&state.account) {
+                Some(w) => w,
+                None => return,
+            };
+            let status = watched.kind.get_status(&state.data);
+            let previous_status = watched.status;
+            if previous_status == Some(status) {
+                return;
+            }
+            watched.status = Some(status);
+            (
+                watched.kind,
+                previous_status,
+                status,
+                watched.markets.clone(),
+            )
+        };
+
+        let actions = self.config.get_actions(status).to_vec();
+        warn!(
+            "Account {} changed status from {:?} to {:?} at slot {} - Actions: {:?}",
+            state.account, previous_status, status, state.slot, actions
+        );
+
+        for market in markets.iter() {
+            self.update_quoting(market);
+        }
+        if actions.contains(&KillSwitchAction::CancelAllOrders) {
+            for market in markets.iter() {
+                self.cancel_all_orders(market).await;
+            }
+        }
+
+        let event = KillSwitchEvent {
+            account: state.account,
+            kind,
+            previous_status,
+            status,
+            actions,
+            slot: state.slot,
+        };
+        if self.sender.send(event).is_err() {
+            info!("No active receivers for kill switch events.");
+        }
+    }
+
+    /// Updates whether the given market can be quoted from the current status of all watched accounts affecting it,
+    /// e.g. a market quoted against a halted pool stays disabled while the market itself flips back to active.
+    ///
+    /// Quoting is disabled while any of these accounts is in a status that stops quoting and is only re-enabled
+    /// once all of them are [`Status::Active`].
+    fn update_quoting(&self, market: &Pubkey) {
+        let statuses = self
+            .watched
+            .iter()
+            .filter(|w| w.markets.contains(market))
+            .map(|w| w.status)
+            .collect::<Vec<Option<Status>>>();
+        let stop_quoting_status = statuses.iter().flatten().find(|s| {
+            self.config
+                .get_actions(**s)
+                .contains(&KillSwitchAction::StopQuoting)
+        });
+        match stop_quoting_status {
+            Some(status) => {
+                self.quoting_disabled.insert(*market, *status);
+            }
+            None => {
+                if statuses.iter().all(|s| *s == Some(Status::Active)) {
+                    self.quoting_disabled.remove(market);
+                }
+            }
+        }
+    }
+
+    async fn cancel_all_orders(&self, market: &Pubkey) {
+        let accounts = match self.cancel_accounts.get(market) {
+            Some(a) => *a,
+            None => return,
+        };
+        let orders_account_ctx =
+            match OrdersAccountContext::load(&self.rpc_client, market, &accounts.master_account)
+                .await
+            {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    warn!(
+                        "Failed to load orders account for market {}: {}",
+                        market,
+                        e.to_string()
+                    );
+                    return;
+                }
+            };
+        let master_account_state =
+            match get_cypher_account(&self.rpc_client, &accounts.master_account).await {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!(
+                        "Failed to load master account {}: {}",
+                        accounts.master_account,
+                        e.to_string()
+                    );
+                    return;
+                }
+            };
+        let open_orders = orders_account_ctx.state.get_orders();
+        let ixs = get_cancel_orders_ixs(
+            &accounts,
+            &master_account_state,
+            &open_orders,
+            &CancelOrderFilter::default(),
+        );
+        if ixs.is_empty() {
+            return;
+        }
+        match send_transactions(
+            &self.rpc_client,
+            ixs,
+            &self.signer,
+            true,
+            Some((KILL_SWITCH_COMPUTE_UNITS, 1)),
+            None,
+        )
+        .await
+        {
+            Ok(s) => {
+                info!(
+                    "Cancelled {} orders on market {} - Signatures: {:?}",
+                    open_orders.len(),
+                    market,
+                    s
+                );
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to cancel orders on market {}: {}",
+                    market,
+                    e.to_string()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use cypher_client::{
+    utils::{convert_coin_to_lots_fixed, convert_price_to_lots_fixed},
+    AgnosticMarket, DerivativeOrderType, NewDerivativeOrderArgs, Side,
+};
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::services::MarketParams;
+
+use super::debug_assert_order_units;
+
+/// The number of basis points in one unit.
+const BPS_UNIT: u64 = 10_000;
+
+/// The configuration of a quoting ladder.
+#[derive(Debug, Clone)]
+pub struct LadderConfig {
+    /// The spread between the best bid and the best ask, in bps of the mid price.
+    pub spread_bps: u64,
+    /// The distance between consecutive levels on the same side, in bps of the mid price.
+    pub level_spacing_bps: u64,
+    /// The number of levels on each side.
+    pub levels: usize,
+    /// The size of each level, in base token units, starting at the level closest to the mid price.
+    ///
+    /// If there are fewer sizes than levels, the last size is used for the remaining levels.
+    pub sizes: Vec<I80F48>,
+    /// The order type of every order.
+    pub order_type: DerivativeOrderType,
+    /// The client order id of the first order, subsequent orders are numbered sequentially, bids first.
+    pub client_order_id: u64,
+    /// The unix timestamp after which the orders expire.
+    pub max_ts: u64,
+}
+
+impl Default for LadderConfig {
+    fn default() -> Self {
+        Self {
+            spread_bps: 10,
+            level_spacing_bps: 5,
+            levels: 1,
+            sizes: Vec::new(),
+            order_type: DerivativeOrderType::PostOnly,
+            client_order_id: 0,
+            max_ts: u64::MAX,
+        }
+    }
+}
+
+impl LadderConfig {
+    /// Gets the size of the level at the given index, in base token units.
+    pub fn get_size(&self, level: usize) -> I80F48 {
+        match self.sizes.get(level) {
+            Some(s) => *s,
+            None => self.sizes.last().copied().unwrap_or(I80F48::ZERO),
+        }
+    }
+}
+
+/// Generates a quoting ladder around the given mid price, in the market's quote token units per base token unit.
+///
+/// Prices are converted to lots and aligned to the market's tick size away from the mid price, i.e. bids are
+/// rounded down and asks up. Sizes are converted to lots and clamped to the market's base and quote order size limits,
+/// levels whose price or size is zero after conversion are skipped.
+/// In debug builds every order is cross-checked against the market's configuration, see [`debug_assert_order_units`].
+pub fn get_quoting_ladder(
+    params: &MarketParams,
+    decimals: u8,
+    mid_price: I80F48,
+    config: &LadderConfig,
+) -> Vec<NewDerivativeOrderArgs> {
+    let mut orders = Vec::with_capacity(config.levels * 2);
+    let coin_decimals_factor = 10u64.pow(decimals as u32);
+    let mut client_order_id = config.client_order_id;
+
+    for side in [Side::Bid, Side::Ask] {
+        for level in 0..config.levels {
+            let offset_bps = config.spread_bps / 2 + config.level_spacing_bps * level as u64;
+            let offset = mid_price * I80F48::from(offset_bps) / I80F48::from(BPS_UNIT);
+            let price = match side {
+                Side::Bid => mid_price - offset,
+                Side::Ask => mid_price + offset,
+            };
+            if !price.is_positive() {
+                continue;
+            }
+            let price_lots = convert_price_to_lots_fixed(
+                price,
+                params.base_multiplier,
+                coin_decimals_factor,
+                params.quote_multiplier,
+            );
+            let limit_price = match side {
+                Side::Bid => params.round_price_down(price_lots),
+                Side::Ask => params.round_price_up(price_lots),
+            };
+            if limit_price == 0 {
+                continue;
+            }
+
+            let size = config.get_size(level) * I80F48::from(coin_decimals_factor);
+            let mut max_base_qty = params
+                .clamp_base_order_size(convert_coin_to_lots_fixed(size, params.base_multiplier));
+            if params.max_quote_order_size != 0 {
+                max_base_qty = params.clamp_base_order_size(
+                    max_base_qty.min(params.max_quote_order_size / limit_price),
+                );
+            }
+            if max_base_qty == 0 {
+                continue;
+            }
+            let max_quote_qty = match max_base_qty.checked_mul(limit_price) {
+                Some(q) if params.max_quote_order_size != 0 => q.min(params.max_quote_order_size),
+                Some(q) => q,
+                None => u64::MAX,
+            };
+
+            let order = NewDerivativeOrderArgs {
+                side,
+                limit_price,
+                max_base_qty,
+                max_quote_qty,
+                order_type: config.order_type,
+                client_order_id,
+                limit: u16::MAX,
+                max_ts: config.max_ts,
+            };
+            debug_assert_order_units(params, decimals, &order, price, config.get_size(level));
+            orders.push(order);
+            client_order_id = client_order_id.wrapping_add(1);
+        }
+    }
+
+    orders
+}
+
+/// Generates a quoting ladder for the given market, see [`get_quoting_ladder`].
+pub fn get_market_quoting_ladder(
+    market: &Pubkey,
+    state: &AgnosticMarket,
+    mid_price: I80F48,
+    config: &LadderConfig,
+) -> Vec<NewDerivativeOrderArgs> {
+    get_quoting_ladder(
+        &MarketParams::new(market, state),
+        state.config.decimals,
+        mid_price,
+        config,
+    )
+}
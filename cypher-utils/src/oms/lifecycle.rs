@@ -0,0 +1,297 @@
+use solana_sdk::{
+    clock::{Clock, DEFAULT_MS_PER_SLOT},
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use std::{collections::HashMap, time::Duration};
+
+use super::{JournalEntry, JournalRecord};
+
+/// Estimates the unix timestamps of slots from a reference [`Clock`], assuming a constant slot duration.
+///
+/// The estimate drifts as the actual slot times deviate from the assumed duration, so the reference
+/// should be refreshed regularly, e.g. from the [`ClockService`](crate::services::ClockService).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotClock {
+    /// The slot of the reference clock.
+    pub reference_slot: u64,
+    /// The unix timestamp of the reference clock, in seconds.
+    pub reference_timestamp: i64,
+    /// The assumed duration of a slot, in milliseconds.
+    pub ms_per_slot: u64,
+}
+
+impl SlotClock {
+    /// Creates a new [`SlotClock`] from the given reference [`Clock`] with the default slot duration.
+    pub fn new(clock: &Clock) -> Self {
+        Self::new_with_ms_per_slot(clock, DEFAULT_MS_PER_SLOT)
+    }
+
+    /// Creates a new [`SlotClock`] from the given reference [`Clock`] and slot duration in milliseconds.
+    pub fn new_with_ms_per_slot(clock: &Clock, ms_per_slot: u64) -> Self {
+        Self {
+            reference_slot: clock.slot,
+            reference_timestamp: clock.unix_timestamp,
+            ms_per_slot,
+        }
+    }
+
+    /// Gets the estimated unix timestamp of the given slot, in milliseconds.
+    pub fn get_timestamp_millis(&self, slot: u64) -> i64 {
+        let slots = slot as i64 - self.reference_slot as i64;
+        self.reference_timestamp * 1000 + slots * self.ms_per_slot as i64
+    }
+
+    /// Gets the estimated duration between the given slots, zero if `to_slot` is before `from_slot`.
+    pub fn get_duration(&self, from_slot: u64, to_slot: u64) -> Duration {
+        Duration::from_millis(to_slot.saturating_sub(from_slot) * self.ms_per_slot)
+    }
+}
+
+/// A fill of an order, at the slot it was observed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleFill {
+    /// The slot at which the fill was observed, see [`super::StrategyFill::slot`], or zero if it is not known.
+    pub slot: u64,
+    /// The price.
+    pub price: u64,
+    /// The base quantity.
+    pub base_quantity: u64,
+}
+
+/// The slots at which an order went through each stage of it's lifecycle, as recorded in the OMS journal.
+///
+/// Slots which were not recorded, or recorded as zero because they were not known, are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderLifecycle {
+    /// The market.
+    pub market: Pubkey,
+    /// The client order id.
+    pub client_order_id: u64,
+    /// The order id the order was acknowledged under.
+    pub order_id: Option<u128>,
+    /// The signature of the transaction which placed the order.
+    pub signature: Option<Signature>,
+    /// The slot at which the OMS decided to place the order.
+    pub intent_slot: Option<u64>,
+    /// The slot at which the transaction placing the order was submitted.
+    pub submission_slot: Option<u64>,
+    /// The slot at which the transaction placing the order landed, i.e. the order was acknowledged.
+    pub landed_slot: Option<u64>,
+    /// The fills of the order, ordered from oldest to newest.
+    pub fills: Vec<LifecycleFill>,
+    /// The slot at which the order was completely filled, cancelled or rejected.
+    pub closed_slot: Option<u64>,
+    /// Whether the order was completely filled, cancelled or rejected.
+    pub closed: bool,
+    /// Whether the order was rejected.
+    pub rejected: bool,
+}
+
+impl OrderLifecycle {
+    fn new(market: Pubkey, client_order_id: u64) -> Self {
+        Self {
+            market,
+            client_order_id,
+            order_id: None,
+            signature: None,
+            intent_slot: None,
+            submission_slot: None,
+            landed_slot: None,
+            fills: Vec::new(),
+            closed_slot: None,
+            closed: false,
+            rejected: false,
+        }
+    }
+
+    /// The slot of the first fill, if any.
+    pub fn first_fill_slot(&self) -> Option<u64> {
+        self.fills.iter().map(|f| f.slot).find(|s| *s != 0)
+    }
+
+    /// The number of slots between the submission of the order and it landing.
+    pub fn slots_to_land(&self) -> Option<u64> {
+        slots_between(self.submission_slot, self.landed_slot)
+    }
+
+    /// The number of slots between the order landing and it's first fill.
+    ///
+    /// This is the time the order rested on the book before being hit, which together with the landed slot of other
+    /// orders at the same price allows reasoning about time priority.
+    pub fn slots_to_first_fill(&self) -> Option<u64> {
+        slots_between(self.landed_slot, self.first_fill_slot())
+    }
+
+    /// The number of slots between the OMS deciding to place the order and it landing.
+    pub fn slots_from_intent_to_land(&self) -> Option<u64> {
+        slots_between(self.intent_slot, self.landed_slot)
+    }
+
+    /// The number of slots the order was open on the book for, if it has been closed.
+    pub fn slots_open(&self) -> Option<u64> {
+        slots_between(self.landed_slot, self.closed_slot)
+    }
+
+    /// The estimated duration between the submission of the order and it landing, see [`SlotClock`].
+    pub fn get_landing_latency(&self, clock: &SlotClock) -> Option<Duration> {
+        match (self.submission_slot, self.landed_slot) {
+            (Some(from), Some(to)) => Some(clock.get_duration(from, to)),
+            _ => None,
+        }
+    }
+
+    /// The estimated duration between the order landing and it's first fill, see [`SlotClock`].
+    pub fn get_time_to_first_fill(&self, clock: &SlotClock) -> Option<Duration> {
+        match (self.landed_slot, self.first_fill_slot()) {
+            (Some(from), Some(to)) => Some(clock.get_duration(from, to)),
+            _ => None,
+        }
+    }
+
+    /// Whether the order is still open, i.e. it was not completely filled, cancelled or rejected.
+    pub fn is_open(&self) -> bool {
+        !self.closed
+    }
+
+    fn close(&mut self, slot: Option<u64>) {
+        self.closed = true;
+        self.closed_slot = slot;
+    }
+}
+
+fn known_slot(slot: u64) -> Option<u64> {
+    if slot == 0 {
+        None
+    } else {
+        Some(slot)
+    }
+}
+
+fn slots_between(from: Option<u64>, to: Option<u64>) -> Option<u64> {
+    match (from, to) {
+        (Some(from), Some(to)) => Some(to.saturating_sub(from)),
+        _ => None,
+    }
+}
+
+/// Folds the given records into the lifecycle of every order they mention, in the order the orders were placed.
+///
+/// A client order id which is reused after the previous order with it was closed starts a new lifecycle.
+pub fn get_order_lifecycles(records: &[JournalRecord]) -> Vec<OrderLifecycle> {
+    let mut lifecycles = OrderLifecycles::default();
+
+    for record in records.iter() {
+        let slot = known_slot(record.slot);
+        match &record.entry {
+            JournalEntry::Intent {
+                market,
+                client_order_id,
+                ..
+            } => {
+                let lifecycle = lifecycles.get_new(market, *client_order_id);
+                lifecycle.intent_slot = slot;
+            }
+            JournalEntry::Submission {
+                market,
+                client_order_ids,
+                signature,
+            } => {
+                for client_order_id in client_order_ids.iter() {
+                    let lifecycle = lifecycles.get(market, *client_order_id);
+                    // a resubmission supersedes the previous submission
+                    lifecycle.submission_slot = slot;
+                    lifecycle.signature = Some(*signature);
+                }
+            }
+            JournalEntry::Ack {
+                market,
+                client_order_id,
+                order_id,
+            } => {
+                let lifecycle = lifecycles.get(market, *client_order_id);
+                lifecycle.order_id = Some(*order_id);
+                lifecycle.landed_slot = slot;
+            }
+            JournalEntry::Fill {
+                market,
+                client_order_id,
+                price,
+                base_quantity,
+                completed,
+            } => {
+                let lifecycle = lifecycles.get(market, *client_order_id);
+                lifecycle.fills.push(LifecycleFill {
+                    slot: record.slot,
+                    price: *price,
+                    base_quantity: *base_quantity,
+                });
+                if *completed {
+                    lifecycle.close(slot);
+                }
+            }
+            JournalEntry::Cancel {
+                market,
+                client_order_id,
+            } => {
+                lifecycles.get(market, *client_order_id).close(slot);
+            }
+            JournalEntry::Rejected {
+                market,
+                client_order_id,
+                ..
+            } => {
+                let lifecycle = lifecycles.get(market, *client_order_id);
+                lifecycle.rejected = true;
+                lifecycle.close(slot);
+            }
+        }
+    }
+
+    lifecycles.lifecycles
+}
+
+/// The lifecycles being folded by [`get_order_lifecycles`].
+#[derive(Default)]
+struct OrderLifecycles {
+    lifecycles: Vec<OrderLifecycle>,
+    // the index of the latest lifecycle of each order
+    latest: HashMap<(Pubkey, u64), usize>,
+}
+
+impl OrderLifecycles {
+    /// Gets the latest lifecycle of the given order, starting one if there is none.
+    fn get(&mut self, market: &Pubkey, client_order_id: u64) -> &mut OrderLifecycle {
+        match self.latest.get(&(*market, client_order_id)).copied() {
+            Some(idx) => &mut self.lifecycles[idx],
+            None => self.push(market, client_order_id),
+        }
+    }
+
+    /// Gets the lifecycle of a newly placed order, starting a new one unless the latest one
+    /// is open and was only started by entries recorded ahead of the intent.
+    fn get_new(&mut self, market: &Pubkey, client_order_id: u64) -> &mut OrderLifecycle {
+        let idx = self
+            .latest
+            .get(&(*market, client_order_id))
+            .copied()
+            .filter(|idx| {
+                let lifecycle = &self.lifecycles[*idx];
+                lifecycle.is_open()
+                    && lifecycle.intent_slot.is_none()
+                    && lifecycle.submission_slot.is_none()
+            });
+        match idx {
+            Some(idx) => &mut self.lifecycles[idx],
+            None => self.push(market, client_order_id),
+        }
+    }
+
+    fn push(&mut self, market: &Pubkey, client_order_id: u64) -> &mut OrderLifecycle {
+        self.lifecycles
+            .push(OrderLifecycle::new(*market, client_order_id));
+        self.latest
+            .insert((*market, client_order_id), self.lifecycles.len() - 1);
+        self.lifecycles.last_mut().unwrap()
+    }
+}
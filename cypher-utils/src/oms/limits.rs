@@ -0,0 +1,301 @@
+use cypher_client::{NewDerivativeOrderArgs, Side};
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, fmt::Display, fs, path::Path, str::FromStr};
+
+use super::{OmsError, StrategyId};
+
+/// The number of seconds in a day, used to reset the daily loss.
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The number of basis points in one unit.
+const BPS_UNIT: u128 = 10_000;
+
+/// Client-side limits enforced on every order before it's instruction is built.
+///
+/// Limits that are not set are not enforced.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RiskLimits {
+    /// The maximum base quantity of a single order, in lots.
+    pub max_order_base_qty: Option<u64>,
+    /// The maximum quote quantity of a single order, in lots.
+    pub max_order_quote_qty: Option<u64>,
+    /// The maximum absolute base position, in lots, including the order being placed.
+    pub max_position: Option<u64>,
+    /// The maximum loss per UTC day, in native quote units.
+    pub max_daily_loss: Option<u64>,
+    /// The maximum deviation of an order's limit price from the reference price, in bps.
+    pub price_collar_bps: Option<u64>,
+}
+
+impl RiskLimits {
+    fn set(&mut self, key: &str, value: u64) -> bool {
+        match key {
+            "max_order_base_qty" => self.max_order_base_qty = Some(value),
+            "max_order_quote_qty" => self.max_order_quote_qty = Some(value),
+            "max_position" => self.max_position = Some(value),
+            "max_daily_loss" => self.max_daily_loss = Some(value),
+            "price_collar_bps" => self.price_collar_bps = Some(value),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Display for RiskLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = [
+            ("max_order_base_qty", self.max_order_base_qty),
+            ("max_order_quote_qty", self.max_order_quote_qty),
+            ("max_position", self.max_position),
+            ("max_daily_loss", self.max_daily_loss),
+            ("price_collar_bps", self.price_collar_bps),
+        ];
+        for (key, value) in fields.iter() {
+            if let Some(v) = value {
+                write!(f, " {}={}", key, v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The risk limits of a strategy, with optional per market overrides.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StrategyRiskLimits {
+    /// The limits applied to markets without an override.
+    pub default: RiskLimits,
+    /// The limits applied to specific markets.
+    pub markets: HashMap<Pubkey, RiskLimits>,
+}
+
+impl StrategyRiskLimits {
+    /// Gets the limits applied to the given market.
+    pub fn get_limits(&self, market: &Pubkey) -> &RiskLimits {
+        self.markets.get(market).unwrap_or(&self.default)
+    }
+}
+
+/// The state of an order that should be checked against the [`RiskLimits`].
+#[derive(Debug, Clone, Copy)]
+pub struct OrderRiskCheck<'a> {
+    /// The market.
+    pub market: &'a Pubkey,
+    /// The order.
+    pub args: &'a NewDerivativeOrderArgs,
+    /// The strategy's current base position in the market, in lots.
+    pub base_position: i64,
+    /// The reference price used for the price collar, as an FP32 lot price, e.g. the oracle or mid price.
+    pub reference_price: u64,
+}
+
+/// The [`RiskLimits`] of every strategy along with the state needed to enforce them.
+#[derive(Debug, Default, Clone)]
+pub struct RiskLimitsRegistry {
+    strategies: HashMap<StrategyId, StrategyRiskLimits>,
+    daily_pnl: HashMap<StrategyId, (u64, I80F48)>,
+}
+
+impl RiskLimitsRegistry {
+    /// Creates a new, empty, [`RiskLimitsRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the risk limits of the given strategy.
+    pub fn set_limits(&mut self, strategy: StrategyId, limits: StrategyRiskLimits) {
+        self.strategies.insert(strategy, limits);
+    }
+
+    /// Gets the risk limits of the given strategy.
+    pub fn get_limits(&self, strategy: StrategyId) -> Option<&StrategyRiskLimits> {
+        self.strategies.get(&strategy)
+    }
+
+    /// Records realized or unrealized pnl, in native quote units, for the given strategy at the given unix timestamp.
+    ///
+    /// The pnl is accumulated per UTC day and reset on the first record of a new day.
+    pub fn record_pnl(&mut self, strategy: StrategyId, pnl: I80F48, timestamp: u64) {
+        let day = timestamp / SECONDS_PER_DAY;
+        let entry = self
+            .daily_pnl
+            .entry(strategy)
+            .or_insert((day, I80F48::ZERO));
+        if entry.0 != day {
+            *entry = (day, I80F48::ZERO);
+        }
+        entry.1 = entry.1.saturating_add(pnl);
+    }
+
+    /// Gets the pnl of the given strategy for the UTC day of the given unix timestamp.
+    pub fn get_daily_pnl(&self, strategy: StrategyId, now: u64) -> I80F48 {
+        match self.daily_pnl.get(&strategy) {
+            Some((day, pnl)) if *day == now / SECONDS_PER_DAY => *pnl,
+            _ => I80F48::ZERO,
+        }
+    }
+
+    /// Checks the given order against the risk limits of the given strategy at the given unix timestamp.
+    ///
+    /// Orders of strategies without risk limits are always accepted.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error describing the first limit the order breaches.
+    pub fn check_order(
+        &self,
+        strategy: StrategyId,
+        order: &OrderRiskCheck,
+        now: u64,
+    ) -> Result<(), OmsError> {
+        let limits = match self.strategies.get(&strategy) {
+            Some(l) => l.get_limits(order.market),
+            None => return Ok(()),
+        };
+        let args = order.args;
+
+        if let Some(max) = limits.max_order_base_qty {
+            if args.max_base_qty > max {
+                return Err(OmsError::OrderSizeExceeded(args.max_base_qty, max));
+            }
+        }
+        if let Some(max) = limits.max_order_quote_qty {
+            if args.max_quote_qty > max {
+                return Err(OmsError::OrderSizeExceeded(args.max_quote_qty, max));
+            }
+        }
+        if let Some(max) = limits.max_position {
+            let qty = args.max_base_qty.min(i64::MAX as u64) as i64;
+            let resulting_position = if args.side == Side::Bid {
+                order.base_position.saturating_add(qty)
+            } else {
+                order.base_position.saturating_sub(qty)
+            };
+            // orders that reduce the position are always allowed
+            if resulting_position.unsigned_abs() > max
+                && resulting_position.unsigned_abs() > order.base_position.unsigned_abs()
+            {
+                return Err(OmsError::PositionLimitExceeded(
+                    resulting_position.unsigned_abs(),
+                    max,
+                ));
+            }
+        }
+        if let Some(max) = limits.max_daily_loss {
+            let pnl = self.get_daily_pnl(strategy, now);
+            if pnl.is_negative() && pnl.abs() >= I80F48::from(max) {
+                return Err(OmsError::DailyLossLimitExceeded(pnl, max));
+            }
+        }
+        if let Some(bps) = limits.price_collar_bps {
+            if order.reference_price != 0 {
+                let deviation = (args.limit_price as i128 - order.reference_price as i128)
+                    .unsigned_abs()
+                    * BPS_UNIT
+                    / order.reference_price as u128;
+                if deviation > bps as u128 {
+                    return Err(OmsError::PriceOutsideCollar(
+                        args.limit_price,
+                        order.reference_price,
+                        bps,
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads the [`RiskLimitsRegistry`] from the given file.
+    ///
+    /// Each line of the file holds the limits of a strategy for a market, or `*` for the strategy's default limits,
+    /// e.g. `strategy=1 market=* max_order_base_qty=100 price_collar_bps=250`.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be read or contains invalid limits.
+    pub fn load(path: &Path) -> Result<Self, OmsError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(OmsError::Io(e));
+            }
+        };
+        contents.parse::<Self>()
+    }
+
+    /// Saves the risk limits to the given file, see [`RiskLimitsRegistry::load`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be written.
+    pub fn save(&self, path: &Path) -> Result<(), OmsError> {
+        match fs::write(path, self.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(OmsError::Io(e)),
+        }
+    }
+}
+
+impl Display for RiskLimitsRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut strategies = self.strategies.iter().collect::<Vec<_>>();
+        strategies.sort_by_key(|(id, _)| **id);
+        for (id, limits) in strategies {
+            writeln!(f, "strategy={} market=*{}", id, limits.default)?;
+            for (market, market_limits) in limits.markets.iter() {
+                writeln!(f, "strategy={} market={}{}", id, market, market_limits)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RiskLimitsRegistry {
+    type Err = OmsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |line: &str| OmsError::InvalidRiskLimits(line.to_string());
+        let mut registry = Self::new();
+
+        for line in s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        {
+            let mut strategy = None;
+            let mut market = None;
+            let mut limits = RiskLimits::default();
+            for token in line.split_whitespace() {
+                let (key, value) = token.split_once('=').ok_or_else(|| invalid(line))?;
+                match key {
+                    "strategy" => {
+                        strategy = Some(value.parse::<StrategyId>().map_err(|_| invalid(line))?)
+                    }
+                    "market" if value == "*" => market = Some(None),
+                    "market" => {
+                        market = Some(Some(Pubkey::from_str(value).map_err(|_| invalid(line))?))
+                    }
+                    _ => {
+                        let value = value.parse::<u64>().map_err(|_| invalid(line))?;
+                        if !limits.set(key, value) {
+                            return Err(invalid(line));
+                        }
+                    }
+                }
+            }
+            let strategy_limits = registry
+                .strategies
+                .entry(strategy.ok_or_else(|| invalid(line))?)
+                .or_default();
+            match market.ok_or_else(|| invalid(line))? {
+                Some(m) => {
+                    strategy_limits.markets.insert(m, limits);
+                }
+                None => strategy_limits.default = limits,
+            }
+        }
+
+        Ok(registry)
+    }
+}
@@ -0,0 +1,155 @@
+use cypher_client::{CypherAccount, NewDerivativeOrderArgs};
+use fixed::types::I80F48;
+use log::warn;
+
+use crate::services::MarketParams;
+
+/// The configuration of the [`NetLiquidityConstraint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetLiquidityConfig {
+    /// The maximum total quoted notional across all markets, as a multiple of the account equity.
+    pub max_notional_to_equity: I80F48,
+    /// The init c-ratio at or above which the full notional budget is available.
+    pub full_c_ratio: I80F48,
+    /// The init c-ratio at or below which nothing is quoted, e.g. the clearing's init margin ratio plus a buffer.
+    ///
+    /// Between this and [`NetLiquidityConfig::full_c_ratio`] the budget shrinks linearly.
+    pub min_c_ratio: I80F48,
+}
+
+/// The margin state of the account shared by all quoted markets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NetLiquidityState {
+    /// The init weighted assets value of the account, in native quote units.
+    pub assets_value: I80F48,
+    /// The init weighted liabilities value of the account, in native quote units.
+    pub liabilities_value: I80F48,
+}
+
+impl NetLiquidityState {
+    /// Creates the [`NetLiquidityState`] from the cached values of the account's cross margined sub accounts,
+    /// as of their last on-chain update.
+    pub fn from_account(account: &CypherAccount) -> Self {
+        Self {
+            assets_value: account.get_assets_value(),
+            liabilities_value: account.get_liabilities_value(),
+        }
+    }
+
+    /// The equity of the account, in native quote units.
+    pub fn equity(&self) -> I80F48 {
+        self.assets_value - self.liabilities_value
+    }
+
+    /// The c-ratio of the account.
+    pub fn c_ratio(&self) -> I80F48 {
+        if self.liabilities_value == I80F48::ZERO {
+            I80F48::MAX
+        } else {
+            self.assets_value.saturating_div(self.liabilities_value)
+        }
+    }
+}
+
+/// The quotes of a market, subject to the [`NetLiquidityConstraint`].
+#[derive(Debug, Clone)]
+pub struct MarketQuotes {
+    /// The parameters of the market.
+    pub params: MarketParams,
+    /// The orders quoted on the market, e.g. generated with [`super::get_quoting_ladder`].
+    pub orders: Vec<NewDerivativeOrderArgs>,
+}
+
+impl MarketQuotes {
+    /// The total notional of the quoted orders, in native quote units.
+    pub fn notional(&self) -> I80F48 {
+        self.orders
+            .iter()
+            .map(|o| get_order_notional(&self.params, o))
+            .sum()
+    }
+}
+
+/// Gets the notional of the given order, in native quote units.
+pub fn get_order_notional(params: &MarketParams, order: &NewDerivativeOrderArgs) -> I80F48 {
+    I80F48::from(order.limit_price)
+        * I80F48::from(order.max_base_qty)
+        * I80F48::from(params.quote_multiplier)
+}
+
+/// A constraint which caps the total notional quoted across all markets sharing one margin account,
+/// relative to the account's equity and current init c-ratio.
+///
+/// Quoting each market as if it had the whole account's margin to itself over-commits the account as soon as
+/// several ladders are filled at once. This constraint shrinks every ladder proportionally so the sum of all
+/// quotes fits the budget, which itself shrinks as the c-ratio approaches the minimum.
+#[derive(Debug, Clone, Copy)]
+pub struct NetLiquidityConstraint {
+    config: NetLiquidityConfig,
+}
+
+impl NetLiquidityConstraint {
+    /// Creates a new [`NetLiquidityConstraint`].
+    pub fn new(config: NetLiquidityConfig) -> Self {
+        Self { config }
+    }
+
+    /// Gets the fraction of the notional budget available at the given c-ratio, between zero and one.
+    pub fn get_budget_factor(&self, c_ratio: I80F48) -> I80F48 {
+        if c_ratio >= self.config.full_c_ratio {
+            return I80F48::ONE;
+        }
+        if c_ratio <= self.config.min_c_ratio {
+            return I80F48::ZERO;
+        }
+        (c_ratio - self.config.min_c_ratio) / (self.config.full_c_ratio - self.config.min_c_ratio)
+    }
+
+    /// Gets the total notional that can be quoted across all markets, in native quote units.
+    pub fn get_notional_budget(&self, state: &NetLiquidityState) -> I80F48 {
+        let equity = state.equity();
+        if !equity.is_positive() {
+            return I80F48::ZERO;
+        }
+        equity.saturating_mul(self.config.max_notional_to_equity)
+            * self.get_budget_factor(state.c_ratio())
+    }
+
+    /// Shrinks the given quotes so that their total notional fits within the budget of the given state.
+    ///
+    /// Every order's base quantity is scaled by the same factor and clamped to it's market's order size limits,
+    /// orders smaller than the market's minimum order size are removed.
+    /// Returns the factor the quotes were scaled by, which is one if they already fit within the budget.
+    pub fn apply(&self, state: &NetLiquidityState, quotes: &mut [MarketQuotes]) -> I80F48 {
+        let budget = self.get_notional_budget(state);
+        let total_notional = quotes.iter().map(|q| q.notional()).sum::<I80F48>();
+        if total_notional <= budget {
+            return I80F48::ONE;
+        }
+
+        let factor = budget / total_notional;
+        warn!(
+            "Quoted notional {} exceeds the budget {} at c-ratio {}, shrinking quotes by {}.",
+            total_notional,
+            budget,
+            state.c_ratio(),
+            factor
+        );
+        for market_quotes in quotes.iter_mut() {
+            let params = market_quotes.params;
+            market_quotes.orders.retain_mut(|o| {
+                let max_base_qty = params
+                    .clamp_base_order_size((I80F48::from(o.max_base_qty) * factor).to_num::<u64>());
+                if max_base_qty == 0 {
+                    return false;
+                }
+                o.max_base_qty = max_base_qty;
+                o.max_quote_qty = o
+                    .max_quote_qty
+                    .min(max_base_qty.saturating_mul(o.limit_price));
+                true
+            });
+        }
+        factor
+    }
+}
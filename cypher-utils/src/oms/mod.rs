@@ -0,0 +1,67 @@
+pub mod cancel;
+pub mod dead_man;
+pub mod expiry;
+pub mod flatten;
+#[cfg(feature = "storage")]
+pub mod journal;
+pub mod kill_switch;
+pub mod ladder;
+#[cfg(feature = "storage")]
+pub mod lifecycle;
+pub mod limits;
+pub mod liquidity;
+pub mod paper;
+pub mod reconcile;
+pub mod router;
+pub mod startup;
+pub mod units;
+
+pub use cancel::*;
+pub use dead_man::*;
+pub use expiry::*;
+pub use flatten::*;
+#[cfg(feature = "storage")]
+pub use journal::*;
+pub use kill_switch::*;
+pub use ladder::*;
+#[cfg(feature = "storage")]
+pub use lifecycle::*;
+pub use limits::*;
+pub use liquidity::*;
+pub use paper::*;
+pub use reconcile::*;
+pub use router::*;
+pub use startup::*;
+pub use units::*;
+
+use fixed::types::I80F48;
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OmsError {
+    #[error("Strategy {0} is already registered.")]
+    StrategyAlreadyRegistered(u64),
+    #[error("Client order ids of strategy {0} overlap with strategy {1}.")]
+    OverlappingClientIds(u64, u64),
+    #[error("Order size {0} exceeds the maximum order size {1}.")]
+    OrderSizeExceeded(u64, u64),
+    #[error("Resulting position {0} exceeds the maximum position {1}.")]
+    PositionLimitExceeded(u64, u64),
+    #[error("Daily pnl {0} exceeds the maximum daily loss {1}.")]
+    DailyLossLimitExceeded(I80F48, u64),
+    #[error("Limit price {0} is outside of the {2} bps collar around reference price {1}.")]
+    PriceOutsideCollar(u64, u64, u64),
+    #[error("Market {0} is not active until {1}.")]
+    MarketNotActive(Pubkey, u64),
+    #[error("Market {0} expires at {1}, only orders reducing a position are allowed.")]
+    MarketExpiring(Pubkey, u64),
+    #[error("Market {0} expired at {1}.")]
+    MarketExpired(Pubkey, u64),
+    #[error("Invalid risk limits: {0}")]
+    InvalidRiskLimits(String),
+    #[error("Order rejected: {0}")]
+    OrderRejected(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
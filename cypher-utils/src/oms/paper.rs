@@ -0,0 +1,399 @@
+use cypher_client::{DerivativeOrderType, NewDerivativeOrderArgs, Side};
+use dashmap::DashMap;
+use log::debug;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::contexts::{Fill, GenericOrderBook, Order};
+
+use super::{FillRouter, OmsError};
+
+/// The flag set on the ids of paper orders, which keeps them apart from the ids of orders on the book.
+pub const PAPER_ORDER_ID_FLAG: u128 = 1 << 127;
+
+/// An order resting in the [`PaperTradingEngine`].
+#[derive(Debug, Clone)]
+pub struct PaperOrder {
+    /// The paper order id.
+    pub order_id: u128,
+    /// The client order id.
+    pub client_order_id: u64,
+    /// The side.
+    pub side: Side,
+    /// The limit price, in the same units as the book's order prices.
+    pub price: u64,
+    /// The base quantity left to fill.
+    pub remaining_base_qty: u64,
+    /// The maximum timestamp at which it can be filled, zero if it does not expire.
+    pub max_ts: u64,
+}
+
+impl PaperOrder {
+    fn crosses(&self, price: u64) -> bool {
+        crosses(self.side, self.price, price)
+    }
+}
+
+/// The simulated position of a market in the [`PaperTradingEngine`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PaperPosition {
+    /// The base position, in base lots, positive if long.
+    pub base_position: i64,
+    /// The quote position, in quote lots, which decreases when buying and increases when selling.
+    pub quote_position: i64,
+    /// The total base quantity traded, in base lots.
+    pub base_volume: u64,
+}
+
+impl PaperPosition {
+    fn apply(&mut self, side: Side, fill: &Fill) {
+        let base = fill.base_quantity as i64;
+        let quote = fill.quote_quantity as i64;
+        if side == Side::Bid {
+            self.base_position += base;
+            self.quote_position -= quote;
+        } else {
+            self.base_position -= base;
+            self.quote_position += quote;
+        }
+        self.base_volume += fill.base_quantity;
+    }
+
+    /// Gets the profit and loss of the position marked at the given price, in quote lots.
+    pub fn get_pnl(&self, mark_price: u64) -> i64 {
+        self.quote_position + self.base_position * mark_price as i64
+    }
+}
+
+/// The result of placing an order in the [`PaperTradingEngine`].
+#[derive(Debug, Clone)]
+pub struct PaperOrderResult {
+    /// The paper order id.
+    pub order_id: u128,
+    /// The fills of the order's taker leg.
+    pub fills: Vec<Fill>,
+    /// The base quantity left resting on the paper book.
+    pub resting_base_qty: u64,
+}
+
+#[derive(Debug, Default)]
+struct PaperMarket {
+    orders: Vec<PaperOrder>,
+    position: PaperPosition,
+}
+
+/// A local fill engine for paper trading.
+///
+/// Orders are matched against the live book locally instead of being submitted, the resulting synthetic fills
+/// are published through the [`FillRouter`] so strategies receive them on the same channels as real fills.
+/// Synthetic fills carry the paper order id as the maker order id, regardless of which side provided liquidity.
+///
+/// Matching is optimistic in that liquidity taken by a paper order is not removed from the next book snapshot,
+/// while resting paper orders are only filled once the book or a trade moves through their price.
+pub struct PaperTradingEngine {
+    router: Arc<FillRouter>,
+    markets: DashMap<Pubkey, PaperMarket>,
+    next_order_id: AtomicU64,
+}
+
+impl std::fmt::Debug for PaperTradingEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PaperTradingEngine")
+            .field("markets", &self.markets.len())
+            .finish()
+    }
+}
+
+impl PaperTradingEngine {
+    /// Creates a new [`PaperTradingEngine`] which publishes fills through the given [`FillRouter`].
+    pub fn new(router: Arc<FillRouter>) -> Self {
+        Self {
+            router,
+            markets: DashMap::new(),
+            next_order_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Places the given order on the given market, matching it against the given book.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if a post only order would cross the book
+    /// or a fill or kill order can not be filled entirely, in which case nothing is filled.
+    pub fn place_order(
+        &self,
+        market: &Pubkey,
+        args: &NewDerivativeOrderArgs,
+        book: &dyn GenericOrderBook,
+    ) -> Result<PaperOrderResult, OmsError> {
+        let order_id =
+            PAPER_ORDER_ID_FLAG | self.next_order_id.fetch_add(1, Ordering::Relaxed) as u128;
+        let opposite = if args.side == Side::Bid {
+            book.get_asks()
+        } else {
+            book.get_bids()
+        };
+
+        if args.order_type == DerivativeOrderType::PostOnly
+            && opposite
+                .first()
+                .map_or(false, |o| crosses(args.side, args.limit_price, o.price))
+        {
+            return Err(OmsError::OrderRejected(format!(
+                "post only order {} would cross the book",
+                args.client_order_id
+            )));
+        }
+
+        let fills = if args.order_type == DerivativeOrderType::PostOnly {
+            Vec::new()
+        } else {
+            match_order(order_id, args, &opposite)
+        };
+        let filled_base_qty = fills.iter().map(|f| f.base_quantity).sum::<u64>();
+        if args.order_type == DerivativeOrderType::FillOrKill && filled_base_qty < args.max_base_qty
+        {
+            return Err(OmsError::OrderRejected(format!(
+                "fill or kill order {} can only fill {} of {}",
+                args.client_order_id, filled_base_qty, args.max_base_qty
+            )));
+        }
+
+        let remaining_base_qty = args.max_base_qty - filled_base_qty;
+        let rests = remaining_base_qty != 0
+            && (args.order_type == DerivativeOrderType::Limit
+                || args.order_type == DerivativeOrderType::PostOnly);
+
+        self.router.track_order(order_id, args.client_order_id);
+        {
+            let mut paper_market = self.markets.entry(*market).or_default();
+            for fill in fills.iter() {
+                paper_market.position.apply(args.side, fill);
+            }
+            if rests {
+                paper_market.orders.push(PaperOrder {
+                    order_id,
+                    client_order_id: args.client_order_id,
+                    side: args.side,
+                    price: args.limit_price,
+                    remaining_base_qty,
+                    max_ts: args.max_ts,
+                });
+            }
+        }
+        // paper fills never land on-chain, so they have no slot
+        self.router.route_fills(market, &fills, 0);
+        if !rests {
+            self.router.untrack_order(order_id);
+        }
+
+        Ok(PaperOrderResult {
+            order_id,
+            fills,
+            resting_base_qty: if rests { remaining_base_qty } else { 0 },
+        })
+    }
+
+    /// Cancels the resting paper order with the given client order id on the given market.
+    ///
+    /// Returns the cancelled order, if it was resting.
+    pub fn cancel_order(&self, market: &Pubkey, client_order_id: u64) -> Option<PaperOrder> {
+        let mut paper_market = self.markets.get_mut(market)?;
+        let idx = paper_market
+            .orders
+            .iter()
+            .position(|o| o.client_order_id == client_order_id)?;
+        let order = paper_market.orders.remove(idx);
+        self.router.untrack_order(order.order_id);
+        Some(order)
+    }
+
+    /// Cancels all resting paper orders on the given market, returning the cancelled orders.
+    pub fn cancel_all_orders(&self, market: &Pubkey) -> Vec<PaperOrder> {
+        let orders = match self.markets.get_mut(market) {
+            Some(mut m) => std::mem::take(&mut m.orders),
+            None => return Vec::new(),
+        };
+        for order in orders.iter() {
+            self.router.untrack_order(order.order_id);
+        }
+        orders
+    }
+
+    /// Fills the resting paper orders on the given market which the given book has moved through,
+    /// and expires the orders whose maximum timestamp is before the given unix timestamp.
+    ///
+    /// Returns the synthetic fills.
+    pub fn process_book(
+        &self,
+        market: &Pubkey,
+        book: &dyn GenericOrderBook,
+        now: u64,
+    ) -> Vec<Fill> {
+        let bids = book.get_bids();
+        let asks = book.get_asks();
+        self.process(market, now, |order| {
+            let opposite = if order.side == Side::Bid {
+                &asks
+            } else {
+                &bids
+            };
+            opposite
+                .iter()
+                .take_while(|o| order.crosses(o.price))
+                .map(|o| o.base_quantity)
+                .sum()
+        })
+    }
+
+    /// Fills the resting paper orders on the given market which the given public trades have traded through,
+    /// i.e. trades whose taker hit a price strictly better than the paper order's price.
+    ///
+    /// Returns the synthetic fills.
+    pub fn process_trades(&self, market: &Pubkey, trades: &[Fill], now: u64) -> Vec<Fill> {
+        self.process(market, now, |order| {
+            trades
+                .iter()
+                .filter(|t| {
+                    t.taker_side != order.side && order.crosses(t.price) && t.price != order.price
+                })
+                .map(|t| t.base_quantity)
+                .sum()
+        })
+    }
+
+    /// Gets the simulated position on the given market.
+    pub fn get_position(&self, market: &Pubkey) -> PaperPosition {
+        self.markets
+            .get(market)
+            .map(|m| m.position)
+            .unwrap_or_default()
+    }
+
+    /// Gets the resting paper orders on the given market.
+    pub fn get_open_orders(&self, market: &Pubkey) -> Vec<PaperOrder> {
+        self.markets
+            .get(market)
+            .map(|m| m.orders.clone())
+            .unwrap_or_default()
+    }
+
+    fn process<F>(&self, market: &Pubkey, now: u64, get_available_qty: F) -> Vec<Fill>
+    where
+        F: Fn(&PaperOrder) -> u64,
+    {
+        let mut fills = Vec::new();
+        let mut closed_orders = Vec::new();
+        {
+            let mut paper_market = match self.markets.get_mut(market) {
+                Some(m) => m,
+                None => return fills,
+            };
+            let PaperMarket { orders, position } = &mut *paper_market;
+            // better priced paper orders take the available liquidity first
+            orders.sort_by_key(|o| {
+                if o.side == Side::Bid {
+                    (0, u64::MAX - o.price)
+                } else {
+                    (1, o.price)
+                }
+            });
+            let mut consumed_bids = 0;
+            let mut consumed_asks = 0;
+
+            for order in orders.iter_mut() {
+                if order.max_ts != 0 && order.max_ts < now {
+                    debug!("Paper order {} expired.", order.client_order_id);
+                    order.remaining_base_qty = 0;
+                    closed_orders.push(order.order_id);
+                    continue;
+                }
+                let consumed = if order.side == Side::Bid {
+                    &mut consumed_bids
+                } else {
+                    &mut consumed_asks
+                };
+                let available = get_available_qty(order).saturating_sub(*consumed);
+                let base_quantity = available.min(order.remaining_base_qty);
+                if base_quantity == 0 {
+                    continue;
+                }
+                *consumed += base_quantity;
+                order.remaining_base_qty -= base_quantity;
+                let fill = Fill {
+                    base_quantity,
+                    quote_quantity: base_quantity * order.price,
+                    price: order.price,
+                    taker_side: opposite_side(order.side),
+                    maker_order_id: order.order_id,
+                    maker: None,
+                    taker: None,
+                };
+                position.apply(order.side, &fill);
+                fills.push(fill);
+                if order.remaining_base_qty == 0 {
+                    closed_orders.push(order.order_id);
+                }
+            }
+            orders.retain(|o| o.remaining_base_qty != 0);
+        }
+
+        self.router.route_fills(market, &fills, 0);
+        for order_id in closed_orders {
+            self.router.untrack_order(order_id);
+        }
+        fills
+    }
+}
+
+fn match_order(order_id: u128, args: &NewDerivativeOrderArgs, opposite: &[Order]) -> Vec<Fill> {
+    let mut fills = Vec::new();
+    let mut remaining_base_qty = args.max_base_qty;
+    let mut remaining_quote_qty = args.max_quote_qty;
+
+    for level in opposite.iter().take(args.limit as usize) {
+        if remaining_base_qty == 0 || !crosses(args.side, args.limit_price, level.price) {
+            break;
+        }
+        let mut base_quantity = remaining_base_qty.min(level.base_quantity);
+        if level.price != 0 {
+            base_quantity = base_quantity.min(remaining_quote_qty / level.price);
+        }
+        if base_quantity == 0 {
+            break;
+        }
+        let quote_quantity = base_quantity * level.price;
+        remaining_base_qty -= base_quantity;
+        remaining_quote_qty -= quote_quantity;
+        fills.push(Fill {
+            base_quantity,
+            quote_quantity,
+            price: level.price,
+            taker_side: args.side,
+            maker_order_id: order_id,
+            maker: None,
+            taker: None,
+        });
+    }
+
+    fills
+}
+
+fn crosses(side: Side, limit_price: u64, price: u64) -> bool {
+    if side == Side::Bid {
+        price <= limit_price
+    } else {
+        price >= limit_price
+    }
+}
+
+fn opposite_side(side: Side) -> Side {
+    if side == Side::Bid {
+        Side::Ask
+    } else {
+        Side::Bid
+    }
+}
@@ -0,0 +1,117 @@
+use cypher_client::{OpenOrder, Side};
+use log::debug;
+use std::collections::HashSet;
+
+use crate::contexts::{GenericOrderBook, Order};
+
+/// An order that is resting on the book and present in the [`OrdersAccount`](cypher_client::OrdersAccount).
+#[derive(Debug, Clone)]
+pub struct ReconciledOrder {
+    /// The open order as stored in the orders account.
+    pub open_order: OpenOrder,
+    /// The order as it is resting on the book.
+    pub book_order: Order,
+}
+
+/// The result of reconciling the [`OrdersAccount`](cypher_client::OrdersAccount) against the book.
+///
+/// While the event queue is backed up, orders that were filled or cancelled are no longer on the book
+/// but remain in the orders account until their events are consumed. Likewise, the book may be observed
+/// at a later slot than the orders account, in which case newly placed orders are on the book but not yet
+/// in the orders account.
+#[derive(Debug, Default, Clone)]
+pub struct OrdersReconciliation {
+    /// The orders present both in the orders account and on the book.
+    pub resting: Vec<ReconciledOrder>,
+    /// The orders present in the orders account but absent from the book,
+    /// these were filled or cancelled and are pending event consumption.
+    pub pending_consumption: Vec<OpenOrder>,
+    /// The orders known to belong to the user that are on the book but absent from the orders account.
+    pub missing_from_orders_account: Vec<Order>,
+}
+
+impl OrdersReconciliation {
+    /// Whether the orders account and the book agree with each other.
+    pub fn is_consistent(&self) -> bool {
+        self.pending_consumption.is_empty() && self.missing_from_orders_account.is_empty()
+    }
+
+    /// The base quantity actually resting on the book for the given side, in native units.
+    ///
+    /// Use this instead of the orders account's locked amounts to avoid double counting exposure
+    /// of orders that were already filled but whose events have not been consumed.
+    pub fn resting_base_quantity(&self, side: Side) -> u64 {
+        self.resting
+            .iter()
+            .filter(|o| o.book_order.side == side)
+            .map(|o| o.book_order.base_quantity)
+            .sum::<u64>()
+            + self
+                .missing_from_orders_account
+                .iter()
+                .filter(|o| o.side == side)
+                .map(|o| o.base_quantity)
+                .sum::<u64>()
+    }
+}
+
+/// Reconciles the given orders account open orders against the given order book.
+///
+/// The `known_order_ids` are the ids of the orders the caller knows to have been placed by the user,
+/// e.g. the orders acknowledged by the OMS, and are used to detect orders that are on the book but not
+/// yet in the orders account.
+pub fn reconcile_orders(
+    open_orders: &[&OpenOrder],
+    orderbook: &dyn GenericOrderBook,
+    known_order_ids: &[u128],
+) -> OrdersReconciliation {
+    let bids = orderbook.get_bids();
+    let asks = orderbook.get_asks();
+    let mut reconciliation = OrdersReconciliation::default();
+
+    let orders_account_ids = open_orders
+        .iter()
+        .filter(|o| o.order_id != u128::default())
+        .map(|o| o.order_id)
+        .collect::<HashSet<u128>>();
+
+    for open_order in open_orders.iter().filter(|o| o.order_id != u128::default()) {
+        let book_side = if open_order.side == Side::Bid {
+            &bids
+        } else {
+            &asks
+        };
+        match book_side.iter().find(|o| o.order_id == open_order.order_id) {
+            Some(book_order) => reconciliation.resting.push(ReconciledOrder {
+                open_order: **open_order,
+                book_order: book_order.clone(),
+            }),
+            None => reconciliation.pending_consumption.push(**open_order),
+        }
+    }
+
+    for order_id in known_order_ids
+        .iter()
+        .filter(|id| !orders_account_ids.contains(id))
+    {
+        if let Some(book_order) = bids
+            .iter()
+            .chain(asks.iter())
+            .find(|o| o.order_id == *order_id)
+        {
+            reconciliation
+                .missing_from_orders_account
+                .push(book_order.clone());
+        }
+    }
+
+    if !reconciliation.is_consistent() {
+        debug!(
+            "Orders account and book are inconsistent - Pending consumption: {} - Missing from orders account: {}",
+            reconciliation.pending_consumption.len(),
+            reconciliation.missing_from_orders_account.len()
+        );
+    }
+
+    reconciliation
+}
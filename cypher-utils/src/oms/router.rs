@@ -0,0 +1,244 @@
+use cypher_client::OrdersAccount;
+use dashmap::DashMap;
+use log::{debug, warn};
+use solana_sdk::pubkey::Pubkey;
+use std::{ops::RangeInclusive, sync::RwLock};
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+
+use crate::contexts::{Fill, GenericEventQueue};
+
+use super::{ClientIdPrefix, OmsError};
+
+/// The identifier of a strategy registered with the [`FillRouter`].
+pub type StrategyId = u64;
+
+/// Matches the client order ids owned by a strategy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientIdMatcher {
+    /// An inclusive range of client order ids.
+    Range(RangeInclusive<u64>),
+    /// A prefix of the client order id's most significant bits.
+    Prefix(ClientIdPrefix),
+}
+
+impl ClientIdMatcher {
+    /// Whether the given client order id is matched.
+    pub fn matches(&self, client_order_id: u64) -> bool {
+        match self {
+            ClientIdMatcher::Range(r) => r.contains(&client_order_id),
+            ClientIdMatcher::Prefix(p) => p.matches(client_order_id),
+        }
+    }
+
+    /// Gets the inclusive range of client order ids matched.
+    pub fn range(&self) -> RangeInclusive<u64> {
+        match self {
+            ClientIdMatcher::Range(r) => r.clone(),
+            ClientIdMatcher::Prefix(p) => {
+                if p.bits == 0 {
+                    return 0..=u64::MAX;
+                }
+                let shift = u64::BITS - p.bits;
+                let start = p.prefix << shift;
+                let end = start | (u64::MAX >> p.bits);
+                start..=end
+            }
+        }
+    }
+
+    /// Whether this matcher overlaps with the given matcher.
+    pub fn overlaps(&self, other: &ClientIdMatcher) -> bool {
+        let a = self.range();
+        let b = other.range();
+        a.start() <= b.end() && b.start() <= a.end()
+    }
+}
+
+/// A fill routed to the strategy that owns the order.
+#[derive(Debug, Clone)]
+pub struct StrategyFill {
+    /// The strategy that owns the order.
+    pub strategy: StrategyId,
+    /// The market.
+    pub market: Pubkey,
+    /// The client order id of the filled order.
+    pub client_order_id: u64,
+    /// The fill.
+    pub fill: Fill,
+    /// The slot at which the fill was observed, i.e. the slot of the event queue reload which yielded it,
+    /// see [`GenericEventQueue::get_slot`], or zero if it is not known.
+    pub slot: u64,
+}
+
+struct StrategyRoute {
+    strategy: StrategyId,
+    matcher: ClientIdMatcher,
+    sender: Sender<StrategyFill>,
+}
+
+/// Routes fills to the strategies that own the filled orders, allowing multiple strategies
+/// to share the same [`CypherAccount`](cypher_client::CypherAccount).
+///
+/// Strategies register the client order ids they use, the router then keeps track of the order ids
+/// assigned to those client order ids and publishes each fill on the owning strategy's channel.
+pub struct FillRouter {
+    routes: RwLock<Vec<StrategyRoute>>,
+    client_order_ids: DashMap<u128, u64>,
+    channel_capacity: usize,
+}
+
+impl Default for FillRouter {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl std::fmt::Debug for FillRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FillRouter")
+            .field(
+                "tracked_orders",
+                &format!("{}", self.client_order_ids.len()),
+            )
+            .finish()
+    }
+}
+
+impl FillRouter {
+    /// Creates a new [`FillRouter`] whose strategy channels have the given capacity.
+    pub fn new(channel_capacity: usize) -> Self {
+        Self {
+            routes: RwLock::new(Vec::new()),
+            client_order_ids: DashMap::new(),
+            channel_capacity,
+        }
+    }
+
+    /// Registers a strategy with the given client order id matcher.
+    ///
+    /// Returns a [`Receiver`] for the fills of the strategy's orders.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the strategy is already registered
+    /// or if the matcher overlaps with the matcher of another strategy.
+    pub fn register_strategy(
+        &self,
+        strategy: StrategyId,
+        matcher: ClientIdMatcher,
+    ) -> Result<Receiver<StrategyFill>, OmsError> {
+        let mut routes = self.routes.write().unwrap();
+        for route in routes.iter() {
+            if route.strategy == strategy {
+                return Err(OmsError::StrategyAlreadyRegistered(strategy));
+            }
+            if route.matcher.overlaps(&matcher) {
+                return Err(OmsError::OverlappingClientIds(strategy, route.strategy));
+            }
+        }
+        let (sender, receiver) = channel::<StrategyFill>(self.channel_capacity);
+        routes.push(StrategyRoute {
+            strategy,
+            matcher,
+            sender,
+        });
+        Ok(receiver)
+    }
+
+    /// Unregisters the given strategy, closing it's channel.
+    pub fn unregister_strategy(&self, strategy: StrategyId) {
+        self.routes
+            .write()
+            .unwrap()
+            .retain(|r| r.strategy != strategy);
+    }
+
+    /// Gets the strategy that owns the given client order id, if any.
+    pub fn get_strategy(&self, client_order_id: u64) -> Option<StrategyId> {
+        self.routes
+            .read()
+            .unwrap()
+            .iter()
+            .find(|r| r.matcher.matches(client_order_id))
+            .map(|r| r.strategy)
+    }
+
+    /// Tracks the client order id of the order with the given order id.
+    pub fn track_order(&self, order_id: u128, client_order_id: u64) {
+        self.client_order_ids.insert(order_id, client_order_id);
+    }
+
+    /// Stops tracking the order with the given order id.
+    pub fn untrack_order(&self, order_id: u128) {
+        self.client_order_ids.remove(&order_id);
+    }
+
+    /// Tracks the client order ids of all open orders in the given [`OrdersAccount`].
+    pub fn track_orders_account(&self, orders_account: &OrdersAccount) {
+        for order in orders_account.get_orders() {
+            self.track_order(order.order_id, order.client_order_id);
+        }
+    }
+
+    /// Routes the given fill, observed at the given slot, to the strategy that owns the maker order.
+    ///
+    /// Returns the strategy the fill was routed to, if the order is tracked and owned by a registered strategy.
+    pub fn route_fill(&self, market: &Pubkey, fill: &Fill, slot: u64) -> Option<StrategyId> {
+        let client_order_id = match self.client_order_ids.get(&fill.maker_order_id) {
+            Some(c) => *c,
+            None => return None,
+        };
+        let routes = self.routes.read().unwrap();
+        let route = match routes.iter().find(|r| r.matcher.matches(client_order_id)) {
+            Some(r) => r,
+            None => {
+                debug!(
+                    "No strategy registered for client order id {} of order {}.",
+                    client_order_id, fill.maker_order_id
+                );
+                return None;
+            }
+        };
+        match route.sender.send(StrategyFill {
+            strategy: route.strategy,
+            market: *market,
+            client_order_id,
+            fill: fill.clone(),
+            slot,
+        }) {
+            Ok(_) => Some(route.strategy),
+            Err(_) => {
+                warn!(
+                    "Strategy {} has no active receivers, dropping fill for order {}.",
+                    route.strategy, fill.maker_order_id
+                );
+                None
+            }
+        }
+    }
+
+    /// Routes all of the given fills, observed at the given slot, returning the number of fills routed.
+    pub fn route_fills(&self, market: &Pubkey, fills: &[Fill], slot: u64) -> usize {
+        fills
+            .iter()
+            .filter_map(|f| self.route_fill(market, f, slot))
+            .count()
+    }
+
+    /// Routes the fills of the given event queue since the given sequence number, see
+    /// [`GenericEventQueue::get_fills_since`], stamping them with the slot of the event queue's last reload.
+    ///
+    /// Returns the number of fills routed.
+    pub fn route_event_queue_fills(
+        &self,
+        market: &Pubkey,
+        event_queue: &dyn GenericEventQueue,
+        seq_num: u64,
+    ) -> usize {
+        self.route_fills(
+            market,
+            &event_queue.get_fills_since(seq_num),
+            event_queue.get_slot(),
+        )
+    }
+}
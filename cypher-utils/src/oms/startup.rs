@@ -0,0 +1,350 @@
+use cypher_client::{CypherAccount, CypherSubAccount, OpenOrder, OpenOrdersCache, Side};
+use fixed::types::I80F48;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    contexts::{
+        get_cypher_account, AgnosticEventQueueContext, ContextError, Fill, GenericEventQueue,
+        OrdersAccountContext,
+    },
+    scanners::get_sub_accounts,
+};
+
+/// An order the OMS expects to be open, e.g. as recovered from it's persisted journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedOrder {
+    /// The market.
+    pub market: Pubkey,
+    /// The index of the sub account the order was placed with.
+    pub sub_account_idx: u8,
+    /// The side.
+    pub side: Side,
+    /// The client order id.
+    pub client_order_id: u64,
+    /// The order id, if the order was acknowledged.
+    pub order_id: Option<u128>,
+}
+
+impl ExpectedOrder {
+    /// Whether the given open order is this order.
+    ///
+    /// Orders are matched by order id if it is known, otherwise by client order id, side and sub account.
+    pub fn matches(&self, open_order: &OpenOrder) -> bool {
+        match self.order_id {
+            Some(order_id) => open_order.order_id == order_id,
+            None => {
+                open_order.client_order_id == self.client_order_id
+                    && open_order.side == self.side
+                    && open_order.sub_account_idx == self.sub_account_idx
+            }
+        }
+    }
+}
+
+/// The position of a sub account on a market, as stored on-chain.
+#[derive(Debug, Clone, Copy)]
+pub struct SubAccountMarketPosition {
+    /// The index of the sub account.
+    pub sub_account_idx: u8,
+    /// The base position.
+    pub base_position: I80F48,
+    /// The sub account's open orders cache for the market.
+    pub open_orders_cache: OpenOrdersCache,
+}
+
+/// The state of the user's orders and positions on a market, rebuilt purely from on-chain data.
+#[derive(Debug, Clone)]
+pub struct RecoveredMarketState {
+    /// The market.
+    pub market: Pubkey,
+    /// The master account's orders account for the market.
+    pub orders_account: Pubkey,
+    /// The open orders in the orders account.
+    pub open_orders: Vec<OpenOrder>,
+    /// The positions of the sub accounts with a position on the market.
+    pub positions: Vec<SubAccountMarketPosition>,
+    /// The fills of the user's orders account which are still in the event queue, i.e. not yet consumed.
+    pub pending_fills: Vec<Fill>,
+}
+
+impl RecoveredMarketState {
+    /// Rebuilds the state of the given market from the given orders account, sub accounts and event queue.
+    ///
+    /// The sub accounts are given with their index in the master account.
+    pub fn new(
+        orders_account: &OrdersAccountContext,
+        sub_accounts: &[(u8, &CypherSubAccount)],
+        event_queue: &AgnosticEventQueueContext,
+    ) -> Self {
+        let positions = sub_accounts
+            .iter()
+            .filter_map(|(idx, sub_account)| {
+                let position = sub_account
+                    .positions
+                    .iter()
+                    .find(|p| p.derivative.market == orders_account.market)
+                    .map(|p| &p.derivative)?;
+                Some(SubAccountMarketPosition {
+                    sub_account_idx: *idx,
+                    base_position: position.base_position(),
+                    open_orders_cache: position.open_orders_cache,
+                })
+            })
+            .collect();
+        let pending_fills = event_queue
+            .get_fills()
+            .into_iter()
+            .filter(|f| {
+                f.maker == Some(orders_account.address) || f.taker == Some(orders_account.address)
+            })
+            .collect();
+
+        Self {
+            market: orders_account.market,
+            orders_account: orders_account.address,
+            open_orders: orders_account
+                .state
+                .get_orders()
+                .into_iter()
+                .copied()
+                .collect(),
+            positions,
+            pending_fills,
+        }
+    }
+}
+
+/// A discrepancy between the state recovered from on-chain data and the state the OMS expected.
+#[derive(Debug, Clone)]
+pub enum StartupDiscrepancy {
+    /// An expected order is not open on-chain, it was filled or cancelled while the OMS was down.
+    MissingOrder(ExpectedOrder),
+    /// An order is open on-chain but was not expected by the OMS, e.g. it was placed by another process
+    /// or it's submission was never recorded.
+    UnexpectedOrder { market: Pubkey, order: OpenOrder },
+    /// An expected order is open on-chain on a different side or sub account.
+    MismatchedOrder {
+        expected: ExpectedOrder,
+        order: OpenOrder,
+    },
+    /// An expected order is on a market that was not recovered.
+    UnknownMarket(ExpectedOrder),
+}
+
+/// The result of reconciling the recovered on-chain state against the state the OMS expected.
+#[derive(Debug, Default, Clone)]
+pub struct StartupReconciliation {
+    /// The recovered state of each market.
+    pub markets: Vec<RecoveredMarketState>,
+    /// The discrepancies found.
+    pub discrepancies: Vec<StartupDiscrepancy>,
+}
+
+impl StartupReconciliation {
+    /// Whether the recovered state matches the expected state, in which case trading can safely resume.
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    /// Gets the recovered state of the given market.
+    pub fn get_market(&self, market: &Pubkey) -> Option<&RecoveredMarketState> {
+        self.markets.iter().find(|m| &m.market == market)
+    }
+}
+
+/// Reconciles the given recovered market states against the orders the OMS expects to be open.
+pub fn reconcile_startup(
+    markets: Vec<RecoveredMarketState>,
+    expected_orders: &[ExpectedOrder],
+) -> StartupReconciliation {
+    let mut discrepancies = Vec::new();
+    let mut matched = HashSet::new();
+
+    for expected in expected_orders.iter() {
+        let market = match markets.iter().find(|m| m.market == expected.market) {
+            Some(m) => m,
+            None => {
+                discrepancies.push(StartupDiscrepancy::UnknownMarket(*expected));
+                continue;
+            }
+        };
+        let by_client_id = ExpectedOrder {
+            order_id: None,
+            ..*expected
+        };
+        match market.open_orders.iter().find(|o| expected.matches(o)) {
+            Some(order) => {
+                if order.side != expected.side || order.sub_account_idx != expected.sub_account_idx
+                {
+                    discrepancies.push(StartupDiscrepancy::MismatchedOrder {
+                        expected: *expected,
+                        order: *order,
+                    });
+                }
+                matched.insert((market.market, order.order_id));
+            }
+            // the order may have been acknowledged under a different order id than recorded
+            None => match market.open_orders.iter().find(|o| by_client_id.matches(o)) {
+                Some(order) => {
+                    discrepancies.push(StartupDiscrepancy::MismatchedOrder {
+                        expected: *expected,
+                        order: *order,
+                    });
+                    matched.insert((market.market, order.order_id));
+                }
+                None => discrepancies.push(StartupDiscrepancy::MissingOrder(*expected)),
+            },
+        }
+    }
+
+    for market in markets.iter() {
+        for order in market.open_orders.iter() {
+            if !matched.contains(&(market.market, order.order_id)) {
+                discrepancies.push(StartupDiscrepancy::UnexpectedOrder {
+                    market: market.market,
+                    order: *order,
+                });
+            }
+        }
+    }
+
+    if discrepancies.is_empty() {
+        info!(
+            "Recovered state of {} markets matches the expected state.",
+            markets.len()
+        );
+    } else {
+        warn!(
+            "Found {} discrepancies between the recovered and the expected state: {:?}",
+            discrepancies.len(),
+            discrepancies
+        );
+    }
+
+    StartupReconciliation {
+        markets,
+        discrepancies,
+    }
+}
+
+/// Rebuilds the state of the given master account on the given markets, given with their event queues,
+/// purely from on-chain data and reconciles it against the orders the OMS expects to be open.
+///
+/// This is meant to be called on restart, before resuming trading.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests.
+pub async fn recover_startup_state(
+    rpc_client: &Arc<RpcClient>,
+    master_account: &Pubkey,
+    markets: &[(Pubkey, Pubkey)],
+    expected_orders: &[ExpectedOrder],
+) -> Result<StartupReconciliation, ContextError> {
+    let master_account_state: Box<CypherAccount> =
+        match get_cypher_account(rpc_client, master_account).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+    let sub_accounts = match get_sub_accounts(rpc_client, master_account).await {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let indexed_sub_accounts = sub_accounts
+        .iter()
+        .filter_map(|(address, state)| {
+            let idx = master_account_state
+                .sub_account_caches
+                .iter()
+                .position(|c| &c.sub_account == address)?;
+            Some((idx as u8, state.as_ref()))
+        })
+        .collect::<Vec<(u8, &CypherSubAccount)>>();
+
+    let mut recovered = Vec::with_capacity(markets.len());
+    for (market, event_queue) in markets.iter() {
+        let orders_account =
+            match OrdersAccountContext::load(rpc_client, market, master_account).await {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let event_queue_ctx =
+            match AgnosticEventQueueContext::load(rpc_client, market, event_queue).await {
+                Ok(ctx) => ctx,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        recovered.push(RecoveredMarketState::new(
+            &orders_account,
+            &indexed_sub_accounts,
+            &event_queue_ctx,
+        ));
+    }
+
+    Ok(reconcile_startup(recovered, expected_orders))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+    use cypher_client::OrdersAccount;
+
+    #[test]
+    fn recovered_position_skips_empty_slots() {
+        let market = Pubkey::new_unique();
+        let mut orders_account = OrdersAccount::zeroed();
+        orders_account.market = market;
+        let orders_account_ctx =
+            OrdersAccountContext::new(&Pubkey::new_unique(), Box::new(orders_account));
+
+        // the first slot is empty, so the market's slot is the second occupied one but the third in the array
+        let mut sub_account = CypherSubAccount::zeroed();
+        sub_account.positions[1].derivative.market = Pubkey::new_unique();
+        sub_account.positions[1].derivative.base_position = I80F48::from_num(-3).to_bits();
+        sub_account.positions[2].derivative.market = market;
+        sub_account.positions[2].derivative.base_position = I80F48::from_num(7).to_bits();
+        sub_account.positions[2]
+            .derivative
+            .open_orders_cache
+            .coin_total = 5;
+
+        let state = RecoveredMarketState::new(
+            &orders_account_ctx,
+            &[(2, &sub_account)],
+            &AgnosticEventQueueContext::default(),
+        );
+        assert_eq!(state.positions.len(), 1);
+        assert_eq!(state.positions[0].sub_account_idx, 2);
+        assert_eq!(state.positions[0].base_position, I80F48::from_num(7));
+        assert_eq!(state.positions[0].open_orders_cache.coin_total, 5);
+        assert!(state.open_orders.is_empty());
+        assert!(state.pending_fills.is_empty());
+    }
+
+    #[test]
+    fn sub_accounts_without_a_position_are_not_recovered() {
+        let mut orders_account = OrdersAccount::zeroed();
+        orders_account.market = Pubkey::new_unique();
+        let orders_account_ctx =
+            OrdersAccountContext::new(&Pubkey::new_unique(), Box::new(orders_account));
+        let sub_account = CypherSubAccount::zeroed();
+
+        let state = RecoveredMarketState::new(
+            &orders_account_ctx,
+            &[(0, &sub_account)],
+            &AgnosticEventQueueContext::default(),
+        );
+        assert!(state.positions.is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use anchor_spl::dex::serum_dex::state::MarketState;
+use cypher_client::{
+    constants::QUOTE_TOKEN_DECIMALS,
+    math::MathError,
+    utils::{convert_coin_to_decimals_fixed, convert_price_to_decimals_fixed},
+    NewDerivativeOrderArgs, NewSpotOrderArgs, Side,
+};
+use fixed::types::I80F48;
+use log::debug;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt::Display;
+
+use crate::services::MarketParams;
+
+/// A human-readable interpretation of an order, in token units rather than lots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderInterpretation {
+    /// The side.
+    pub side: Side,
+    /// The limit price, in quote token units per base token unit.
+    pub price: I80F48,
+    /// The size, in base token units.
+    pub size: I80F48,
+}
+
+impl OrderInterpretation {
+    /// The notional of the order, in quote token units.
+    pub fn notional(&self) -> I80F48 {
+        self.price * self.size
+    }
+}
+
+impl Display for OrderInterpretation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.side {
+            Side::Bid => "buying",
+            Side::Ask => "selling",
+        };
+        write!(f, "{} {} @ {}", action, self.size, self.price)
+    }
+}
+
+/// Converts the given order from lots back to token units using the given market parameters and base token decimals.
+///
+/// ### Errors
+///
+/// This function will return an error if the decimals are too large for the decimals factor to fit in a `u64`.
+pub fn interpret_derivative_order(
+    params: &MarketParams,
+    decimals: u8,
+    order: &NewDerivativeOrderArgs,
+) -> Result<OrderInterpretation, MathError> {
+    let coin_decimals_factor = match 10u64.checked_pow(decimals as u32) {
+        Some(f) => f,
+        None => {
+            return Err(MathError::UnsupportedDecimals(decimals));
+        }
+    };
+    let price = convert_price_to_decimals_fixed(
+        order.limit_price,
+        params.base_multiplier,
+        coin_decimals_factor,
+        params.quote_multiplier,
+    ) / I80F48::from(10u64.pow(QUOTE_TOKEN_DECIMALS as u32));
+    let size = convert_coin_to_decimals_fixed(order.max_base_qty, params.base_multiplier)
+        / I80F48::from(coin_decimals_factor);
+
+    Ok(OrderInterpretation {
+        side: order.side,
+        price,
+        size,
+    })
+}
+
+/// Converts the given spot order from lots back to token units using the given Serum market and base token decimals.
+///
+/// ### Errors
+///
+/// This function will return an error if the decimals are too large for the decimals factor to fit in a `u64`.
+pub fn interpret_spot_order(
+    market: &MarketState,
+    decimals: u8,
+    order: &NewSpotOrderArgs,
+) -> Result<OrderInterpretation, MathError> {
+    let coin_decimals_factor = match 10u64.checked_pow(decimals as u32) {
+        Some(f) => I80F48::from(f),
+        None => {
+            return Err(MathError::UnsupportedDecimals(decimals));
+        }
+    };
+    let pc_decimals_factor = I80F48::from(10u64.pow(QUOTE_TOKEN_DECIMALS as u32));
+    let price =
+        I80F48::from(order.limit_price) * I80F48::from(market.pc_lot_size) * coin_decimals_factor
+            / (I80F48::from(market.coin_lot_size) * pc_decimals_factor);
+    let size = I80F48::from(order.max_coin_qty) * I80F48::from(market.coin_lot_size)
+        / coin_decimals_factor;
+
+    Ok(OrderInterpretation {
+        side: order.side,
+        price,
+        size,
+    })
+}
+
+/// Cross-checks the given order against the market's configuration in debug builds, without knowing the intended
+/// price and size, and logs a human-readable interpretation of it, e.g. `<market> buying 1.25 @ 143.5`.
+///
+/// This is a no-op in release builds, see [`debug_assert_order_units`] for order builders which know the intended
+/// price and size.
+///
+/// ### Panics
+///
+/// In debug builds, this function will panic if the decimals are not supported or the order is not aligned to
+/// the market's tick size and order size limits.
+#[inline(always)]
+pub fn debug_assert_derivative_order(
+    params: &MarketParams,
+    decimals: u8,
+    order: &NewDerivativeOrderArgs,
+) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let interpretation = match interpret_derivative_order(params, decimals, order) {
+        Ok(i) => i,
+        Err(e) => panic!("{}: {}", params.market, e),
+    };
+    debug!("{} {}", params.market, interpretation);
+
+    assert!(
+        params.tick_size == 0 || order.limit_price % params.tick_size == 0,
+        "{}: limit price {} is not a multiple of the tick size {}",
+        params.market,
+        order.limit_price,
+        params.tick_size
+    );
+    assert!(
+        order.max_base_qty >= params.min_base_order_size
+            && (params.max_base_order_size == 0
+                || order.max_base_qty <= params.max_base_order_size),
+        "{}: base quantity {} is outside of the order size limits [{}, {}]",
+        params.market,
+        order.max_base_qty,
+        params.min_base_order_size,
+        params.max_base_order_size
+    );
+}
+
+/// Cross-checks the given spot order against the Serum market's configuration in debug builds,
+/// and logs a human-readable interpretation of it, e.g. `<market> selling 0.5 @ 143.5`.
+///
+/// This is a no-op in release builds.
+///
+/// ### Panics
+///
+/// In debug builds, this function will panic if the decimals are not supported, the order's price or size is zero
+/// or a bid does not allow any quote tokens to be spent, which means the order was built with the wrong lot sizes
+/// or decimals.
+#[inline(always)]
+pub fn debug_assert_spot_order(
+    market: &Pubkey,
+    state: &MarketState,
+    decimals: u8,
+    order: &NewSpotOrderArgs,
+) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    let interpretation = match interpret_spot_order(state, decimals, order) {
+        Ok(i) => i,
+        Err(e) => panic!("{}: {}", market, e),
+    };
+    debug!("{} {}", market, interpretation);
+
+    assert!(
+        order.limit_price != 0 && order.max_coin_qty != 0,
+        "{}: {} rounds to zero lots, check the market's lot sizes and decimals",
+        market,
+        interpretation
+    );
+    assert!(
+        order.side == Side::Ask || order.max_native_pc_qty_including_fees != 0,
+        "{}: {} does not allow any quote tokens to be spent",
+        market,
+        interpretation
+    );
+}
+
+/// Cross-checks the unit scaling of the given order against the market's configuration and the intended price and
+/// size in debug builds, and logs a human-readable interpretation of it, see [`debug_assert_derivative_order`].
+///
+/// Converting the order back to token units must land within about one tick of the intended price and must not exceed
+/// the intended size, anything else means the order was built with the wrong multipliers or decimals.
+/// This is a no-op in release builds.
+///
+/// ### Panics
+///
+/// In debug builds, this function will panic if the order is not aligned to the market's tick size and order size
+/// limits, or does not match the intended price and size.
+#[inline(always)]
+pub fn debug_assert_order_units(
+    params: &MarketParams,
+    decimals: u8,
+    order: &NewDerivativeOrderArgs,
+    intended_price: I80F48,
+    intended_size: I80F48,
+) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    // the decimals are checked by the call above, so the order can be interpreted
+    debug_assert_derivative_order(params, decimals, order);
+    let interpretation = interpret_derivative_order(params, decimals, order).unwrap();
+
+    // prices are truncated to lots and then aligned to the tick size, sizes are truncated to lots
+    let tolerance = interpret_derivative_order(
+        params,
+        decimals,
+        &NewDerivativeOrderArgs {
+            limit_price: params.tick_size + 1,
+            max_base_qty: 1,
+            ..*order
+        },
+    )
+    .unwrap();
+    assert!(
+        (interpretation.price - intended_price).abs() <= tolerance.price,
+        "{}: {} does not match the intended price {}, check the market's multipliers and decimals",
+        params.market,
+        interpretation,
+        intended_price
+    );
+    // sizes may also be clamped to the order size limits, but never increased
+    assert!(
+        interpretation.size <= intended_size + tolerance.size,
+        "{}: {} exceeds the intended size {}, check the market's multipliers and decimals",
+        params.market,
+        interpretation,
+        intended_size
+    );
+}
@@ -0,0 +1,216 @@
+use {
+    anchor_lang::{Owner, ZeroCopy},
+    log::warn,
+    solana_client::{
+        client_error::{ClientError, ClientErrorKind},
+        nonblocking::rpc_client::RpcClient,
+        rpc_client::SerializableTransaction,
+        rpc_filter::RpcFilterType,
+        rpc_request::RpcError,
+    },
+    solana_sdk::{account::Account, pubkey::Pubkey, signature::Signature},
+    std::{
+        future::Future,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+use crate::utils::{
+    get_cypher_zero_copy_account, get_multiple_cypher_zero_copy_accounts, get_program_accounts,
+    send_transaction,
+};
+
+/// The JSON-RPC error code returned by a node which is behind or otherwise unhealthy.
+const JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY: i64 = -32005;
+
+/// The JSON-RPC error code returned when a slot is not available yet or was skipped by a lagging node.
+const JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE: i64 = -32004;
+
+/// The HTTP status code returned by RPC providers when rate limiting requests.
+const HTTP_TOO_MANY_REQUESTS: u16 = 429;
+
+/// The policy used to retry RPC requests which failed with a transient error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// The backoff before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum backoff between two attempts.
+    pub max_backoff: Duration,
+    /// The factor the backoff is multiplied by after every retry.
+    pub backoff_multiplier: u32,
+    /// Whether the backoff is randomized, to avoid several clients retrying in lockstep.
+    ///
+    /// With jitter enabled the actual backoff is drawn between half and the full backoff.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+            backoff_multiplier: 2,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the backoff before the given retry, starting at zero for the first retry.
+    pub fn get_backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(self.backoff_multiplier.saturating_pow(retry))
+            .min(self.max_backoff);
+        if !self.jitter {
+            return backoff;
+        }
+        // no need for a proper rng, the sub-second clock is unpredictable enough to spread retries
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let half = backoff / 2;
+        half + half.mul_f64(nanos as f64 / 1_000_000_000f64)
+    }
+}
+
+/// Whether the given error is transient, i.e. the same request may succeed if retried.
+///
+/// This covers rate limiting, timeouts, connection failures, server errors and unhealthy nodes,
+/// but not errors caused by the request itself, e.g. a failed transaction simulation or an invalid account.
+pub fn is_transient_error(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status()
+                    .map(|s| s.as_u16() == HTTP_TOO_MANY_REQUESTS || s.is_server_error())
+                    .unwrap_or(false)
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+                || *code == JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+        }
+        _ => false,
+    }
+}
+
+/// Runs the given request, retrying it according to the given policy as long as it fails with a transient error,
+/// see [`is_transient_error`].
+///
+/// ### Errors
+///
+/// This function will return the last error if the request failed with a non-transient error
+/// or the policy's maximum number of attempts was reached.
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut request: F) -> Result<T, ClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ClientError>>,
+{
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match request().await {
+            Ok(r) => return Ok(r),
+            Err(e) => {
+                if attempt >= policy.max_attempts || !is_transient_error(&e) {
+                    return Err(e);
+                }
+                let backoff = policy.get_backoff(attempt - 1);
+                warn!(
+                    "Request failed with a transient error on attempt {}/{}, retrying in {}ms: {}",
+                    attempt,
+                    policy.max_attempts,
+                    backoff.as_millis(),
+                    e.to_string()
+                );
+                tokio::time::sleep(backoff).await;
+            }
+        }
+    }
+}
+
+/// Gets the Account's state and decodes it into the given Account type, see [`get_cypher_zero_copy_account`],
+/// retrying transient errors according to the given policy.
+///
+/// ### Errors
+///
+/// This function will return an error if the RPC request failed with a non-transient error
+/// or the policy's maximum number of attempts was reached.
+pub async fn get_cypher_zero_copy_account_with_retry<T: ZeroCopy + Owner>(
+    rpc_client: &RpcClient,
+    account: &Pubkey,
+    policy: &RetryPolicy,
+) -> Result<Box<T>, ClientError> {
+    with_retry(policy, || {
+        get_cypher_zero_copy_account::<T>(rpc_client, account)
+    })
+    .await
+}
+
+/// Gets multiple Account's state and decodes them into the given Account type,
+/// see [`get_multiple_cypher_zero_copy_accounts`], retrying transient errors according to the given policy.
+///
+/// ### Errors
+///
+/// This function will return an error if the RPC request failed with a non-transient error
+/// or the policy's maximum number of attempts was reached.
+pub async fn get_multiple_cypher_zero_copy_accounts_with_retry<T: ZeroCopy + Owner>(
+    rpc_client: &RpcClient,
+    accounts: &[Pubkey],
+    policy: &RetryPolicy,
+) -> Result<Vec<Box<T>>, ClientError> {
+    with_retry(policy, || {
+        get_multiple_cypher_zero_copy_accounts::<T>(rpc_client, accounts)
+    })
+    .await
+}
+
+/// Gets all program accounts according to the given filters for the given program, see [`get_program_accounts`],
+/// retrying transient errors according to the given policy.
+///
+/// ### Errors
+///
+/// This function will return an error if the RPC request failed with a non-transient error
+/// or the policy's maximum number of attempts was reached.
+pub async fn get_program_accounts_with_retry(
+    rpc_client: &RpcClient,
+    filters: Vec<RpcFilterType>,
+    program_id: &Pubkey,
+    policy: &RetryPolicy,
+) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+    with_retry(policy, || {
+        get_program_accounts(rpc_client, filters.clone(), program_id)
+    })
+    .await
+}
+
+/// Sends a transaction, see [`send_transaction`], retrying transient errors according to the given policy.
+///
+/// Resending the same signed transaction is safe, it can only land once.
+///
+/// ### Errors
+///
+/// This function will return an error if the transaction could not be sent due to a non-transient error
+/// or the policy's maximum number of attempts was reached.
+pub async fn send_transaction_with_retry(
+    rpc_client: &RpcClient,
+    tx: &impl SerializableTransaction,
+    confirm: bool,
+    policy: &RetryPolicy,
+) -> Result<Signature, ClientError> {
+    with_retry(policy, || send_transaction(rpc_client, tx, confirm)).await
+}
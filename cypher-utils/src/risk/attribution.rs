@@ -0,0 +1,330 @@
+use cypher_client::{
+    constants::QUOTE_TOKEN_IDX, math::MathError, utils::try_adjust_decimals, CacheAccount,
+    Clearing, CypherAccount, CypherSubAccount, MarginCollateralRatioType,
+};
+use fixed::types::I80F48;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+use crate::{
+    contexts::{get_cypher_account, CacheContext, ContextError},
+    scanners::get_sub_accounts,
+    utils::get_cypher_zero_copy_account,
+};
+
+/// What a [`MarginAttribution`] is attributed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarginSource {
+    /// A spot position, identified by it's token mint.
+    Token(Pubkey),
+    /// A derivative position, identified by it's market.
+    Market(Pubkey),
+}
+
+/// The init margin usage of a single position of a sub account and it's open orders.
+///
+/// All values are init weighted and in native quote units unless stated otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarginAttribution {
+    /// The sub account.
+    pub sub_account: Pubkey,
+    /// The position.
+    pub source: MarginSource,
+    /// The assets value of the position.
+    pub position_assets_value: I80F48,
+    /// The unweighted assets value of the position.
+    pub position_assets_value_unweighted: I80F48,
+    /// The liabilities value of the position.
+    pub position_liabilities_value: I80F48,
+    /// The assets value of the funds locked in the position's open orders.
+    pub orders_assets_value: I80F48,
+    /// The unweighted assets value of the funds locked in the position's open orders.
+    pub orders_assets_value_unweighted: I80F48,
+}
+
+impl MarginAttribution {
+    fn new(sub_account: &Pubkey, source: MarginSource) -> Self {
+        Self {
+            sub_account: *sub_account,
+            source,
+            position_assets_value: I80F48::ZERO,
+            position_assets_value_unweighted: I80F48::ZERO,
+            position_liabilities_value: I80F48::ZERO,
+            orders_assets_value: I80F48::ZERO,
+            orders_assets_value_unweighted: I80F48::ZERO,
+        }
+    }
+
+    /// The assets value of the position and it's open orders.
+    pub fn assets_value(&self) -> I80F48 {
+        self.position_assets_value + self.orders_assets_value
+    }
+
+    /// The init margin consumed by the position, which is the collateral required to cover it's liabilities
+    /// at the given init margin ratio plus the value it's assets lose to their weights.
+    pub fn position_margin_used(&self, init_margin_ratio: I80F48) -> I80F48 {
+        self.position_liabilities_value * init_margin_ratio
+            + (self.position_assets_value_unweighted - self.position_assets_value)
+    }
+
+    /// The init margin consumed by the position's open orders, which is the value the funds locked in them
+    /// lose to their weights.
+    pub fn orders_margin_used(&self) -> I80F48 {
+        self.orders_assets_value_unweighted - self.orders_assets_value
+    }
+
+    /// The init margin consumed by the position and it's open orders.
+    pub fn margin_used(&self, init_margin_ratio: I80F48) -> I80F48 {
+        self.position_margin_used(init_margin_ratio) + self.orders_margin_used()
+    }
+
+    /// The contribution of the position and it's open orders to the free init margin of the account,
+    /// i.e. `assets - init_margin_ratio * liabilities`, which is negative if they consume margin.
+    pub fn free_margin_contribution(&self, init_margin_ratio: I80F48) -> I80F48 {
+        self.assets_value() - self.position_liabilities_value * init_margin_ratio
+    }
+}
+
+/// Decomposes the init margin usage of the given sub account by position, including it's open orders.
+///
+/// The values mirror [`CypherSubAccount::get_assets_value`] and [`CypherSubAccount::get_liabilities_value`],
+/// so the attributions of a sub account sum up to it's total assets and liabilities values.
+///
+/// ### Errors
+///
+/// This function will return an error if the value of a position can not be computed.
+pub fn get_sub_account_margin_attribution(
+    sub_account: &Pubkey,
+    state: &CypherSubAccount,
+    cache_account: &CacheAccount,
+) -> Result<Vec<MarginAttribution>, MathError> {
+    let mcr_type = MarginCollateralRatioType::Initialization;
+    let quote_position = state.positions[QUOTE_TOKEN_IDX].spot;
+    let quote_cache = cache_account.try_get_price_cache(quote_position.cache_index as usize)?;
+    let quote_asset_weight = quote_cache.spot_asset_weight(mcr_type);
+    // the quote locked in open orders is valued at the quote token's oracle price and asset weight
+    let value_quote_locked = |pc_total: u64| -> Result<(I80F48, I80F48), MathError> {
+        let unweighted = try_adjust_decimals(I80F48::from(pc_total), quote_cache.decimals)?
+            .checked_mul(quote_cache.oracle_price())
+            .ok_or(MathError::Overflow("quote total value"))?;
+        Ok((unweighted * quote_asset_weight, unweighted))
+    };
+
+    let mut attributions = Vec::new();
+    for position in state.iter_position_slots() {
+        if position.spot.token_mint != Pubkey::default() {
+            let cache = cache_account.try_get_price_cache(position.spot.cache_index as usize)?;
+            let price = cache.oracle_price();
+            let mut attribution =
+                MarginAttribution::new(sub_account, MarginSource::Token(position.spot.token_mint));
+            let spot_position = position.spot.try_total_position(cache)?;
+            if spot_position.is_positive() {
+                let asset_weight = cache.spot_asset_weight(mcr_type);
+                let position_value = try_adjust_decimals(spot_position, cache.decimals)?
+                    .checked_mul(price)
+                    .ok_or(MathError::Overflow("spot asset value"))?;
+                let orders_value = try_adjust_decimals(
+                    I80F48::from(position.spot.open_orders_cache.coin_total),
+                    cache.decimals,
+                )?
+                .checked_mul(price)
+                .ok_or(MathError::Overflow("spot asset value"))?;
+                attribution.position_assets_value_unweighted += position_value;
+                attribution.position_assets_value += position_value * asset_weight;
+                attribution.orders_assets_value_unweighted += orders_value;
+                attribution.orders_assets_value += orders_value * asset_weight;
+            } else if spot_position.is_negative() {
+                attribution.position_liabilities_value +=
+                    try_adjust_decimals(spot_position, cache.decimals)?
+                        .abs()
+                        .checked_mul(price)
+                        .ok_or(MathError::Overflow("spot liability value"))?
+                        * cache.spot_liab_weight(mcr_type);
+            }
+            let (quote_value, quote_value_unweighted) =
+                value_quote_locked(position.spot.open_orders_cache.pc_total)?;
+            attribution.orders_assets_value += quote_value;
+            attribution.orders_assets_value_unweighted += quote_value_unweighted;
+            attributions.push(attribution);
+        }
+
+        if position.derivative.market != Pubkey::default() {
+            let market_type = position.derivative.market_type;
+            let cache =
+                cache_account.try_get_price_cache(position.derivative.cache_index as usize)?;
+            let decimals = cache.decimals_for(market_type);
+            let price = cache.price_for(market_type);
+            let asset_weight = cache
+                .derivative_asset_weight(market_type, mcr_type)
+                .ok_or(MathError::UnsupportedMarketType(position.derivative.market))?;
+            let liability_weight = cache
+                .derivative_liab_weight(market_type, mcr_type)
+                .ok_or(MathError::UnsupportedMarketType(position.derivative.market))?;
+            let mut attribution = MarginAttribution::new(
+                sub_account,
+                MarginSource::Market(position.derivative.market),
+            );
+            let open_orders_cache = position.derivative.open_orders_cache;
+
+            let base_position = position.derivative.base_position();
+            if base_position.is_positive() {
+                let position_value = try_adjust_decimals(base_position, decimals)?
+                    .checked_mul(price)
+                    .ok_or(MathError::Overflow("derivative asset value"))?;
+                let orders_value =
+                    try_adjust_decimals(I80F48::from(open_orders_cache.coin_total), decimals)?
+                        .checked_mul(price)
+                        .ok_or(MathError::Overflow("derivative asset value"))?;
+                attribution.position_assets_value_unweighted += position_value;
+                attribution.position_assets_value += position_value * asset_weight;
+                attribution.orders_assets_value_unweighted += orders_value;
+                attribution.orders_assets_value += orders_value * asset_weight;
+            } else if base_position.is_negative() {
+                // the liability weight is applied twice, as it is by the program
+                attribution.position_liabilities_value +=
+                    try_adjust_decimals(base_position, decimals)?
+                        .abs()
+                        .checked_mul(price)
+                        .and_then(|n| n.checked_mul(liability_weight))
+                        .and_then(|n| n.checked_mul(liability_weight))
+                        .ok_or(MathError::Overflow("derivative liability value"))?;
+            }
+            let coin_locked_value =
+                try_adjust_decimals(I80F48::from(open_orders_cache.coin_locked()), decimals)?
+                    .checked_mul(price)
+                    .ok_or(MathError::Overflow("derivative coin locked value"))?;
+            attribution.orders_assets_value_unweighted += coin_locked_value;
+            attribution.orders_assets_value += coin_locked_value * asset_weight;
+            let (quote_value, quote_value_unweighted) =
+                value_quote_locked(open_orders_cache.pc_total)?;
+            attribution.orders_assets_value += quote_value;
+            attribution.orders_assets_value_unweighted += quote_value_unweighted;
+            attributions.push(attribution);
+        }
+    }
+
+    Ok(attributions)
+}
+
+/// The init margin usage of an account, decomposed by position, see [`MarginAttribution`].
+#[derive(Debug, Default, Clone)]
+pub struct MarginAttributionReport {
+    /// The init margin ratio of the clearing.
+    pub init_margin_ratio: I80F48,
+    /// The attributions of every position of every sub account.
+    pub attributions: Vec<MarginAttribution>,
+}
+
+impl MarginAttributionReport {
+    /// Computes the [`MarginAttributionReport`] of the given sub accounts.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the value of a position can not be computed.
+    pub fn from_sub_accounts(
+        clearing_state: &Clearing,
+        cache_account: &CacheAccount,
+        sub_accounts: &[(Pubkey, Box<CypherSubAccount>)],
+    ) -> Result<Self, MathError> {
+        let mut attributions = Vec::new();
+        for (pubkey, sub_account) in sub_accounts.iter() {
+            match get_sub_account_margin_attribution(pubkey, sub_account, cache_account) {
+                Ok(a) => attributions.extend(a),
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+        Ok(Self {
+            init_margin_ratio: clearing_state.init_margin_ratio(),
+            attributions,
+        })
+    }
+
+    /// The total init margin consumed by all positions and their open orders.
+    pub fn total_margin_used(&self) -> I80F48 {
+        self.attributions
+            .iter()
+            .map(|a| a.margin_used(self.init_margin_ratio))
+            .sum()
+    }
+
+    /// The init margin consumed by the positions and open orders of the given source across all sub accounts.
+    pub fn get_margin_used(&self, source: &MarginSource) -> I80F48 {
+        self.attributions
+            .iter()
+            .filter(|a| &a.source == source)
+            .map(|a| a.margin_used(self.init_margin_ratio))
+            .sum()
+    }
+
+    /// Gets the share of the total init margin consumed by each source across all sub accounts,
+    /// sorted from largest to smallest.
+    pub fn get_margin_shares(&self) -> Vec<(MarginSource, I80F48)> {
+        let total = self.total_margin_used();
+        let mut shares: Vec<(MarginSource, I80F48)> = Vec::new();
+        for attribution in self.attributions.iter() {
+            let used = attribution.margin_used(self.init_margin_ratio);
+            match shares.iter_mut().find(|(s, _)| s == &attribution.source) {
+                Some((_, share)) => *share += used,
+                None => shares.push((attribution.source, used)),
+            }
+        }
+        if total != I80F48::ZERO {
+            for (_, share) in shares.iter_mut() {
+                *share /= total;
+            }
+        }
+        shares.sort_by(|a, b| b.1.cmp(&a.1));
+        shares
+    }
+}
+
+/// Loads the given master account, it's sub accounts, clearing and the cache account
+/// and computes the [`MarginAttributionReport`].
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests
+/// or the value of a position can not be computed.
+pub async fn get_margin_attribution_report(
+    rpc_client: &Arc<RpcClient>,
+    master_account: &Pubkey,
+) -> Result<MarginAttributionReport, ContextError> {
+    let account_state: Box<CypherAccount> =
+        match get_cypher_account(rpc_client, master_account).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+    let clearing_state =
+        match get_cypher_zero_copy_account::<Clearing>(rpc_client, &account_state.clearing).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        };
+    let cache_ctx = match CacheContext::load(rpc_client).await {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let sub_accounts = match get_sub_accounts(rpc_client, master_account).await {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    match MarginAttributionReport::from_sub_accounts(
+        &clearing_state,
+        &cache_ctx.state,
+        &sub_accounts,
+    ) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(ContextError::MathError(e)),
+    }
+}
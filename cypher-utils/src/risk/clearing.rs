@@ -0,0 +1,294 @@
+use cypher_client::{
+    CacheAccount, Clearing, CypherAccount, CypherSubAccount, MarginCollateralRatioType,
+    SubAccountMargining,
+};
+use fixed::types::I80F48;
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    contexts::{CacheContext, ContextError},
+    utils::get_cypher_zero_copy_account,
+};
+
+pub use crate::scanners::{get_clearing_accounts, get_clearing_sub_accounts};
+
+/// Aggregated deposits and borrows of a given token across all accounts of a clearing.
+#[derive(Debug, Default, Clone)]
+pub struct TokenRiskMetrics {
+    /// The token mint.
+    pub token_mint: Pubkey,
+    /// The total deposits, in native units.
+    pub total_deposits: I80F48,
+    /// The total borrows, in native units.
+    pub total_borrows: I80F48,
+    /// The number of positions with a deposit.
+    pub depositors: u64,
+    /// The number of positions with a borrow.
+    pub borrowers: u64,
+}
+
+/// Aggregated positions of a given derivatives market across all accounts of a clearing.
+#[derive(Debug, Default, Clone)]
+pub struct MarketRiskMetrics {
+    /// The market.
+    pub market: Pubkey,
+    /// The sum of all long positions, in native units.
+    pub long_open_interest: I80F48,
+    /// The sum of all short positions, in native units.
+    pub short_open_interest: I80F48,
+    /// The number of long positions.
+    pub longs: u64,
+    /// The number of short positions.
+    pub shorts: u64,
+}
+
+impl MarketRiskMetrics {
+    /// The open interest of the market, which is the larger of both sides.
+    pub fn open_interest(&self) -> I80F48 {
+        I80F48::max(self.long_open_interest, self.short_open_interest)
+    }
+
+    /// The long/short skew of the market, from `-1` if all positions are short to `1` if all positions are long.
+    ///
+    /// Returns zero if there are no positions.
+    pub fn skew(&self) -> I80F48 {
+        let total = self.long_open_interest + self.short_open_interest;
+        if total == I80F48::ZERO {
+            return I80F48::ZERO;
+        }
+        (self.long_open_interest - self.short_open_interest) / total
+    }
+
+    /// Adds the given base position to the metrics.
+    pub fn add_position(&mut self, base_position: I80F48) {
+        if base_position.is_positive() {
+            self.long_open_interest += base_position;
+            self.longs += 1;
+        } else if base_position.is_negative() {
+            self.short_open_interest += base_position.abs();
+            self.shorts += 1;
+        }
+    }
+}
+
+/// Clearing-level risk metrics, computed from all accounts and sub accounts of a clearing.
+#[derive(Debug, Default, Clone)]
+pub struct ClearingRiskReport {
+    /// The clearing.
+    pub clearing: Pubkey,
+    /// The deposits and borrows per token mint.
+    pub tokens: HashMap<Pubkey, TokenRiskMetrics>,
+    /// The open interest per derivatives market.
+    pub markets: HashMap<Pubkey, MarketRiskMetrics>,
+    /// The aggregate maintenance weighted assets value.
+    pub assets_value: I80F48,
+    /// The aggregate maintenance weighted liabilities value.
+    pub liabilities_value: I80F48,
+    /// The number of accounts scanned.
+    pub accounts_count: u64,
+    /// The number of sub accounts scanned.
+    pub sub_accounts_count: u64,
+    /// The number of accounts with liabilities.
+    pub accounts_with_liabilities: u64,
+    /// The number of accounts whose c-ratio is within the given threshold of the maintenance margin.
+    pub accounts_near_maintenance: u64,
+    /// The number of accounts whose c-ratio is below the maintenance margin.
+    pub accounts_below_maintenance: u64,
+}
+
+impl ClearingRiskReport {
+    /// Computes the [`ClearingRiskReport`] from the given accounts and sub accounts.
+    ///
+    /// The `threshold` is the distance to the maintenance margin ratio under which an account
+    /// is considered close to maintenance, e.g. `0.1` flags every account whose c-ratio is
+    /// lower than 110% of the maintenance margin ratio.
+    pub fn from_accounts(
+        clearing: &Pubkey,
+        clearing_state: &Clearing,
+        cache_account: &CacheAccount,
+        accounts: &[(Pubkey, Box<CypherAccount>)],
+        sub_accounts: &[(Pubkey, Box<CypherSubAccount>)],
+        threshold: I80F48,
+    ) -> Self {
+        let mut report = Self {
+            clearing: *clearing,
+            accounts_count: accounts.len() as u64,
+            sub_accounts_count: sub_accounts.len() as u64,
+            ..Default::default()
+        };
+        let mcr_type = MarginCollateralRatioType::Maintenance;
+        let maint_margin_ratio = clearing_state.maint_margin_ratio();
+        let near_maint_ratio = maint_margin_ratio
+            .checked_mul(I80F48::ONE + threshold)
+            .unwrap_or(I80F48::MAX);
+
+        // per master account cross margined assets and liabilities
+        let mut account_values: HashMap<Pubkey, (I80F48, I80F48)> = HashMap::new();
+
+        for (pubkey, sub_account) in sub_accounts.iter() {
+            let (assets_value, _) = match sub_account.get_assets_value(cache_account, mcr_type) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(
+                        "Failed to compute assets value of sub account {}: {}",
+                        pubkey, e
+                    );
+                    continue;
+                }
+            };
+            let (liabilities_value, _) =
+                match sub_account.get_liabilities_value(cache_account, mcr_type) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!(
+                            "Failed to compute liabilities value of sub account {}: {}",
+                            pubkey, e
+                        );
+                        continue;
+                    }
+                };
+
+            // the spot positions are valued before anything is aggregated so that an account with
+            // an invalid cache index is skipped entirely instead of being partially counted
+            let mut spot_positions = Vec::new();
+            let mut invalid_cache = false;
+            for position in sub_account.iter_position_slots() {
+                if position.spot.token_mint != Pubkey::default() {
+                    match cache_account.try_get_price_cache(position.spot.cache_index as usize) {
+                        Ok(cache) => spot_positions.push((
+                            position.spot.token_mint,
+                            position.spot.total_position(cache),
+                        )),
+                        Err(e) => {
+                            warn!("Failed to get price cache of sub account {}: {}", pubkey, e);
+                            invalid_cache = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            if invalid_cache {
+                continue;
+            }
+
+            for (token_mint, total_position) in spot_positions.into_iter() {
+                let metrics = report
+                    .tokens
+                    .entry(token_mint)
+                    .or_insert_with(|| TokenRiskMetrics {
+                        token_mint,
+                        ..Default::default()
+                    });
+                if total_position.is_positive() {
+                    metrics.total_deposits += total_position;
+                    metrics.depositors += 1;
+                } else if total_position.is_negative() {
+                    metrics.total_borrows += total_position.abs();
+                    metrics.borrowers += 1;
+                }
+            }
+            for position in sub_account.iter_position_slots() {
+                if position.derivative.market != Pubkey::default() {
+                    let base_position = position.derivative.base_position();
+                    let metrics = report
+                        .markets
+                        .entry(position.derivative.market)
+                        .or_insert_with(|| MarketRiskMetrics {
+                            market: position.derivative.market,
+                            ..Default::default()
+                        });
+                    metrics.add_position(base_position);
+                }
+            }
+
+            report.assets_value += assets_value;
+            report.liabilities_value += liabilities_value;
+
+            if sub_account.margining_type == SubAccountMargining::Cross {
+                let values = account_values
+                    .entry(sub_account.master_account)
+                    .or_insert((I80F48::ZERO, I80F48::ZERO));
+                values.0 += assets_value;
+                values.1 += liabilities_value;
+            }
+        }
+
+        for (account, _) in accounts.iter() {
+            let (assets_value, liabilities_value) = match account_values.get(account) {
+                Some(v) => *v,
+                None => continue,
+            };
+            if liabilities_value == I80F48::ZERO {
+                continue;
+            }
+            report.accounts_with_liabilities += 1;
+            let c_ratio = assets_value.saturating_div(liabilities_value);
+            if c_ratio < maint_margin_ratio {
+                report.accounts_below_maintenance += 1;
+            } else if c_ratio < near_maint_ratio {
+                report.accounts_near_maintenance += 1;
+            }
+        }
+
+        report
+    }
+
+    /// Gets the aggregate c-ratio of the clearing.
+    pub fn c_ratio(&self) -> I80F48 {
+        if self.liabilities_value == I80F48::ZERO {
+            I80F48::MAX
+        } else {
+            self.assets_value.saturating_div(self.liabilities_value)
+        }
+    }
+}
+
+/// Loads all [`CypherAccount`]s and [`CypherSubAccount`]s of the given clearing
+/// and computes the [`ClearingRiskReport`].
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests.
+pub async fn get_clearing_risk_report(
+    rpc_client: &Arc<RpcClient>,
+    clearing: &Pubkey,
+    threshold: I80F48,
+) -> Result<ClearingRiskReport, ContextError> {
+    let clearing_state = match get_cypher_zero_copy_account::<Clearing>(rpc_client, clearing).await
+    {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(ContextError::ClientError(e));
+        }
+    };
+    let cache_ctx = match CacheContext::load(rpc_client).await {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let accounts = match get_clearing_accounts(rpc_client, clearing).await {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let sub_accounts = match get_clearing_sub_accounts(rpc_client, clearing).await {
+        Ok(a) => a,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    Ok(ClearingRiskReport::from_accounts(
+        clearing,
+        &clearing_state,
+        &cache_ctx.state,
+        &accounts,
+        &sub_accounts,
+        threshold,
+    ))
+}
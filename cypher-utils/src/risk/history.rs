@@ -0,0 +1,160 @@
+use cypher_client::{
+    CacheAccount, CypherSubAccount, MarginCollateralRatioType, SubAccountMargining,
+};
+use fixed::types::I80F48;
+use log::warn;
+use solana_sdk::pubkey::Pubkey;
+
+/// A recorded snapshot of the [`CacheAccount`].
+#[derive(Clone)]
+pub struct CacheSnapshot {
+    /// The slot at which the snapshot was taken.
+    pub slot: u64,
+    /// The cache account's state.
+    pub state: Box<CacheAccount>,
+}
+
+/// A recorded snapshot of a [`CypherSubAccount`].
+#[derive(Clone)]
+pub struct SubAccountSnapshot {
+    /// The slot at which the snapshot was taken.
+    pub slot: u64,
+    /// The sub account.
+    pub address: Pubkey,
+    /// The sub account's state.
+    pub state: Box<CypherSubAccount>,
+}
+
+/// The c-ratio of a sub account at a given slot.
+#[derive(Debug, Clone)]
+pub struct SubAccountCRatio {
+    /// The sub account.
+    pub sub_account: Pubkey,
+    /// The slot of the sub account snapshot used.
+    pub snapshot_slot: u64,
+    /// The weighted assets value.
+    pub assets_value: I80F48,
+    /// The weighted liabilities value.
+    pub liabilities_value: I80F48,
+    /// The c-ratio.
+    pub c_ratio: I80F48,
+}
+
+/// The c-ratio of a master account at a given slot.
+#[derive(Debug, Clone)]
+pub struct CRatioPoint {
+    /// The slot of the cache snapshot used.
+    pub slot: u64,
+    /// The c-ratio of the cross margined sub accounts.
+    pub c_ratio: I80F48,
+    /// The weighted assets value of the cross margined sub accounts.
+    pub assets_value: I80F48,
+    /// The weighted liabilities value of the cross margined sub accounts.
+    pub liabilities_value: I80F48,
+    /// The c-ratio of each sub account.
+    pub sub_accounts: Vec<SubAccountCRatio>,
+}
+
+/// Reconstructs the c-ratio time series of a master account from recorded cache and sub account snapshots.
+///
+/// A point is produced for every cache snapshot between `start_slot` and `end_slot`, inclusive, using the
+/// most recent snapshot of each sub account taken at or before the cache snapshot's slot. Sub accounts
+/// without a snapshot at that point are omitted. The snapshots do not need to be sorted.
+pub fn reconstruct_c_ratio_history(
+    cache_snapshots: &[CacheSnapshot],
+    sub_account_snapshots: &[SubAccountSnapshot],
+    mcr_type: MarginCollateralRatioType,
+    start_slot: u64,
+    end_slot: u64,
+) -> Vec<CRatioPoint> {
+    let mut cache_snapshots = cache_snapshots
+        .iter()
+        .filter(|s| s.slot >= start_slot && s.slot <= end_slot)
+        .collect::<Vec<&CacheSnapshot>>();
+    cache_snapshots.sort_by_key(|s| s.slot);
+
+    let mut sub_accounts = sub_account_snapshots
+        .iter()
+        .map(|s| s.address)
+        .collect::<Vec<Pubkey>>();
+    sub_accounts.sort();
+    sub_accounts.dedup();
+
+    let mut points = Vec::with_capacity(cache_snapshots.len());
+    for cache_snapshot in cache_snapshots {
+        let mut point = CRatioPoint {
+            slot: cache_snapshot.slot,
+            c_ratio: I80F48::MAX,
+            assets_value: I80F48::ZERO,
+            liabilities_value: I80F48::ZERO,
+            sub_accounts: Vec::new(),
+        };
+        for sub_account in sub_accounts.iter() {
+            let snapshot = match sub_account_snapshots
+                .iter()
+                .filter(|s| s.address == *sub_account && s.slot <= cache_snapshot.slot)
+                .max_by_key(|s| s.slot)
+            {
+                Some(s) => s,
+                None => continue,
+            };
+            let (c_ratio, assets_value, liabilities_value) = match snapshot
+                .state
+                .get_margin_c_ratio_components(&cache_snapshot.state, mcr_type)
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    warn!(
+                        "Failed to compute c-ratio of sub account {} at slot {}: {}",
+                        sub_account, snapshot.slot, e
+                    );
+                    continue;
+                }
+            };
+            if snapshot.state.margining_type == SubAccountMargining::Cross {
+                point.assets_value += assets_value;
+                point.liabilities_value += liabilities_value;
+            }
+            point.sub_accounts.push(SubAccountCRatio {
+                sub_account: *sub_account,
+                snapshot_slot: snapshot.slot,
+                assets_value,
+                liabilities_value,
+                c_ratio,
+            });
+        }
+        if point.liabilities_value != I80F48::ZERO {
+            point.c_ratio = point.assets_value.saturating_div(point.liabilities_value);
+        }
+        points.push(point);
+    }
+
+    points
+}
+
+/// Reconstructs the c-ratio time series of a master account around the given incident slot,
+/// e.g. the slot of a liquidation.
+///
+/// See [`reconstruct_c_ratio_history`].
+pub fn reconstruct_c_ratio_around(
+    cache_snapshots: &[CacheSnapshot],
+    sub_account_snapshots: &[SubAccountSnapshot],
+    mcr_type: MarginCollateralRatioType,
+    incident_slot: u64,
+    slots_before: u64,
+    slots_after: u64,
+) -> Vec<CRatioPoint> {
+    reconstruct_c_ratio_history(
+        cache_snapshots,
+        sub_account_snapshots,
+        mcr_type,
+        incident_slot.saturating_sub(slots_before),
+        incident_slot.saturating_add(slots_after),
+    )
+}
+
+/// Finds the first point in the given c-ratio time series at which the c-ratio fell below the given threshold,
+/// e.g. the clearing's maintenance margin ratio.
+pub fn find_first_breach(points: &[CRatioPoint], threshold: I80F48) -> Option<&CRatioPoint> {
+    points.iter().find(|p| p.c_ratio < threshold)
+}
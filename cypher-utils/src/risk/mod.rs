@@ -0,0 +1,13 @@
+pub mod attribution;
+pub mod clearing;
+pub mod history;
+pub mod pools;
+pub mod vaults;
+pub mod velocity;
+
+pub use attribution::*;
+pub use clearing::*;
+pub use history::*;
+pub use pools::*;
+pub use vaults::*;
+pub use velocity::*;
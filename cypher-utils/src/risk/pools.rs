@@ -0,0 +1,157 @@
+use cypher_client::{Pool, PoolNode};
+use fixed::types::I80F48;
+use log::{info, warn};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::contexts::PoolContext;
+
+/// The severity of a pool utilization alert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PoolUtilizationSeverity {
+    /// The pool's utilization is below the warning threshold.
+    Normal,
+    /// The pool's utilization is above the warning threshold.
+    Warning,
+    /// The pool's utilization is above the critical threshold, withdrawals and borrows may fail.
+    Critical,
+}
+
+/// The utilization thresholds used to classify pools.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolUtilizationThresholds {
+    /// The utilization rate above which a warning is emitted.
+    pub warning: I80F48,
+    /// The utilization rate above which a critical alert is emitted.
+    pub critical: I80F48,
+}
+
+impl Default for PoolUtilizationThresholds {
+    fn default() -> Self {
+        Self {
+            warning: I80F48::from_num(0.9),
+            critical: I80F48::from_num(0.98),
+        }
+    }
+}
+
+/// The utilization state of a pool.
+#[derive(Debug, Clone)]
+pub struct PoolUtilizationAlert {
+    /// The pool.
+    pub pool: Pubkey,
+    /// The pool's token mint.
+    pub token_mint: Pubkey,
+    /// The pool's utilization rate.
+    pub utilization_rate: I80F48,
+    /// The remaining utilization until the pool is at 100%.
+    pub headroom: I80F48,
+    /// The amount that can currently be withdrawn or borrowed from the pool, in native units.
+    pub available_liquidity: I80F48,
+    /// The severity of the alert.
+    pub severity: PoolUtilizationSeverity,
+}
+
+/// The amount that can be immediately withdrawn from a pool node.
+#[derive(Debug, Clone)]
+pub struct PoolNodeLiquidity {
+    /// The pool node.
+    pub pool_node: Pubkey,
+    /// The amount that can be withdrawn from the pool node's vault, in native units.
+    pub withdrawable: I80F48,
+    /// The pool node's utilization rate.
+    pub utilization_rate: I80F48,
+    /// The share of the pool's deposits held by the pool node.
+    pub deposits_share: I80F48,
+}
+
+/// Gets the liquidity available in the given [`Pool`], in native units.
+pub fn get_pool_available_liquidity(pool: &Pool) -> I80F48 {
+    I80F48::max(pool.total_deposits() - pool.total_borrows(), I80F48::ZERO)
+}
+
+/// Gets the amount that can be immediately withdrawn from the given [`PoolNode`], in native units.
+///
+/// This is the node's deposits minus the node's borrows, both adjusted by the pool's indexes.
+pub fn get_pool_node_withdrawable_amount(pool: &Pool, pool_node: &PoolNode) -> I80F48 {
+    let deposits = pool_node.total_deposits(pool);
+    let borrows = pool_node.total_borrows(pool);
+    I80F48::max(deposits.saturating_sub(borrows), I80F48::ZERO)
+}
+
+/// Gets the amount that can be immediately withdrawn from each of the loaded pool nodes of the given [`PoolContext`].
+pub fn get_pool_nodes_liquidity(pool_ctx: &PoolContext) -> Vec<PoolNodeLiquidity> {
+    pool_ctx
+        .pool_nodes
+        .iter()
+        .map(|pn| PoolNodeLiquidity {
+            pool_node: pn.address,
+            withdrawable: get_pool_node_withdrawable_amount(&pool_ctx.state, &pn.state),
+            utilization_rate: pn.state.utilization_rate(&pool_ctx.state),
+            deposits_share: pn.state.deposits_share(&pool_ctx.state),
+        })
+        .collect()
+}
+
+/// Gets the maximum amount that can be withdrawn in a single instruction from the given [`PoolContext`],
+/// along with the pool node that should be used, if any of the pool nodes has liquidity available.
+pub fn get_max_withdrawable(pool_ctx: &PoolContext) -> Option<PoolNodeLiquidity> {
+    get_pool_nodes_liquidity(pool_ctx)
+        .into_iter()
+        .filter(|l| l.withdrawable > I80F48::ZERO)
+        .max_by(|a, b| a.withdrawable.cmp(&b.withdrawable))
+}
+
+/// Checks the utilization of the given [`PoolContext`] against the given thresholds.
+pub fn check_pool_utilization(
+    pool_ctx: &PoolContext,
+    thresholds: &PoolUtilizationThresholds,
+) -> PoolUtilizationAlert {
+    let utilization_rate = pool_ctx.state.utilization_rate();
+    let severity = if utilization_rate >= thresholds.critical {
+        PoolUtilizationSeverity::Critical
+    } else if utilization_rate >= thresholds.warning {
+        PoolUtilizationSeverity::Warning
+    } else {
+        PoolUtilizationSeverity::Normal
+    };
+    PoolUtilizationAlert {
+        pool: pool_ctx.address,
+        token_mint: pool_ctx.state.token_mint,
+        utilization_rate,
+        headroom: I80F48::max(I80F48::ONE - utilization_rate, I80F48::ZERO),
+        available_liquidity: get_pool_available_liquidity(&pool_ctx.state),
+        severity,
+    }
+}
+
+/// Checks the utilization of all of the given [`PoolContext`]s and logs an alert for each pool
+/// that is above the warning threshold.
+///
+/// Returns the alerts for the pools that are above the warning threshold, sorted by descending utilization.
+pub fn check_pools_utilization(
+    pool_ctxs: &[PoolContext],
+    thresholds: &PoolUtilizationThresholds,
+) -> Vec<PoolUtilizationAlert> {
+    let mut alerts = pool_ctxs
+        .iter()
+        .map(|p| check_pool_utilization(p, thresholds))
+        .filter(|a| a.severity != PoolUtilizationSeverity::Normal)
+        .collect::<Vec<PoolUtilizationAlert>>();
+    alerts.sort_by(|a, b| b.utilization_rate.cmp(&a.utilization_rate));
+
+    for alert in alerts.iter() {
+        match alert.severity {
+            PoolUtilizationSeverity::Critical => warn!(
+                "Pool {} ({}) is at critical utilization: {} - Available liquidity: {}",
+                alert.pool, alert.token_mint, alert.utilization_rate, alert.available_liquidity
+            ),
+            PoolUtilizationSeverity::Warning => info!(
+                "Pool {} ({}) is at elevated utilization: {} - Available liquidity: {}",
+                alert.pool, alert.token_mint, alert.utilization_rate, alert.available_liquidity
+            ),
+            PoolUtilizationSeverity::Normal => (),
+        }
+    }
+
+    alerts
+}
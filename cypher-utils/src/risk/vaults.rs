@@ -0,0 +1,171 @@
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::TokenAccount;
+use cypher_client::{math::MathError, Pool, PoolNode};
+use fixed::types::I80F48;
+use log::warn;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+use crate::contexts::{ContextError, PoolContext};
+
+/// The result of reconciling a pool node's vault balance against it's state.
+#[derive(Debug, Clone)]
+pub struct VaultReconciliation {
+    /// The pool.
+    pub pool: Pubkey,
+    /// The pool node.
+    pub pool_node: Pubkey,
+    /// The pool node's token vault.
+    pub vault: Pubkey,
+    /// The SPL Token balance of the vault, in native units.
+    pub vault_balance: u64,
+    /// The balance implied by the pool node's deposits, borrows and protocol fees, in native units.
+    pub expected_balance: I80F48,
+    /// The difference between the vault balance and the expected balance.
+    ///
+    /// A negative value means the vault holds less tokens than the pool node's state implies.
+    pub discrepancy: I80F48,
+}
+
+impl VaultReconciliation {
+    /// Whether the absolute discrepancy is larger than the given tolerance, in native units.
+    pub fn is_discrepant(&self, tolerance: I80F48) -> bool {
+        self.discrepancy.abs() > tolerance
+    }
+}
+
+/// Gets the balance the vault of the given [`PoolNode`] is expected to hold, in native units.
+///
+/// This is the node's deposits minus the node's borrows, both adjusted by the pool's indexes,
+/// plus the protocol fees that have not yet been swept.
+///
+/// ### Errors
+///
+/// This function will return an error if the expected balance overflows.
+pub fn get_expected_vault_balance(pool: &Pool, pool_node: &PoolNode) -> Result<I80F48, MathError> {
+    pool_node
+        .total_deposits(pool)
+        .checked_sub(pool_node.total_borrows(pool))
+        .and_then(|n| n.checked_add(I80F48::from(pool_node.protocol_fees)))
+        .ok_or(MathError::Overflow("expected vault balance"))
+}
+
+/// Reconciles the given [`PoolNode`] state against the given vault balance.
+///
+/// ### Errors
+///
+/// This function will return an error if the expected balance or the discrepancy overflows.
+pub fn reconcile_pool_node_vault(
+    pool: &Pubkey,
+    pool_state: &Pool,
+    pool_node: &Pubkey,
+    pool_node_state: &PoolNode,
+    vault_balance: u64,
+) -> Result<VaultReconciliation, MathError> {
+    let expected_balance = match get_expected_vault_balance(pool_state, pool_node_state) {
+        Ok(b) => b,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let discrepancy = I80F48::from(vault_balance)
+        .checked_sub(expected_balance)
+        .ok_or(MathError::Overflow("vault discrepancy"))?;
+    Ok(VaultReconciliation {
+        pool: *pool,
+        pool_node: *pool_node,
+        vault: pool_node_state.token_vault,
+        vault_balance,
+        expected_balance,
+        discrepancy,
+    })
+}
+
+/// Fetches the vault balances of all loaded pool nodes of the given [`PoolContext`]s
+/// and reconciles them against the pool node states.
+///
+/// Every reconciliation whose discrepancy exceeds the given tolerance is logged,
+/// pool nodes whose vault can not be deserialized or reconciled are logged and skipped.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn reconcile_pool_vaults(
+    rpc_client: &Arc<RpcClient>,
+    pool_ctxs: &[PoolContext],
+    tolerance: I80F48,
+) -> Result<Vec<VaultReconciliation>, ContextError> {
+    let nodes = pool_ctxs
+        .iter()
+        .flat_map(|p| p.pool_nodes.iter().map(move |pn| (p, pn)))
+        .collect::<Vec<_>>();
+    let vaults = nodes
+        .iter()
+        .map(|(_, pn)| pn.state.token_vault)
+        .collect::<Vec<Pubkey>>();
+
+    let mut vault_accounts = Vec::with_capacity(vaults.len());
+    // the rpc limits the number of accounts per request
+    for chunk in vaults.chunks(100) {
+        match rpc_client.get_multiple_accounts(chunk).await {
+            Ok(a) => vault_accounts.extend(a),
+            Err(e) => {
+                return Err(ContextError::ClientError(e));
+            }
+        }
+    }
+
+    let mut reconciliations = Vec::with_capacity(nodes.len());
+    for ((pool_ctx, pool_node_ctx), vault_account) in nodes.iter().zip(vault_accounts.iter()) {
+        let vault_balance = match vault_account {
+            Some(a) => match TokenAccount::try_deserialize(&mut a.data.as_slice()) {
+                Ok(ta) => ta.amount,
+                Err(e) => {
+                    warn!(
+                        "Failed to deserialize vault {} of pool node {}: {}",
+                        pool_node_ctx.state.token_vault,
+                        pool_node_ctx.address,
+                        e.to_string()
+                    );
+                    continue;
+                }
+            },
+            None => {
+                return Err(ContextError::AccountNotFound(format!(
+                    "Could not find vault {} of pool node {}",
+                    pool_node_ctx.state.token_vault, pool_node_ctx.address
+                )));
+            }
+        };
+        let reconciliation = match reconcile_pool_node_vault(
+            &pool_ctx.address,
+            &pool_ctx.state,
+            &pool_node_ctx.address,
+            &pool_node_ctx.state,
+            vault_balance,
+        ) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!(
+                    "Failed to reconcile vault {} of pool node {}: {}",
+                    pool_node_ctx.state.token_vault, pool_node_ctx.address, e
+                );
+                continue;
+            }
+        };
+        if reconciliation.is_discrepant(tolerance) {
+            warn!(
+                "Vault {} of pool node {} has a discrepancy of {} - Balance: {} - Expected: {}",
+                reconciliation.vault,
+                reconciliation.pool_node,
+                reconciliation.discrepancy,
+                reconciliation.vault_balance,
+                reconciliation.expected_balance
+            );
+        }
+        reconciliations.push(reconciliation);
+    }
+
+    Ok(reconciliations)
+}
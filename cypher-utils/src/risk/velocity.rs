@@ -0,0 +1,230 @@
+use cypher_client::{
+    math::MathError, utils::try_adjust_decimals, CacheAccount, Clearing, CypherSubAccount,
+    MarginCollateralRatioType,
+};
+use fixed::types::I80F48;
+use log::warn;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+/// The number of basis points in one unit.
+const BPS_UNIT: u64 = 10_000;
+
+/// An observation of a price cache's oracle price.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PriceObservation {
+    /// The slot at which the price was observed.
+    pub slot: u64,
+    /// The observed oracle price.
+    pub price: I80F48,
+    /// The rate of change of the price since the previous observation, in bps per slot.
+    pub velocity: I80F48,
+}
+
+/// Tracks the rate of change of the oracle prices in the [`CacheAccount`].
+///
+/// The velocity of each price cache is smoothed with an exponential moving average
+/// so a single noisy update does not reorder the liquidator's scanning queue.
+#[derive(Debug, Clone)]
+pub struct PriceVelocityTracker {
+    /// The smoothing factor applied to new velocity samples, between zero and one.
+    pub smoothing: I80F48,
+    observations: HashMap<usize, PriceObservation>,
+}
+
+impl Default for PriceVelocityTracker {
+    fn default() -> Self {
+        Self::new(I80F48::from_num(0.5))
+    }
+}
+
+impl PriceVelocityTracker {
+    /// Creates a new [`PriceVelocityTracker`] with the given smoothing factor.
+    pub fn new(smoothing: I80F48) -> Self {
+        Self {
+            smoothing: smoothing.clamp(I80F48::ZERO, I80F48::ONE),
+            observations: HashMap::new(),
+        }
+    }
+
+    /// Updates the tracker with the prices in the given [`CacheAccount`], observed at the given slot.
+    ///
+    /// Observations at a slot that is not newer than the previous observation are ignored.
+    pub fn update(&mut self, cache_account: &CacheAccount, slot: u64) {
+        for (idx, cache) in cache_account.caches.iter().enumerate() {
+            if cache.oracle_products == Pubkey::default() {
+                continue;
+            }
+            let price = cache.oracle_price();
+            let observation = match self.observations.get(&idx) {
+                Some(previous) => {
+                    if slot <= previous.slot {
+                        continue;
+                    }
+                    let velocity = if previous.price == I80F48::ZERO {
+                        I80F48::ZERO
+                    } else {
+                        let slots = I80F48::from(slot - previous.slot);
+                        (price - previous.price)
+                            .saturating_div(previous.price)
+                            .saturating_mul(I80F48::from(BPS_UNIT))
+                            .saturating_div(slots)
+                    };
+                    PriceObservation {
+                        slot,
+                        price,
+                        velocity: previous.velocity
+                            + (velocity - previous.velocity).saturating_mul(self.smoothing),
+                    }
+                }
+                None => PriceObservation {
+                    slot,
+                    price,
+                    velocity: I80F48::ZERO,
+                },
+            };
+            self.observations.insert(idx, observation);
+        }
+    }
+
+    /// Gets the latest observation for the price cache at the given index.
+    pub fn get_observation(&self, cache_idx: usize) -> Option<&PriceObservation> {
+        self.observations.get(&cache_idx)
+    }
+
+    /// Gets the velocity of the price cache at the given index, in bps per slot.
+    pub fn get_velocity(&self, cache_idx: usize) -> I80F48 {
+        match self.observations.get(&cache_idx) {
+            Some(o) => o.velocity,
+            None => I80F48::ZERO,
+        }
+    }
+}
+
+/// The rate at which a sub account's health is changing due to the price velocity of it's positions.
+#[derive(Debug, Clone)]
+pub struct HealthVelocity {
+    /// The sub account.
+    pub sub_account: Pubkey,
+    /// The sub account's master account.
+    pub master_account: Pubkey,
+    /// The change in value of the sub account's positions, in quote per slot.
+    ///
+    /// A negative value means the sub account's health is deteriorating.
+    pub value_velocity: I80F48,
+    /// The value by which the maintenance weighted assets exceed the maintenance requirement.
+    pub margin_buffer: I80F48,
+    /// The estimated number of slots until the sub account reaches the maintenance margin,
+    /// if it's health is deteriorating.
+    pub slots_to_maintenance: Option<u64>,
+}
+
+/// Gets the [`HealthVelocity`] of the given [`CypherSubAccount`].
+///
+/// ### Errors
+///
+/// This function will return an error if a position references an invalid price cache
+/// or the sub account's assets or liabilities value can not be computed.
+pub fn get_sub_account_health_velocity(
+    tracker: &PriceVelocityTracker,
+    clearing: &Clearing,
+    cache_account: &CacheAccount,
+    sub_account: &Pubkey,
+    sub_account_state: &CypherSubAccount,
+) -> Result<HealthVelocity, MathError> {
+    let mcr_type = MarginCollateralRatioType::Maintenance;
+    let mut value_velocity = I80F48::ZERO;
+
+    for position in sub_account_state.iter_position_slots() {
+        if position.spot.token_mint != Pubkey::default() {
+            let cache_idx = position.spot.cache_index as usize;
+            let cache = cache_account.try_get_price_cache(cache_idx)?;
+            let exposure =
+                try_adjust_decimals(position.spot.try_total_position(cache)?, cache.decimals)?
+                    .saturating_mul(cache.oracle_price());
+            value_velocity += exposure
+                .saturating_mul(tracker.get_velocity(cache_idx))
+                .saturating_div(I80F48::from(BPS_UNIT));
+        }
+        if position.derivative.market != Pubkey::default() {
+            let cache_idx = position.derivative.cache_index as usize;
+            let cache = cache_account.try_get_price_cache(cache_idx)?;
+            let decimals = cache.decimals_for(position.derivative.market_type);
+            let exposure = try_adjust_decimals(position.derivative.base_position(), decimals)?
+                .saturating_mul(cache.oracle_price());
+            value_velocity += exposure
+                .saturating_mul(tracker.get_velocity(cache_idx))
+                .saturating_div(I80F48::from(BPS_UNIT));
+        }
+    }
+
+    let (assets_value, _) = sub_account_state.get_assets_value(cache_account, mcr_type)?;
+    let (liabilities_value, _) =
+        sub_account_state.get_liabilities_value(cache_account, mcr_type)?;
+    let margin_buffer =
+        assets_value - liabilities_value.saturating_mul(clearing.maint_margin_ratio());
+
+    let slots_to_maintenance = if value_velocity.is_negative() {
+        if margin_buffer.is_positive() {
+            Some(
+                margin_buffer
+                    .saturating_div(value_velocity.abs())
+                    .ceil()
+                    .saturating_to_num::<u64>(),
+            )
+        } else {
+            Some(0)
+        }
+    } else {
+        None
+    };
+
+    Ok(HealthVelocity {
+        sub_account: *sub_account,
+        master_account: sub_account_state.master_account,
+        value_velocity,
+        margin_buffer,
+        slots_to_maintenance,
+    })
+}
+
+/// Ranks the given [`CypherSubAccount`]s by how fast their health is deteriorating.
+///
+/// Only sub accounts whose health is deteriorating are returned, sorted by the estimated number of slots
+/// until they reach the maintenance margin, so the liquidator can scan the most at-risk accounts first.
+/// If `max_slots` is provided, sub accounts that are further than `max_slots` from maintenance are omitted,
+/// as are sub accounts whose health can not be computed.
+pub fn rank_sub_accounts_by_health_velocity(
+    tracker: &PriceVelocityTracker,
+    clearing: &Clearing,
+    cache_account: &CacheAccount,
+    sub_accounts: &[(Pubkey, Box<CypherSubAccount>)],
+    max_slots: Option<u64>,
+) -> Vec<HealthVelocity> {
+    let mut ranked = sub_accounts
+        .iter()
+        .filter_map(|(pubkey, state)| {
+            match get_sub_account_health_velocity(tracker, clearing, cache_account, pubkey, state) {
+                Ok(hv) => Some(hv),
+                Err(e) => {
+                    warn!(
+                        "Failed to compute health velocity of sub account {}: {}",
+                        pubkey, e
+                    );
+                    None
+                }
+            }
+        })
+        .filter(|hv| match (hv.slots_to_maintenance, max_slots) {
+            (Some(slots), Some(max)) => slots <= max,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+        .collect::<Vec<HealthVelocity>>();
+    ranked.sort_by(|a, b| {
+        a.slots_to_maintenance
+            .cmp(&b.slots_to_maintenance)
+            .then(a.value_velocity.cmp(&b.value_velocity))
+    });
+    ranked
+}
@@ -0,0 +1,189 @@
+use anchor_lang::{Owner, ZeroCopy};
+use cypher_client::{utils::get_zero_copy_account, CypherAccount, CypherSubAccount, OrdersAccount};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+
+use crate::{contexts::ContextError, layouts::get_data_size_filter, utils::get_program_accounts};
+
+/// The offset of the `clearing` field in the [`CypherAccount`], including the discriminator.
+pub const CYPHER_ACCOUNT_CLEARING_OFFSET: usize = 24;
+/// The offset of the `authority` field in the [`CypherAccount`], including the discriminator.
+pub const CYPHER_ACCOUNT_AUTHORITY_OFFSET: usize = 56;
+/// The offset of the `clearing` field in the [`CypherSubAccount`], including the discriminator.
+pub const CYPHER_SUB_ACCOUNT_CLEARING_OFFSET: usize = 56;
+/// The offset of the `master_account` field in the [`CypherSubAccount`], including the discriminator.
+pub const CYPHER_SUB_ACCOUNT_MASTER_ACCOUNT_OFFSET: usize = 88;
+/// The offset of the `authority` field in the [`CypherSubAccount`], including the discriminator.
+pub const CYPHER_SUB_ACCOUNT_AUTHORITY_OFFSET: usize = 120;
+/// The offset of the `authority` field in the [`OrdersAccount`], including the discriminator.
+pub const ORDERS_ACCOUNT_AUTHORITY_OFFSET: usize = 16;
+/// The offset of the `market` field in the [`OrdersAccount`], including the discriminator.
+pub const ORDERS_ACCOUNT_MARKET_OFFSET: usize = 48;
+/// The offset of the `master_account` field in the [`OrdersAccount`], including the discriminator.
+pub const ORDERS_ACCOUNT_MASTER_ACCOUNT_OFFSET: usize = 80;
+
+/// Gets the filters for all accounts of type `T` with the given [`Pubkey`] at the given offset.
+///
+/// The offset must include the discriminator, see the offsets exported by this module.
+pub fn get_account_filters<T: ZeroCopy>(offset: usize, pubkey: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        get_data_size_filter::<T>(),
+        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, pubkey.as_ref())),
+    ]
+}
+
+/// Gets the filters for all [`CypherAccount`]s of the given clearing.
+pub fn clearing_accounts_filters(clearing: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<CypherAccount>(CYPHER_ACCOUNT_CLEARING_OFFSET, clearing)
+}
+
+/// Gets the filters for all [`CypherAccount`]s of the given authority.
+pub fn authority_accounts_filters(authority: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<CypherAccount>(CYPHER_ACCOUNT_AUTHORITY_OFFSET, authority)
+}
+
+/// Gets the filters for all [`CypherSubAccount`]s.
+pub fn sub_accounts_filters() -> Vec<RpcFilterType> {
+    vec![get_data_size_filter::<CypherSubAccount>()]
+}
+
+/// Gets the filters for all [`CypherSubAccount`]s of the given clearing.
+pub fn clearing_sub_accounts_filters(clearing: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<CypherSubAccount>(CYPHER_SUB_ACCOUNT_CLEARING_OFFSET, clearing)
+}
+
+/// Gets the filters for all [`CypherSubAccount`]s of the given master account.
+pub fn master_account_sub_accounts_filters(master_account: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<CypherSubAccount>(
+        CYPHER_SUB_ACCOUNT_MASTER_ACCOUNT_OFFSET,
+        master_account,
+    )
+}
+
+/// Gets the filters for all [`CypherSubAccount`]s of the given authority.
+pub fn authority_sub_accounts_filters(authority: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<CypherSubAccount>(CYPHER_SUB_ACCOUNT_AUTHORITY_OFFSET, authority)
+}
+
+/// Gets the filters for all [`OrdersAccount`]s of the given market.
+pub fn market_orders_accounts_filters(market: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<OrdersAccount>(ORDERS_ACCOUNT_MARKET_OFFSET, market)
+}
+
+/// Gets the filters for all [`OrdersAccount`]s of the given master account.
+pub fn master_account_orders_accounts_filters(master_account: &Pubkey) -> Vec<RpcFilterType> {
+    get_account_filters::<OrdersAccount>(ORDERS_ACCOUNT_MASTER_ACCOUNT_OFFSET, master_account)
+}
+
+/// Gets all program accounts of type `T` that match the given filters.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn scan_accounts<T: ZeroCopy + Owner>(
+    rpc_client: &Arc<RpcClient>,
+    filters: Vec<RpcFilterType>,
+) -> Result<Vec<(Pubkey, Box<T>)>, ContextError> {
+    match get_program_accounts(rpc_client, filters, &cypher_client::id()).await {
+        Ok(s) => Ok(s
+            .iter()
+            .map(|(pubkey, account)| (*pubkey, get_zero_copy_account::<T>(&account.data)))
+            .collect()),
+        Err(e) => Err(ContextError::ClientError(e)),
+    }
+}
+
+/// Gets all [`CypherAccount`]s of the given clearing.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_clearing_accounts(
+    rpc_client: &Arc<RpcClient>,
+    clearing: &Pubkey,
+) -> Result<Vec<(Pubkey, Box<CypherAccount>)>, ContextError> {
+    scan_accounts(rpc_client, clearing_accounts_filters(clearing)).await
+}
+
+/// Gets all [`CypherAccount`]s of the given authority.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_authority_accounts(
+    rpc_client: &Arc<RpcClient>,
+    authority: &Pubkey,
+) -> Result<Vec<(Pubkey, Box<CypherAccount>)>, ContextError> {
+    scan_accounts(rpc_client, authority_accounts_filters(authority)).await
+}
+
+/// Gets all [`CypherSubAccount`]s.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_all_sub_accounts(
+    rpc_client: &Arc<RpcClient>,
+) -> Result<Vec<(Pubkey, Box<CypherSubAccount>)>, ContextError> {
+    scan_accounts(rpc_client, sub_accounts_filters()).await
+}
+
+/// Gets all [`CypherSubAccount`]s of the given clearing.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_clearing_sub_accounts(
+    rpc_client: &Arc<RpcClient>,
+    clearing: &Pubkey,
+) -> Result<Vec<(Pubkey, Box<CypherSubAccount>)>, ContextError> {
+    scan_accounts(rpc_client, clearing_sub_accounts_filters(clearing)).await
+}
+
+/// Gets all [`CypherSubAccount`]s of the given master account.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_sub_accounts(
+    rpc_client: &Arc<RpcClient>,
+    master_account: &Pubkey,
+) -> Result<Vec<(Pubkey, Box<CypherSubAccount>)>, ContextError> {
+    scan_accounts(
+        rpc_client,
+        master_account_sub_accounts_filters(master_account),
+    )
+    .await
+}
+
+/// Gets all [`OrdersAccount`]s of the given market.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_market_orders_accounts(
+    rpc_client: &Arc<RpcClient>,
+    market: &Pubkey,
+) -> Result<Vec<(Pubkey, Box<OrdersAccount>)>, ContextError> {
+    scan_accounts(rpc_client, market_orders_accounts_filters(market)).await
+}
+
+/// Gets all [`OrdersAccount`]s of the given master account.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_master_account_orders_accounts(
+    rpc_client: &Arc<RpcClient>,
+    master_account: &Pubkey,
+) -> Result<Vec<(Pubkey, Box<OrdersAccount>)>, ContextError> {
+    scan_accounts(
+        rpc_client,
+        master_account_orders_accounts_filters(master_account),
+    )
+    .await
+}
@@ -1,5 +1,8 @@
 use {
-    crate::constants::{JSON_RPC_URL, PUBSUB_RPC_URL},
+    crate::{
+        constants::{JSON_RPC_URL, PUBSUB_RPC_URL},
+        fees::{get_priority_fee_ema, get_priority_fee_percentile, PriorityFeePercentile},
+    },
     futures::StreamExt,
     log::{info, warn},
     solana_client::{
@@ -383,4 +386,39 @@ impl ChainMetaService {
             None => Vec::new(),
         }
     }
+
+    /// Gets the given percentile of the recent priority fees for the given accounts, in micro-lamports.
+    ///
+    /// Returns `None` if there are no recent priority fees for the accounts.
+    #[inline(always)]
+    pub async fn get_priority_fee_percentile(
+        self: &Arc<Self>,
+        alias: &str,
+        percentile: PriorityFeePercentile,
+    ) -> Option<u64> {
+        let fees = self.get_priority_fees_for_accounts(alias).await;
+        get_priority_fee_percentile(&fees, percentile.percentile())
+    }
+
+    /// Gets the exponential moving average of the recent priority fees for the given accounts
+    /// over the given span of slots, in micro-lamports.
+    ///
+    /// Returns `None` if there are no recent priority fees for the accounts.
+    #[inline(always)]
+    pub async fn get_priority_fee_ema(self: &Arc<Self>, alias: &str, span: u64) -> Option<u64> {
+        let fees = self.get_priority_fees_for_accounts(alias).await;
+        get_priority_fee_ema(&fees, span)
+    }
+
+    /// Gets the given percentile of the general recent priority fees, in micro-lamports.
+    ///
+    /// Returns `None` if there are no recent priority fees.
+    #[inline(always)]
+    pub async fn get_general_priority_fee_percentile(
+        self: &Arc<Self>,
+        percentile: PriorityFeePercentile,
+    ) -> Option<u64> {
+        let fees = self.get_priority_fees().await;
+        get_priority_fee_percentile(&fees, percentile.percentile())
+    }
 }
@@ -0,0 +1,124 @@
+use {
+    crate::accounts_cache::AccountsCache,
+    log::{info, warn},
+    solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient},
+    solana_sdk::{clock::Clock, sysvar},
+    std::sync::Arc,
+    tokio::sync::{
+        broadcast::{error::RecvError, Sender},
+        RwLock,
+    },
+};
+
+/// The length of the [`Clock`] sysvar account data.
+const CLOCK_LEN: usize = 40;
+
+/// Parses the [`Clock`] from the given sysvar account data.
+///
+/// Returns `None` if the data is too short.
+pub fn parse_clock(account_data: &[u8]) -> Option<Clock> {
+    if account_data.len() < CLOCK_LEN {
+        return None;
+    }
+    let read_u64 =
+        |offset: usize| u64::from_le_bytes(account_data[offset..offset + 8].try_into().unwrap());
+    Some(Clock {
+        slot: read_u64(0),
+        epoch_start_timestamp: read_u64(8) as i64,
+        epoch: read_u64(16),
+        leader_schedule_epoch: read_u64(24),
+        unix_timestamp: read_u64(32) as i64,
+    })
+}
+
+/// A Service which keeps track of the cluster's [`Clock`] sysvar via the [`AccountsCache`].
+///
+/// The cache must be fed updates for the [`Clock`] sysvar, e.g. by adding it to the accounts of a subscription service.
+pub struct ClockService {
+    cache: Arc<AccountsCache>,
+    clock: RwLock<Clock>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for ClockService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClockService").finish()
+    }
+}
+
+impl ClockService {
+    /// Creates a new [`ClockService`].
+    pub fn new(cache: Arc<AccountsCache>, shutdown: Arc<Sender<bool>>) -> Self {
+        Self {
+            cache,
+            clock: RwLock::new(Clock::default()),
+            shutdown,
+        }
+    }
+
+    /// Fetches the [`Clock`] sysvar using the given [`RpcClient`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn fetch(&self, rpc_client: &RpcClient) -> Result<Clock, ClientError> {
+        let data = match rpc_client.get_account_data(&sysvar::clock::id()).await {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        if let Some(clock) = parse_clock(&data) {
+            *self.clock.write().await = clock.clone();
+            return Ok(clock);
+        }
+        Ok(self.get_clock().await)
+    }
+
+    /// Starts the service's work cycle.
+    /// Processes updates to the [`Clock`] sysvar in the [`AccountsCache`] until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        if let Some(state) = self.cache.get(&sysvar::clock::id()) {
+            if let Some(clock) = parse_clock(&state.data) {
+                *self.clock.write().await = clock;
+            }
+        }
+
+        let mut receiver = self.cache.subscribe(&[sysvar::clock::id()]).await;
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => {
+                    match update {
+                        Ok(state) => {
+                            if let Some(clock) = parse_clock(&state.data) {
+                                *self.clock.write().await = clock;
+                            }
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => {
+                            warn!("Clock updates channel closed.");
+                            break;
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down clock service.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Gets the latest [`Clock`].
+    pub async fn get_clock(&self) -> Clock {
+        self.clock.read().await.clone()
+    }
+
+    /// Gets the latest cluster unix timestamp.
+    pub async fn get_unix_timestamp(&self) -> i64 {
+        self.clock.read().await.unix_timestamp
+    }
+}
@@ -0,0 +1,324 @@
+use {
+    cypher_client::{
+        logs::{decode_instruction_names, decode_logs, CypherEvent},
+        DepositOrWithdrawLog, FundingPaymentLog, LiquidatePositionLog, OrderFillLog,
+        SettlePositionLog, TransferBetweenSubAccountsLog,
+    },
+    futures::{stream::select_all, StreamExt},
+    log::{debug, info, warn},
+    solana_client::{
+        nonblocking::pubsub_client::{PubsubClient, PubsubClientError},
+        rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::{
+        collections::{HashSet, VecDeque},
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    },
+    tokio::sync::broadcast::{channel, Receiver, Sender},
+};
+
+/// The number of recent signatures remembered to drop transactions delivered by more than one subscription.
+const RECENT_SIGNATURES_CAPACITY: usize = 1024;
+
+/// The cypher instructions which place, cancel or prune orders.
+const ORDER_INSTRUCTIONS: [&str; 11] = [
+    "NewSpotOrder",
+    "CancelSpotOrder",
+    "NewPerpOrder",
+    "MultipleNewPerpOrders",
+    "CancelPerpOrder",
+    "CancelPerpOrders",
+    "PrunePerpOrders",
+    "NewFuturesOrder",
+    "MultipleNewFuturesOrders",
+    "CancelFuturesOrder",
+    "CancelFuturesOrders",
+];
+
+/// The kind of activity recorded by the [`DropCopyService`].
+pub enum DropCopyEventKind {
+    /// An order instruction was executed successfully.
+    OrderAck(String),
+    /// An order was filled.
+    Fill(OrderFillLog),
+    /// A funding payment was settled.
+    FundingSettlement(FundingPaymentLog),
+    /// A futures position was settled.
+    PositionSettlement(SettlePositionLog),
+    /// A deposit or withdrawal.
+    DepositOrWithdraw(DepositOrWithdrawLog),
+    /// A transfer between sub accounts.
+    Transfer(TransferBetweenSubAccountsLog),
+    /// A liquidation, either as the liqor or the liqee.
+    Liquidation(LiquidatePositionLog),
+}
+
+impl DropCopyEventKind {
+    /// The name of the kind of activity.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OrderAck(_) => "OrderAck",
+            Self::Fill(_) => "Fill",
+            Self::FundingSettlement(_) => "FundingSettlement",
+            Self::PositionSettlement(_) => "PositionSettlement",
+            Self::DepositOrWithdraw(_) => "DepositOrWithdraw",
+            Self::Transfer(_) => "Transfer",
+            Self::Liquidation(_) => "Liquidation",
+        }
+    }
+
+    /// Gets the accounts affected by the activity, as logged by the program.
+    fn get_affected_accounts(&self) -> Vec<Pubkey> {
+        match self {
+            Self::OrderAck(_) => Vec::new(),
+            Self::Fill(f) => vec![f.maker, f.taker],
+            Self::FundingSettlement(f) => vec![f.account, f.sub_account],
+            Self::PositionSettlement(s) => vec![s.master_account, s.sub_account],
+            Self::DepositOrWithdraw(d) => vec![d.master_account, d.sub_account],
+            Self::Transfer(t) => vec![t.master_account, t.from_sub_account, t.to_sub_account],
+            Self::Liquidation(l) => vec![
+                l.liqee_master_account,
+                l.liqee_sub_account,
+                l.liqor_master_account,
+                l.liqor_sub_account,
+            ],
+        }
+    }
+
+    fn from_event(event: CypherEvent) -> Option<Self> {
+        match event {
+            CypherEvent::OrderFill(f) => Some(Self::Fill(f)),
+            CypherEvent::FundingPayment(f) => Some(Self::FundingSettlement(f)),
+            CypherEvent::SettlePosition(s) => Some(Self::PositionSettlement(s)),
+            CypherEvent::DepositOrWithdraw(d) => Some(Self::DepositOrWithdraw(d)),
+            CypherEvent::TransferBetweenSubAccounts(t) => Some(Self::Transfer(t)),
+            CypherEvent::LiquidatePosition(l) => Some(Self::Liquidation(l)),
+            _ => None,
+        }
+    }
+}
+
+/// A record of activity affecting one of the accounts configured in the [`DropCopyService`].
+pub struct DropCopyEvent {
+    /// The sequence number, strictly increasing and without gaps across all recorded events.
+    pub seq: u64,
+    /// The slot at which the transaction was observed.
+    pub slot: u64,
+    /// The signature of the transaction.
+    pub signature: String,
+    /// The configured account which is affected.
+    pub account: Pubkey,
+    /// The activity.
+    pub kind: DropCopyEventKind,
+}
+
+impl std::fmt::Debug for DropCopyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DropCopyEvent")
+            .field("seq", &self.seq)
+            .field("slot", &self.slot)
+            .field("signature", &self.signature)
+            .field("account", &format!("{}", self.account))
+            .field("kind", &self.kind.name())
+            .finish()
+    }
+}
+
+/// A Service which records all activity affecting the configured accounts, i.e. order acks, fills, funding and position
+/// settlements, deposits, withdrawals, transfers and liquidations, into one ordered stream of sequenced events.
+///
+/// The configured accounts can be master accounts, sub accounts or orders accounts. The transaction logs mentioning each
+/// of them are subscribed to, fills of resting orders are only recorded if the transaction which consumed them mentions
+/// one of the configured accounts.
+pub struct DropCopyService {
+    pubsub_client: Arc<PubsubClient>,
+    accounts: Vec<Pubkey>,
+    seq: AtomicU64,
+    recent_signatures: Mutex<(HashSet<String>, VecDeque<String>)>,
+    sender: Arc<Sender<Arc<DropCopyEvent>>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for DropCopyService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DropCopyService")
+            .field("accounts", &self.accounts.len())
+            .field("seq", &self.seq.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl DropCopyService {
+    /// Creates a new [`DropCopyService`] for the given accounts.
+    pub fn new(
+        pubsub_client: Arc<PubsubClient>,
+        accounts: Vec<Pubkey>,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            pubsub_client,
+            accounts,
+            seq: AtomicU64::new(0),
+            recent_signatures: Mutex::new((HashSet::new(), VecDeque::new())),
+            sender: Arc::new(channel::<Arc<DropCopyEvent>>(1024).0),
+            shutdown,
+        }
+    }
+
+    /// Subscribes to the recorded events.
+    pub fn subscribe(&self) -> Receiver<Arc<DropCopyEvent>> {
+        self.sender.subscribe()
+    }
+
+    /// Gets the sequence number of the next recorded event.
+    pub fn get_next_seq(&self) -> u64 {
+        self.seq.load(Ordering::Relaxed)
+    }
+
+    /// Starts the service's work cycle.
+    /// Subscribes to the transaction logs that mention each of the configured accounts and processes them until shutdown.
+    #[inline(always)]
+    pub async fn start_service(
+        self: &Arc<Self>,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<(), PubsubClientError> {
+        let mut shutdown_receiver = self.shutdown.subscribe();
+        let mut streams = Vec::new();
+        let mut unsubscribes = Vec::new();
+
+        for account in self.accounts.iter() {
+            let sub = match self
+                .pubsub_client
+                .logs_subscribe(
+                    RpcTransactionLogsFilter::Mentions(vec![account.to_string()]),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(commitment.unwrap_or(CommitmentConfig::confirmed())),
+                    },
+                )
+                .await
+            {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!(
+                        "Failed to subscribe to logs of account {}: {}",
+                        account,
+                        e.to_string()
+                    );
+                    return Err(e);
+                }
+            };
+            let account = *account;
+            streams.push(sub.0.map(move |response| (account, response)));
+            unsubscribes.push(sub.1);
+        }
+
+        let mut stream = select_all(streams);
+        loop {
+            tokio::select! {
+                update = stream.next() => {
+                    match update {
+                        Some((account, response)) => {
+                            if response.value.err.is_some() {
+                                continue;
+                            }
+                            self.process_logs(
+                                &response.value.signature,
+                                response.context.slot,
+                                &response.value.logs,
+                                &account,
+                            );
+                        }
+                        None => {
+                            warn!("Drop copy logs subscription streams ended.");
+                            break;
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down drop copy service.");
+                    break;
+                }
+            }
+        }
+
+        drop(stream);
+        for unsubscribe in unsubscribes {
+            unsubscribe().await;
+        }
+        Ok(())
+    }
+
+    /// Processes the logs of a successful transaction which mentions the given configured account,
+    /// recording an event for every activity affecting the configured accounts.
+    ///
+    /// Transactions which were already processed are ignored. Returns the number of recorded events.
+    pub fn process_logs(
+        &self,
+        signature: &str,
+        slot: u64,
+        logs: &[String],
+        mentioned_account: &Pubkey,
+    ) -> usize {
+        {
+            let mut recent_signatures = self.recent_signatures.lock().unwrap();
+            let (set, queue) = &mut *recent_signatures;
+            if !set.insert(signature.to_string()) {
+                return 0;
+            }
+            queue.push_back(signature.to_string());
+            if queue.len() > RECENT_SIGNATURES_CAPACITY {
+                if let Some(s) = queue.pop_front() {
+                    set.remove(&s);
+                }
+            }
+        }
+
+        let mut recorded = 0;
+        for instruction in decode_instruction_names(logs) {
+            if ORDER_INSTRUCTIONS.contains(&instruction.as_str()) {
+                self.record(
+                    signature,
+                    slot,
+                    *mentioned_account,
+                    DropCopyEventKind::OrderAck(instruction),
+                );
+                recorded += 1;
+            }
+        }
+        for decoded in decode_logs(logs) {
+            let kind = match DropCopyEventKind::from_event(decoded.event) {
+                Some(k) => k,
+                None => continue,
+            };
+            let account = match kind
+                .get_affected_accounts()
+                .into_iter()
+                .find(|a| self.accounts.contains(a))
+            {
+                Some(a) => a,
+                None => continue,
+            };
+            self.record(signature, slot, account, kind);
+            recorded += 1;
+        }
+        recorded
+    }
+
+    fn record(&self, signature: &str, slot: u64, account: Pubkey, kind: DropCopyEventKind) {
+        let event = DropCopyEvent {
+            seq: self.seq.fetch_add(1, Ordering::SeqCst),
+            slot,
+            signature: signature.to_string(),
+            account,
+            kind,
+        };
+        debug!("Drop copy: {:?}", event);
+        if self.sender.send(Arc::new(event)).is_err() {
+            debug!("No active receivers for drop copy events.");
+        }
+    }
+}
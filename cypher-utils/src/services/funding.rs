@@ -0,0 +1,303 @@
+use {
+    crate::storage::{append_events, query_events, Storage, StorageError},
+    cypher_client::{utils::get_zero_copy_account, PerpetualMarket},
+    fixed::types::I80F48,
+    log::{info, warn},
+    serde::{Deserialize, Serialize},
+    solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient},
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::{
+        collections::{HashMap, VecDeque},
+        sync::Arc,
+        time::Duration,
+    },
+    tokio::sync::{broadcast::Sender, RwLock},
+};
+
+/// The storage stream funding observations are persisted to.
+pub const FUNDING_STREAM: &str = "funding";
+
+/// The number of seconds in an hour.
+const SECONDS_PER_HOUR: u64 = 3_600;
+
+/// An observation of a perpetual market's funding, taken whenever the market's funding is updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FundingObservation {
+    /// The market.
+    pub market: Pubkey,
+    /// The slot at which the observation was taken.
+    pub slot: u64,
+    /// The unix timestamp of the market's funding update, in seconds.
+    pub timestamp: u64,
+    /// The cumulative long funding.
+    pub long_funding: I80F48,
+    /// The cumulative short funding.
+    pub short_funding: I80F48,
+    /// The hourly funding rate since the previous funding update, i.e. the change in the cumulative long funding
+    /// per hour, which is paid per unit of base position.
+    ///
+    /// This is `None` for the first observation of a market, since there is no previous update to compare to.
+    pub hourly_funding: Option<I80F48>,
+}
+
+impl FundingObservation {
+    /// Creates the [`FundingObservation`] of the given market state, given the market's previous observation.
+    ///
+    /// Returns `None` if the market's funding was not updated since the previous observation.
+    pub fn new(
+        market: &Pubkey,
+        state: &PerpetualMarket,
+        slot: u64,
+        previous: Option<&FundingObservation>,
+    ) -> Option<Self> {
+        let long_funding = state.long_funding();
+        let hourly_funding = match previous {
+            Some(p) if state.last_funding_update <= p.timestamp => return None,
+            Some(p) => {
+                let elapsed = I80F48::from(state.last_funding_update - p.timestamp);
+                Some((long_funding - p.long_funding) * I80F48::from(SECONDS_PER_HOUR) / elapsed)
+            }
+            None => None,
+        };
+        Some(Self {
+            market: *market,
+            slot,
+            timestamp: state.last_funding_update,
+            long_funding,
+            short_funding: state.short_funding(),
+            hourly_funding,
+        })
+    }
+}
+
+/// The configuration of the [`FundingRecorder`].
+#[derive(Debug, Clone)]
+pub struct FundingRecorderConfig {
+    /// The perpetual markets.
+    pub markets: Vec<Pubkey>,
+    /// The interval at which the markets are read.
+    pub interval: Duration,
+    /// The maximum number of observations kept in memory per market.
+    pub max_observations: usize,
+}
+
+/// A Service which periodically reads perpetual markets and records an observation of their funding
+/// whenever it is updated, building a funding rate history for UIs and carry strategies.
+///
+/// Observations can optionally be persisted to a [`Storage`] backend so the history survives restarts.
+pub struct FundingRecorder {
+    rpc_client: Arc<RpcClient>,
+    config: FundingRecorderConfig,
+    series: RwLock<HashMap<Pubkey, VecDeque<FundingObservation>>>,
+    storage: Option<Box<dyn Storage>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for FundingRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FundingRecorder")
+            .field("config", &self.config)
+            .field("storage", &self.storage.is_some())
+            .finish()
+    }
+}
+
+impl FundingRecorder {
+    /// Creates a new [`FundingRecorder`], which persists observations to the given [`Storage`], if any.
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        config: FundingRecorderConfig,
+        storage: Option<Box<dyn Storage>>,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            rpc_client,
+            config,
+            series: RwLock::new(HashMap::new()),
+            storage,
+            shutdown,
+        }
+    }
+
+    /// Loads the observations persisted to the [`Storage`] with a timestamp between `start` and `end`, inclusive.
+    ///
+    /// Returns the number of loaded observations.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the observations could not be read.
+    pub async fn load_history(&self, start: i64, end: i64) -> Result<usize, StorageError> {
+        let observations = match &self.storage {
+            Some(storage) => {
+                match query_events::<FundingObservation>(
+                    storage.as_ref(),
+                    FUNDING_STREAM,
+                    start,
+                    end,
+                ) {
+                    Ok(o) => o,
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+            }
+            None => return Ok(0),
+        };
+        let loaded = observations.len();
+        let mut series = self.series.write().await;
+        for observation in observations {
+            self.push(&mut series, observation);
+        }
+        Ok(loaded)
+    }
+
+    /// Starts the service's work cycle.
+    /// Reads the markets at the configured interval until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.interval);
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.record().await {
+                        warn!("Failed to read perpetual markets: {}", e.to_string());
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down funding recorder.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Reads the markets and records an observation for each market whose funding was updated.
+    ///
+    /// Returns the number of recorded observations. Observations that could not be persisted are still recorded in memory.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC request.
+    pub async fn record(&self) -> Result<usize, ClientError> {
+        let response = match self
+            .rpc_client
+            .get_multiple_accounts_with_commitment(
+                &self.config.markets,
+                CommitmentConfig::confirmed(),
+            )
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let slot = response.context.slot;
+
+        let mut series = self.series.write().await;
+        let mut observations = Vec::new();
+        for (market, account) in self.config.markets.iter().zip(response.value.iter()) {
+            let account = match account {
+                Some(a) => a,
+                None => {
+                    warn!("Perpetual market {} does not exist.", market);
+                    continue;
+                }
+            };
+            let state = get_zero_copy_account::<PerpetualMarket>(&account.data);
+            let previous = series.get(market).and_then(|s| s.back());
+            if let Some(observation) = FundingObservation::new(market, &state, slot, previous) {
+                self.push(&mut series, observation);
+                observations.push(observation);
+            }
+        }
+        drop(series);
+
+        if let Some(storage) = &self.storage {
+            if !observations.is_empty() {
+                if let Err(e) = append_events(
+                    storage.as_ref(),
+                    FUNDING_STREAM,
+                    &observations
+                        .iter()
+                        .map(|o| (o.slot, o.timestamp as i64, *o))
+                        .collect::<Vec<_>>(),
+                ) {
+                    warn!("Failed to persist funding observations: {}", e.to_string());
+                }
+            }
+        }
+        Ok(observations.len())
+    }
+
+    fn push(
+        &self,
+        series: &mut HashMap<Pubkey, VecDeque<FundingObservation>>,
+        observation: FundingObservation,
+    ) {
+        let market_series = series.entry(observation.market).or_default();
+        if let Some(last) = market_series.back() {
+            if observation.timestamp <= last.timestamp {
+                return;
+            }
+        }
+        market_series.push_back(observation);
+        while market_series.len() > self.config.max_observations {
+            market_series.pop_front();
+        }
+    }
+
+    /// Gets the last `n` observations of the given market, ordered from oldest to newest.
+    pub async fn get_last(&self, market: &Pubkey, n: usize) -> Vec<FundingObservation> {
+        match self.series.read().await.get(market) {
+            Some(s) => s.iter().skip(s.len().saturating_sub(n)).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Gets the most recent observation of the given market, if any.
+    pub async fn get_latest(&self, market: &Pubkey) -> Option<FundingObservation> {
+        self.series
+            .read()
+            .await
+            .get(market)
+            .and_then(|s| s.back().copied())
+    }
+
+    /// Gets the time-weighted average hourly funding rate of the given market over the window of the given number
+    /// of seconds up to the given unix timestamp.
+    ///
+    /// Each observation's rate is weighted by the time since the previous funding update.
+    /// Returns `None` if there are no observations with a rate within the window.
+    pub async fn get_average_hourly_funding(
+        &self,
+        market: &Pubkey,
+        window: u64,
+        now: u64,
+    ) -> Option<I80F48> {
+        let series = self.series.read().await;
+        let market_series = series.get(market)?;
+        let start = now.saturating_sub(window);
+        let mut previous_timestamp = None;
+        let mut weighted_sum = I80F48::ZERO;
+        let mut total_weight = 0u64;
+
+        for observation in market_series.iter() {
+            if let (Some(rate), Some(previous)) = (observation.hourly_funding, previous_timestamp) {
+                if observation.timestamp > start && observation.timestamp <= now {
+                    let weight = observation.timestamp - start.max(previous);
+                    weighted_sum += rate * I80F48::from(weight);
+                    total_weight += weight;
+                }
+            }
+            previous_timestamp = Some(observation.timestamp);
+        }
+
+        if total_weight == 0 {
+            return None;
+        }
+        Some(weighted_sum / I80F48::from(total_weight))
+    }
+}
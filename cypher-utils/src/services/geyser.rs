@@ -0,0 +1,211 @@
+use {
+    crate::accounts_cache::{AccountState, AccountsCache},
+    futures::StreamExt,
+    log::{debug, info, warn},
+    solana_sdk::pubkey::Pubkey,
+    std::{collections::HashMap, sync::Arc, time::Duration},
+    thiserror::Error,
+    tokio::sync::broadcast::Sender,
+    yellowstone_grpc_client::{GeyserGrpcClient, GeyserGrpcClientError},
+    yellowstone_grpc_proto::prelude::{
+        subscribe_update::UpdateOneof, CommitmentLevel, SubscribeRequest,
+        SubscribeRequestFilterAccounts,
+    },
+};
+
+/// The name of the accounts filter sent with the subscription request.
+const ACCOUNTS_FILTER_NAME: &str = "cypher";
+
+#[derive(Debug, Error)]
+pub enum GeyserServiceError {
+    #[error(transparent)]
+    ClientError(#[from] GeyserGrpcClientError),
+    #[error("The Geyser stream was closed.")]
+    StreamClosed,
+}
+
+/// The configuration of the [`GeyserAccountService`].
+#[derive(Debug, Clone)]
+pub struct GeyserConfig {
+    /// The Yellowstone gRPC endpoint.
+    pub endpoint: String,
+    /// The authentication token, if the endpoint requires one.
+    pub x_token: Option<String>,
+    /// The accounts to stream, e.g. slabs and event queues.
+    pub accounts: Vec<Pubkey>,
+    /// The programs whose accounts should all be streamed.
+    pub owners: Vec<Pubkey>,
+    /// The commitment of the updates, defaults to processed for the lowest latency.
+    pub commitment: CommitmentLevel,
+    /// The delay before reconnecting after the stream is closed or fails.
+    pub reconnect_delay: Duration,
+}
+
+impl GeyserConfig {
+    /// Creates a new [`GeyserConfig`] for the given endpoint and accounts.
+    pub fn new(endpoint: &str, x_token: Option<String>, accounts: &[Pubkey]) -> Self {
+        Self {
+            endpoint: endpoint.to_string(),
+            x_token,
+            accounts: accounts.to_vec(),
+            owners: Vec::new(),
+            commitment: CommitmentLevel::Processed,
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+
+    fn get_subscribe_request(&self) -> SubscribeRequest {
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            ACCOUNTS_FILTER_NAME.to_string(),
+            SubscribeRequestFilterAccounts {
+                account: self.accounts.iter().map(|a| a.to_string()).collect(),
+                owner: self.owners.iter().map(|o| o.to_string()).collect(),
+                ..Default::default()
+            },
+        );
+        SubscribeRequest {
+            accounts,
+            commitment: Some(self.commitment as i32),
+            ..Default::default()
+        }
+    }
+}
+
+/// A Service which keeps the [`AccountsCache`] updated from a Yellowstone Geyser gRPC stream.
+///
+/// Geyser updates are pushed as soon as the validator processes an account write, which makes this
+/// service a lower latency alternative to the RPC pubsub based services, e.g. for slabs and event queues.
+pub struct GeyserAccountService {
+    cache: Arc<AccountsCache>,
+    config: GeyserConfig,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for GeyserAccountService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeyserAccountService")
+            .field("endpoint", &self.config.endpoint)
+            .field("accounts", &self.config.accounts.len())
+            .field("owners", &self.config.owners.len())
+            .finish()
+    }
+}
+
+impl GeyserAccountService {
+    /// Creates a new [`GeyserAccountService`].
+    pub fn new(
+        cache: Arc<AccountsCache>,
+        config: GeyserConfig,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            cache,
+            config,
+            shutdown,
+        }
+    }
+
+    /// Starts the service's work cycle.
+    /// Subscribes to the Geyser stream and processes updates until shutdown,
+    /// reconnecting whenever the stream is closed or fails.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                res = self.run() => {
+                    match res {
+                        Ok(()) => (),
+                        Err(e) => {
+                            warn!("Geyser stream failed: {}", e.to_string());
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down Geyser account service.");
+                    break;
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.config.reconnect_delay) => {
+                    info!("Reconnecting to Geyser endpoint {}.", self.config.endpoint);
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down Geyser account service.");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn run(&self) -> Result<(), GeyserServiceError> {
+        let mut client = match GeyserGrpcClient::connect(
+            self.config.endpoint.clone(),
+            self.config.x_token.clone(),
+            None,
+        ) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(GeyserServiceError::ClientError(e));
+            }
+        };
+        let (_sink, mut stream) = match client
+            .subscribe_with_request(Some(self.config.get_subscribe_request()))
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(GeyserServiceError::ClientError(e));
+            }
+        };
+        info!(
+            "Subscribed to {} accounts and {} owners via Geyser.",
+            self.config.accounts.len(),
+            self.config.owners.len()
+        );
+
+        while let Some(message) = stream.next().await {
+            let update = match message {
+                Ok(u) => u,
+                Err(e) => {
+                    warn!(
+                        "Received an error from the Geyser stream: {}",
+                        e.to_string()
+                    );
+                    break;
+                }
+            };
+            let account_update = match update.update_oneof {
+                Some(UpdateOneof::Account(a)) => a,
+                _ => continue,
+            };
+            let account_info = match account_update.account {
+                Some(a) => a,
+                None => continue,
+            };
+            let key = match <[u8; 32]>::try_from(account_info.pubkey.as_slice()) {
+                Ok(k) => Pubkey::new_from_array(k),
+                Err(_) => {
+                    warn!("Received an account update with an invalid pubkey.");
+                    continue;
+                }
+            };
+            debug!("Received account update for {}, updating cache.", key);
+            self.cache
+                .insert(
+                    key,
+                    AccountState {
+                        account: key,
+                        data: account_info.data,
+                        slot: account_update.slot,
+                    },
+                )
+                .await;
+        }
+
+        Err(GeyserServiceError::StreamClosed)
+    }
+}
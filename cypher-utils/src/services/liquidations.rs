@@ -0,0 +1,258 @@
+use {
+    cypher_client::{logs, LiquidatePositionLog},
+    dashmap::DashMap,
+    fixed::types::I80F48,
+    futures::StreamExt,
+    log::{debug, info, warn},
+    solana_client::{
+        nonblocking::pubsub_client::{PubsubClient, PubsubClientError},
+        rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::sync::Arc,
+    tokio::sync::broadcast::{channel, Receiver, Sender},
+};
+
+/// The kind of position that was liquidated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidationKind {
+    Spot,
+    Perpetual,
+    Futures,
+    Unknown,
+}
+
+impl LiquidationKind {
+    fn from_instruction_name(name: &str) -> Option<Self> {
+        match name {
+            "LiquidateSpotPosition" => Some(LiquidationKind::Spot),
+            "LiquidatePerpPosition" => Some(LiquidationKind::Perpetual),
+            "LiquidateFuturesPosition" => Some(LiquidationKind::Futures),
+            _ => None,
+        }
+    }
+}
+
+/// A structured record of a liquidation.
+#[derive(Debug, Clone)]
+pub struct LiquidationRecord {
+    /// The signature of the transaction.
+    pub signature: String,
+    /// The slot at which the liquidation was observed.
+    pub slot: u64,
+    /// The kind of position that was liquidated.
+    pub kind: LiquidationKind,
+    /// The liqor's master account.
+    pub liqor_master_account: Pubkey,
+    /// The liqor's sub account.
+    pub liqor_sub_account: Pubkey,
+    /// The liqee's master account.
+    pub liqee_master_account: Pubkey,
+    /// The liqee's sub account.
+    pub liqee_sub_account: Pubkey,
+    /// The asset, either a token mint or a market.
+    pub asset: Pubkey,
+    /// The liability, either a token mint or a market.
+    pub liability: Pubkey,
+    /// The price of the asset.
+    pub asset_price: I80F48,
+    /// The price of the liability.
+    pub liability_price: I80F48,
+    /// The liqee's asset position before the liquidation.
+    pub pre_asset_position: I80F48,
+    /// The liqee's liability position before the liquidation.
+    pub pre_liability_position: I80F48,
+    /// The liqee's asset position after the liquidation.
+    pub post_asset_position: I80F48,
+    /// The liqee's liability position after the liquidation.
+    pub post_liability_position: I80F48,
+}
+
+impl LiquidationRecord {
+    /// Creates a new [`LiquidationRecord`] from the given [`LiquidatePositionLog`].
+    pub fn new(
+        signature: &str,
+        slot: u64,
+        kind: LiquidationKind,
+        log: &LiquidatePositionLog,
+    ) -> Self {
+        Self {
+            signature: signature.to_string(),
+            slot,
+            kind,
+            liqor_master_account: log.liqor_master_account,
+            liqor_sub_account: log.liqor_sub_account,
+            liqee_master_account: log.liqee_master_account,
+            liqee_sub_account: log.liqee_sub_account,
+            asset: log.asset,
+            liability: log.liability,
+            asset_price: I80F48::from_bits(log.asset_price),
+            liability_price: I80F48::from_bits(log.liability_price),
+            pre_asset_position: I80F48::from_bits(log.pre_asset_position),
+            pre_liability_position: I80F48::from_bits(log.pre_liab_position),
+            post_asset_position: I80F48::from_bits(log.post_asset_position),
+            post_liability_position: I80F48::from_bits(log.post_liab_position),
+        }
+    }
+
+    /// The size of the asset position transferred to the liqor.
+    pub fn asset_size(&self) -> I80F48 {
+        (self.pre_asset_position - self.post_asset_position).abs()
+    }
+
+    /// The size of the liability position taken over by the liqor.
+    pub fn liability_size(&self) -> I80F48 {
+        (self.pre_liability_position - self.post_liability_position).abs()
+    }
+
+    /// The value of the asset position transferred to the liqor.
+    pub fn asset_value(&self) -> I80F48 {
+        self.asset_size().saturating_mul(self.asset_price)
+    }
+
+    /// The value of the liability position taken over by the liqor.
+    pub fn liability_value(&self) -> I80F48 {
+        self.liability_size().saturating_mul(self.liability_price)
+    }
+}
+
+/// Decodes the [`LiquidatePositionLog`]s emitted in the given transaction logs,
+/// along with the kind of liquidation instruction that emitted them.
+pub fn decode_liquidation_logs(logs: &[String]) -> Vec<(LiquidationKind, LiquidatePositionLog)> {
+    logs::decode_liquidation_logs(logs)
+        .into_iter()
+        .map(|(instruction, log)| {
+            let kind = instruction
+                .and_then(|name| LiquidationKind::from_instruction_name(&name))
+                .unwrap_or(LiquidationKind::Unknown);
+            (kind, log)
+        })
+        .collect()
+}
+
+/// A Service which monitors the program's transaction logs for liquidations
+/// and emits structured [`LiquidationRecord`]s.
+///
+/// Consumers can either subscribe to every liquidation or only to the liquidations of a given liqee.
+pub struct LiquidationMonitor {
+    pubsub_client: Arc<PubsubClient>,
+    sender: Arc<Sender<LiquidationRecord>>,
+    liqee_senders: DashMap<Pubkey, Sender<LiquidationRecord>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for LiquidationMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiquidationMonitor").finish()
+    }
+}
+
+impl LiquidationMonitor {
+    /// Creates a new [`LiquidationMonitor`].
+    pub fn new(
+        pubsub_client: Arc<PubsubClient>,
+        sender: Arc<Sender<LiquidationRecord>>,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            pubsub_client,
+            sender,
+            liqee_senders: DashMap::new(),
+            shutdown,
+        }
+    }
+
+    /// Subscribes to all liquidations.
+    pub fn subscribe(&self) -> Receiver<LiquidationRecord> {
+        self.sender.subscribe()
+    }
+
+    /// Subscribes to the liquidations of the given liqee master account.
+    pub fn subscribe_liqee(&self, liqee_master_account: &Pubkey) -> Receiver<LiquidationRecord> {
+        self.liqee_senders
+            .entry(*liqee_master_account)
+            .or_insert_with(|| channel::<LiquidationRecord>(64).0)
+            .subscribe()
+    }
+
+    /// Starts the service's work cycle.
+    /// Subscribes to the transaction logs that mention the program and processes them until shutdown.
+    #[inline(always)]
+    pub async fn start_service(
+        self: &Arc<Self>,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<(), PubsubClientError> {
+        let mut shutdown_receiver = self.shutdown.subscribe();
+        let sub = match self
+            .pubsub_client
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![cypher_client::id().to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(commitment.unwrap_or(CommitmentConfig::confirmed())),
+                },
+            )
+            .await
+        {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Failed to subscribe to program logs: {}", e.to_string());
+                return Err(e);
+            }
+        };
+
+        let mut stream = sub.0;
+        loop {
+            tokio::select! {
+                update = stream.next() => {
+                    match update {
+                        Some(response) => {
+                            if response.value.err.is_some() {
+                                continue;
+                            }
+                            self.process_logs(&response.value.signature, response.context.slot, &response.value.logs);
+                        }
+                        None => {
+                            warn!("Program logs subscription stream ended.");
+                            break;
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down liquidation monitor.");
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Processes the given transaction logs, emitting a [`LiquidationRecord`] for every liquidation found.
+    ///
+    /// Returns the number of liquidations found.
+    pub fn process_logs(&self, signature: &str, slot: u64, logs: &[String]) -> usize {
+        let liquidations = decode_liquidation_logs(logs);
+        for (kind, log) in liquidations.iter() {
+            let record = LiquidationRecord::new(signature, slot, *kind, log);
+            info!(
+                "Liquidation - Liqee: {} - Liqor: {} - Asset: {} - Liability: {} - Signature: {}",
+                record.liqee_master_account,
+                record.liqor_master_account,
+                record.asset,
+                record.liability,
+                record.signature
+            );
+            if let Some(liqee_sender) = self.liqee_senders.get(&record.liqee_master_account) {
+                if liqee_sender.send(record.clone()).is_err() {
+                    debug!(
+                        "No active receivers for liqee {}.",
+                        record.liqee_master_account
+                    );
+                }
+            }
+            if self.sender.send(record).is_err() {
+                debug!("No active receivers for liquidation records.");
+            }
+        }
+        liquidations.len()
+    }
+}
@@ -0,0 +1,255 @@
+use {
+    crate::{accounts_cache::AccountsCache, layouts::CypherAccountType, oms::Status},
+    cypher_client::{utils::get_zero_copy_account, AgnosticMarket, FuturesMarket, PerpetualMarket},
+    dashmap::DashMap,
+    log::{info, warn},
+    solana_sdk::pubkey::Pubkey,
+    std::sync::Arc,
+    tokio::sync::broadcast::{channel, error::RecvError, Receiver, Sender},
+};
+
+/// The parameters of a derivative market which can be changed by the market's authority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketParams {
+    /// The market.
+    pub market: Pubkey,
+    /// The tick size, in quote lots.
+    pub tick_size: u64,
+    /// The minimum base order size, in base lots.
+    pub min_base_order_size: u64,
+    /// The maximum base order size, in base lots.
+    pub max_base_order_size: u64,
+    /// The maximum quote order size, in quote lots.
+    pub max_quote_order_size: u64,
+    /// The base multiplier, the size of a base lot in native units.
+    pub base_multiplier: u64,
+    /// The quote multiplier, the size of a quote lot in native units.
+    pub quote_multiplier: u64,
+    /// The initial asset weight.
+    pub init_asset_weight: u8,
+    /// The initial liability weight.
+    pub init_liab_weight: u8,
+    /// The maintenance asset weight.
+    pub maint_asset_weight: u8,
+    /// The maintenance liability weight.
+    pub maint_liab_weight: u8,
+    /// The operating status.
+    pub status: Status,
+}
+
+impl MarketParams {
+    /// Creates the [`MarketParams`] of the given market.
+    pub fn new(market: &Pubkey, state: &AgnosticMarket) -> Self {
+        Self {
+            market: *market,
+            tick_size: state.tick_size,
+            min_base_order_size: state.min_base_order_size,
+            max_base_order_size: state.max_base_order_size,
+            max_quote_order_size: state.max_quote_order_size,
+            base_multiplier: state.base_multiplier,
+            quote_multiplier: state.quote_multiplier,
+            init_asset_weight: state.config.init_asset_weight,
+            init_liab_weight: state.config.init_liab_weight,
+            maint_asset_weight: state.config.maint_asset_weight,
+            maint_liab_weight: state.config.maint_liab_weight,
+            status: Status::from(&state.config.operating_status),
+        }
+    }
+
+    /// Creates the [`MarketParams`] from the given perpetual or futures market account data.
+    ///
+    /// Returns `None` if the account is neither a perpetual nor a futures market.
+    pub fn from_account_data(market: &Pubkey, account_data: &[u8]) -> Option<Self> {
+        match CypherAccountType::from_account_data(account_data) {
+            Some(CypherAccountType::PerpetualMarket) => {
+                let state = get_zero_copy_account::<PerpetualMarket>(account_data);
+                Some(Self::new(market, &state.inner))
+            }
+            Some(CypherAccountType::FuturesMarket) => {
+                let state = get_zero_copy_account::<FuturesMarket>(account_data);
+                Some(Self::new(market, &state.inner))
+            }
+            _ => None,
+        }
+    }
+
+    /// Rounds the given price, in quote lots, down to the tick size.
+    pub fn round_price_down(&self, price: u64) -> u64 {
+        if self.tick_size == 0 {
+            return price;
+        }
+        price - price % self.tick_size
+    }
+
+    /// Rounds the given price, in quote lots, up to the tick size.
+    pub fn round_price_up(&self, price: u64) -> u64 {
+        let rounded = self.round_price_down(price);
+        if rounded == price {
+            price
+        } else {
+            rounded.saturating_add(self.tick_size)
+        }
+    }
+
+    /// Clamps the given base order size, in base lots, to the market's order size limits.
+    ///
+    /// Returns zero if the size is below the minimum order size.
+    pub fn clamp_base_order_size(&self, base_qty: u64) -> u64 {
+        if base_qty < self.min_base_order_size {
+            return 0;
+        }
+        if self.max_base_order_size != 0 {
+            base_qty.min(self.max_base_order_size)
+        } else {
+            base_qty
+        }
+    }
+
+    /// Whether any of the margin weights differ from the given parameters.
+    pub fn weights_changed(&self, other: &MarketParams) -> bool {
+        self.init_asset_weight != other.init_asset_weight
+            || self.init_liab_weight != other.init_liab_weight
+            || self.maint_asset_weight != other.maint_asset_weight
+            || self.maint_liab_weight != other.maint_liab_weight
+    }
+}
+
+/// A change to the parameters of a market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketParamsUpdate {
+    /// The previous parameters.
+    pub previous: MarketParams,
+    /// The new parameters.
+    pub current: MarketParams,
+    /// The slot at which the change was observed.
+    pub slot: u64,
+}
+
+/// A Service which keeps track of the parameters of derivative markets and notifies subscribers when they change,
+/// e.g. after the market authority updates the tick size or weights, so that quoting and execution services
+/// can pick them up without restarting.
+///
+/// Market account updates are read from the [`AccountsCache`], which must be fed by a subscription service.
+pub struct MarketParamsService {
+    cache: Arc<AccountsCache>,
+    markets: DashMap<Pubkey, Option<MarketParams>>,
+    sender: Arc<Sender<MarketParamsUpdate>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for MarketParamsService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MarketParamsService")
+            .field("markets", &self.markets.len())
+            .finish()
+    }
+}
+
+impl MarketParamsService {
+    /// Creates a new [`MarketParamsService`].
+    pub fn new(cache: Arc<AccountsCache>, shutdown: Arc<Sender<bool>>) -> Self {
+        Self {
+            cache,
+            markets: DashMap::new(),
+            sender: Arc::new(channel::<MarketParamsUpdate>(64).0),
+            shutdown,
+        }
+    }
+
+    /// Subscribes to market parameter changes.
+    pub fn subscribe(&self) -> Receiver<MarketParamsUpdate> {
+        self.sender.subscribe()
+    }
+
+    /// Starts tracking the parameters of the given market, using the account state in the [`AccountsCache`] if it exists.
+    ///
+    /// Returns the current parameters of the market if they are known, otherwise they are set on the market's first update.
+    pub fn watch_market(&self, market: &Pubkey) -> Option<MarketParams> {
+        let params = self
+            .cache
+            .get(market)
+            .and_then(|state| MarketParams::from_account_data(market, &state.data));
+        self.markets.insert(*market, params);
+        params
+    }
+
+    /// Starts tracking the parameters of the given market with the given initial parameters.
+    pub fn watch_market_with_params(&self, params: MarketParams) {
+        self.markets.insert(params.market, Some(params));
+    }
+
+    /// Stops tracking the parameters of the given market.
+    pub fn unwatch_market(&self, market: &Pubkey) {
+        self.markets.remove(market);
+    }
+
+    /// Gets the current parameters of the given market, if it is tracked and they are known.
+    pub fn get_params(&self, market: &Pubkey) -> Option<MarketParams> {
+        self.markets.get(market).and_then(|p| *p)
+    }
+
+    /// Starts the service's work cycle.
+    /// Processes updates to the tracked markets in the [`AccountsCache`] until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut receiver = self.cache.subscribe_all();
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => {
+                    match update {
+                        Ok(state) => {
+                            self.process_update(&state.account, &state.data, state.slot);
+                        }
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Market params service lagged behind by {} updates.", n);
+                        }
+                        Err(RecvError::Closed) => {
+                            warn!("Accounts cache updates channel closed.");
+                            break;
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down market params service.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Processes an update to the given market's account data, notifying subscribers if its parameters changed.
+    pub fn process_update(&self, market: &Pubkey, account_data: &[u8], slot: u64) {
+        let mut entry = match self.markets.get_mut(market) {
+            Some(e) => e,
+            None => return,
+        };
+        let current = match MarketParams::from_account_data(market, account_data) {
+            Some(p) => p,
+            None => return,
+        };
+        let previous = match *entry {
+            Some(p) if p == current => return,
+            Some(p) => p,
+            None => {
+                *entry = Some(current);
+                return;
+            }
+        };
+        *entry = Some(current);
+        drop(entry);
+
+        info!(
+            "Market {} parameters changed at slot {}: {:?} -> {:?}",
+            market, slot, previous, current
+        );
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(MarketParamsUpdate {
+                previous,
+                current,
+                slot,
+            });
+        }
+    }
+}
@@ -1,6 +1,36 @@
 pub mod chain_meta;
+#[cfg(feature = "services")]
+pub mod clock;
+pub mod drop_copy;
+#[cfg(all(feature = "services", feature = "storage"))]
+pub mod funding;
+#[cfg(feature = "geyser")]
+pub mod geyser;
+pub mod liquidations;
+#[cfg(feature = "services")]
+pub mod market_params;
+#[cfg(feature = "services")]
+pub mod program_upgrades;
+pub mod rebroadcast;
 pub mod streaming;
+pub mod subscriptions;
+pub mod trade_stream;
 mod utils;
 
 pub use chain_meta::*;
+#[cfg(feature = "services")]
+pub use clock::*;
+pub use drop_copy::*;
+#[cfg(all(feature = "services", feature = "storage"))]
+pub use funding::*;
+#[cfg(feature = "geyser")]
+pub use geyser::*;
+pub use liquidations::*;
+#[cfg(feature = "services")]
+pub use market_params::*;
+#[cfg(feature = "services")]
+pub use program_upgrades::*;
+pub use rebroadcast::*;
 pub use streaming::*;
+pub use subscriptions::*;
+pub use trade_stream::*;
@@ -0,0 +1,313 @@
+use anchor_lang::{Discriminator, ZeroCopy};
+use dashmap::DashMap;
+use log::{info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{
+    broadcast::{channel, Receiver, Sender},
+    RwLock,
+};
+
+use crate::version::{
+    get_program_deployment, matches_layout, ProgramDeployment, VersionError, VersionGuard,
+};
+
+/// The maximum number of accounts per `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// An account watched by the [`ProgramUpgradeWatcher`], along with the check of it's expected layout.
+#[derive(Clone, Copy)]
+struct WatchedAccount {
+    /// The name of the account's type.
+    name: &'static str,
+    /// Whether the given account data matches the layout of the account's type, see [`matches_layout`].
+    matches: fn(&[u8]) -> bool,
+}
+
+/// The configuration of the [`ProgramUpgradeWatcher`].
+#[derive(Debug, Clone)]
+pub struct ProgramUpgradeConfig {
+    /// The interval at which the program's ProgramData account is polled.
+    pub poll_interval: Duration,
+    /// The guard which is re-run against the upgraded program.
+    pub guard: VersionGuard,
+    /// Whether to pause services on every detected upgrade, until [`ProgramUpgradeWatcher::resume`] is called.
+    pub pause_on_upgrade: bool,
+}
+
+impl Default for ProgramUpgradeConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(30),
+            guard: VersionGuard::default(),
+            pause_on_upgrade: false,
+        }
+    }
+}
+
+/// An upgrade of the cypher program detected by the [`ProgramUpgradeWatcher`].
+#[derive(Debug, Clone)]
+pub struct ProgramUpgrade {
+    /// The deployment before the upgrade.
+    pub previous: ProgramDeployment,
+    /// The deployment after the upgrade.
+    pub current: ProgramDeployment,
+    /// The watched accounts which no longer match the layout of their account type in this crate's IDL.
+    /// Each account is given along with the name of it's type.
+    pub invalid_accounts: Vec<(Pubkey, &'static str)>,
+    /// The error returned by the [`VersionGuard`], if the upgraded program failed it.
+    pub guard_error: Option<String>,
+    /// Whether services were paused because of the upgrade.
+    pub paused: bool,
+}
+
+impl ProgramUpgrade {
+    /// Whether the upgraded program is still compatible with this crate, as far as the checks can tell.
+    pub fn is_compatible(&self) -> bool {
+        self.invalid_accounts.is_empty() && self.guard_error.is_none()
+    }
+}
+
+/// A hook called whenever the [`ProgramUpgradeWatcher`] pauses services, e.g. to cancel orders or stop quoting.
+pub type PauseHook = Box<dyn Fn(&ProgramUpgrade) + Send + Sync>;
+
+/// A Service which watches the cypher program's ProgramData account for upgrades, see [`get_program_deployment`].
+///
+/// Whenever the program's last deployment slot changes, the [`VersionGuard`] is re-run and the watched accounts are
+/// fetched again and checked against the layouts of this crate's IDL. Subscribers are then notified with a
+/// [`ProgramUpgrade`] so that they can reload their contexts, which may hold state the upgraded program interprets
+/// differently. Services can optionally be paused until an operator calls [`ProgramUpgradeWatcher::resume`].
+pub struct ProgramUpgradeWatcher {
+    rpc_client: Arc<RpcClient>,
+    clearing: Pubkey,
+    config: ProgramUpgradeConfig,
+    watched: DashMap<Pubkey, WatchedAccount>,
+    deployment: RwLock<Option<ProgramDeployment>>,
+    paused: AtomicBool,
+    pause_hook: Option<PauseHook>,
+    sender: Arc<Sender<ProgramUpgrade>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for ProgramUpgradeWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgramUpgradeWatcher")
+            .field("clearing", &self.clearing)
+            .field("config", &self.config)
+            .field("watched", &self.watched.len())
+            .field("paused", &self.is_paused())
+            .finish()
+    }
+}
+
+impl ProgramUpgradeWatcher {
+    /// Creates a new [`ProgramUpgradeWatcher`], the [`VersionGuard`] checks the layout of the given clearing.
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        clearing: &Pubkey,
+        config: ProgramUpgradeConfig,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            rpc_client,
+            clearing: *clearing,
+            config,
+            watched: DashMap::new(),
+            deployment: RwLock::new(None),
+            paused: AtomicBool::new(false),
+            pause_hook: None,
+            sender: Arc::new(channel::<ProgramUpgrade>(16).0),
+            shutdown,
+        }
+    }
+
+    /// Sets the hook called whenever services are paused because of an upgrade.
+    pub fn with_pause_hook(mut self, hook: PauseHook) -> Self {
+        self.pause_hook = Some(hook);
+        self
+    }
+
+    /// Subscribes to the detected [`ProgramUpgrade`]s.
+    pub fn subscribe(&self) -> Receiver<ProgramUpgrade> {
+        self.sender.subscribe()
+    }
+
+    /// Watches the given account, whose layout is re-validated against `T` on every upgrade.
+    pub fn watch_account<T: ZeroCopy + Discriminator>(&self, account: &Pubkey) {
+        let name = std::any::type_name::<T>();
+        self.watched.insert(
+            *account,
+            WatchedAccount {
+                name: name.rsplit("::").next().unwrap_or(name),
+                matches: matches_layout::<T>,
+            },
+        );
+    }
+
+    /// Stops watching the given account.
+    pub fn unwatch_account(&self, account: &Pubkey) {
+        self.watched.remove(account);
+    }
+
+    /// Whether services are paused because of an upgrade.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Resumes services after an upgrade paused them, e.g. after an operator verified the upgrade.
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::Relaxed) {
+            info!("Resuming services after program upgrade.");
+        }
+    }
+
+    /// Gets the last known deployment of the program.
+    pub async fn get_deployment(&self) -> Option<ProgramDeployment> {
+        *self.deployment.read().await
+    }
+
+    /// Starts the service's work cycle.
+    /// Polls the program's ProgramData account at the configured interval until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.check().await {
+                        warn!("Failed to check for program upgrades: {}", e.to_string());
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down program upgrade watcher.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Checks whether the program was upgraded since the last check, notifying subscribers if it was.
+    ///
+    /// The first check only records the current deployment.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests
+    /// or the program's ProgramData account is invalid.
+    pub async fn check(&self) -> Result<Option<ProgramUpgrade>, VersionError> {
+        let current = match get_program_deployment(&self.rpc_client, &cypher_client::id()).await {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let previous = {
+            let mut deployment = self.deployment.write().await;
+            match *deployment {
+                Some(p) if p.last_deploy_slot != current.last_deploy_slot => p,
+                Some(_) => return Ok(None),
+                None => {
+                    *deployment = Some(current);
+                    info!(
+                        "Watching program upgrades - Last deployed at slot {}",
+                        current.last_deploy_slot
+                    );
+                    return Ok(None);
+                }
+            }
+        };
+        warn!(
+            "Program was upgraded at slot {}, previously deployed at slot {}.",
+            current.last_deploy_slot, previous.last_deploy_slot
+        );
+
+        // the deployment is only recorded once the upgrade was fully checked, so a failed check is retried
+        let guard_error = match self
+            .config
+            .guard
+            .check(&self.rpc_client, &self.clearing)
+            .await
+        {
+            Ok(_) => None,
+            Err(VersionError::ClientError(e)) => {
+                return Err(VersionError::ClientError(e));
+            }
+            Err(e) => {
+                warn!(
+                    "Upgraded program failed the version guard: {}",
+                    e.to_string()
+                );
+                Some(e.to_string())
+            }
+        };
+        let invalid_accounts = match self.get_invalid_accounts().await {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        *self.deployment.write().await = Some(current);
+        for (account, name) in invalid_accounts.iter() {
+            warn!(
+                "Account {} no longer matches the layout of {} after the program upgrade.",
+                account, name
+            );
+        }
+
+        let paused = self.config.pause_on_upgrade;
+        let upgrade = ProgramUpgrade {
+            previous,
+            current,
+            invalid_accounts,
+            guard_error,
+            paused,
+        };
+        if paused {
+            self.paused.store(true, Ordering::Relaxed);
+            warn!("Pausing services until the program upgrade is acknowledged.");
+            if let Some(hook) = self.pause_hook.as_ref() {
+                hook(&upgrade);
+            }
+        }
+        if self.sender.send(upgrade.clone()).is_err() {
+            info!("No active receivers for program upgrades.");
+        }
+        Ok(Some(upgrade))
+    }
+
+    /// Fetches the watched accounts, returning the ones which do not match the layout of their account type.
+    async fn get_invalid_accounts(&self) -> Result<Vec<(Pubkey, &'static str)>, VersionError> {
+        let watched = self
+            .watched
+            .iter()
+            .map(|w| (*w.key(), *w.value()))
+            .collect::<Vec<(Pubkey, WatchedAccount)>>();
+        let mut invalid_accounts = Vec::new();
+        for chunk in watched.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let keys = chunk.iter().map(|(k, _)| *k).collect::<Vec<Pubkey>>();
+            let accounts = match self.rpc_client.get_multiple_accounts(&keys).await {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(VersionError::ClientError(e));
+                }
+            };
+            for ((address, watched), account) in chunk.iter().zip(accounts.iter()) {
+                let data = account.as_ref().map(|a| a.data.as_slice()).unwrap_or(&[]);
+                if !(watched.matches)(data) {
+                    invalid_accounts.push((*address, watched.name));
+                }
+            }
+        }
+        Ok(invalid_accounts)
+    }
+}
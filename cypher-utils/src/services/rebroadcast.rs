@@ -0,0 +1,244 @@
+use {
+    super::ChainMetaService,
+    crate::utils::{create_transaction, send_transaction},
+    log::{info, warn},
+    solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient},
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        hash::Hash,
+        instruction::Instruction,
+        signature::{Keypair, Signature},
+        transaction::{Transaction, TransactionError},
+    },
+    std::sync::Arc,
+    thiserror::Error,
+    tokio::time::{sleep, Duration, Instant},
+};
+
+#[derive(Debug, Error)]
+pub enum RebroadcastError {
+    #[error("Transaction {0} failed: {1}")]
+    TransactionFailed(Signature, TransactionError),
+    #[error("Transaction was not confirmed before the deadline, signed {0} times.")]
+    DeadlineExceeded(usize),
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+}
+
+/// The configuration of the [`RebroadcastSender`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebroadcastConfig {
+    /// The time after which the sender gives up if the transaction has not been confirmed.
+    pub deadline: Duration,
+    /// The interval at which the transaction is rebroadcast and it's confirmation status polled.
+    pub rebroadcast_interval: Duration,
+}
+
+impl Default for RebroadcastConfig {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(90),
+            rebroadcast_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// The outcome of a transaction sent by the [`RebroadcastSender`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebroadcastOutcome {
+    /// The signature of the confirmed transaction.
+    pub signature: Signature,
+    /// The number of times the transaction was signed, one more than the number of times it's blockhash expired.
+    pub signings: usize,
+    /// The number of times the transaction was broadcast.
+    pub broadcasts: usize,
+}
+
+/// The state of the signatures of a transaction sent by the [`RebroadcastSender`].
+enum SignaturesState {
+    /// None of the signatures has landed.
+    Pending,
+    /// One of the signatures has landed but is not confirmed yet, or the state is unknown.
+    Processed,
+    /// One of the signatures is confirmed.
+    Confirmed(RebroadcastOutcome),
+}
+
+/// A sender which rebroadcasts a transaction until it is confirmed or a deadline passes.
+///
+/// The transaction is signed with the latest blockhash cached by the [`ChainMetaService`] and rebroadcast at a fixed
+/// interval. Once it's blockhash expires without the transaction being confirmed, it can no longer land, so it is
+/// signed again with a fresh blockhash. Re-signing only after expiry guarantees the instructions are executed
+/// at most once.
+pub struct RebroadcastSender {
+    rpc_client: Arc<RpcClient>,
+    chain_meta_service: Arc<ChainMetaService>,
+    config: RebroadcastConfig,
+}
+
+impl std::fmt::Debug for RebroadcastSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RebroadcastSender")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl RebroadcastSender {
+    /// Creates a new [`RebroadcastSender`].
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        chain_meta_service: Arc<ChainMetaService>,
+        config: RebroadcastConfig,
+    ) -> Self {
+        Self {
+            rpc_client,
+            chain_meta_service,
+            config,
+        }
+    }
+
+    /// Gets a blockhash to sign with, the one cached by the [`ChainMetaService`] unless it is the given
+    /// expired blockhash or the service has not fetched one yet.
+    async fn get_blockhash(&self, expired: Option<Hash>) -> Result<Hash, ClientError> {
+        let blockhash = self.chain_meta_service.get_latest_blockhash().await;
+        if blockhash != Hash::default() && Some(blockhash) != expired {
+            return Ok(blockhash);
+        }
+        self.rpc_client.get_latest_blockhash().await
+    }
+
+    /// Sends a transaction with the given instructions, payer and signers, rebroadcasting it and re-signing it
+    /// with a fresh blockhash whenever it expires, until it is confirmed or the deadline passes.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed, was not confirmed before the deadline
+    /// or something goes wrong during the RPC requests.
+    pub async fn send_and_confirm(
+        &self,
+        ixs: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<RebroadcastOutcome, RebroadcastError> {
+        let deadline = Instant::now() + self.config.deadline;
+        let mut signatures: Vec<Signature> = Vec::new();
+        let mut broadcasts = 0;
+        let mut blockhash: Option<Hash> = None;
+        let mut tx: Option<Transaction> = None;
+
+        while Instant::now() < deadline {
+            let expired = match blockhash {
+                Some(h) => match self
+                    .rpc_client
+                    .is_blockhash_valid(&h, CommitmentConfig::processed())
+                    .await
+                {
+                    Ok(valid) => !valid,
+                    Err(e) => {
+                        warn!("Failed to check blockhash validity: {}", e.to_string());
+                        false
+                    }
+                },
+                None => true,
+            };
+            if expired {
+                // the previous signature can no longer land, make sure it did not land before it expired
+                // since re-signing a transaction which has landed would execute it twice
+                match self.get_signatures_state(&signatures, broadcasts).await {
+                    Ok(SignaturesState::Confirmed(outcome)) => return Ok(outcome),
+                    Ok(SignaturesState::Processed) => {
+                        sleep(self.config.rebroadcast_interval).await;
+                        continue;
+                    }
+                    Ok(SignaturesState::Pending) => (),
+                    Err(e) => {
+                        return Err(e);
+                    }
+                }
+                let new_blockhash = match self.get_blockhash(blockhash).await {
+                    Ok(h) => h,
+                    Err(e) => {
+                        return Err(RebroadcastError::ClientError(e));
+                    }
+                };
+                if blockhash.is_some() {
+                    info!(
+                        "Blockhash {} expired, re-signing transaction with blockhash {}.",
+                        blockhash.unwrap(),
+                        new_blockhash
+                    );
+                }
+                let new_tx = create_transaction(new_blockhash, ixs, payer, Some(signers));
+                signatures.push(new_tx.signatures[0]);
+                blockhash = Some(new_blockhash);
+                tx = Some(new_tx);
+            }
+
+            if let Some(tx) = tx.as_ref() {
+                match send_transaction(&self.rpc_client, tx, false).await {
+                    Ok(_) => broadcasts += 1,
+                    Err(e) => {
+                        // a transaction which fails simulation will not succeed when rebroadcast
+                        if let Some(err) = e
+                            .get_transaction_error()
+                            .filter(|err| *err != TransactionError::AlreadyProcessed)
+                        {
+                            return Err(RebroadcastError::TransactionFailed(tx.signatures[0], err));
+                        }
+                        warn!("Failed to broadcast transaction: {}", e.to_string());
+                    }
+                }
+            }
+
+            sleep(self.config.rebroadcast_interval).await;
+
+            match self.get_signatures_state(&signatures, broadcasts).await {
+                Ok(SignaturesState::Confirmed(outcome)) => return Ok(outcome),
+                Ok(_) => (),
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+
+        Err(RebroadcastError::DeadlineExceeded(signatures.len()))
+    }
+
+    /// Gets the state of the given signatures of the transaction.
+    async fn get_signatures_state(
+        &self,
+        signatures: &[Signature],
+        broadcasts: usize,
+    ) -> Result<SignaturesState, RebroadcastError> {
+        if signatures.is_empty() {
+            return Ok(SignaturesState::Pending);
+        }
+        let statuses = match self.rpc_client.get_signature_statuses(signatures).await {
+            Ok(s) => s.value,
+            Err(e) => {
+                warn!("Failed to get signature statuses: {}", e.to_string());
+                return Ok(SignaturesState::Processed);
+            }
+        };
+        let mut state = SignaturesState::Pending;
+        for (signature, status) in signatures.iter().zip(statuses.iter()) {
+            let status = match status {
+                Some(s) => s,
+                None => continue,
+            };
+            if let Some(err) = &status.err {
+                return Err(RebroadcastError::TransactionFailed(*signature, err.clone()));
+            }
+            if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                return Ok(SignaturesState::Confirmed(RebroadcastOutcome {
+                    signature: *signature,
+                    signings: signatures.len(),
+                    broadcasts,
+                }));
+            }
+            state = SignaturesState::Processed;
+        }
+        Ok(state)
+    }
+}
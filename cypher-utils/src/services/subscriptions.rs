@@ -0,0 +1,403 @@
+use {
+    crate::{
+        accounts_cache::{AccountState, AccountsCache},
+        scanners::{authority_accounts_filters, authority_sub_accounts_filters},
+        services::utils::get_account_info,
+        utils::get_program_accounts,
+    },
+    futures::StreamExt,
+    log::{debug, info, warn},
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::RpcFilterType,
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey},
+    std::{str::FromStr, sync::Arc, time::Duration},
+    tokio::sync::broadcast::Sender,
+};
+
+/// The maximum number of accounts fetched in a single `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// A program subscription, which keeps every account of the program that matches the filters updated.
+#[derive(Debug, Clone)]
+pub struct ProgramSubscription {
+    /// The program.
+    pub program_id: Pubkey,
+    /// The filters applied to the program's accounts.
+    pub filters: Vec<RpcFilterType>,
+}
+
+impl ProgramSubscription {
+    /// Creates a new [`ProgramSubscription`].
+    pub fn new(program_id: &Pubkey, filters: Vec<RpcFilterType>) -> Self {
+        Self {
+            program_id: *program_id,
+            filters,
+        }
+    }
+
+    /// Creates the [`ProgramSubscription`]s for all [`CypherAccount`](cypher_client::CypherAccount)s
+    /// and [`CypherSubAccount`](cypher_client::CypherSubAccount)s of the given authority.
+    pub fn user_accounts(authority: &Pubkey) -> Vec<Self> {
+        vec![
+            Self::new(&cypher_client::id(), authority_accounts_filters(authority)),
+            Self::new(
+                &cypher_client::id(),
+                authority_sub_accounts_filters(authority),
+            ),
+        ]
+    }
+}
+
+/// The configuration of the [`AccountSubscriptionService`].
+#[derive(Debug, Clone)]
+pub struct AccountSubscriptionConfig {
+    /// The accounts kept updated with individual account subscriptions,
+    /// e.g. the clearing, the cache account and markets.
+    pub accounts: Vec<Pubkey>,
+    /// The program subscriptions, e.g. the user's accounts.
+    pub programs: Vec<ProgramSubscription>,
+    /// The commitment of the subscriptions.
+    pub commitment: CommitmentConfig,
+    /// The delay before resubscribing after a subscription is dropped.
+    pub resubscribe_delay: Duration,
+}
+
+impl Default for AccountSubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            accounts: Vec::new(),
+            programs: Vec::new(),
+            commitment: CommitmentConfig::confirmed(),
+            resubscribe_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl AccountSubscriptionConfig {
+    /// Adds the given accounts.
+    pub fn with_accounts(mut self, accounts: &[Pubkey]) -> Self {
+        for account in accounts.iter() {
+            if !self.accounts.contains(account) {
+                self.accounts.push(*account);
+            }
+        }
+        self
+    }
+
+    /// Adds the given program subscription.
+    pub fn with_program(mut self, program: ProgramSubscription) -> Self {
+        self.programs.push(program);
+        self
+    }
+
+    /// Adds the program subscriptions for all accounts and sub accounts of the given authority.
+    pub fn with_user_accounts(mut self, authority: &Pubkey) -> Self {
+        self.programs
+            .extend(ProgramSubscription::user_accounts(authority));
+        self
+    }
+
+    /// Sets the commitment of the subscriptions.
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+}
+
+/// A Service which keeps the [`AccountsCache`] continuously updated for a configured set of
+/// accounts and program subscriptions.
+///
+/// Account states are initially fetched using the [`RpcClient`] and then kept updated via the [`PubsubClient`],
+/// subscriptions that are dropped by the node are automatically resubscribed until the service is shut down.
+pub struct AccountSubscriptionService {
+    cache: Arc<AccountsCache>,
+    pubsub_client: Arc<PubsubClient>,
+    rpc_client: Arc<RpcClient>,
+    config: AccountSubscriptionConfig,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for AccountSubscriptionService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccountSubscriptionService")
+            .field("accounts", &self.config.accounts.len())
+            .field("programs", &self.config.programs.len())
+            .finish()
+    }
+}
+
+impl AccountSubscriptionService {
+    /// Creates a new [`AccountSubscriptionService`].
+    pub fn new(
+        cache: Arc<AccountsCache>,
+        pubsub_client: Arc<PubsubClient>,
+        rpc_client: Arc<RpcClient>,
+        config: AccountSubscriptionConfig,
+        shutdown: Arc<Sender<bool>>,
+    ) -> Self {
+        Self {
+            cache,
+            pubsub_client,
+            rpc_client,
+            config,
+            shutdown,
+        }
+    }
+
+    /// Starts the service's work cycle.
+    /// Initially fetches the Account's states using the [`RpcClient`]
+    /// and then subscribes to changes via [`PubsubClient`] until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        self.fetch_accounts().await;
+
+        let mut handles = Vec::new();
+        for account in self.config.accounts.iter() {
+            let service = Arc::clone(self);
+            let account = *account;
+            handles.push(tokio::spawn(async move {
+                service.run_account_subscription(&account).await;
+            }));
+        }
+        for program in self.config.programs.iter() {
+            let service = Arc::clone(self);
+            let program = program.clone();
+            handles.push(tokio::spawn(async move {
+                service.run_program_subscription(&program).await;
+            }));
+        }
+        info!(
+            "Started {} account and {} program subscriptions.",
+            self.config.accounts.len(),
+            self.config.programs.len()
+        );
+
+        for handle in handles {
+            if let Err(e) = handle.await {
+                warn!("A subscription task failed: {}", e.to_string());
+            }
+        }
+        info!("Account subscription service stopped.");
+    }
+
+    /// Fetches the state of all configured accounts and program subscriptions into the [`AccountsCache`].
+    #[inline(always)]
+    pub async fn fetch_accounts(&self) {
+        let commitment = self.config.commitment;
+        for chunk in self.config.accounts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let res = match self
+                .rpc_client
+                .get_multiple_accounts_with_commitment(chunk, commitment)
+                .await
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("Could not fetch account infos: {}", e.to_string());
+                    continue;
+                }
+            };
+            for (key, account) in chunk.iter().zip(res.value.into_iter()) {
+                match account {
+                    Some(a) => self.update_cache(key, a.data, res.context.slot).await,
+                    None => {
+                        warn!("Account {} does not exist.", key);
+                    }
+                }
+            }
+        }
+
+        for program in self.config.programs.iter() {
+            let slot = match self.rpc_client.get_slot_with_commitment(commitment).await {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Could not fetch slot: {}", e.to_string());
+                    0
+                }
+            };
+            let accounts = match get_program_accounts(
+                &self.rpc_client,
+                program.filters.clone(),
+                &program.program_id,
+            )
+            .await
+            {
+                Ok(a) => a,
+                Err(e) => {
+                    warn!(
+                        "Could not fetch program accounts for {}: {}",
+                        program.program_id,
+                        e.to_string()
+                    );
+                    continue;
+                }
+            };
+            debug!(
+                "Fetched {} program accounts for {}.",
+                accounts.len(),
+                program.program_id
+            );
+            for (key, account) in accounts.into_iter() {
+                self.update_cache(&key, account.data, slot).await;
+            }
+        }
+    }
+
+    async fn run_account_subscription(&self, account: &Pubkey) {
+        let mut shutdown_receiver = self.shutdown.subscribe();
+        loop {
+            let sub = match self
+                .pubsub_client
+                .account_subscribe(
+                    account,
+                    Some(RpcAccountInfoConfig {
+                        commitment: Some(self.config.commitment),
+                        encoding: Some(UiAccountEncoding::Base64),
+                        ..Default::default()
+                    }),
+                )
+                .await
+            {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!(
+                        "Failed to subscribe to account {}: {}",
+                        account,
+                        e.to_string()
+                    );
+                    None
+                }
+            };
+
+            if let Some((mut stream, _unsubscribe)) = sub {
+                loop {
+                    tokio::select! {
+                        update = stream.next() => {
+                            match update {
+                                Some(response) => {
+                                    let account_data = match get_account_info(&response.value) {
+                                        Ok(data) => data,
+                                        Err(e) => {
+                                            warn!("Failed to decode account data: {}", e.to_string());
+                                            continue;
+                                        }
+                                    };
+                                    self.update_cache(account, account_data, response.context.slot).await;
+                                }
+                                None => {
+                                    warn!("Subscription for account {} was dropped, resubscribing.", account);
+                                    break;
+                                }
+                            }
+                        },
+                        _ = shutdown_receiver.recv() => {
+                            info!("Shutting down subscription for account {}.", account);
+                            return;
+                        }
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.config.resubscribe_delay) => {},
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down subscription for account {}.", account);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn run_program_subscription(&self, program: &ProgramSubscription) {
+        let mut shutdown_receiver = self.shutdown.subscribe();
+        loop {
+            let sub = match self
+                .pubsub_client
+                .program_subscribe(
+                    &program.program_id,
+                    Some(RpcProgramAccountsConfig {
+                        filters: Some(program.filters.clone()),
+                        account_config: RpcAccountInfoConfig {
+                            commitment: Some(self.config.commitment),
+                            encoding: Some(UiAccountEncoding::Base64),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }),
+                )
+                .await
+            {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!(
+                        "Failed to subscribe to program {}: {}",
+                        program.program_id,
+                        e.to_string()
+                    );
+                    None
+                }
+            };
+
+            if let Some((mut stream, _unsubscribe)) = sub {
+                loop {
+                    tokio::select! {
+                        update = stream.next() => {
+                            match update {
+                                Some(response) => {
+                                    let key = match Pubkey::from_str(&response.value.pubkey) {
+                                        Ok(k) => k,
+                                        Err(e) => {
+                                            warn!("Failed to parse account pubkey: {}", e.to_string());
+                                            continue;
+                                        }
+                                    };
+                                    let account_data = match get_account_info(&response.value.account) {
+                                        Ok(data) => data,
+                                        Err(e) => {
+                                            warn!("Failed to decode account data: {}", e.to_string());
+                                            continue;
+                                        }
+                                    };
+                                    self.update_cache(&key, account_data, response.context.slot).await;
+                                }
+                                None => {
+                                    warn!("Subscription for program {} was dropped, resubscribing.", program.program_id);
+                                    break;
+                                }
+                            }
+                        },
+                        _ = shutdown_receiver.recv() => {
+                            info!("Shutting down subscription for program {}.", program.program_id);
+                            return;
+                        }
+                    }
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.config.resubscribe_delay) => {},
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down subscription for program {}.", program.program_id);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn update_cache(&self, key: &Pubkey, data: Vec<u8>, slot: u64) {
+        debug!("Received account update for {}, updating cache.", key);
+        self.cache
+            .insert(
+                *key,
+                AccountState {
+                    account: *key,
+                    data,
+                    slot,
+                },
+            )
+            .await;
+    }
+}
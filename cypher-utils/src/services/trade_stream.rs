@@ -0,0 +1,227 @@
+use {
+    crate::{
+        accounts_cache::AccountsCache,
+        contexts::{AgnosticEventQueueContext, Fill, GenericEventQueue, SerumEventQueueContext},
+    },
+    dashmap::DashMap,
+    log::{info, warn},
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        sync::Arc,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    tokio::sync::broadcast::{channel, error::RecvError, Receiver, Sender},
+};
+
+/// The capacity of the channel over which trades are published.
+const TRADES_CHANNEL_CAPACITY: usize = 1024;
+
+/// A fill on a market, as published by the [`TradeStreamService`].
+#[derive(Debug, Clone)]
+pub struct Trade {
+    /// The market.
+    pub market: Pubkey,
+    /// The slot at which the event queue update with the fill was seen.
+    pub slot: u64,
+    /// The local unix timestamp at which the fill was seen.
+    pub timestamp: u64,
+    /// The fill.
+    pub fill: Fill,
+}
+
+/// The event queue of a market watched by the [`TradeStreamService`].
+enum EventQueueStream {
+    Agnostic(AgnosticEventQueueContext),
+    Serum(SerumEventQueueContext),
+}
+
+impl EventQueueStream {
+    fn market(&self) -> Pubkey {
+        match self {
+            Self::Agnostic(ctx) => ctx.market,
+            Self::Serum(ctx) => ctx.market,
+        }
+    }
+
+    /// Reloads the event queue from the given account data, returning the fills pushed since the previous
+    /// reload and the number of events that were missed in between.
+    fn reload_new_fills(&mut self, account_data: &[u8]) -> (Vec<Fill>, u64) {
+        match self {
+            Self::Agnostic(ctx) => {
+                let missed = ctx.reload_new_events_from_account_data(account_data);
+                (ctx.get_fills(), missed)
+            }
+            Self::Serum(ctx) => {
+                let missed = ctx.reload_new_events_from_account_data(account_data);
+                (ctx.get_fills(), missed)
+            }
+        }
+    }
+}
+
+/// A watched event queue, which is seeded with the queue's sequence number on it's first update
+/// so that only fills that happen afterwards are published.
+struct WatchedEventQueue {
+    stream: EventQueueStream,
+    seeded: bool,
+}
+
+/// A Service which streams the fills of markets by following the updates to their event queues
+/// and publishes them as [`Trade`]s.
+///
+/// Event queue updates are read from the [`AccountsCache`], which must be fed by a subscription service.
+/// Only fills pushed after a market starts being watched are published, fills of events that were pushed
+/// and consumed in between two updates can not be recovered and are reported in the logs.
+pub struct TradeStreamService {
+    cache: Arc<AccountsCache>,
+    event_queues: DashMap<Pubkey, WatchedEventQueue>,
+    sender: Arc<Sender<Trade>>,
+    shutdown: Arc<Sender<bool>>,
+}
+
+impl std::fmt::Debug for TradeStreamService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TradeStreamService")
+            .field("event_queues", &self.event_queues.len())
+            .finish()
+    }
+}
+
+impl TradeStreamService {
+    /// Creates a new [`TradeStreamService`].
+    pub fn new(cache: Arc<AccountsCache>, shutdown: Arc<Sender<bool>>) -> Self {
+        Self {
+            cache,
+            event_queues: DashMap::new(),
+            sender: Arc::new(channel::<Trade>(TRADES_CHANNEL_CAPACITY).0),
+            shutdown,
+        }
+    }
+
+    /// Subscribes to the trades of the watched markets.
+    pub fn subscribe(&self) -> Receiver<Trade> {
+        self.sender.subscribe()
+    }
+
+    /// Starts streaming the fills of the given AOB market from it's event queue.
+    pub fn watch_aob_market(&self, market: &Pubkey, event_queue: &Pubkey) {
+        let stream = EventQueueStream::Agnostic(AgnosticEventQueueContext::new(
+            market,
+            event_queue,
+            0,
+            0,
+            Vec::new(),
+            Vec::new(),
+        ));
+        self.watch(event_queue, stream);
+    }
+
+    /// Starts streaming the fills of the given Serum market from it's event queue.
+    pub fn watch_serum_market(&self, market: &Pubkey, event_queue: &Pubkey) {
+        let stream = EventQueueStream::Serum(SerumEventQueueContext::new(
+            market,
+            event_queue,
+            0,
+            0,
+            Vec::new(),
+        ));
+        self.watch(event_queue, stream);
+    }
+
+    fn watch(&self, event_queue: &Pubkey, stream: EventQueueStream) {
+        let mut watched = WatchedEventQueue {
+            stream,
+            seeded: false,
+        };
+        // seed with the cached state, if it exists, so fills between now and the next update are not missed
+        if let Some(state) = self.cache.get(event_queue) {
+            watched.stream.reload_new_fills(&state.data);
+            watched.seeded = true;
+        }
+        self.event_queues.insert(*event_queue, watched);
+    }
+
+    /// Stops streaming the fills from the given event queue.
+    pub fn unwatch_event_queue(&self, event_queue: &Pubkey) {
+        self.event_queues.remove(event_queue);
+    }
+
+    /// Starts the service's work cycle.
+    /// Processes updates to the watched event queues in the [`AccountsCache`] until shutdown.
+    #[inline(always)]
+    pub async fn start_service(self: &Arc<Self>) {
+        let mut receiver = self.cache.subscribe_all();
+        let mut shutdown_receiver = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                update = receiver.recv() => {
+                    match update {
+                        Ok(state) => {
+                            self.process_update(&state.account, &state.data, state.slot);
+                        }
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Trade stream service lagged behind by {} updates.", n);
+                        }
+                        Err(RecvError::Closed) => {
+                            warn!("Accounts cache updates channel closed.");
+                            break;
+                        }
+                    }
+                },
+                _ = shutdown_receiver.recv() => {
+                    info!("Shutting down trade stream service.");
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Processes an update to the given event queue's account data, publishing the fills pushed since the previous update.
+    ///
+    /// Returns the number of published trades.
+    pub fn process_update(&self, event_queue: &Pubkey, account_data: &[u8], slot: u64) -> usize {
+        let mut entry = match self.event_queues.get_mut(event_queue) {
+            Some(e) => e,
+            None => return 0,
+        };
+        let (fills, missed) = entry.stream.reload_new_fills(account_data);
+        if !entry.seeded {
+            entry.seeded = true;
+            return 0;
+        }
+        let market = entry.stream.market();
+        drop(entry);
+
+        if missed != 0 {
+            warn!(
+                "Missed {} events of market {} before slot {}.",
+                missed, market, slot
+            );
+        }
+        if fills.is_empty() || self.sender.receiver_count() == 0 {
+            return 0;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut published = 0;
+        for fill in fills {
+            if self
+                .sender
+                .send(Trade {
+                    market,
+                    slot,
+                    timestamp,
+                    fill,
+                })
+                .is_ok()
+            {
+                published += 1;
+            }
+        }
+        published
+    }
+}
@@ -0,0 +1,213 @@
+use log::{info, warn};
+use solana_client::{
+    client_error::ClientError, nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcSimulateTransactionConfig,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::{Instruction, InstructionError},
+    message::Message,
+    pubkey::Pubkey,
+    transaction::{Transaction, TransactionError},
+};
+
+const ANCHOR_ERROR_LOG_PREFIX: &str = "Program log: AnchorError ";
+const ANCHOR_ERROR_ACCOUNT_PREFIX: &str = "caused by account: ";
+const ANCHOR_ERROR_CODE_PREFIX: &str = "Error Code: ";
+const ANCHOR_ERROR_NUMBER_PREFIX: &str = "Error Number: ";
+const ANCHOR_ERROR_MESSAGE_PREFIX: &str = "Error Message: ";
+
+/// An Anchor error as logged by the program.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AnchorErrorLog {
+    /// The name of the account that caused the error, if the error was caused by an account.
+    pub account: Option<String>,
+    /// The name of the error.
+    pub error_code: String,
+    /// The number of the error.
+    pub error_number: u32,
+    /// The error message.
+    pub error_message: String,
+}
+
+impl AnchorErrorLog {
+    /// Whether the error is an Anchor constraint or account error, i.e. an account passed to the
+    /// instruction is incorrect.
+    pub fn is_account_error(&self) -> bool {
+        (2000..4000).contains(&self.error_number)
+    }
+}
+
+/// Parses the last Anchor error logged in the given transaction logs.
+pub fn parse_anchor_error(logs: &[String]) -> Option<AnchorErrorLog> {
+    let log = logs
+        .iter()
+        .rev()
+        .find_map(|l| l.strip_prefix(ANCHOR_ERROR_LOG_PREFIX))?;
+
+    let mut error = AnchorErrorLog::default();
+    for part in log.split(". ") {
+        let part = part.trim();
+        if let Some(account) = part.strip_prefix(ANCHOR_ERROR_ACCOUNT_PREFIX) {
+            error.account = Some(account.to_string());
+        } else if let Some(code) = part.strip_prefix(ANCHOR_ERROR_CODE_PREFIX) {
+            error.error_code = code.to_string();
+        } else if let Some(number) = part.strip_prefix(ANCHOR_ERROR_NUMBER_PREFIX) {
+            error.error_number = number.parse::<u32>().unwrap_or_default();
+        } else if let Some(message) = part.strip_prefix(ANCHOR_ERROR_MESSAGE_PREFIX) {
+            error.error_message = message.trim_end_matches('.').to_string();
+        }
+    }
+    Some(error)
+}
+
+/// The outcome of simulating an instruction.
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    /// The name given to the instruction.
+    pub name: String,
+    /// The error returned by the program, if the simulation failed.
+    pub error: Option<TransactionError>,
+    /// The Anchor error logged by the program, if any.
+    pub anchor_error: Option<AnchorErrorLog>,
+    /// The transaction logs.
+    pub logs: Vec<String>,
+    /// The compute units consumed.
+    pub units_consumed: Option<u64>,
+}
+
+impl SimulationReport {
+    /// Whether the simulation succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Whether the instruction is missing accounts.
+    pub fn is_missing_accounts(&self) -> bool {
+        matches!(
+            self.error,
+            Some(TransactionError::InstructionError(
+                _,
+                InstructionError::NotEnoughAccountKeys
+            ))
+        ) || self
+            .anchor_error
+            .as_ref()
+            .map(|e| e.error_code == "AccountNotEnoughKeys")
+            .unwrap_or(false)
+    }
+
+    /// Whether the simulation failed due to missing or incorrect accounts.
+    pub fn is_account_error(&self) -> bool {
+        self.is_missing_accounts()
+            || self
+                .anchor_error
+                .as_ref()
+                .map(|e| e.is_account_error())
+                .unwrap_or(false)
+            || matches!(
+                self.error,
+                Some(TransactionError::AccountNotFound)
+                    | Some(TransactionError::InstructionError(
+                        _,
+                        InstructionError::InvalidAccountData
+                            | InstructionError::InvalidAccountOwner
+                            | InstructionError::IncorrectProgramId
+                            | InstructionError::MissingAccount
+                    ))
+            )
+    }
+}
+
+/// Simulates the given instruction against the current cluster state, preceded by the given instructions,
+/// e.g. compute budget instructions or instructions that create accounts the instruction depends on.
+///
+/// Signatures are not verified and the blockhash is replaced, so the instruction can be simulated
+/// without access to the signers' keypairs.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request,
+/// a failed simulation is reported in the [`SimulationReport`].
+pub async fn simulate_instruction(
+    rpc_client: &RpcClient,
+    payer: &Pubkey,
+    name: &str,
+    pre_ixs: &[Instruction],
+    ix: &Instruction,
+) -> Result<SimulationReport, ClientError> {
+    let mut ixs = pre_ixs.to_vec();
+    ixs.push(ix.clone());
+    let tx = Transaction::new_unsigned(Message::new(&ixs, Some(payer)));
+
+    let res = match rpc_client
+        .simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(CommitmentConfig::processed()),
+                ..Default::default()
+            },
+        )
+        .await
+    {
+        Ok(r) => r.value,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+
+    let logs = res.logs.unwrap_or_default();
+    Ok(SimulationReport {
+        name: name.to_string(),
+        anchor_error: parse_anchor_error(&logs),
+        error: res.err,
+        logs,
+        units_consumed: res.units_consumed,
+    })
+}
+
+/// Simulates each of the given named instructions and reports the outcome of each,
+/// logging the instructions that failed due to missing or incorrect accounts.
+///
+/// This can be used to validate instruction builders against the deployed program,
+/// e.g. to detect drift between the IDL and the program after a program upgrade.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during any of the RPC requests.
+pub async fn validate_instructions(
+    rpc_client: &RpcClient,
+    payer: &Pubkey,
+    pre_ixs: &[Instruction],
+    ixs: &[(String, Instruction)],
+) -> Result<Vec<SimulationReport>, ClientError> {
+    let mut reports = Vec::with_capacity(ixs.len());
+
+    for (name, ix) in ixs.iter() {
+        let report = match simulate_instruction(rpc_client, payer, name, pre_ixs, ix).await {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        if report.is_account_error() {
+            warn!(
+                "Instruction {} failed due to accounts - Account: {:?} - Error: {:?} - Anchor Error: {:?}",
+                name,
+                report.anchor_error.as_ref().and_then(|e| e.account.clone()),
+                report.error,
+                report.anchor_error.as_ref().map(|e| e.error_code.clone())
+            );
+        } else if !report.is_ok() {
+            info!(
+                "Instruction {} failed for reasons unrelated to accounts - Error: {:?}",
+                name, report.error
+            );
+        }
+        reports.push(report);
+    }
+
+    Ok(reports)
+}
@@ -0,0 +1,166 @@
+use {
+    super::{Storage, StorageError, StoredEvent},
+    log::warn,
+    std::{
+        fs::{create_dir_all, File, OpenOptions},
+        io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// A [`Storage`] which keeps each stream in a newline delimited JSON file within a directory.
+///
+/// Appends are serialized through a lock, queries scan the whole file of the stream, which makes this
+/// backend suitable for local recording and research rather than large histories.
+///
+/// A record left partially written by a crash is ignored by queries and truncated by the next append,
+/// so a stream remains readable after the process is killed mid-write.
+#[derive(Debug)]
+pub struct FileStorage {
+    directory: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FileStorage {
+    /// Creates a new [`FileStorage`] in the given directory, creating the directory if it does not exist.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the directory can not be created.
+    pub fn new<P: AsRef<Path>>(directory: P) -> Result<Self, StorageError> {
+        let directory = directory.as_ref().to_path_buf();
+        match create_dir_all(&directory) {
+            Ok(()) => Ok(Self {
+                directory,
+                lock: Mutex::new(()),
+            }),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    /// Gets the path of the file of the given stream.
+    pub fn get_stream_path(&self, stream: &str) -> PathBuf {
+        self.directory.join(format!("{}.jsonl", stream))
+    }
+}
+
+impl Storage for FileStorage {
+    fn append(&self, stream: &str, events: &[StoredEvent]) -> Result<(), StorageError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut file = match OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(self.get_stream_path(stream))
+        {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(StorageError::Io(e));
+            }
+        };
+        if let Err(e) = truncate_partial_record(&mut file) {
+            return Err(StorageError::Io(e));
+        }
+        let mut writer = BufWriter::new(file);
+        for event in events.iter() {
+            let line = match serde_json::to_string(event) {
+                Ok(l) => l,
+                Err(e) => {
+                    return Err(StorageError::Serialization(e));
+                }
+            };
+            if let Err(e) = writeln!(writer, "{}", line) {
+                return Err(StorageError::Io(e));
+            }
+        }
+        match writer.flush() {
+            Ok(()) => Ok(()),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    fn query_range(
+        &self,
+        stream: &str,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        let _guard = self.lock.lock().unwrap();
+        let file = match File::open(self.get_stream_path(stream)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok(Vec::new());
+            }
+            Err(e) => {
+                return Err(StorageError::Io(e));
+            }
+        };
+
+        let mut events = Vec::new();
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) => {
+                    return Err(StorageError::Io(e));
+                }
+            };
+            let terminated = line.ends_with('\n');
+            let record = line.trim_end();
+            if record.is_empty() {
+                continue;
+            }
+            let event = match serde_json::from_str::<StoredEvent>(record) {
+                Ok(e) => e,
+                Err(_) if !terminated => {
+                    warn!(
+                        "Ignoring partially written record at the end of stream {}.",
+                        stream
+                    );
+                    break;
+                }
+                Err(e) => {
+                    return Err(StorageError::Serialization(e));
+                }
+            };
+            if event.timestamp >= start && event.timestamp <= end {
+                events.push(event);
+            }
+        }
+        // stable sort keeps the append order of events with the same timestamp
+        events.sort_by_key(|e| e.timestamp);
+
+        Ok(events)
+    }
+}
+
+/// Truncates the record left partially written at the end of the given file by a crash, if any.
+fn truncate_partial_record(file: &mut File) -> std::io::Result<()> {
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(());
+    }
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::Start(len - 1))?;
+    file.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        return Ok(());
+    }
+
+    let mut data = Vec::with_capacity(len as usize);
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut data)?;
+    let valid_len = match data.iter().rposition(|b| *b == b'\n') {
+        Some(i) => i as u64 + 1,
+        None => 0,
+    };
+    warn!(
+        "Truncating {} bytes of a partially written record.",
+        len - valid_len
+    );
+    file.set_len(valid_len)
+}
@@ -0,0 +1,135 @@
+pub mod file;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use file::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite::*;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+/// An event stored by a [`Storage`] backend.
+///
+/// The payload is the JSON representation of the recorded event, which allows backends to store
+/// any kind of event without knowing its type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredEvent {
+    /// The slot at which the event occurred.
+    pub slot: u64,
+    /// The unix timestamp at which the event occurred, in seconds.
+    pub timestamp: i64,
+    /// The JSON payload.
+    pub payload: String,
+}
+
+impl StoredEvent {
+    /// Creates a new [`StoredEvent`] with the JSON representation of the given event.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the event can not be serialized.
+    pub fn new<T: Serialize>(slot: u64, timestamp: i64, event: &T) -> Result<Self, StorageError> {
+        let payload = match serde_json::to_string(event) {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(StorageError::Serialization(e));
+            }
+        };
+        Ok(Self {
+            slot,
+            timestamp,
+            payload,
+        })
+    }
+
+    /// Deserializes the payload into the recorded event.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the payload is not a valid representation of the event.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, StorageError> {
+        match serde_json::from_str(&self.payload) {
+            Ok(e) => Ok(e),
+            Err(e) => Err(StorageError::Serialization(e)),
+        }
+    }
+}
+
+/// A storage backend used by recorders to persist events and query them back.
+///
+/// Events are appended to named streams, e.g. `fills` or `funding`, and queried by timestamp range.
+/// Implement this trait to plug in a different backend, such as Postgres or ClickHouse, without changing the recorders.
+pub trait Storage: Send + Sync {
+    /// Appends the given events to the stream.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the events could not be persisted.
+    fn append(&self, stream: &str, events: &[StoredEvent]) -> Result<(), StorageError>;
+
+    /// Queries the events of the stream with a timestamp between `start` and `end`, inclusive,
+    /// ordered by timestamp and then by the order in which they were appended.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the events could not be read.
+    fn query_range(
+        &self,
+        stream: &str,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<StoredEvent>, StorageError>;
+}
+
+/// Serializes and appends the given events to the stream of the [`Storage`].
+///
+/// ### Errors
+///
+/// This function will return an error if an event can not be serialized or the events could not be persisted.
+pub fn append_events<T: Serialize>(
+    storage: &dyn Storage,
+    stream: &str,
+    events: &[(u64, i64, T)],
+) -> Result<(), StorageError> {
+    let mut stored = Vec::with_capacity(events.len());
+    for (slot, timestamp, event) in events.iter() {
+        match StoredEvent::new(*slot, *timestamp, event) {
+            Ok(e) => stored.push(e),
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+    storage.append(stream, &stored)
+}
+
+/// Queries and deserializes the events of the stream of the [`Storage`] with a timestamp between `start` and `end`, inclusive.
+///
+/// ### Errors
+///
+/// This function will return an error if the events could not be read or deserialized.
+pub fn query_events<T: DeserializeOwned>(
+    storage: &dyn Storage,
+    stream: &str,
+    start: i64,
+    end: i64,
+) -> Result<Vec<T>, StorageError> {
+    let stored = match storage.query_range(stream, start, end) {
+        Ok(s) => s,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    stored.iter().map(|e| e.decode::<T>()).collect()
+}
@@ -0,0 +1,148 @@
+use {
+    super::{Storage, StorageError, StoredEvent},
+    rusqlite::{params, Connection},
+    std::{path::Path, sync::Mutex},
+};
+
+/// A [`Storage`] which keeps all streams in one SQLite database, with one table per stream.
+///
+/// Events are indexed by timestamp, which makes range queries cheap even for large histories.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    /// Opens the SQLite database at the given path, creating it if it does not exist.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the database can not be opened.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StorageError> {
+        match Connection::open(path) {
+            Ok(c) => Ok(Self {
+                connection: Mutex::new(c),
+            }),
+            Err(e) => Err(StorageError::Backend(e.to_string())),
+        }
+    }
+
+    /// Opens an in-memory SQLite database.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the database can not be opened.
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        match Connection::open_in_memory() {
+            Ok(c) => Ok(Self {
+                connection: Mutex::new(c),
+            }),
+            Err(e) => Err(StorageError::Backend(e.to_string())),
+        }
+    }
+}
+
+/// Gets the table name of the given stream, only keeping characters which are valid in an identifier.
+fn get_table_name(stream: &str) -> String {
+    let name = stream
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<String>();
+    format!("events_{}", name)
+}
+
+fn create_table(connection: &Connection, table: &str) -> Result<(), StorageError> {
+    match connection.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            slot INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            payload TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS {table}_timestamp ON {table} (timestamp);"
+    )) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(StorageError::Backend(e.to_string())),
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn append(&self, stream: &str, events: &[StoredEvent]) -> Result<(), StorageError> {
+        let table = get_table_name(stream);
+        let mut connection = self.connection.lock().unwrap();
+        create_table(&connection, &table)?;
+
+        let tx = match connection.transaction() {
+            Ok(tx) => tx,
+            Err(e) => {
+                return Err(StorageError::Backend(e.to_string()));
+            }
+        };
+        {
+            let mut stmt = match tx.prepare(&format!(
+                "INSERT INTO {} (slot, timestamp, payload) VALUES (?1, ?2, ?3)",
+                table
+            )) {
+                Ok(s) => s,
+                Err(e) => {
+                    return Err(StorageError::Backend(e.to_string()));
+                }
+            };
+            for event in events.iter() {
+                if let Err(e) =
+                    stmt.execute(params![event.slot as i64, event.timestamp, event.payload])
+                {
+                    return Err(StorageError::Backend(e.to_string()));
+                }
+            }
+        }
+        match tx.commit() {
+            Ok(()) => Ok(()),
+            Err(e) => Err(StorageError::Backend(e.to_string())),
+        }
+    }
+
+    fn query_range(
+        &self,
+        stream: &str,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<StoredEvent>, StorageError> {
+        let table = get_table_name(stream);
+        let connection = self.connection.lock().unwrap();
+        create_table(&connection, &table)?;
+
+        let mut stmt = match connection.prepare(&format!(
+            "SELECT slot, timestamp, payload FROM {} WHERE timestamp >= ?1 AND timestamp <= ?2 ORDER BY timestamp, id",
+            table
+        )) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(StorageError::Backend(e.to_string()));
+            }
+        };
+        let rows = match stmt.query_map(params![start, end], |row| {
+            Ok(StoredEvent {
+                slot: row.get::<_, i64>(0)? as u64,
+                timestamp: row.get(1)?,
+                payload: row.get(2)?,
+            })
+        }) {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(StorageError::Backend(e.to_string()));
+            }
+        };
+
+        let mut events = Vec::new();
+        for row in rows {
+            match row {
+                Ok(e) => events.push(e),
+                Err(e) => {
+                    return Err(StorageError::Backend(e.to_string()));
+                }
+            }
+        }
+        Ok(events)
+    }
+}
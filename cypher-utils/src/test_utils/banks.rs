@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+use solana_program_test::{BanksClient, BanksClientError};
+use solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+
+use crate::transport::{AccountFetcher, TxSender};
+
+/// Converts a [`BanksClientError`] into the [`ClientError`] the
+/// [`RpcClient`](solana_client::nonblocking::rpc_client::RpcClient) would have returned,
+/// so that callers inspecting transaction errors behave the same against both.
+pub fn to_client_error(error: BanksClientError) -> ClientError {
+    match error {
+        BanksClientError::TransactionError(e) => ClientErrorKind::TransactionError(e).into(),
+        BanksClientError::SimulationError { err, .. } => {
+            ClientErrorKind::TransactionError(err).into()
+        }
+        e => ClientErrorKind::Custom(e.to_string()).into(),
+    }
+}
+
+/// A transport backed by a `solana-program-test` [`BanksClient`], see [`AccountFetcher`] and [`TxSender`].
+///
+/// The [`BanksClient`] is cheap to clone, every request is made with a clone of it.
+#[derive(Clone)]
+pub struct BanksTransport {
+    banks_client: BanksClient,
+}
+
+impl std::fmt::Debug for BanksTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BanksTransport").finish()
+    }
+}
+
+impl BanksTransport {
+    /// Creates a new [`BanksTransport`].
+    pub fn new(banks_client: BanksClient) -> Self {
+        Self { banks_client }
+    }
+
+    /// Gets a clone of the underlying [`BanksClient`].
+    pub fn get_banks_client(&self) -> BanksClient {
+        self.banks_client.clone()
+    }
+}
+
+#[async_trait]
+impl AccountFetcher for BanksTransport {
+    async fn get_account_data(&self, account: &Pubkey) -> Result<Vec<u8>, ClientError> {
+        match self.banks_client.clone().get_account(*account).await {
+            Ok(Some(a)) => Ok(a.data),
+            // mirror the error returned by the rpc so callers can not tell the transports apart
+            Ok(None) => Err(ClientErrorKind::RpcError(RpcError::ForUser(format!(
+                "AccountNotFound: pubkey={}",
+                account
+            )))
+            .into()),
+            Err(e) => Err(to_client_error(e)),
+        }
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        let mut banks_client = self.banks_client.clone();
+        let mut result = Vec::with_capacity(accounts.len());
+        for account in accounts.iter() {
+            match banks_client.get_account(*account).await {
+                Ok(a) => result.push(a),
+                Err(e) => {
+                    return Err(to_client_error(e));
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl TxSender for BanksTransport {
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        match self.banks_client.clone().get_latest_blockhash().await {
+            Ok(h) => Ok(h),
+            Err(e) => Err(to_client_error(e)),
+        }
+    }
+
+    /// Sends the given signed transaction, processing it before returning if `confirm` is set.
+    async fn send_transaction(
+        &self,
+        tx: &Transaction,
+        confirm: bool,
+    ) -> Result<Signature, ClientError> {
+        let mut banks_client = self.banks_client.clone();
+        let result = if confirm {
+            banks_client.process_transaction(tx.clone()).await
+        } else {
+            banks_client.send_transaction(tx.clone()).await
+        };
+        match result {
+            Ok(()) => Ok(tx.signatures[0]),
+            Err(e) => Err(to_client_error(e)),
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use agnostic_orderbook::state::{
+    critbit::LeafNode as AobLeafNode, event_queue::FillEvent, AccountTag, Side as AobSide,
+};
+use anchor_spl::dex::serum_dex::{
+    fees::FeeTier,
+    matching::Side as DexSide,
+    state::{Event, EventView, MarketState},
+};
+use bytemuck::Zeroable;
+use cypher_client::{
+    aob::{build_aob_event_queue, build_book_side, CallBackInfo},
+    serum::{build_dex_book_side, build_dex_event_queue, LeafNode},
+    Market, Side,
+};
+
+use crate::contexts::{Fill, Order};
+
+/// Gets the order id the AOB assigns to an order with the given price, in the units of [`Order::price`],
+/// and the given sequence number, i.e. the fixed point price in the upper 64 bits and the sequence number,
+/// inverted for bids so that older orders have priority, in the lower 64 bits.
+///
+/// The price must fit in 32 bits, as the AOB stores it as a 32.32 fixed point number.
+pub fn get_aob_order_id(price: u64, seq_num: u64, side: Side) -> u128 {
+    get_order_id((price as u128) << 32, seq_num, side)
+}
+
+/// Gets the order id Serum assigns to an order with the given price, in the units of [`Order::price`],
+/// and the given sequence number, see [`get_aob_order_id`].
+pub fn get_serum_order_id(price: u64, seq_num: u64, side: Side) -> u128 {
+    get_order_id(price as u128, seq_num, side)
+}
+
+fn get_order_id(key_price: u128, seq_num: u64, side: Side) -> u128 {
+    let seq_num = if side == Side::Bid { !seq_num } else { seq_num };
+    (key_price << 64) | seq_num as u128
+}
+
+/// Gets the key of the given order, which is it's order id if set or the order id derived from it's price
+/// with it's index as the sequence number otherwise, returning `None` if the key does not encode the order's price.
+fn get_order_key(
+    order: &Order,
+    idx: usize,
+    side: Side,
+    get_order_id: fn(u64, u64, Side) -> u128,
+    get_price: fn(u128) -> u64,
+) -> Option<u128> {
+    let key = if order.order_id == u128::default() {
+        get_order_id(order.price, idx as u64, side)
+    } else {
+        order.order_id
+    };
+    if get_price(key) != order.price {
+        return None;
+    }
+    Some(key)
+}
+
+/// Builds the account data of one side of an AOB order book for the given [`Market`] holding the given orders,
+/// without [`CallBackInfo`]s, see [`build_aob_book_side_data_with_callback_infos`].
+pub fn build_aob_book_side_data(
+    market: &dyn Market,
+    side: Side,
+    capacity: usize,
+    orders: &[Order],
+) -> Option<Vec<u8>> {
+    let orders = orders
+        .iter()
+        .map(|o| (o.clone(), CallBackInfo::default()))
+        .collect::<Vec<(Order, CallBackInfo)>>();
+    build_aob_book_side_data_with_callback_infos(market, side, capacity, &orders)
+}
+
+/// Builds the account data of one side of an AOB order book for the given [`Market`] with the given order capacity,
+/// holding the given orders along with their [`CallBackInfo`]s, as loaded by the [`AgnosticOrderBookContext`].
+///
+/// Only the price, base quantity, order id and max timestamp of the orders are stored, the quote quantity is derived
+/// from them when the book side is loaded. Orders without an order id are assigned one with their index as the
+/// sequence number, see [`get_aob_order_id`].
+///
+/// Returns `None` if an order's id does not encode it's price, it's base quantity is not a multiple of the market's
+/// base multiplier, two orders have the same id or the orders do not fit in the book side.
+///
+/// [`AgnosticOrderBookContext`]: crate::contexts::AgnosticOrderBookContext
+pub fn build_aob_book_side_data_with_callback_infos(
+    market: &dyn Market,
+    side: Side,
+    capacity: usize,
+    orders: &[(Order, CallBackInfo)],
+) -> Option<Vec<u8>> {
+    let base_multiplier = market.base_multiplier();
+    let mut leaves = Vec::with_capacity(orders.len());
+    for (idx, (order, callback_info)) in orders.iter().enumerate() {
+        let key = get_order_key(order, idx, side, get_aob_order_id, |key| {
+            ((key >> 64) as u64) >> 32
+        })?;
+        if order.base_quantity.checked_rem(base_multiplier)? != 0 {
+            return None;
+        }
+        let mut leaf = AobLeafNode::zeroed();
+        leaf.key = key;
+        leaf.base_quantity = order.base_quantity / base_multiplier;
+        leaf.max_ts = order.max_ts;
+        leaves.push((leaf, *callback_info));
+    }
+
+    let account_tag = if side == Side::Bid {
+        AccountTag::Bids
+    } else {
+        AccountTag::Asks
+    };
+    build_book_side(account_tag, capacity, &leaves)
+}
+
+/// Builds the account data of one side of a Serum order book for the given [`MarketState`] with the given node
+/// capacity, holding the given orders, as loaded by the [`SerumOrderBookContext`].
+///
+/// Only the price, base quantity, order id and client order id of the orders are stored, the quote quantity is derived
+/// from them when the book side is loaded. Orders without an order id are assigned one with their index as the
+/// sequence number, see [`get_serum_order_id`]. A book side holding `n` orders needs a capacity of `2 * n - 1` nodes.
+///
+/// Returns `None` if an order's id does not encode it's price, it's base quantity is not a multiple of the market's
+/// coin lot size, two orders have the same id or the orders do not fit in the book side.
+///
+/// [`SerumOrderBookContext`]: crate::contexts::SerumOrderBookContext
+pub fn build_serum_book_side_data(
+    market: &MarketState,
+    side: Side,
+    capacity: usize,
+    orders: &[Order],
+) -> Option<Vec<u8>> {
+    let coin_lot_size = market.coin_lot_size;
+    let mut leaves = Vec::with_capacity(orders.len());
+    for (idx, order) in orders.iter().enumerate() {
+        let key = get_order_key(order, idx, side, get_serum_order_id, |key| {
+            (key >> 64) as u64
+        })?;
+        if order.base_quantity.checked_rem(coin_lot_size)? != 0 {
+            return None;
+        }
+        leaves.push(LeafNode::new(
+            key,
+            [0; 4],
+            0,
+            0,
+            order.base_quantity / coin_lot_size,
+            order.client_order_id,
+        ));
+    }
+
+    build_dex_book_side(side == Side::Ask, capacity, &leaves)
+}
+
+/// Builds the account data of an AOB event queue holding the given fills, without [`CallBackInfo`]s,
+/// see [`build_aob_event_queue_data_with_callback_infos`].
+pub fn build_aob_event_queue_data(
+    capacity: usize,
+    seq_num: u64,
+    fills: &[Fill],
+) -> Option<Vec<u8>> {
+    let fills = fills
+        .iter()
+        .map(|f| (f.clone(), CallBackInfo::default(), CallBackInfo::default()))
+        .collect::<Vec<(Fill, CallBackInfo, CallBackInfo)>>();
+    build_aob_event_queue_data_with_callback_infos(capacity, seq_num, &fills)
+}
+
+/// Builds the account data of an AOB event queue with the given event capacity holding the given fills,
+/// ordered from oldest to newest, along with their maker and taker [`CallBackInfo`]s,
+/// as loaded by the [`AgnosticEventQueueContext`].
+///
+/// The newest fill has the sequence number `seq_num - 1`. Only the base and quote quantities, taker side and maker
+/// order id of the fills are stored, the price and orders accounts are derived from them when the queue is loaded.
+///
+/// Returns `None` if the fills do not fit in the event queue or more fills are given than the sequence number allows.
+///
+/// [`AgnosticEventQueueContext`]: crate::contexts::AgnosticEventQueueContext
+pub fn build_aob_event_queue_data_with_callback_infos(
+    capacity: usize,
+    seq_num: u64,
+    fills: &[(Fill, CallBackInfo, CallBackInfo)],
+) -> Option<Vec<u8>> {
+    let events = fills
+        .iter()
+        .map(|(fill, maker, taker)| {
+            let mut event = FillEvent::zeroed();
+            event.taker_side = if fill.taker_side == Side::Bid {
+                AobSide::Bid as u8
+            } else {
+                AobSide::Ask as u8
+            };
+            event.maker_order_id = fill.maker_order_id;
+            event.quote_size = fill.quote_quantity;
+            event.base_size = fill.base_quantity;
+            (event, *maker, *taker)
+        })
+        .collect::<Vec<(FillEvent, CallBackInfo, CallBackInfo)>>();
+    build_aob_event_queue(capacity, seq_num, &events)
+}
+
+/// Builds the account data of a Serum event queue with the given event capacity holding the given fills, ordered from
+/// oldest to newest, as loaded by the [`SerumEventQueueContext`].
+///
+/// Each fill is stored as the maker's fill event, the newest fill has the sequence number `seq_num - 1`.
+/// Only the base and quote quantities, taker side and maker order id of the fills are stored,
+/// the price is derived from them when the queue is loaded.
+///
+/// Returns `None` if the fills do not fit in the event queue or more fills are given than the sequence number allows.
+///
+/// [`SerumEventQueueContext`]: crate::contexts::SerumEventQueueContext
+pub fn build_serum_event_queue_data(
+    capacity: usize,
+    seq_num: u64,
+    fills: &[Fill],
+) -> Option<Vec<u8>> {
+    let events = fills
+        .iter()
+        .map(|fill| {
+            let maker_side = if fill.taker_side == Side::Bid {
+                DexSide::Ask
+            } else {
+                DexSide::Bid
+            };
+            // the maker pays base tokens for quote tokens when it's order is an ask and vice versa
+            let (native_qty_paid, native_qty_received) = if maker_side == DexSide::Ask {
+                (fill.base_quantity, fill.quote_quantity)
+            } else {
+                (fill.quote_quantity, fill.base_quantity)
+            };
+            Event::new(EventView::Fill {
+                side: maker_side,
+                maker: true,
+                native_qty_paid,
+                native_qty_received,
+                native_fee_or_rebate: 0,
+                order_id: fill.maker_order_id,
+                owner: [0; 4],
+                owner_slot: 0,
+                fee_tier: FeeTier::Base,
+                client_order_id: None,
+            })
+        })
+        .collect::<Vec<Event>>();
+    build_dex_event_queue(capacity, seq_num, &events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contexts::{
+        AgnosticEventQueueContext, AgnosticOrderBookContext, GenericEventQueue, GenericOrderBook,
+        SerumEventQueueContext, SerumOrderBookContext,
+    };
+    use cypher_client::PerpetualMarket;
+    use solana_sdk::pubkey::Pubkey;
+
+    const BASE_MULTIPLIER: u64 = 10;
+    const QUOTE_MULTIPLIER: u64 = 10;
+
+    fn get_perp_market() -> PerpetualMarket {
+        let mut market = PerpetualMarket::zeroed();
+        market.inner.base_multiplier = BASE_MULTIPLIER;
+        market.inner.quote_multiplier = QUOTE_MULTIPLIER;
+        market
+    }
+
+    fn get_serum_market() -> MarketState {
+        let mut market = MarketState::zeroed();
+        market.coin_lot_size = BASE_MULTIPLIER;
+        market.pc_lot_size = QUOTE_MULTIPLIER;
+        market
+    }
+
+    /// Gets orders on the given side at the given prices, each with a base quantity of `100` times it's index plus one,
+    /// listed in the order they are expected to be loaded in, i.e. from best to worst price.
+    fn get_orders(side: Side, prices: &[u64]) -> Vec<Order> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(idx, price)| {
+                let base_quantity = 100 * (idx as u64 + 1);
+                Order {
+                    side,
+                    price: *price,
+                    base_quantity,
+                    quote_quantity: base_quantity * price,
+                    client_order_id: idx as u64 + 1,
+                    max_ts: 1_000 + idx as u64,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn get_fills() -> Vec<Fill> {
+        [
+            (100, 1_500, Side::Bid),
+            (200, 2_800, Side::Ask),
+            (300, 4_500, Side::Bid),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(idx, (base_quantity, quote_quantity, taker_side))| Fill {
+            base_quantity: *base_quantity,
+            quote_quantity: *quote_quantity,
+            price: quote_quantity / base_quantity,
+            taker_side: *taker_side,
+            maker_order_id: idx as u128 + 1,
+            maker: None,
+            taker: None,
+        })
+        .collect()
+    }
+
+    fn assert_orders_eq(actual: &[Order], expected: &[Order], max_ts: bool, client_order_id: bool) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!(a.side == e.side);
+            assert_eq!(a.price, e.price);
+            assert_eq!(a.base_quantity, e.base_quantity);
+            assert_eq!(a.quote_quantity, e.quote_quantity);
+            assert_eq!(a.order_id, e.order_id);
+            if max_ts {
+                assert_eq!(a.max_ts, e.max_ts);
+            }
+            if client_order_id {
+                assert_eq!(a.client_order_id, e.client_order_id);
+            }
+            assert!(a.maker.is_none());
+        }
+    }
+
+    fn assert_fills_eq(actual: &[Fill], expected: &[Fill]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.base_quantity, e.base_quantity);
+            assert_eq!(a.quote_quantity, e.quote_quantity);
+            assert_eq!(a.price, e.price);
+            assert!(a.taker_side == e.taker_side);
+            assert_eq!(a.maker_order_id, e.maker_order_id);
+            assert!(a.maker.is_none());
+            assert!(a.taker.is_none());
+        }
+    }
+
+    #[test]
+    fn aob_book_sides_round_trip() {
+        let market = get_perp_market();
+        for (side, prices) in [(Side::Bid, [120, 110, 100]), (Side::Ask, [130, 140, 150])] {
+            let mut orders = get_orders(side, &prices);
+            // the book is built from the worst to the best price, so the insertion order does not match the loaded one
+            orders.reverse();
+            let data = build_aob_book_side_data(&market, side, 8, &orders).unwrap();
+            for (idx, order) in orders.iter_mut().enumerate() {
+                order.order_id = get_aob_order_id(order.price, idx as u64, side);
+            }
+            orders.reverse();
+
+            let ctx = AgnosticOrderBookContext::from_account_data(
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &market,
+                &data,
+                side,
+            );
+            let loaded = if side == Side::Bid {
+                ctx.get_bids()
+            } else {
+                ctx.get_asks()
+            };
+            assert_orders_eq(&loaded, &orders, true, false);
+
+            let loaded = get_aob_orders_with_callback_infos(&market, &data, side)
+                .into_iter()
+                .map(|(o, _)| o)
+                .collect::<Vec<Order>>();
+            assert_orders_eq(&loaded, &orders, true, false);
+        }
+    }
+
+    #[test]
+    fn aob_book_side_rejects_invalid_orders() {
+        let market = get_perp_market();
+        let mut orders = get_orders(Side::Bid, &[120, 110]);
+        // more orders than the capacity
+        assert!(build_aob_book_side_data(&market, Side::Bid, 1, &orders).is_none());
+
+        // two orders at the same price with the same id
+        orders[1].price = 120;
+        orders[0].order_id = get_aob_order_id(120, 0, Side::Bid);
+        orders[1].order_id = orders[0].order_id;
+        assert!(build_aob_book_side_data(&market, Side::Bid, 8, &orders).is_none());
+
+        // a base quantity which is not a multiple of the base multiplier
+        let mut orders = get_orders(Side::Bid, &[120]);
+        orders[0].base_quantity += 1;
+        assert!(build_aob_book_side_data(&market, Side::Bid, 8, &orders).is_none());
+    }
+
+    #[test]
+    fn serum_book_sides_round_trip() {
+        let market = get_serum_market();
+        for (side, prices) in [(Side::Bid, [120, 110, 100]), (Side::Ask, [130, 140, 150])] {
+            let mut orders = get_orders(side, &prices);
+            let data = build_serum_book_side_data(&market, side, 5, &orders).unwrap();
+            for (idx, order) in orders.iter_mut().enumerate() {
+                order.order_id = get_serum_order_id(order.price, idx as u64, side);
+                // this version of Serum does not have TIF capability
+                order.max_ts = u64::MAX;
+            }
+
+            let ctx = SerumOrderBookContext::from_account_data(
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &market,
+                &data,
+                side,
+            );
+            let loaded = if side == Side::Bid {
+                ctx.get_bids()
+            } else {
+                ctx.get_asks()
+            };
+            assert_orders_eq(&loaded, &orders, true, true);
+        }
+        // three orders need five nodes
+        let orders = get_orders(Side::Bid, &[120, 110, 100]);
+        assert!(build_serum_book_side_data(&market, Side::Bid, 4, &orders).is_none());
+    }
+
+    #[test]
+    fn aob_event_queues_round_trip() {
+        let fills = get_fills();
+        // the second case wraps around the end of the ring buffer, the oldest fill is at the last index
+        for (capacity, seq_num, head) in [(8, 3, 0), (4, 10, 3)] {
+            let data = build_aob_event_queue_data(capacity, seq_num, &fills).unwrap();
+            let ctx = AgnosticEventQueueContext::from_account_data(
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &data,
+            );
+            assert_eq!(ctx.seq_num, seq_num);
+            assert_eq!(ctx.count, fills.len() as u64);
+            assert_eq!(ctx.head, head);
+            assert_fills_eq(&ctx.get_fills(), &fills);
+            assert_fills_eq(&ctx.get_fills_since(seq_num - 1), &fills[2..]);
+        }
+        assert!(build_aob_event_queue_data(2, 10, &fills).is_none());
+        assert!(build_aob_event_queue_data(8, 2, &fills).is_none());
+    }
+
+    #[test]
+    fn serum_event_queues_round_trip() {
+        let fills = get_fills();
+        // the second case wraps around the end of the ring buffer, the oldest fill is at the last index
+        for (capacity, seq_num, head) in [(8, 3, 0), (4, 10, 3)] {
+            let data = build_serum_event_queue_data(capacity, seq_num, &fills).unwrap();
+            let ctx = SerumEventQueueContext::from_account_data(
+                &Pubkey::default(),
+                &Pubkey::default(),
+                &data,
+            );
+            assert_eq!(ctx.seq_num, seq_num);
+            assert_eq!(ctx.count, fills.len() as u64);
+            assert_eq!(ctx.head, head);
+            assert_fills_eq(&ctx.get_fills(), &fills);
+            assert_fills_eq(&ctx.get_fills_since(seq_num - 1), &fills[2..]);
+        }
+        assert!(build_serum_event_queue_data(2, 10, &fills).is_none());
+        assert!(build_serum_event_queue_data(8, 2, &fills).is_none());
+    }
+}
@@ -0,0 +1,724 @@
+use anchor_lang::{Owner, ZeroCopy};
+use cypher_client::{
+    cache_account,
+    math::MathError,
+    utils::{derive_account_address, try_get_zero_copy_account},
+    CacheAccount, Clearing, CypherAccount, CypherSubAccount, MarginCollateralRatioType,
+};
+use fixed::types::I80F48;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::{fs, path::Path, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+use crate::{
+    contexts::{
+        AccountContext, AccountsSnapshot, CacheContext, ContextError, SubAccountContext,
+        UserContext,
+    },
+    utils::get_cypher_zero_copy_account,
+};
+
+#[derive(Debug, Error)]
+pub enum GoldenError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    #[error(transparent)]
+    ContextError(#[from] ContextError),
+    #[error("Invalid pubkey: {0}")]
+    InvalidPubkey(String),
+    #[error("Invalid account data for account {0}")]
+    InvalidAccountData(String),
+    #[error("Margin math error: {0}")]
+    Math(MathError),
+    #[error("Fixture {0} does not have golden values")]
+    MissingGolden(String),
+    #[error("Fixture {fixture} mismatch on {field}: expected {expected}, got {actual}")]
+    Mismatch {
+        fixture: String,
+        field: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// The state of an account as captured in a fixture.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountFixture {
+    /// The address of the account.
+    pub address: String,
+    /// The base64 encoded account data.
+    pub data: String,
+}
+
+impl AccountFixture {
+    /// Creates a new [`AccountFixture`] from the given account data.
+    pub fn new(address: &Pubkey, data: &[u8]) -> Self {
+        Self {
+            address: address.to_string(),
+            data: base64::encode(data),
+        }
+    }
+
+    /// Gets the address of the account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the address is not a valid pubkey.
+    pub fn get_address(&self) -> Result<Pubkey, GoldenError> {
+        match Pubkey::from_str(&self.address) {
+            Ok(p) => Ok(p),
+            Err(_) => Err(GoldenError::InvalidPubkey(self.address.clone())),
+        }
+    }
+
+    /// Gets the decoded account data.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the data is not valid base64.
+    pub fn get_data(&self) -> Result<Vec<u8>, GoldenError> {
+        match base64::decode(&self.data) {
+            Ok(d) => Ok(d),
+            Err(e) => Err(GoldenError::Base64(e)),
+        }
+    }
+
+    /// Deserializes the account data as the given zero copy account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the data is not valid base64 or is not a valid account of the given type.
+    pub fn get_state<T: ZeroCopy + Owner>(&self) -> Result<Box<T>, GoldenError> {
+        let data = match self.get_data() {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        match try_get_zero_copy_account::<T>(&data) {
+            Some(s) => Ok(s),
+            None => Err(GoldenError::InvalidAccountData(self.address.clone())),
+        }
+    }
+}
+
+/// The raw bits of the [`I80F48`] margin values of a sub account under a given margin collateral ratio type.
+///
+/// The bits are stored instead of the numbers so that the values are compared bit-for-bit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarginValuesGolden {
+    /// The weighted assets value.
+    pub assets_value: i128,
+    /// The unweighted assets value.
+    pub assets_value_unweighted: i128,
+    /// The weighted liabilities value.
+    pub liabilities_value: i128,
+    /// The unweighted liabilities value.
+    pub liabilities_value_unweighted: i128,
+    /// The c-ratio.
+    pub c_ratio: i128,
+}
+
+impl MarginValuesGolden {
+    /// Computes the margin values of the given sub account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the margin math can not be computed.
+    pub fn compute(
+        sub_account: &CypherSubAccount,
+        cache_account: &CacheAccount,
+        mcr_type: MarginCollateralRatioType,
+    ) -> Result<Self, MathError> {
+        let (assets_value, assets_value_unweighted) =
+            match sub_account.get_assets_value(cache_account, mcr_type) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let (liabilities_value, liabilities_value_unweighted) =
+            match sub_account.get_liabilities_value(cache_account, mcr_type) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        let c_ratio = match sub_account.get_margin_c_ratio(cache_account, mcr_type) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        Ok(Self {
+            assets_value: assets_value.to_bits(),
+            assets_value_unweighted: assets_value_unweighted.to_bits(),
+            liabilities_value: liabilities_value.to_bits(),
+            liabilities_value_unweighted: liabilities_value_unweighted.to_bits(),
+            c_ratio: c_ratio.to_bits(),
+        })
+    }
+
+    fn fields(&self) -> [(&'static str, i128); 5] {
+        [
+            ("assets_value", self.assets_value),
+            ("assets_value_unweighted", self.assets_value_unweighted),
+            ("liabilities_value", self.liabilities_value),
+            (
+                "liabilities_value_unweighted",
+                self.liabilities_value_unweighted,
+            ),
+            ("c_ratio", self.c_ratio),
+        ]
+    }
+}
+
+/// The golden margin values of a sub account.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubAccountGolden {
+    /// The address of the sub account.
+    pub address: String,
+    /// The values under the initialization margin collateral ratio type.
+    pub initialization: MarginValuesGolden,
+    /// The values under the maintenance margin collateral ratio type.
+    pub maintenance: MarginValuesGolden,
+    /// Whether the sub account is bankrupt.
+    pub is_bankrupt: bool,
+}
+
+/// The golden margin values of an account and it's sub accounts.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarginGolden {
+    /// The raw bits of the account's initialization c-ratio, see [`UserContext::get_margin_c_ratio`].
+    pub initialization_c_ratio: i128,
+    /// The raw bits of the account's maintenance c-ratio, see [`UserContext::get_margin_c_ratio`].
+    pub maintenance_c_ratio: i128,
+    /// The golden values of the sub accounts, in the order of the fixture's sub accounts.
+    pub sub_accounts: Vec<SubAccountGolden>,
+}
+
+/// A set of accounts captured at the same slot along with the margin values computed from them when captured.
+///
+/// Fixtures are meant to be captured once from a cluster, see [`MarginFixture::capture`], and checked into the
+/// consumer's repository, so that refactors of the valuation code can be verified bit-for-bit with
+/// [`MarginFixture::verify`] without any RPC requests.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarginFixture {
+    /// The name of the fixture, used in errors.
+    pub name: String,
+    /// The slot the accounts were captured at.
+    pub slot: u64,
+    /// The authority of the account.
+    pub authority: String,
+    /// The [`CypherAccount`].
+    pub account: AccountFixture,
+    /// The [`CypherSubAccount`]s of the account.
+    pub sub_accounts: Vec<AccountFixture>,
+    /// The [`Clearing`] of the account.
+    pub clearing: AccountFixture,
+    /// The [`CacheAccount`].
+    pub cache: AccountFixture,
+    /// The golden values, if they were recorded.
+    pub golden: Option<MarginGolden>,
+}
+
+impl MarginFixture {
+    /// Captures the given account, it's sub accounts, clearing and the cache account at the same slot
+    /// and records their current margin values as the golden values.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests,
+    /// one of the accounts does not exist or the margin math can not be computed.
+    pub async fn capture(
+        rpc_client: &Arc<RpcClient>,
+        name: &str,
+        authority: &Pubkey,
+        account_number: u8,
+    ) -> Result<Self, GoldenError> {
+        let (account, _) = derive_account_address(authority, account_number);
+        let account_state =
+            match get_cypher_zero_copy_account::<CypherAccount>(rpc_client, &account).await {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(GoldenError::ContextError(ContextError::ClientError(e)));
+                }
+            };
+        let sub_accounts = account_state
+            .sub_account_caches
+            .iter()
+            .filter(|c| c.sub_account != Pubkey::default())
+            .map(|c| c.sub_account)
+            .collect::<Vec<Pubkey>>();
+
+        // the account is requested again so that every account of the fixture is observed at the same slot
+        let mut accounts = vec![account, account_state.clearing, cache_account::id()];
+        accounts.extend(sub_accounts.iter());
+        let snapshot =
+            match AccountsSnapshot::fetch(rpc_client, &accounts, CommitmentConfig::confirmed())
+                .await
+            {
+                Ok(s) => s,
+                Err(e) => {
+                    return Err(GoldenError::ContextError(e));
+                }
+            };
+        let get_fixture = |address: &Pubkey| match snapshot.get_account_data(address) {
+            Some(d) => Ok(AccountFixture::new(address, d)),
+            None => Err(GoldenError::ContextError(ContextError::AccountNotFound(
+                format!(
+                    "Account {} does not exist in snapshot at slot {}",
+                    address, snapshot.slot
+                ),
+            ))),
+        };
+
+        let mut fixture = Self {
+            name: name.to_string(),
+            slot: snapshot.slot,
+            authority: authority.to_string(),
+            account: get_fixture(&account)?,
+            sub_accounts: sub_accounts
+                .iter()
+                .map(get_fixture)
+                .collect::<Result<Vec<AccountFixture>, GoldenError>>()?,
+            clearing: get_fixture(&account_state.clearing)?,
+            cache: get_fixture(&cache_account::id())?,
+            golden: None,
+        };
+        fixture.golden = match fixture.compute_golden() {
+            Ok(g) => Some(g),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        Ok(fixture)
+    }
+
+    /// Loads a [`MarginFixture`] from the given JSON file.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be read or is not a valid fixture.
+    pub fn load(path: &Path) -> Result<Self, GoldenError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(GoldenError::Io(e));
+            }
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(f) => Ok(f),
+            Err(e) => Err(GoldenError::Json(e)),
+        }
+    }
+
+    /// Saves the [`MarginFixture`] to the given file as JSON.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the file can not be written.
+    pub fn save(&self, path: &Path) -> Result<(), GoldenError> {
+        let contents = match serde_json::to_string_pretty(self) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(GoldenError::Json(e));
+            }
+        };
+        match fs::write(path, contents) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(GoldenError::Io(e)),
+        }
+    }
+
+    /// Gets the [`UserContext`] of the fixture's account and sub accounts.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if one of the accounts is invalid.
+    pub fn get_user_ctx(&self) -> Result<UserContext, GoldenError> {
+        let authority = match Pubkey::from_str(&self.authority) {
+            Ok(p) => p,
+            Err(_) => {
+                return Err(GoldenError::InvalidPubkey(self.authority.clone()));
+            }
+        };
+        let account_ctx = AccountContext::new(
+            self.account.get_address()?,
+            self.account.get_state::<CypherAccount>()?,
+        );
+        let mut sub_account_ctxs = Vec::with_capacity(self.sub_accounts.len());
+        for sub_account in self.sub_accounts.iter() {
+            sub_account_ctxs.push(SubAccountContext::new(
+                sub_account.get_address()?,
+                sub_account.get_state::<CypherSubAccount>()?,
+            ));
+        }
+        Ok(UserContext::new(authority, account_ctx, sub_account_ctxs))
+    }
+
+    /// Gets the [`CacheContext`] of the fixture.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the cache account is invalid.
+    pub fn get_cache_ctx(&self) -> Result<CacheContext, GoldenError> {
+        match self.cache.get_state::<CacheAccount>() {
+            Ok(s) => Ok(CacheContext::new(s)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Computes the margin values of the fixture's accounts with the current valuation code.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if one of the accounts is invalid or the margin math can not be computed.
+    pub fn compute_golden(&self) -> Result<MarginGolden, GoldenError> {
+        let user_ctx = match self.get_user_ctx() {
+            Ok(u) => u,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let cache_ctx = match self.get_cache_ctx() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let clearing = match self.clearing.get_state::<Clearing>() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let cache_account = cache_ctx.state.as_ref();
+
+        let mut sub_accounts = Vec::with_capacity(user_ctx.sub_account_ctxs.len());
+        for sub_account_ctx in user_ctx.sub_account_ctxs.iter() {
+            let state = sub_account_ctx.state.as_ref();
+            let initialization = MarginValuesGolden::compute(
+                state,
+                cache_account,
+                MarginCollateralRatioType::Initialization,
+            )
+            .map_err(GoldenError::Math)?;
+            let maintenance = MarginValuesGolden::compute(
+                state,
+                cache_account,
+                MarginCollateralRatioType::Maintenance,
+            )
+            .map_err(GoldenError::Math)?;
+            let is_bankrupt = state
+                .is_bankrupt(&clearing, cache_account)
+                .map_err(GoldenError::Math)?;
+            sub_accounts.push(SubAccountGolden {
+                address: sub_account_ctx.address.to_string(),
+                initialization,
+                maintenance,
+                is_bankrupt,
+            });
+        }
+
+        let initialization_c_ratio = user_ctx
+            .get_margin_c_ratio(&cache_ctx, MarginCollateralRatioType::Initialization)
+            .map_err(GoldenError::Math)?;
+        let maintenance_c_ratio = user_ctx
+            .get_margin_c_ratio(&cache_ctx, MarginCollateralRatioType::Maintenance)
+            .map_err(GoldenError::Math)?;
+
+        Ok(MarginGolden {
+            initialization_c_ratio: initialization_c_ratio.to_bits(),
+            maintenance_c_ratio: maintenance_c_ratio.to_bits(),
+            sub_accounts,
+        })
+    }
+
+    /// Verifies that the margin values computed with the current valuation code match the golden values bit-for-bit.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the fixture has no golden values, the values can not be computed
+    /// or describing the first value which does not match.
+    pub fn verify(&self) -> Result<(), GoldenError> {
+        let expected = match &self.golden {
+            Some(g) => g,
+            None => {
+                return Err(GoldenError::MissingGolden(self.name.clone()));
+            }
+        };
+        let actual = match self.compute_golden() {
+            Ok(g) => g,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let mismatch = |field: String, expected: String, actual: String| GoldenError::Mismatch {
+            fixture: self.name.clone(),
+            field,
+            expected,
+            actual,
+        };
+        let fmt_bits = |bits: i128| format!("{} ({})", I80F48::from_bits(bits), bits);
+
+        for (field, e_bits, a_bits) in [
+            (
+                "initialization_c_ratio",
+                expected.initialization_c_ratio,
+                actual.initialization_c_ratio,
+            ),
+            (
+                "maintenance_c_ratio",
+                expected.maintenance_c_ratio,
+                actual.maintenance_c_ratio,
+            ),
+        ] {
+            if e_bits != a_bits {
+                return Err(mismatch(
+                    field.to_string(),
+                    fmt_bits(e_bits),
+                    fmt_bits(a_bits),
+                ));
+            }
+        }
+        if expected.sub_accounts.len() != actual.sub_accounts.len() {
+            return Err(mismatch(
+                "sub_accounts".to_string(),
+                expected.sub_accounts.len().to_string(),
+                actual.sub_accounts.len().to_string(),
+            ));
+        }
+        for (e, a) in expected.sub_accounts.iter().zip(actual.sub_accounts.iter()) {
+            for (mcr_type, e_values, a_values) in [
+                ("initialization", &e.initialization, &a.initialization),
+                ("maintenance", &e.maintenance, &a.maintenance),
+            ] {
+                for ((field, e_bits), (_, a_bits)) in
+                    e_values.fields().iter().zip(a_values.fields().iter())
+                {
+                    if e_bits != a_bits {
+                        return Err(mismatch(
+                            format!("{}.{}.{}", e.address, mcr_type, field),
+                            fmt_bits(*e_bits),
+                            fmt_bits(*a_bits),
+                        ));
+                    }
+                }
+            }
+            if e.is_bankrupt != a.is_bankrupt {
+                return Err(mismatch(
+                    format!("{}.is_bankrupt", e.address),
+                    e.is_bankrupt.to_string(),
+                    a.is_bankrupt.to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Verifies every [`MarginFixture`] in the given directory, i.e. every file with a `json` extension,
+/// returning the number of fixtures verified.
+///
+/// ### Errors
+///
+/// This function will return an error if the directory can not be read or a fixture can not be loaded or verified.
+pub fn verify_margin_fixtures(dir: &Path) -> Result<usize, GoldenError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => {
+            return Err(GoldenError::Io(e));
+        }
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(e) => {
+                let path = e.path();
+                if path.extension().map(|e| e == "json").unwrap_or(false) {
+                    paths.push(path);
+                }
+            }
+            Err(e) => {
+                return Err(GoldenError::Io(e));
+            }
+        }
+    }
+    // sorted so that the first failing fixture is always the same one
+    paths.sort();
+
+    for path in paths.iter() {
+        let fixture = match MarginFixture::load(path) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        match fixture.verify() {
+            Ok(()) => (),
+            Err(e) => {
+                return Err(e);
+            }
+        }
+    }
+    Ok(paths.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+    use bytemuck::{Pod, Zeroable};
+    use cypher_client::{constants::QUOTE_TOKEN_IDX, quote_mint};
+
+    /// The raw bits of [`INV_ONE_HUNDRED_FIXED`], which every weight of a [`Cache`] is multiplied by.
+    ///
+    /// [`INV_ONE_HUNDRED_FIXED`]: cypher_client::constants::INV_ONE_HUNDRED_FIXED
+    /// [`Cache`]: cypher_client::Cache
+    const INV_ONE_HUNDRED_BITS: i128 = 2_814_749_767_106;
+
+    fn get_account_data<T: Discriminator + Pod>(state: &T) -> Vec<u8> {
+        let mut data = T::discriminator().to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(state));
+        data
+    }
+
+    /// Builds a fixture with a single cross margined sub account, which deposits 1000 units of the quote token
+    /// and borrows 10 units of a token priced at 20, so that every margin value is known exactly.
+    fn get_fixture() -> MarginFixture {
+        let authority = Pubkey::new_unique();
+        let (account, _) = derive_account_address(&authority, 0);
+        let sub_account = Pubkey::new_unique();
+        let clearing = Pubkey::new_unique();
+
+        let mut cache_account = CacheAccount::zeroed();
+        let quote_cache = &mut cache_account.caches[0];
+        quote_cache.oracle_price = I80F48::ONE.to_bits();
+        quote_cache.deposit_index = I80F48::ONE.to_bits();
+        quote_cache.borrow_index = I80F48::ONE.to_bits();
+        quote_cache.spot_init_asset_weight = 100;
+        quote_cache.spot_maint_asset_weight = 100;
+        let token_cache = &mut cache_account.caches[1];
+        token_cache.oracle_price = I80F48::from_num(20).to_bits();
+        token_cache.deposit_index = I80F48::ONE.to_bits();
+        token_cache.borrow_index = I80F48::ONE.to_bits();
+        token_cache.spot_init_liab_weight = 125;
+        token_cache.spot_maint_liab_weight = 100;
+
+        let mut sub_account_state = CypherSubAccount::zeroed();
+        sub_account_state.authority = authority;
+        sub_account_state.master_account = account;
+        sub_account_state.clearing = clearing;
+        let quote_position = &mut sub_account_state.positions[QUOTE_TOKEN_IDX].spot;
+        quote_position.token_mint = quote_mint::ID;
+        quote_position.cache_index = 0;
+        quote_position.position = I80F48::from_num(1_000).to_bits();
+        let token_position = &mut sub_account_state.positions[0].spot;
+        token_position.token_mint = Pubkey::new_unique();
+        token_position.cache_index = 1;
+        token_position.position = I80F48::from_num(-10).to_bits();
+
+        let mut account_state = CypherAccount::zeroed();
+        account_state.authority = authority;
+        account_state.clearing = clearing;
+        account_state.sub_account_caches[0].sub_account = sub_account;
+
+        MarginFixture {
+            name: "synthetic".to_string(),
+            slot: 0,
+            authority: authority.to_string(),
+            account: AccountFixture::new(&account, &get_account_data(&account_state)),
+            sub_accounts: vec![AccountFixture::new(
+                &sub_account,
+                &get_account_data(&sub_account_state),
+            )],
+            clearing: AccountFixture::new(&clearing, &get_account_data(&Clearing::zeroed())),
+            cache: AccountFixture::new(&cache_account::id(), &get_account_data(&cache_account)),
+            golden: None,
+        }
+    }
+
+    #[test]
+    fn synthetic_fixture_matches_golden_values() {
+        let fixture = get_fixture();
+        let golden = fixture.compute_golden().unwrap();
+
+        // the quote deposit is worth 1000 and the borrow 200, the weights only cancel out in the c-ratios
+        let initialization = MarginValuesGolden {
+            assets_value: 1_000 * 100 * INV_ONE_HUNDRED_BITS,
+            assets_value_unweighted: I80F48::from_num(1_000).to_bits(),
+            liabilities_value: 200 * 125 * INV_ONE_HUNDRED_BITS,
+            liabilities_value_unweighted: I80F48::from_num(200).to_bits(),
+            c_ratio: I80F48::from_num(4).to_bits(),
+        };
+        let maintenance = MarginValuesGolden {
+            liabilities_value: 200 * 100 * INV_ONE_HUNDRED_BITS,
+            c_ratio: I80F48::from_num(5).to_bits(),
+            ..initialization
+        };
+        assert_eq!(golden.initialization_c_ratio, initialization.c_ratio);
+        assert_eq!(golden.maintenance_c_ratio, maintenance.c_ratio);
+        assert_eq!(golden.sub_accounts.len(), 1);
+        assert_eq!(
+            golden.sub_accounts[0].address,
+            fixture.sub_accounts[0].address
+        );
+        assert_eq!(golden.sub_accounts[0].initialization, initialization);
+        assert_eq!(golden.sub_accounts[0].maintenance, maintenance);
+        assert!(!golden.sub_accounts[0].is_bankrupt);
+    }
+
+    #[test]
+    fn saved_fixture_verifies() {
+        let mut fixture = get_fixture();
+        fixture.golden = Some(fixture.compute_golden().unwrap());
+
+        let path =
+            std::env::temp_dir().join(format!("cypher-margin-fixture-{}.json", std::process::id()));
+        fixture.save(&path).unwrap();
+        let loaded = MarginFixture::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded, fixture);
+        assert!(loaded.verify().is_ok());
+    }
+
+    #[test]
+    fn tampered_golden_value_is_a_mismatch() {
+        let mut fixture = get_fixture();
+        let mut golden = fixture.compute_golden().unwrap();
+        golden.sub_accounts[0].maintenance.liabilities_value += 1;
+        fixture.golden = Some(golden);
+
+        match fixture.verify() {
+            Err(GoldenError::Mismatch { field, .. }) => {
+                assert!(field.ends_with("maintenance.liabilities_value"))
+            }
+            r => panic!("expected a mismatch, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn fixture_without_golden_values_does_not_verify() {
+        assert!(matches!(
+            get_fixture().verify(),
+            Err(GoldenError::MissingGolden(_))
+        ));
+    }
+
+    #[test]
+    fn committed_fixtures_match_golden_values() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/margin");
+        // an empty directory would otherwise pass without verifying anything
+        assert!(verify_margin_fixtures(&dir).unwrap() > 0);
+    }
+}
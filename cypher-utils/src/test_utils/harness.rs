@@ -0,0 +1,427 @@
+use anchor_spl::token::spl_token::{self, state::Mint};
+use cypher_client::{
+    cache_account,
+    constants::QUOTE_TOKEN_DECIMALS,
+    instructions::{
+        create_oracle_products, create_oracle_stub, create_pool, create_public_clearing,
+        init_cache_account, set_oracle_stub_price,
+    },
+    quote_mint,
+    utils::{
+        derive_oracle_products_address, derive_oracle_stub_address, derive_pool_address,
+        derive_pool_node_address, derive_pool_node_vault_address,
+        derive_pool_node_vault_signer_address, derive_public_clearing_address,
+    },
+    CacheAccount, ClearingType, CreateClearingArgs, CreateOracleProductsArgs, CreatePoolArgs,
+    ProductsType,
+};
+use fixed::types::I80F48;
+use solana_client::client_error::ClientError;
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    bpf_loader,
+    instruction::Instruction,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use std::{fs, path::Path};
+use thiserror::Error;
+
+use crate::{transport::sign_and_send_transaction, utils::encode_string};
+
+use super::BanksTransport;
+
+/// The compute units available to each transaction processed by the harness.
+pub const HARNESS_COMPUTE_MAX_UNITS: u64 = 1_400_000;
+
+#[derive(Debug, Error)]
+pub enum HarnessError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+}
+
+/// The accounts of a pool created by the [`CypherTestHarness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolFixture {
+    /// The pool.
+    pub pool: Pubkey,
+    /// The pool's first node.
+    pub pool_node: Pubkey,
+    /// The token vault of the pool node.
+    pub vault: Pubkey,
+    /// The signer of the token vault.
+    pub vault_signer: Pubkey,
+    /// The oracle products of the pool's token.
+    pub oracle_products: Pubkey,
+}
+
+/// The accounts created by [`CypherTestHarness::create_fixtures`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CypherFixtures {
+    /// The public clearing.
+    pub clearing: Pubkey,
+    /// The quote token pool.
+    pub quote_pool: PoolFixture,
+}
+
+/// A local cluster running the cypher program with `solana-program-test`.
+///
+/// The program is loaded from a dumped `.so`, e.g. obtained with `solana program dump`, at it's usual address, and the
+/// accounts the program expects at fixed addresses are pre-allocated: the cache account, which is zeroed and owned
+/// by the program, and the quote mint, whose mint authority is [`CypherTestHarness::quote_mint_authority`].
+///
+/// The contexts and services can be run against the harness through it's [`BanksTransport`], while fixtures are
+/// created with the instruction builders of `cypher-client`, signed by the payer, which is the authority of every
+/// created account.
+pub struct CypherTestHarness {
+    /// The context of the local cluster.
+    pub context: ProgramTestContext,
+    /// The transport of the local cluster.
+    pub transport: BanksTransport,
+    /// The mint authority of the quote mint.
+    pub quote_mint_authority: Keypair,
+}
+
+impl std::fmt::Debug for CypherTestHarness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CypherTestHarness")
+            .field("payer", &self.context.payer.pubkey())
+            .field("quote_mint_authority", &self.quote_mint_authority.pubkey())
+            .finish()
+    }
+}
+
+impl CypherTestHarness {
+    /// Creates the [`ProgramTest`] of the harness, to which further accounts can be added before it is started
+    /// with [`CypherTestHarness::start_with_program_test`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the program file can not be read.
+    pub fn new_program_test(
+        program_path: &Path,
+        quote_mint_authority: &Pubkey,
+    ) -> Result<ProgramTest, HarnessError> {
+        let program_data = match fs::read(program_path) {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(HarnessError::Io(e));
+            }
+        };
+
+        let mut program_test = ProgramTest::default();
+        program_test.set_compute_max_units(HARNESS_COMPUTE_MAX_UNITS);
+        program_test.add_account(
+            cypher_client::id(),
+            new_rent_exempt_account(program_data, &bpf_loader::id(), true),
+        );
+        program_test.add_account(
+            cache_account::id(),
+            new_rent_exempt_account(
+                vec![0; 8 + std::mem::size_of::<CacheAccount>()],
+                &cypher_client::id(),
+                false,
+            ),
+        );
+
+        let mut mint_data = vec![0; Mint::LEN];
+        let mint = Mint {
+            mint_authority: COption::Some(*quote_mint_authority),
+            supply: 0,
+            decimals: QUOTE_TOKEN_DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        Mint::pack(mint, &mut mint_data).unwrap();
+        program_test.add_account(
+            quote_mint::id(),
+            new_rent_exempt_account(mint_data, &spl_token::id(), false),
+        );
+
+        Ok(program_test)
+    }
+
+    /// Starts a local cluster running the program at the given path, see [`CypherTestHarness`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the program file can not be read.
+    pub async fn start(program_path: &Path) -> Result<Self, HarnessError> {
+        let quote_mint_authority = Keypair::new();
+        let program_test =
+            match Self::new_program_test(program_path, &quote_mint_authority.pubkey()) {
+                Ok(p) => p,
+                Err(e) => {
+                    return Err(e);
+                }
+            };
+        Ok(Self::start_with_program_test(program_test, quote_mint_authority).await)
+    }
+
+    /// Starts a local cluster with the given [`ProgramTest`], see [`CypherTestHarness::new_program_test`].
+    pub async fn start_with_program_test(
+        program_test: ProgramTest,
+        quote_mint_authority: Keypair,
+    ) -> Self {
+        let context = program_test.start_with_context().await;
+        let transport = BanksTransport::new(context.banks_client.clone());
+        Self {
+            context,
+            transport,
+            quote_mint_authority,
+        }
+    }
+
+    /// The payer of every transaction processed by the harness.
+    pub fn payer(&self) -> &Keypair {
+        &self.context.payer
+    }
+
+    /// Processes a transaction with the given instructions, paid for by the payer and signed by the given signers.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed.
+    pub async fn process_instructions(
+        &self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, ClientError> {
+        sign_and_send_transaction(&self.transport, ixs, self.payer(), signers, true).await
+    }
+
+    /// Creates the public clearing with the given arguments, the bump is filled in.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed.
+    pub async fn create_clearing(&self, args: CreateClearingArgs) -> Result<Pubkey, ClientError> {
+        let (clearing, bump) = derive_public_clearing_address();
+        let payer = self.payer().pubkey();
+        let ix = create_public_clearing(
+            &clearing,
+            &payer,
+            &payer,
+            CreateClearingArgs { bump, ..args },
+        );
+        match self.process_instructions(&[ix], &[]).await {
+            Ok(_) => Ok(clearing),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Initializes the pre-allocated cache account for the given clearing.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed.
+    pub async fn init_cache_account(&self, clearing: &Pubkey) -> Result<(), ClientError> {
+        let ix = init_cache_account(clearing, &cache_account::id(), &self.payer().pubkey());
+        match self.process_instructions(&[ix], &[]).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates an oracle stub with the given price and the oracle products reading it,
+    /// returning the address of the oracle products.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed.
+    pub async fn create_oracle(&self, symbol: &str, price: I80F48) -> Result<Pubkey, ClientError> {
+        let encoded_symbol = encode_string(symbol);
+        let (oracle_stub, _) = derive_oracle_stub_address(&encoded_symbol);
+        let (oracle_products, _) = derive_oracle_products_address(&encoded_symbol);
+        let payer = self.payer().pubkey();
+        let ixs = vec![
+            create_oracle_stub(&oracle_stub, &payer, encoded_symbol),
+            set_oracle_stub_price(&oracle_stub, price.to_bits()),
+            create_oracle_products(
+                &cache_account::id(),
+                &oracle_products,
+                &payer,
+                &payer,
+                Some(&[oracle_stub]),
+                CreateOracleProductsArgs {
+                    products_type: ProductsType::Stub,
+                    num_products: 1,
+                    max_confidence_interval: 0.0,
+                    // the stub is the only product
+                    weights: vec![100],
+                    symbol: encoded_symbol,
+                },
+            ),
+        ];
+        match self.process_instructions(&ixs, &[]).await {
+            Ok(_) => Ok(oracle_products),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the price of the oracle stub of the given symbol, see [`CypherTestHarness::create_oracle`].
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed.
+    pub async fn set_oracle_price(&self, symbol: &str, price: I80F48) -> Result<(), ClientError> {
+        let (oracle_stub, _) = derive_oracle_stub_address(&encode_string(symbol));
+        let ix = set_oracle_stub_price(&oracle_stub, price.to_bits());
+        match self.process_instructions(&[ix], &[]).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a pool and it's first node for the given token with the given arguments,
+    /// the bumps, decimals and pool name are filled in.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction failed.
+    pub async fn create_pool(
+        &self,
+        clearing: &Pubkey,
+        pool_name: &str,
+        token_mint: &Pubkey,
+        decimals: u8,
+        oracle_products: &Pubkey,
+        args: CreatePoolArgs,
+    ) -> Result<PoolFixture, ClientError> {
+        let encoded_pool_name = encode_string(pool_name);
+        let (pool, pool_bump) = derive_pool_address(&encoded_pool_name);
+        let (pool_node, _) = derive_pool_node_address(&pool, 0);
+        let (vault, _) = derive_pool_node_vault_address(&pool_node);
+        let (vault_signer, vault_signer_bump) = derive_pool_node_vault_signer_address(&pool_node);
+        let payer = self.payer().pubkey();
+        let ix = create_pool(
+            clearing,
+            &cache_account::id(),
+            &pool,
+            &pool_node,
+            token_mint,
+            &vault,
+            &vault_signer,
+            oracle_products,
+            &Pubkey::default(),
+            &payer,
+            &payer,
+            CreatePoolArgs {
+                vault_signer_bump,
+                pool_bump,
+                decimals,
+                pool_name: encoded_pool_name,
+                ..args
+            },
+        );
+        match self.process_instructions(&[ix], &[]).await {
+            Ok(_) => Ok(PoolFixture {
+                pool,
+                pool_node,
+                vault,
+                vault_signer,
+                oracle_products: *oracle_products,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates the minimal set of accounts most tests need: the public clearing, the cache account,
+    /// the quote token's oracle, priced at one, and the quote token pool.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if one of the transactions failed.
+    pub async fn create_fixtures(&self) -> Result<CypherFixtures, ClientError> {
+        let clearing = match self.create_clearing(get_default_clearing_args()).await {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        match self.init_cache_account(&clearing).await {
+            Ok(()) => (),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let quote_oracle_products = match self.create_oracle("USDC", I80F48::ONE).await {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        let quote_pool = match self
+            .create_pool(
+                &clearing,
+                "USDC",
+                &quote_mint::id(),
+                QUOTE_TOKEN_DECIMALS,
+                &quote_oracle_products,
+                get_default_pool_args(),
+            )
+            .await
+        {
+            Ok(p) => p,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+        Ok(CypherFixtures {
+            clearing,
+            quote_pool,
+        })
+    }
+}
+
+/// Gets the [`CreateClearingArgs`] used by [`CypherTestHarness::create_fixtures`],
+/// with an init margin of 125%, a maintenance margin of 110% and a 5% liquidator fee.
+pub fn get_default_clearing_args() -> CreateClearingArgs {
+    CreateClearingArgs {
+        clearing_number: 0,
+        bump: 0,
+        maint_margin: 110,
+        init_margin: 125,
+        target_margin: 150,
+        liq_liqor_fee: 5,
+        liq_insurance_fee: 1,
+        fee_mint: quote_mint::id(),
+        clearing_type: ClearingType::Public,
+        fee_tiers: Vec::new(),
+    }
+}
+
+/// Gets the [`CreatePoolArgs`] used by [`CypherTestHarness::create_fixtures`], a pool whose positions are fully
+/// weighted as assets and liabilities, with an optimal APR of 5% at 80% utilization and a maximum APR of 50%.
+pub fn get_default_pool_args() -> CreatePoolArgs {
+    CreatePoolArgs {
+        vault_signer_bump: 0,
+        pool_bump: 0,
+        optimal_apr: 5,
+        optimal_util: 80,
+        max_apr: 50,
+        decimals: 0,
+        init_asset_weight: 100,
+        init_liab_weight: 100,
+        maint_asset_weight: 100,
+        maint_liab_weight: 100,
+        pool_name: [0; 32],
+    }
+}
+
+fn new_rent_exempt_account(data: Vec<u8>, owner: &Pubkey, executable: bool) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: *owner,
+        executable,
+        rent_epoch: 0,
+    }
+}
@@ -0,0 +1,9 @@
+pub mod banks;
+pub mod books;
+pub mod golden;
+pub mod harness;
+
+pub use banks::*;
+pub use books::*;
+pub use golden::*;
+pub use harness::*;
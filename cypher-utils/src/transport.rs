@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    transaction::Transaction,
+};
+use std::sync::Arc;
+
+use crate::utils::{create_transaction, send_transaction};
+
+/// Fetches account states, the only access to the cluster the contexts need to be loaded and reloaded.
+///
+/// This is implemented for the [`RpcClient`], other implementations allow running the contexts against
+/// e.g. `solana-program-test`'s `BanksClient` or a mock holding fixed account states. Their errors can be
+/// surfaced as a [`ClientError`] of kind [`ClientErrorKind::Custom`](solana_client::client_error::ClientErrorKind).
+#[async_trait]
+pub trait AccountFetcher: Send + Sync {
+    /// Gets the data of the given account.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the account does not exist or the request failed.
+    async fn get_account_data(&self, account: &Pubkey) -> Result<Vec<u8>, ClientError>;
+
+    /// Gets the given accounts, `None` for each account that does not exist.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the request failed.
+    async fn get_multiple_accounts(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError>;
+}
+
+/// Sends transactions to the cluster.
+///
+/// See [`AccountFetcher`] for the rationale.
+#[async_trait]
+pub trait TxSender: Send + Sync {
+    /// Gets the latest blockhash to sign transactions with.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the request failed.
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError>;
+
+    /// Sends the given signed transaction, waiting for it to be confirmed if `confirm` is set.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if the transaction could not be sent, failed or was not confirmed.
+    async fn send_transaction(
+        &self,
+        tx: &Transaction,
+        confirm: bool,
+    ) -> Result<Signature, ClientError>;
+}
+
+#[async_trait]
+impl AccountFetcher for RpcClient {
+    async fn get_account_data(&self, account: &Pubkey) -> Result<Vec<u8>, ClientError> {
+        RpcClient::get_account_data(self, account).await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        RpcClient::get_multiple_accounts(self, accounts).await
+    }
+}
+
+#[async_trait]
+impl TxSender for RpcClient {
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        RpcClient::get_latest_blockhash(self).await
+    }
+
+    async fn send_transaction(
+        &self,
+        tx: &Transaction,
+        confirm: bool,
+    ) -> Result<Signature, ClientError> {
+        send_transaction(self, tx, confirm).await
+    }
+}
+
+#[async_trait]
+impl<T: AccountFetcher + ?Sized> AccountFetcher for Arc<T> {
+    async fn get_account_data(&self, account: &Pubkey) -> Result<Vec<u8>, ClientError> {
+        self.as_ref().get_account_data(account).await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        self.as_ref().get_multiple_accounts(accounts).await
+    }
+}
+
+#[async_trait]
+impl<T: TxSender + ?Sized> TxSender for Arc<T> {
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        self.as_ref().get_latest_blockhash().await
+    }
+
+    async fn send_transaction(
+        &self,
+        tx: &Transaction,
+        confirm: bool,
+    ) -> Result<Signature, ClientError> {
+        self.as_ref().send_transaction(tx, confirm).await
+    }
+}
+
+#[async_trait]
+impl<T: AccountFetcher + ?Sized> AccountFetcher for &T {
+    async fn get_account_data(&self, account: &Pubkey) -> Result<Vec<u8>, ClientError> {
+        (**self).get_account_data(account).await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        accounts: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, ClientError> {
+        (**self).get_multiple_accounts(accounts).await
+    }
+}
+
+#[async_trait]
+impl<T: TxSender + ?Sized> TxSender for &T {
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        (**self).get_latest_blockhash().await
+    }
+
+    async fn send_transaction(
+        &self,
+        tx: &Transaction,
+        confirm: bool,
+    ) -> Result<Signature, ClientError> {
+        (**self).send_transaction(tx, confirm).await
+    }
+}
+
+/// Creates a transaction with the given instructions, payer and signers, signs it with the sender's latest blockhash
+/// and sends it, waiting for it to be confirmed if `confirm` is set.
+///
+/// ### Errors
+///
+/// This function will return an error if the blockhash could not be fetched or the transaction could not be sent,
+/// failed or was not confirmed.
+pub async fn sign_and_send_transaction(
+    sender: &impl TxSender,
+    ixs: &[Instruction],
+    payer: &Keypair,
+    signers: &[&Keypair],
+    confirm: bool,
+) -> Result<Signature, ClientError> {
+    let blockhash = match sender.get_latest_blockhash().await {
+        Ok(h) => h,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let tx = create_transaction(blockhash, ixs, payer, Some(signers));
+    sender.send_transaction(&tx, confirm).await
+}
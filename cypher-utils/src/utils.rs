@@ -1,8 +1,9 @@
 use anchor_lang::{
     prelude::AccountMeta, AccountDeserialize, AccountSerialize, Discriminator, Owner, ZeroCopy,
 };
+use anchor_spl::token::TokenAccount;
 use bytemuck::Pod;
-use cypher_client::serum::parse_dex_account;
+use cypher_client::{serum::parse_dex_account, utils::derive_token_address, wrapped_sol};
 use log::warn;
 use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
 use solana_client::{
@@ -28,7 +29,7 @@ use std::{
 };
 use thiserror::Error;
 
-use crate::transaction_builder::TransactionBuilder;
+use crate::{transaction_builder::TransactionBuilder, transport::AccountFetcher};
 
 use {
     cypher_client::utils::get_zero_copy_account,
@@ -198,7 +199,7 @@ pub async fn get_program_accounts_without_data(
 pub async fn get_cypher_program_account<
     T: AccountSerialize + AccountDeserialize + Discriminator + Clone + Owner,
 >(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     account: &Pubkey,
 ) -> Result<Box<T>, ClientError> {
     let account_res = rpc_client.get_account_data(account).await;
@@ -222,7 +223,7 @@ pub async fn get_cypher_program_account<
 /// or the given account has an invalid Anchor discriminator for the given type.
 #[inline(always)]
 pub async fn get_cypher_zero_copy_account<T: ZeroCopy + Owner>(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     account: &Pubkey,
 ) -> Result<Box<T>, ClientError> {
     let account_res = rpc_client.get_account_data(account).await;
@@ -248,7 +249,7 @@ pub async fn get_cypher_zero_copy_account<T: ZeroCopy + Owner>(
 pub async fn get_multiple_cypher_program_accounts<
     T: AccountSerialize + AccountDeserialize + Discriminator + Clone + Owner,
 >(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     accounts: &[Pubkey],
 ) -> Result<Vec<Box<T>>, ClientError> {
     let account_res = rpc_client.get_multiple_accounts(accounts).await;
@@ -276,7 +277,7 @@ pub async fn get_multiple_cypher_program_accounts<
 /// or the given accounts have an invalid Anchor discriminator for the given type.
 #[inline(always)]
 pub async fn get_multiple_cypher_zero_copy_accounts<T: ZeroCopy + Owner>(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     accounts: &[Pubkey],
 ) -> Result<Vec<Box<T>>, ClientError> {
     let account_res = rpc_client.get_multiple_accounts(accounts).await;
@@ -304,7 +305,7 @@ pub async fn get_multiple_cypher_zero_copy_accounts<T: ZeroCopy + Owner>(
 /// or the given accounts have an invalid Anchor discriminator for the given type.
 #[inline(always)]
 pub async fn get_multiple_dex_accounts<T: Pod>(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     accounts: &[Pubkey],
 ) -> Result<Vec<Box<T>>, ClientError> {
     let account_res = rpc_client.get_multiple_accounts(accounts).await;
@@ -516,9 +517,63 @@ pub fn get_create_account_ix(
     system_instruction::create_account(&payer.pubkey(), &target.pubkey(), rent, space as u64, pid)
 }
 
+/// Gets the amount, in native units, the wallet needs to hold to deposit the given amount of the given token mint.
+///
+/// Wrapped SOL is deposited from a temporary token account funded by the wallet, so the wallet needs
+/// the rent of that account on top of the amount, which is returned when the account is closed.
+pub fn get_deposit_requirement(token_mint: &Pubkey, amount: u64) -> u64 {
+    if token_mint == &wrapped_sol::ID {
+        amount.saturating_add(Rent::default().minimum_balance(TokenAccount::LEN))
+    } else {
+        amount
+    }
+}
+
+/// Gets the balance, in native units, the wallet can deposit of the given token mint.
+///
+/// This is the balance of the wallet's Associated Token Account, zero if it does not exist,
+/// or the wallet's lamports for wrapped SOL, which is wrapped when deposited.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request.
+pub async fn get_wallet_balance(
+    rpc_client: &RpcClient,
+    wallet: &Pubkey,
+    token_mint: &Pubkey,
+) -> Result<u64, ClientError> {
+    if token_mint == &wrapped_sol::ID {
+        return rpc_client.get_balance(wallet).await;
+    }
+    let token_account = derive_token_address(wallet, token_mint);
+    let account = match rpc_client
+        .get_account_with_commitment(&token_account, rpc_client.commitment())
+        .await
+    {
+        Ok(r) => r.value,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    match account {
+        Some(a) => match TokenAccount::try_deserialize(&mut a.data.as_slice()) {
+            Ok(ta) => Ok(ta.amount),
+            Err(e) => {
+                warn!(
+                    "Failed to deserialize token account {}: {}",
+                    token_account,
+                    e.to_string()
+                );
+                Ok(0)
+            }
+        },
+        None => Ok(0),
+    }
+}
+
 /// Attempts to get an OpenBook DEX account data and parses it into the given `T`.
 pub async fn get_dex_account<T: Pod>(
-    rpc_client: &RpcClient,
+    rpc_client: &impl AccountFetcher,
     account: &Pubkey,
 ) -> Result<T, ClientError> {
     match rpc_client.get_account_data(account).await {
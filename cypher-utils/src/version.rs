@@ -0,0 +1,181 @@
+use anchor_lang::{Discriminator, ZeroCopy};
+use cypher_client::{
+    cache_account,
+    version::{idl_hash, IDL_VERSION},
+    CacheAccount, Clearing,
+};
+use log::{info, warn};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{bpf_loader_upgradeable, pubkey::Pubkey};
+use thiserror::Error;
+
+use crate::layouts::AccountLayout;
+
+/// The length of the metadata at the start of an upgradeable program's ProgramData account.
+const PROGRAM_DATA_METADATA_LEN: usize = 45;
+
+/// The discriminant of the `ProgramData` variant of the upgradeable loader's state.
+const PROGRAM_DATA_DISCRIMINANT: u32 = 3;
+
+#[derive(Debug, Error)]
+pub enum VersionError {
+    #[error("The program's ProgramData account {0} is invalid.")]
+    InvalidProgramData(Pubkey),
+    #[error("The program was upgraded at slot {0}, after the last known compatible deployment at slot {1}.")]
+    ProgramUpgraded(u64, u64),
+    #[error("Account {0} does not match the layout of {1} in IDL version {2}.")]
+    LayoutMismatch(Pubkey, String, String),
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+}
+
+/// The deployment information of an upgradeable program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramDeployment {
+    /// The program's ProgramData account.
+    pub program_data: Pubkey,
+    /// The slot at which the program was last deployed or upgraded.
+    pub last_deploy_slot: u64,
+    /// The program's upgrade authority, if the program is upgradeable.
+    pub upgrade_authority: Option<Pubkey>,
+}
+
+/// Gets the [`ProgramDeployment`] of the given upgradeable program.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC request
+/// or the program's ProgramData account is invalid.
+pub async fn get_program_deployment(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<ProgramDeployment, VersionError> {
+    let (program_data, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    let data = match rpc_client.get_account_data(&program_data).await {
+        Ok(d) => d,
+        Err(e) => {
+            return Err(VersionError::ClientError(e));
+        }
+    };
+    if data.len() < PROGRAM_DATA_METADATA_LEN
+        || u32::from_le_bytes(data[0..4].try_into().unwrap()) != PROGRAM_DATA_DISCRIMINANT
+    {
+        return Err(VersionError::InvalidProgramData(program_data));
+    }
+
+    let last_deploy_slot = u64::from_le_bytes(data[4..12].try_into().unwrap());
+    let upgrade_authority = if data[12] == 1 {
+        Some(Pubkey::new_from_array(data[13..45].try_into().unwrap()))
+    } else {
+        None
+    };
+
+    Ok(ProgramDeployment {
+        program_data,
+        last_deploy_slot,
+        upgrade_authority,
+    })
+}
+
+/// Whether the given account data matches the discriminator and size of `T` as defined by the crate's IDL.
+pub fn matches_layout<T: ZeroCopy + Discriminator>(account_data: &[u8]) -> bool {
+    AccountLayout::of::<T>(std::any::type_name::<T>()).matches(account_data)
+}
+
+/// Guards against running against a deployed program that has changed incompatibly
+/// with the IDL this crate was generated from.
+#[derive(Debug, Default, Clone)]
+pub struct VersionGuard {
+    /// The slot of the last program deployment known to be compatible with this crate's IDL.
+    ///
+    /// If set, any later deployment is considered a potentially incompatible upgrade.
+    pub compatible_deploy_slot: Option<u64>,
+    /// Whether a potentially incompatible upgrade is an error, otherwise only a warning is logged.
+    ///
+    /// Account layout mismatches are always errors.
+    pub strict: bool,
+}
+
+impl VersionGuard {
+    /// Creates a new [`VersionGuard`].
+    pub fn new(compatible_deploy_slot: Option<u64>, strict: bool) -> Self {
+        Self {
+            compatible_deploy_slot,
+            strict,
+        }
+    }
+
+    /// Checks the deployed program against this crate's IDL.
+    ///
+    /// The program's deployment slot is compared against the last known compatible deployment and
+    /// the layouts of the given [`Clearing`] and the [`CacheAccount`] are compared against the IDL.
+    ///
+    /// ### Errors
+    ///
+    /// This function will return an error if something goes wrong during the RPC requests,
+    /// an account's layout does not match the IDL or, if the guard is strict, the program
+    /// was upgraded after the last known compatible deployment.
+    pub async fn check(
+        &self,
+        rpc_client: &RpcClient,
+        clearing: &Pubkey,
+    ) -> Result<ProgramDeployment, VersionError> {
+        let deployment = match get_program_deployment(rpc_client, &cypher_client::id()).await {
+            Ok(d) => d,
+            Err(e) => {
+                return Err(e);
+            }
+        };
+
+        if let Some(compatible_slot) = self.compatible_deploy_slot {
+            if deployment.last_deploy_slot > compatible_slot {
+                if self.strict {
+                    return Err(VersionError::ProgramUpgraded(
+                        deployment.last_deploy_slot,
+                        compatible_slot,
+                    ));
+                }
+                warn!(
+                    "Program was upgraded at slot {} after the last known compatible deployment at slot {}, IDL version {} ({}) may be outdated.",
+                    deployment.last_deploy_slot,
+                    compatible_slot,
+                    IDL_VERSION,
+                    idl_hash()
+                );
+            }
+        }
+
+        let accounts = [*clearing, cache_account::id()];
+        let account_infos = match rpc_client.get_multiple_accounts(&accounts).await {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(VersionError::ClientError(e));
+            }
+        };
+        for (idx, (address, account)) in accounts.iter().zip(account_infos.iter()).enumerate() {
+            let data = account.as_ref().map(|a| a.data.as_slice()).unwrap_or(&[]);
+            let (matches, name) = if idx == 0 {
+                (matches_layout::<Clearing>(data), "Clearing")
+            } else {
+                (matches_layout::<CacheAccount>(data), "CacheAccount")
+            };
+            if !matches {
+                return Err(VersionError::LayoutMismatch(
+                    *address,
+                    name.to_string(),
+                    IDL_VERSION.to_string(),
+                ));
+            }
+        }
+
+        info!(
+            "Deployed program is compatible with IDL version {} ({}) - Last deployed at slot {}",
+            IDL_VERSION,
+            idl_hash(),
+            deployment.last_deploy_slot
+        );
+
+        Ok(deployment)
+    }
+}
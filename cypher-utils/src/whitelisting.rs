@@ -0,0 +1,369 @@
+use cypher_client::{
+    instructions::{create_whitelist, create_whitelisted_account},
+    utils::{derive_account_address, derive_whitelist_address, get_zero_copy_account},
+    Whitelist, WhitelistStatus,
+};
+use log::{info, warn};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use std::{fmt::Display, fs, path::Path, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+use crate::utils::send_transactions;
+
+/// The compute units requested for each whitelisting transaction.
+const WHITELISTING_COMPUTE_UNITS: u32 = 400_000;
+
+/// The maximum number of accounts per `getMultipleAccounts` request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum WhitelistingError {
+    #[error("Invalid wallet pubkey on line {0}: {1}")]
+    InvalidWallet(usize, String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+}
+
+/// Parses a list of wallet pubkeys, one per line, e.g. a CSV file whose first column is the wallet.
+///
+/// Empty lines, lines starting with `#` and a header line are skipped, duplicate wallets are only kept once.
+///
+/// ### Errors
+///
+/// This function will return an error if a line does not start with a valid pubkey.
+pub fn parse_wallets(contents: &str) -> Result<Vec<Pubkey>, WhitelistingError> {
+    let mut wallets: Vec<Pubkey> = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let field = line.split(',').next().unwrap_or_default().trim();
+        match Pubkey::from_str(field) {
+            Ok(wallet) => {
+                if !wallets.contains(&wallet) {
+                    wallets.push(wallet);
+                }
+            }
+            // the first line may be a header
+            Err(_) if i == 0 => continue,
+            Err(_) => {
+                return Err(WhitelistingError::InvalidWallet(i + 1, field.to_string()));
+            }
+        }
+    }
+    Ok(wallets)
+}
+
+/// Loads the list of wallet pubkeys from the given file, see [`parse_wallets`].
+///
+/// ### Errors
+///
+/// This function will return an error if the file can not be read or contains an invalid pubkey.
+pub fn load_wallets(path: &Path) -> Result<Vec<Pubkey>, WhitelistingError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(WhitelistingError::Io(e));
+        }
+    };
+    parse_wallets(&contents)
+}
+
+/// The whitelisting status of a wallet in a private clearing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletWhitelistStatus {
+    /// The wallet has no whitelist.
+    Missing,
+    /// The wallet's whitelist belongs to another clearing.
+    OtherClearing(Pubkey),
+    /// The wallet is whitelisted but has not created it's account yet.
+    Pending,
+    /// The wallet is whitelisted and has created it's account.
+    Active,
+    /// The wallet's whitelist was revoked.
+    Revoked,
+}
+
+impl Display for WalletWhitelistStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalletWhitelistStatus::Missing => write!(f, "Missing"),
+            WalletWhitelistStatus::OtherClearing(c) => write!(f, "OtherClearing({})", c),
+            WalletWhitelistStatus::Pending => write!(f, "Pending"),
+            WalletWhitelistStatus::Active => write!(f, "Active"),
+            WalletWhitelistStatus::Revoked => write!(f, "Revoked"),
+        }
+    }
+}
+
+/// The whitelisting status of a single wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalletWhitelist {
+    /// The wallet.
+    pub wallet: Pubkey,
+    /// The wallet's whitelist.
+    pub whitelist: Pubkey,
+    /// The status.
+    pub status: WalletWhitelistStatus,
+    /// The whitelisted account, if it was created.
+    pub account: Option<Pubkey>,
+}
+
+/// The whitelisting status of a list of wallets in a private clearing, read back from their [`Whitelist`]s.
+#[derive(Debug, Default, Clone)]
+pub struct WhitelistStatusReport {
+    /// The clearing.
+    pub clearing: Pubkey,
+    /// The status of each wallet, in the order they were given.
+    pub wallets: Vec<WalletWhitelist>,
+}
+
+impl WhitelistStatusReport {
+    /// Gets the wallets with the given status.
+    pub fn get_wallets_with_status(&self, status: WalletWhitelistStatus) -> Vec<Pubkey> {
+        self.wallets
+            .iter()
+            .filter(|w| w.status == status)
+            .map(|w| w.wallet)
+            .collect()
+    }
+
+    /// Gets the number of wallets with the given status.
+    pub fn count(&self, status: WalletWhitelistStatus) -> usize {
+        self.wallets.iter().filter(|w| w.status == status).count()
+    }
+}
+
+impl Display for WhitelistStatusReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "wallet,whitelist,status,account")?;
+        for wallet in self.wallets.iter() {
+            writeln!(
+                f,
+                "{},{},{},{}",
+                wallet.wallet,
+                wallet.whitelist,
+                wallet.status,
+                wallet.account.map(|a| a.to_string()).unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the [`Whitelist`]s of the given wallets and reports their status in the given clearing.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests.
+pub async fn get_whitelist_status_report(
+    rpc_client: &Arc<RpcClient>,
+    clearing: &Pubkey,
+    wallets: &[Pubkey],
+) -> Result<WhitelistStatusReport, WhitelistingError> {
+    let mut report = WhitelistStatusReport {
+        clearing: *clearing,
+        wallets: Vec::with_capacity(wallets.len()),
+    };
+
+    for chunk in wallets.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let whitelists = chunk
+            .iter()
+            .map(|w| derive_whitelist_address(w).0)
+            .collect::<Vec<Pubkey>>();
+        let accounts = match rpc_client.get_multiple_accounts(&whitelists).await {
+            Ok(a) => a,
+            Err(e) => {
+                return Err(WhitelistingError::ClientError(e));
+            }
+        };
+        for ((wallet, whitelist), account) in chunk.iter().zip(whitelists).zip(accounts) {
+            let (status, account) = match account {
+                Some(a) => {
+                    let state = get_zero_copy_account::<Whitelist>(&a.data);
+                    let account = if state.account == Pubkey::default() {
+                        None
+                    } else {
+                        Some(state.account)
+                    };
+                    let status = if &state.clearing != clearing {
+                        WalletWhitelistStatus::OtherClearing(state.clearing)
+                    } else {
+                        match state.status {
+                            WhitelistStatus::Pending => WalletWhitelistStatus::Pending,
+                            WhitelistStatus::Active => WalletWhitelistStatus::Active,
+                            WhitelistStatus::Revoked => WalletWhitelistStatus::Revoked,
+                        }
+                    };
+                    (status, account)
+                }
+                None => (WalletWhitelistStatus::Missing, None),
+            };
+            report.wallets.push(WalletWhitelist {
+                wallet: *wallet,
+                whitelist,
+                status,
+                account,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Creates the [`Whitelist`]s of the given wallets in the given clearing, in batched transactions signed by
+/// the clearing's authority, skipping wallets which already have a whitelist so an interrupted run can be resumed.
+///
+/// Returns the signatures of the sent transactions.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests.
+pub async fn create_whitelists(
+    rpc_client: &Arc<RpcClient>,
+    clearing: &Pubkey,
+    authority: &Keypair,
+    wallets: &[Pubkey],
+) -> Result<Vec<Signature>, WhitelistingError> {
+    let report = match get_whitelist_status_report(rpc_client, clearing, wallets).await {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    for wallet in report.wallets.iter() {
+        if let WalletWhitelistStatus::OtherClearing(c) = wallet.status {
+            warn!(
+                "Wallet {} is already whitelisted in clearing {}, skipping.",
+                wallet.wallet, c
+            );
+        }
+    }
+    let ixs = report
+        .get_wallets_with_status(WalletWhitelistStatus::Missing)
+        .iter()
+        .map(|wallet| {
+            create_whitelist(
+                clearing,
+                &derive_whitelist_address(wallet).0,
+                wallet,
+                &authority.pubkey(),
+                &authority.pubkey(),
+            )
+        })
+        .collect::<Vec<Instruction>>();
+    if ixs.is_empty() {
+        info!("All wallets already have a whitelist.");
+        return Ok(Vec::new());
+    }
+
+    info!("Creating {} whitelists.", ixs.len());
+    match send_transactions(
+        rpc_client,
+        ixs,
+        authority,
+        true,
+        Some((WHITELISTING_COMPUTE_UNITS, 1)),
+        None,
+    )
+    .await
+    {
+        Ok(s) => Ok(s),
+        Err(e) => Err(WhitelistingError::ClientError(e)),
+    }
+}
+
+/// Gets the instruction which creates the whitelisted account with the given number for the given wallet,
+/// which must be signed by the wallet.
+pub fn get_create_whitelisted_account_ix(
+    clearing: &Pubkey,
+    wallet: &Pubkey,
+    payer: &Pubkey,
+    account_number: u8,
+) -> Instruction {
+    let (account, account_bump) = derive_account_address(wallet, account_number);
+    create_whitelisted_account(
+        clearing,
+        &derive_whitelist_address(wallet).0,
+        wallet,
+        payer,
+        &account,
+        account_bump,
+        account_number,
+    )
+}
+
+/// Creates the whitelisted account with the given number for each of the given wallets whose whitelist is pending,
+/// one transaction per wallet since each must be signed by it's wallet, which also pays for it.
+///
+/// Returns the signatures of the sent transactions.
+///
+/// ### Errors
+///
+/// This function will return an error if something goes wrong during the RPC requests.
+pub async fn create_whitelisted_accounts(
+    rpc_client: &Arc<RpcClient>,
+    clearing: &Pubkey,
+    wallets: &[Keypair],
+    account_number: u8,
+) -> Result<Vec<Signature>, WhitelistingError> {
+    let report = match get_whitelist_status_report(
+        rpc_client,
+        clearing,
+        &wallets.iter().map(|w| w.pubkey()).collect::<Vec<Pubkey>>(),
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(e);
+        }
+    };
+    let mut signatures = Vec::new();
+
+    for (wallet, status) in wallets.iter().zip(report.wallets.iter()) {
+        if status.status != WalletWhitelistStatus::Pending {
+            info!(
+                "Skipping wallet {} with whitelist status {}.",
+                status.wallet, status.status
+            );
+            continue;
+        }
+        let ix = get_create_whitelisted_account_ix(
+            clearing,
+            &wallet.pubkey(),
+            &wallet.pubkey(),
+            account_number,
+        );
+        match send_transactions(
+            rpc_client,
+            vec![ix],
+            wallet,
+            true,
+            Some((WHITELISTING_COMPUTE_UNITS, 1)),
+            None,
+        )
+        .await
+        {
+            Ok(s) => signatures.extend(s),
+            Err(e) => {
+                return Err(WhitelistingError::ClientError(e));
+            }
+        }
+        info!(
+            "Created whitelisted account for wallet {}.",
+            wallet.pubkey()
+        );
+    }
+
+    Ok(signatures)
+}